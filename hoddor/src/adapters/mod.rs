@@ -1,19 +1,44 @@
 #[cfg(target_arch = "wasm32")]
 pub mod wasm;
+#[cfg(all(feature = "sync", target_arch = "wasm32"))]
+pub use wasm::ManualSdpTransport as Transport;
 #[cfg(target_arch = "wasm32")]
 pub use wasm::{
-    Clock, ConsoleLogger, Locks, Notifier, OpfsStorage as Storage, Persistence, WebAuthnPrf as Prf,
+    Clock, ConsoleLogger, ErrorReporter, Locks, Notifier, OpfsStorage as Storage, Persistence,
+    WebAuthnPrf as Prf, WorkerKdf as Kdf,
 };
 
 #[cfg(not(target_arch = "wasm32"))]
 pub mod native;
+#[cfg(all(feature = "fido2", not(target_arch = "wasm32")))]
+pub use native::Fido2Prf as Prf;
+#[cfg(all(feature = "sync", not(target_arch = "wasm32")))]
+pub use native::LanTransport as Transport;
+#[cfg(all(not(feature = "fido2"), not(target_arch = "wasm32")))]
+pub use native::MockPrf as Prf;
 #[cfg(not(target_arch = "wasm32"))]
 pub use native::{
-    Clock, ConsoleLogger, FsStorage as Storage, Locks, MockPrf as Prf, Notifier, Persistence,
+    Clock, ConsoleLogger, ErrorReporter, FsStorage as Storage, Locks, Notifier, Persistence,
 };
 
 pub mod shared;
-pub use shared::{AgeEncryption, AgeIdentity, Argon2Kdf};
+#[cfg(not(target_arch = "wasm32"))]
+pub use shared::Argon2Kdf as Kdf;
+pub use shared::{AgeEncryption, AgeIdentity, Argon2Kdf, InstrumentedStorage, RecordedNotifier};
 
-#[cfg(all(feature = "graph", target_arch = "wasm32"))]
-pub use wasm::CozoGraphAdapter as Graph;
+#[cfg(all(
+    feature = "graph",
+    not(all(
+        any(feature = "graph-cozo", feature = "graph-remote"),
+        target_arch = "wasm32"
+    ))
+))]
+pub type Graph = shared::CachedGraph<shared::MemoryGraphAdapter>;
+#[cfg(all(feature = "graph-cozo", target_arch = "wasm32"))]
+pub type Graph = shared::CachedGraph<wasm::CozoGraphAdapter>;
+#[cfg(all(
+    feature = "graph-remote",
+    not(feature = "graph-cozo"),
+    target_arch = "wasm32"
+))]
+pub type Graph = shared::CachedGraph<wasm::RemoteGraphAdapter>;
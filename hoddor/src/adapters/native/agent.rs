@@ -0,0 +1,610 @@
+//! Local agent answering decrypt and sign requests with identities it holds,
+//! in the manner of `ssh-agent`.
+//!
+//! Clients connect to a Unix socket, readable by its owner only, and send one
+//! JSON request per line; each gets one JSON response line. The socket lives
+//! in a directory that must belong to the user with mode 0700, and clients
+//! running as another user are turned away even if they can reach it. Private keys
+//! never leave the agent: clients get plaintexts and signatures back, and
+//! refer to identities by label.
+//!
+//! Identities are X25519 keys, which cannot sign, so signatures are
+//! HMAC-SHA256 tags under a key derived from the identity. Only the agent
+//! (or another holder of the identity) can check them, with `verify`.
+
+use crate::domain::crypto::{self, mac::hmac_sha256};
+use crate::domain::vault::error::VaultError;
+use crate::domain::vault::operations;
+use crate::platform::Platform;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::executor::block_on;
+use hkdf::Hkdf;
+use hmac::Mac;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use zeroize::Zeroizing;
+
+/// Longest request line accepted, base64 payload included.
+const MAX_REQUEST_LEN: u64 = 16 * 1024 * 1024;
+const SIGNING_KEY_INFO: &[u8] = b"hoddor-agent-signing-key";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum AgentRequest {
+    Identities,
+    /// Decrypts age ciphertext with `identity`, or with whichever identity
+    /// can when none is named.
+    #[serde(rename_all = "camelCase")]
+    Decrypt {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        identity: Option<String>,
+        /// Base64 ciphertext.
+        data: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Sign {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        identity: Option<String>,
+        /// Base64 message.
+        data: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Verify {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        identity: Option<String>,
+        data: String,
+        /// Hex signature returned by `sign`.
+        signature: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    ReadNamespace {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        identity: Option<String>,
+        vault: String,
+        namespace: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentIdentityInfo {
+    pub label: String,
+    pub public_key: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "camelCase")]
+pub enum AgentResponse {
+    Identities {
+        identities: Vec<AgentIdentityInfo>,
+    },
+    /// Base64 plaintext.
+    Data {
+        data: String,
+    },
+    Signature {
+        signature: String,
+    },
+    Verified {
+        valid: bool,
+    },
+    Error {
+        code: String,
+        message: String,
+    },
+}
+
+impl AgentResponse {
+    fn error(code: &str, message: impl Into<String>) -> Self {
+        AgentResponse::Error {
+            code: code.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl From<VaultError> for AgentResponse {
+    fn from(error: VaultError) -> Self {
+        AgentResponse::error(error.code(), error.to_string())
+    }
+}
+
+struct AgentIdentity {
+    label: String,
+    public_key: String,
+    private_key: Zeroizing<String>,
+}
+
+/// Identities unlocked for the lifetime of the agent.
+#[derive(Default)]
+pub struct Agent {
+    identities: Vec<AgentIdentity>,
+}
+
+impl Agent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `private_key` under `label`. The first identity added answers
+    /// requests that do not name one.
+    pub fn add_identity(&mut self, label: &str, private_key: &str) -> Result<(), VaultError> {
+        let public_key = crypto::identity_to_public(&Platform::new(), private_key)
+            .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+        self.identities.retain(|identity| identity.label != label);
+        self.identities.push(AgentIdentity {
+            label: label.to_string(),
+            public_key,
+            private_key: Zeroizing::new(private_key.to_string()),
+        });
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.identities.is_empty()
+    }
+
+    fn identity(&self, label: Option<&str>) -> Result<&AgentIdentity, AgentResponse> {
+        match label {
+            Some(label) => self
+                .identities
+                .iter()
+                .find(|identity| identity.label == label),
+            None => self.identities.first(),
+        }
+        .ok_or_else(|| {
+            AgentResponse::error(
+                "unknown_identity",
+                format!("No identity labelled {}", label.unwrap_or("(default)")),
+            )
+        })
+    }
+
+    pub async fn handle(&self, platform: &Platform, request: AgentRequest) -> AgentResponse {
+        self.try_handle(platform, request)
+            .await
+            .unwrap_or_else(|response| response)
+    }
+
+    async fn try_handle(
+        &self,
+        platform: &Platform,
+        request: AgentRequest,
+    ) -> Result<AgentResponse, AgentResponse> {
+        match request {
+            AgentRequest::Identities => Ok(AgentResponse::Identities {
+                identities: self
+                    .identities
+                    .iter()
+                    .map(|identity| AgentIdentityInfo {
+                        label: identity.label.clone(),
+                        public_key: identity.public_key.clone(),
+                    })
+                    .collect(),
+            }),
+            AgentRequest::Decrypt { identity, data } => {
+                let ciphertext = decode(&data)?;
+                let candidates: Vec<&AgentIdentity> = match identity.as_deref() {
+                    Some(label) => vec![self.identity(Some(label))?],
+                    None => self.identities.iter().collect(),
+                };
+
+                for candidate in candidates {
+                    if let Ok(plaintext) =
+                        crypto::decrypt_with_identity(platform, &ciphertext, &candidate.private_key)
+                            .await
+                    {
+                        return Ok(AgentResponse::Data {
+                            data: STANDARD.encode(plaintext),
+                        });
+                    }
+                }
+                Err(AgentResponse::error(
+                    "decryption_failed",
+                    "No identity decrypts the data",
+                ))
+            }
+            AgentRequest::Sign { identity, data } => {
+                let identity = self.identity(identity.as_deref())?;
+                let tag = signing_mac(identity, &decode(&data)?)
+                    .finalize()
+                    .into_bytes();
+                Ok(AgentResponse::Signature {
+                    signature: hex::encode(tag),
+                })
+            }
+            AgentRequest::Verify {
+                identity,
+                data,
+                signature,
+            } => {
+                let identity = self.identity(identity.as_deref())?;
+                let data = decode(&data)?;
+                let valid = hex::decode(signature).is_ok_and(|signature| {
+                    signing_mac(identity, &data)
+                        .verify_slice(&signature)
+                        .is_ok()
+                });
+                Ok(AgentResponse::Verified { valid })
+            }
+            AgentRequest::ReadNamespace {
+                identity,
+                vault,
+                namespace,
+            } => {
+                let identity = self.identity(identity.as_deref())?;
+                let plaintext =
+                    operations::read_namespace(platform, &vault, &identity.private_key, &namespace)
+                        .await?;
+                Ok(AgentResponse::Data {
+                    data: STANDARD.encode(plaintext),
+                })
+            }
+        }
+    }
+
+    /// Listens on `path` until the process ends, answering each client on
+    /// its own thread. A socket left by an agent that is gone is replaced;
+    /// one with a live agent behind it is an error.
+    pub fn serve(self, path: &Path) -> io::Result<()> {
+        let listener = bind(path)?;
+        let agent = Arc::new(self);
+        tracing::info!(socket = %path.display(), "Agent listening");
+
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Agent failed to accept a connection");
+                    continue;
+                }
+            };
+            match peer_uid(&stream) {
+                Ok(uid) if uid == current_uid() => {}
+                Ok(uid) => {
+                    tracing::warn!(uid, "Agent refused a client running as another user");
+                    continue;
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "Agent failed to identify a client");
+                    continue;
+                }
+            }
+            let agent = Arc::clone(&agent);
+            thread::spawn(move || {
+                if let Err(e) = agent.serve_client(stream) {
+                    tracing::debug!(error = %e, "Agent client disconnected");
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    fn serve_client(&self, stream: UnixStream) -> io::Result<()> {
+        let platform = Platform::new();
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+
+        loop {
+            let mut line = String::new();
+            let read = (&mut reader).take(MAX_REQUEST_LEN).read_line(&mut line)?;
+            if read == 0 {
+                return Ok(());
+            }
+            if !line.ends_with('\n') && read as u64 == MAX_REQUEST_LEN {
+                let response = AgentResponse::error("request_too_large", "Request too large");
+                write_line(&mut writer, &response)?;
+                return Ok(());
+            }
+
+            let response = match serde_json::from_str::<AgentRequest>(&line) {
+                Ok(request) => block_on(self.handle(&platform, request)),
+                Err(e) => AgentResponse::error("invalid_request", e.to_string()),
+            };
+            write_line(&mut writer, &response)?;
+        }
+    }
+}
+
+fn signing_mac(identity: &AgentIdentity, data: &[u8]) -> crypto::mac::HmacSha256 {
+    let mut key = Zeroizing::new([0u8; 32]);
+    let hkdf = Hkdf::<Sha256>::new(None, identity.private_key.as_bytes());
+    // 32 bytes is well within what HKDF-SHA256 can expand to.
+    let _ = hkdf.expand(SIGNING_KEY_INFO, key.as_mut());
+
+    let mut mac = hmac_sha256(key.as_ref());
+    mac.update(data);
+    mac
+}
+
+fn decode(data: &str) -> Result<Vec<u8>, AgentResponse> {
+    STANDARD
+        .decode(data)
+        .map_err(|e| AgentResponse::error("invalid_request", format!("Invalid base64: {e}")))
+}
+
+fn write_line(writer: &mut impl Write, value: &impl Serialize) -> io::Result<()> {
+    let mut line = serde_json::to_vec(value).map_err(io::Error::other)?;
+    line.push(b'\n');
+    writer.write_all(&line)?;
+    writer.flush()
+}
+
+fn current_uid() -> u32 {
+    // SAFETY: getuid has no preconditions and cannot fail.
+    unsafe { libc::getuid() }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let mut credentials = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: `credentials` and `len` outlive the call and `len` is the size
+    // of the buffer SO_PEERCRED writes to.
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut credentials as *mut libc::ucred).cast(),
+            &mut len,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(credentials.uid)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let mut uid = 0;
+    let mut gid = 0;
+    // SAFETY: both out pointers are valid for the duration of the call.
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
+// Anyone who can write to the directory can swap the socket for their own,
+// so it has to be ours and closed to everyone else, whoever created it.
+fn check_socket_directory(directory: &Path) -> io::Result<()> {
+    let metadata = std::fs::symlink_metadata(directory)?;
+    if !metadata.is_dir() || metadata.uid() != current_uid() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "{} is not a directory owned by the current user",
+                directory.display()
+            ),
+        ));
+    }
+    if metadata.mode() & 0o777 != 0o700 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "{} has mode {:o}, expected 700",
+                directory.display(),
+                metadata.mode() & 0o777
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn bind(path: &Path) -> io::Result<UnixListener> {
+    if let Some(parent) = path.parent() {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(parent)?;
+        check_socket_directory(parent)?;
+    }
+
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("An agent is already listening on {}", path.display()),
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+
+    // The socket is created with the umask applied, so it is never open to
+    // others, even before its permissions are set.
+    // SAFETY: umask has no preconditions and cannot fail.
+    let previous = unsafe { libc::umask(0o177) };
+    let listener = UnixListener::bind(path);
+    unsafe { libc::umask(previous) };
+
+    let listener = listener?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+/// `HODDOR_AGENT_SOCK` when set, otherwise a socket in the user's runtime
+/// directory, or in a directory of the temporary directory named after the
+/// user id. Either way the agent refuses a directory that another user owns
+/// or can access.
+pub fn default_socket_path() -> PathBuf {
+    if let Some(path) = std::env::var_os("HODDOR_AGENT_SOCK") {
+        return PathBuf::from(path);
+    }
+
+    let directory = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(|dir| PathBuf::from(dir).join("hoddor"))
+        .unwrap_or_else(|| std::env::temp_dir().join(format!("hoddor-{}", current_uid())));
+    directory.join("agent.sock")
+}
+
+/// Connection to a running agent.
+pub struct AgentClient {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl AgentClient {
+    pub fn connect(path: &Path) -> io::Result<Self> {
+        let writer = UnixStream::connect(path)?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(Self { reader, writer })
+    }
+
+    pub fn request(&mut self, request: &AgentRequest) -> io::Result<AgentResponse> {
+        write_line(&mut self.writer, request)?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Agent closed the connection",
+            ));
+        }
+        serde_json::from_str(&line).map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent_with(labels: &[&str]) -> (Agent, Vec<String>) {
+        let platform = Platform::new();
+        let mut agent = Agent::new();
+        let mut public_keys = Vec::new();
+        for label in labels {
+            let private_key = crypto::generate_identity(&platform).unwrap();
+            agent.add_identity(label, &private_key).unwrap();
+            public_keys.push(crypto::identity_to_public(&platform, &private_key).unwrap());
+        }
+        (agent, public_keys)
+    }
+
+    #[test]
+    fn test_agent_decrypts_signs_and_verifies() {
+        let platform = Platform::new();
+        let (agent, public_keys) = agent_with(&["laptop", "ci"]);
+
+        block_on(async {
+            let ciphertext =
+                crypto::encrypt_for_recipients(&platform, b"secret", &[&public_keys[1]])
+                    .await
+                    .unwrap();
+            let request = AgentRequest::Decrypt {
+                identity: None,
+                data: STANDARD.encode(&ciphertext),
+            };
+            assert_eq!(
+                agent.handle(&platform, request).await,
+                AgentResponse::Data {
+                    data: STANDARD.encode(b"secret")
+                }
+            );
+
+            let request = AgentRequest::Decrypt {
+                identity: Some("laptop".to_string()),
+                data: STANDARD.encode(&ciphertext),
+            };
+            assert!(matches!(
+                agent.handle(&platform, request).await,
+                AgentResponse::Error { code, .. } if code == "decryption_failed"
+            ));
+
+            let data = STANDARD.encode(b"release v1.2");
+            let AgentResponse::Signature { signature } = agent
+                .handle(
+                    &platform,
+                    AgentRequest::Sign {
+                        identity: Some("ci".to_string()),
+                        data: data.clone(),
+                    },
+                )
+                .await
+            else {
+                panic!("expected a signature");
+            };
+
+            for (identity, expected) in [("ci", true), ("laptop", false)] {
+                let request = AgentRequest::Verify {
+                    identity: Some(identity.to_string()),
+                    data: data.clone(),
+                    signature: signature.clone(),
+                };
+                assert_eq!(
+                    agent.handle(&platform, request).await,
+                    AgentResponse::Verified { valid: expected }
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn test_agent_serves_clients_over_socket() {
+        let (agent, public_keys) = agent_with(&["laptop"]);
+        let path = std::env::temp_dir()
+            .join(format!("hoddor_agent_{}", std::process::id()))
+            .join("agent.sock");
+        let server_path = path.clone();
+        thread::spawn(move || agent.serve(&server_path));
+
+        let mut client = (0..100)
+            .find_map(|_| {
+                AgentClient::connect(&path).ok().or_else(|| {
+                    thread::sleep(std::time::Duration::from_millis(10));
+                    None
+                })
+            })
+            .unwrap();
+
+        assert_eq!(
+            client.request(&AgentRequest::Identities).unwrap(),
+            AgentResponse::Identities {
+                identities: vec![AgentIdentityInfo {
+                    label: "laptop".to_string(),
+                    public_key: public_keys[0].clone(),
+                }]
+            }
+        );
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(bind(&path).is_err());
+
+        writeln!(client.writer, "{{\"op\":\"unknown\"}}").unwrap();
+        let mut line = String::new();
+        client.reader.read_line(&mut line).unwrap();
+        assert!(line.contains("invalid_request"));
+    }
+
+    #[test]
+    fn test_agent_refuses_shared_directories_and_checks_peers() {
+        let directory =
+            std::env::temp_dir().join(format!("hoddor_agent_shared_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::set_permissions(&directory, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let error = bind(&directory.join("agent.sock")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+
+        std::fs::set_permissions(&directory, std::fs::Permissions::from_mode(0o700)).unwrap();
+        assert!(bind(&directory.join("agent.sock")).is_ok());
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        let (client, _server) = UnixStream::pair().unwrap();
+        assert_eq!(peer_uid(&client).unwrap(), current_uid());
+    }
+}
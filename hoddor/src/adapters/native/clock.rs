@@ -1,4 +1,5 @@
 use crate::ports::clock::ClockPort;
+use async_trait::async_trait;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Clone, Copy)]
@@ -16,17 +17,22 @@ impl Clock {
     }
 }
 
+#[async_trait(?Send)]
 impl ClockPort for Clock {
     fn now(&self) -> f64 {
         SystemTime::now()
             .duration_since(UNIX_EPOCH)
-            .unwrap()
+            .unwrap_or_default()
             .as_millis() as f64
     }
 
     fn is_available(&self) -> bool {
         true
     }
+
+    async fn sleep(&self, milliseconds: u32) {
+        std::thread::sleep(std::time::Duration::from_millis(milliseconds.into()));
+    }
 }
 
 #[cfg(test)]
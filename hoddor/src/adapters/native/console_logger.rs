@@ -1,10 +1,7 @@
 use crate::ports::LoggerPort;
 
-/// Native logger implementation using stdout/stderr.
-///
-/// This is a simple implementation that prints to the console.
-/// In the future, this could be replaced with a more sophisticated
-/// logging framework like `tracing` or `log`.
+/// Native logger forwarding to `tracing`; install a subscriber such as
+/// [`init_tracing`](super::init_tracing) to see the messages.
 #[derive(Debug, Clone, Copy)]
 pub struct ConsoleLogger;
 
@@ -22,23 +19,23 @@ impl Default for ConsoleLogger {
 
 impl LoggerPort for ConsoleLogger {
     fn log(&self, message: &str) {
-        println!("[LOG] {message}");
+        tracing::info!("{message}");
     }
 
     fn error(&self, message: &str) {
-        eprintln!("[ERROR] {message}");
+        tracing::error!("{message}");
     }
 
     fn warn(&self, message: &str) {
-        eprintln!("[WARN] {message}");
+        tracing::warn!("{message}");
     }
 
     fn time(&self, label: &str) {
-        println!("[TIME:START] {label}");
+        tracing::debug!(label, "Timer started");
     }
 
     fn time_end(&self, label: &str) {
-        println!("[TIME:END] {label}");
+        tracing::debug!(label, "Timer ended");
     }
 }
 
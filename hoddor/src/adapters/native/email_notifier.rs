@@ -0,0 +1,134 @@
+use crate::notifications::EventType;
+use crate::ports::NotifierPort;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{SmtpTransport, Transport};
+
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+/// Emails configured vault events through an SMTP relay (implicit TLS).
+/// Sending blocks, so register it with `Notifier::register` rather than
+/// calling it from vault operations.
+pub struct EmailNotifier {
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    events: Vec<EventType>,
+    transport: SmtpTransport,
+}
+
+impl EmailNotifier {
+    pub fn new(config: SmtpConfig, events: &[EventType]) -> Result<Self, String> {
+        let parse = |address: &str| {
+            address
+                .parse::<Mailbox>()
+                .map_err(|e| format!("Invalid email address '{address}': {e}"))
+        };
+
+        let from = parse(&config.from)?;
+        let to = config
+            .to
+            .iter()
+            .map(|address| parse(address))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if to.is_empty() {
+            return Err("At least one recipient is required".to_string());
+        }
+
+        let mut builder = SmtpTransport::relay(&config.host)
+            .map_err(|e| format!("Invalid SMTP relay: {e}"))?
+            .port(config.port);
+
+        if let (Some(username), Some(password)) = (config.username, config.password) {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Ok(Self {
+            from,
+            to,
+            events: events.to_vec(),
+            transport: builder.build(),
+        })
+    }
+}
+
+impl NotifierPort for EmailNotifier {
+    fn notify_vault_update(&self, _vault_name: &str, _vault_data: &[u8]) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn notify_event(&self, vault_name: &str, event: EventType, detail: &str) -> Result<(), String> {
+        if !self.events.contains(&event) {
+            return Ok(());
+        }
+
+        let mut builder = lettre::Message::builder()
+            .from(self.from.clone())
+            .subject(format!(
+                "[hoddor] {} on vault {}",
+                event.as_str(),
+                vault_name
+            ));
+        for recipient in &self.to {
+            builder = builder.to(recipient.clone());
+        }
+
+        let email = builder
+            .body(detail.to_string())
+            .map_err(|e| format!("Failed to build email: {e}"))?;
+
+        self.transport
+            .send(&email)
+            .map(|_| ())
+            .map_err(|e| format!("Email delivery failed: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SmtpConfig {
+        SmtpConfig {
+            host: "smtp.example.com".to_string(),
+            port: 465,
+            username: None,
+            password: None,
+            from: "hoddor@example.com".to_string(),
+            to: vec!["ops@example.com".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_email_notifier_rejects_invalid_address() {
+        let mut config = config();
+        config.from = "not an address".to_string();
+
+        assert!(EmailNotifier::new(config, &[EventType::SyncConflict]).is_err());
+    }
+
+    #[test]
+    fn test_email_notifier_requires_recipient() {
+        let mut config = config();
+        config.to.clear();
+
+        assert!(EmailNotifier::new(config, &[EventType::SyncConflict]).is_err());
+    }
+
+    #[test]
+    fn test_email_notifier_ignores_unconfigured_event() {
+        let notifier = EmailNotifier::new(config(), &[EventType::SyncConflict]).unwrap();
+
+        assert!(notifier
+            .notify_event("vault", EventType::BackupCompleted, "exported")
+            .is_ok());
+    }
+}
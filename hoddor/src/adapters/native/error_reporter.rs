@@ -0,0 +1,72 @@
+use crate::domain::error_report::ErrorReport;
+use crate::ports::ErrorReporterPort;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+static SINKS: Lazy<RwLock<Vec<Arc<dyn ErrorReporterPort>>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// Native error reporter adapter.
+///
+/// Forwards reports to the sinks registered with [`ErrorReporter::register`];
+/// without any, reports are dropped.
+#[derive(Clone, Copy)]
+pub struct ErrorReporter;
+
+impl Default for ErrorReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ErrorReporter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn register(sink: Arc<dyn ErrorReporterPort>) {
+        SINKS.write().push(sink);
+    }
+
+    pub fn clear() {
+        SINKS.write().clear();
+    }
+}
+
+impl ErrorReporterPort for ErrorReporter {
+    fn report(&self, report: &ErrorReport) {
+        let sinks = SINKS.read().clone();
+
+        for sink in sinks {
+            sink.report(report);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+
+    struct RecordingSink {
+        codes: Mutex<Vec<String>>,
+    }
+
+    impl ErrorReporterPort for RecordingSink {
+        fn report(&self, report: &ErrorReport) {
+            self.codes.lock().push(report.code.clone());
+        }
+    }
+
+    #[test]
+    fn test_reports_reach_registered_sinks() {
+        let sink = Arc::new(RecordingSink {
+            codes: Mutex::new(Vec::new()),
+        });
+        ErrorReporter::register(sink.clone());
+
+        ErrorReporter::new().report(&ErrorReport::error("vault_not_found", "Vault not found"));
+
+        assert!(sink.codes.lock().contains(&"vault_not_found".to_string()));
+    }
+}
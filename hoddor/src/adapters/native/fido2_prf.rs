@@ -0,0 +1,135 @@
+use crate::adapters::shared::seed_from_prf;
+use crate::ports::PrfPort;
+use ctap_hid_fido2::fidokey::get_assertion::get_assertion_params::Extension as AssertionExtension;
+use ctap_hid_fido2::fidokey::make_credential::make_credential_params::Extension as CredentialExtension;
+use ctap_hid_fido2::fidokey::{GetAssertionArgsBuilder, MakeCredentialArgsBuilder};
+use ctap_hid_fido2::public_key_credential_user_entity::PublicKeyCredentialUserEntity;
+use ctap_hid_fido2::{get_fidokey_devices, verifier, Cfg, FidoKeyHidFactory};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+
+/// Relying party id the credentials of native builds are scoped to.
+pub const DEFAULT_RP_ID: &str = "hoddor";
+
+/// PRF adapter backed by a FIDO2 security key over USB HID.
+///
+/// The PRF values are the outputs of the `hmac-secret` extension, which is
+/// what WebAuthn PRF is built on. Credentials are scoped to their relying
+/// party id, so a security key registered in the browser under a web origin
+/// does not unlock a vault through this adapter, nor the other way round.
+#[derive(Clone, Copy, Debug)]
+pub struct Fido2Prf {
+    rp_id: &'static str,
+}
+
+impl Fido2Prf {
+    pub fn new() -> Self {
+        Self::with_rp_id(DEFAULT_RP_ID)
+    }
+
+    pub fn with_rp_id(rp_id: &'static str) -> Self {
+        Self { rp_id }
+    }
+
+    /// Whether a FIDO2 device is plugged in.
+    pub fn has_device(&self) -> bool {
+        !get_fidokey_devices().is_empty()
+    }
+
+    /// Registers a credential with `hmac-secret` on the security key, which
+    /// asks for a touch, and returns its id.
+    pub fn make_credential(
+        &self,
+        username: &str,
+        pin: Option<&str>,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let device = FidoKeyHidFactory::create(&Cfg::init())?;
+        let challenge = verifier::create_challenge();
+        let user = PublicKeyCredentialUserEntity::new(
+            Some(username.as_bytes()),
+            Some(username),
+            Some(username),
+        );
+
+        let mut args = MakeCredentialArgsBuilder::new(self.rp_id, &challenge)
+            .extensions(&[CredentialExtension::HmacSecret(Some(true))])
+            .user_entity(&user);
+        if let Some(pin) = pin {
+            args = args.pin(pin);
+        }
+
+        let attestation = device.make_credential_with_args(&args.build())?;
+        let verified = verifier::verify_attestation(self.rp_id, &challenge, &attestation);
+        if !verified.is_success {
+            return Err("Security key attestation could not be verified".into());
+        }
+
+        Ok(verified.credential_id)
+    }
+
+    /// Asks the security key, with a touch, for the two PRF values of
+    /// `credential_id` under `salt`.
+    pub fn evaluate(
+        &self,
+        credential_id: &[u8],
+        salt: &[u8; 32],
+        pin: Option<&str>,
+    ) -> Result<(Vec<u8>, Vec<u8>), Box<dyn Error>> {
+        let device = FidoKeyHidFactory::create(&Cfg::init())?;
+        let challenge = verifier::create_challenge();
+
+        // The crate evaluates a single `hmac-secret` salt per assertion, so
+        // the second value is derived from the first rather than asking for
+        // another touch.
+        let mut args = GetAssertionArgsBuilder::new(self.rp_id, &challenge)
+            .credential_id(credential_id)
+            .extensions(&[AssertionExtension::HmacSecret(Some(prf_salt(salt)))]);
+        if let Some(pin) = pin {
+            args = args.pin(pin);
+        }
+
+        let assertions = device.get_assertion_with_args(&args.build())?;
+        let first = assertions
+            .iter()
+            .flat_map(|assertion| &assertion.extensions)
+            .find_map(|extension| match extension {
+                AssertionExtension::HmacSecret(Some(output)) => Some(output.to_vec()),
+                _ => None,
+            })
+            .ok_or("Security key returned no hmac-secret output")?;
+        let second = Sha256::new()
+            .chain_update(b"hoddor/prf\x02")
+            .chain_update(&first)
+            .finalize()
+            .to_vec();
+
+        Ok((first, second))
+    }
+}
+
+impl Default for Fido2Prf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PrfPort for Fido2Prf {
+    fn derive_from_prf(&self, first: &[u8], second: &[u8]) -> Result<[u8; 32], Box<dyn Error>> {
+        seed_from_prf(first, second)
+    }
+
+    fn is_available(&self) -> bool {
+        true
+    }
+}
+
+// Same input as the first PRF value the browser asks for, hashed the way
+// WebAuthn turns PRF inputs into `hmac-secret` salts.
+fn prf_salt(salt: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(b"WebAuthn PRF\x00")
+        .chain_update(b"hoddor/prf\x01")
+        .chain_update(salt)
+        .finalize()
+        .into()
+}
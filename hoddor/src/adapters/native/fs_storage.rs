@@ -1,9 +1,22 @@
 use crate::domain::vault::error::VaultError;
 use crate::ports::StoragePort;
 use async_trait::async_trait;
+use once_cell::sync::OnceCell;
 use std::fs;
 use std::path::PathBuf;
 
+const DEFAULT_ROOT_PATH: &str = "./hoddor_data";
+
+static ROOT_PATH: OnceCell<&'static str> = OnceCell::new();
+
+/// Stores vaults under `path` instead of `./hoddor_data` for every
+/// `FsStorage` created afterwards. Only the first call has an effect; it
+/// returns whether `path` was taken.
+pub fn set_root_path(path: impl Into<String>) -> bool {
+    let path: &'static str = Box::leak(path.into().into_boxed_str());
+    ROOT_PATH.set(path).is_ok()
+}
+
 #[derive(Clone, Copy)]
 pub struct FsStorage {
     root_path: &'static str,
@@ -18,10 +31,15 @@ impl Default for FsStorage {
 impl FsStorage {
     pub fn new() -> Self {
         Self {
-            root_path: "./hoddor_data",
+            root_path: ROOT_PATH.get().copied().unwrap_or(DEFAULT_ROOT_PATH),
         }
     }
 
+    /// Directory every path is relative to.
+    pub fn root_path(&self) -> &'static str {
+        self.root_path
+    }
+
     fn get_full_path(&self, path: &str) -> PathBuf {
         if path.is_empty() || path == "." {
             PathBuf::from(self.root_path)
@@ -39,6 +57,8 @@ impl StoragePort for FsStorage {
     }
 
     async fn write_file(&self, path: &str, content: &str) -> Result<(), VaultError> {
+        #[cfg(feature = "watch")]
+        local_writes::record(path);
         let full_path = self.get_full_path(path);
 
         if let Some(parent) = full_path.parent() {
@@ -46,21 +66,34 @@ impl StoragePort for FsStorage {
                 .map_err(|_| VaultError::io_error("Failed to create parent directories"))?;
         }
 
-        fs::write(&full_path, content).map_err(|_| VaultError::io_error("Failed to write file"))
+        // Written aside then renamed over the file, so a crash never leaves
+        // it half written: the vault journal relies on every write being
+        // atomic, like OPFS writes are.
+        let mut temp_path = full_path.clone().into_os_string();
+        temp_path.push(".tmp");
+        fs::write(&temp_path, content)
+            .and_then(|()| fs::rename(&temp_path, &full_path))
+            .map_err(|_| VaultError::io_error("Failed to write file"))
     }
 
     async fn delete_file(&self, path: &str) -> Result<(), VaultError> {
+        #[cfg(feature = "watch")]
+        local_writes::record(path);
         let full_path = self.get_full_path(path);
         fs::remove_file(&full_path).map_err(|_| VaultError::io_error("Failed to delete file"))
     }
 
     async fn create_directory(&self, path: &str) -> Result<(), VaultError> {
+        #[cfg(feature = "watch")]
+        local_writes::record(path);
         let full_path = self.get_full_path(path);
         fs::create_dir_all(&full_path)
             .map_err(|_| VaultError::io_error("Failed to create directory"))
     }
 
     async fn delete_directory(&self, path: &str) -> Result<(), VaultError> {
+        #[cfg(feature = "watch")]
+        local_writes::record(path);
         let full_path = self.get_full_path(path);
         fs::remove_dir_all(&full_path)
             .map_err(|_| VaultError::io_error("Failed to delete directory"))
@@ -85,6 +118,56 @@ impl StoragePort for FsStorage {
 
         Ok(names)
     }
+
+    async fn rename_directory(&self, from: &str, to: &str) -> Result<(), VaultError> {
+        #[cfg(feature = "watch")]
+        {
+            local_writes::record(from);
+            local_writes::record(to);
+        }
+        let to_path = self.get_full_path(to);
+        if to_path.exists() {
+            return Err(VaultError::io_error("Target directory already exists"));
+        }
+        fs::rename(self.get_full_path(from), &to_path)
+            .map_err(|_| VaultError::io_error("Failed to rename directory"))
+    }
+}
+
+/// Paths this process wrote lately, so that a watcher on the storage
+/// directory can tell its own writes from those of other processes.
+#[cfg(feature = "watch")]
+pub(crate) mod local_writes {
+    use once_cell::sync::Lazy;
+    use std::collections::HashMap;
+    use std::sync::{Mutex, PoisonError};
+    use std::time::{Duration, Instant};
+
+    /// How long after a write its file system events are attributed to it.
+    const WINDOW: Duration = Duration::from_secs(2);
+
+    static WRITES: Lazy<Mutex<HashMap<String, Instant>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+    pub fn record(path: &str) {
+        let now = Instant::now();
+        let mut writes = WRITES.lock().unwrap_or_else(PoisonError::into_inner);
+        writes.retain(|_, at| now.duration_since(*at) < WINDOW);
+        writes.insert(path.trim_end_matches('/').to_string(), now);
+    }
+
+    /// Whether `path`, or a directory containing it, was written lately.
+    pub fn contains(path: &str) -> bool {
+        let now = Instant::now();
+        let writes = WRITES.lock().unwrap_or_else(PoisonError::into_inner);
+
+        writes.iter().any(|(written, at)| {
+            now.duration_since(*at) < WINDOW
+                && (path == written
+                    || path
+                        .strip_prefix(written.as_str())
+                        .is_some_and(|rest| rest.starts_with('/')))
+        })
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,658 @@
+use crate::ports::TransportPort;
+use async_trait::async_trait;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::channel::oneshot;
+use futures::lock::Mutex as AsyncMutex;
+use futures::StreamExt;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use x25519_dalek::{EphemeralSecret, PublicKey};
+use zeroize::Zeroize;
+
+pub const SERVICE_TYPE: &str = "_hoddor._tcp.local.";
+
+const OFFER_SCHEME: &str = "tcp://";
+const CODE_SEPARATOR: char = '#';
+const CODE_LEN: usize = 16;
+const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+const OFFERER_KEY_CONTEXT: &str = "hoddor 2024 lan transport offerer key";
+const ANSWERER_KEY_CONTEXT: &str = "hoddor 2024 lan transport answerer key";
+const ANSWER_CONTEXT: &str = "hoddor 2024 lan transport answer";
+const CONFIRMATION: &[u8] = b"hoddor lan pairing";
+
+struct Session {
+    /// Pairing code of the offer, which both peers must prove.
+    code: [u8; CODE_LEN],
+    writer: Mutex<Option<FrameWriter>>,
+    /// Answer expected from the peer, known once its handshake completed.
+    answer: Mutex<Option<String>>,
+    sender: UnboundedSender<Vec<u8>>,
+    receiver: Arc<AsyncMutex<UnboundedReceiver<Vec<u8>>>>,
+    closed: AtomicBool,
+    advertisement: Mutex<Option<String>>,
+}
+
+static SESSIONS: Lazy<Mutex<HashMap<String, Arc<Session>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+static MDNS: Lazy<Option<ServiceDaemon>> = Lazy::new(|| ServiceDaemon::new().ok());
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredPeer {
+    pub session: String,
+    /// Address of the offer, without its pairing code.
+    pub offer: String,
+}
+
+impl DiscoveredPeer {
+    /// The offer to accept, given the pairing code shown on the offering
+    /// device.
+    pub fn offer_with_code(&self, code: &str) -> String {
+        format!("{}{CODE_SEPARATOR}{code}", self.offer)
+    }
+}
+
+/// Direct TCP transport for replicas on the same local network.
+///
+/// Offers are `tcp://<ip>:<port>#<code>` addresses. The address is also
+/// advertised over mDNS, so a peer can either receive the offer by hand or
+/// find it with [`LanTransport::discover`] and complete it with the code.
+///
+/// Peers authenticate each other with the pairing code while agreeing on
+/// ephemeral X25519 keys, then every frame is sealed with ChaCha20-Poly1305.
+/// Connections that do not prove the code are dropped.
+#[derive(Clone, Copy)]
+pub struct LanTransport;
+
+impl Default for LanTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LanTransport {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Browses the local network for `timeout` and returns every pairing
+    /// offer currently advertised. Advertisements carry no pairing code.
+    pub fn discover(&self, timeout: Duration) -> Result<Vec<DiscoveredPeer>, Box<dyn Error>> {
+        let daemon = MDNS.as_ref().ok_or("mDNS is unavailable on this host")?;
+        let events = daemon.browse(SERVICE_TYPE)?;
+
+        let deadline = Instant::now() + timeout;
+        let mut peers = Vec::new();
+
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            match events.recv_timeout(remaining) {
+                Ok(ServiceEvent::ServiceResolved(info)) => {
+                    let Some(session) = info.get_property_val_str("session") else {
+                        continue;
+                    };
+
+                    for address in info.get_addresses_v4() {
+                        let peer = DiscoveredPeer {
+                            session: session.to_string(),
+                            offer: format!("{OFFER_SCHEME}{}:{}", address, info.get_port()),
+                        };
+                        if !peers.contains(&peer) {
+                            peers.push(peer);
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+
+        let _ = daemon.stop_browse(SERVICE_TYPE);
+
+        Ok(peers)
+    }
+
+    fn new_session(&self, session: &str, code: [u8; CODE_LEN]) -> Arc<Session> {
+        self.close(session);
+
+        let (sender, receiver) = mpsc::unbounded();
+        let state = Arc::new(Session {
+            code,
+            writer: Mutex::new(None),
+            answer: Mutex::new(None),
+            sender,
+            receiver: Arc::new(AsyncMutex::new(receiver)),
+            closed: AtomicBool::new(false),
+            advertisement: Mutex::new(None),
+        });
+
+        SESSIONS.lock().insert(session.to_string(), state.clone());
+
+        state
+    }
+
+    fn session(&self, session: &str) -> Result<Arc<Session>, Box<dyn Error>> {
+        SESSIONS
+            .lock()
+            .get(session)
+            .cloned()
+            .ok_or_else(|| format!("No pairing session named '{session}'").into())
+    }
+}
+
+// Runs blocking socket work on a thread of its own, so callers never block
+// the executor polling them.
+async fn run_blocking<T: Send + 'static>(
+    work: impl FnOnce() -> io::Result<T> + Send + 'static,
+) -> io::Result<T> {
+    let (sender, receiver) = oneshot::channel();
+    thread::spawn(move || {
+        let _ = sender.send(work());
+    });
+
+    receiver
+        .await
+        .map_err(|_| io::Error::other("LAN worker thread panicked"))?
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// One direction of a connection: frames are sealed with its key and
+/// numbered, the number being the nonce.
+struct Channel {
+    cipher: ChaCha20Poly1305,
+    counter: u64,
+}
+
+impl Channel {
+    fn new(mut key: [u8; 32]) -> Self {
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+        key.zeroize();
+        Self { cipher, counter: 0 }
+    }
+
+    fn next_nonce(&mut self) -> io::Result<Nonce> {
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&self.counter.to_le_bytes());
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or_else(|| invalid_data("LAN channel exhausted its nonces"))?;
+        Ok(Nonce::from(nonce))
+    }
+
+    fn seal(&mut self, plaintext: &[u8]) -> io::Result<Vec<u8>> {
+        let nonce = self.next_nonce()?;
+        self.cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| invalid_data("Failed to seal LAN frame"))
+    }
+
+    fn open(&mut self, ciphertext: &[u8]) -> io::Result<Vec<u8>> {
+        let nonce = self.next_nonce()?;
+        self.cipher
+            .decrypt(&nonce, ciphertext)
+            .map_err(|_| invalid_data("LAN frame failed authentication"))
+    }
+}
+
+struct FrameWriter {
+    stream: TcpStream,
+    channel: Channel,
+}
+
+impl FrameWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        let sealed = self.channel.seal(data)?;
+        write_frame(&mut self.stream, &sealed)
+    }
+}
+
+struct FrameReader {
+    stream: TcpStream,
+    channel: Channel,
+}
+
+impl FrameReader {
+    fn read(&mut self) -> io::Result<Vec<u8>> {
+        let sealed = read_frame(&mut self.stream)?;
+        self.channel.open(&sealed)
+    }
+}
+
+struct Connection {
+    writer: FrameWriter,
+    reader: FrameReader,
+    /// Derived from the handshake: the answerer hands it back to the
+    /// offerer, binding the answer to this very connection.
+    answer: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Offerer,
+    Answerer,
+}
+
+// Agrees on ephemeral keys mixed with the pairing code, then has each side
+// seal a confirmation: a peer without the code derives other keys and fails
+// to open it.
+fn handshake(stream: TcpStream, code: &[u8; CODE_LEN], role: Role) -> io::Result<Connection> {
+    stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+    let mut stream = stream;
+
+    let secret = EphemeralSecret::random_from_rng(OsRng);
+    let public = PublicKey::from(&secret);
+    write_frame(&mut stream, public.as_bytes())?;
+
+    let theirs: [u8; 32] = read_frame(&mut stream)?
+        .try_into()
+        .map_err(|_| invalid_data("Malformed LAN handshake"))?;
+    let shared = secret.diffie_hellman(&PublicKey::from(theirs));
+    if !shared.was_contributory() {
+        return Err(invalid_data("Malformed LAN handshake"));
+    }
+
+    let (offerer, answerer) = match role {
+        Role::Offerer => (*public.as_bytes(), theirs),
+        Role::Answerer => (theirs, *public.as_bytes()),
+    };
+    let mut material = Vec::with_capacity(CODE_LEN + 3 * 32);
+    material.extend_from_slice(code);
+    material.extend_from_slice(&offerer);
+    material.extend_from_slice(&answerer);
+    material.extend_from_slice(shared.as_bytes());
+
+    let offerer_key = blake3::derive_key(OFFERER_KEY_CONTEXT, &material);
+    let answerer_key = blake3::derive_key(ANSWERER_KEY_CONTEXT, &material);
+    let answer = hex::encode(blake3::derive_key(ANSWER_CONTEXT, &material));
+    material.zeroize();
+
+    let (sending, receiving) = match role {
+        Role::Offerer => (offerer_key, answerer_key),
+        Role::Answerer => (answerer_key, offerer_key),
+    };
+    let mut connection = Connection {
+        writer: FrameWriter {
+            stream: stream.try_clone()?,
+            channel: Channel::new(sending),
+        },
+        reader: FrameReader {
+            stream,
+            channel: Channel::new(receiving),
+        },
+        answer,
+    };
+
+    connection.writer.write(CONFIRMATION)?;
+    if connection.reader.read()? != CONFIRMATION {
+        return Err(invalid_data("LAN peer did not prove the pairing code"));
+    }
+
+    connection.reader.stream.set_read_timeout(None)?;
+    Ok(connection)
+}
+
+fn connect_session(state: &Session, connection: Connection) {
+    let Connection {
+        writer,
+        mut reader,
+        answer,
+    } = connection;
+    *state.writer.lock() = Some(writer);
+    *state.answer.lock() = Some(answer);
+
+    let sender = state.sender.clone();
+    thread::spawn(move || {
+        while let Ok(frame) = reader.read() {
+            if sender.unbounded_send(frame).is_err() {
+                break;
+            }
+        }
+        sender.close_channel();
+    });
+}
+
+fn spawn_acceptor(state: Arc<Session>, listener: TcpListener) -> io::Result<()> {
+    listener.set_nonblocking(true)?;
+
+    thread::spawn(move || {
+        while !state.closed.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, address)) => {
+                    let connection = stream
+                        .set_nonblocking(false)
+                        .and_then(|_| handshake(stream, &state.code, Role::Offerer));
+                    match connection {
+                        Ok(connection) => {
+                            connect_session(&state, connection);
+                            break;
+                        }
+                        Err(e) => {
+                            tracing::warn!(peer = %address, error = %e, "Rejected LAN peer");
+                        }
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(ACCEPT_POLL_INTERVAL);
+                }
+                Err(_) => break,
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn read_frame(stream: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut length = [0u8; 4];
+    stream.read_exact(&mut length)?;
+
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "LAN frame exceeds maximum size",
+        ));
+    }
+
+    let mut frame = vec![0; length];
+    stream.read_exact(&mut frame)?;
+    Ok(frame)
+}
+
+fn write_frame(stream: &mut impl Write, data: &[u8]) -> io::Result<()> {
+    let length = u32::try_from(data.len())
+        .ok()
+        .filter(|length| *length as usize <= MAX_FRAME_LEN)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Payload too large"))?;
+
+    stream.write_all(&length.to_be_bytes())?;
+    stream.write_all(data)?;
+    stream.flush()
+}
+
+fn local_ip() -> IpAddr {
+    // Connecting a UDP socket sends nothing; it only selects the outbound
+    // interface, whose address is what other LAN peers can reach.
+    UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .and_then(|socket| {
+            socket.connect("224.0.0.251:5353")?;
+            socket.local_addr()
+        })
+        .map(|address| address.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+}
+
+fn parse_offer(offer: &str) -> Result<(SocketAddr, [u8; CODE_LEN]), Box<dyn Error>> {
+    let (address, code) = offer
+        .trim()
+        .strip_prefix(OFFER_SCHEME)
+        .and_then(|offer| offer.split_once(CODE_SEPARATOR))
+        .ok_or("Invalid LAN offer: expected tcp://<ip>:<port>#<code>")?;
+
+    let address = address
+        .parse()
+        .map_err(|e| format!("Invalid LAN offer: {e}"))?;
+    let code = hex::decode(code)
+        .ok()
+        .and_then(|code| <[u8; CODE_LEN]>::try_from(code).ok())
+        .ok_or("Invalid LAN offer: malformed pairing code")?;
+
+    Ok((address, code))
+}
+
+fn advertise(session: &str, ip: IpAddr, port: u16) -> Option<String> {
+    let daemon = MDNS.as_ref()?;
+
+    let host = format!("hoddor-{port}.local.");
+    let properties = [("session", session)];
+    let info = ServiceInfo::new(
+        SERVICE_TYPE,
+        &format!("{session}-{port}"),
+        &host,
+        ip,
+        port,
+        &properties[..],
+    )
+    .ok()?;
+
+    let fullname = info.get_fullname().to_string();
+    daemon.register(info).ok()?;
+
+    Some(fullname)
+}
+
+#[async_trait(?Send)]
+impl TransportPort for LanTransport {
+    async fn create_offer(&self, session: &str) -> Result<String, Box<dyn Error>> {
+        let mut code = [0u8; CODE_LEN];
+        OsRng.fill_bytes(&mut code);
+        let state = self.new_session(session, code);
+
+        // Only the interface facing the LAN listens, not every interface.
+        let ip = local_ip();
+        let listener = TcpListener::bind((ip, 0))?;
+        let port = listener.local_addr()?.port();
+        spawn_acceptor(state.clone(), listener)?;
+
+        *state.advertisement.lock() = advertise(session, ip, port);
+
+        Ok(format!(
+            "{OFFER_SCHEME}{ip}:{port}{CODE_SEPARATOR}{}",
+            hex::encode(code)
+        ))
+    }
+
+    async fn accept_offer(&self, session: &str, offer: &str) -> Result<String, Box<dyn Error>> {
+        let (address, code) = parse_offer(offer)?;
+        let connection = run_blocking(move || {
+            let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+            handshake(stream, &code, Role::Answerer)
+        })
+        .await?;
+
+        let state = self.new_session(session, code);
+        let answer = connection.answer.clone();
+        connect_session(&state, connection);
+
+        Ok(answer)
+    }
+
+    async fn accept_answer(&self, session: &str, answer: &str) -> Result<(), Box<dyn Error>> {
+        let state = self.session(session)?;
+
+        // The acceptor may still be finishing its side of the handshake.
+        let expected = run_blocking(move || {
+            let deadline = Instant::now() + CONNECT_TIMEOUT;
+            loop {
+                if let Some(expected) = state.answer.lock().clone() {
+                    return Ok(expected);
+                }
+                if state.closed.load(Ordering::SeqCst) || Instant::now() >= deadline {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "LAN peer never completed pairing",
+                    ));
+                }
+                thread::sleep(ACCEPT_POLL_INTERVAL);
+            }
+        })
+        .await?;
+
+        if answer.trim() != expected {
+            return Err("Invalid LAN answer".into());
+        }
+
+        Ok(())
+    }
+
+    async fn send(&self, session: &str, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let state = self.session(session)?;
+        let data = data.to_vec();
+
+        run_blocking(move || {
+            let mut writer = state.writer.lock();
+            let writer = writer.as_mut().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotConnected,
+                    "LAN peer has not connected yet",
+                )
+            })?;
+            writer.write(&data)
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    async fn receive(&self, session: &str) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let receiver = self.session(session)?.receiver.clone();
+        let mut receiver = receiver.lock().await;
+
+        Ok(receiver.next().await)
+    }
+
+    fn is_connected(&self, session: &str) -> bool {
+        self.session(session)
+            .map(|state| !state.closed.load(Ordering::SeqCst) && state.writer.lock().is_some())
+            .unwrap_or(false)
+    }
+
+    fn close(&self, session: &str) {
+        let Some(state) = SESSIONS.lock().remove(session) else {
+            return;
+        };
+
+        state.closed.store(true, Ordering::SeqCst);
+
+        if let Some(writer) = state.writer.lock().take() {
+            let _ = writer.stream.shutdown(std::net::Shutdown::Both);
+        }
+
+        if let (Some(daemon), Some(fullname)) = (MDNS.as_ref(), state.advertisement.lock().take()) {
+            let _ = daemon.unregister(&fullname);
+        }
+
+        state.sender.close_channel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    fn wait_until_connected(transport: &LanTransport, session: &str) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !transport.is_connected(session) {
+            assert!(Instant::now() < deadline, "{session} never connected");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_lan_pairing_roundtrip() {
+        let transport = LanTransport::new();
+
+        let offer = block_on(transport.create_offer("lan-test-a")).unwrap();
+        let answer = block_on(transport.accept_offer("lan-test-b", &offer)).unwrap();
+        block_on(transport.accept_answer("lan-test-a", &answer)).unwrap();
+        wait_until_connected(&transport, "lan-test-a");
+
+        block_on(transport.send("lan-test-a", b"to b")).unwrap();
+        block_on(transport.send("lan-test-b", b"to a")).unwrap();
+
+        assert_eq!(
+            block_on(transport.receive("lan-test-b")).unwrap(),
+            Some(b"to b".to_vec())
+        );
+        assert_eq!(
+            block_on(transport.receive("lan-test-a")).unwrap(),
+            Some(b"to a".to_vec())
+        );
+
+        transport.close("lan-test-a");
+        assert_eq!(block_on(transport.receive("lan-test-b")).unwrap(), None);
+        transport.close("lan-test-b");
+    }
+
+    #[test]
+    fn test_send_before_peer_connects_fails() {
+        let transport = LanTransport::new();
+
+        block_on(transport.create_offer("lan-test-pending")).unwrap();
+
+        assert!(!transport.is_connected("lan-test-pending"));
+        assert!(block_on(transport.send("lan-test-pending", b"data")).is_err());
+
+        transport.close("lan-test-pending");
+    }
+
+    #[test]
+    fn test_peer_without_the_code_is_rejected() {
+        let transport = LanTransport::new();
+
+        let offer = block_on(transport.create_offer("lan-test-code-a")).unwrap();
+        let (address, _) = offer.split_once(CODE_SEPARATOR).unwrap();
+        let forged = format!("{address}{CODE_SEPARATOR}{}", "00".repeat(CODE_LEN));
+
+        assert!(block_on(transport.accept_offer("lan-test-code-b", &forged)).is_err());
+        assert!(!transport.is_connected("lan-test-code-a"));
+
+        // The offer stays open to the peer holding the code.
+        let answer = block_on(transport.accept_offer("lan-test-code-b", &offer)).unwrap();
+        block_on(transport.accept_answer("lan-test-code-a", &answer)).unwrap();
+        assert!(block_on(transport.accept_answer("lan-test-code-a", "ok")).is_err());
+
+        transport.close("lan-test-code-a");
+        transport.close("lan-test-code-b");
+    }
+
+    #[test]
+    fn test_parse_offer() {
+        let code = "0123456789abcdef0123456789abcdef";
+        let (address, parsed) = parse_offer(&format!("tcp://192.168.1.10:4242#{code}")).unwrap();
+
+        assert_eq!(address, "192.168.1.10:4242".parse::<SocketAddr>().unwrap());
+        assert_eq!(hex::encode(parsed), code);
+        assert!(parse_offer("tcp://192.168.1.10:4242").is_err());
+        assert!(parse_offer("tcp://192.168.1.10:4242#0123").is_err());
+        assert!(parse_offer(&format!("192.168.1.10:4242#{code}")).is_err());
+        assert!(parse_offer(&format!("tcp://not-an-address#{code}")).is_err());
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, b"payload").unwrap();
+
+        assert_eq!(&buffer[..4], &7u32.to_be_bytes());
+        assert_eq!(read_frame(&mut buffer.as_slice()).unwrap(), b"payload");
+    }
+
+    #[test]
+    fn test_oversized_frame_is_rejected() {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(&u32::MAX.to_be_bytes());
+
+        assert!(read_frame(&mut buffer.as_slice()).is_err());
+    }
+}
@@ -1,13 +1,48 @@
+//! Advisory file locks shared by every process using the same storage root.
+//!
+//! Each lock name maps to a file under `<storage root>.locks/<vault>/`, kept
+//! outside the storage root so lock files never show up as vaults or
+//! entries. Locks are taken with `flock` on Unix and `LockFileEx` on Windows
+//! and are tied to the open file, so separate acquisitions in one process
+//! exclude each other as they would across processes.
+//!
+//! The OS drops the lock of a process that exits, but not always on network
+//! filesystems. Exclusive holders therefore write their pid to the file and
+//! clear it on release: a waiter finding the pid of a process that is gone
+//! removes the file and locks a fresh one.
+
+use super::{Clock, FsStorage};
 use crate::domain::vault::error::VaultError;
-use crate::ports::{LockGuard, LockPort};
+use crate::ports::{ClockPort, LockGuard, LockPort};
 use async_trait::async_trait;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+const POLL_INTERVAL_MS: u32 = 20;
+const STALE_CHECK_AFTER_MS: f64 = 1_000.0;
+const LOCK_TIMEOUT_MS: f64 = 30_000.0;
 
-pub struct NativeLockGuard;
+pub struct NativeLockGuard {
+    file: File,
+    exclusive: bool,
+}
 
 impl LockGuard for NativeLockGuard {}
 
+impl Drop for NativeLockGuard {
+    fn drop(&mut self) {
+        if self.exclusive {
+            let _ = self.file.set_len(0);
+        }
+        let _ = self.file.unlock();
+    }
+}
+
 #[derive(Clone, Copy)]
-pub struct Locks;
+pub struct Locks {
+    storage_root: &'static str,
+}
 
 impl Default for Locks {
     fn default() -> Self {
@@ -17,14 +52,140 @@ impl Default for Locks {
 
 impl Locks {
     pub fn new() -> Self {
-        Self
+        Self {
+            storage_root: FsStorage::new().root_path(),
+        }
+    }
+
+    /// `vault/namespace/notes` becomes `<root>.locks/vault/namespace.notes.lock`.
+    fn lock_path(&self, name: &str) -> PathBuf {
+        let (vault, rest) = name.split_once('/').unwrap_or((name, "vault"));
+
+        PathBuf::from(format!("{}.locks", self.storage_root))
+            .join(vault)
+            .join(format!("{}.lock", rest.replace(['/', '\\'], ".")))
+    }
+
+    async fn lock(&self, name: &str, exclusive: bool) -> Result<NativeLockGuard, VaultError> {
+        let path = self.lock_path(name);
+        let started = Clock.now();
+        let mut stale_checked = false;
+
+        loop {
+            let mut file = open_lock_file(&path)?;
+            let attempt = if exclusive {
+                file.try_lock()
+            } else {
+                file.try_lock_shared()
+            };
+
+            match attempt {
+                Ok(()) if is_current(&file, &path) => {
+                    if exclusive {
+                        write_holder(&mut file);
+                    }
+                    return Ok(NativeLockGuard { file, exclusive });
+                }
+                // The file was removed as stale between opening and locking.
+                Ok(()) => continue,
+                Err(TryLockError::WouldBlock) => {}
+                Err(TryLockError::Error(e)) => {
+                    return Err(VaultError::io_error(format!("Failed to lock {name}: {e}")));
+                }
+            }
+            drop(file);
+
+            let waited = Clock.now() - started;
+            if !stale_checked && waited >= STALE_CHECK_AFTER_MS {
+                stale_checked = true;
+                if let Some(pid) = read_holder(&path).filter(|&pid| !process_exists(pid)) {
+                    tracing::warn!(
+                        lock = name,
+                        pid,
+                        "Removing stale lock left by exited process"
+                    );
+                    let _ = fs::remove_file(&path);
+                    continue;
+                }
+            }
+            if waited >= LOCK_TIMEOUT_MS {
+                let holder = read_holder(&path)
+                    .map(|pid| format!(" held by process {pid}"))
+                    .unwrap_or_default();
+                return Err(VaultError::io_error(format!(
+                    "Timed out waiting for lock {name}{holder}"
+                )));
+            }
+
+            Clock.sleep(POLL_INTERVAL_MS).await;
+        }
+    }
+}
+
+fn open_lock_file(path: &PathBuf) -> Result<File, VaultError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|_| VaultError::io_error("Failed to create lock directory"))?;
+    }
+
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|_| VaultError::io_error("Failed to open lock file"))
+}
+
+/// Whether `file` is still the one at `path`, as a waiter may have removed
+/// it as stale after it was opened.
+#[cfg(unix)]
+fn is_current(file: &File, path: &PathBuf) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(locked), Ok(current)) => locked.ino() == current.ino() && locked.dev() == current.dev(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_current(_file: &File, path: &PathBuf) -> bool {
+    path.exists()
+}
+
+fn write_holder(file: &mut File) {
+    let _ = file.set_len(0);
+    let _ = file.seek(SeekFrom::Start(0));
+    let _ = write!(file, "{}", std::process::id());
+    let _ = file.flush();
+}
+
+/// Pid of the exclusive holder, when the file names one. Shared holders
+/// leave it empty.
+fn read_holder(path: &PathBuf) -> Option<u32> {
+    let mut content = String::new();
+    File::open(path).ok()?.read_to_string(&mut content).ok()?;
+    content.trim().parse().ok()
+}
+
+/// Only Linux can tell cheaply; elsewhere a holder is assumed alive.
+fn process_exists(pid: u32) -> bool {
+    if cfg!(target_os = "linux") {
+        PathBuf::from(format!("/proc/{pid}")).exists()
+    } else {
+        true
     }
 }
 
 #[async_trait(?Send)]
 impl LockPort for Locks {
-    async fn acquire(&self, _name: &str) -> Result<Box<dyn LockGuard>, VaultError> {
-        Ok(Box::new(NativeLockGuard))
+    async fn acquire(&self, name: &str) -> Result<Box<dyn LockGuard>, VaultError> {
+        Ok(Box::new(self.lock(name, true).await?))
+    }
+
+    async fn acquire_shared(&self, name: &str) -> Result<Box<dyn LockGuard>, VaultError> {
+        Ok(Box::new(self.lock(name, false).await?))
     }
 }
 
@@ -50,4 +211,51 @@ mod tests {
         let locks = Locks::new();
         let _guard = block_on(locks.acquire("test_vault")).unwrap();
     }
+
+    #[test]
+    fn test_exclusive_lock_excludes_other_holders() {
+        let locks = Locks::new();
+        let path = locks.lock_path("locks_test/metadata");
+
+        block_on(async {
+            let guard = locks.acquire("locks_test/metadata").await.unwrap();
+            assert_eq!(read_holder(&path), Some(std::process::id()));
+
+            let other = open_lock_file(&path).unwrap();
+            assert!(matches!(
+                other.try_lock_shared(),
+                Err(TryLockError::WouldBlock)
+            ));
+
+            drop(guard);
+            assert_eq!(read_holder(&path), None);
+
+            let first = locks.acquire_shared("locks_test/metadata").await.unwrap();
+            let second = locks.acquire_shared("locks_test/metadata").await.unwrap();
+            assert!(matches!(other.try_lock(), Err(TryLockError::WouldBlock)));
+            drop((first, second));
+
+            assert!(other.try_lock().is_ok());
+        });
+    }
+
+    #[test]
+    fn test_stale_lock_of_exited_process_is_removed() {
+        if !cfg!(target_os = "linux") {
+            return;
+        }
+        let locks = Locks::new();
+        let path = locks.lock_path("locks_test/namespace/stale");
+
+        let mut stale = open_lock_file(&path).unwrap();
+        stale.try_lock().unwrap();
+        stale.set_len(0).unwrap();
+        write!(stale, "{}", u32::MAX).unwrap();
+
+        let guard = block_on(locks.acquire("locks_test/namespace/stale")).unwrap();
+        assert_eq!(read_holder(&path), Some(std::process::id()));
+
+        drop(guard);
+        drop(stale);
+    }
 }
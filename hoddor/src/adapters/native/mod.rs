@@ -1,15 +1,47 @@
+#[cfg(unix)]
+pub mod agent;
 pub mod clock;
 pub mod console_logger;
+pub mod error_reporter;
+#[cfg(feature = "fido2")]
+pub mod fido2_prf;
 pub mod fs_storage;
+#[cfg(feature = "sync")]
+pub mod lan_transport;
 pub mod locks;
 pub mod mock_prf;
 pub mod notifier;
 pub mod persistence;
+pub mod telemetry;
 
+#[cfg(feature = "notifications")]
+pub mod email_notifier;
+#[cfg(feature = "watch")]
+pub mod vault_watcher;
+#[cfg(feature = "notifications")]
+pub mod webhook_notifier;
+
+#[cfg(unix)]
+pub use agent::{Agent, AgentClient, AgentRequest, AgentResponse};
 pub use clock::Clock;
 pub use console_logger::ConsoleLogger;
+pub use error_reporter::ErrorReporter;
+#[cfg(feature = "fido2")]
+pub use fido2_prf::Fido2Prf;
 pub use fs_storage::FsStorage;
+#[cfg(feature = "sync")]
+pub use lan_transport::{DiscoveredPeer, LanTransport};
 pub use locks::Locks;
 pub use mock_prf::MockPrf;
 pub use notifier::Notifier;
 pub use persistence::Persistence;
+pub use telemetry::init_tracing;
+#[cfg(feature = "otlp")]
+pub use telemetry::{init_otlp_tracing, OtlpGuard};
+#[cfg(feature = "watch")]
+pub use vault_watcher::{ExternalChange, VaultWatcher};
+
+#[cfg(feature = "notifications")]
+pub use email_notifier::{EmailNotifier, SmtpConfig};
+#[cfg(feature = "notifications")]
+pub use webhook_notifier::WebhookNotifier;
@@ -1,9 +1,69 @@
+use crate::notifications::EventType;
 use crate::ports::NotifierPort;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
 
-/// Native notifier adapter (no-op).
+type Sink = Arc<dyn NotifierPort>;
+
+static SINKS: Lazy<RwLock<HashMap<String, Vec<Sink>>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+enum Delivery {
+    Event {
+        sinks: Vec<Sink>,
+        vault_name: String,
+        event: EventType,
+        detail: String,
+    },
+    Flush(Sender<()>),
+}
+
+// Sinks such as webhooks and SMTP block for seconds, so events are handed to
+// a thread of their own rather than delivered from the vault operation that
+// raised them.
+static QUEUE: Lazy<Sender<Delivery>> = Lazy::new(|| {
+    let (sender, receiver) = mpsc::channel::<Delivery>();
+
+    let spawned = std::thread::Builder::new()
+        .name("hoddor-notifier".to_string())
+        .spawn(move || {
+            for delivery in receiver {
+                match delivery {
+                    Delivery::Event {
+                        sinks,
+                        vault_name,
+                        event,
+                        detail,
+                    } => {
+                        for sink in sinks {
+                            if let Err(error) = sink.notify_event(&vault_name, event, &detail) {
+                                tracing::warn!(vault = %vault_name, %error, "Failed to deliver notification");
+                            }
+                        }
+                    }
+                    Delivery::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+    if let Err(error) = spawned {
+        tracing::error!(%error, "Failed to start the notification thread");
+    }
+
+    sender
+});
+
+/// Native notifier adapter.
 ///
 /// On native, there's no need for inter-context notifications since
-/// it's a single process with no workers or multiple tabs.
+/// it's a single process with no workers or multiple tabs. Events are
+/// forwarded to the sinks registered for their vault with
+/// [`Notifier::register`] instead, e.g. webhooks or email for headless
+/// deployments. Sinks are called in order on a background thread, so a slow
+/// one never holds up the operation raising the event.
 #[derive(Clone, Copy)]
 pub struct Notifier;
 
@@ -17,10 +77,138 @@ impl Notifier {
     pub fn new() -> Self {
         Self
     }
+
+    /// Forwards the events of `vault_name` to `sink`.
+    pub fn register(vault_name: &str, sink: Arc<dyn NotifierPort>) {
+        SINKS
+            .write()
+            .entry(vault_name.to_string())
+            .or_default()
+            .push(sink);
+    }
+
+    /// Removes the sinks registered for `vault_name`.
+    pub fn clear(vault_name: &str) {
+        SINKS.write().remove(vault_name);
+    }
+
+    /// Waits until the events raised so far have been delivered, e.g. before
+    /// the process exits.
+    pub fn flush() {
+        let (done, delivered) = mpsc::channel();
+        if QUEUE.send(Delivery::Flush(done)).is_ok() {
+            let _ = delivered.recv();
+        }
+    }
 }
 
 impl NotifierPort for Notifier {
     fn notify_vault_update(&self, _vault_name: &str, _vault_data: &[u8]) -> Result<(), String> {
         Ok(())
     }
+
+    fn notify_event(&self, vault_name: &str, event: EventType, detail: &str) -> Result<(), String> {
+        let Some(sinks) = SINKS.read().get(vault_name).cloned() else {
+            return Ok(());
+        };
+
+        QUEUE
+            .send(Delivery::Event {
+                sinks,
+                vault_name: vault_name.to_string(),
+                event,
+                detail: detail.to_string(),
+            })
+            .map_err(|_| "Notification thread is not running".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use std::sync::mpsc::Receiver;
+
+    struct RecordingSink {
+        events: Mutex<Vec<(String, EventType)>>,
+    }
+
+    impl NotifierPort for RecordingSink {
+        fn notify_vault_update(&self, _vault_name: &str, _vault_data: &[u8]) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn notify_event(
+            &self,
+            vault_name: &str,
+            event: EventType,
+            _detail: &str,
+        ) -> Result<(), String> {
+            self.events.lock().push((vault_name.to_string(), event));
+            Ok(())
+        }
+    }
+
+    // Blocks every delivery until the test releases it.
+    struct BlockedSink {
+        release: Mutex<Receiver<()>>,
+    }
+
+    impl NotifierPort for BlockedSink {
+        fn notify_vault_update(&self, _vault_name: &str, _vault_data: &[u8]) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn notify_event(
+            &self,
+            _vault_name: &str,
+            _event: EventType,
+            _detail: &str,
+        ) -> Result<(), String> {
+            let _ = self.release.lock().recv();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_events_are_forwarded_to_the_sinks_of_their_vault() {
+        let sink = Arc::new(RecordingSink {
+            events: Mutex::new(Vec::new()),
+        });
+        Notifier::register("notifier_test_vault", sink.clone());
+
+        Notifier::new()
+            .notify_event("notifier_test_vault", EventType::SyncConflict, "detail")
+            .unwrap();
+        Notifier::new()
+            .notify_event("notifier_other_vault", EventType::SyncConflict, "detail")
+            .unwrap();
+        Notifier::flush();
+
+        assert_eq!(
+            *sink.events.lock(),
+            [("notifier_test_vault".to_string(), EventType::SyncConflict)]
+        );
+        Notifier::clear("notifier_test_vault");
+    }
+
+    #[test]
+    fn test_slow_sinks_do_not_block_the_caller() {
+        let (release, released) = mpsc::channel();
+        Notifier::register(
+            "notifier_slow_vault",
+            Arc::new(BlockedSink {
+                release: Mutex::new(released),
+            }),
+        );
+
+        // Returns while the sink is still waiting to be released.
+        Notifier::new()
+            .notify_event("notifier_slow_vault", EventType::BackupCompleted, "")
+            .unwrap();
+
+        release.send(()).unwrap();
+        Notifier::flush();
+        Notifier::clear("notifier_slow_vault");
+    }
 }
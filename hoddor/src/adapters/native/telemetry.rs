@@ -0,0 +1,99 @@
+use tracing_subscriber::EnvFilter;
+
+/// Environment variable holding the event filter, in `RUST_LOG` syntax.
+pub const LOG_FILTER_ENV: &str = "HODDOR_LOG";
+
+/// Installs a formatting subscriber writing events and span context to
+/// stderr, keeping stdout free for command output.
+///
+/// The filter comes from `HODDOR_LOG`, or `default_filter` when it is unset
+/// or invalid. Returns false when another subscriber was installed first.
+pub fn init_tracing(default_filter: &str) -> bool {
+    let filter =
+        EnvFilter::try_from_env(LOG_FILTER_ENV).unwrap_or_else(|_| EnvFilter::new(default_filter));
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .try_init()
+        .is_ok()
+}
+
+/// Exporters installed by [`init_otlp_tracing`]. Dropping the guard flushes
+/// spans and metrics still buffered, so keep it alive until shutdown.
+#[cfg(feature = "otlp")]
+pub struct OtlpGuard {
+    tracer: opentelemetry_sdk::trace::SdkTracerProvider,
+    meter: opentelemetry_sdk::metrics::SdkMeterProvider,
+}
+
+#[cfg(feature = "otlp")]
+impl Drop for OtlpGuard {
+    fn drop(&mut self) {
+        let _ = self.tracer.shutdown();
+        let _ = self.meter.shutdown();
+    }
+}
+
+/// Like [`init_tracing`], and also exports spans and the
+/// `monotonic_counter.*`, `counter.*` and `histogram.*` fields of events over
+/// OTLP/HTTP. The endpoint and headers come from the standard `OTEL_*`
+/// variables.
+///
+/// The exporters use a blocking HTTP client: call this before starting an
+/// async runtime. Returns `None` when the exporters cannot be built or
+/// another subscriber was installed first.
+#[cfg(feature = "otlp")]
+pub fn init_otlp_tracing(default_filter: &str, service_name: &str) -> Option<OtlpGuard> {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::{MetricExporter, SpanExporter};
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+    use tracing_opentelemetry::{MetricsLayer, OpenTelemetryLayer};
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    let resource = Resource::builder()
+        .with_service_name(service_name.to_string())
+        .build();
+    let span_exporter = SpanExporter::builder().with_http().build().ok()?;
+    let metric_exporter = MetricExporter::builder().with_http().build().ok()?;
+
+    let guard = OtlpGuard {
+        tracer: SdkTracerProvider::builder()
+            .with_batch_exporter(span_exporter)
+            .with_resource(resource.clone())
+            .build(),
+        meter: SdkMeterProvider::builder()
+            .with_periodic_exporter(metric_exporter)
+            .with_resource(resource)
+            .build(),
+    };
+
+    let filter =
+        EnvFilter::try_from_env(LOG_FILTER_ENV).unwrap_or_else(|_| EnvFilter::new(default_filter));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(OpenTelemetryLayer::new(
+            guard.tracer.tracer(service_name.to_string()),
+        ))
+        .with(MetricsLayer::new(guard.meter.clone()))
+        .try_init()
+        .ok()?;
+
+    Some(guard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_tracing_installs_once() {
+        assert!(init_tracing("off"));
+        assert!(!init_tracing("off"));
+    }
+}
@@ -0,0 +1,147 @@
+use super::fs_storage::local_writes;
+use super::FsStorage;
+use crate::domain::vault::error::VaultError;
+use crate::domain::vault::external_changes;
+use crate::notifications::EventType;
+use crate::platform::Platform;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+
+/// A file changed on disk by another process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalChange {
+    pub vault: String,
+    /// Path of the file within the vault directory, empty when the vault
+    /// directory itself changed.
+    pub file: String,
+}
+
+/// Watches the storage directory for changes made by other processes, such
+/// as `hoddor-cli` working on the vaults of a running daemon.
+///
+/// Every change marks the vault stale for open [`VaultHandle`]s, is sent to
+/// the registered notifier sinks as an `externalChange` event and is passed
+/// to the callback. Writes made by this process are recognised and skipped.
+/// Watching stops when the watcher is dropped.
+///
+/// [`VaultHandle`]: crate::domain::vault::VaultHandle
+pub struct VaultWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl VaultWatcher {
+    /// Watches the directory of [`FsStorage`]. `on_change` runs on the
+    /// watcher thread.
+    pub fn start(on_change: impl Fn(&ExternalChange) + Send + 'static) -> Result<Self, VaultError> {
+        Self::start_at(FsStorage::new().root_path(), on_change)
+    }
+
+    pub fn start_at(
+        root: impl AsRef<Path>,
+        on_change: impl Fn(&ExternalChange) + Send + 'static,
+    ) -> Result<Self, VaultError> {
+        std::fs::create_dir_all(root.as_ref())
+            .map_err(|_| VaultError::io_error("Failed to create storage directory"))?;
+        let root = std::fs::canonicalize(root.as_ref())
+            .map_err(|_| VaultError::io_error("Failed to resolve storage directory"))?;
+
+        let handler_root = root.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "File watcher error");
+                        return;
+                    }
+                };
+                if matches!(event.kind, EventKind::Access(_) | EventKind::Other) {
+                    return;
+                }
+
+                for path in &event.paths {
+                    if let Some(change) = external_change(&handler_root, path) {
+                        external_changes::record_external_change(&change.vault);
+                        let _ = Platform::new().notifier().notify_event(
+                            &change.vault,
+                            EventType::ExternalChange,
+                            &change.file,
+                        );
+                        on_change(&change);
+                    }
+                }
+            })
+            .map_err(|e| VaultError::io_error(format!("Failed to start file watcher: {e}")))?;
+
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|e| VaultError::io_error(format!("Failed to watch storage: {e}")))?;
+
+        tracing::debug!("Watching storage for external changes");
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+fn external_change(root: &Path, path: &Path) -> Option<ExternalChange> {
+    let relative: PathBuf = path.strip_prefix(root).ok()?.components().collect();
+    let relative = relative.to_str()?.replace('\\', "/");
+
+    if relative.is_empty() || local_writes::contains(&relative) {
+        return None;
+    }
+
+    let (vault, file) = relative.split_once('/').unwrap_or((&relative, ""));
+
+    Some(ExternalChange {
+        vault: vault.to_string(),
+        file: file.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_external_change_skips_local_writes() {
+        let root = Path::new("/data");
+
+        assert_eq!(
+            external_change(root, Path::new("/data/notes/metadata.json")),
+            Some(ExternalChange {
+                vault: "notes".to_string(),
+                file: "metadata.json".to_string(),
+            })
+        );
+        assert_eq!(external_change(root, Path::new("/elsewhere/file")), None);
+
+        local_writes::record("watcher_test_vault");
+        assert_eq!(
+            external_change(root, Path::new("/data/watcher_test_vault/a.hoddor")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_watcher_reports_writes_by_others() {
+        let root = std::env::temp_dir().join("hoddor_watcher_test");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("shared")).unwrap();
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let _watcher = VaultWatcher::start_at(&root, move |change| {
+            let _ = sender.send(change.clone());
+        })
+        .unwrap();
+
+        std::fs::write(root.join("shared/notes.hoddor"), "{}").unwrap();
+
+        let change = receiver
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(change.vault, "shared");
+        assert!(external_changes::change_generation("shared") > 0);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::notifications::{EventType, Message, VaultEvent};
+use crate::ports::NotifierPort;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// POSTs configured vault events as JSON to a webhook URL.
+///
+/// Vault contents are never sent: `notify_vault_update` is a no-op. Requests
+/// block until answered, so register it with `Notifier::register` rather
+/// than calling it from vault operations.
+pub struct WebhookNotifier {
+    url: String,
+    events: Vec<EventType>,
+    agent: ureq::Agent,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: impl Into<String>, events: &[EventType]) -> Self {
+        Self {
+            url: url.into(),
+            events: events.to_vec(),
+            agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+        }
+    }
+}
+
+impl NotifierPort for WebhookNotifier {
+    fn notify_vault_update(&self, _vault_name: &str, _vault_data: &[u8]) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn notify_event(&self, vault_name: &str, event: EventType, detail: &str) -> Result<(), String> {
+        if !self.events.contains(&event) {
+            return Ok(());
+        }
+
+        let message = Message {
+            event,
+            data: VaultEvent {
+                vault_name: vault_name.to_string(),
+                detail: detail.to_string(),
+            },
+        };
+
+        self.agent
+            .post(&self.url)
+            .send_json(&message)
+            .map(|_| ())
+            .map_err(|e| format!("Webhook delivery failed: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn serve_once(listener: TcpListener) -> thread::JoinHandle<String> {
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            loop {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some((headers, body)) = text.split_once("\r\n\r\n") {
+                    let length = headers
+                        .lines()
+                        .find_map(|line| {
+                            line.to_ascii_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|value| value.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break;
+                    }
+                }
+            }
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        })
+    }
+
+    #[test]
+    fn test_webhook_posts_configured_event() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = serve_once(listener);
+
+        let notifier = WebhookNotifier::new(url, &[EventType::BackupCompleted]);
+        notifier
+            .notify_event("vault", EventType::BackupCompleted, "exported")
+            .unwrap();
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /hook"));
+        assert!(request.contains(r#""event":"backupCompleted""#));
+        assert!(request.contains(r#""vault_name":"vault""#));
+    }
+
+    #[test]
+    fn test_webhook_ignores_unconfigured_event() {
+        let notifier = WebhookNotifier::new("http://127.0.0.1:9/hook", &[EventType::SyncConflict]);
+
+        assert!(notifier
+            .notify_event("vault", EventType::BackupCompleted, "exported")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_webhook_reports_delivery_failure() {
+        let notifier = WebhookNotifier::new("http://127.0.0.1:9/hook", &[EventType::SyncConflict]);
+
+        assert!(notifier
+            .notify_event("vault", EventType::SyncConflict, "conflict")
+            .is_err());
+    }
+}
@@ -1,12 +1,13 @@
+use crate::domain::crypto::{CryptoError, DecryptFailure};
 use crate::ports::EncryptionPort;
 use age::{
     x25519::{Identity, Recipient},
-    Decryptor, Encryptor,
+    DecryptError, Decryptor, EncryptError, Encryptor,
 };
 use async_trait::async_trait;
 use futures::io::{AllowStdIo, AsyncReadExt, AsyncWriteExt};
 use std::error::Error;
-use std::io::Cursor;
+use std::io::{self, Cursor};
 
 #[derive(Clone, Copy, Debug)]
 pub struct AgeEncryption;
@@ -45,10 +46,16 @@ impl EncryptionPort for AgeEncryption {
         let mut encrypted = vec![];
         let cursor = Cursor::new(&mut encrypted);
         let async_cursor = AllowStdIo::new(cursor);
-        let mut writer = encryptor.wrap_output(Box::new(async_cursor))?;
+        let mut writer = encryptor
+            .wrap_output(Box::new(async_cursor))
+            .map_err(translate_encrypt_error)?;
 
-        AsyncWriteExt::write_all(&mut writer, data).await?;
-        AsyncWriteExt::close(&mut writer).await?;
+        AsyncWriteExt::write_all(&mut writer, data)
+            .await
+            .map_err(translate_io_error)?;
+        AsyncWriteExt::close(&mut writer)
+            .await
+            .map_err(translate_io_error)?;
 
         Ok(encrypted)
     }
@@ -60,21 +67,73 @@ impl EncryptionPort for AgeEncryption {
     ) -> Result<Vec<u8>, Box<dyn Error>> {
         let identity: Identity = identity_str.parse()?;
 
-        let decryptor = Decryptor::new(encrypted)?;
+        let decryptor = Decryptor::new(encrypted).map_err(translate_decrypt_error)?;
 
         match decryptor {
             Decryptor::Recipients(d) => {
                 let mut decrypted = vec![];
-                let reader = d.decrypt(std::iter::once(&identity as &dyn age::Identity))?;
+                let reader = d
+                    .decrypt(std::iter::once(&identity as &dyn age::Identity))
+                    .map_err(translate_decrypt_error)?;
                 let mut async_reader = AllowStdIo::new(reader);
-                AsyncReadExt::read_to_end(&mut async_reader, &mut decrypted).await?;
+                AsyncReadExt::read_to_end(&mut async_reader, &mut decrypted)
+                    .await
+                    .map_err(translate_io_error)?;
                 Ok(decrypted)
             }
-            _ => Err("File was not encrypted with recipients".into()),
+            // Passphrase-encrypted payloads need a passphrase, not an identity.
+            _ => Err(Box::new(CryptoError::Undecryptable(
+                DecryptFailure::NoMatchingKey,
+            ))),
         }
     }
 }
 
+// age's errors must not be formatted: their `Display` goes through a fluent
+// loader that fails in wasm. Only the variant, or the kind of an I/O error,
+// is looked at.
+
+fn translate_decrypt_error(error: DecryptError) -> CryptoError {
+    let failure = match error {
+        DecryptError::NoMatchingKeys | DecryptError::KeyDecryptionFailed => {
+            DecryptFailure::NoMatchingKey
+        }
+        DecryptError::DecryptionFailed | DecryptError::InvalidMac => DecryptFailure::Corrupted,
+        DecryptError::ExcessiveWork { .. } => DecryptFailure::ExcessiveWork,
+        DecryptError::InvalidHeader | DecryptError::UnknownFormat => DecryptFailure::UnknownFormat,
+        // Only the header is read before the payload stream starts.
+        DecryptError::Io(error)
+            if matches!(
+                error.kind(),
+                io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof
+            ) =>
+        {
+            DecryptFailure::UnknownFormat
+        }
+        DecryptError::Io(error) => return CryptoError::DecryptionError(error.kind().to_string()),
+    };
+
+    CryptoError::Undecryptable(failure)
+}
+
+fn translate_encrypt_error(error: EncryptError) -> CryptoError {
+    match error {
+        EncryptError::EncryptedIdentities(error) => translate_decrypt_error(error),
+        EncryptError::Io(error) => CryptoError::EncryptionError(error.kind().to_string()),
+    }
+}
+
+/// Payload streams report authentication failures as `InvalidData` and
+/// truncation as `UnexpectedEof`.
+fn translate_io_error(error: io::Error) -> CryptoError {
+    match error.kind() {
+        io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => {
+            CryptoError::Undecryptable(DecryptFailure::Corrupted)
+        }
+        kind => CryptoError::DecryptionError(kind.to_string()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -104,6 +163,34 @@ mod tests {
         assert!(result.is_err());
     }
 
+    fn failure_code(error: Box<dyn Error>) -> &'static str {
+        error.downcast::<CryptoError>().unwrap().code()
+    }
+
+    #[test]
+    fn test_decrypt_failures_have_codes() {
+        let adapter = AgeEncryption::new();
+        let identity = age::x25519::Identity::generate();
+        let other = age::x25519::Identity::generate();
+        let recipient = identity.to_public().to_string();
+        let other_str = other.to_string().expose_secret().to_string();
+        let identity_str = identity.to_string().expose_secret().to_string();
+
+        let encrypted = block_on(adapter.encrypt(b"secret", &[&recipient])).unwrap();
+
+        let wrong_key = block_on(adapter.decrypt(&encrypted, &other_str)).unwrap_err();
+        assert_eq!(failure_code(wrong_key), "no_matching_key");
+
+        let not_age = block_on(adapter.decrypt(b"not age", &identity_str)).unwrap_err();
+        assert_eq!(failure_code(not_age), "unknown_format");
+
+        let mut tampered = encrypted.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        let tampered = block_on(adapter.decrypt(&tampered, &identity_str)).unwrap_err();
+        assert_eq!(failure_code(tampered), "corrupted_data");
+    }
+
     #[test]
     fn test_encrypt_multiple_recipients() {
         let adapter = AgeEncryption::new();
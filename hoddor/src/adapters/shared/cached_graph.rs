@@ -0,0 +1,316 @@
+use crate::domain::graph::query_cache::{query_hash, QueryCache};
+use crate::domain::graph::{
+    GraphBackup, GraphNode, GraphResult, GraphStats, Id, NeighborFilter, NeighborNode, SearchQuery,
+    SearchResult,
+};
+use crate::ports::clock::ClockPort;
+use crate::ports::graph::GraphPort;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+type SharedCache = Arc<Mutex<QueryCache>>;
+
+static SHARED_DATABASE_CACHE: Lazy<SharedCache> = Lazy::new(SharedCache::default);
+
+/// What [`CachedGraph`] needs of the backend it wraps.
+pub trait GraphBackend: GraphPort + Clone + Default {
+    /// Backend over the database shared by every platform of the process.
+    fn shared() -> GraphResult<Self>;
+
+    /// Backend over a database of its own.
+    fn isolated() -> GraphResult<Self>;
+
+    fn with_clock(self, clock: &'static dyn ClockPort) -> Self;
+}
+
+/// Graph decorator caching vector search results in a
+/// [`QueryCache`](crate::domain::graph::query_cache), invalidated by the
+/// writes made through it. Adapters over the shared database share one
+/// cache, so a write through any platform invalidates it.
+#[derive(Clone)]
+pub struct CachedGraph<G> {
+    inner: G,
+    cache: SharedCache,
+}
+
+impl<G: GraphBackend> CachedGraph<G> {
+    pub fn new() -> GraphResult<Self> {
+        Ok(Self {
+            inner: G::shared()?,
+            cache: SHARED_DATABASE_CACHE.clone(),
+        })
+    }
+
+    /// Graph over a database and a cache of its own, for embedders that
+    /// must not see each other's graphs.
+    pub fn isolated() -> GraphResult<Self> {
+        Ok(Self {
+            inner: G::isolated()?,
+            cache: SharedCache::default(),
+        })
+    }
+
+    /// Same graph, timestamping nodes and edges with `clock`.
+    pub fn with_clock(self, clock: &'static dyn ClockPort) -> Self {
+        Self {
+            inner: self.inner.with_clock(clock),
+            cache: self.cache,
+        }
+    }
+
+    async fn cached(
+        &self,
+        vault_id: &str,
+        hash: u64,
+        search: impl std::future::Future<Output = GraphResult<Vec<SearchResult>>>,
+    ) -> GraphResult<Vec<SearchResult>> {
+        let generation = {
+            let cache = self.cache.lock();
+            if let Some(results) = cache.get(vault_id, hash) {
+                return Ok(results);
+            }
+            cache.generation()
+        };
+
+        let results = search.await?;
+        self.cache
+            .lock()
+            .insert(vault_id, hash, results.clone(), generation);
+        Ok(results)
+    }
+}
+
+impl<G: GraphBackend> Default for CachedGraph<G> {
+    fn default() -> Self {
+        Self {
+            inner: G::default(),
+            cache: SHARED_DATABASE_CACHE.clone(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<G: GraphBackend> GraphPort for CachedGraph<G> {
+    async fn create_node(
+        &self,
+        vault_id: &str,
+        node_type: &str,
+        content: String,
+        labels: Vec<String>,
+        embedding: Option<Vec<f32>>,
+        node_id: Option<&Id>,
+    ) -> GraphResult<Id> {
+        let result = self
+            .inner
+            .create_node(vault_id, node_type, content, labels, embedding, node_id)
+            .await;
+        self.cache.lock().invalidate_vault(vault_id);
+        result
+    }
+
+    async fn list_nodes_by_type(
+        &self,
+        vault_id: &str,
+        node_type: &str,
+        limit: Option<usize>,
+    ) -> GraphResult<Vec<GraphNode>> {
+        self.inner
+            .list_nodes_by_type(vault_id, node_type, limit)
+            .await
+    }
+
+    async fn create_edge(
+        &self,
+        vault_id: &str,
+        from_node: &Id,
+        to_node: &Id,
+        edge_type: &str,
+        weight: Option<f32>,
+        edge_id: Option<&Id>,
+    ) -> GraphResult<Id> {
+        let result = self
+            .inner
+            .create_edge(vault_id, from_node, to_node, edge_type, weight, edge_id)
+            .await;
+        self.cache.lock().invalidate_vault(vault_id);
+        result
+    }
+
+    async fn get_neighbors(
+        &self,
+        vault_id: &str,
+        node_id: &Id,
+        filter: &NeighborFilter,
+    ) -> GraphResult<Vec<NeighborNode>> {
+        self.inner.get_neighbors(vault_id, node_id, filter).await
+    }
+
+    async fn merge_nodes(&self, vault_id: &str, keep: &Id, remove: &Id) -> GraphResult<()> {
+        let result = self.inner.merge_nodes(vault_id, keep, remove).await;
+        self.cache.lock().invalidate_vault(vault_id);
+        result
+    }
+
+    async fn record_access(&self, vault_id: &str, node_ids: &[Id]) -> GraphResult<()> {
+        let result = self.inner.record_access(vault_id, node_ids).await;
+        self.cache.lock().invalidate_vault(vault_id);
+        result
+    }
+
+    async fn delete_nodes(&self, vault_id: &str, node_ids: &[Id]) -> GraphResult<usize> {
+        let result = self.inner.delete_nodes(vault_id, node_ids).await;
+        self.cache.lock().invalidate_vault(vault_id);
+        result
+    }
+
+    async fn set_embedding(
+        &self,
+        vault_id: &str,
+        node_id: &Id,
+        embedding: Vec<f32>,
+        version: u32,
+    ) -> GraphResult<()> {
+        let result = self
+            .inner
+            .set_embedding(vault_id, node_id, embedding, version)
+            .await;
+        self.cache.lock().invalidate_vault(vault_id);
+        result
+    }
+
+    async fn compact(&self) -> GraphResult<()> {
+        self.inner.compact().await
+    }
+
+    async fn vector_search_with_neighbors(
+        &self,
+        vault_id: &str,
+        query_embedding: Vec<f32>,
+        max_results: usize,
+        search_quality: usize,
+        include_neighbors: bool,
+    ) -> GraphResult<Vec<SearchResult>> {
+        let query = SearchQuery {
+            query_embedding,
+            max_results,
+            search_quality,
+            include_neighbors,
+        };
+        let hash = query_hash(&query, None);
+
+        self.cached(
+            vault_id,
+            hash,
+            self.inner.vector_search_with_neighbors(
+                vault_id,
+                query.query_embedding.clone(),
+                max_results,
+                search_quality,
+                include_neighbors,
+            ),
+        )
+        .await
+    }
+
+    async fn vector_search_page(
+        &self,
+        vault_id: &str,
+        query: &SearchQuery,
+        offset: usize,
+        limit: usize,
+    ) -> GraphResult<Vec<SearchResult>> {
+        let hash = query_hash(query, Some((offset, limit)));
+
+        self.cached(
+            vault_id,
+            hash,
+            self.inner
+                .vector_search_page(vault_id, query, offset, limit),
+        )
+        .await
+    }
+
+    async fn export_backup(&self, vault_id: &str) -> GraphResult<GraphBackup> {
+        self.inner.export_backup(vault_id).await
+    }
+
+    async fn import_backup(&self, backup: &GraphBackup) -> GraphResult<()> {
+        let result = self.inner.import_backup(backup).await;
+
+        let vaults: BTreeSet<&str> = backup
+            .nodes
+            .iter()
+            .map(|node| node.vault_id.as_str())
+            .chain(backup.edges.iter().map(|edge| edge.vault_id.as_str()))
+            .collect();
+        let mut cache = self.cache.lock();
+        for vault_id in vaults {
+            cache.invalidate_vault(vault_id);
+        }
+
+        result
+    }
+
+    async fn stats(&self) -> GraphResult<GraphStats> {
+        self.inner.stats().await
+    }
+
+    async fn reset(&self) -> GraphResult<()> {
+        let result = self.inner.reset().await;
+        self.cache.lock().clear();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::shared::MemoryGraphAdapter;
+    use crate::domain::graph::query_cache::query_cache_stats;
+    use crate::domain::graph::EMBEDDING_DIM;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_searches_are_cached_until_the_vault_is_written() {
+        let graph = CachedGraph::<MemoryGraphAdapter>::isolated().unwrap();
+        let embedding = vec![1.0; EMBEDDING_DIM];
+        let add_node = |vault: &'static str| {
+            graph.create_node(
+                vault,
+                "memory",
+                "content".to_string(),
+                Vec::new(),
+                Some(embedding.clone()),
+                None,
+            )
+        };
+        let search =
+            || graph.vector_search_with_neighbors("vault", embedding.clone(), 10, 50, false);
+        let cached = || graph.cache.lock().len();
+
+        block_on(async {
+            add_node("vault").await.unwrap();
+            assert_eq!(search().await.unwrap().len(), 1);
+            let hits = query_cache_stats().hits;
+            assert_eq!(search().await.unwrap().len(), 1);
+            assert!(query_cache_stats().hits > hits);
+
+            // Writes to another vault leave the results alone.
+            add_node("other").await.unwrap();
+            assert_eq!(cached(), 1);
+
+            add_node("vault").await.unwrap();
+            assert_eq!(cached(), 0);
+            assert_eq!(search().await.unwrap().len(), 2);
+        });
+    }
+
+    #[test]
+    fn test_conforms_to_graph_port() {
+        let graph = CachedGraph::<MemoryGraphAdapter>::isolated().unwrap();
+        block_on(crate::adapters::shared::graph_conformance::check_graph_port(&graph));
+    }
+}
@@ -0,0 +1,452 @@
+//! Behavior every [`GraphPort`] implementation shares, so that switching
+//! between the `graph-simple` and `graph-cozo` backends, or putting the
+//! query cache in front of one, changes nothing for callers. The tests of
+//! each adapter run [`check_graph_port`] on a graph of their own.
+//!
+//! Search results and neighbors are only compared on the fields every
+//! backend fills in: id, type, content and, for search results, labels.
+
+use crate::domain::graph::{
+    EdgeDirection, GraphError, GraphNode, GraphStats, Id, NeighborFilter, SearchQuery,
+    EMBEDDING_DIM,
+};
+use crate::ports::graph::GraphPort;
+
+const VAULT: &str = "conformance";
+const OTHER_VAULT: &str = "conformance_other";
+
+fn embedding(x: f32, y: f32) -> Vec<f32> {
+    let mut embedding = vec![0.0; EMBEDDING_DIM];
+    embedding[0] = x;
+    embedding[1] = y;
+    embedding
+}
+
+async fn node<G: GraphPort + ?Sized>(
+    graph: &G,
+    vault_id: &str,
+    content: &str,
+    labels: &[&str],
+    embedding: Option<Vec<f32>>,
+) -> Id {
+    graph
+        .create_node(
+            vault_id,
+            "doc",
+            content.to_string(),
+            labels.iter().map(|label| label.to_string()).collect(),
+            embedding,
+            None,
+        )
+        .await
+        .unwrap()
+}
+
+async fn nodes_of<G: GraphPort + ?Sized>(graph: &G, vault_id: &str) -> Vec<GraphNode> {
+    let mut nodes = graph
+        .list_nodes_by_type(vault_id, "doc", None)
+        .await
+        .unwrap();
+    nodes.sort_by_key(|node| node.id.as_str());
+    nodes
+}
+
+async fn neighbors<G: GraphPort + ?Sized>(
+    graph: &G,
+    node_id: &Id,
+    filter: NeighborFilter,
+) -> Vec<String> {
+    graph
+        .get_neighbors(VAULT, node_id, &filter)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|neighbor| neighbor.node.content)
+        .collect()
+}
+
+/// Runs every check on `graph`, resetting it before each.
+pub async fn check_graph_port<G: GraphPort + ?Sized>(graph: &G) {
+    check_nodes(graph).await;
+    check_neighbors(graph).await;
+    check_vector_search(graph).await;
+    check_merge_and_access(graph).await;
+    check_delete_and_embedding(graph).await;
+    check_backup_and_reset(graph).await;
+}
+
+async fn check_nodes<G: GraphPort + ?Sized>(graph: &G) {
+    graph.reset().await.unwrap();
+
+    let id = Id::new();
+    let created = graph
+        .create_node(
+            VAULT,
+            "doc",
+            "first".to_string(),
+            vec!["a".to_string(), "b".to_string()],
+            Some(embedding(1.0, 0.0)),
+            Some(&id),
+        )
+        .await
+        .unwrap();
+    assert_eq!(created, id);
+    node(graph, VAULT, "second", &[], None).await;
+    node(graph, OTHER_VAULT, "elsewhere", &[], None).await;
+    graph
+        .create_node(VAULT, "note", "other type".to_string(), vec![], None, None)
+        .await
+        .unwrap();
+
+    let nodes = nodes_of(graph, VAULT).await;
+    assert_eq!(nodes.len(), 2);
+    let first = nodes.iter().find(|node| node.id == id).unwrap();
+    assert_eq!(first.content, "first");
+    assert_eq!(first.node_type, "doc");
+    assert_eq!(first.vault_id, VAULT);
+    assert_eq!(first.labels, ["a", "b"]);
+    assert_eq!(first.embedding, Some(embedding(1.0, 0.0)));
+    assert_eq!(first.access_count, 0);
+    assert!(first.created_at > 0);
+
+    let limited = graph
+        .list_nodes_by_type(VAULT, "doc", Some(1))
+        .await
+        .unwrap();
+    assert_eq!(limited.len(), 1);
+    assert_eq!(
+        graph.stats().await.unwrap(),
+        GraphStats { nodes: 4, edges: 0 }
+    );
+}
+
+async fn check_neighbors<G: GraphPort + ?Sized>(graph: &G) {
+    graph.reset().await.unwrap();
+
+    let hub = node(graph, VAULT, "hub", &[], None).await;
+    let cited = node(graph, VAULT, "cited", &[], None).await;
+    let citing = node(graph, VAULT, "citing", &[], None).await;
+    let related = node(graph, VAULT, "related", &[], None).await;
+    for (from, to, edge_type, weight) in [
+        (&hub, &cited, "cites", Some(0.9)),
+        (&citing, &hub, "cites", Some(0.4)),
+        (&hub, &related, "related", None),
+        (&hub, &hub, "self", None),
+    ] {
+        graph
+            .create_edge(VAULT, from, to, edge_type, weight, None)
+            .await
+            .unwrap();
+    }
+    graph
+        .create_edge(OTHER_VAULT, &hub, &cited, "cites", None, None)
+        .await
+        .unwrap();
+
+    // Edges weigh 1 unless told otherwise, and self-loops are no neighbors.
+    let all = graph
+        .get_neighbors(VAULT, &hub, &NeighborFilter::default())
+        .await
+        .unwrap();
+    assert_eq!(all[0].weight, 1.0);
+    assert_eq!(all[0].edge_type, "related");
+    assert_eq!(
+        neighbors(graph, &hub, NeighborFilter::default()).await,
+        ["related", "cited", "citing"]
+    );
+    assert_eq!(
+        neighbors(
+            graph,
+            &hub,
+            NeighborFilter {
+                direction: EdgeDirection::Outgoing,
+                ..NeighborFilter::default()
+            }
+        )
+        .await,
+        ["related", "cited"]
+    );
+    assert_eq!(
+        neighbors(
+            graph,
+            &hub,
+            NeighborFilter {
+                direction: EdgeDirection::Incoming,
+                ..NeighborFilter::default()
+            }
+        )
+        .await,
+        ["citing"]
+    );
+    assert_eq!(
+        neighbors(
+            graph,
+            &hub,
+            NeighborFilter {
+                edge_types: vec!["cites".to_string()],
+                min_weight: Some(0.5),
+                ..NeighborFilter::default()
+            }
+        )
+        .await,
+        ["cited"]
+    );
+    assert_eq!(
+        neighbors(
+            graph,
+            &hub,
+            NeighborFilter {
+                limit: Some(1),
+                ..NeighborFilter::default()
+            }
+        )
+        .await,
+        ["related"]
+    );
+}
+
+async fn check_vector_search<G: GraphPort + ?Sized>(graph: &G) {
+    graph.reset().await.unwrap();
+
+    let far = node(graph, VAULT, "far", &[], Some(embedding(0.0, 1.0))).await;
+    let near = node(graph, VAULT, "near", &["close"], Some(embedding(1.0, 0.1))).await;
+    node(graph, VAULT, "unembedded", &[], None).await;
+    node(
+        graph,
+        OTHER_VAULT,
+        "elsewhere",
+        &[],
+        Some(embedding(1.0, 0.0)),
+    )
+    .await;
+    graph
+        .create_edge(VAULT, &near, &far, "cites", Some(0.5), None)
+        .await
+        .unwrap();
+
+    let results = graph
+        .vector_search_with_neighbors(VAULT, embedding(1.0, 0.0), 10, 50, true)
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].node.id, near);
+    assert_eq!(results[0].node.labels, ["close"]);
+    assert!(results[0].node.embedding.is_none());
+    assert!(results[0].distance < results[1].distance);
+    assert_eq!(results[0].neighbors.len(), 1);
+    assert_eq!(results[0].neighbors[0].node.id, far);
+    assert_eq!(results[0].neighbors[0].edge_type, "cites");
+    assert_eq!(results[0].neighbors[0].weight, 0.5);
+    assert_eq!(results[1].neighbors[0].node.id, near);
+
+    let results = graph
+        .vector_search_with_neighbors(VAULT, embedding(1.0, 0.0), 10, 50, false)
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result.neighbors.is_empty()));
+
+    let query = SearchQuery {
+        query_embedding: embedding(1.0, 0.0),
+        max_results: 10,
+        search_quality: 50,
+        include_neighbors: false,
+    };
+    let page = graph.vector_search_page(VAULT, &query, 1, 5).await.unwrap();
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].node.id, far);
+
+    assert!(matches!(
+        graph
+            .vector_search_with_neighbors(VAULT, vec![1.0; 3], 10, 50, false)
+            .await,
+        Err(GraphError::InvalidEmbedding(_))
+    ));
+    let query = SearchQuery {
+        query_embedding: vec![1.0; 3],
+        ..query
+    };
+    assert!(matches!(
+        graph.vector_search_page(VAULT, &query, 0, 5).await,
+        Err(GraphError::InvalidEmbedding(_))
+    ));
+}
+
+async fn check_merge_and_access<G: GraphPort + ?Sized>(graph: &G) {
+    graph.reset().await.unwrap();
+
+    let keep = node(graph, VAULT, "Paris", &["city"], None).await;
+    let duplicate = node(graph, VAULT, "Paris, France", &["capital"], None).await;
+    let france = node(graph, VAULT, "France", &[], None).await;
+    for (from, to, edge_type) in [
+        (&duplicate, &france, "in"),
+        (&keep, &france, "in"),
+        (&keep, &duplicate, "same_as"),
+    ] {
+        graph
+            .create_edge(VAULT, from, to, edge_type, None, None)
+            .await
+            .unwrap();
+    }
+
+    for id in [&duplicate, &duplicate, &keep] {
+        graph
+            .record_access(VAULT, std::slice::from_ref(id))
+            .await
+            .unwrap();
+    }
+    graph
+        .record_access(OTHER_VAULT, std::slice::from_ref(&keep))
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        graph.merge_nodes(VAULT, &keep, &keep).await,
+        Err(GraphError::Other(_))
+    ));
+    assert!(matches!(
+        graph.merge_nodes(VAULT, &keep, &Id::new()).await,
+        Err(GraphError::NodeNotFound(_))
+    ));
+    assert!(matches!(
+        graph.merge_nodes(OTHER_VAULT, &keep, &duplicate).await,
+        Err(GraphError::NodeNotFound(_))
+    ));
+
+    graph.merge_nodes(VAULT, &keep, &duplicate).await.unwrap();
+
+    let nodes = nodes_of(graph, VAULT).await;
+    assert_eq!(nodes.len(), 2);
+    let kept = nodes.iter().find(|node| node.id == keep).unwrap();
+    let mut labels = kept.labels.clone();
+    labels.sort();
+    assert_eq!(labels, ["capital", "city"]);
+    assert_eq!(kept.access_count, 3);
+    assert!(kept.last_accessed_at > 0);
+
+    // The edge between the two is gone, the moved one matched an existing
+    // edge of the node kept.
+    assert_eq!(
+        neighbors(graph, &keep, NeighborFilter::default()).await,
+        ["France"]
+    );
+    assert_eq!(
+        graph.stats().await.unwrap(),
+        GraphStats { nodes: 2, edges: 1 }
+    );
+}
+
+async fn check_delete_and_embedding<G: GraphPort + ?Sized>(graph: &G) {
+    graph.reset().await.unwrap();
+
+    let a = node(graph, VAULT, "a", &[], Some(embedding(1.0, 0.0))).await;
+    let b = node(graph, VAULT, "b", &[], None).await;
+    let c = node(graph, VAULT, "c", &[], None).await;
+    graph
+        .create_edge(VAULT, &a, &b, "next", None, None)
+        .await
+        .unwrap();
+    graph
+        .create_edge(VAULT, &b, &c, "next", None, None)
+        .await
+        .unwrap();
+
+    graph
+        .set_embedding(VAULT, &a, embedding(0.0, 1.0), 7)
+        .await
+        .unwrap();
+    let nodes = nodes_of(graph, VAULT).await;
+    let updated = nodes.iter().find(|node| node.id == a).unwrap();
+    assert_eq!(updated.embedding, Some(embedding(0.0, 1.0)));
+    assert_eq!(updated.embedding_version, 7);
+    let results = graph
+        .vector_search_with_neighbors(VAULT, embedding(0.0, 1.0), 1, 50, false)
+        .await
+        .unwrap();
+    assert_eq!(results[0].node.id, a);
+    assert!(results[0].distance < 1e-3);
+
+    assert!(matches!(
+        graph
+            .set_embedding(VAULT, &Id::new(), embedding(0.0, 1.0), 7)
+            .await,
+        Err(GraphError::NodeNotFound(_))
+    ));
+    assert!(matches!(
+        graph
+            .set_embedding(OTHER_VAULT, &a, embedding(0.0, 1.0), 7)
+            .await,
+        Err(GraphError::NodeNotFound(_))
+    ));
+    assert!(matches!(
+        graph.set_embedding(VAULT, &a, vec![1.0; 3], 7).await,
+        Err(GraphError::InvalidEmbedding(_))
+    ));
+
+    assert_eq!(
+        graph
+            .delete_nodes(OTHER_VAULT, std::slice::from_ref(&a))
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        graph
+            .delete_nodes(VAULT, &[b.clone(), Id::new()])
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        graph.stats().await.unwrap(),
+        GraphStats { nodes: 2, edges: 0 }
+    );
+    graph.compact().await.unwrap();
+    assert_eq!(nodes_of(graph, VAULT).await.len(), 2);
+}
+
+async fn check_backup_and_reset<G: GraphPort + ?Sized>(graph: &G) {
+    graph.reset().await.unwrap();
+
+    let a = node(graph, VAULT, "a", &["x", "y"], Some(embedding(1.0, 0.0))).await;
+    let b = node(graph, VAULT, "b", &[], None).await;
+    graph
+        .create_edge(VAULT, &a, &b, "links", Some(0.3), None)
+        .await
+        .unwrap();
+    graph
+        .record_access(VAULT, std::slice::from_ref(&a))
+        .await
+        .unwrap();
+    node(graph, OTHER_VAULT, "elsewhere", &[], None).await;
+
+    let backup = graph.export_backup(VAULT).await.unwrap();
+    assert_eq!(backup.nodes.len(), 2);
+    assert_eq!(backup.edges.len(), 1);
+    let exported = serde_json::to_value(&backup).unwrap();
+    assert_eq!(
+        serde_json::to_value(graph.export_backup(VAULT).await.unwrap()).unwrap(),
+        exported
+    );
+
+    graph.reset().await.unwrap();
+    assert_eq!(graph.stats().await.unwrap(), GraphStats::default());
+
+    // Nodes come back as they were, timestamps and accesses included;
+    // edges are created again.
+    graph.import_backup(&backup).await.unwrap();
+    assert_eq!(
+        graph.stats().await.unwrap(),
+        GraphStats { nodes: 2, edges: 1 }
+    );
+    let restored = graph.export_backup(VAULT).await.unwrap();
+    assert_eq!(
+        serde_json::to_value(&restored.nodes).unwrap(),
+        exported["nodes"]
+    );
+    let edge = &restored.edges[0];
+    assert_eq!(edge.id, backup.edges[0].id);
+    assert_eq!((&edge.from_node, &edge.to_node), (&a, &b));
+    assert_eq!(edge.edge_type, "links");
+    assert_eq!(edge.weight, 0.3);
+}
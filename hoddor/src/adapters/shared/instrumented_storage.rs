@@ -0,0 +1,139 @@
+use crate::domain::io_stats::{self, IoOperation};
+use crate::domain::vault::error::VaultError;
+use crate::ports::{ClockPort, StoragePort};
+use async_trait::async_trait;
+use std::future::Future;
+
+/// Storage decorator recording the duration and size of every operation in
+/// [`io_stats`].
+#[derive(Clone, Copy)]
+pub struct InstrumentedStorage<S> {
+    inner: S,
+    clock: &'static dyn ClockPort,
+}
+
+impl<S: StoragePort> InstrumentedStorage<S> {
+    pub fn new(inner: S, clock: &'static dyn ClockPort) -> Self {
+        Self { inner, clock }
+    }
+
+    /// Same storage timed with `clock`.
+    pub fn with_clock(self, clock: &'static dyn ClockPort) -> Self {
+        Self { clock, ..self }
+    }
+
+    async fn timed<T>(
+        &self,
+        operation: IoOperation,
+        path: &str,
+        future: impl Future<Output = Result<T, VaultError>>,
+        size: impl FnOnce(&T) -> usize,
+    ) -> Result<T, VaultError> {
+        let started = self.clock.now();
+        let result = future.await;
+        let millis = (self.clock.now() - started).max(0.0);
+
+        let bytes = result.as_ref().map(size).unwrap_or(0);
+        io_stats::record(operation, path, bytes, millis, result.is_ok());
+
+        result
+    }
+}
+
+#[async_trait(?Send)]
+impl<S: StoragePort> StoragePort for InstrumentedStorage<S> {
+    async fn read_file(&self, path: &str) -> Result<String, VaultError> {
+        self.timed(
+            IoOperation::Read,
+            path,
+            self.inner.read_file(path),
+            |content| content.len(),
+        )
+        .await
+    }
+
+    // The reads of a batch overlap, so each is recorded with an even share
+    // of the batch duration.
+    async fn read_files(&self, paths: &[String]) -> Vec<Result<String, VaultError>> {
+        let started = self.clock.now();
+        let results = self.inner.read_files(paths).await;
+        let millis = (self.clock.now() - started).max(0.0) / paths.len().max(1) as f64;
+
+        for (path, result) in paths.iter().zip(&results) {
+            let bytes = result.as_ref().map(String::len).unwrap_or(0);
+            io_stats::record(IoOperation::Read, path, bytes, millis, result.is_ok());
+        }
+
+        results
+    }
+
+    async fn write_file(&self, path: &str, content: &str) -> Result<(), VaultError> {
+        self.timed(
+            IoOperation::Write,
+            path,
+            self.inner.write_file(path, content),
+            |_| content.len(),
+        )
+        .await
+    }
+
+    async fn delete_file(&self, path: &str) -> Result<(), VaultError> {
+        self.timed(
+            IoOperation::Delete,
+            path,
+            self.inner.delete_file(path),
+            |_| 0,
+        )
+        .await
+    }
+
+    async fn create_directory(&self, path: &str) -> Result<(), VaultError> {
+        self.timed(
+            IoOperation::CreateDirectory,
+            path,
+            self.inner.create_directory(path),
+            |_| 0,
+        )
+        .await
+    }
+
+    async fn delete_directory(&self, path: &str) -> Result<(), VaultError> {
+        self.timed(
+            IoOperation::DeleteDirectory,
+            path,
+            self.inner.delete_directory(path),
+            |_| 0,
+        )
+        .await
+    }
+
+    async fn directory_exists(&self, path: &str) -> Result<bool, VaultError> {
+        self.timed(
+            IoOperation::DirectoryExists,
+            path,
+            self.inner.directory_exists(path),
+            |_| 0,
+        )
+        .await
+    }
+
+    async fn list_entries(&self, path: &str) -> Result<Vec<String>, VaultError> {
+        self.timed(
+            IoOperation::List,
+            path,
+            self.inner.list_entries(path),
+            |_| 0,
+        )
+        .await
+    }
+
+    async fn rename_directory(&self, from: &str, to: &str) -> Result<(), VaultError> {
+        self.timed(
+            IoOperation::RenameDirectory,
+            from,
+            self.inner.rename_directory(from, to),
+            |_| 0,
+        )
+        .await
+    }
+}
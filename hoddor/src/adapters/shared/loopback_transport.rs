@@ -0,0 +1,160 @@
+use crate::ports::TransportPort;
+use async_trait::async_trait;
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::lock::Mutex as AsyncMutex;
+use futures::StreamExt;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+
+const OFFER_PREFIX: &str = "loopback:";
+
+struct Endpoint {
+    peer: Option<String>,
+    inbox: UnboundedSender<Vec<u8>>,
+    receiver: Arc<AsyncMutex<UnboundedReceiver<Vec<u8>>>>,
+}
+
+static ENDPOINTS: Lazy<Mutex<HashMap<String, Endpoint>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// In-memory transport of test mode. Offers and answers name the session
+/// that made them, and payloads go straight to the other session's inbox,
+/// so two vaults of one page pair without WebRTC or a network.
+#[derive(Clone, Copy, Default)]
+pub struct LoopbackTransport;
+
+impl LoopbackTransport {
+    fn open(&self, session: &str, peer: Option<String>) {
+        let (inbox, receiver) = mpsc::unbounded();
+        ENDPOINTS.lock().insert(
+            session.to_string(),
+            Endpoint {
+                peer,
+                inbox,
+                receiver: Arc::new(AsyncMutex::new(receiver)),
+            },
+        );
+    }
+
+    /// Closes every session, between two tests.
+    pub fn reset() {
+        ENDPOINTS.lock().clear();
+    }
+}
+
+fn session_of(code: &str) -> Result<&str, Box<dyn Error>> {
+    code.strip_prefix(OFFER_PREFIX)
+        .ok_or_else(|| format!("Not a loopback pairing code: {code}").into())
+}
+
+#[async_trait(?Send)]
+impl TransportPort for LoopbackTransport {
+    async fn create_offer(&self, session: &str) -> Result<String, Box<dyn Error>> {
+        self.open(session, None);
+        Ok(format!("{OFFER_PREFIX}{session}"))
+    }
+
+    async fn accept_offer(&self, session: &str, offer: &str) -> Result<String, Box<dyn Error>> {
+        let initiator = session_of(offer)?;
+        if !ENDPOINTS.lock().contains_key(initiator) {
+            return Err(format!("No loopback session named '{initiator}'").into());
+        }
+
+        self.open(session, Some(initiator.to_string()));
+        Ok(format!("{OFFER_PREFIX}{session}"))
+    }
+
+    async fn accept_answer(&self, session: &str, answer: &str) -> Result<(), Box<dyn Error>> {
+        let responder = session_of(answer)?.to_string();
+
+        let mut endpoints = ENDPOINTS.lock();
+        if !endpoints.contains_key(&responder) {
+            return Err(format!("No loopback session named '{responder}'").into());
+        }
+        let endpoint = endpoints
+            .get_mut(session)
+            .ok_or_else(|| format!("No pairing session named '{session}'"))?;
+        endpoint.peer = Some(responder);
+
+        Ok(())
+    }
+
+    async fn send(&self, session: &str, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let endpoints = ENDPOINTS.lock();
+        let peer = endpoints
+            .get(session)
+            .and_then(|endpoint| endpoint.peer.as_ref())
+            .and_then(|peer| endpoints.get(peer))
+            .filter(|peer| peer.peer.as_deref() == Some(session))
+            .ok_or_else(|| format!("Pairing session '{session}' is not connected"))?;
+
+        peer.inbox
+            .unbounded_send(data.to_vec())
+            .map_err(|e| e.to_string().into())
+    }
+
+    async fn receive(&self, session: &str) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let receiver = ENDPOINTS
+            .lock()
+            .get(session)
+            .map(|endpoint| endpoint.receiver.clone())
+            .ok_or_else(|| format!("No pairing session named '{session}'"))?;
+
+        let mut receiver = receiver.lock().await;
+        Ok(receiver.next().await)
+    }
+
+    fn is_connected(&self, session: &str) -> bool {
+        let endpoints = ENDPOINTS.lock();
+        endpoints
+            .get(session)
+            .and_then(|endpoint| endpoint.peer.as_ref())
+            .and_then(|peer| endpoints.get(peer))
+            .is_some_and(|peer| peer.peer.as_deref() == Some(session))
+    }
+
+    fn close(&self, session: &str) {
+        ENDPOINTS.lock().remove(session);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_sessions_pair_and_exchange_payloads() {
+        let transport = LoopbackTransport;
+
+        block_on(async {
+            let offer = transport.create_offer("loopback-a").await.unwrap();
+            let answer = transport.accept_offer("loopback-b", &offer).await.unwrap();
+            assert!(!transport.is_connected("loopback-a"));
+
+            transport
+                .accept_answer("loopback-a", &answer)
+                .await
+                .unwrap();
+            assert!(transport.is_connected("loopback-a"));
+            assert!(transport.is_connected("loopback-b"));
+
+            transport.send("loopback-a", b"ping").await.unwrap();
+            transport.send("loopback-b", b"pong").await.unwrap();
+            assert_eq!(
+                transport.receive("loopback-b").await.unwrap(),
+                Some(b"ping".to_vec())
+            );
+            assert_eq!(
+                transport.receive("loopback-a").await.unwrap(),
+                Some(b"pong".to_vec())
+            );
+
+            transport.close("loopback-b");
+            assert!(!transport.is_connected("loopback-a"));
+            assert!(transport.send("loopback-a", b"lost").await.is_err());
+        });
+    }
+}
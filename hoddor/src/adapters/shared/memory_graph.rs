@@ -0,0 +1,800 @@
+use crate::adapters::shared::cached_graph::GraphBackend;
+use crate::adapters::Clock;
+use crate::domain::graph::{
+    embedding_version, EdgeDirection, GraphBackup, GraphEdge, GraphError, GraphNode, GraphResult,
+    GraphStats, Id, NeighborFilter, NeighborNode, SearchQuery, SearchResult, EMBEDDING_DIM,
+};
+use crate::ports::clock::ClockPort;
+use crate::ports::graph::GraphPort;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+/// Nodes and edges of every vault, keyed by id.
+#[derive(Default)]
+struct Graphs {
+    nodes: BTreeMap<String, GraphNode>,
+    edges: BTreeMap<String, GraphEdge>,
+}
+
+type Database = Arc<Mutex<Graphs>>;
+
+static GLOBAL_MEMORY_GRAPH: Lazy<Database> = Lazy::new(Database::default);
+
+/// Graph backend of the `graph-simple` feature: plain maps and an exact,
+/// linear vector search instead of CozoDB and its HNSW index. It answers the
+/// same queries as the `graph-cozo` backend for a fraction of the binary
+/// size, and is fast enough for a few thousand nodes per vault.
+#[derive(Clone)]
+pub struct MemoryGraphAdapter {
+    db: Database,
+    clock: &'static dyn ClockPort,
+}
+
+impl MemoryGraphAdapter {
+    /// Adapter over the graphs shared by every platform of the process.
+    pub fn new() -> GraphResult<Self> {
+        Ok(Self {
+            db: GLOBAL_MEMORY_GRAPH.clone(),
+            clock: &Clock,
+        })
+    }
+
+    /// Adapter over graphs of its own, for embedders that must not see each
+    /// other's graphs.
+    pub fn isolated() -> GraphResult<Self> {
+        Ok(Self {
+            db: Database::default(),
+            clock: &Clock,
+        })
+    }
+
+    /// Same graphs, timestamping nodes and edges with `clock`.
+    pub fn with_clock(mut self, clock: &'static dyn ClockPort) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn get_timestamp(&self) -> u64 {
+        self.clock.now() as u64
+    }
+
+    /// Nodes of the vault with an embedding, closest to `query` first.
+    fn rank(graphs: &Graphs, vault_id: &str, query: &[f32]) -> GraphResult<Vec<SearchResult>> {
+        check_embedding(query)?;
+
+        let mut results: Vec<SearchResult> = graphs
+            .nodes
+            .values()
+            .filter(|node| node.vault_id == vault_id)
+            .filter_map(|node| {
+                let distance = cosine_distance(query, node.embedding.as_deref()?);
+                Some(SearchResult {
+                    node: without_embedding(node),
+                    distance,
+                    neighbors: Vec::new(),
+                })
+            })
+            .collect();
+
+        results.sort_by(|a, b| {
+            a.distance
+                .total_cmp(&b.distance)
+                .then_with(|| a.node.id.as_str().cmp(&b.node.id.as_str()))
+        });
+
+        Ok(results)
+    }
+
+    fn add_neighbors(graphs: &Graphs, vault_id: &str, results: &mut [SearchResult]) {
+        for result in results {
+            let id = result.node.id.as_str();
+
+            for edge in graphs.edges.values() {
+                if edge.vault_id != vault_id {
+                    continue;
+                }
+
+                let neighbor_id = if edge.from_node.as_str() == id {
+                    edge.to_node.as_str()
+                } else if edge.to_node.as_str() == id {
+                    edge.from_node.as_str()
+                } else {
+                    continue;
+                };
+
+                if neighbor_id == id {
+                    continue;
+                }
+
+                if let Some(neighbor) = graphs.nodes.get(&neighbor_id) {
+                    result.neighbors.push(NeighborNode {
+                        node: without_embedding(neighbor),
+                        edge_type: edge.edge_type.clone(),
+                        weight: edge.weight,
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl Default for MemoryGraphAdapter {
+    fn default() -> Self {
+        Self {
+            db: GLOBAL_MEMORY_GRAPH.clone(),
+            clock: &Clock,
+        }
+    }
+}
+
+impl GraphBackend for MemoryGraphAdapter {
+    fn shared() -> GraphResult<Self> {
+        Self::new()
+    }
+
+    fn isolated() -> GraphResult<Self> {
+        Self::isolated()
+    }
+
+    fn with_clock(self, clock: &'static dyn ClockPort) -> Self {
+        self.with_clock(clock)
+    }
+}
+
+fn check_embedding(embedding: &[f32]) -> GraphResult<()> {
+    if embedding.len() != EMBEDDING_DIM {
+        return Err(GraphError::InvalidEmbedding(format!(
+            "Expected {} dimensions, got {}",
+            EMBEDDING_DIM,
+            embedding.len()
+        )));
+    }
+
+    Ok(())
+}
+
+/// `1 - cos(a, b)`, the distance CozoDB's cosine index reports. A zero
+/// vector is at distance 1 from everything.
+fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 1.0;
+    }
+
+    1.0 - dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+/// Search results leave embeddings out, as the CozoDB backend does.
+fn without_embedding(node: &GraphNode) -> GraphNode {
+    GraphNode {
+        embedding: None,
+        ..node.clone()
+    }
+}
+
+#[async_trait(?Send)]
+impl GraphPort for MemoryGraphAdapter {
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, node_type = node_type))]
+    async fn create_node(
+        &self,
+        vault_id: &str,
+        node_type: &str,
+        content: String,
+        labels: Vec<String>,
+        embedding: Option<Vec<f32>>,
+        node_id: Option<&Id>,
+    ) -> GraphResult<Id> {
+        if let Some(embedding) = &embedding {
+            check_embedding(embedding)?;
+        }
+
+        let node_id = node_id.cloned().unwrap_or_default();
+        let node = GraphNode {
+            id: node_id.clone(),
+            node_type: node_type.to_string(),
+            vault_id: vault_id.to_string(),
+            content,
+            labels,
+            embedding,
+            created_at: self.get_timestamp(),
+            access_count: 0,
+            last_accessed_at: 0,
+            embedding_version: embedding_version(),
+        };
+
+        self.db.lock().nodes.insert(node_id.as_str(), node);
+
+        Ok(node_id)
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, node_type = node_type, limit = ?limit))]
+    async fn list_nodes_by_type(
+        &self,
+        vault_id: &str,
+        node_type: &str,
+        limit: Option<usize>,
+    ) -> GraphResult<Vec<GraphNode>> {
+        Ok(self
+            .db
+            .lock()
+            .nodes
+            .values()
+            .filter(|node| node.vault_id == vault_id && node.node_type == node_type)
+            .take(limit.unwrap_or(100))
+            .cloned()
+            .collect())
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, edge_type = edge_type))]
+    async fn create_edge(
+        &self,
+        vault_id: &str,
+        from_node: &Id,
+        to_node: &Id,
+        edge_type: &str,
+        weight: Option<f32>,
+        edge_id: Option<&Id>,
+    ) -> GraphResult<Id> {
+        let edge_id = edge_id.cloned().unwrap_or_default();
+        let edge = GraphEdge {
+            id: edge_id.clone(),
+            from_node: from_node.clone(),
+            to_node: to_node.clone(),
+            edge_type: edge_type.to_string(),
+            vault_id: vault_id.to_string(),
+            weight: weight.unwrap_or(1.0),
+            created_at: self.get_timestamp(),
+        };
+
+        self.db.lock().edges.insert(edge_id.as_str(), edge);
+
+        Ok(edge_id)
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, node = %node_id.as_str()))]
+    async fn get_neighbors(
+        &self,
+        vault_id: &str,
+        node_id: &Id,
+        filter: &NeighborFilter,
+    ) -> GraphResult<Vec<NeighborNode>> {
+        let graphs = self.db.lock();
+        let id = node_id.as_str();
+
+        let mut neighbors: Vec<NeighborNode> = graphs
+            .edges
+            .values()
+            .filter(|edge| edge.vault_id == vault_id)
+            .filter(|edge| {
+                filter.edge_types.is_empty() || filter.edge_types.contains(&edge.edge_type)
+            })
+            .filter(|edge| filter.min_weight.is_none_or(|min| edge.weight >= min))
+            .filter_map(|edge| {
+                let outgoing = edge.from_node.as_str() == id;
+                let incoming = edge.to_node.as_str() == id;
+                let neighbor_id = match filter.direction {
+                    EdgeDirection::Outgoing | EdgeDirection::Both if outgoing => {
+                        edge.to_node.as_str()
+                    }
+                    EdgeDirection::Incoming | EdgeDirection::Both if incoming => {
+                        edge.from_node.as_str()
+                    }
+                    _ => return None,
+                };
+                if neighbor_id == id {
+                    return None;
+                }
+
+                graphs.nodes.get(&neighbor_id).map(|neighbor| NeighborNode {
+                    node: without_embedding(neighbor),
+                    edge_type: edge.edge_type.clone(),
+                    weight: edge.weight,
+                })
+            })
+            .collect();
+
+        neighbors.sort_by(|a, b| {
+            b.weight
+                .total_cmp(&a.weight)
+                .then_with(|| a.node.id.as_str().cmp(&b.node.id.as_str()))
+        });
+        if let Some(limit) = filter.limit {
+            neighbors.truncate(limit);
+        }
+
+        Ok(neighbors)
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id))]
+    async fn merge_nodes(&self, vault_id: &str, keep: &Id, remove: &Id) -> GraphResult<()> {
+        if keep == remove {
+            return Err(GraphError::Other(
+                "Cannot merge a node into itself".to_string(),
+            ));
+        }
+
+        let mut graphs = self.db.lock();
+        for id in [keep, remove] {
+            if graphs
+                .nodes
+                .get(&id.as_str())
+                .is_none_or(|node| node.vault_id != vault_id)
+            {
+                return Err(GraphError::NodeNotFound(id.as_str()));
+            }
+        }
+
+        if let Some(removed) = graphs.nodes.remove(&remove.as_str()) {
+            if let Some(kept) = graphs.nodes.get_mut(&keep.as_str()) {
+                for label in removed.labels {
+                    if !kept.labels.contains(&label) {
+                        kept.labels.push(label);
+                    }
+                }
+                kept.access_count += removed.access_count;
+                kept.last_accessed_at = kept.last_accessed_at.max(removed.last_accessed_at);
+            }
+        }
+
+        let (moved, kept): (Vec<GraphEdge>, Vec<GraphEdge>) = graphs
+            .edges
+            .values()
+            .filter(|edge| edge.vault_id == vault_id)
+            .cloned()
+            .partition(|edge| edge.from_node == *remove || edge.to_node == *remove);
+        let mut existing: HashSet<(Id, Id, String)> = kept
+            .into_iter()
+            .map(|edge| (edge.from_node, edge.to_node, edge.edge_type))
+            .collect();
+
+        for mut edge in moved {
+            let id = edge.id.as_str();
+            graphs.edges.remove(&id);
+
+            if edge.from_node == *remove {
+                edge.from_node = keep.clone();
+            }
+            if edge.to_node == *remove {
+                edge.to_node = keep.clone();
+            }
+            if edge.from_node != edge.to_node
+                && existing.insert((
+                    edge.from_node.clone(),
+                    edge.to_node.clone(),
+                    edge.edge_type.clone(),
+                ))
+            {
+                graphs.edges.insert(id, edge);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, nodes = node_ids.len()))]
+    async fn record_access(&self, vault_id: &str, node_ids: &[Id]) -> GraphResult<()> {
+        let now = self.get_timestamp();
+        let mut graphs = self.db.lock();
+
+        for id in node_ids {
+            if let Some(node) = graphs.nodes.get_mut(&id.as_str()) {
+                if node.vault_id == vault_id {
+                    node.access_count += 1;
+                    node.last_accessed_at = now;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, nodes = node_ids.len()))]
+    async fn delete_nodes(&self, vault_id: &str, node_ids: &[Id]) -> GraphResult<usize> {
+        let mut graphs = self.db.lock();
+
+        let mut deleted = HashSet::new();
+        for id in node_ids {
+            let id = id.as_str();
+            if graphs
+                .nodes
+                .get(&id)
+                .is_some_and(|node| node.vault_id == vault_id)
+            {
+                graphs.nodes.remove(&id);
+                deleted.insert(id);
+            }
+        }
+
+        graphs.edges.retain(|_, edge| {
+            edge.vault_id != vault_id
+                || !(deleted.contains(&edge.from_node.as_str())
+                    || deleted.contains(&edge.to_node.as_str()))
+        });
+
+        Ok(deleted.len())
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, node = %node_id.as_str()))]
+    async fn set_embedding(
+        &self,
+        vault_id: &str,
+        node_id: &Id,
+        embedding: Vec<f32>,
+        version: u32,
+    ) -> GraphResult<()> {
+        check_embedding(&embedding)?;
+
+        let mut graphs = self.db.lock();
+        let node = graphs
+            .nodes
+            .get_mut(&node_id.as_str())
+            .filter(|node| node.vault_id == vault_id)
+            .ok_or_else(|| GraphError::NodeNotFound(node_id.as_str()))?;
+        node.embedding = Some(embedding);
+        node.embedding_version = version;
+
+        Ok(())
+    }
+
+    /// Removed entries are freed right away, nothing is left to reclaim.
+    async fn compact(&self) -> GraphResult<()> {
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, max_results = max_results, include_neighbors = include_neighbors))]
+    async fn vector_search_with_neighbors(
+        &self,
+        vault_id: &str,
+        query_embedding: Vec<f32>,
+        max_results: usize,
+        _search_quality: usize,
+        include_neighbors: bool,
+    ) -> GraphResult<Vec<SearchResult>> {
+        let graphs = self.db.lock();
+
+        let mut results = Self::rank(&graphs, vault_id, &query_embedding)?;
+        results.truncate(max_results);
+
+        if include_neighbors {
+            Self::add_neighbors(&graphs, vault_id, &mut results);
+        }
+
+        Ok(results)
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, offset = offset, limit = limit))]
+    async fn vector_search_page(
+        &self,
+        vault_id: &str,
+        query: &SearchQuery,
+        offset: usize,
+        limit: usize,
+    ) -> GraphResult<Vec<SearchResult>> {
+        let graphs = self.db.lock();
+
+        let mut results: Vec<SearchResult> = Self::rank(&graphs, vault_id, &query.query_embedding)?
+            .into_iter()
+            .take(query.max_results)
+            .skip(offset)
+            .take(limit)
+            .collect();
+
+        if query.include_neighbors {
+            Self::add_neighbors(&graphs, vault_id, &mut results);
+        }
+
+        Ok(results)
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id))]
+    async fn export_backup(&self, vault_id: &str) -> GraphResult<GraphBackup> {
+        let graphs = self.db.lock();
+
+        let mut nodes: Vec<GraphNode> = graphs
+            .nodes
+            .values()
+            .filter(|node| node.vault_id == vault_id)
+            .cloned()
+            .collect();
+        nodes.sort_by_key(|node| (node.created_at, node.id.as_str()));
+
+        let mut edges: Vec<GraphEdge> = graphs
+            .edges
+            .values()
+            .filter(|edge| edge.vault_id == vault_id)
+            .cloned()
+            .collect();
+        edges.sort_by_key(|edge| (edge.created_at, edge.id.as_str()));
+
+        let created_at = nodes
+            .iter()
+            .map(|node| node.created_at)
+            .chain(edges.iter().map(|edge| edge.created_at))
+            .max()
+            .unwrap_or(0);
+
+        Ok(GraphBackup {
+            version: 1,
+            nodes,
+            edges,
+            created_at,
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(nodes = backup.nodes.len(), edges = backup.edges.len()))]
+    async fn import_backup(&self, backup: &GraphBackup) -> GraphResult<()> {
+        // Nodes keep their timestamps and access counts, which recall
+        // ranks by.
+        for node in &backup.nodes {
+            if let Some(embedding) = &node.embedding {
+                check_embedding(embedding)?;
+            }
+            self.db.lock().nodes.insert(node.id.as_str(), node.clone());
+        }
+
+        for edge in &backup.edges {
+            self.create_edge(
+                &edge.vault_id,
+                &edge.from_node,
+                &edge.to_node,
+                &edge.edge_type,
+                Some(edge.weight),
+                Some(&edge.id),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn stats(&self) -> GraphResult<GraphStats> {
+        let graphs = self.db.lock();
+
+        Ok(GraphStats {
+            nodes: graphs.nodes.len(),
+            edges: graphs.edges.len(),
+        })
+    }
+
+    async fn reset(&self) -> GraphResult<()> {
+        *self.db.lock() = Graphs::default();
+
+        tracing::warn!("Graph database reset");
+        Ok(())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    fn embedding(first: f32, second: f32) -> Vec<f32> {
+        let mut embedding = vec![0.0; EMBEDDING_DIM];
+        embedding[0] = first;
+        embedding[1] = second;
+        embedding
+    }
+
+    #[test]
+    fn test_vector_search_ranks_by_cosine_distance() {
+        let graph = MemoryGraphAdapter::isolated().unwrap();
+
+        block_on(async {
+            let far = graph
+                .create_node(
+                    "v",
+                    "doc",
+                    "far".into(),
+                    vec![],
+                    Some(embedding(0.0, 1.0)),
+                    None,
+                )
+                .await
+                .unwrap();
+            let near = graph
+                .create_node(
+                    "v",
+                    "doc",
+                    "near".into(),
+                    vec![],
+                    Some(embedding(1.0, 0.1)),
+                    None,
+                )
+                .await
+                .unwrap();
+            graph
+                .create_node(
+                    "other",
+                    "doc",
+                    "x".into(),
+                    vec![],
+                    Some(embedding(1.0, 0.0)),
+                    None,
+                )
+                .await
+                .unwrap();
+            graph
+                .create_edge("v", &near, &far, "cites", Some(0.5), None)
+                .await
+                .unwrap();
+
+            let results = graph
+                .vector_search_with_neighbors("v", embedding(1.0, 0.0), 10, 50, true)
+                .await
+                .unwrap();
+
+            assert_eq!(results.len(), 2);
+            assert_eq!(results[0].node.id, near);
+            assert!(results[0].distance < results[1].distance);
+            assert!(results[0].node.embedding.is_none());
+            assert_eq!(results[0].neighbors.len(), 1);
+            assert_eq!(results[0].neighbors[0].node.id, far);
+            assert_eq!(results[0].neighbors[0].edge_type, "cites");
+
+            let page = graph
+                .vector_search_page(
+                    "v",
+                    &SearchQuery {
+                        query_embedding: embedding(1.0, 0.0),
+                        max_results: 10,
+                        search_quality: 50,
+                        include_neighbors: false,
+                    },
+                    1,
+                    5,
+                )
+                .await
+                .unwrap();
+            assert_eq!(page.len(), 1);
+            assert_eq!(page[0].node.id, far);
+        });
+    }
+
+    #[test]
+    fn test_get_neighbors_filters_edges() {
+        let graph = MemoryGraphAdapter::isolated().unwrap();
+
+        block_on(async {
+            let mut ids = Vec::new();
+            for content in ["hub", "cited", "citing", "related"] {
+                ids.push(
+                    graph
+                        .create_node("v", "doc", content.into(), vec![], None, None)
+                        .await
+                        .unwrap(),
+                );
+            }
+            let (hub, cited, citing, related) = (&ids[0], &ids[1], &ids[2], &ids[3]);
+            graph
+                .create_edge("v", hub, cited, "cites", Some(0.9), None)
+                .await
+                .unwrap();
+            graph
+                .create_edge("v", citing, hub, "cites", Some(0.4), None)
+                .await
+                .unwrap();
+            graph
+                .create_edge("v", hub, related, "related", Some(0.2), None)
+                .await
+                .unwrap();
+
+            let neighbors = |filter: NeighborFilter| {
+                let graph = graph.clone();
+                async move {
+                    graph
+                        .get_neighbors("v", hub, &filter)
+                        .await
+                        .unwrap()
+                        .into_iter()
+                        .map(|neighbor| neighbor.node.content)
+                        .collect::<Vec<_>>()
+                }
+            };
+
+            assert_eq!(
+                neighbors(NeighborFilter::default()).await,
+                ["cited", "citing", "related"]
+            );
+            assert_eq!(
+                neighbors(NeighborFilter {
+                    direction: EdgeDirection::Outgoing,
+                    ..NeighborFilter::default()
+                })
+                .await,
+                ["cited", "related"]
+            );
+            assert_eq!(
+                neighbors(NeighborFilter {
+                    direction: EdgeDirection::Incoming,
+                    ..NeighborFilter::default()
+                })
+                .await,
+                ["citing"]
+            );
+            assert_eq!(
+                neighbors(NeighborFilter {
+                    edge_types: vec!["cites".into()],
+                    min_weight: Some(0.5),
+                    ..NeighborFilter::default()
+                })
+                .await,
+                ["cited"]
+            );
+            assert_eq!(
+                neighbors(NeighborFilter {
+                    limit: Some(1),
+                    ..NeighborFilter::default()
+                })
+                .await,
+                ["cited"]
+            );
+        });
+    }
+
+    #[test]
+    fn test_conforms_to_graph_port() {
+        let graph = MemoryGraphAdapter::isolated().unwrap();
+        block_on(crate::adapters::shared::graph_conformance::check_graph_port(&graph));
+    }
+
+    #[test]
+    fn test_rejects_wrong_embedding_dimension() {
+        let graph = MemoryGraphAdapter::isolated().unwrap();
+
+        block_on(async {
+            let created = graph
+                .create_node("v", "doc", "x".into(), vec![], Some(vec![1.0; 3]), None)
+                .await;
+            assert!(matches!(created, Err(GraphError::InvalidEmbedding(_))));
+
+            let searched = graph
+                .vector_search_with_neighbors("v", vec![1.0; 3], 10, 50, false)
+                .await;
+            assert!(matches!(searched, Err(GraphError::InvalidEmbedding(_))));
+        });
+    }
+
+    #[test]
+    fn test_backup_roundtrip_and_reset() {
+        let graph = MemoryGraphAdapter::isolated().unwrap();
+
+        block_on(async {
+            let a = graph
+                .create_node("v", "doc", "a".into(), vec!["t".into()], None, None)
+                .await
+                .unwrap();
+            let b = graph
+                .create_node("v", "doc", "b".into(), vec![], None, None)
+                .await
+                .unwrap();
+            graph
+                .create_edge("v", &a, &b, "links", None, None)
+                .await
+                .unwrap();
+
+            let backup = graph.export_backup("v").await.unwrap();
+            graph.reset().await.unwrap();
+            assert_eq!(graph.stats().await.unwrap(), GraphStats::default());
+
+            graph.import_backup(&backup).await.unwrap();
+            assert_eq!(
+                graph.stats().await.unwrap(),
+                GraphStats { nodes: 2, edges: 1 }
+            );
+            let nodes = graph.list_nodes_by_type("v", "doc", None).await.unwrap();
+            assert!(nodes
+                .iter()
+                .any(|node| node.id == a && node.labels == ["t"]));
+        });
+    }
+}
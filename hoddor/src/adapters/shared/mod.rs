@@ -1,7 +1,33 @@
 pub mod age_encryption;
 pub mod age_identity;
 pub mod argon2_kdf;
+pub mod instrumented_storage;
+pub mod prf_seed;
+pub mod recorded_notifier;
+
+#[cfg(feature = "graph")]
+pub mod cached_graph;
+#[cfg(all(test, feature = "graph"))]
+pub mod graph_conformance;
+#[cfg(feature = "test-mode")]
+pub mod loopback_transport;
+#[cfg(feature = "graph")]
+pub mod memory_graph;
+#[cfg(feature = "test-mode")]
+pub mod test_clock;
 
 pub use age_encryption::AgeEncryption;
 pub use age_identity::AgeIdentity;
 pub use argon2_kdf::Argon2Kdf;
+pub use instrumented_storage::InstrumentedStorage;
+pub use prf_seed::seed_from_prf;
+pub use recorded_notifier::RecordedNotifier;
+
+#[cfg(feature = "graph")]
+pub use cached_graph::{CachedGraph, GraphBackend};
+#[cfg(feature = "test-mode")]
+pub use loopback_transport::LoopbackTransport;
+#[cfg(feature = "graph")]
+pub use memory_graph::MemoryGraphAdapter;
+#[cfg(feature = "test-mode")]
+pub use test_clock::TestClock;
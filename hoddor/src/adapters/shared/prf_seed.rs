@@ -0,0 +1,23 @@
+use hkdf::Hkdf;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+
+/// Mixes the two PRF outputs of an authenticator into an identity seed.
+/// Shared by the PRF adapters so that the same outputs give the same
+/// identity whichever way they were obtained.
+pub fn seed_from_prf(first: &[u8], second: &[u8]) -> Result<[u8; 32], Box<dyn Error>> {
+    if first.is_empty() {
+        return Err("Missing first PRF value".into());
+    }
+    if second.is_empty() {
+        return Err("Missing second PRF value".into());
+    }
+
+    let mut prf = first.to_vec();
+    prf.extend(second);
+
+    let mixed_prf = Sha256::digest(&prf);
+    let (prk, _) = Hkdf::<Sha256>::extract(Some("hoddor/vault".as_bytes()), mixed_prf.as_slice());
+
+    Ok(prk.into())
+}
@@ -0,0 +1,33 @@
+use crate::domain::recent_events;
+use crate::notifications::EventType;
+use crate::ports::{ClockPort, NotifierPort};
+
+/// Notifier decorator keeping every event it forwards in
+/// [`recent_events`].
+#[derive(Clone, Copy)]
+pub struct RecordedNotifier<N> {
+    inner: N,
+    clock: &'static dyn ClockPort,
+}
+
+impl<N: NotifierPort> RecordedNotifier<N> {
+    pub fn new(inner: N, clock: &'static dyn ClockPort) -> Self {
+        Self { inner, clock }
+    }
+
+    /// Same notifier timestamping events with `clock`.
+    pub fn with_clock(self, clock: &'static dyn ClockPort) -> Self {
+        Self { clock, ..self }
+    }
+}
+
+impl<N: NotifierPort> NotifierPort for RecordedNotifier<N> {
+    fn notify_vault_update(&self, vault_name: &str, vault_data: &[u8]) -> Result<(), String> {
+        self.inner.notify_vault_update(vault_name, vault_data)
+    }
+
+    fn notify_event(&self, vault_name: &str, event: EventType, detail: &str) -> Result<(), String> {
+        recent_events::record(vault_name, event, detail, self.clock.now());
+        self.inner.notify_event(vault_name, event, detail)
+    }
+}
@@ -0,0 +1,27 @@
+use crate::domain::test_mode;
+use crate::ports::clock::ClockPort;
+use async_trait::async_trait;
+
+/// Clock of test mode: reads the time the test set, and advances it on
+/// sleep instead of waiting.
+#[derive(Clone, Copy, Default)]
+pub struct TestClock;
+
+#[async_trait(?Send)]
+impl ClockPort for TestClock {
+    fn now(&self) -> f64 {
+        test_mode::test_clock_millis() as f64
+    }
+
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    async fn sleep(&self, milliseconds: u32) {
+        test_mode::advance_test_clock(milliseconds.into());
+
+        // Still yields, so that tasks a retry loop waits on get to run.
+        #[cfg(target_arch = "wasm32")]
+        gloo_timers::future::TimeoutFuture::new(0).await;
+    }
+}
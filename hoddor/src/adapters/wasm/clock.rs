@@ -1,5 +1,6 @@
 use crate::global::get_global_scope;
 use crate::ports::clock::ClockPort;
+use async_trait::async_trait;
 use wasm_bindgen::JsCast;
 use web_sys::{Performance, WorkerGlobalScope};
 
@@ -27,18 +28,23 @@ impl Clock {
     }
 }
 
+#[async_trait(?Send)]
 impl ClockPort for Clock {
     fn now(&self) -> f64 {
         if let Some(perf) = self.get_performance() {
-            perf.now()
+            perf.time_origin() + perf.now()
         } else {
-            0.0
+            js_sys::Date::now()
         }
     }
 
     fn is_available(&self) -> bool {
         self.get_performance().is_some()
     }
+
+    async fn sleep(&self, milliseconds: u32) {
+        gloo_timers::future::TimeoutFuture::new(milliseconds).await;
+    }
 }
 
 #[cfg(all(test, target_arch = "wasm32"))]
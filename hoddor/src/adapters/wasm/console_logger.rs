@@ -3,22 +3,15 @@ use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
 extern "C" {
-    #[wasm_bindgen(js_namespace = console)]
-    fn log(s: &str);
-
     #[wasm_bindgen(js_namespace = console)]
     fn time(s: &str);
 
     #[wasm_bindgen(js_namespace = console, js_name = timeEnd)]
     fn time_end(s: &str);
-
-    #[wasm_bindgen(js_namespace = console)]
-    fn error(s: &str);
-
-    #[wasm_bindgen(js_namespace = console)]
-    fn warn(s: &str);
 }
 
+/// Messages go through `tracing`, so they honour the console level set with
+/// `set_log_level`; timers stay on the console API.
 #[derive(Debug, Clone, Copy)]
 pub struct ConsoleLogger;
 
@@ -36,15 +29,15 @@ impl Default for ConsoleLogger {
 
 impl LoggerPort for ConsoleLogger {
     fn log(&self, message: &str) {
-        log(message);
+        tracing::info!("{message}");
     }
 
     fn error(&self, message: &str) {
-        error(message);
+        tracing::error!("{message}");
     }
 
     fn warn(&self, message: &str) {
-        warn(message);
+        tracing::warn!("{message}");
     }
 
     fn time(&self, label: &str) {
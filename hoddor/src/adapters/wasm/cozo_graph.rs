@@ -1,29 +1,111 @@
+use crate::adapters::shared::cached_graph::GraphBackend;
+use crate::adapters::wasm::Clock;
 use crate::domain::graph::{
-    GraphBackup, GraphEdge, GraphError, GraphNode, GraphResult, Id, NeighborNode, SearchResult,
+    embedding_version, EdgeDirection, GraphBackup, GraphEdge, GraphError, GraphNode, GraphResult,
+    GraphStats, Id, NeighborFilter, NeighborNode, SearchQuery, SearchResult, EMBEDDING_DIM,
 };
+use crate::ports::clock::ClockPort;
 use crate::ports::graph::GraphPort;
 use async_trait::async_trait;
 use cozo::{DataValue, DbInstance, ScriptMutability, Vector};
 use ndarray::Array1;
 use once_cell::sync::Lazy;
-use std::collections::BTreeMap;
-use std::sync::{Arc, Mutex};
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
 
 // HNSW Index Configuration
 // ========================
-// DEFAULT_EMBEDDING_DIM: Vector dimension (384 for sentence-transformers models)
+// DEFAULT_EMBEDDING_DIM: Vector dimension, see EMBEDDING_DIM
 // HNSW_M: Number of bi-directional links per node (16-24 recommended, higher = more memory)
 // HNSW_EF_CONSTRUCTION: Size of candidate list during index building (higher = better quality, slower build)
-const DEFAULT_EMBEDDING_DIM: usize = 384;
+const DEFAULT_EMBEDDING_DIM: usize = EMBEDDING_DIM;
 const HNSW_M: i64 = 16;
 const HNSW_EF_CONSTRUCTION: i64 = 200;
 
-static GLOBAL_COZO_DB: Lazy<Arc<Mutex<DbInstance>>> = Lazy::new(|| {
-    let db = DbInstance::new("mem", "", Default::default())
-        .expect("Failed to create global CozoDB instance");
-    Arc::new(Mutex::new(db))
+type Database = Arc<Mutex<DbInstance>>;
+
+// Holds the database currently in use, so a reset can swap in a fresh one
+// without taking the lock of the old one, which a panic in the middle of a
+// query may have left held.
+type DatabaseSlot = Arc<Mutex<Database>>;
+
+static GLOBAL_COZO_DB: Lazy<Result<DatabaseSlot, String>> = Lazy::new(|| {
+    open_database()
+        .map(|db| Arc::new(Mutex::new(db)))
+        .map_err(|e| format!("Failed to create global CozoDB instance: {}", e))
 });
 
+/// New in-memory database with the schema in place.
+fn open_database() -> GraphResult<Database> {
+    let db = DbInstance::new("mem", "", Default::default())
+        .map_err(|e| GraphError::DatabaseError(format!("Failed to create CozoDB: {}", e)))?;
+    init_schema(&db)?;
+
+    Ok(Arc::new(Mutex::new(db)))
+}
+
+fn init_schema(db: &DbInstance) -> GraphResult<()> {
+    let schema_nodes = format!(
+        r#"
+        :create nodes {{
+            id: String =>
+            node_type: String,
+            vault_id: String,
+            content: String,
+            labels: String,
+            embedding: <F32; {}>?,
+            created_at: Int,
+            access_count: Int default 0,
+            last_accessed_at: Int default 0,
+            embedding_version: Int default 0,
+        }}
+        "#,
+        DEFAULT_EMBEDDING_DIM
+    );
+
+    db.run_script(&schema_nodes, Default::default(), ScriptMutability::Mutable)
+        .map_err(|e| {
+            GraphError::DatabaseError(format!("Failed to create nodes relation: {}", e))
+        })?;
+
+    let schema_edges = r#"
+        :create edges {
+            id: String =>
+            from_node: String,
+            to_node: String,
+            edge_type: String,
+            vault_id: String,
+            weight: Float,
+            created_at: Int,
+        }
+    "#;
+
+    db.run_script(schema_edges, Default::default(), ScriptMutability::Mutable)
+        .map_err(|e| {
+            GraphError::DatabaseError(format!("Failed to create edges relation: {}", e))
+        })?;
+
+    let hnsw_index = format!(
+        r#"
+        ::hnsw create nodes:embedding_idx {{
+            dim: {},
+            m: {},
+            dtype: F32,
+            fields: [embedding],
+            distance: Cosine,
+            ef_construction: {},
+        }}
+        "#,
+        DEFAULT_EMBEDDING_DIM, HNSW_M, HNSW_EF_CONSTRUCTION
+    );
+
+    db.run_script(&hnsw_index, Default::default(), ScriptMutability::Mutable)
+        .map_err(|e| GraphError::DatabaseError(format!("Failed to create HNSW index: {}", e)))?;
+
+    Ok(())
+}
+
 // Helper functions for data conversion
 fn labels_to_string(labels: &[String]) -> String {
     labels.join(",")
@@ -67,6 +149,9 @@ impl TryFrom<Vec<DataValue>> for GraphNode {
                 _ => None,
             },
             created_at: row[6].get_int().unwrap_or(0) as u64,
+            access_count: row.get(7).and_then(DataValue::get_int).unwrap_or(0) as u64,
+            last_accessed_at: row.get(8).and_then(DataValue::get_int).unwrap_or(0) as u64,
+            embedding_version: row.get(9).and_then(DataValue::get_int).unwrap_or(0) as u32,
         })
     }
 }
@@ -104,98 +189,96 @@ impl TryFrom<Vec<DataValue>> for GraphEdge {
 
 #[derive(Clone)]
 pub struct CozoGraphAdapter {
-    db: Arc<Mutex<DbInstance>>,
+    slot: DatabaseSlot,
+    clock: &'static dyn ClockPort,
 }
 
 impl CozoGraphAdapter {
     pub fn new() -> GraphResult<Self> {
-        static SCHEMA_INITIALIZED: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+        Ok(Self {
+            slot: GLOBAL_COZO_DB.clone().map_err(GraphError::DatabaseError)?,
+            clock: &Clock,
+        })
+    }
 
-        let adapter = Self {
-            db: GLOBAL_COZO_DB.clone(),
-        };
+    /// Adapter over a private in-memory database instead of the shared one,
+    /// for embedders that must not see each other's graphs.
+    pub fn isolated() -> GraphResult<Self> {
+        Ok(Self {
+            slot: Arc::new(Mutex::new(open_database()?)),
+            clock: &Clock,
+        })
+    }
 
-        let mut initialized = SCHEMA_INITIALIZED
-            .lock()
-            .map_err(|e| GraphError::DatabaseError(format!("Lock error: {}", e)))?;
+    /// Database currently behind the adapter.
+    fn db(&self) -> Database {
+        self.slot.lock().clone()
+    }
 
-        if !*initialized {
-            adapter.init_schema()?;
-            *initialized = true;
-        }
+    /// Same database, timestamping nodes and edges with `clock`.
+    pub fn with_clock(mut self, clock: &'static dyn ClockPort) -> Self {
+        self.clock = clock;
+        self
+    }
 
-        Ok(adapter)
+    fn get_timestamp(&self) -> u64 {
+        self.clock.now() as u64
     }
 
-    fn init_schema(&self) -> GraphResult<()> {
-        let db = self
-            .db
-            .lock()
-            .map_err(|e| GraphError::DatabaseError(format!("Lock error: {}", e)))?;
+    fn put_node(&self, node: &GraphNode) -> GraphResult<()> {
+        let db = self.db();
+        let db = db.lock();
 
-        let schema_nodes = format!(
-            r#"
-            :create nodes {{
-                id: String =>
-                node_type: String,
-                vault_id: String,
-                content: String,
-                labels: String,
-                embedding: <F32; {}>?,
-                created_at: Int,
-            }}
-            "#,
-            DEFAULT_EMBEDDING_DIM
+        let mut params = BTreeMap::new();
+        params.insert("id".to_string(), DataValue::Str(node.id.as_str().into()));
+        params.insert(
+            "node_type".to_string(),
+            DataValue::Str(node.node_type.as_str().into()),
+        );
+        params.insert(
+            "vault_id".to_string(),
+            DataValue::Str(node.vault_id.as_str().into()),
+        );
+        params.insert(
+            "content".to_string(),
+            DataValue::Str(node.content.as_str().into()),
+        );
+        params.insert(
+            "labels".to_string(),
+            DataValue::Str(labels_to_string(&node.labels).into()),
+        );
+        params.insert(
+            "embedding".to_string(),
+            vec_f32_to_datavalue(node.embedding.clone()),
+        );
+        params.insert(
+            "created_at".to_string(),
+            DataValue::from(node.created_at as i64),
+        );
+        params.insert(
+            "access_count".to_string(),
+            DataValue::from(node.access_count as i64),
+        );
+        params.insert(
+            "last_accessed_at".to_string(),
+            DataValue::from(node.last_accessed_at as i64),
+        );
+        params.insert(
+            "embedding_version".to_string(),
+            DataValue::from(node.embedding_version as i64),
         );
 
-        db.run_script(&schema_nodes, Default::default(), ScriptMutability::Mutable)
-            .map_err(|e| {
-                GraphError::DatabaseError(format!("Failed to create nodes relation: {}", e))
-            })?;
-
-        let schema_edges = r#"
-            :create edges {
-                id: String =>
-                from_node: String,
-                to_node: String,
-                edge_type: String,
-                vault_id: String,
-                weight: Float,
-                created_at: Int,
-            }
+        let query = r#"
+            ?[id, node_type, vault_id, content, labels, embedding, created_at, access_count, last_accessed_at, embedding_version] <- [[$id, $node_type, $vault_id, $content, $labels, $embedding, $created_at, $access_count, $last_accessed_at, $embedding_version]]
+            :put nodes { id => node_type, vault_id, content, labels, embedding, created_at, access_count, last_accessed_at, embedding_version }
         "#;
 
-        db.run_script(schema_edges, Default::default(), ScriptMutability::Mutable)
-            .map_err(|e| {
-                GraphError::DatabaseError(format!("Failed to create edges relation: {}", e))
-            })?;
-
-        let hnsw_index = format!(
-            r#"
-            ::hnsw create nodes:embedding_idx {{
-                dim: {},
-                m: {},
-                dtype: F32,
-                fields: [embedding],
-                distance: Cosine,
-                ef_construction: {},
-            }}
-            "#,
-            DEFAULT_EMBEDDING_DIM, HNSW_M, HNSW_EF_CONSTRUCTION
-        );
-
-        db.run_script(&hnsw_index, Default::default(), ScriptMutability::Mutable)
-            .map_err(|e| {
-                GraphError::DatabaseError(format!("Failed to create HNSW index: {}", e))
-            })?;
+        db.run_script(query, params, ScriptMutability::Mutable)
+            .map_err(|e| GraphError::DatabaseError(format!("Failed to create node: {}", e)))?;
 
         Ok(())
     }
 
-    fn get_timestamp() -> u64 {
-        js_sys::Date::now() as u64
-    }
-
     fn parse_simple_search_results(rows: Vec<Vec<DataValue>>) -> GraphResult<Vec<SearchResult>> {
         let mut results = Vec::new();
 
@@ -217,7 +300,10 @@ impl CozoGraphAdapter {
                     content: row[2].get_str().unwrap_or("").to_string(),
                     labels: string_to_labels(row[3].get_str().unwrap_or("")),
                     embedding: None,
-                    created_at: 0,
+                    created_at: row[5].get_int().unwrap_or(0) as u64,
+                    access_count: row[6].get_int().unwrap_or(0) as u64,
+                    last_accessed_at: row[7].get_int().unwrap_or(0) as u64,
+                    embedding_version: 0,
                 },
                 distance,
                 neighbors: Vec::new(),
@@ -240,18 +326,23 @@ impl CozoGraphAdapter {
                 .ok_or_else(|| GraphError::DatabaseError("Missing id".to_string()))?;
 
             let distance = row[4].get_float().unwrap_or(0.0) as f32;
+            let node_id = Id::from_string(node_id_str)
+                .map_err(|e| GraphError::DatabaseError(format!("Invalid id: {}", e)))?;
 
             let entry = node_map
                 .entry(node_id_str.to_string())
                 .or_insert_with(|| SearchResult {
                     node: GraphNode {
-                        id: Id::from_string(node_id_str).unwrap(),
+                        id: node_id,
                         node_type: row[1].get_str().unwrap_or("").to_string(),
                         vault_id: String::new(),
                         content: row[2].get_str().unwrap_or("").to_string(),
                         labels: string_to_labels(row[3].get_str().unwrap_or("")),
                         embedding: None,
-                        created_at: 0,
+                        created_at: row[10].get_int().unwrap_or(0) as u64,
+                        access_count: row[11].get_int().unwrap_or(0) as u64,
+                        last_accessed_at: row[12].get_int().unwrap_or(0) as u64,
+                        embedding_version: 0,
                     },
                     distance,
                     neighbors: Vec::new(),
@@ -260,13 +351,18 @@ impl CozoGraphAdapter {
             if let Some(neighbor_id_str) = row[5].get_str() {
                 let neighbor = NeighborNode {
                     node: GraphNode {
-                        id: Id::from_string(neighbor_id_str).unwrap(),
+                        id: Id::from_string(neighbor_id_str).map_err(|e| {
+                            GraphError::DatabaseError(format!("Invalid neighbor id: {}", e))
+                        })?,
                         node_type: row[6].get_str().unwrap_or("").to_string(),
                         vault_id: String::new(),
                         content: row[7].get_str().unwrap_or("").to_string(),
                         labels: Vec::new(),
                         embedding: None,
                         created_at: 0,
+                        access_count: 0,
+                        last_accessed_at: 0,
+                        embedding_version: 0,
                     },
                     edge_type: row[8].get_str().unwrap_or("").to_string(),
                     weight: row[9].get_float().unwrap_or(1.0) as f32,
@@ -277,20 +373,38 @@ impl CozoGraphAdapter {
         }
 
         let mut results: Vec<SearchResult> = node_map.into_values().collect();
-        results.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+        results.sort_by(|a, b| a.distance.total_cmp(&b.distance));
 
         Ok(results)
     }
 }
 
 impl Default for CozoGraphAdapter {
+    // The platform is built infallibly. Opening an in-memory database only
+    // fails when allocation does, which aborts the instance regardless.
+    #[allow(clippy::expect_used)]
     fn default() -> Self {
         Self::new().expect("Failed to create CozoGraphAdapter")
     }
 }
 
+impl GraphBackend for CozoGraphAdapter {
+    fn shared() -> GraphResult<Self> {
+        Self::new()
+    }
+
+    fn isolated() -> GraphResult<Self> {
+        Self::isolated()
+    }
+
+    fn with_clock(self, clock: &'static dyn ClockPort) -> Self {
+        self.with_clock(clock)
+    }
+}
+
 #[async_trait(?Send)]
 impl GraphPort for CozoGraphAdapter {
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, node_type = node_type))]
     async fn create_node(
         &self,
         vault_id: &str,
@@ -301,46 +415,32 @@ impl GraphPort for CozoGraphAdapter {
         node_id: Option<&Id>,
     ) -> GraphResult<Id> {
         let node_id = node_id.unwrap_or(&Id::new()).clone();
-        let now = Self::get_timestamp() as i64;
 
-        let db = self
-            .db
-            .lock()
-            .map_err(|e| GraphError::DatabaseError(format!("Lock error: {}", e)))?;
-
-        let mut params = BTreeMap::new();
-        params.insert("id".to_string(), DataValue::Str(node_id.as_str().into()));
-        params.insert("node_type".to_string(), DataValue::Str(node_type.into()));
-        params.insert("vault_id".to_string(), DataValue::Str(vault_id.into()));
-        params.insert("content".to_string(), DataValue::Str(content.into()));
-        params.insert(
-            "labels".to_string(),
-            DataValue::Str(labels_to_string(&labels).into()),
-        );
-        params.insert("embedding".to_string(), vec_f32_to_datavalue(embedding));
-        params.insert("created_at".to_string(), DataValue::from(now));
-
-        let query = r#"
-            ?[id, node_type, vault_id, content, labels, embedding, created_at] <- [[$id, $node_type, $vault_id, $content, $labels, $embedding, $created_at]]
-            :put nodes { id => node_type, vault_id, content, labels, embedding, created_at }
-        "#;
-
-        db.run_script(query, params, ScriptMutability::Mutable)
-            .map_err(|e| GraphError::DatabaseError(format!("Failed to create node: {}", e)))?;
+        self.put_node(&GraphNode {
+            id: node_id.clone(),
+            node_type: node_type.to_string(),
+            vault_id: vault_id.to_string(),
+            content,
+            labels,
+            embedding,
+            created_at: self.get_timestamp(),
+            access_count: 0,
+            last_accessed_at: 0,
+            embedding_version: embedding_version(),
+        })?;
 
         Ok(node_id)
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, node_type = node_type, limit = ?limit))]
     async fn list_nodes_by_type(
         &self,
         vault_id: &str,
         node_type: &str,
         limit: Option<usize>,
     ) -> GraphResult<Vec<GraphNode>> {
-        let db = self
-            .db
-            .lock()
-            .map_err(|e| GraphError::DatabaseError(format!("Lock error: {}", e)))?;
+        let db = self.db();
+        let db = db.lock();
 
         let mut params = BTreeMap::new();
         params.insert("vault_id".to_string(), DataValue::Str(vault_id.into()));
@@ -351,10 +451,13 @@ impl GraphPort for CozoGraphAdapter {
         );
 
         let query = r#"
-            ?[id, node_type, vault_id, content, labels, embedding, created_at] :=
+            ?[
+                id, node_type, vault_id, content, labels, embedding, created_at,
+                access_count, last_accessed_at, embedding_version
+            ] :=
                 *nodes{
-                    id, node_type, vault_id, content, 
-                    labels, embedding, created_at
+                    id, node_type, vault_id, content, labels, embedding, created_at,
+                    access_count, last_accessed_at, embedding_version
                 },
                 node_type == $node_type,
                 vault_id == $vault_id
@@ -368,6 +471,7 @@ impl GraphPort for CozoGraphAdapter {
         result.rows.into_iter().map(GraphNode::try_from).collect()
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, edge_type = edge_type))]
     async fn create_edge(
         &self,
         vault_id: &str,
@@ -378,12 +482,10 @@ impl GraphPort for CozoGraphAdapter {
         edge_id: Option<&Id>,
     ) -> GraphResult<Id> {
         let edge_id = edge_id.unwrap_or(&Id::new()).clone();
-        let now = Self::get_timestamp() as i64;
+        let now = self.get_timestamp() as i64;
 
-        let db = self
-            .db
-            .lock()
-            .map_err(|e| GraphError::DatabaseError(format!("Lock error: {}", e)))?;
+        let db = self.db();
+        let db = db.lock();
 
         let mut params = BTreeMap::new();
         params.insert("id".to_string(), DataValue::Str(edge_id.as_str().into()));
@@ -414,6 +516,422 @@ impl GraphPort for CozoGraphAdapter {
         Ok(edge_id)
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, node = %node_id.as_str()))]
+    async fn get_neighbors(
+        &self,
+        vault_id: &str,
+        node_id: &Id,
+        filter: &NeighborFilter,
+    ) -> GraphResult<Vec<NeighborNode>> {
+        let mut params = BTreeMap::new();
+        params.insert("vault_id".to_string(), DataValue::Str(vault_id.into()));
+        params.insert(
+            "node_id".to_string(),
+            DataValue::Str(node_id.as_str().into()),
+        );
+
+        // Every filter is a clause of the query, so CozoDB only reads the
+        // edges that pass it.
+        let mut clauses = vec![match filter.direction {
+            EdgeDirection::Outgoing => "from_node == $node_id, neighbor_id = to_node",
+            EdgeDirection::Incoming => "to_node == $node_id, neighbor_id = from_node",
+            EdgeDirection::Both => {
+                "(
+                    (from_node == $node_id, neighbor_id = to_node) or
+                    (to_node == $node_id, neighbor_id = from_node)
+                )"
+            }
+        }];
+        if !filter.edge_types.is_empty() {
+            clauses.push("is_in(edge_type, $edge_types)");
+            params.insert(
+                "edge_types".to_string(),
+                DataValue::List(
+                    filter
+                        .edge_types
+                        .iter()
+                        .map(|edge_type| DataValue::Str(edge_type.as_str().into()))
+                        .collect(),
+                ),
+            );
+        }
+        if let Some(min_weight) = filter.min_weight {
+            clauses.push("weight >= $min_weight");
+            params.insert("min_weight".to_string(), DataValue::from(min_weight as f64));
+        }
+        let limit = match filter.limit {
+            Some(limit) => {
+                params.insert("limit".to_string(), DataValue::from(limit as i64));
+                ":limit $limit"
+            }
+            None => "",
+        };
+
+        let query = format!(
+            r#"
+            ?[neighbor_id, neighbor_type, neighbor_content, neighbor_labels, edge_type, weight] :=
+                *edges{{from_node, to_node, edge_type, weight, vault_id: edge_vault}},
+                edge_vault == $vault_id,
+                {},
+                neighbor_id != $node_id,
+                *nodes{{
+                    id: neighbor_id,
+                    node_type: neighbor_type,
+                    content: neighbor_content,
+                    labels: neighbor_labels
+                }}
+
+            :order -weight, neighbor_id
+            {}
+        "#,
+            clauses.join(",\n                "),
+            limit
+        );
+
+        let db = self.db();
+        let db = db.lock();
+        let result = db
+            .run_script(&query, params, ScriptMutability::Immutable)
+            .map_err(|e| GraphError::DatabaseError(format!("Neighbor lookup failed: {}", e)))?;
+
+        result
+            .rows
+            .into_iter()
+            .map(|row| {
+                Ok(NeighborNode {
+                    node: GraphNode {
+                        id: Id::from_string(row[0].get_str().ok_or_else(|| {
+                            GraphError::DatabaseError("Missing neighbor id".to_string())
+                        })?)
+                        .map_err(|e| GraphError::DatabaseError(format!("Invalid id: {}", e)))?,
+                        node_type: row[1].get_str().unwrap_or("").to_string(),
+                        vault_id: vault_id.to_string(),
+                        content: row[2].get_str().unwrap_or("").to_string(),
+                        labels: string_to_labels(row[3].get_str().unwrap_or("")),
+                        embedding: None,
+                        created_at: 0,
+                        access_count: 0,
+                        last_accessed_at: 0,
+                        embedding_version: 0,
+                    },
+                    edge_type: row[4].get_str().unwrap_or("").to_string(),
+                    weight: row[5].get_float().unwrap_or(1.0) as f32,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id))]
+    async fn merge_nodes(&self, vault_id: &str, keep: &Id, remove: &Id) -> GraphResult<()> {
+        if keep == remove {
+            return Err(GraphError::Other(
+                "Cannot merge a node into itself".to_string(),
+            ));
+        }
+
+        let db = self.db();
+        let db = db.lock();
+
+        let mut params = BTreeMap::new();
+        params.insert("vault_id".to_string(), DataValue::Str(vault_id.into()));
+        params.insert("keep".to_string(), DataValue::Str(keep.as_str().into()));
+        params.insert("remove".to_string(), DataValue::Str(remove.as_str().into()));
+
+        let nodes_query = r#"
+            ?[id, labels] :=
+                *nodes{id, vault_id, labels},
+                vault_id == $vault_id,
+                is_in(id, [$keep, $remove])
+        "#;
+        let nodes = db
+            .run_script(nodes_query, params.clone(), ScriptMutability::Immutable)
+            .map_err(|e| GraphError::DatabaseError(format!("Failed to read nodes: {}", e)))?;
+        let labels_of = |id: &Id| {
+            nodes
+                .rows
+                .iter()
+                .find(|row| row[0].get_str() == Some(id.as_str().as_str()))
+                .map(|row| string_to_labels(row[1].get_str().unwrap_or("")))
+                .ok_or_else(|| GraphError::NodeNotFound(id.as_str()))
+        };
+        let mut labels = labels_of(keep)?;
+        for label in labels_of(remove)? {
+            if !labels.contains(&label) {
+                labels.push(label);
+            }
+        }
+
+        let edges_query = r#"
+            ?[id, from_node, to_node, edge_type, vault_id, weight, created_at] :=
+                *edges{id, from_node, to_node, edge_type, vault_id, weight, created_at},
+                vault_id == $vault_id,
+                (is_in(from_node, [$keep, $remove]) || is_in(to_node, [$keep, $remove]))
+        "#;
+        let edges = db
+            .run_script(edges_query, params.clone(), ScriptMutability::Immutable)
+            .map_err(|e| GraphError::DatabaseError(format!("Failed to read edges: {}", e)))?;
+        let (moved, kept): (Vec<GraphEdge>, Vec<GraphEdge>) = edges
+            .rows
+            .into_iter()
+            .map(GraphEdge::try_from)
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .partition(|edge| edge.from_node == *remove || edge.to_node == *remove);
+        let mut existing: HashSet<(Id, Id, String)> = kept
+            .into_iter()
+            .map(|edge| (edge.from_node, edge.to_node, edge.edge_type))
+            .collect();
+
+        let mut dropped = Vec::new();
+        let mut rewired = Vec::new();
+        for mut edge in moved {
+            if edge.from_node == *remove {
+                edge.from_node = keep.clone();
+            }
+            if edge.to_node == *remove {
+                edge.to_node = keep.clone();
+            }
+
+            let id = DataValue::Str(edge.id.as_str().into());
+            if edge.from_node != edge.to_node
+                && existing.insert((
+                    edge.from_node.clone(),
+                    edge.to_node.clone(),
+                    edge.edge_type.clone(),
+                ))
+            {
+                rewired.push(DataValue::List(vec![
+                    id,
+                    DataValue::Str(edge.from_node.as_str().into()),
+                    DataValue::Str(edge.to_node.as_str().into()),
+                    DataValue::Str(edge.edge_type.into()),
+                    DataValue::Str(edge.vault_id.into()),
+                    DataValue::from(edge.weight as f64),
+                    DataValue::from(edge.created_at as i64),
+                ]));
+            } else {
+                dropped.push(DataValue::List(vec![id]));
+            }
+        }
+
+        params.insert(
+            "labels".to_string(),
+            DataValue::Str(labels_to_string(&labels).into()),
+        );
+        params.insert("dropped".to_string(), DataValue::List(dropped));
+        params.insert("rewired".to_string(), DataValue::List(rewired));
+
+        // One transaction, so a failure leaves both nodes as they were.
+        let merge = r#"
+            {
+                ?[id] <- $dropped
+                :rm edges { id }
+            }
+            {
+                ?[id, from_node, to_node, edge_type, vault_id, weight, created_at] <- $rewired
+                :put edges { id => from_node, to_node, edge_type, vault_id, weight, created_at }
+            }
+            {
+                ?[
+                    id, node_type, vault_id, content, labels, embedding, created_at,
+                    access_count, last_accessed_at, embedding_version
+                ] :=
+                    *nodes{
+                        id, node_type, vault_id, content, embedding, created_at,
+                        access_count: kept_count, last_accessed_at: kept_access,
+                        embedding_version
+                    },
+                    id == $keep,
+                    *nodes{
+                        id: $remove,
+                        access_count: removed_count,
+                        last_accessed_at: removed_access
+                    },
+                    labels = $labels,
+                    access_count = kept_count + removed_count,
+                    last_accessed_at = max(kept_access, removed_access)
+                :put nodes {
+                    id => node_type, vault_id, content, labels, embedding, created_at,
+                    access_count, last_accessed_at, embedding_version
+                }
+            }
+            {
+                ?[id] <- [[$remove]]
+                :rm nodes { id }
+            }
+        "#;
+        db.run_script(merge, params, ScriptMutability::Mutable)
+            .map_err(|e| GraphError::DatabaseError(format!("Failed to merge nodes: {}", e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, nodes = node_ids.len()))]
+    async fn record_access(&self, vault_id: &str, node_ids: &[Id]) -> GraphResult<()> {
+        let mut params = BTreeMap::new();
+        params.insert("vault_id".to_string(), DataValue::Str(vault_id.into()));
+        params.insert(
+            "ids".to_string(),
+            DataValue::List(
+                node_ids
+                    .iter()
+                    .map(|id| DataValue::Str(id.as_str().into()))
+                    .collect(),
+            ),
+        );
+        params.insert(
+            "now".to_string(),
+            DataValue::from(self.get_timestamp() as i64),
+        );
+
+        let query = r#"
+            ?[
+                id, node_type, vault_id, content, labels, embedding, created_at,
+                access_count, last_accessed_at, embedding_version
+            ] :=
+                *nodes{
+                    id, node_type, vault_id, content, labels, embedding, created_at,
+                    access_count: count, embedding_version
+                },
+                is_in(id, $ids),
+                vault_id == $vault_id,
+                access_count = count + 1,
+                last_accessed_at = $now
+            :put nodes {
+                id => node_type, vault_id, content, labels, embedding, created_at,
+                access_count, last_accessed_at, embedding_version
+            }
+        "#;
+
+        let db = self.db();
+        let db = db.lock();
+        db.run_script(query, params, ScriptMutability::Mutable)
+            .map_err(|e| GraphError::DatabaseError(format!("Failed to record access: {}", e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, nodes = node_ids.len()))]
+    async fn delete_nodes(&self, vault_id: &str, node_ids: &[Id]) -> GraphResult<usize> {
+        let mut params = BTreeMap::new();
+        params.insert("vault_id".to_string(), DataValue::Str(vault_id.into()));
+        params.insert(
+            "ids".to_string(),
+            DataValue::List(
+                node_ids
+                    .iter()
+                    .map(|id| DataValue::Str(id.as_str().into()))
+                    .collect(),
+            ),
+        );
+
+        let db = self.db();
+        let db = db.lock();
+
+        let count_query = r#"
+            ?[count(id)] := *nodes{id, vault_id}, vault_id == $vault_id, is_in(id, $ids)
+        "#;
+        let count = db
+            .run_script(count_query, params.clone(), ScriptMutability::Immutable)
+            .map_err(|e| GraphError::DatabaseError(format!("Failed to count nodes: {}", e)))?
+            .rows
+            .first()
+            .and_then(|row| row.first())
+            .and_then(DataValue::get_int)
+            .unwrap_or(0) as usize;
+
+        // One transaction, so no edge is left pointing at a deleted node.
+        let delete = r#"
+            {
+                ?[id] :=
+                    *edges{id, from_node, to_node, vault_id},
+                    vault_id == $vault_id,
+                    (is_in(from_node, $ids) || is_in(to_node, $ids))
+                :rm edges { id }
+            }
+            {
+                ?[id] := *nodes{id, vault_id}, vault_id == $vault_id, is_in(id, $ids)
+                :rm nodes { id }
+            }
+        "#;
+        db.run_script(delete, params, ScriptMutability::Mutable)
+            .map_err(|e| GraphError::DatabaseError(format!("Failed to delete nodes: {}", e)))?;
+
+        Ok(count)
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, node = %node_id.as_str()))]
+    async fn set_embedding(
+        &self,
+        vault_id: &str,
+        node_id: &Id,
+        embedding: Vec<f32>,
+        version: u32,
+    ) -> GraphResult<()> {
+        if embedding.len() != DEFAULT_EMBEDDING_DIM {
+            return Err(GraphError::InvalidEmbedding(format!(
+                "Expected {} dimensions, got {}",
+                DEFAULT_EMBEDDING_DIM,
+                embedding.len()
+            )));
+        }
+
+        let mut params = BTreeMap::new();
+        params.insert("vault_id".to_string(), DataValue::Str(vault_id.into()));
+        params.insert("id".to_string(), DataValue::Str(node_id.as_str().into()));
+
+        let db = self.db();
+        let db = db.lock();
+
+        let exists_query = r#"
+            ?[id] := *nodes{id, vault_id}, id == $id, vault_id == $vault_id
+        "#;
+        let exists = db
+            .run_script(exists_query, params.clone(), ScriptMutability::Immutable)
+            .map_err(|e| GraphError::DatabaseError(format!("Failed to read node: {}", e)))?;
+        if exists.rows.is_empty() {
+            return Err(GraphError::NodeNotFound(node_id.as_str()));
+        }
+
+        params.insert(
+            "embedding".to_string(),
+            vec_f32_to_datavalue(Some(embedding)),
+        );
+        params.insert("version".to_string(), DataValue::from(version as i64));
+
+        let query = r#"
+            ?[
+                id, node_type, vault_id, content, labels, embedding, created_at,
+                access_count, last_accessed_at, embedding_version
+            ] :=
+                *nodes{
+                    id, node_type, vault_id, content, labels, created_at,
+                    access_count, last_accessed_at
+                },
+                id == $id,
+                embedding = $embedding,
+                embedding_version = $version
+            :put nodes {
+                id => node_type, vault_id, content, labels, embedding, created_at,
+                access_count, last_accessed_at, embedding_version
+            }
+        "#;
+        db.run_script(query, params, ScriptMutability::Mutable)
+            .map_err(|e| GraphError::DatabaseError(format!("Failed to set embedding: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn compact(&self) -> GraphResult<()> {
+        let db = self.db();
+        let db = db.lock();
+        db.run_script("::compact", Default::default(), ScriptMutability::Mutable)
+            .map_err(|e| GraphError::DatabaseError(format!("Failed to compact: {}", e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, max_results = max_results, include_neighbors = include_neighbors))]
     async fn vector_search_with_neighbors(
         &self,
         vault_id: &str,
@@ -430,10 +948,8 @@ impl GraphPort for CozoGraphAdapter {
             )));
         }
 
-        let db = self
-            .db
-            .lock()
-            .map_err(|e| GraphError::DatabaseError(format!("Lock error: {}", e)))?;
+        let db = self.db();
+        let db = db.lock();
 
         let mut params = BTreeMap::new();
         params.insert(
@@ -471,14 +987,18 @@ impl GraphPort for CozoGraphAdapter {
 
             ?[
                 id, node_type, content, labels, dist,
-                neighbor_id, neighbor_type, neighbor_content, edge_type, weight
+                neighbor_id, neighbor_type, neighbor_content, edge_type, weight,
+                created_at, access_count, last_accessed_at
             ] :=
                 similar_nodes[id, dist],
                 *nodes{
                     id,
                     node_type,
                     content,
-                    labels
+                    labels,
+                    created_at,
+                    access_count,
+                    last_accessed_at
                 },
                 *edges{from_node, to_node, edge_type, weight, vault_id: edge_vault},
                 edge_vault == $vault_id,
@@ -495,17 +1015,18 @@ impl GraphPort for CozoGraphAdapter {
 
             ?[
                 id, node_type, content, labels, dist,
-                null, null, null, null, null
+                null, null, null, null, null,
+                created_at, access_count, last_accessed_at
             ] :=
                 similar_nodes[id, dist],
-                *nodes{id, node_type, content, labels},
+                *nodes{id, node_type, content, labels, created_at, access_count, last_accessed_at},
                 not nodes_with_neighbors[id]
 
             :order dist
         "#
         } else {
             r#"
-            ?[id, node_type, content, labels, dist] :=
+            ?[id, node_type, content, labels, dist, created_at, access_count, last_accessed_at] :=
                 ~nodes:embedding_idx{
                     id, embedding |
                     query: $query_vec,
@@ -513,7 +1034,10 @@ impl GraphPort for CozoGraphAdapter {
                     ef: $search_quality,
                     bind_distance: dist
                 },
-                *nodes{id, vault_id, node_type, content, labels},
+                *nodes{
+                    id, vault_id, node_type, content, labels,
+                    created_at, access_count, last_accessed_at
+                },
                 vault_id == $vault_id
             
             :order dist
@@ -532,17 +1056,155 @@ impl GraphPort for CozoGraphAdapter {
         }
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, offset = offset, limit = limit))]
+    async fn vector_search_page(
+        &self,
+        vault_id: &str,
+        query: &SearchQuery,
+        offset: usize,
+        limit: usize,
+    ) -> GraphResult<Vec<SearchResult>> {
+        if query.query_embedding.len() != DEFAULT_EMBEDDING_DIM {
+            return Err(GraphError::InvalidEmbedding(format!(
+                "Expected {} dimensions, got {}",
+                DEFAULT_EMBEDDING_DIM,
+                query.query_embedding.len()
+            )));
+        }
+
+        let db = self.db();
+        let db = db.lock();
+
+        let mut params = BTreeMap::new();
+        params.insert(
+            "query_vec".to_string(),
+            vec_f32_to_datavalue(Some(query.query_embedding.clone())),
+        );
+        params.insert("vault_id".to_string(), DataValue::Str(vault_id.into()));
+        params.insert(
+            "max_results".to_string(),
+            DataValue::from(query.max_results as i64),
+        );
+        params.insert(
+            "search_quality".to_string(),
+            DataValue::from(query.search_quality as i64),
+        );
+        params.insert("offset".to_string(), DataValue::from(offset as i64));
+        params.insert("limit".to_string(), DataValue::from(limit as i64));
+
+        let page_query = r#"
+            ?[id, node_type, content, labels, dist, created_at, access_count, last_accessed_at] :=
+                ~nodes:embedding_idx{
+                    id, embedding |
+                    query: $query_vec,
+                    k: $max_results,
+                    ef: $search_quality,
+                    bind_distance: dist
+                },
+                *nodes{
+                    id, vault_id, node_type, content, labels,
+                    created_at, access_count, last_accessed_at
+                },
+                vault_id == $vault_id
+
+            :order dist
+            :offset $offset
+            :limit $limit
+        "#;
+
+        let result = db
+            .run_script(page_query, params, ScriptMutability::Immutable)
+            .map_err(|e| GraphError::DatabaseError(format!("Vector search failed: {}", e)))?;
+
+        let mut results = Self::parse_simple_search_results(result.rows)?;
+
+        if !query.include_neighbors || results.is_empty() {
+            return Ok(results);
+        }
+
+        // Neighbors are only fetched for the nodes of this page, which keeps
+        // hub nodes elsewhere in the ranking from inflating the page.
+        let mut params = BTreeMap::new();
+        params.insert("vault_id".to_string(), DataValue::Str(vault_id.into()));
+        params.insert(
+            "ids".to_string(),
+            DataValue::List(
+                results
+                    .iter()
+                    .map(|result| DataValue::Str(result.node.id.as_str().into()))
+                    .collect(),
+            ),
+        );
+
+        let neighbors_query = r#"
+            ?[id, neighbor_id, neighbor_type, neighbor_content, edge_type, weight] :=
+                *edges{from_node, to_node, edge_type, weight, vault_id: edge_vault},
+                edge_vault == $vault_id,
+                (
+                    (is_in(from_node, $ids), id = from_node, neighbor_id = to_node) or
+                    (is_in(to_node, $ids), id = to_node, neighbor_id = from_node)
+                ),
+                neighbor_id != id,
+                *nodes{
+                    id: neighbor_id,
+                    node_type: neighbor_type,
+                    content: neighbor_content
+                }
+        "#;
+
+        let neighbors = db
+            .run_script(neighbors_query, params, ScriptMutability::Immutable)
+            .map_err(|e| GraphError::DatabaseError(format!("Neighbor lookup failed: {}", e)))?;
+
+        for row in neighbors.rows {
+            let Some(result) = results
+                .iter_mut()
+                .find(|result| row[0].get_str() == Some(result.node.id.as_str().as_str()))
+            else {
+                continue;
+            };
+
+            let neighbor_id = Id::from_string(
+                row[1]
+                    .get_str()
+                    .ok_or_else(|| GraphError::DatabaseError("Missing neighbor id".to_string()))?,
+            )
+            .map_err(|e| GraphError::DatabaseError(format!("Invalid id: {}", e)))?;
+
+            result.neighbors.push(NeighborNode {
+                node: GraphNode {
+                    id: neighbor_id,
+                    node_type: row[2].get_str().unwrap_or("").to_string(),
+                    vault_id: String::new(),
+                    content: row[3].get_str().unwrap_or("").to_string(),
+                    labels: Vec::new(),
+                    embedding: None,
+                    created_at: 0,
+                    access_count: 0,
+                    last_accessed_at: 0,
+                    embedding_version: 0,
+                },
+                edge_type: row[4].get_str().unwrap_or("").to_string(),
+                weight: row[5].get_float().unwrap_or(1.0) as f32,
+            });
+        }
+
+        Ok(results)
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id))]
     async fn export_backup(&self, vault_id: &str) -> GraphResult<GraphBackup> {
-        let db = self
-            .db
-            .lock()
-            .map_err(|e| GraphError::DatabaseError(format!("Lock error: {}", e)))?;
+        let db = self.db();
+        let db = db.lock();
 
         let mut params = BTreeMap::new();
         params.insert("vault_id".to_string(), DataValue::Str(vault_id.into()));
 
         let nodes_query = r#"
-            ?[id, node_type, vault_id, content, labels, embedding, created_at] :=
+            ?[
+                id, node_type, vault_id, content, labels, embedding, created_at,
+                access_count, last_accessed_at, embedding_version
+            ] :=
                 *nodes{
                     id,
                     node_type,
@@ -550,10 +1212,13 @@ impl GraphPort for CozoGraphAdapter {
                     content,
                     labels,
                     embedding,
-                    created_at
+                    created_at,
+                    access_count,
+                    last_accessed_at,
+                    embedding_version
                 },
                 vault_id == $vault_id
-            :order created_at
+            :order created_at, id
         "#;
 
         let nodes_result = db
@@ -578,7 +1243,7 @@ impl GraphPort for CozoGraphAdapter {
                     created_at
                 },
                 vault_id == $vault_id
-            :order created_at
+            :order created_at, id
         "#;
 
         let edges_result = db
@@ -591,25 +1256,29 @@ impl GraphPort for CozoGraphAdapter {
             .map(GraphEdge::try_from)
             .collect::<Result<Vec<_>, _>>()?;
 
+        // Stamped with the newest entry rather than the clock, so that an
+        // unchanged graph always exports the same backup.
+        let created_at = nodes
+            .iter()
+            .map(|node| node.created_at)
+            .chain(edges.iter().map(|edge| edge.created_at))
+            .max()
+            .unwrap_or(0);
+
         Ok(GraphBackup {
             version: 1,
             nodes,
             edges,
-            created_at: Self::get_timestamp(),
+            created_at,
         })
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(nodes = backup.nodes.len(), edges = backup.edges.len()))]
     async fn import_backup(&self, backup: &GraphBackup) -> GraphResult<()> {
+        // Nodes keep their timestamps and access counts, which recall
+        // ranks by.
         for node in &backup.nodes {
-            self.create_node(
-                &node.vault_id,
-                &node.node_type,
-                node.content.clone(),
-                node.labels.clone(),
-                node.embedding.clone(),
-                Some(&node.id),
-            )
-            .await?;
+            self.put_node(node)?;
         }
 
         for edge in &backup.edges {
@@ -626,6 +1295,37 @@ impl GraphPort for CozoGraphAdapter {
 
         Ok(())
     }
+
+    async fn stats(&self) -> GraphResult<GraphStats> {
+        let db = self.db();
+        let db = db.lock();
+
+        let count = |query: &str| -> GraphResult<usize> {
+            let result = db
+                .run_script(query, Default::default(), ScriptMutability::Immutable)
+                .map_err(|e| GraphError::DatabaseError(format!("Failed to count: {}", e)))?;
+
+            Ok(result
+                .rows
+                .first()
+                .and_then(|row| row.first())
+                .and_then(DataValue::get_int)
+                .unwrap_or(0) as usize)
+        };
+
+        Ok(GraphStats {
+            nodes: count("?[count(id)] := *nodes{id}")?,
+            edges: count("?[count(id)] := *edges{id}")?,
+        })
+    }
+
+    async fn reset(&self) -> GraphResult<()> {
+        let db = open_database()?;
+        *self.slot.lock() = db;
+
+        tracing::warn!("Graph database reset");
+        Ok(())
+    }
 }
 
 #[cfg(all(test, target_arch = "wasm32"))]
@@ -640,6 +1340,12 @@ mod tests {
         let _adapter = CozoGraphAdapter::new().unwrap();
     }
 
+    #[wasm_bindgen_test]
+    async fn test_conforms_to_graph_port() {
+        let adapter = CozoGraphAdapter::isolated().unwrap();
+        crate::adapters::shared::graph_conformance::check_graph_port(&adapter).await;
+    }
+
     #[wasm_bindgen_test]
     async fn test_create_node_basic() {
         let adapter = CozoGraphAdapter::new().unwrap();
@@ -918,6 +1624,148 @@ mod tests {
         assert_eq!(results[0].neighbors.len(), 2);
     }
 
+    #[wasm_bindgen_test]
+    async fn test_get_neighbors_filters_in_the_query() {
+        let adapter = CozoGraphAdapter::isolated().unwrap();
+
+        let mut ids = Vec::new();
+        for content in ["hub", "cited", "citing", "related"] {
+            ids.push(
+                adapter
+                    .create_node(
+                        "test_vault_get_neighbors",
+                        "document",
+                        content.to_string(),
+                        vec![],
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap(),
+            );
+        }
+        for (from, to, edge_type, weight) in [
+            (0, 1, "cites", 0.9),
+            (2, 0, "cites", 0.4),
+            (0, 3, "related", 0.2),
+        ] {
+            adapter
+                .create_edge(
+                    "test_vault_get_neighbors",
+                    &ids[from],
+                    &ids[to],
+                    edge_type,
+                    Some(weight),
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+
+        let contents = |neighbors: Vec<NeighborNode>| {
+            neighbors
+                .into_iter()
+                .map(|neighbor| neighbor.node.content)
+                .collect::<Vec<_>>()
+        };
+        let get = |filter: NeighborFilter| {
+            let adapter = adapter.clone();
+            let hub = ids[0].clone();
+            async move {
+                adapter
+                    .get_neighbors("test_vault_get_neighbors", &hub, &filter)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        assert_eq!(
+            contents(get(NeighborFilter::default()).await),
+            ["cited", "citing", "related"]
+        );
+        assert_eq!(
+            contents(
+                get(NeighborFilter {
+                    direction: EdgeDirection::Incoming,
+                    ..NeighborFilter::default()
+                })
+                .await
+            ),
+            ["citing"]
+        );
+        assert_eq!(
+            contents(
+                get(NeighborFilter {
+                    direction: EdgeDirection::Outgoing,
+                    edge_types: vec!["cites".to_string()],
+                    min_weight: Some(0.5),
+                    limit: Some(5),
+                })
+                .await
+            ),
+            ["cited"]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_merge_nodes_rewires_edges() {
+        let adapter = CozoGraphAdapter::isolated().unwrap();
+        let vault = "test_vault_merge";
+
+        let mut ids = Vec::new();
+        for (content, label) in [
+            ("Paris", "city"),
+            ("Paris, France", "capital"),
+            ("France", ""),
+        ] {
+            ids.push(
+                adapter
+                    .create_node(
+                        vault,
+                        "entity",
+                        content.to_string(),
+                        string_to_labels(label),
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap(),
+            );
+        }
+        let (keep, remove, france) = (&ids[0], &ids[1], &ids[2]);
+        adapter
+            .create_edge(vault, remove, france, "capital_of", None, None)
+            .await
+            .unwrap();
+        adapter
+            .create_edge(vault, keep, remove, "same_as", None, None)
+            .await
+            .unwrap();
+
+        adapter.merge_nodes(vault, keep, remove).await.unwrap();
+
+        let nodes = adapter
+            .list_nodes_by_type(vault, "entity", None)
+            .await
+            .unwrap();
+        assert_eq!(nodes.len(), 2);
+        let kept = nodes.iter().find(|node| node.id == *keep).unwrap();
+        assert_eq!(kept.labels, ["city", "capital"]);
+
+        let neighbors = adapter
+            .get_neighbors(vault, keep, &NeighborFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(neighbors.len(), 1);
+        assert_eq!(neighbors[0].node.id, *france);
+        assert_eq!(neighbors[0].edge_type, "capital_of");
+
+        assert!(matches!(
+            adapter.merge_nodes(vault, keep, remove).await,
+            Err(GraphError::NodeNotFound(_))
+        ));
+    }
+
     #[wasm_bindgen_test]
     async fn test_export_import_backup() {
         let adapter1 = CozoGraphAdapter::new().unwrap();
@@ -1135,4 +1983,65 @@ mod tests {
         assert_eq!(documents[0].node_type, "document");
         assert_eq!(memories[0].node_type, "memory");
     }
+
+    #[wasm_bindgen_test]
+    async fn test_reset_recovers_from_held_lock() {
+        let adapter = CozoGraphAdapter::isolated().unwrap();
+
+        adapter
+            .create_node(
+                "test_vault_reset",
+                "document",
+                "Document".to_string(),
+                vec![],
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        // As left by a panic in the middle of a query.
+        let stuck = adapter.db();
+        std::mem::forget(stuck.lock());
+
+        adapter.reset().await.unwrap();
+
+        let nodes = adapter
+            .list_nodes_by_type("test_vault_reset", "document", None)
+            .await
+            .unwrap();
+        assert!(nodes.is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_stats_counts_nodes_and_edges() {
+        let adapter = CozoGraphAdapter::isolated().unwrap();
+        assert_eq!(adapter.stats().await.unwrap(), GraphStats::default());
+
+        let mut ids = Vec::new();
+        for content in ["first", "second"] {
+            ids.push(
+                adapter
+                    .create_node(
+                        "test_vault_stats",
+                        "document",
+                        content.to_string(),
+                        vec![],
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap(),
+            );
+        }
+        adapter
+            .create_edge("test_vault_stats", &ids[0], &ids[1], "links", None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            adapter.stats().await.unwrap(),
+            GraphStats { nodes: 2, edges: 1 }
+        );
+    }
 }
@@ -1,4 +1,9 @@
+use super::ErrorReporter;
+use crate::domain::error_messages;
+use crate::domain::error_report::ErrorReport;
+use crate::domain::retry::RetryError;
 use crate::domain::vault::error::VaultError;
+use crate::ports::ErrorReporterPort;
 use wasm_bindgen::JsValue;
 
 impl From<JsValue> for VaultError {
@@ -12,6 +17,24 @@ impl From<JsValue> for VaultError {
 
 impl From<VaultError> for JsValue {
     fn from(error: VaultError) -> Self {
-        JsValue::from_str(&error.to_string())
+        report_vault_error(&error);
+        JsValue::from_str(&error_messages::localize(error.code(), error.to_string()))
+    }
+}
+
+impl From<RetryError<JsValue>> for JsValue {
+    fn from(error: RetryError<JsValue>) -> Self {
+        match error {
+            RetryError::Cancelled => VaultError::Cancelled.into(),
+            RetryError::Exhausted { last_error, .. } => last_error,
+        }
+    }
+}
+
+/// Reports an error on its way out of a facade. Cancellation is what the
+/// caller asked for, not a failure.
+pub(crate) fn report_vault_error(error: &VaultError) {
+    if !matches!(error, VaultError::Cancelled) {
+        ErrorReporter::new().report(&ErrorReport::from(error));
     }
 }
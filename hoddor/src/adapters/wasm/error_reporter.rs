@@ -0,0 +1,78 @@
+use crate::domain::error_report::ErrorReport;
+use crate::ports::ErrorReporterPort;
+use std::cell::{Cell, RefCell};
+use std::sync::Once;
+use wasm_bindgen::JsValue;
+
+thread_local! {
+    static CALLBACK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+    static REPORTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Passes reports to the callback registered by the application, as plain
+/// `{ kind, code, message, location }` objects.
+#[derive(Clone, Copy)]
+pub struct ErrorReporter;
+
+impl Default for ErrorReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ErrorReporter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Replaces the callback; `None` turns reporting off.
+    pub fn set_callback(callback: Option<js_sys::Function>) {
+        CALLBACK.with(|current| *current.borrow_mut() = callback);
+    }
+}
+
+impl ErrorReporterPort for ErrorReporter {
+    fn report(&self, report: &ErrorReport) {
+        let Some(callback) = CALLBACK.with(|callback| callback.borrow().clone()) else {
+            return;
+        };
+
+        // A callback calling back into hoddor and failing would otherwise
+        // report its own failure forever.
+        if REPORTING.with(|reporting| reporting.replace(true)) {
+            return;
+        }
+
+        if let Ok(value) = serde_wasm_bindgen::to_value(report) {
+            let _ = callback.call1(&JsValue::NULL, &value);
+        }
+
+        REPORTING.with(|reporting| reporting.set(false));
+    }
+}
+
+/// Installs a panic hook that prints the panic to the console, when the
+/// `console_error_panic_hook` feature is on, and reports it. Only the first
+/// call has an effect.
+pub fn install_panic_hook() {
+    static INSTALLED: Once = Once::new();
+
+    INSTALLED.call_once(|| {
+        std::panic::set_hook(Box::new(|info| {
+            #[cfg(feature = "console_error_panic_hook")]
+            console_error_panic_hook::hook(info);
+
+            let payload = info.payload();
+            let message = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("Box<dyn Any>");
+            let location = info
+                .location()
+                .map(|location| format!("{}:{}", location.file(), location.line()));
+
+            ErrorReporter::new().report(&ErrorReport::panic(message, location));
+        }));
+    });
+}
@@ -2,19 +2,28 @@ use crate::domain::vault::error::VaultError;
 use crate::global::get_global_scope;
 use crate::ports::{LockGuard, LockPort};
 use async_trait::async_trait;
+use futures::channel::oneshot;
+use std::cell::RefCell;
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{Lock, LockManager, LockOptions, WorkerGlobalScope};
+use web_sys::{LockManager, LockOptions, WorkerGlobalScope};
 
+/// Holds a Web Lock until dropped. The lock callback returns a promise that
+/// only settles when the guard goes away, which is what keeps the lock held.
 pub struct WebLockGuard {
-    _lock: Lock,
-    _callback: Closure<dyn Fn()>,
+    release: Rc<RefCell<Option<js_sys::Function>>>,
+    _callback: Closure<dyn FnMut(JsValue) -> js_sys::Promise>,
 }
 
 impl LockGuard for WebLockGuard {}
 
 impl Drop for WebLockGuard {
-    fn drop(&mut self) {}
+    fn drop(&mut self) {
+        if let Some(release) = self.release.borrow_mut().take() {
+            let _ = release.call0(&JsValue::NULL);
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -36,53 +45,64 @@ impl Locks {
             Err(VaultError::io_error("Could not access navigator"))
         }
     }
+
+    async fn request(&self, name: &str, mode: &str) -> Result<Box<dyn LockGuard>, VaultError> {
+        let lock_manager = self.get_lock_manager().await?;
+        let lock_name = format!("vault_{}_lock", name);
+
+        let options = LockOptions::new();
+        js_sys::Reflect::set(
+            &options,
+            &JsValue::from_str("mode"),
+            &JsValue::from_str(mode),
+        )?;
+
+        let (granted_tx, granted_rx) = oneshot::channel::<()>();
+        let mut granted_tx = Some(granted_tx);
+        let release = Rc::new(RefCell::new(None));
+
+        let callback = {
+            let release = release.clone();
+            Closure::wrap(Box::new(move |_lock: JsValue| {
+                let held = js_sys::Promise::new(&mut |resolve, _reject| {
+                    *release.borrow_mut() = Some(resolve);
+                });
+                if let Some(granted_tx) = granted_tx.take() {
+                    let _ = granted_tx.send(());
+                }
+                held
+            }) as Box<dyn FnMut(JsValue) -> js_sys::Promise>)
+        };
+
+        // The returned promise settles on release, so it is left running.
+        let request = lock_manager.request_with_options_and_callback(
+            &lock_name,
+            &options,
+            callback.as_ref().unchecked_ref(),
+        );
+        wasm_bindgen_futures::spawn_local(async move {
+            let _ = JsFuture::from(request).await;
+        });
+
+        granted_rx
+            .await
+            .map_err(|_| VaultError::io_error("Failed to acquire lock"))?;
+
+        Ok(Box::new(WebLockGuard {
+            release,
+            _callback: callback,
+        }))
+    }
 }
 
 #[async_trait(?Send)]
 impl LockPort for Locks {
     async fn acquire(&self, name: &str) -> Result<Box<dyn LockGuard>, VaultError> {
-        let lock_manager = self.get_lock_manager().await?;
-        let lock_name = format!("vault_{}_lock", name);
-        let mut retries = 10;
-        let mut delay = 50;
-
-        while retries > 0 {
-            let options = LockOptions::new();
-            js_sys::Reflect::set(
-                &options,
-                &JsValue::from_str("mode"),
-                &JsValue::from_str("exclusive"),
-            )?;
-            options.set_if_available(true);
-
-            let callback = Closure::wrap(Box::new(|| {}) as Box<dyn Fn()>);
-            let promise = lock_manager.request_with_options_and_callback(
-                &lock_name,
-                &options,
-                callback.as_ref().unchecked_ref(),
-            );
-
-            match JsFuture::from(promise).await {
-                Ok(lock) => {
-                    let web_lock = lock.unchecked_into::<Lock>();
-                    let guard = WebLockGuard {
-                        _lock: web_lock,
-                        _callback: callback,
-                    };
-                    return Ok(Box::new(guard));
-                }
-                Err(_) => {
-                    retries -= 1;
-                    if retries > 0 {
-                        delay = ((delay as f64 * 1.5) as u32).min(1000);
-                        let jitter = (js_sys::Math::random() * 50.0) as u32;
-                        gloo_timers::future::TimeoutFuture::new(delay + jitter).await;
-                    }
-                }
-            }
-        }
+        self.request(name, "exclusive").await
+    }
 
-        Err(VaultError::io_error("Failed to acquire lock"))
+    async fn acquire_shared(&self, name: &str) -> Result<Box<dyn LockGuard>, VaultError> {
+        self.request(name, "shared").await
     }
 }
 
@@ -170,6 +190,38 @@ mod tests {
         assert!(guard_b.is_ok(), "Should acquire lock for vault_b");
     }
 
+    #[wasm_bindgen_test]
+    async fn test_shared_locks_coexist() {
+        let locks = Locks::new();
+
+        let reader_a = locks.acquire_shared("shared_scope").await;
+        let reader_b = locks.acquire_shared("shared_scope").await;
+
+        assert!(reader_a.is_ok() && reader_b.is_ok());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_exclusive_lock_waits_for_release() {
+        let locks = Locks::new();
+        let guard = locks.acquire("exclusive_scope").await.unwrap();
+
+        let acquired = Rc::new(RefCell::new(false));
+        {
+            let acquired = acquired.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let _second = Locks::new().acquire("exclusive_scope").await.unwrap();
+                *acquired.borrow_mut() = true;
+            });
+        }
+
+        gloo_timers::future::TimeoutFuture::new(50).await;
+        assert!(!*acquired.borrow(), "Second holder must wait for release");
+
+        drop(guard);
+        gloo_timers::future::TimeoutFuture::new(50).await;
+        assert!(*acquired.borrow(), "Second holder should run after release");
+    }
+
     #[wasm_bindgen_test]
     async fn test_get_lock_manager() {
         let locks = Locks::new();
@@ -0,0 +1,314 @@
+use crate::ports::TransportPort;
+use async_trait::async_trait;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::lock::Mutex;
+use futures::StreamExt;
+use js_sys::Reflect;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    MessageEvent, RtcDataChannel, RtcDataChannelEvent, RtcDataChannelState, RtcDataChannelType,
+    RtcIceGatheringState, RtcPeerConnection, RtcSdpType, RtcSessionDescriptionInit,
+};
+
+const CHANNEL_LABEL: &str = "hoddor-pairing";
+
+/// How long gathering ICE candidates for a pairing code may take.
+const ICE_GATHERING_TIMEOUT_MS: u32 = 15_000;
+
+struct Session {
+    connection: RtcPeerConnection,
+    channel: Rc<RefCell<Option<RtcDataChannel>>>,
+    sender: UnboundedSender<Vec<u8>>,
+    receiver: Rc<Mutex<UnboundedReceiver<Vec<u8>>>>,
+}
+
+thread_local! {
+    static SESSIONS: RefCell<HashMap<String, Rc<Session>>> = RefCell::new(HashMap::new());
+}
+
+/// WebRTC transport paired by exchanging offers and answers out of band
+/// (copy/paste, QR code). Offers embed every ICE candidate so no signaling
+/// server is involved.
+#[derive(Clone, Copy)]
+pub struct ManualSdpTransport;
+
+impl Default for ManualSdpTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManualSdpTransport {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn new_session(&self, session: &str) -> Result<Rc<Session>, JsValue> {
+        self.close(session);
+
+        let connection = RtcPeerConnection::new()?;
+        let (sender, receiver) = mpsc::unbounded();
+        let channel = Rc::new(RefCell::new(None));
+
+        let ondatachannel = {
+            let channel = channel.clone();
+            let sender = sender.clone();
+            Closure::wrap(Box::new(move |ev: RtcDataChannelEvent| {
+                attach_channel(&ev.channel(), &channel, sender.clone());
+            }) as Box<dyn FnMut(RtcDataChannelEvent)>)
+        };
+        connection.set_ondatachannel(Some(ondatachannel.as_ref().unchecked_ref()));
+        ondatachannel.forget();
+
+        let session_state = Rc::new(Session {
+            connection,
+            channel,
+            sender,
+            receiver: Rc::new(Mutex::new(receiver)),
+        });
+
+        SESSIONS.with(|sessions| {
+            sessions
+                .borrow_mut()
+                .insert(session.to_string(), session_state.clone())
+        });
+
+        Ok(session_state)
+    }
+
+    fn session(&self, session: &str) -> Result<Rc<Session>, Box<dyn Error>> {
+        SESSIONS
+            .with(|sessions| sessions.borrow().get(session).cloned())
+            .ok_or_else(|| format!("No pairing session named '{session}'").into())
+    }
+}
+
+fn attach_channel(
+    channel: &RtcDataChannel,
+    slot: &Rc<RefCell<Option<RtcDataChannel>>>,
+    sender: UnboundedSender<Vec<u8>>,
+) {
+    channel.set_binary_type(RtcDataChannelType::Arraybuffer);
+
+    let onmessage = Closure::wrap(Box::new(move |ev: MessageEvent| {
+        if let Ok(data) = ev.data().dyn_into::<js_sys::ArrayBuffer>() {
+            let _ = sender.unbounded_send(js_sys::Uint8Array::new(&data).to_vec());
+        }
+    }) as Box<dyn FnMut(MessageEvent)>);
+    channel.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+    onmessage.forget();
+
+    *slot.borrow_mut() = Some(channel.clone());
+}
+
+async fn wait_for_ice_gathering(connection: &RtcPeerConnection) -> Result<(), JsValue> {
+    if connection.ice_gathering_state() == RtcIceGatheringState::Complete {
+        return Ok(());
+    }
+
+    let (complete, gathered) = futures::channel::oneshot::channel::<()>();
+    let complete = RefCell::new(Some(complete));
+    let watched = connection.clone();
+    let callback = Closure::wrap(Box::new(move |_: web_sys::Event| {
+        if watched.ice_gathering_state() == RtcIceGatheringState::Complete {
+            if let Some(complete) = complete.borrow_mut().take() {
+                let _ = complete.send(());
+            }
+        }
+    }) as Box<dyn FnMut(web_sys::Event)>);
+    connection.set_onicegatheringstatechange(Some(callback.as_ref().unchecked_ref()));
+
+    // Gathering stalls on networks where a STUN server never answers, which
+    // would otherwise leave the pairing code pending forever.
+    let deadline = gloo_timers::future::TimeoutFuture::new(ICE_GATHERING_TIMEOUT_MS);
+    let result = match futures::future::select(gathered, deadline).await {
+        futures::future::Either::Left((Ok(()), _)) => Ok(()),
+        futures::future::Either::Left((Err(_), _)) => {
+            Err(JsValue::from_str("ICE gathering was interrupted"))
+        }
+        futures::future::Either::Right(_) => Err(JsValue::from_str(&format!(
+            "ICE gathering did not complete within {} seconds",
+            ICE_GATHERING_TIMEOUT_MS / 1000
+        ))),
+    };
+
+    connection.set_onicegatheringstatechange(None);
+    drop(callback);
+    result
+}
+
+async fn set_local_description(
+    connection: &RtcPeerConnection,
+    description: &JsValue,
+    sdp_type: RtcSdpType,
+) -> Result<String, JsValue> {
+    let sdp = Reflect::get(description, &JsValue::from_str("sdp"))?
+        .as_string()
+        .ok_or_else(|| JsValue::from_str("Session description has no SDP"))?;
+
+    let init = RtcSessionDescriptionInit::new(sdp_type);
+    init.set_sdp(&sdp);
+    JsFuture::from(connection.set_local_description(&init)).await?;
+
+    wait_for_ice_gathering(connection).await?;
+
+    connection
+        .local_description()
+        .map(|description| URL_SAFE_NO_PAD.encode(description.sdp()))
+        .ok_or_else(|| JsValue::from_str("Missing local description"))
+}
+
+async fn set_remote_description(
+    connection: &RtcPeerConnection,
+    encoded: &str,
+    sdp_type: RtcSdpType,
+) -> Result<(), Box<dyn Error>> {
+    let sdp = URL_SAFE_NO_PAD
+        .decode(encoded.trim())
+        .map_err(|e| format!("Invalid pairing code: {e}"))?;
+    let sdp = String::from_utf8(sdp).map_err(|e| format!("Invalid pairing code: {e}"))?;
+
+    let init = RtcSessionDescriptionInit::new(sdp_type);
+    init.set_sdp(&sdp);
+    JsFuture::from(connection.set_remote_description(&init))
+        .await
+        .map_err(js_error)?;
+
+    Ok(())
+}
+
+fn js_error(error: JsValue) -> Box<dyn Error> {
+    format!("{error:?}").into()
+}
+
+#[async_trait(?Send)]
+impl TransportPort for ManualSdpTransport {
+    async fn create_offer(&self, session: &str) -> Result<String, Box<dyn Error>> {
+        let state = self.new_session(session).map_err(js_error)?;
+
+        let channel = state.connection.create_data_channel(CHANNEL_LABEL);
+        attach_channel(&channel, &state.channel, state.sender.clone());
+
+        let offer = JsFuture::from(state.connection.create_offer())
+            .await
+            .map_err(js_error)?;
+
+        set_local_description(&state.connection, &offer, RtcSdpType::Offer)
+            .await
+            .map_err(js_error)
+    }
+
+    async fn accept_offer(&self, session: &str, offer: &str) -> Result<String, Box<dyn Error>> {
+        let state = self.new_session(session).map_err(js_error)?;
+
+        set_remote_description(&state.connection, offer, RtcSdpType::Offer).await?;
+
+        let answer = JsFuture::from(state.connection.create_answer())
+            .await
+            .map_err(js_error)?;
+
+        set_local_description(&state.connection, &answer, RtcSdpType::Answer)
+            .await
+            .map_err(js_error)
+    }
+
+    async fn accept_answer(&self, session: &str, answer: &str) -> Result<(), Box<dyn Error>> {
+        let state = self.session(session)?;
+
+        set_remote_description(&state.connection, answer, RtcSdpType::Answer).await
+    }
+
+    async fn send(&self, session: &str, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let state = self.session(session)?;
+
+        let channel = state
+            .channel
+            .borrow()
+            .clone()
+            .filter(|channel| channel.ready_state() == RtcDataChannelState::Open)
+            .ok_or("Pairing channel is not open")?;
+
+        channel.send_with_u8_array(data).map_err(js_error)
+    }
+
+    async fn receive(&self, session: &str) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let receiver = self.session(session)?.receiver.clone();
+        let mut receiver = receiver.lock().await;
+
+        Ok(receiver.next().await)
+    }
+
+    fn is_connected(&self, session: &str) -> bool {
+        self.session(session)
+            .ok()
+            .and_then(|state| state.channel.borrow().clone())
+            .map(|channel| channel.ready_state() == RtcDataChannelState::Open)
+            .unwrap_or(false)
+    }
+
+    fn close(&self, session: &str) {
+        let removed = SESSIONS.with(|sessions| sessions.borrow_mut().remove(session));
+
+        if let Some(state) = removed {
+            if let Some(channel) = state.channel.borrow().as_ref() {
+                channel.close();
+            }
+            state.connection.close();
+            state.sender.close_channel();
+        }
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    async fn test_manual_pairing_roundtrip() {
+        let transport = ManualSdpTransport::new();
+
+        let offer = transport.create_offer("desk-a").await.unwrap();
+        let answer = transport.accept_offer("desk-b", &offer).await.unwrap();
+        transport.accept_answer("desk-a", &answer).await.unwrap();
+
+        while !transport.is_connected("desk-a") || !transport.is_connected("desk-b") {
+            gloo_timers::future::TimeoutFuture::new(10).await;
+        }
+
+        transport.send("desk-a", b"hello").await.unwrap();
+        let received = transport.receive("desk-b").await.unwrap();
+        assert_eq!(received.as_deref(), Some(&b"hello"[..]));
+
+        transport.close("desk-a");
+        transport.close("desk-b");
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_accept_answer_without_session_fails() {
+        let transport = ManualSdpTransport::new();
+
+        assert!(transport.accept_answer("missing", "").await.is_err());
+        assert!(!transport.is_connected("missing"));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_invalid_pairing_code_is_rejected() {
+        let transport = ManualSdpTransport::new();
+
+        assert!(transport
+            .accept_offer("desk", "not a pairing code!")
+            .await
+            .is_err());
+        transport.close("desk");
+    }
+}
@@ -1,23 +1,44 @@
-mod error_conversions;
+pub(crate) mod error_conversions;
 
 pub mod clock;
 pub mod console_logger;
+pub mod error_reporter;
 pub mod locks;
-pub mod notifier;
+#[cfg(feature = "sync")]
+pub mod manual_sdp_transport;
 pub mod opfs_storage;
 pub mod persistence;
+pub mod tab_channel;
+pub mod telemetry;
 pub mod webauthn_prf;
+pub mod worker_kdf;
 
-#[cfg(feature = "graph")]
+#[cfg(feature = "graph-cozo")]
 pub mod cozo_graph;
+#[cfg(feature = "notifications")]
+pub mod notifier;
+#[cfg(all(feature = "graph-remote", not(feature = "graph-cozo")))]
+pub mod remote_graph;
+#[cfg(not(feature = "notifications"))]
+pub mod silent_notifier;
 
 pub use clock::Clock;
 pub use console_logger::ConsoleLogger;
+pub use error_reporter::{install_panic_hook, ErrorReporter};
 pub use locks::Locks;
-pub use notifier::Notifier;
+#[cfg(feature = "sync")]
+pub use manual_sdp_transport::ManualSdpTransport;
 pub use opfs_storage::OpfsStorage;
 pub use persistence::Persistence;
+pub use telemetry::{init_console_tracing, set_console_level};
 pub use webauthn_prf::WebAuthnPrf;
+pub use worker_kdf::WorkerKdf;
 
-#[cfg(feature = "graph")]
+#[cfg(feature = "graph-cozo")]
 pub use cozo_graph::CozoGraphAdapter;
+#[cfg(feature = "notifications")]
+pub use notifier::Notifier;
+#[cfg(all(feature = "graph-remote", not(feature = "graph-cozo")))]
+pub use remote_graph::RemoteGraphAdapter;
+#[cfg(not(feature = "notifications"))]
+pub use silent_notifier::SilentNotifier as Notifier;
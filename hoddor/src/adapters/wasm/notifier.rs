@@ -12,19 +12,11 @@ impl Notifier {
     }
 }
 
-impl NotifierPort for Notifier {
-    fn notify_vault_update(&self, _vault_name: &str, vault_data: &[u8]) -> Result<(), String> {
+impl Notifier {
+    fn post<T: serde::Serialize>(&self, msg: &notifications::Message<T>) -> Result<(), String> {
         let global_scope = get_global_scope().map_err(|e| format!("{:?}", e))?;
 
-        let vault: crate::domain::vault::Vault = serde_json::from_slice(vault_data)
-            .map_err(|e| format!("Failed to deserialize vault: {}", e))?;
-
-        let msg = notifications::Message {
-            event: notifications::EventType::VaultUpdate,
-            data: vault,
-        };
-
-        let js_value = serde_wasm_bindgen::to_value(&msg)
+        let js_value = serde_wasm_bindgen::to_value(msg)
             .map_err(|e| format!("Failed to serialize: {:?}", e))?;
 
         if let Ok(worker_scope) = global_scope
@@ -46,22 +38,64 @@ impl NotifierPort for Notifier {
     }
 }
 
+impl NotifierPort for Notifier {
+    fn notify_vault_update(&self, vault_name: &str, vault_data: &[u8]) -> Result<(), String> {
+        super::tab_channel::broadcast_vault_change(vault_name)?;
+
+        let vault: crate::domain::vault::Vault = serde_json::from_slice(vault_data)
+            .map_err(|e| format!("Failed to deserialize vault: {}", e))?;
+
+        self.post(&notifications::Message {
+            event: notifications::EventType::VaultUpdate,
+            data: vault,
+        })
+    }
+
+    fn notify_event(
+        &self,
+        vault_name: &str,
+        event: notifications::EventType,
+        detail: &str,
+    ) -> Result<(), String> {
+        self.post(&notifications::Message {
+            event,
+            data: notifications::VaultEvent {
+                vault_name: vault_name.to_string(),
+                detail: detail.to_string(),
+            },
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::vault::{IdentitySalts, Vault, VaultMetadata};
-    use std::collections::HashMap;
+    use crate::domain::vault::{Compression, IdentitySalts, Vault, VaultMetadata};
+    use std::collections::BTreeMap;
     use wasm_bindgen_test::*;
 
     wasm_bindgen_test_configure!(run_in_browser);
 
     fn create_test_vault() -> Vault {
         Vault {
-            metadata: VaultMetadata { peer_id: None },
+            metadata: VaultMetadata {
+                peer_id: None,
+                sync_policy: Default::default(),
+            },
             identity_salts: IdentitySalts::new(),
-            username_pk: HashMap::new(),
-            namespaces: HashMap::new(),
+            username_pk: BTreeMap::new(),
+            namespaces: BTreeMap::new(),
             sync_enabled: false,
+            observer: false,
+            frozen: false,
+            retention: BTreeMap::new(),
+            residency: BTreeMap::new(),
+            chunks: BTreeMap::new(),
+            max_namespace_bytes: None,
+            manifest: None,
+            compression: Compression::None,
+            journal_sequence: 0,
+            stored_digests: BTreeMap::new(),
         }
     }
 
@@ -115,7 +149,7 @@ mod tests {
         let mut vault = create_test_vault();
         vault.metadata.peer_id = Some("test_peer_123".to_string());
 
-        let mut username_pk = HashMap::new();
+        let mut username_pk = BTreeMap::new();
         username_pk.insert("user1".to_string(), "pk1".to_string());
         username_pk.insert("user2".to_string(), "pk2".to_string());
         vault.username_pk = username_pk;
@@ -132,6 +166,19 @@ mod tests {
         );
     }
 
+    #[wasm_bindgen_test]
+    fn test_notify_event() {
+        let notifier = Notifier::new();
+
+        let result = notifier.notify_event(
+            "test_vault",
+            notifications::EventType::BackupCompleted,
+            "exported 42 bytes",
+        );
+
+        assert!(result.is_ok());
+    }
+
     #[wasm_bindgen_test]
     fn test_notify_with_empty_vault_name() {
         let notifier = Notifier::new();
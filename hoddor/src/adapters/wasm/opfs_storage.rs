@@ -2,6 +2,8 @@ use crate::domain::vault::error::VaultError;
 use crate::global::get_storage_manager;
 use crate::ports::StoragePort;
 use async_trait::async_trait;
+use futures::future::join_all;
+use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{FileSystemDirectoryHandle, FileSystemFileHandle, FileSystemGetFileOptions};
@@ -41,21 +43,10 @@ impl OpfsStorage {
         Ok(current)
     }
 
-    fn split_path(path: &str) -> (&str, &str) {
-        if let Some(pos) = path.rfind('/') {
-            (&path[..pos], &path[pos + 1..])
-        } else {
-            (".", path)
-        }
-    }
-}
-
-#[async_trait(?Send)]
-impl StoragePort for OpfsStorage {
-    async fn read_file(&self, path: &str) -> Result<String, VaultError> {
-        let (dir_path, filename) = Self::split_path(path);
-        let dir_handle = self.navigate_to_dir(dir_path).await?;
-
+    async fn read_from_dir(
+        dir_handle: &FileSystemDirectoryHandle,
+        filename: &str,
+    ) -> Result<String, VaultError> {
         let file_handle = JsFuture::from(dir_handle.get_file_handle(filename))
             .await
             .map_err(|_| VaultError::io_error("Failed to get file handle"))?
@@ -76,6 +67,60 @@ impl StoragePort for OpfsStorage {
         Ok(text)
     }
 
+    fn split_path(path: &str) -> (&str, &str) {
+        if let Some(pos) = path.rfind('/') {
+            (&path[..pos], &path[pos + 1..])
+        } else {
+            (".", path)
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl StoragePort for OpfsStorage {
+    async fn read_file(&self, path: &str) -> Result<String, VaultError> {
+        let (dir_path, filename) = Self::split_path(path);
+        let dir_handle = self.navigate_to_dir(dir_path).await?;
+
+        Self::read_from_dir(&dir_handle, filename).await
+    }
+
+    // Resolves each distinct directory once, then reads every file
+    // concurrently, so a batch costs about one round trip instead of one
+    // per file and path segment.
+    async fn read_files(&self, paths: &[String]) -> Vec<Result<String, VaultError>> {
+        let split: Vec<(&str, &str)> = paths.iter().map(|path| Self::split_path(path)).collect();
+
+        let mut dir_paths: Vec<&str> = split.iter().map(|(dir_path, _)| *dir_path).collect();
+        dir_paths.sort_unstable();
+        dir_paths.dedup();
+
+        let handles: HashMap<&str, Result<FileSystemDirectoryHandle, VaultError>> = dir_paths
+            .iter()
+            .copied()
+            .zip(
+                join_all(
+                    dir_paths
+                        .iter()
+                        .map(|dir_path| self.navigate_to_dir(dir_path)),
+                )
+                .await,
+            )
+            .collect();
+
+        join_all(split.iter().map(|(dir_path, filename)| {
+            let handle = handles.get(dir_path);
+            async move {
+                match handle {
+                    Some(Ok(dir_handle)) => Self::read_from_dir(dir_handle, filename).await,
+                    Some(Err(e)) => Err(e.clone()),
+                    None => Err(VaultError::io_error("Failed to navigate to directory")),
+                }
+            }
+        }))
+        .await
+    }
+
     async fn write_file(&self, path: &str, content: &str) -> Result<(), VaultError> {
         let (dir_path, filename) = Self::split_path(path);
         let dir_handle = self.navigate_to_dir(dir_path).await?;
@@ -338,6 +383,40 @@ mod tests {
         assert!(!storage.directory_exists(test_dir).await.unwrap());
     }
 
+    #[wasm_bindgen_test]
+    async fn test_read_files_keeps_order_and_errors() {
+        let storage = OpfsStorage::new();
+        let test_dir = "test_read_files_opfs";
+
+        storage.create_directory(test_dir).await.unwrap();
+        storage
+            .create_directory("test_read_files_opfs/nested")
+            .await
+            .unwrap();
+        storage
+            .write_file("test_read_files_opfs/a.txt", "a")
+            .await
+            .unwrap();
+        storage
+            .write_file("test_read_files_opfs/nested/b.txt", "b")
+            .await
+            .unwrap();
+
+        let paths = [
+            "test_read_files_opfs/nested/b.txt",
+            "test_read_files_opfs/missing.txt",
+            "test_read_files_opfs/a.txt",
+        ]
+        .map(String::from);
+        let contents = storage.read_files(&paths).await;
+
+        assert_eq!(contents[0].as_deref().ok(), Some("b"));
+        assert!(contents[1].is_err());
+        assert_eq!(contents[2].as_deref().ok(), Some("a"));
+
+        storage.delete_directory(test_dir).await.unwrap();
+    }
+
     #[wasm_bindgen_test]
     async fn test_list_entries() {
         let storage = OpfsStorage::new();
@@ -0,0 +1,326 @@
+use crate::adapters::shared::cached_graph::GraphBackend;
+use crate::domain::graph::rpc::{GraphEnvelope, GraphRequest, GraphResponse, SHARED_DATABASE};
+use crate::domain::graph::{
+    GraphBackup, GraphError, GraphNode, GraphResult, GraphStats, Id, NeighborFilter, NeighborNode,
+    SearchQuery, SearchResult,
+};
+use crate::ports::clock::ClockPort;
+use crate::ports::graph::GraphPort;
+use async_trait::async_trait;
+use js_sys::{Function, Promise};
+use serde::Deserialize;
+use std::cell::{Cell, RefCell};
+use std::sync::Arc;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+
+thread_local! {
+    static ENGINE: RefCell<Option<Function>> = const { RefCell::new(None) };
+    static NEXT_DATABASE: Cell<u32> = const { Cell::new(SHARED_DATABASE + 1) };
+}
+
+/// Registers the function requests are sent to: it takes the JSON request
+/// and resolves to the JSON response, typically by importing the graph
+/// engine package on first use and calling its `graph_rpc`.
+pub fn set_engine(handler: Option<Function>) {
+    ENGINE.with(|engine| *engine.borrow_mut() = handler);
+}
+
+/// Database of the engine an adapter talks to. Isolated ones are released
+/// once the last adapter using them is dropped.
+struct Database {
+    id: u32,
+}
+
+impl Drop for Database {
+    fn drop(&mut self) {
+        if self.id == SHARED_DATABASE {
+            return;
+        }
+
+        let id = self.id;
+        wasm_bindgen_futures::spawn_local(async move {
+            let _ = call(id, GraphRequest::Release).await;
+        });
+    }
+}
+
+/// Graph backend of the `graph-remote` feature: a shim forwarding every call
+/// to a graph engine built as a separate wasm package, which the application
+/// loads only once the graph is used. Nodes and edges are timestamped by the
+/// engine's clock.
+#[derive(Clone)]
+pub struct RemoteGraphAdapter {
+    database: Arc<Database>,
+}
+
+impl RemoteGraphAdapter {
+    pub fn new() -> GraphResult<Self> {
+        Ok(Self::default())
+    }
+
+    /// Adapter over a graph of its own in the engine, for embedders that
+    /// must not see each other's graphs.
+    pub fn isolated() -> GraphResult<Self> {
+        let id = NEXT_DATABASE.with(|next| {
+            let id = next.get();
+            next.set(id.wrapping_add(1).max(SHARED_DATABASE + 1));
+            id
+        });
+
+        Ok(Self {
+            database: Arc::new(Database { id }),
+        })
+    }
+
+    /// Kept for parity with the local backends; the engine stamps entries
+    /// with its own clock.
+    pub fn with_clock(self, _clock: &'static dyn ClockPort) -> Self {
+        self
+    }
+
+    async fn request<T: for<'de> Deserialize<'de>>(&self, request: GraphRequest) -> GraphResult<T> {
+        call(self.database.id, request).await?.into_result()
+    }
+}
+
+impl Default for RemoteGraphAdapter {
+    fn default() -> Self {
+        Self {
+            database: Arc::new(Database {
+                id: SHARED_DATABASE,
+            }),
+        }
+    }
+}
+
+impl GraphBackend for RemoteGraphAdapter {
+    fn shared() -> GraphResult<Self> {
+        Self::new()
+    }
+
+    fn isolated() -> GraphResult<Self> {
+        Self::isolated()
+    }
+
+    fn with_clock(self, clock: &'static dyn ClockPort) -> Self {
+        self.with_clock(clock)
+    }
+}
+
+async fn call(database: u32, request: GraphRequest) -> GraphResult<GraphResponse> {
+    let envelope = serde_json::to_string(&GraphEnvelope { database, request })
+        .map_err(|e| GraphError::SerializationError(e.to_string()))?;
+
+    let engine = ENGINE
+        .with(|engine| engine.borrow().clone())
+        .ok_or_else(|| {
+            GraphError::DatabaseError(
+                "No graph engine registered, call set_graph_engine first".to_string(),
+            )
+        })?;
+
+    let reply = engine
+        .call1(&JsValue::NULL, &JsValue::from_str(&envelope))
+        .map_err(engine_error)?;
+    let reply = JsFuture::from(Promise::resolve(&reply))
+        .await
+        .map_err(engine_error)?;
+
+    let reply = reply.as_string().ok_or_else(|| {
+        GraphError::DatabaseError("Graph engine replied with a non-string value".to_string())
+    })?;
+
+    serde_json::from_str(&reply).map_err(|e| GraphError::SerializationError(e.to_string()))
+}
+
+fn engine_error(error: JsValue) -> GraphError {
+    GraphError::DatabaseError(format!("Graph engine failed: {error:?}"))
+}
+
+#[async_trait(?Send)]
+impl GraphPort for RemoteGraphAdapter {
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, node_type = node_type))]
+    async fn create_node(
+        &self,
+        vault_id: &str,
+        node_type: &str,
+        content: String,
+        labels: Vec<String>,
+        embedding: Option<Vec<f32>>,
+        node_id: Option<&Id>,
+    ) -> GraphResult<Id> {
+        self.request(GraphRequest::CreateNode {
+            vault_id: vault_id.to_string(),
+            node_type: node_type.to_string(),
+            content,
+            labels,
+            embedding,
+            node_id: node_id.cloned(),
+        })
+        .await
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, node_type = node_type, limit = ?limit))]
+    async fn list_nodes_by_type(
+        &self,
+        vault_id: &str,
+        node_type: &str,
+        limit: Option<usize>,
+    ) -> GraphResult<Vec<GraphNode>> {
+        self.request(GraphRequest::ListNodesByType {
+            vault_id: vault_id.to_string(),
+            node_type: node_type.to_string(),
+            limit,
+        })
+        .await
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, edge_type = edge_type))]
+    async fn create_edge(
+        &self,
+        vault_id: &str,
+        from_node: &Id,
+        to_node: &Id,
+        edge_type: &str,
+        weight: Option<f32>,
+        edge_id: Option<&Id>,
+    ) -> GraphResult<Id> {
+        self.request(GraphRequest::CreateEdge {
+            vault_id: vault_id.to_string(),
+            from_node: from_node.clone(),
+            to_node: to_node.clone(),
+            edge_type: edge_type.to_string(),
+            weight,
+            edge_id: edge_id.cloned(),
+        })
+        .await
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, node = %node_id.as_str()))]
+    async fn get_neighbors(
+        &self,
+        vault_id: &str,
+        node_id: &Id,
+        filter: &NeighborFilter,
+    ) -> GraphResult<Vec<NeighborNode>> {
+        self.request(GraphRequest::GetNeighbors {
+            vault_id: vault_id.to_string(),
+            node_id: node_id.clone(),
+            filter: filter.clone(),
+        })
+        .await
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id))]
+    async fn merge_nodes(&self, vault_id: &str, keep: &Id, remove: &Id) -> GraphResult<()> {
+        self.request(GraphRequest::MergeNodes {
+            vault_id: vault_id.to_string(),
+            keep: keep.clone(),
+            remove: remove.clone(),
+        })
+        .await
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, nodes = node_ids.len()))]
+    async fn record_access(&self, vault_id: &str, node_ids: &[Id]) -> GraphResult<()> {
+        self.request(GraphRequest::RecordAccess {
+            vault_id: vault_id.to_string(),
+            node_ids: node_ids.to_vec(),
+        })
+        .await
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, nodes = node_ids.len()))]
+    async fn delete_nodes(&self, vault_id: &str, node_ids: &[Id]) -> GraphResult<usize> {
+        self.request(GraphRequest::DeleteNodes {
+            vault_id: vault_id.to_string(),
+            node_ids: node_ids.to_vec(),
+        })
+        .await
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, node = %node_id.as_str()))]
+    async fn set_embedding(
+        &self,
+        vault_id: &str,
+        node_id: &Id,
+        embedding: Vec<f32>,
+        version: u32,
+    ) -> GraphResult<()> {
+        self.request(GraphRequest::SetEmbedding {
+            vault_id: vault_id.to_string(),
+            node_id: node_id.clone(),
+            embedding,
+            version,
+        })
+        .await
+    }
+
+    async fn compact(&self) -> GraphResult<()> {
+        self.request(GraphRequest::Compact).await
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, max_results = max_results, include_neighbors = include_neighbors))]
+    async fn vector_search_with_neighbors(
+        &self,
+        vault_id: &str,
+        query_embedding: Vec<f32>,
+        max_results: usize,
+        search_quality: usize,
+        include_neighbors: bool,
+    ) -> GraphResult<Vec<SearchResult>> {
+        self.request(GraphRequest::VectorSearch {
+            vault_id: vault_id.to_string(),
+            query_embedding,
+            max_results,
+            search_quality,
+            include_neighbors,
+        })
+        .await
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id, offset = offset, limit = limit))]
+    async fn vector_search_page(
+        &self,
+        vault_id: &str,
+        query: &SearchQuery,
+        offset: usize,
+        limit: usize,
+    ) -> GraphResult<Vec<SearchResult>> {
+        self.request(GraphRequest::VectorSearchPage {
+            vault_id: vault_id.to_string(),
+            query_embedding: query.query_embedding.clone(),
+            max_results: query.max_results,
+            search_quality: query.search_quality,
+            include_neighbors: query.include_neighbors,
+            offset,
+            limit,
+        })
+        .await
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(vault = vault_id))]
+    async fn export_backup(&self, vault_id: &str) -> GraphResult<GraphBackup> {
+        self.request(GraphRequest::ExportBackup {
+            vault_id: vault_id.to_string(),
+        })
+        .await
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(nodes = backup.nodes.len(), edges = backup.edges.len()))]
+    async fn import_backup(&self, backup: &GraphBackup) -> GraphResult<()> {
+        self.request(GraphRequest::ImportBackup {
+            backup: backup.clone(),
+        })
+        .await
+    }
+
+    async fn stats(&self) -> GraphResult<GraphStats> {
+        self.request(GraphRequest::Stats).await
+    }
+
+    async fn reset(&self) -> GraphResult<()> {
+        self.request(GraphRequest::Reset).await
+    }
+}
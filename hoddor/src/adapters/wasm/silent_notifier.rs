@@ -0,0 +1,30 @@
+use crate::notifications::EventType;
+use crate::ports::NotifierPort;
+
+/// Notifier of builds without the `notifications` feature: vault events are
+/// dropped instead of being posted to the page and workers, which also
+/// spares serializing the whole vault on every write. Other tabs are still
+/// told about writes, so they do not work on stale state.
+#[derive(Clone, Copy, Default)]
+pub struct SilentNotifier;
+
+impl SilentNotifier {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl NotifierPort for SilentNotifier {
+    fn notify_vault_update(&self, vault_name: &str, _vault_data: &[u8]) -> Result<(), String> {
+        super::tab_channel::broadcast_vault_change(vault_name)
+    }
+
+    fn notify_event(
+        &self,
+        _vault_name: &str,
+        _event: EventType,
+        _detail: &str,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+}
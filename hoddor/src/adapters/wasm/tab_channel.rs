@@ -0,0 +1,76 @@
+//! Vault changes shared between the tabs and workers of an origin.
+//!
+//! OPFS and Web Locks are shared by every tab of an origin, but what a tab
+//! keeps in memory is not: once another tab has written, open
+//! [`VaultHandle`](crate::domain::vault::VaultHandle)s are stale and
+//! namespace watches may select other values. Every write is announced on a
+//! `BroadcastChannel`, which delivers it to the other instances only, and
+//! each of them reacts as to an external change instead of polling.
+
+use crate::domain::vault::external_changes;
+use crate::notifications::EventType;
+use js_sys::{Object, Reflect};
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+use web_sys::{BroadcastChannel, MessageEvent};
+
+const CHANNEL_NAME: &str = "hoddor-vault-changes";
+
+thread_local! {
+    static CHANNEL: RefCell<Option<BroadcastChannel>> = const { RefCell::new(None) };
+}
+
+/// Opens the channel and starts listening to the other tabs, once.
+pub fn listen() -> Result<(), JsValue> {
+    CHANNEL.with(|slot| {
+        if slot.borrow().is_some() {
+            return Ok(());
+        }
+
+        let channel = BroadcastChannel::new(CHANNEL_NAME)?;
+        let onmessage = Closure::wrap(Box::new(|event: MessageEvent| {
+            let vault_name = Reflect::get(&event.data(), &"vaultName".into())
+                .ok()
+                .and_then(|name| name.as_string());
+            if let Some(vault_name) = vault_name {
+                on_remote_change(vault_name);
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+        channel.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+        onmessage.forget();
+
+        *slot.borrow_mut() = Some(channel);
+        Ok(())
+    })
+}
+
+/// Tells the other tabs `vault_name` was written. Does nothing until
+/// [`listen`] opened the channel.
+pub fn broadcast_vault_change(vault_name: &str) -> Result<(), String> {
+    CHANNEL.with(|slot| {
+        let Some(channel) = slot.borrow().as_ref().cloned() else {
+            return Ok(());
+        };
+
+        let message = Object::new();
+        Reflect::set(&message, &"vaultName".into(), &vault_name.into())
+            .map_err(|e| format!("{:?}", e))?;
+        channel
+            .post_message(&message)
+            .map_err(|e| format!("{:?}", e))
+    })
+}
+
+fn on_remote_change(vault_name: String) {
+    tracing::debug!(vault = %vault_name, "Vault changed in another tab");
+
+    external_changes::record_external_change(&vault_name);
+    let platform = crate::Platform::new();
+    let _ = platform
+        .notifier()
+        .notify_event(&vault_name, EventType::ExternalChange, "tab");
+
+    wasm_bindgen_futures::spawn_local(async move {
+        crate::domain::vault::watch::notify_watches_from_storage(&platform, &vault_name).await;
+    });
+}
@@ -0,0 +1,31 @@
+use once_cell::sync::OnceCell;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::{reload, Registry};
+use tracing_wasm::{WASMLayer, WASMLayerConfig};
+
+static LEVEL: OnceCell<reload::Handle<LevelFilter, Registry>> = OnceCell::new();
+
+/// Installs a subscriber printing events to the browser console and marking
+/// spans in the performance timeline. Returns false when another subscriber
+/// was installed first.
+pub fn init_console_tracing(level: LevelFilter) -> bool {
+    let (filter, handle) = reload::Layer::new(level);
+    let subscriber = Registry::default()
+        .with(filter)
+        .with(WASMLayer::new(WASMLayerConfig::default()));
+
+    if tracing::subscriber::set_global_default(subscriber).is_err() {
+        return false;
+    }
+
+    LEVEL.set(handle).is_ok()
+}
+
+/// Changes the level of the console subscriber. Returns false when it is not
+/// the installed subscriber.
+pub fn set_console_level(level: LevelFilter) -> bool {
+    LEVEL
+        .get()
+        .is_some_and(|handle| handle.modify(|filter| *filter = level).is_ok())
+}
@@ -1,6 +1,5 @@
+use crate::adapters::shared::seed_from_prf;
 use crate::ports::PrfPort;
-use hkdf::Hkdf;
-use sha2::{Digest, Sha256};
 use std::error::Error;
 
 #[derive(Clone, Copy, Debug)]
@@ -20,21 +19,7 @@ impl Default for WebAuthnPrf {
 
 impl PrfPort for WebAuthnPrf {
     fn derive_from_prf(&self, first: &[u8], second: &[u8]) -> Result<[u8; 32], Box<dyn Error>> {
-        if first.is_empty() {
-            return Err("Missing first PRF value".into());
-        }
-        if second.is_empty() {
-            return Err("Missing second PRF value".into());
-        }
-
-        let mut prf = first.to_vec();
-        prf.extend(second);
-
-        let mixed_prf = Sha256::digest(&prf);
-        let (prk, _) =
-            Hkdf::<Sha256>::extract(Some("hoddor/vault".as_bytes()), mixed_prf.as_slice());
-
-        Ok(prk.into())
+        seed_from_prf(first, second)
     }
 
     fn is_available(&self) -> bool {
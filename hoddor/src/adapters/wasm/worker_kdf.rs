@@ -0,0 +1,235 @@
+//! Argon2 off the main thread.
+//!
+//! Deriving an identity from a passphrase takes hundreds of milliseconds of
+//! Argon2, during which a page calling from its main thread freezes. Once
+//! [`enable`] has been given the URL of the crate's JavaScript module,
+//! derivations run in a dedicated module worker the crate spawns from a
+//! blob: the worker loads its own instance of the module and answers each
+//! passphrase and salt with the seed. Callers already in a worker, and every
+//! derivation while the worker is disabled or failed to load, run Argon2 in
+//! place. Errors of Argon2 itself in the worker are returned as they are.
+//!
+//! The worker flags its global scope before loading the module, so that
+//! [`crate::start_app`] skips the start-up work meant for the page.
+
+use crate::adapters::shared::Argon2Kdf;
+use crate::ports::KeyDerivationPort;
+use async_trait::async_trait;
+use futures_channel::oneshot;
+use js_sys::{Array, Object, Reflect, Uint8Array};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::error::Error;
+use wasm_bindgen::prelude::*;
+use web_sys::{Blob, BlobPropertyBag, MessageEvent, Url, Worker, WorkerOptions, WorkerType};
+
+const WORKER_FLAG: &str = "__hoddorKdfWorker";
+
+const WORKER_SCRIPT: &str = r#"
+self.__hoddorKdfWorker = true;
+let module;
+self.onmessage = async (event) => {
+  const { id, moduleUrl, passphrase, salt } = event.data;
+  let loaded;
+  try {
+    module ??= import(moduleUrl).then(async (m) => { await m.default(); return m; });
+    loaded = await module;
+  } catch (error) {
+    module = undefined;
+    self.postMessage({ id, loadError: String(error) });
+    return;
+  }
+  try {
+    const seed = await loaded.derive_passphrase_seed(passphrase, salt);
+    self.postMessage({ id, seed });
+  } catch (error) {
+    self.postMessage({ id, error: String(error) });
+  }
+};
+"#;
+
+enum WorkerError {
+    /// The worker could not be spawned, reached or load the module.
+    Unavailable(String),
+    /// Argon2 failed in the worker, as it would have in place.
+    Derivation(String),
+}
+
+type Reply = oneshot::Sender<Result<Vec<u8>, WorkerError>>;
+
+thread_local! {
+    static MODULE_URL: RefCell<Option<String>> = const { RefCell::new(None) };
+    static WORKER: RefCell<Option<Worker>> = const { RefCell::new(None) };
+    static PENDING: RefCell<HashMap<u32, Reply>> = RefCell::new(HashMap::new());
+    static NEXT_ID: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Runs later derivations in a worker loading the module at `module_url`,
+/// the URL of the `hoddor.js` the page imported.
+pub fn enable(module_url: String) {
+    disable();
+    MODULE_URL.with(|url| *url.borrow_mut() = Some(module_url));
+}
+
+/// Whether this is the Argon2 worker spawned by [`enable`].
+pub fn in_kdf_worker() -> bool {
+    Reflect::get(&js_sys::global(), &WORKER_FLAG.into()).is_ok_and(|flag| flag.is_truthy())
+}
+
+/// Stops the worker; later derivations run in place.
+pub fn disable() {
+    MODULE_URL.with(|url| url.borrow_mut().take());
+    if let Some(worker) = WORKER.with(|worker| worker.borrow_mut().take()) {
+        worker.terminate();
+    }
+    fail_pending("Worker stopped");
+}
+
+/// Key derivation of the wasm platform: Argon2 in the worker when enabled,
+/// in place otherwise.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WorkerKdf;
+
+impl WorkerKdf {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait(?Send)]
+impl KeyDerivationPort for WorkerKdf {
+    async fn derive_from_passphrase(
+        &self,
+        passphrase: &str,
+        salt: &[u8],
+    ) -> Result<[u8; 32], Box<dyn Error>> {
+        let on_main_thread = web_sys::window().is_some();
+        let module_url = MODULE_URL.with(|url| url.borrow().clone());
+
+        if let (true, Some(module_url)) = (on_main_thread, module_url) {
+            match derive_in_worker(&module_url, passphrase, salt).await {
+                Ok(seed) => return Ok(seed),
+                Err(WorkerError::Derivation(e)) => return Err(e.into()),
+                Err(WorkerError::Unavailable(e)) => {
+                    tracing::warn!(error = %e, "Argon2 worker unavailable, deriving in place")
+                }
+            }
+        }
+
+        Argon2Kdf::new()
+            .derive_from_passphrase(passphrase, salt)
+            .await
+    }
+}
+
+async fn derive_in_worker(
+    module_url: &str,
+    passphrase: &str,
+    salt: &[u8],
+) -> Result<[u8; 32], WorkerError> {
+    let worker = worker().map_err(WorkerError::Unavailable)?;
+
+    let id = NEXT_ID.with(|next| {
+        let id = next.get();
+        next.set(id.wrapping_add(1));
+        id
+    });
+    let (sender, receiver) = oneshot::channel();
+    PENDING.with(|pending| pending.borrow_mut().insert(id, sender));
+
+    let message = Object::new();
+    let set = |key: &str, value: &JsValue| Reflect::set(&message, &key.into(), value);
+    set("id", &id.into())
+        .and_then(|_| set("moduleUrl", &module_url.into()))
+        .and_then(|_| set("passphrase", &passphrase.into()))
+        .and_then(|_| set("salt", &Uint8Array::from(salt)))
+        .and_then(|_| worker.post_message(&message))
+        .map_err(|e| {
+            PENDING.with(|pending| pending.borrow_mut().remove(&id));
+            WorkerError::Unavailable(format!("{e:?}"))
+        })?;
+
+    let seed = receiver
+        .await
+        .map_err(|_| WorkerError::Unavailable("Worker stopped".to_string()))??;
+    seed.try_into().map_err(|_| {
+        WorkerError::Derivation("Worker returned a seed of the wrong length".to_string())
+    })
+}
+
+// The running worker, spawned on first use.
+fn worker() -> Result<Worker, String> {
+    if let Some(worker) = WORKER.with(|worker| worker.borrow().clone()) {
+        return Ok(worker);
+    }
+
+    let options = BlobPropertyBag::new();
+    options.set_type("text/javascript");
+    let blob =
+        Blob::new_with_str_sequence_and_options(&Array::of1(&WORKER_SCRIPT.into()), &options)
+            .map_err(|e| format!("{e:?}"))?;
+    let url = Url::create_object_url_with_blob(&blob).map_err(|e| format!("{e:?}"))?;
+
+    let worker_options = WorkerOptions::new();
+    worker_options.set_type(WorkerType::Module);
+    worker_options.set_name("hoddor-argon2");
+    let worker = Worker::new_with_options(&url, &worker_options).map_err(|e| format!("{e:?}"))?;
+
+    let onmessage = Closure::wrap(Box::new(|event: MessageEvent| {
+        let data = event.data();
+        let Some(id) = Reflect::get(&data, &"id".into())
+            .ok()
+            .and_then(|id| id.as_f64())
+        else {
+            return;
+        };
+        let Some(reply) = PENDING.with(|pending| pending.borrow_mut().remove(&(id as u32))) else {
+            return;
+        };
+
+        let field = |key: &str| {
+            Reflect::get(&data, &key.into())
+                .ok()
+                .and_then(|value| value.as_string())
+        };
+        let result = if let Some(error) = field("loadError") {
+            Err(WorkerError::Unavailable(error))
+        } else if let Some(error) = field("error") {
+            Err(WorkerError::Derivation(error))
+        } else {
+            Reflect::get(&data, &"seed".into())
+                .map(|seed| Uint8Array::new(&seed).to_vec())
+                .map_err(|e| WorkerError::Derivation(format!("{e:?}")))
+        };
+        let _ = reply.send(result);
+    }) as Box<dyn FnMut(MessageEvent)>);
+    worker.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+    onmessage.forget();
+
+    // A worker that fails to load takes its requests down with it and is
+    // spawned again on the next derivation.
+    let onerror = Closure::wrap(Box::new(|_: JsValue| {
+        if let Some(worker) = WORKER.with(|worker| worker.borrow_mut().take()) {
+            worker.terminate();
+        }
+        fail_pending("Worker failed to load");
+    }) as Box<dyn FnMut(JsValue)>);
+    worker.set_onerror(Some(onerror.as_ref().unchecked_ref()));
+    onerror.forget();
+
+    WORKER.with(|slot| *slot.borrow_mut() = Some(worker.clone()));
+    Ok(worker)
+}
+
+fn fail_pending(reason: &str) {
+    let pending: Vec<Reply> = PENDING.with(|pending| {
+        pending
+            .borrow_mut()
+            .drain()
+            .map(|(_, reply)| reply)
+            .collect()
+    });
+    for reply in pending {
+        let _ = reply.send(Err(WorkerError::Unavailable(reason.to_string())));
+    }
+}
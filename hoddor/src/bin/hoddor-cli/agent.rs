@@ -0,0 +1,84 @@
+use hoddor::adapters::native::agent::{default_socket_path, Agent};
+use hoddor::facades::native::Config;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: hoddor-cli agent [--socket <path>] [--identity <key or label>]...
+
+Holds identities unlocked and answers decrypt and sign requests from other
+programs on a Unix socket, without handing out the keys. Each --identity
+adds one; without any, the agent holds every identity of the config file,
+or the one in HODDOR_IDENTITY. The socket defaults to HODDOR_AGENT_SOCK or
+$XDG_RUNTIME_DIR/hoddor/agent.sock. Its directory must belong to you with
+mode 0700, and clients running as other users are refused.
+
+The agent runs in the foreground and first prints the HODDOR_AGENT_SOCK
+assignment for clients started with another socket than the default.
+Identities given as keys are labelled identity-1, identity-2 and so on.
+";
+
+pub fn run(args: &[String], config: Option<&Config>) -> Result<ExitCode, String> {
+    let mut socket = default_socket_path();
+    let mut identities = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |option: &str| {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{option} needs a value\n\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--socket" => socket = PathBuf::from(value("--socket")?),
+            "--identity" => identities.push(value("--identity")?),
+            "--help" | "-h" => {
+                print!("{USAGE}");
+                return Ok(ExitCode::SUCCESS);
+            }
+            other => return Err(format!("Unknown option: {other}\n\n{USAGE}")),
+        }
+    }
+
+    let mut agent = Agent::new();
+    for (index, identity) in identities.iter().enumerate() {
+        let label = if identity.starts_with("AGE-SECRET-KEY-") {
+            format!("identity-{}", index + 1)
+        } else {
+            identity.clone()
+        };
+        let private_key = crate::resolve_identity(config, identity)?;
+        agent
+            .add_identity(&label, &private_key)
+            .map_err(|e| e.to_string())?;
+    }
+
+    if identities.is_empty() {
+        if let Some(config) = config {
+            for label in config.identity_labels() {
+                let identity = config.identity(label).map_err(|e| e.to_string())?;
+                agent
+                    .add_identity(label, &identity.private_key())
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        if let (true, Ok(private_key)) = (agent.is_empty(), std::env::var("HODDOR_IDENTITY")) {
+            agent
+                .add_identity("default", &private_key)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    if agent.is_empty() {
+        return Err(format!("No identity to hold\n\n{USAGE}"));
+    }
+
+    println!(
+        "HODDOR_AGENT_SOCK={}; export HODDOR_AGENT_SOCK;",
+        socket.display()
+    );
+    agent
+        .serve(&socket)
+        .map_err(|e| format!("{}: {e}", socket.display()))?;
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,272 @@
+use crate::output::Output;
+use std::fmt::Write;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: hoddor-cli completions <bash|zsh|fish>
+
+Prints a completion script for the shell, e.g.
+
+  hoddor-cli completions bash > /etc/bash_completion.d/hoddor-cli
+  hoddor-cli completions zsh > \"${fpath[1]}/_hoddor-cli\"
+  hoddor-cli completions fish > ~/.config/fish/completions/hoddor-cli.fish
+";
+
+const BIN: &str = "hoddor-cli";
+
+struct Command {
+    name: &'static str,
+    about: &'static str,
+    /// Options with the values they take: `None` for flags, an empty list
+    /// for free-form values.
+    options: &'static [(&'static str, Option<&'static [&'static str]>)],
+    subcommands: &'static [&'static str],
+}
+
+const OUTPUT: (&str, Option<&[&str]>) = ("--output", Some(&Output::MODES));
+const JSON: (&str, Option<&[&str]>) = ("--json", None);
+const STRATEGIES: [&str; 3] = ["prefer-newest", "prefer-primary", "keep-both-with-suffix"];
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "create",
+        about: "Create an empty vault",
+        options: &[OUTPUT, JSON],
+        subcommands: &[],
+    },
+    Command {
+        name: "upsert",
+        about: "Store stdin in a namespace",
+        options: &[
+            ("--identity", Some(&[])),
+            ("--expires-in", Some(&[])),
+            ("--no-replace", None),
+            OUTPUT,
+            JSON,
+        ],
+        subcommands: &[],
+    },
+    Command {
+        name: "read",
+        about: "Print a decrypted namespace",
+        options: &[("--identity", Some(&[]))],
+        subcommands: &[],
+    },
+    Command {
+        name: "list",
+        about: "List vaults, or the namespaces of a vault",
+        options: &[OUTPUT, JSON],
+        subcommands: &[],
+    },
+    Command {
+        name: "export",
+        about: "Print a vault export",
+        options: &[],
+        subcommands: &[],
+    },
+    Command {
+        name: "import",
+        about: "Import a vault export read from stdin",
+        options: &[OUTPUT, JSON],
+        subcommands: &[],
+    },
+    Command {
+        name: "rekey",
+        about: "Re-encrypt a vault to a new passphrase",
+        options: &[
+            ("--identity", Some(&[])),
+            ("--save-as", Some(&[])),
+            OUTPUT,
+            JSON,
+        ],
+        subcommands: &[],
+    },
+    Command {
+        name: "diff",
+        about: "Compare two vaults or vault exports",
+        options: &[("--identity", Some(&[])), OUTPUT, JSON],
+        subcommands: &[],
+    },
+    Command {
+        name: "merge",
+        about: "Merge a vault or vault export into a vault",
+        options: &[
+            ("--strategy", Some(&STRATEGIES)),
+            ("--dry-run", None),
+            OUTPUT,
+            JSON,
+        ],
+        subcommands: &[],
+    },
+    Command {
+        name: "replay",
+        about: "Replay a recording of sync messages",
+        options: &[
+            ("--identity", Some(&[])),
+            ("--until", Some(&[])),
+            OUTPUT,
+            JSON,
+        ],
+        subcommands: &[],
+    },
+    Command {
+        name: "config",
+        about: "Read or change the encrypted config file",
+        options: &[OUTPUT, JSON],
+        subcommands: &["path", "list", "get", "set", "unset", "identity"],
+    },
+    Command {
+        name: "git-credential",
+        about: "Git credential helper backed by a vault",
+        options: &[
+            ("--vault", Some(&[])),
+            ("--namespace", Some(&[])),
+            ("--identity", Some(&[])),
+        ],
+        subcommands: &["get", "store", "erase"],
+    },
+    Command {
+        name: "run",
+        about: "Run a command with variables from a vault",
+        options: &[
+            ("--vault", Some(&[])),
+            ("--namespace", Some(&[])),
+            ("--identity", Some(&[])),
+        ],
+        subcommands: &[],
+    },
+    Command {
+        name: "agent",
+        about: "Answer decrypt and sign requests on a socket",
+        options: &[("--socket", Some(&[])), ("--identity", Some(&[]))],
+        subcommands: &[],
+    },
+    Command {
+        name: "completions",
+        about: "Print a shell completion script",
+        options: &[],
+        subcommands: &["bash", "zsh", "fish"],
+    },
+    Command {
+        name: "help",
+        about: "Show usage",
+        options: &[],
+        subcommands: &[],
+    },
+];
+
+pub fn run(args: &[String]) -> Result<ExitCode, String> {
+    let script = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["bash"] => bash(),
+        ["zsh"] => format!(
+            "#compdef {BIN}\n\nautoload -U +X bashcompinit && bashcompinit\n\n{}",
+            bash()
+        ),
+        ["fish"] => fish(),
+        ["--help"] | ["-h"] => {
+            print!("{USAGE}");
+            return Ok(ExitCode::SUCCESS);
+        }
+        _ => return Err(USAGE.to_string()),
+    };
+
+    print!("{script}");
+    Ok(ExitCode::SUCCESS)
+}
+
+fn bash() -> String {
+    let names: Vec<&str> = COMMANDS.iter().map(|command| command.name).collect();
+    let mut script = String::new();
+
+    let _ = writeln!(script, "_hoddor_cli() {{");
+    let _ = writeln!(script, "    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"");
+    let _ = writeln!(script, "    local prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"");
+    let _ = writeln!(script, "    if [ \"$COMP_CWORD\" -eq 1 ]; then");
+    let _ = writeln!(
+        script,
+        "        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+        names.join(" ")
+    );
+    let _ = writeln!(script, "        return");
+    let _ = writeln!(script, "    fi");
+    let _ = writeln!(script, "    case \"${{COMP_WORDS[1]}}\" in");
+
+    for command in COMMANDS {
+        let _ = writeln!(script, "    {})", command.name);
+        let _ = writeln!(script, "        case \"$prev\" in");
+        for (option, values) in command.options {
+            if let Some(values) = values {
+                let _ = writeln!(
+                    script,
+                    "        {option}) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return ;;",
+                    values.join(" ")
+                );
+            }
+        }
+        let _ = writeln!(script, "        esac");
+        let options: Vec<&str> = command.options.iter().map(|(option, _)| *option).collect();
+        let _ = writeln!(
+            script,
+            "        if [[ \"$cur\" == -* ]]; then COMPREPLY=($(compgen -W \"{} --help\" -- \"$cur\"))",
+            options.join(" ")
+        );
+        if !command.subcommands.is_empty() {
+            let _ = writeln!(
+                script,
+                "        elif [ \"$COMP_CWORD\" -eq 2 ]; then COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+                command.subcommands.join(" ")
+            );
+        }
+        let _ = writeln!(script, "        fi");
+        let _ = writeln!(script, "        ;;");
+    }
+
+    let _ = writeln!(script, "    esac");
+    let _ = writeln!(script, "}}");
+    let _ = writeln!(script, "complete -o default -F _hoddor_cli {BIN}");
+    script
+}
+
+fn fish() -> String {
+    let names: Vec<&str> = COMMANDS.iter().map(|command| command.name).collect();
+    let mut script = String::new();
+
+    for command in COMMANDS {
+        let _ = writeln!(
+            script,
+            "complete -c {BIN} -n '__fish_use_subcommand' -f -a {} -d '{}'",
+            command.name, command.about
+        );
+    }
+
+    for command in COMMANDS {
+        let condition = format!("__fish_seen_subcommand_from {}", command.name);
+        for (option, values) in command.options {
+            let long = option.trim_start_matches("--");
+            let _ = match values {
+                None => writeln!(script, "complete -c {BIN} -n '{condition}' -l {long}"),
+                Some([]) => writeln!(script, "complete -c {BIN} -n '{condition}' -l {long} -r"),
+                Some(values) => writeln!(
+                    script,
+                    "complete -c {BIN} -n '{condition}' -l {long} -x -a '{}'",
+                    values.join(" ")
+                ),
+            };
+        }
+        if !command.subcommands.is_empty() {
+            let _ = writeln!(
+                script,
+                "complete -c {BIN} -n '{condition}; and not __fish_seen_subcommand_from {}' -f -a '{}'",
+                command.subcommands.join(" "),
+                command.subcommands.join(" ")
+            );
+        }
+    }
+
+    let _ = writeln!(
+        script,
+        "complete -c {BIN} -n 'not __fish_seen_subcommand_from {}' -f",
+        names.join(" ")
+    );
+    script
+}
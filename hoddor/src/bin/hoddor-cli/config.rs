@@ -0,0 +1,171 @@
+use crate::output::{self, Output};
+use futures::executor::block_on;
+use hoddor::facades::native::{Config, ConfigFile, IdentityHandle, VaultManager};
+use hoddor::Platform;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: hoddor-cli config <command> [--output table|json]
+
+Commands:
+  path                            Print the location of the config file
+  list                            Print every setting and identity label
+  get <key>                       Print a setting
+  set <key> <value>               Change a setting
+  unset <key>                     Reset a setting to its default
+  identity add <label>            Store the private key read from stdin
+  identity generate <label>       Store a new identity, printing its public key
+  identity remove <label>         Forget an identity
+
+Settings: vault-directory, signaling-server, kdf (argon2id).
+
+The config file is encrypted to HODDOR_CONFIG_PASSPHRASE when set, and to
+a key file created next to it otherwise. Other commands accept identity
+labels wherever they take a private key.
+";
+
+pub fn run(args: &[String]) -> Result<ExitCode, String> {
+    let mut output = Output::from_env()?;
+    let mut operands = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !output.parse_option(arg, &mut args, USAGE)? {
+            operands.push(arg.as_str());
+        }
+    }
+
+    let args = operands;
+    if matches!(args[..], [] | ["--help"] | ["-h"]) {
+        print!("{USAGE}");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let platform = Platform::new();
+    let file = ConfigFile::locate().map_err(|e| e.to_string())?;
+    let mut config = block_on(file.load(&platform)).map_err(|e| e.to_string())?;
+
+    let changed = match args[..] {
+        ["path"] => {
+            let path = file.path();
+            if output.is_json() {
+                output::print_json(&path)?;
+            } else {
+                println!("{}", path.display());
+            }
+            false
+        }
+        ["list"] => {
+            let mut settings = BTreeMap::new();
+            for key in Config::KEYS {
+                settings.insert(key, config.get(key).map_err(|e| e.to_string())?);
+            }
+            let mut identities = BTreeMap::new();
+            for label in config.identity_labels() {
+                let identity = config.identity(label).map_err(|e| e.to_string())?;
+                identities.insert(label, identity.public_key());
+            }
+
+            if output.is_json() {
+                output::print_json(&json!({ "settings": settings, "identities": identities }))?;
+            } else {
+                for (key, value) in settings {
+                    println!("{key:<20} {}", value.unwrap_or_default());
+                }
+                for (label, public_key) in identities {
+                    println!("{:<20} {public_key}", format!("identity {label}"));
+                }
+            }
+            false
+        }
+        ["get", key] => {
+            let value = config.get(key).map_err(|e| e.to_string())?;
+            if output.is_json() {
+                output::print_json(&value)?;
+            } else if let Some(value) = value {
+                println!("{value}");
+            }
+            false
+        }
+        ["set", key, value] => {
+            config.set(key, Some(value)).map_err(|e| e.to_string())?;
+            true
+        }
+        ["unset", key] => {
+            config.set(key, None).map_err(|e| e.to_string())?;
+            true
+        }
+        ["identity", "add", label] => {
+            let mut private_key = String::new();
+            std::io::stdin()
+                .read_to_string(&mut private_key)
+                .map_err(|e| e.to_string())?;
+            let identity =
+                IdentityHandle::from_private_key(private_key.trim()).map_err(|e| e.to_string())?;
+            config
+                .add_identity(label, &identity)
+                .map_err(|e| e.to_string())?;
+            true
+        }
+        ["identity", "generate", label] => {
+            let (public_key, private_key) =
+                hoddor::facades::native::generate_identity().map_err(|e| e.to_string())?;
+            let identity =
+                IdentityHandle::from_private_key(&private_key).map_err(|e| e.to_string())?;
+            config
+                .add_identity(label, &identity)
+                .map_err(|e| e.to_string())?;
+            if output.is_json() {
+                output::print_json(&json!({ "label": label, "publicKey": public_key }))?;
+            } else {
+                println!("{public_key}");
+            }
+            true
+        }
+        ["identity", "remove", label] => {
+            if !config.remove_identity(label) {
+                return Err(format!("No identity labelled {label}"));
+            }
+            true
+        }
+        _ => return Err(USAGE.to_string()),
+    };
+
+    if changed {
+        block_on(file.save(&platform, &config)).map_err(|e| e.to_string())?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Reads the config file when there is one and points storage at its vault
+/// directory, then completes the saves an earlier run left interrupted,
+/// before anything opens a vault.
+pub fn apply() -> Result<Option<Config>, String> {
+    let config = load()?;
+
+    if let Err(e) = block_on(VaultManager::new().recover_vaults()) {
+        eprintln!("Failed to recover interrupted saves: {e}");
+    }
+
+    Ok(config)
+}
+
+fn load() -> Result<Option<Config>, String> {
+    let Ok(file) = ConfigFile::locate() else {
+        return Ok(None);
+    };
+    if !file.exists() {
+        return Ok(None);
+    }
+
+    let config = block_on(file.load(&Platform::new()))
+        .map_err(|e| format!("{}: {e}", file.path().display()))?;
+    if let Some(directory) = &config.vault_directory {
+        hoddor::adapters::native::fs_storage::set_root_path(directory.as_str());
+    }
+
+    Ok(Some(config))
+}
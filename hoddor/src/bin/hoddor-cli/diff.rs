@@ -0,0 +1,129 @@
+use crate::output::{self, Output};
+use futures::executor::block_on;
+use hoddor::domain::vault::diff::{self, NamespaceStatus, NamespaceSummary, VaultDiff};
+use hoddor::facades::native::Config;
+use hoddor::Platform;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: hoddor-cli diff <left> <right> [--identity <key or label>] [--output table|json]
+
+<left> and <right> are paths to files written by export_vault, or names of
+vaults in ./hoddor_data. Namespaces whose ciphertext differs are compared by
+content when the identity can decrypt them; the identity is read from
+HODDOR_IDENTITY when --identity is not given, and may be the label of an
+identity stored with `hoddor-cli config identity`.
+
+Exits with 0 when both sides are identical, 1 when they differ.
+";
+
+pub fn run(args: &[String], config: Option<&Config>) -> Result<ExitCode, String> {
+    let mut operands = Vec::new();
+    let mut identity = std::env::var("HODDOR_IDENTITY").ok();
+    let mut output = Output::from_env()?;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if output.parse_option(arg, &mut args, USAGE)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--identity" => {
+                identity = Some(
+                    args.next()
+                        .ok_or_else(|| format!("--identity needs a value\n\n{USAGE}"))?
+                        .clone(),
+                );
+            }
+            "--help" | "-h" => {
+                print!("{USAGE}");
+                return Ok(ExitCode::SUCCESS);
+            }
+            option if option.starts_with("--") => {
+                return Err(format!("Unknown option: {option}\n\n{USAGE}"));
+            }
+            operand => operands.push(operand),
+        }
+    }
+
+    let [left, right] = operands[..] else {
+        return Err(USAGE.to_string());
+    };
+    let identity = identity
+        .map(|identity| crate::resolve_identity(config, &identity))
+        .transpose()?;
+
+    let platform = Platform::new();
+    let report = block_on(async {
+        let left = crate::load_vault(&platform, left).await?;
+        let right = crate::load_vault(&platform, right).await?;
+
+        Ok::<_, String>(diff::diff_vaults(&platform, &left, &right, identity.as_deref()).await)
+    })?;
+
+    if output.is_json() {
+        output::print_json(&report)?;
+    } else {
+        print_report(&report);
+    }
+
+    Ok(if report.is_identical() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    })
+}
+
+fn print_report(report: &VaultDiff) {
+    for recipient in &report.recipients_only_in_left {
+        println!("recipient only in left:  {recipient}");
+    }
+    for recipient in &report.recipients_only_in_right {
+        println!("recipient only in right: {recipient}");
+    }
+    for flag in &report.flags_changed {
+        println!("flag changed:            {flag}");
+    }
+
+    for namespace in &report.namespaces {
+        let marker = match namespace.status {
+            NamespaceStatus::OnlyInLeft => "-",
+            NamespaceStatus::OnlyInRight => "+",
+            NamespaceStatus::Identical => "=",
+            NamespaceStatus::SameContent => "~",
+            NamespaceStatus::Different => "!",
+            NamespaceStatus::Undetermined => "?",
+        };
+        println!(
+            "{marker} {}  {} -> {}",
+            namespace.namespace,
+            describe(namespace.left.as_ref()),
+            describe(namespace.right.as_ref())
+        );
+    }
+
+    println!(
+        "{} identical, {} same content, {} different, {} undetermined, {} only in left, {} only in right",
+        report.count(NamespaceStatus::Identical),
+        report.count(NamespaceStatus::SameContent),
+        report.count(NamespaceStatus::Different),
+        report.count(NamespaceStatus::Undetermined),
+        report.count(NamespaceStatus::OnlyInLeft),
+        report.count(NamespaceStatus::OnlyInRight),
+    );
+}
+
+fn describe(summary: Option<&NamespaceSummary>) -> String {
+    let Some(summary) = summary else {
+        return "(none)".to_string();
+    };
+
+    let mut description = summary.revision[..12].to_string();
+    if let Some(expires_at) = summary.expires_at {
+        description.push_str(&format!(" expires@{expires_at}"));
+    }
+    if summary.timelocked {
+        description.push_str(" timelocked");
+    }
+    description
+}
@@ -0,0 +1,349 @@
+//! Git credential helper backed by a vault namespace.
+//!
+//! Credentials are kept as one JSON object keyed by `protocol://host[/path]`
+//! in a single namespace, encrypted to the identity given on the command
+//! line like any other namespace.
+
+use futures::executor::block_on;
+use hoddor::domain::vault::error::VaultError;
+use hoddor::facades::native::{Config, IdentityHandle, VaultManager};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const USAGE: &str = "\
+Usage: hoddor-cli git-credential [--vault <name>] [--namespace <namespace>]
+                                 [--identity <key or label>] <get|store|erase>
+
+Implements the git credential helper protocol, keeping credentials in
+<namespace> (default git-credentials) of <vault> (default git), which is
+created on the first store. The identity is read from HODDOR_IDENTITY when
+--identity is not given. Configure git with
+
+  git config --global credential.helper \\
+    '!hoddor-cli git-credential --vault git --identity <label>'
+";
+
+const DEFAULT_VAULT: &str = "git";
+const DEFAULT_NAMESPACE: &str = "git-credentials";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredCredential {
+    username: String,
+    password: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    password_expiry_utc: Option<i64>,
+}
+
+pub fn run(args: &[String], config: Option<&Config>) -> Result<ExitCode, String> {
+    let mut vault = DEFAULT_VAULT.to_string();
+    let mut namespace = DEFAULT_NAMESPACE.to_string();
+    let mut identity = std::env::var("HODDOR_IDENTITY").ok();
+    let mut operation = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |option: &str| {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{option} needs a value\n\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--vault" => vault = value("--vault")?,
+            "--namespace" => namespace = value("--namespace")?,
+            "--identity" => identity = Some(value("--identity")?),
+            "--help" | "-h" => {
+                print!("{USAGE}");
+                return Ok(ExitCode::SUCCESS);
+            }
+            option if option.starts_with("--") => {
+                return Err(format!("Unknown option: {option}\n\n{USAGE}"));
+            }
+            "get" | "store" | "erase" if operation.is_none() => operation = Some(arg.clone()),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    let Some(operation) = operation else {
+        return Err(USAGE.to_string());
+    };
+    let identity = identity.ok_or_else(|| format!("No identity given\n\n{USAGE}"))?;
+    let identity = IdentityHandle::from_private_key(&crate::resolve_identity(config, &identity)?)
+        .map_err(|e| e.to_string())?;
+    let request = read_request(std::io::stdin().lock())?;
+
+    let store = Store {
+        manager: VaultManager::new(),
+        vault,
+        namespace,
+        identity,
+    };
+
+    block_on(async {
+        match operation.as_str() {
+            "get" => store.get(&request).await,
+            "store" => store.store(&request).await,
+            _ => store.erase(&request).await,
+        }
+    })
+    .map_err(|e| e.to_string())?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Reads `key=value` lines up to a blank line or the end of input. Array
+/// attributes (`key[]=`) are not used and skipped.
+fn read_request(input: impl BufRead) -> Result<BTreeMap<String, String>, String> {
+    let mut request = BTreeMap::new();
+
+    for line in input.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            if !key.ends_with("[]") {
+                request.insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+
+    Ok(request)
+}
+
+/// `protocol://host[/path]`, or `None` when git sent neither protocol nor
+/// host, as it does for credentials it cannot describe.
+fn credential_key(request: &BTreeMap<String, String>, with_path: bool) -> Option<String> {
+    let protocol = request.get("protocol")?;
+    let host = request.get("host")?;
+
+    Some(match request.get("path").filter(|_| with_path) {
+        Some(path) => format!("{protocol}://{host}/{path}"),
+        None => format!("{protocol}://{host}"),
+    })
+}
+
+/// The credential stored for the request's path, or else for its host,
+/// skipping those of another username and those expired at `now`.
+fn find_credential<'c>(
+    credentials: &'c BTreeMap<String, StoredCredential>,
+    request: &BTreeMap<String, String>,
+    now: i64,
+) -> Option<&'c StoredCredential> {
+    [true, false]
+        .into_iter()
+        .filter_map(|with_path| credential_key(request, with_path))
+        .filter_map(|key| credentials.get(&key))
+        .find(|credential| {
+            request
+                .get("username")
+                .is_none_or(|username| *username == credential.username)
+                && credential
+                    .password_expiry_utc
+                    .is_none_or(|expiry| expiry > now)
+        })
+}
+
+struct Store {
+    manager: VaultManager,
+    vault: String,
+    namespace: String,
+    identity: IdentityHandle,
+}
+
+impl Store {
+    async fn vault_exists(&self) -> bool {
+        self.manager
+            .list_vaults()
+            .await
+            .is_ok_and(|vaults| vaults.contains(&self.vault))
+    }
+
+    async fn load(&self) -> Result<BTreeMap<String, StoredCredential>, VaultError> {
+        if !self.vault_exists().await {
+            return Ok(BTreeMap::new());
+        }
+
+        let bytes = match self
+            .manager
+            .read_namespace(&self.vault, &self.identity.private_key(), &self.namespace)
+            .await
+        {
+            Ok(bytes) => bytes,
+            Err(VaultError::NamespaceNotFound) => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e),
+        };
+
+        serde_json::from_slice(&bytes).map_err(|e| VaultError::serialization_error(e.to_string()))
+    }
+
+    async fn save(
+        &self,
+        credentials: &BTreeMap<String, StoredCredential>,
+    ) -> Result<(), VaultError> {
+        if !self.vault_exists().await {
+            self.manager.create_vault(&self.vault).await?;
+        }
+
+        let data = serde_json::to_vec(credentials)
+            .map_err(|e| VaultError::serialization_error(e.to_string()))?;
+        self.manager
+            .upsert_namespace(
+                &self.vault,
+                &self.identity.public_key(),
+                &self.namespace,
+                data,
+                None,
+                true,
+            )
+            .await
+    }
+
+    async fn get(&self, request: &BTreeMap<String, String>) -> Result<(), VaultError> {
+        let credentials = self.load().await?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs() as i64);
+
+        if let Some(credential) = find_credential(&credentials, request, now) {
+            let mut stdout = std::io::stdout().lock();
+            let _ = writeln!(stdout, "username={}", credential.username);
+            let _ = writeln!(stdout, "password={}", credential.password);
+            if let Some(expiry) = credential.password_expiry_utc {
+                let _ = writeln!(stdout, "password_expiry_utc={expiry}");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn store(&self, request: &BTreeMap<String, String>) -> Result<(), VaultError> {
+        let (Some(key), Some(username), Some(password)) = (
+            credential_key(request, true),
+            request.get("username"),
+            request.get("password"),
+        ) else {
+            return Ok(());
+        };
+
+        let mut credentials = self.load().await?;
+        credentials.insert(
+            key,
+            StoredCredential {
+                username: username.clone(),
+                password: password.clone(),
+                password_expiry_utc: request
+                    .get("password_expiry_utc")
+                    .and_then(|expiry| expiry.parse().ok()),
+            },
+        );
+
+        self.save(&credentials).await
+    }
+
+    /// Forgets the credential git rejected. Only a matching username (or
+    /// none given) erases it, as git may erase on behalf of another helper.
+    async fn erase(&self, request: &BTreeMap<String, String>) -> Result<(), VaultError> {
+        let Some(key) = credential_key(request, true) else {
+            return Ok(());
+        };
+
+        let mut credentials = self.load().await?;
+        let matches = credentials.get(&key).is_some_and(|credential| {
+            request
+                .get("username")
+                .is_none_or(|username| *username == credential.username)
+        });
+        if !matches {
+            return Ok(());
+        }
+
+        credentials.remove(&key);
+        self.save(&credentials).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(lines: &str) -> BTreeMap<String, String> {
+        read_request(lines.as_bytes()).unwrap()
+    }
+
+    fn credential(username: &str, password_expiry_utc: Option<i64>) -> StoredCredential {
+        StoredCredential {
+            username: username.to_string(),
+            password: format!("{username}-password"),
+            password_expiry_utc,
+        }
+    }
+
+    #[test]
+    fn test_read_request() {
+        let parsed = request(
+            "protocol=https\nhost=example.com\nwwwauth[]=Basic realm=x\n\
+             malformed line\npassword=a=b\n\nusername=after-blank\n",
+        );
+
+        assert_eq!(
+            parsed.into_iter().collect::<Vec<_>>(),
+            [
+                ("host".to_string(), "example.com".to_string()),
+                ("password".to_string(), "a=b".to_string()),
+                ("protocol".to_string(), "https".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_credential_key() {
+        let with_path = request("protocol=https\nhost=example.com\npath=org/repo.git\n");
+        assert_eq!(
+            credential_key(&with_path, true).as_deref(),
+            Some("https://example.com/org/repo.git")
+        );
+        assert_eq!(
+            credential_key(&with_path, false).as_deref(),
+            Some("https://example.com")
+        );
+        assert_eq!(
+            credential_key(&request("protocol=https\nhost=example.com\n"), true).as_deref(),
+            Some("https://example.com")
+        );
+        assert_eq!(credential_key(&request("protocol=https\n"), true), None);
+        assert_eq!(credential_key(&request("host=example.com\n"), true), None);
+    }
+
+    #[test]
+    fn test_find_credential() {
+        let credentials = BTreeMap::from([
+            ("https://example.com".to_string(), credential("host", None)),
+            (
+                "https://example.com/org/repo.git".to_string(),
+                credential("repo", Some(1_000)),
+            ),
+        ]);
+        let find = |lines: &str, now: i64| {
+            find_credential(&credentials, &request(lines), now)
+                .map(|credential| credential.username.as_str())
+        };
+        let repo = "protocol=https\nhost=example.com\npath=org/repo.git\n";
+
+        // The path's credential comes first, the host's once it expired.
+        assert_eq!(find(repo, 999), Some("repo"));
+        assert_eq!(find(repo, 1_000), Some("host"));
+        assert_eq!(find("protocol=https\nhost=example.com\n", 0), Some("host"));
+        assert_eq!(
+            find("protocol=https\nhost=example.com\npath=other.git\n", 0),
+            Some("host")
+        );
+
+        assert_eq!(find(&format!("{repo}username=host\n"), 0), Some("host"));
+        assert_eq!(find(&format!("{repo}username=nobody\n"), 0), None);
+        assert_eq!(find("protocol=http\nhost=example.com\n", 0), None);
+        assert_eq!(find("host=example.com\n", 0), None);
+    }
+}
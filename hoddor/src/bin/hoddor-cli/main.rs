@@ -0,0 +1,111 @@
+//! Command line tool for vaults stored by the native build.
+//!
+//! Vaults live under `./hoddor_data`, as for every native embedder, unless
+//! the config file sets another `vault-directory`.
+
+#[cfg(unix)]
+mod agent;
+mod completions;
+mod config;
+mod diff;
+mod git_credential;
+mod merge;
+mod output;
+mod replay;
+mod run;
+mod vault;
+
+use hoddor::domain::vault::{deserialize_vault, operations, Vault};
+use hoddor::facades::native::Config;
+use hoddor::Platform;
+use std::path::Path;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: hoddor-cli <command> [options]
+
+Commands:
+  create <vault>                  Create an empty vault
+  upsert <vault> <namespace>      Store stdin in a namespace
+  read <vault> <namespace>        Print a decrypted namespace
+  list [<vault>]                  List vaults, or the namespaces of a vault
+  export <vault>                  Print a vault export
+  import <vault>                  Import a vault export read from stdin
+  rekey <vault>                   Re-encrypt a vault to a new passphrase
+  diff <left> <right>             Compare two vaults or vault exports
+  merge <primary> <secondary>     Merge a vault or vault export into a vault
+  replay <recording>              Replay a recording of sync messages
+  config <command>                Read or change the encrypted config file
+  git-credential <operation>      Git credential helper backed by a vault
+  run -- <command> [args...]      Run a command with variables from a vault
+  agent                           Answer decrypt and sign requests on a socket
+  completions <shell>             Print a bash, zsh or fish completion script
+  help                            Show this message
+
+Commands other than read, export, git-credential, run and agent take
+--output table|json
+(--json for short); HODDOR_OUTPUT sets the default. Set HODDOR_LOG (e.g.
+HODDOR_LOG=debug) to trace vault operations on stderr.
+";
+
+fn main() -> ExitCode {
+    hoddor::adapters::native::init_tracing("warn");
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        #[cfg(unix)]
+        Some("agent") => config::apply().and_then(|config| agent::run(&args[1..], config.as_ref())),
+        Some("completions") => completions::run(&args[1..]),
+        Some("config") => config::run(&args[1..]),
+        Some("diff") => config::apply().and_then(|config| diff::run(&args[1..], config.as_ref())),
+        Some("git-credential") => {
+            config::apply().and_then(|config| git_credential::run(&args[1..], config.as_ref()))
+        }
+        Some("run") => config::apply().and_then(|config| run::run(&args[1..], config.as_ref())),
+        Some("merge") => config::apply().and_then(|_| merge::run(&args[1..])),
+        Some("replay") => {
+            config::apply().and_then(|config| replay::run(&args[1..], config.as_ref()))
+        }
+        Some(command) if vault::COMMANDS.contains(&command) => {
+            config::apply().and_then(|config| vault::run(command, &args[1..], config.as_ref()))
+        }
+        Some("help") | Some("--help") | Some("-h") => {
+            print!("{USAGE}");
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(command) => Err(format!("Unknown command: {command}\n\n{USAGE}")),
+        None => Err(USAGE.to_string()),
+    };
+
+    result.unwrap_or_else(|message| {
+        eprintln!("{message}");
+        ExitCode::from(2)
+    })
+}
+
+// An existing file is read as a vault export, anything else as a vault name.
+async fn load_vault(platform: &Platform, operand: &str) -> Result<Vault, String> {
+    if Path::new(operand).is_file() {
+        let bytes = std::fs::read(operand).map_err(|e| format!("{operand}: {e}"))?;
+        return deserialize_vault(&bytes).map_err(|e| format!("{operand}: {e}"));
+    }
+
+    operations::read_vault(platform, operand)
+        .await
+        .map_err(|e| format!("{operand}: {e}"))
+}
+
+/// Private keys are taken as they are, anything else as the label of an
+/// identity in the config file.
+fn resolve_identity(config: Option<&Config>, identity: &str) -> Result<String, String> {
+    if identity.starts_with("AGE-SECRET-KEY-") {
+        return Ok(identity.to_string());
+    }
+
+    config
+        .ok_or_else(|| format!("No config file to look up identity {identity} in"))?
+        .identity(identity)
+        .map(|identity| identity.private_key())
+        .map_err(|e| e.to_string())
+}
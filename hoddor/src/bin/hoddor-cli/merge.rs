@@ -0,0 +1,95 @@
+use crate::output::{self, Output};
+use futures::executor::block_on;
+use hoddor::domain::vault::merge::{self, MergeAction, MergeReport, MergeStrategy};
+use hoddor::Platform;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: hoddor-cli merge <primary> <secondary> [--strategy <strategy>] [--dry-run] [--output table|json]
+
+Merges <secondary>, a vault export or a vault name, into the vault
+<primary> in ./hoddor_data. When both hold a namespace with different
+content, <strategy> decides which copy wins:
+
+  prefer-newest           the copy written last (default)
+  prefer-primary          the copy in <primary>
+  keep-both-with-suffix   both, the secondary one renamed to <namespace>.merged
+
+--dry-run prints what would change without writing anything.
+";
+
+pub fn run(args: &[String]) -> Result<ExitCode, String> {
+    let mut operands = Vec::new();
+    let mut strategy = MergeStrategy::PreferNewest;
+    let mut dry_run = false;
+    let mut output = Output::from_env()?;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if output.parse_option(arg, &mut args, USAGE)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--strategy" => {
+                strategy = args
+                    .next()
+                    .ok_or_else(|| format!("--strategy needs a value\n\n{USAGE}"))?
+                    .parse()
+                    .map_err(|e| format!("{e}\n\n{USAGE}"))?;
+            }
+            "--dry-run" => dry_run = true,
+            "--help" | "-h" => {
+                print!("{USAGE}");
+                return Ok(ExitCode::SUCCESS);
+            }
+            option if option.starts_with("--") => {
+                return Err(format!("Unknown option: {option}\n\n{USAGE}"));
+            }
+            operand => operands.push(operand),
+        }
+    }
+
+    let [primary, secondary] = operands[..] else {
+        return Err(USAGE.to_string());
+    };
+
+    let platform = Platform::new();
+    let report = block_on(async {
+        let secondary = crate::load_vault(&platform, secondary).await?;
+
+        merge::merge_vaults(&platform, primary, &secondary, strategy, dry_run)
+            .await
+            .map_err(|e| format!("{primary}: {e}"))
+    })?;
+
+    if output.is_json() {
+        output::print_json(&report)?;
+    } else {
+        print_report(&report);
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_report(report: &MergeReport) {
+    for recipient in &report.recipients_added {
+        println!("add recipient  {recipient}");
+    }
+
+    for entry in &report.entries {
+        match &entry.action {
+            MergeAction::Add => println!("add            {}", entry.namespace),
+            MergeAction::Replace => println!("replace        {}", entry.namespace),
+            MergeAction::Keep => println!("keep           {}", entry.namespace),
+            MergeAction::AddAs { namespace } => {
+                println!("add            {namespace} (from {})", entry.namespace)
+            }
+        }
+    }
+
+    if report.is_noop() {
+        println!("Nothing to merge");
+    } else if report.dry_run {
+        println!("Dry run: nothing was written");
+    }
+}
@@ -0,0 +1,73 @@
+//! Output modes shared by every command.
+//!
+//! `table` is meant for people and may change between releases; `json` is
+//! the stable form for scripts. The default comes from `HODDOR_OUTPUT`.
+
+use serde::Serialize;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Output {
+    #[default]
+    Table,
+    Json,
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Output::Table),
+            "json" => Ok(Output::Json),
+            other => Err(format!(
+                "Unknown output mode: {other} (expected table or json)"
+            )),
+        }
+    }
+}
+
+impl Output {
+    pub const MODES: [&'static str; 2] = ["table", "json"];
+
+    pub fn from_env() -> Result<Self, String> {
+        std::env::var("HODDOR_OUTPUT")
+            .ok()
+            .map_or(Ok(Output::Table), |mode| mode.parse())
+    }
+
+    /// Takes `--output <mode>` or its `--json` shorthand when `arg` is one
+    /// of them, returning whether it was.
+    pub fn parse_option<'a>(
+        &mut self,
+        arg: &str,
+        args: &mut impl Iterator<Item = &'a String>,
+        usage: &str,
+    ) -> Result<bool, String> {
+        match arg {
+            "--output" => {
+                *self = args
+                    .next()
+                    .ok_or_else(|| format!("--output needs a value\n\n{usage}"))?
+                    .parse()
+                    .map_err(|e| format!("{e}\n\n{usage}"))?;
+                Ok(true)
+            }
+            "--json" => {
+                *self = Output::Json;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    pub fn is_json(self) -> bool {
+        self == Output::Json
+    }
+}
+
+pub fn print_json(value: &impl Serialize) -> Result<(), String> {
+    let output = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{output}");
+    Ok(())
+}
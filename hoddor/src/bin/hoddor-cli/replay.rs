@@ -0,0 +1,139 @@
+use crate::output::{self, Output};
+use futures::executor::block_on;
+use hoddor::domain::vault::sync_recording::{self, Replay, SyncDirection};
+use hoddor::facades::native::Config;
+use hoddor::Platform;
+use std::path::Path;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: hoddor-cli replay <recording> [--identity <key or label>] [--until <sequence>] [--output table|json]
+
+<recording> is a file written by export_sync_recording, or the name of a
+vault in ./hoddor_data that recorded its sync messages. The records are
+decrypted with the identity matching the key given when recording started,
+read from HODDOR_IDENTITY when --identity is not given.
+
+Prints the recorded messages one step at a time, with the revision of the
+namespace each leaves behind, then the namespaces of the recording replica
+after the last step. --until stops after the record with that sequence.
+";
+
+pub fn run(args: &[String], config: Option<&Config>) -> Result<ExitCode, String> {
+    let mut operands = Vec::new();
+    let mut identity = std::env::var("HODDOR_IDENTITY").ok();
+    let mut until = None;
+    let mut output = Output::from_env()?;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if output.parse_option(arg, &mut args, USAGE)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--identity" => {
+                identity = Some(
+                    args.next()
+                        .ok_or_else(|| format!("--identity needs a value\n\n{USAGE}"))?
+                        .clone(),
+                );
+            }
+            "--until" => {
+                until = Some(
+                    args.next()
+                        .ok_or_else(|| format!("--until needs a value\n\n{USAGE}"))?
+                        .parse::<u64>()
+                        .map_err(|e| format!("--until: {e}\n\n{USAGE}"))?,
+                );
+            }
+            "--help" | "-h" => {
+                print!("{USAGE}");
+                return Ok(ExitCode::SUCCESS);
+            }
+            option if option.starts_with("--") => {
+                return Err(format!("Unknown option: {option}\n\n{USAGE}"));
+            }
+            operand => operands.push(operand),
+        }
+    }
+
+    let [recording] = operands[..] else {
+        return Err(USAGE.to_string());
+    };
+    let identity = identity
+        .map(|identity| crate::resolve_identity(config, &identity))
+        .transpose()?
+        .ok_or_else(|| format!("An identity is needed to decrypt the recording\n\n{USAGE}"))?;
+
+    let platform = Platform::new();
+    let replay = block_on(async {
+        let export = if Path::new(recording).is_file() {
+            std::fs::read_to_string(recording).map_err(|e| format!("{recording}: {e}"))?
+        } else {
+            sync_recording::export_sync_recording(&platform, recording)
+                .await
+                .map_err(|e| format!("{recording}: {e}"))?
+        };
+
+        let records = sync_recording::decrypt_sync_recording(&platform, &export, &identity)
+            .await
+            .map_err(|e| format!("{recording}: {e}"))?;
+
+        Ok::<_, String>(sync_recording::replay(&records, until))
+    })?;
+
+    if output.is_json() {
+        output::print_json(&replay)?;
+    } else {
+        print_replay(&replay);
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_replay(replay: &Replay) {
+    for step in &replay.steps {
+        let direction = match step.direction {
+            SyncDirection::Sent => "->",
+            SyncDirection::Received => "<-",
+        };
+        let marker = if step.changed { "*" } else { " " };
+        let clock: Vec<String> = step
+            .vector_clock
+            .iter()
+            .map(|(peer, count)| format!("{peer}:{count}"))
+            .collect();
+        println!(
+            "{:>6} {direction} {marker} {:<7} {}  {}  by {} [{}]",
+            step.sequence,
+            step.operation,
+            step.namespace,
+            short(step.revision.as_deref()),
+            step.author,
+            clock.join(" ")
+        );
+    }
+
+    for sequence in &replay.skipped {
+        println!("{sequence:>6} skipped: not a sync message");
+    }
+
+    println!();
+    for (name, namespace) in &replay.namespaces {
+        println!(
+            "{name}  {}  by {} at {}",
+            short(Some(&namespace.revision)),
+            namespace.author,
+            namespace.timestamp
+        );
+    }
+    println!(
+        "{} steps, {} namespaces",
+        replay.steps.len(),
+        replay.namespaces.len()
+    );
+}
+
+fn short(revision: Option<&str>) -> &str {
+    revision.map_or("(deleted)", |revision| &revision[..12])
+}
@@ -0,0 +1,255 @@
+//! Runs a command with the variables of a vault namespace in its
+//! environment.
+//!
+//! The namespace holds either a JSON object of strings or dotenv text
+//! (`KEY=value` lines, `#` comments, optionally `export`ed or quoted). The
+//! decrypted values only live in this process until the child exits and are
+//! wiped afterwards; the child does not inherit the identity it was
+//! decrypted with.
+
+use futures::executor::block_on;
+use hoddor::facades::native::{Config, VaultManager};
+use std::collections::BTreeMap;
+use std::process::{Command, ExitCode};
+use zeroize::Zeroize;
+
+const USAGE: &str = "\
+Usage: hoddor-cli run --vault <name> [--namespace <namespace>]
+                      [--identity <key or label>] -- <command> [args...]
+
+Runs <command> with the variables stored in <namespace> (default env) added
+to its environment, and exits with its status. The identity is read from
+HODDOR_IDENTITY when --identity is not given.
+";
+
+const DEFAULT_NAMESPACE: &str = "env";
+
+/// Variables of the parent that must not reach the child.
+const WITHHELD: [&str; 2] = ["HODDOR_IDENTITY", "HODDOR_CONFIG_PASSPHRASE"];
+
+pub fn run(args: &[String], config: Option<&Config>) -> Result<ExitCode, String> {
+    let mut vault = None;
+    let mut namespace = DEFAULT_NAMESPACE.to_string();
+    let mut identity = std::env::var("HODDOR_IDENTITY").ok();
+    let mut command = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |option: &str| {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{option} needs a value\n\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--vault" => vault = Some(value("--vault")?),
+            "--namespace" => namespace = value("--namespace")?,
+            "--identity" => identity = Some(value("--identity")?),
+            "--help" | "-h" => {
+                print!("{USAGE}");
+                return Ok(ExitCode::SUCCESS);
+            }
+            "--" => {
+                command.extend(args.by_ref().cloned());
+                break;
+            }
+            other => return Err(format!("Unknown option: {other}\n\n{USAGE}")),
+        }
+    }
+
+    let (Some(vault), Some((program, program_args))) = (vault, command.split_first()) else {
+        return Err(USAGE.to_string());
+    };
+    let identity = identity.ok_or_else(|| format!("No identity given\n\n{USAGE}"))?;
+    let mut private_key = crate::resolve_identity(config, &identity)?;
+
+    let decrypted = block_on(VaultManager::new().read_namespace(&vault, &private_key, &namespace))
+        .map_err(|e| format!("{vault}/{namespace}: {e}"));
+    private_key.zeroize();
+    let mut decrypted = decrypted?;
+
+    let variables = parse_variables(&decrypted);
+    decrypted.zeroize();
+    let mut variables = variables.map_err(|e| format!("{vault}/{namespace}: {e}"))?;
+
+    let mut child = Command::new(program);
+    child.args(program_args).envs(&variables);
+    for name in WITHHELD {
+        child.env_remove(name);
+    }
+    let status = child.status();
+
+    for (mut name, mut value) in std::mem::take(&mut variables) {
+        name.zeroize();
+        value.zeroize();
+    }
+
+    let status = status.map_err(|e| format!("{program}: {e}"))?;
+    Ok(exit_code(status))
+}
+
+fn exit_code(status: std::process::ExitStatus) -> ExitCode {
+    if let Some(code) = status.code() {
+        return code_to_exit(code);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return ExitCode::from(128u8.wrapping_add(signal as u8));
+        }
+    }
+
+    ExitCode::FAILURE
+}
+
+// Windows exit codes are 32 bits wide; those that do not fit in the status
+// of this process are still failures rather than whatever their low byte is.
+fn code_to_exit(code: i32) -> ExitCode {
+    u8::try_from(code).map_or(ExitCode::FAILURE, ExitCode::from)
+}
+
+/// Reads a JSON object of strings, or dotenv text.
+fn parse_variables(bytes: &[u8]) -> Result<BTreeMap<String, String>, String> {
+    if let Ok(variables) = serde_json::from_slice::<BTreeMap<String, String>>(bytes) {
+        return validate(variables);
+    }
+
+    let text = std::str::from_utf8(bytes).map_err(|_| "Namespace is neither JSON nor text")?;
+    let mut variables = BTreeMap::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (name, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("Line {}: expected NAME=value", number + 1))?;
+        variables.insert(name.trim().to_string(), unquote(value.trim()));
+    }
+
+    validate(variables)
+}
+
+fn unquote(value: &str) -> String {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|rest| rest.strip_suffix(quote))
+        {
+            return if quote == '"' {
+                inner.replace("\\n", "\n").replace("\\\"", "\"")
+            } else {
+                inner.to_string()
+            };
+        }
+    }
+
+    // Unquoted values end at an inline comment.
+    value
+        .split_once(" #")
+        .map_or(value, |(value, _)| value.trim_end())
+        .to_string()
+}
+
+fn validate(variables: BTreeMap<String, String>) -> Result<BTreeMap<String, String>, String> {
+    for name in variables.keys() {
+        let valid = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(format!("Invalid variable name: {name}"));
+        }
+    }
+
+    Ok(variables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(text: &str) -> Result<Vec<(String, String)>, String> {
+        parse_variables(text.as_bytes()).map(|variables| variables.into_iter().collect())
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_dotenv_text() {
+        let text = "\
+# database settings
+
+export DB_HOST=localhost
+DB_PORT = 5432 # default port
+  DB_USER='admin # not a comment'
+DB_PASS=\"multi\\nline \\\"quoted\\\"\"
+EMPTY=
+";
+
+        assert_eq!(
+            parsed(text).unwrap(),
+            pairs(&[
+                ("DB_HOST", "localhost"),
+                ("DB_PASS", "multi\nline \"quoted\""),
+                ("DB_PORT", "5432"),
+                ("DB_USER", "admin # not a comment"),
+                ("EMPTY", ""),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_json_object() {
+        assert_eq!(
+            parsed(r#"{"API_KEY": "abc", "_PRIVATE": "x=y"}"#).unwrap(),
+            pairs(&[("API_KEY", "abc"), ("_PRIVATE", "x=y")])
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_input() {
+        assert_eq!(
+            parsed("VALID=1\nnot a variable\n").unwrap_err(),
+            "Line 2: expected NAME=value"
+        );
+        for text in ["1ST=value", "WITH-DASH=value", "=value", r#"{"a b": "c"}"#] {
+            assert!(
+                parsed(text)
+                    .unwrap_err()
+                    .starts_with("Invalid variable name"),
+                "{text}"
+            );
+        }
+        assert!(parse_variables(&[0xff, 0xfe]).is_err());
+    }
+
+    #[test]
+    fn test_unquote() {
+        assert_eq!(unquote("\"a # b\""), "a # b");
+        assert_eq!(unquote("'raw\\n'"), "raw\\n");
+        assert_eq!(unquote("plain # comment"), "plain");
+        assert_eq!(unquote("no#comment"), "no#comment");
+        assert_eq!(unquote("\"unterminated"), "\"unterminated");
+        assert_eq!(unquote("\""), "\"");
+    }
+
+    #[test]
+    fn test_exit_codes_outside_a_byte_are_failures() {
+        assert_eq!(code_to_exit(0), ExitCode::SUCCESS);
+        assert_eq!(code_to_exit(3), ExitCode::from(3));
+        assert_eq!(code_to_exit(255), ExitCode::from(255));
+        assert_eq!(code_to_exit(256), ExitCode::FAILURE);
+        assert_eq!(code_to_exit(-1), ExitCode::FAILURE);
+    }
+}
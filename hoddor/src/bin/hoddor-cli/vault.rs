@@ -0,0 +1,244 @@
+//! Vault management commands: create, upsert, read, list, export, import
+//! and rekey.
+//!
+//! Namespace payloads go through stdin and stdout as raw bytes, so any
+//! file format can be stored and piped back out unchanged.
+
+use crate::output::{self, Output};
+use futures::executor::block_on;
+use hoddor::facades::native::{Config, ConfigFile, IdentityHandle, VaultManager};
+use hoddor::Platform;
+use serde_json::json;
+use std::io::{Read, Write};
+use std::process::ExitCode;
+
+pub const COMMANDS: [&str; 7] = [
+    "create", "upsert", "read", "list", "export", "import", "rekey",
+];
+
+const USAGE: &str = "\
+Usage: hoddor-cli create <vault>
+       hoddor-cli upsert <vault> <namespace> [--identity <key or label>]
+                         [--expires-in <seconds>] [--no-replace] < data
+       hoddor-cli read <vault> <namespace> [--identity <key or label>] > data
+       hoddor-cli list [<vault>]
+       hoddor-cli export <vault> > export
+       hoddor-cli import <vault> < export
+       hoddor-cli rekey <vault> [--identity <key or label>] [--save-as <label>]
+
+upsert stores stdin in <namespace>, encrypted to the identity; read prints
+the decrypted namespace. list prints the vaults, or the namespaces of
+<vault>. export and import move a whole vault as the VAULT1 container
+written by export_vault. rekey re-encrypts the vault to an identity derived
+from the passphrase in HODDOR_NEW_PASSPHRASE, or the first line of stdin,
+and forgets the old one; the new identity is stored in the config file
+under --save-as, and its private key printed otherwise.
+
+The identity is read from HODDOR_IDENTITY when --identity is not given. All
+commands but read and export take --output table|json.
+";
+
+#[derive(Default)]
+struct Options<'a> {
+    operands: Vec<&'a str>,
+    identity: Option<String>,
+    expires_in: Option<i64>,
+    no_replace: bool,
+    save_as: Option<String>,
+    output: Output,
+}
+
+pub fn run(command: &str, args: &[String], config: Option<&Config>) -> Result<ExitCode, String> {
+    let mut options = Options {
+        identity: std::env::var("HODDOR_IDENTITY").ok(),
+        output: Output::from_env()?,
+        ..Options::default()
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if options.output.parse_option(arg, &mut args, USAGE)? {
+            continue;
+        }
+        let mut value = |option: &str| {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{option} needs a value\n\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--identity" => options.identity = Some(value("--identity")?),
+            "--expires-in" => {
+                options.expires_in = Some(
+                    value("--expires-in")?
+                        .parse()
+                        .map_err(|_| format!("--expires-in takes seconds\n\n{USAGE}"))?,
+                );
+            }
+            "--no-replace" => options.no_replace = true,
+            "--save-as" => options.save_as = Some(value("--save-as")?),
+            "--help" | "-h" => {
+                print!("{USAGE}");
+                return Ok(ExitCode::SUCCESS);
+            }
+            option if option.starts_with("--") => {
+                return Err(format!("Unknown option: {option}\n\n{USAGE}"));
+            }
+            operand => options.operands.push(operand),
+        }
+    }
+
+    let manager = VaultManager::new();
+    match (command, &options.operands[..]) {
+        ("create", [vault]) => {
+            block_on(manager.create_vault(vault)).map_err(|e| format!("{vault}: {e}"))?;
+            report(&options, json!({ "vault": vault }), || {
+                println!("Created {vault}")
+            })
+        }
+        ("upsert", [vault, namespace]) => {
+            let identity = identity(&options, config)?;
+            let data = read_stdin()?;
+            let size = data.len();
+            block_on(manager.upsert_namespace(
+                vault,
+                &identity.public_key(),
+                namespace,
+                data,
+                options.expires_in,
+                !options.no_replace,
+            ))
+            .map_err(|e| format!("{vault}/{namespace}: {e}"))?;
+            report(
+                &options,
+                json!({ "vault": vault, "namespace": namespace, "size": size }),
+                || println!("Stored {size} bytes in {vault}/{namespace}"),
+            )
+        }
+        ("read", [vault, namespace]) => {
+            let identity = identity(&options, config)?;
+            let data = block_on(manager.read_namespace(vault, &identity.private_key(), namespace))
+                .map_err(|e| format!("{vault}/{namespace}: {e}"))?;
+            write_stdout(&data)
+        }
+        ("list", []) => {
+            let vaults = block_on(manager.list_vaults()).map_err(|e| e.to_string())?;
+            print_names(&options, &vaults)
+        }
+        ("list", [vault]) => {
+            let namespaces =
+                block_on(manager.list_namespaces(vault)).map_err(|e| format!("{vault}: {e}"))?;
+            print_names(&options, &namespaces)
+        }
+        ("export", [vault]) => {
+            let bytes =
+                block_on(manager.export_vault(vault)).map_err(|e| format!("{vault}: {e}"))?;
+            write_stdout(&bytes)
+        }
+        ("import", [vault]) => {
+            let bytes = read_stdin()?;
+            block_on(manager.import_vault(vault, &bytes)).map_err(|e| format!("{vault}: {e}"))?;
+            report(&options, json!({ "vault": vault }), || {
+                println!("Imported {vault}")
+            })
+        }
+        ("rekey", [vault]) => rekey(&manager, vault, &options, config),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn rekey(
+    manager: &VaultManager,
+    vault: &str,
+    options: &Options,
+    config: Option<&Config>,
+) -> Result<ExitCode, String> {
+    let old_identity = identity(options, config)?;
+    let passphrase = match std::env::var("HODDOR_NEW_PASSPHRASE") {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            let mut line = String::new();
+            std::io::stdin()
+                .read_line(&mut line)
+                .map_err(|e| e.to_string())?;
+            line.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+
+    let (public_key, private_key) =
+        block_on(manager.rotate_vault_identity(vault, &old_identity.private_key(), &passphrase))
+            .map_err(|e| format!("{vault}: {e}"))?;
+
+    if let Some(label) = &options.save_as {
+        let platform = Platform::new();
+        let file = ConfigFile::locate().map_err(|e| e.to_string())?;
+        let mut config = block_on(file.load(&platform)).map_err(|e| e.to_string())?;
+        let identity = IdentityHandle::from_private_key(&private_key).map_err(|e| e.to_string())?;
+        config
+            .add_identity(label, &identity)
+            .map_err(|e| e.to_string())?;
+        block_on(file.save(&platform, &config)).map_err(|e| e.to_string())?;
+
+        return report(
+            options,
+            json!({ "vault": vault, "publicKey": public_key, "label": label }),
+            || println!("{public_key}"),
+        );
+    }
+
+    report(
+        options,
+        json!({ "vault": vault, "publicKey": public_key, "privateKey": private_key }),
+        || println!("{private_key}"),
+    )
+}
+
+fn identity(options: &Options, config: Option<&Config>) -> Result<IdentityHandle, String> {
+    let identity = options
+        .identity
+        .as_deref()
+        .ok_or_else(|| format!("No identity given\n\n{USAGE}"))?;
+
+    IdentityHandle::from_private_key(&crate::resolve_identity(config, identity)?)
+        .map_err(|e| e.to_string())
+}
+
+fn report(
+    options: &Options,
+    value: serde_json::Value,
+    print_table: impl FnOnce(),
+) -> Result<ExitCode, String> {
+    if options.output.is_json() {
+        output::print_json(&value)?;
+    } else {
+        print_table();
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_names(options: &Options, names: &[String]) -> Result<ExitCode, String> {
+    if options.output.is_json() {
+        output::print_json(&names)?;
+    } else {
+        for name in names {
+            println!("{name}");
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn read_stdin() -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    std::io::stdin()
+        .read_to_end(&mut data)
+        .map_err(|e| e.to_string())?;
+    Ok(data)
+}
+
+fn write_stdout(data: &[u8]) -> Result<ExitCode, String> {
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(data)
+        .and_then(|()| stdout.flush())
+        .map_err(|e| e.to_string())?;
+    Ok(ExitCode::SUCCESS)
+}
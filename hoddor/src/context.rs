@@ -0,0 +1,331 @@
+//! Per-embedder registries of the sync and graph state.
+//!
+//! Everything that used to be process-global (sync managers, app message
+//! handlers, signaling clients and the graph database) hangs off a
+//! [`Context`]. The flat wasm functions share [`default_context`]; embedders
+//! that must not interfere with each other create their own and dispose of it
+//! explicitly. Vault storage is per origin and stays shared.
+
+use crate::platform::Platform;
+use std::cell::Cell;
+use std::rc::Rc;
+use wasm_bindgen::JsValue;
+
+#[cfg(feature = "sync")]
+use crate::signaling::SignalingManager;
+#[cfg(feature = "sync")]
+use crate::sync::{AppMessage, SyncManager};
+#[cfg(feature = "sync")]
+use js_sys::{Function, Object, Reflect};
+#[cfg(feature = "sync")]
+use std::cell::RefCell;
+#[cfg(feature = "sync")]
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature = "graph")]
+use crate::adapters::Graph;
+
+thread_local! {
+    static DEFAULT_CONTEXT: Rc<Context> = Rc::new(Context::with_id(0));
+    static NEXT_CONTEXT_ID: Cell<u32> = const { Cell::new(1) };
+}
+
+/// Context behind the flat wasm API.
+pub fn default_context() -> Rc<Context> {
+    DEFAULT_CONTEXT.with(Rc::clone)
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PeerState {
+    pub vault_name: String,
+    pub peer_id: String,
+    pub connected: bool,
+}
+
+pub struct Context {
+    id: u32,
+    #[cfg(feature = "sync")]
+    sync_managers: RefCell<HashMap<String, Rc<RefCell<SyncManager>>>>,
+    #[cfg(feature = "sync")]
+    app_message_handlers: RefCell<HashMap<String, Function>>,
+    #[cfg(feature = "sync")]
+    signaling: SignalingManager,
+    #[cfg(feature = "sync")]
+    pairing_sessions: RefCell<HashSet<String>>,
+    #[cfg(feature = "graph")]
+    graph: Option<Graph>,
+}
+
+impl Context {
+    fn with_id(id: u32) -> Self {
+        Self {
+            id,
+            #[cfg(feature = "sync")]
+            sync_managers: RefCell::new(HashMap::new()),
+            #[cfg(feature = "sync")]
+            app_message_handlers: RefCell::new(HashMap::new()),
+            #[cfg(feature = "sync")]
+            signaling: SignalingManager::new(),
+            #[cfg(feature = "sync")]
+            pairing_sessions: RefCell::new(HashSet::new()),
+            #[cfg(feature = "graph")]
+            graph: None,
+        }
+    }
+
+    /// Creates a context isolated from the default one and from every other
+    /// context, with its own in-memory graph database.
+    pub fn new() -> Result<Rc<Self>, JsValue> {
+        let id = NEXT_CONTEXT_ID.with(|next| {
+            let id = next.get();
+            next.set(id.wrapping_add(1).max(1));
+            id
+        });
+
+        #[allow(unused_mut)]
+        let mut context = Self::with_id(id);
+
+        #[cfg(feature = "graph")]
+        {
+            context.graph = Some(Graph::isolated().map_err(|e| JsValue::from_str(&e.to_string()))?);
+        }
+
+        Ok(Rc::new(context))
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.id == 0
+    }
+
+    /// Platform whose graph is the context's own.
+    pub fn platform(&self) -> Platform {
+        let platform = Platform::new();
+
+        #[cfg(feature = "graph")]
+        if let Some(graph) = &self.graph {
+            return platform.with_graph(graph.clone());
+        }
+
+        platform
+    }
+
+    #[cfg(feature = "sync")]
+    pub fn signaling(&self) -> &SignalingManager {
+        &self.signaling
+    }
+
+    #[cfg(feature = "sync")]
+    pub fn sync_manager(&self, vault_name: &str) -> Rc<RefCell<SyncManager>> {
+        self.sync_managers
+            .borrow_mut()
+            .entry(vault_name.to_string())
+            .or_insert_with(|| Rc::new(RefCell::new(SyncManager::new(vault_name.to_string()))))
+            .clone()
+    }
+
+    /// Sync operations queued by the context and bytes of payload they hold.
+    #[cfg(feature = "sync")]
+    pub fn pending_sync(&self) -> (usize, usize) {
+        self.sync_managers
+            .borrow()
+            .values()
+            .map(|manager| {
+                let manager = manager.borrow();
+                (manager.pending_operations.len(), manager.pending_bytes())
+            })
+            .fold((0, 0), |(operations, bytes), (o, b)| {
+                (operations + o, bytes + b)
+            })
+    }
+
+    /// Peers of every sync manager of the context, ordered by vault and
+    /// peer id.
+    #[cfg(feature = "sync")]
+    pub fn peer_states(&self) -> Vec<PeerState> {
+        let mut states: Vec<PeerState> = self
+            .sync_managers
+            .borrow()
+            .iter()
+            .flat_map(|(vault_name, manager)| {
+                manager
+                    .borrow()
+                    .peers
+                    .iter()
+                    .map(|(peer_id, peer)| PeerState {
+                        vault_name: vault_name.clone(),
+                        peer_id: peer_id.clone(),
+                        connected: peer.borrow().is_connected(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        states.sort_by(|a, b| (&a.vault_name, &a.peer_id).cmp(&(&b.vault_name, &b.peer_id)));
+        states
+    }
+
+    #[cfg(feature = "sync")]
+    pub fn set_app_message_handler(&self, vault_name: &str, handler: Option<Function>) {
+        let mut handlers = self.app_message_handlers.borrow_mut();
+        match handler {
+            Some(handler) => {
+                handlers.insert(vault_name.to_string(), handler);
+            }
+            None => {
+                handlers.remove(vault_name);
+            }
+        }
+    }
+
+    /// Hands `message`, received from peer `from`, to the handler of its
+    /// vault. The author it claims must be `from`, who must be allowed to
+    /// send on its namespace.
+    #[cfg(feature = "sync")]
+    pub fn dispatch_app_message(&self, message: AppMessage, from: &str) -> Result<(), JsValue> {
+        if message.author != from {
+            return Err(JsValue::from_str(&format!(
+                "Peer {} sent a message claiming to be from {}",
+                from, message.author
+            )));
+        }
+
+        let manager = self.sync_manager(&message.vault_name);
+
+        let allowed = {
+            let manager = manager.borrow();
+            manager
+                .peers
+                .get(from)
+                .map(|peer| manager.can_receive_app_message(&message, &peer.borrow()))
+                .unwrap_or(false)
+        };
+
+        if !allowed {
+            return Err(JsValue::from_str(&format!(
+                "Peer {} is not allowed to send messages on namespace {}",
+                message.author, message.namespace
+            )));
+        }
+
+        let handler = self
+            .app_message_handlers
+            .borrow()
+            .get(&message.vault_name)
+            .cloned();
+
+        if let Some(handler) = handler {
+            let event = Object::new();
+            Reflect::set(&event, &"vaultName".into(), &message.vault_name.into())?;
+            Reflect::set(&event, &"namespace".into(), &message.namespace.into())?;
+            Reflect::set(&event, &"from".into(), &message.author.into())?;
+            Reflect::set(
+                &event,
+                &"timestamp".into(),
+                &(message.timestamp as f64).into(),
+            )?;
+            Reflect::set(
+                &event,
+                &"data".into(),
+                &crate::facades::wasm::converters::bytes_to_js_value(&message.payload)?,
+            )?;
+            handler.call1(&JsValue::NULL, &event)?;
+        }
+
+        Ok(())
+    }
+
+    /// Transport session pairing `vault_name` within this context. Sessions
+    /// of the default context keep the bare vault name.
+    #[cfg(feature = "sync")]
+    pub fn pairing_session(&self, vault_name: &str) -> String {
+        let session = self.session_name(vault_name);
+        self.pairing_sessions.borrow_mut().insert(session.clone());
+        session
+    }
+
+    #[cfg(feature = "sync")]
+    fn session_name(&self, vault_name: &str) -> String {
+        if self.is_default() {
+            vault_name.to_string()
+        } else {
+            format!("{}/{vault_name}", self.id)
+        }
+    }
+
+    /// Closes the peers and pairing session of `vault_name` and forgets its
+    /// sync manager and app message handler, e.g. once the vault was
+    /// renamed: peers address it by the name it was paired under, so it is
+    /// paired again under its new one.
+    #[cfg(feature = "sync")]
+    pub fn forget_vault(&self, vault_name: &str) {
+        if let Some(manager) = self.sync_managers.borrow_mut().remove(vault_name) {
+            for peer in manager.borrow().peers.values() {
+                peer.borrow().close();
+            }
+        }
+        self.app_message_handlers.borrow_mut().remove(vault_name);
+
+        let session = self.session_name(vault_name);
+        if self.pairing_sessions.borrow_mut().remove(&session) {
+            Platform::new().transport().close(&session);
+        }
+    }
+
+    /// Closes every peer, signaling socket and pairing session opened
+    /// through the context and forgets its sync state. The graph database
+    /// is freed once the last object holding the context is gone.
+    pub fn dispose(&self) {
+        #[cfg(feature = "sync")]
+        self.dispose_sync();
+    }
+
+    #[cfg(feature = "sync")]
+    fn dispose_sync(&self) {
+        let platform = Platform::new();
+
+        for (_, manager) in self.sync_managers.borrow_mut().drain() {
+            for peer in manager.borrow().peers.values() {
+                peer.borrow().close();
+            }
+        }
+        self.app_message_handlers.borrow_mut().clear();
+        self.signaling.close_all();
+
+        for session in self.pairing_sessions.borrow_mut().drain() {
+            platform.transport().close(&session);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "sync"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_forget_vault_drops_its_sync_state() {
+        let context = Context::new().unwrap();
+        let manager = context.sync_manager("forget_test");
+        context.sync_manager("forget_test_other");
+        context.set_app_message_handler("forget_test", Some(Function::new_no_args("")));
+        let session = context.pairing_session("forget_test");
+
+        context.forget_vault("forget_test");
+
+        assert!(!context.sync_managers.borrow().contains_key("forget_test"));
+        assert!(context
+            .sync_managers
+            .borrow()
+            .contains_key("forget_test_other"));
+        assert!(context.app_message_handlers.borrow().is_empty());
+        assert!(!context.pairing_sessions.borrow().contains(&session));
+        // A later sync of the old name starts from a fresh manager.
+        assert!(!Rc::ptr_eq(&manager, &context.sync_manager("forget_test")));
+    }
+}
@@ -0,0 +1,136 @@
+use super::types::IdentityKeys;
+use crate::platform::Platform;
+use argon2::{Algorithm, Argon2, Params, Version};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use zeroize::Zeroize;
+
+type CacheKey = [u8; 32];
+
+/// How long a derived identity is kept, in milliseconds.
+pub const IDENTITY_CACHE_TTL_MS: f64 = 15.0 * 60.0 * 1000.0;
+
+const CACHE_SALT_CONTEXT: &str = "hoddor 2025-01-01 identity cache key v1";
+
+// Far cheaper than the derivation of the identity itself, which is what the
+// cache saves, but still an Argon2 evaluation per guess for anyone reading
+// the keys out of memory.
+const CACHE_KEY_MEMORY_KIB: u32 = 4 * 1024;
+const CACHE_KEY_ITERATIONS: u32 = 1;
+
+struct CachedIdentity {
+    identity: IdentityKeys,
+    expires_at: f64,
+}
+
+impl Drop for CachedIdentity {
+    fn drop(&mut self) {
+        self.identity.private_key.zeroize();
+    }
+}
+
+// Identities derived during this session, keyed by an Argon2 hash of the
+// salt and passphrase so the passphrase itself is never kept around.
+static CACHE: Lazy<Mutex<HashMap<CacheKey, CachedIdentity>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn cache_key(salt: &[u8], passphrase: &str) -> Option<CacheKey> {
+    let params = Params::new(CACHE_KEY_MEMORY_KIB, CACHE_KEY_ITERATIONS, 1, Some(32)).ok()?;
+    let salt = blake3::derive_key(CACHE_SALT_CONTEXT, salt);
+
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+        .ok()?;
+    Some(key)
+}
+
+fn lookup(key: &CacheKey, now: f64) -> Option<IdentityKeys> {
+    let mut cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    cache.retain(|_, entry| entry.expires_at > now);
+    cache.get(key).map(|entry| entry.identity.clone())
+}
+
+fn insert(key: CacheKey, identity: &IdentityKeys, now: f64) {
+    CACHE.lock().unwrap_or_else(PoisonError::into_inner).insert(
+        key,
+        CachedIdentity {
+            identity: identity.clone(),
+            expires_at: now + IDENTITY_CACHE_TTL_MS,
+        },
+    );
+}
+
+pub fn cached_identity(platform: &Platform, salt: &[u8], passphrase: &str) -> Option<IdentityKeys> {
+    lookup(&cache_key(salt, passphrase)?, platform.clock().now())
+}
+
+pub fn cache_identity(platform: &Platform, salt: &[u8], passphrase: &str, identity: &IdentityKeys) {
+    if let Some(key) = cache_key(salt, passphrase) {
+        insert(key, identity, platform.clock().now());
+    }
+}
+
+/// Forgets the identity of `public_key`, e.g. once it has been rotated out.
+pub fn evict_identity(public_key: &str) {
+    CACHE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .retain(|_, entry| entry.identity.public_key != public_key);
+}
+
+/// Number of cached identities and bytes of key material they hold.
+pub fn identity_cache_usage() -> (usize, usize) {
+    let cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    let bytes = cache
+        .values()
+        .map(|entry| entry.identity.public_key.len() + entry.identity.private_key.len())
+        .sum();
+
+    (cache.len(), bytes)
+}
+
+/// Forgets every identity derived so far, e.g. when the user locks the app.
+pub fn clear_identity_cache() {
+    CACHE.lock().unwrap_or_else(PoisonError::into_inner).clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_is_keyed_by_salt_and_passphrase() {
+        let platform = Platform::new();
+        let identity = IdentityKeys::new("age1cached".to_string(), "AGE-SECRET".to_string());
+        let salt = [42u8; 32];
+
+        cache_identity(&platform, &salt, "cache passphrase", &identity);
+
+        assert_eq!(
+            cached_identity(&platform, &salt, "cache passphrase").map(|keys| keys.public_key),
+            Some("age1cached".to_string())
+        );
+        assert!(cached_identity(&platform, &salt, "other passphrase").is_none());
+        assert!(cached_identity(&platform, &[7u8; 32], "cache passphrase").is_none());
+
+        let (entries, bytes) = identity_cache_usage();
+        assert!(entries >= 1);
+        assert!(bytes >= "age1cached".len() + "AGE-SECRET".len());
+
+        evict_identity("age1cached");
+        assert!(cached_identity(&platform, &salt, "cache passphrase").is_none());
+    }
+
+    #[test]
+    fn test_cached_identities_expire() {
+        let identity = IdentityKeys::new("age1expiring".to_string(), "AGE-SECRET".to_string());
+        let key = cache_key(&[43u8; 32], "expiring passphrase").unwrap();
+
+        insert(key, &identity, 1_000.0);
+
+        assert!(lookup(&key, 1_000.0 + IDENTITY_CACHE_TTL_MS - 1.0).is_some());
+        assert!(lookup(&key, 1_000.0 + IDENTITY_CACHE_TTL_MS).is_none());
+    }
+}
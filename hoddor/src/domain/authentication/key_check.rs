@@ -0,0 +1,59 @@
+use crate::domain::crypto::mac::hmac_sha256;
+use crate::domain::vault::types::IdentitySalts;
+use hmac::Mac;
+
+const KEY_CHECK_CONSTANT: &[u8] = b"hoddor/key-check/v1";
+const KEY_CHECK_LEN: usize = 16;
+
+/// MAC over a fixed constant keyed by the identity's private key. It can only
+/// be reproduced once the identity has been derived, so it adds no shortcut
+/// around Argon2 for someone guessing passphrases.
+pub fn key_check_value(private_key: &str) -> String {
+    let mut mac = hmac_sha256(private_key.as_bytes());
+    mac.update(KEY_CHECK_CONSTANT);
+    hex::encode(&mac.finalize().into_bytes()[..KEY_CHECK_LEN])
+}
+
+pub fn record_key_check(salts: &mut IdentitySalts, public_key: &str, private_key: &str) {
+    salts.set_key_check(public_key.to_string(), key_check_value(private_key));
+}
+
+/// Checks `private_key` against the key check value stored for `public_key`.
+/// Returns `None` for identities created before key check values existed.
+pub fn verify_key_check(
+    salts: &IdentitySalts,
+    public_key: &str,
+    private_key: &str,
+) -> Option<bool> {
+    let stored = salts.get_key_check(public_key)?;
+    let expected = hex::decode(stored).ok()?;
+
+    let mut mac = hmac_sha256(private_key.as_bytes());
+    mac.update(KEY_CHECK_CONSTANT);
+
+    Some(mac.verify_truncated_left(&expected).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_check_roundtrip() {
+        let mut salts = IdentitySalts::new();
+        record_key_check(&mut salts, "age1owner", "AGE-SECRET-KEY-OWNER");
+
+        assert_eq!(
+            verify_key_check(&salts, "age1owner", "AGE-SECRET-KEY-OWNER"),
+            Some(true)
+        );
+        assert_eq!(
+            verify_key_check(&salts, "age1owner", "AGE-SECRET-KEY-OTHER"),
+            Some(false)
+        );
+        assert_eq!(
+            verify_key_check(&salts, "age1unknown", "AGE-SECRET-KEY-OWNER"),
+            None
+        );
+    }
+}
@@ -1,7 +1,11 @@
 pub mod error;
+pub mod identity_cache;
+pub mod key_check;
 pub mod operations;
 pub mod types;
 
 pub use error::AuthenticationError;
-pub use operations::{derive_vault_identity, generate_random_identity};
+pub use identity_cache::{clear_identity_cache, identity_cache_usage};
+pub use key_check::{key_check_value, record_key_check, verify_key_check};
+pub use operations::{create_vault_identity, derive_vault_identity, generate_random_identity};
 pub use types::IdentityKeys;
@@ -1,4 +1,6 @@
 use super::error::AuthenticationError;
+use super::identity_cache;
+use super::key_check;
 use super::types::IdentityKeys;
 use crate::domain::vault::types::Vault;
 use crate::domain::vault::validation::validate_passphrase;
@@ -6,6 +8,7 @@ use crate::platform::Platform;
 use argon2::password_hash::rand_core::OsRng;
 use rand::RngCore;
 
+#[tracing::instrument(skip_all, fields(vault = _vault_name))]
 pub async fn derive_vault_identity(
     platform: &Platform,
     passphrase: &str,
@@ -15,62 +18,115 @@ pub async fn derive_vault_identity(
     validate_passphrase(passphrase)
         .map_err(|e| AuthenticationError::InvalidPassphrase(e.to_string()))?;
 
-    for (stored_pubkey, salt) in vault.identity_salts.iter() {
-        platform
-            .logger()
-            .log(&format!("Checking stored public key: {stored_pubkey}"));
+    let mut matched = None;
 
-        if salt.len() != 32 {
-            platform.logger().error(&format!(
-                "Invalid salt length ({}) for public key: {}",
-                salt.len(),
-                stored_pubkey
-            ));
+    for (stored_pubkey, salt) in vault.identity_salts.iter_hinted_first() {
+        if let Some(identity) = identity_cache::cached_identity(platform, salt, passphrase) {
+            if identity.public_key == *stored_pubkey {
+                tracing::debug!("Found matching identity in cache");
+                matched = Some(identity);
+                break;
+            }
             continue;
         }
 
-        platform.logger().log(&format!("Using salt: {salt:?}"));
+        tracing::debug!(public_key = %stored_pubkey, "Checking stored identity");
+
+        if salt.len() != 32 {
+            tracing::error!(
+                public_key = %stored_pubkey,
+                salt_length = salt.len(),
+                "Invalid salt length"
+            );
+            continue;
+        }
 
         match derive_identity_from_passphrase(platform, passphrase, salt).await {
             Ok(identity) => {
-                platform
-                    .logger()
-                    .log(&format!("Generated public key: {}", identity.public_key));
+                tracing::debug!(public_key = %identity.public_key, "Derived identity");
+                identity_cache::cache_identity(platform, salt, passphrase, &identity);
                 if identity.public_key == *stored_pubkey {
-                    platform.logger().log("Found matching identity");
-                    return Ok(identity);
+                    tracing::debug!("Found matching identity");
+                    matched = Some(identity);
+                    break;
                 } else {
-                    platform
-                        .logger()
-                        .warn("Public key does not match stored salt");
+                    tracing::warn!("Public key does not match stored salt");
                 }
             }
             Err(err) => {
-                platform.logger().warn(&format!(
-                    "Failed to generate identity with stored salt for public key {stored_pubkey}: {err:?}"
-                ));
+                tracing::warn!(
+                    public_key = %stored_pubkey,
+                    error = ?err,
+                    "Failed to derive identity with stored salt"
+                );
             }
         }
     }
 
-    platform
-        .logger()
-        .log("No matching identity found; generating new salt");
+    if let Some(identity) = matched {
+        if vault.identity_salts.last_unlocked() != Some(&identity.public_key) {
+            vault
+                .identity_salts
+                .set_last_unlocked(identity.public_key.clone());
+        }
+        if vault
+            .identity_salts
+            .get_key_check(&identity.public_key)
+            .is_none()
+        {
+            key_check::record_key_check(
+                &mut vault.identity_salts,
+                &identity.public_key,
+                &identity.private_key,
+            );
+        }
+        return Ok(identity);
+    }
+
+    tracing::debug!("No matching identity found; generating new salt");
+    add_vault_identity(platform, passphrase, vault).await
+}
+
+/// Derives an identity from `passphrase` under a fresh salt and records it
+/// in `vault`, even when the passphrase already unlocks another identity.
+pub async fn create_vault_identity(
+    platform: &Platform,
+    passphrase: &str,
+    vault: &mut Vault,
+) -> Result<IdentityKeys, AuthenticationError> {
+    validate_passphrase(passphrase)
+        .map_err(|e| AuthenticationError::InvalidPassphrase(e.to_string()))?;
+
+    add_vault_identity(platform, passphrase, vault).await
+}
+
+async fn add_vault_identity(
+    platform: &Platform,
+    passphrase: &str,
+    vault: &mut Vault,
+) -> Result<IdentityKeys, AuthenticationError> {
     let mut new_salt = [0u8; 32];
     OsRng.fill_bytes(&mut new_salt);
 
     let identity = derive_identity_from_passphrase(platform, passphrase, &new_salt)
         .await
         .map_err(|e| {
-            platform
-                .logger()
-                .error(&format!("Failed to create new identity: {e:?}"));
+            tracing::error!(error = ?e, "Failed to create new identity");
             e
         })?;
 
+    identity_cache::cache_identity(platform, &new_salt, passphrase, &identity);
     vault
         .identity_salts
         .set_salt(identity.public_key.clone(), new_salt);
+    vault
+        .identity_salts
+        .set_last_unlocked(identity.public_key.clone());
+    key_check::record_key_check(
+        &mut vault.identity_salts,
+        &identity.public_key,
+        &identity.private_key,
+    );
 
     Ok(identity)
 }
@@ -90,9 +146,7 @@ async fn derive_identity_from_passphrase(
     let identity_str = crate::domain::crypto::identity_from_passphrase(platform, passphrase, salt)
         .await
         .map_err(|e| {
-            platform
-                .logger()
-                .log(&format!("Failed to derive identity: {e}"));
+            tracing::debug!(error = %e, "Failed to derive identity");
             AuthenticationError::DerivationFailed(e.to_string())
         })?;
 
@@ -130,6 +184,54 @@ pub fn generate_random_identity(platform: &Platform) -> Result<IdentityKeys, Aut
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_derive_vault_identity_records_last_unlocked() {
+        use futures::executor::block_on;
+
+        let platform = Platform::new();
+        let mut vault = block_on(crate::domain::vault::create_vault()).unwrap();
+
+        let first = block_on(derive_vault_identity(
+            &platform,
+            "first passphrase",
+            "hint_vault",
+            &mut vault,
+        ))
+        .unwrap();
+        let second = block_on(derive_vault_identity(
+            &platform,
+            "second passphrase",
+            "hint_vault",
+            &mut vault,
+        ))
+        .unwrap();
+        assert_eq!(
+            vault.identity_salts.last_unlocked(),
+            Some(&second.public_key)
+        );
+
+        let again = block_on(derive_vault_identity(
+            &platform,
+            "first passphrase",
+            "hint_vault",
+            &mut vault,
+        ))
+        .unwrap();
+        assert_eq!(again.public_key, first.public_key);
+        assert_eq!(
+            vault.identity_salts.last_unlocked(),
+            Some(&first.public_key)
+        );
+        assert_eq!(
+            vault
+                .identity_salts
+                .iter_hinted_first()
+                .next()
+                .map(|(pk, _)| pk),
+            Some(&first.public_key)
+        );
+    }
+
     #[test]
     fn test_identity_keys_creation() {
         let keys = IdentityKeys::new(
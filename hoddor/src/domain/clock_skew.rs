@@ -0,0 +1,77 @@
+/// Estimated offset between a remote peer's clock and the local one.
+///
+/// Every message yields `received_at - sent_at`, which is the offset plus
+/// the transit delay. The delay is never negative, so the smallest sample
+/// seen so far is the closest estimate of the offset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClockSkew {
+    offset_ms: Option<i64>,
+}
+
+impl ClockSkew {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a message sent at `sent_at_ms` on the remote clock and
+    /// received at `received_at_ms` on the local one.
+    pub fn observe(&mut self, sent_at_ms: u64, received_at_ms: u64) {
+        let sample = received_at_ms as i64 - sent_at_ms as i64;
+
+        self.offset_ms = Some(match self.offset_ms {
+            Some(offset) => offset.min(sample),
+            None => sample,
+        });
+    }
+
+    /// Milliseconds to add to a remote timestamp to read it on the local
+    /// clock; zero until a message has been observed.
+    pub fn offset_ms(&self) -> i64 {
+        self.offset_ms.unwrap_or(0)
+    }
+
+    /// Converts a remote Unix timestamp in seconds to the local clock.
+    pub fn to_local_seconds(&self, remote_seconds: i64) -> i64 {
+        remote_seconds + (self.offset_ms() as f64 / 1000.0).round() as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unobserved_skew_is_zero() {
+        let skew = ClockSkew::new();
+
+        assert_eq!(skew.offset_ms(), 0);
+        assert_eq!(skew.to_local_seconds(1000), 1000);
+    }
+
+    #[test]
+    fn test_remote_clock_behind() {
+        let mut skew = ClockSkew::new();
+        skew.observe(1_000_000, 1_600_050);
+
+        assert_eq!(skew.offset_ms(), 600_050);
+        assert_eq!(skew.to_local_seconds(2000), 2600);
+    }
+
+    #[test]
+    fn test_remote_clock_ahead() {
+        let mut skew = ClockSkew::new();
+        skew.observe(1_600_000, 1_000_020);
+
+        assert_eq!(skew.to_local_seconds(2000), 1400);
+    }
+
+    #[test]
+    fn test_keeps_fastest_sample() {
+        let mut skew = ClockSkew::new();
+        skew.observe(10_000, 15_400);
+        skew.observe(20_000, 25_010);
+        skew.observe(30_000, 35_900);
+
+        assert_eq!(skew.offset_ms(), 5_010);
+    }
+}
@@ -1,10 +1,48 @@
 use std::fmt;
 
+/// Why age refused to decrypt a payload. The adapter maps age's errors to
+/// these without formatting them: age renders its messages through a
+/// localization loader that does not work in wasm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecryptFailure {
+    /// None of the identities unwraps the file key: wrong passphrase or key.
+    NoMatchingKey,
+    /// The header or payload fails authentication, or is truncated.
+    Corrupted,
+    /// No readable age header, or one from a newer age version.
+    UnknownFormat,
+    /// Passphrase-encrypted with a work factor too high for this device.
+    ExcessiveWork,
+}
+
+impl DecryptFailure {
+    pub fn code(&self) -> &'static str {
+        match self {
+            DecryptFailure::NoMatchingKey => "no_matching_key",
+            DecryptFailure::Corrupted => "corrupted_data",
+            DecryptFailure::UnknownFormat => "unknown_format",
+            DecryptFailure::ExcessiveWork => "excessive_work",
+        }
+    }
+}
+
+impl fmt::Display for DecryptFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecryptFailure::NoMatchingKey => write!(f, "no identity matches the data"),
+            DecryptFailure::Corrupted => write!(f, "data is corrupted or truncated"),
+            DecryptFailure::UnknownFormat => write!(f, "unknown data format"),
+            DecryptFailure::ExcessiveWork => write!(f, "key derivation too expensive"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum CryptoError {
     KeyDerivationError(String),
     EncryptionError(String),
     DecryptionError(String),
+    Undecryptable(DecryptFailure),
     InvalidPrfOutput(String),
     InvalidIdentity(String),
     InvalidRecipient(String),
@@ -16,6 +54,7 @@ impl fmt::Display for CryptoError {
             CryptoError::KeyDerivationError(msg) => write!(f, "Key derivation failed: {msg}"),
             CryptoError::EncryptionError(msg) => write!(f, "Encryption failed: {msg}"),
             CryptoError::DecryptionError(msg) => write!(f, "Decryption failed: {msg}"),
+            CryptoError::Undecryptable(failure) => write!(f, "Decryption failed: {failure}"),
             CryptoError::InvalidPrfOutput(msg) => write!(f, "Invalid PRF output: {msg}"),
             CryptoError::InvalidIdentity(msg) => write!(f, "Invalid identity: {msg}"),
             CryptoError::InvalidRecipient(msg) => write!(f, "Invalid recipient: {msg}"),
@@ -49,4 +88,17 @@ impl CryptoError {
     pub fn invalid_recipient(message: impl Into<String>) -> Self {
         CryptoError::InvalidRecipient(message.into())
     }
+
+    /// Stable identifier of the failure, unlike the message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CryptoError::KeyDerivationError(_) => "key_derivation_failed",
+            CryptoError::EncryptionError(_) => "encryption_failed",
+            CryptoError::DecryptionError(_) => "decryption_failed",
+            CryptoError::Undecryptable(failure) => failure.code(),
+            CryptoError::InvalidPrfOutput(_) => "invalid_prf_output",
+            CryptoError::InvalidIdentity(_) => "invalid_identity",
+            CryptoError::InvalidRecipient(_) => "invalid_recipient",
+        }
+    }
 }
@@ -0,0 +1,11 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+pub type HmacSha256 = Hmac<Sha256>;
+
+/// HMAC-SHA256 keyed with `key`. HMAC hashes or pads keys of any length, so
+/// the error `Mac::new_from_slice` declares for other MACs never occurs.
+#[allow(clippy::expect_used)]
+pub fn hmac_sha256(key: &[u8]) -> HmacSha256 {
+    HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length")
+}
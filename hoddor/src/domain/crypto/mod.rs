@@ -1,8 +1,10 @@
 pub mod error;
+pub mod mac;
 pub mod operations;
+pub mod shamir;
 
-pub use error::CryptoError;
+pub use error::{CryptoError, DecryptFailure};
 pub use operations::{
-    decrypt_with_identity, encrypt_for_recipients, generate_identity, identity_from_passphrase,
-    identity_from_prf, identity_to_public, parse_recipient,
+    decrypt_many, decrypt_with_identity, encrypt_for_recipients, generate_identity,
+    identity_from_passphrase, identity_from_prf, identity_to_public, parse_recipient,
 };
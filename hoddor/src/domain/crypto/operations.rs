@@ -1,6 +1,7 @@
 use super::error::CryptoError;
 use crate::platform::Platform;
 
+#[tracing::instrument(level = "debug", skip_all)]
 pub async fn identity_from_passphrase(
     platform: &Platform,
     passphrase: &str,
@@ -39,6 +40,7 @@ pub fn identity_to_public(platform: &Platform, identity: &str) -> Result<String,
         .map_err(|e| CryptoError::InvalidIdentity(e.to_string()))
 }
 
+#[tracing::instrument(level = "debug", skip_all, fields(bytes = data.len(), recipients = recipients.len()))]
 pub async fn encrypt_for_recipients(
     platform: &Platform,
     data: &[u8],
@@ -51,6 +53,7 @@ pub async fn encrypt_for_recipients(
         .map_err(|e| CryptoError::EncryptionError(e.to_string()))
 }
 
+#[tracing::instrument(level = "debug", skip_all, fields(bytes = encrypted_data.len()))]
 pub async fn decrypt_with_identity(
     platform: &Platform,
     encrypted_data: &[u8],
@@ -60,7 +63,76 @@ pub async fn decrypt_with_identity(
         .encryption()
         .decrypt(encrypted_data, identity)
         .await
-        .map_err(|e| CryptoError::DecryptionError(e.to_string()))
+        .map_err(decryption_error)
+}
+
+/// Keeps the [`CryptoError`] an adapter returned, so its code survives.
+fn decryption_error(error: Box<dyn std::error::Error>) -> CryptoError {
+    match error.downcast::<CryptoError>() {
+        Ok(error) => *error,
+        Err(error) => CryptoError::DecryptionError(error.to_string()),
+    }
+}
+
+/// Decrypts several payloads with the same identity. With the `parallel`
+/// feature the work is spread over the rayon thread pool; on wasm that pool
+/// only exists once `initThreadPool` has run in a cross-origin isolated page,
+/// and is only used from workers.
+#[tracing::instrument(level = "debug", skip_all, fields(payloads = payloads.len()))]
+pub async fn decrypt_many(
+    platform: &Platform,
+    payloads: &[&[u8]],
+    identity: &str,
+) -> Vec<Result<Vec<u8>, CryptoError>> {
+    #[cfg(feature = "parallel")]
+    if may_block_on_pool() {
+        use futures::FutureExt;
+        use rayon::prelude::*;
+
+        let encryption = platform.encryption();
+
+        // Age decryption never suspends, so each worker resolves its future
+        // with a single poll instead of nesting an executor. Adapters that
+        // do suspend are awaited here instead.
+        let polled: Vec<Option<Result<Vec<u8>, CryptoError>>> = payloads
+            .par_iter()
+            .map(|payload| {
+                encryption
+                    .decrypt(payload, identity)
+                    .now_or_never()
+                    .map(|result| result.map_err(decryption_error))
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(payloads.len());
+        for (payload, result) in payloads.iter().zip(polled) {
+            results.push(match result {
+                Some(result) => result,
+                None => decrypt_with_identity(platform, payload, identity).await,
+            });
+        }
+        return results;
+    }
+
+    let mut results = Vec::with_capacity(payloads.len());
+    for payload in payloads {
+        results.push(decrypt_with_identity(platform, payload, identity).await);
+    }
+    results
+}
+
+// Rayon blocks the calling thread until the pool is done, which the main
+// thread of a page is not allowed to do.
+#[cfg(feature = "parallel")]
+fn may_block_on_pool() -> bool {
+    #[cfg(target_arch = "wasm32")]
+    {
+        web_sys::window().is_none()
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        true
+    }
 }
 
 pub fn identity_from_prf(
@@ -107,6 +179,26 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_decrypt_many_preserves_order() {
+        let platform = Platform::new();
+        let identity = generate_identity(&platform).unwrap();
+        let recipient = identity_to_public(&platform, &identity).unwrap();
+
+        let first = block_on(encrypt_for_recipients(&platform, b"first", &[&recipient])).unwrap();
+        let second = block_on(encrypt_for_recipients(&platform, b"second", &[&recipient])).unwrap();
+
+        let results = block_on(decrypt_many(
+            &platform,
+            &[&first, b"not age", &second],
+            &identity,
+        ));
+
+        assert_eq!(results[0].as_deref().unwrap(), b"first");
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_deref().unwrap(), b"second");
+    }
+
     #[test]
     fn test_generate_identity() {
         let platform = Platform::new();
@@ -0,0 +1,160 @@
+//! Shamir secret sharing over GF(2^8).
+//!
+//! Each share is the x coordinate (1..=255) followed by one polynomial
+//! evaluation per secret byte. Any `threshold` shares rebuild the secret;
+//! fewer reveal nothing about it.
+
+use super::error::CryptoError;
+use rand::RngCore;
+
+pub fn split(secret: &[u8], threshold: u8, count: u8) -> Result<Vec<Vec<u8>>, CryptoError> {
+    if secret.is_empty() || threshold == 0 || threshold > count {
+        return Err(CryptoError::encryption_error(format!(
+            "Cannot split a secret into {count} shares with a threshold of {threshold}"
+        )));
+    }
+
+    let mut coefficients = vec![0u8; secret.len() * (threshold as usize - 1)];
+    rand::thread_rng().fill_bytes(&mut coefficients);
+
+    let shares = (1..=count)
+        .map(|x| {
+            let mut share = Vec::with_capacity(secret.len() + 1);
+            share.push(x);
+
+            for (i, &byte) in secret.iter().enumerate() {
+                let higher = coefficients.chunks(secret.len()).map(|row| row[i]);
+
+                // Horner's rule, highest degree first.
+                let y = higher
+                    .rev()
+                    .fold(0u8, |acc, coefficient| gf_mul(acc, x) ^ coefficient);
+                share.push(gf_mul(y, x) ^ byte);
+            }
+
+            share
+        })
+        .collect();
+
+    Ok(shares)
+}
+
+pub fn combine(shares: &[Vec<u8>]) -> Result<Vec<u8>, CryptoError> {
+    let invalid = |reason: &str| CryptoError::decryption_error(format!("Invalid shares: {reason}"));
+
+    let first = shares.first().ok_or_else(|| invalid("none given"))?;
+    if first.len() < 2 || shares.iter().any(|share| share.len() != first.len()) {
+        return Err(invalid("lengths differ"));
+    }
+
+    let xs: Vec<u8> = shares.iter().map(|share| share[0]).collect();
+    for (i, &x) in xs.iter().enumerate() {
+        if x == 0 || xs[..i].contains(&x) {
+            return Err(invalid("duplicate or zero index"));
+        }
+    }
+
+    // Lagrange basis polynomials evaluated at zero.
+    let basis: Vec<u8> = xs
+        .iter()
+        .enumerate()
+        .map(|(i, &xi)| {
+            xs.iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .fold(1u8, |acc, (_, &xj)| gf_mul(acc, gf_div(xj, xj ^ xi)))
+        })
+        .collect();
+
+    let secret = (1..first.len())
+        .map(|byte| {
+            shares
+                .iter()
+                .zip(&basis)
+                .fold(0u8, |acc, (share, &weight)| {
+                    acc ^ gf_mul(share[byte], weight)
+                })
+        })
+        .collect();
+
+    Ok(secret)
+}
+
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+
+    product
+}
+
+fn gf_div(a: u8, b: u8) -> u8 {
+    // b^254 is the inverse of b in GF(2^8).
+    let mut inverse = 1u8;
+    for _ in 0..254 {
+        inverse = gf_mul(inverse, b);
+    }
+
+    gf_mul(a, inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_any_threshold_subset_rebuilds_secret() {
+        let secret = b"AGE-SECRET-KEY-1EXAMPLE".to_vec();
+        let shares = split(&secret, 3, 5).unwrap();
+
+        assert_eq!(combine(&shares[..3]).unwrap(), secret);
+        assert_eq!(combine(&shares[2..]).unwrap(), secret);
+        assert_eq!(
+            combine(&[shares[4].clone(), shares[0].clone(), shares[2].clone()]).unwrap(),
+            secret
+        );
+    }
+
+    #[test]
+    fn test_fewer_shares_than_threshold_do_not_rebuild_secret() {
+        let secret = b"embargoed until spring".to_vec();
+        let shares = split(&secret, 3, 5).unwrap();
+
+        assert_ne!(combine(&shares[..2]).unwrap(), secret);
+    }
+
+    #[test]
+    fn test_threshold_of_one_copies_secret() {
+        let shares = split(b"key", 1, 2).unwrap();
+
+        assert_eq!(&shares[0][1..], b"key");
+        assert_eq!(&shares[1][1..], b"key");
+    }
+
+    #[test]
+    fn test_invalid_parameters_are_rejected() {
+        assert!(split(b"key", 0, 3).is_err());
+        assert!(split(b"key", 4, 3).is_err());
+        assert!(combine(&[]).is_err());
+
+        let shares = split(b"key", 2, 2).unwrap();
+        assert!(combine(&[shares[0].clone(), shares[0].clone()]).is_err());
+    }
+
+    #[test]
+    fn test_field_division_inverts_multiplication() {
+        for a in 1..=255u8 {
+            assert_eq!(gf_div(gf_mul(a, 0x53), 0x53), a);
+        }
+    }
+}
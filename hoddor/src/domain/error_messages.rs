@@ -0,0 +1,99 @@
+//! Translated error messages, looked up by error code.
+//!
+//! Errors keep their English `Display` text; an application that shows
+//! errors to users registers a bundle per locale mapping codes such as
+//! `invalid_password` or `no_matching_key` to its own wording and selects
+//! the locale. Codes missing from the bundle fall back to English. Error
+//! reports are never translated, so crash trackers group them as before.
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+
+#[derive(Default)]
+struct Catalog {
+    locale: Option<String>,
+    bundles: HashMap<String, HashMap<String, String>>,
+}
+
+static CATALOG: Lazy<Mutex<Catalog>> = Lazy::new(|| Mutex::new(Catalog::default()));
+
+/// Adds the messages of `locale`, replacing those registered before for the
+/// same codes.
+pub fn register_error_messages(locale: &str, messages: HashMap<String, String>) {
+    CATALOG
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .bundles
+        .entry(locale.to_ascii_lowercase())
+        .or_default()
+        .extend(messages);
+}
+
+/// Selects the locale errors are translated to, e.g. `fr` or `fr-CA`, which
+/// falls back to `fr`. `None` goes back to English.
+pub fn set_error_locale(locale: Option<&str>) {
+    CATALOG
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .locale = locale.map(str::to_ascii_lowercase);
+}
+
+/// Message for `code` in the selected locale, if its bundle has one.
+pub fn localized_message(code: &str) -> Option<String> {
+    let catalog = CATALOG.lock().unwrap_or_else(PoisonError::into_inner);
+    let locale = catalog.locale.as_deref()?;
+
+    let language = locale.split(['-', '_']).next().unwrap_or(locale);
+    let message = [locale, language]
+        .into_iter()
+        .filter_map(|locale| catalog.bundles.get(locale))
+        .find_map(|bundle| bundle.get(code))
+        .cloned();
+    message
+}
+
+/// `message` translated through its `code`, or as it is.
+pub fn localize(code: &str, message: String) -> String {
+    localized_message(code).unwrap_or(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_falls_back_to_language_then_english() {
+        register_error_messages(
+            "fr",
+            HashMap::from([(
+                "invalid_password".to_string(),
+                "Mot de passe incorrect".to_string(),
+            )]),
+        );
+        register_error_messages(
+            "fr-CA",
+            HashMap::from([(
+                "vault_not_found".to_string(),
+                "Coffre introuvable".to_string(),
+            )]),
+        );
+
+        set_error_locale(Some("fr-CA"));
+        assert_eq!(
+            localized_message("vault_not_found").as_deref(),
+            Some("Coffre introuvable")
+        );
+        assert_eq!(
+            localized_message("invalid_password").as_deref(),
+            Some("Mot de passe incorrect")
+        );
+        assert_eq!(
+            localize("data_expired", "Data has expired".to_string()),
+            "Data has expired"
+        );
+
+        set_error_locale(None);
+        assert_eq!(localized_message("invalid_password"), None);
+    }
+}
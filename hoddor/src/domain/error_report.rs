@@ -0,0 +1,148 @@
+//! Error reports handed to the embedding application.
+//!
+//! Reports leave the vault's trust boundary (they typically end up in a
+//! third-party crash tracker), so their message is scrubbed when built:
+//! quoted values, storage paths, keys and anything that looks like an
+//! encoded secret are replaced, which also removes vault and namespace names
+//! as they only appear in messages inside paths or quotes.
+
+use super::vault::error::VaultError;
+use serde::Serialize;
+
+const REDACTED: &str = "[redacted]";
+
+/// Shortest run of key-like characters treated as an encoded secret. Long
+/// enough to spare ordinary words, short enough to catch 128-bit hex ids.
+const MIN_SECRET_LENGTH: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorReportKind {
+    /// An error returned to the caller of a facade function.
+    Error,
+    /// A panic, which leaves the module unusable until it is reloaded.
+    Panic,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorReport {
+    pub kind: ErrorReportKind,
+    /// Stable identifier of the failure, e.g. `namespace_not_found`.
+    pub code: String,
+    /// Scrubbed description.
+    pub message: String,
+    /// Source location of a panic.
+    pub location: Option<String>,
+}
+
+impl ErrorReport {
+    pub fn error(code: &str, message: &str) -> Self {
+        Self {
+            kind: ErrorReportKind::Error,
+            code: code.to_string(),
+            message: scrub(message),
+            location: None,
+        }
+    }
+
+    pub fn panic(message: &str, location: Option<String>) -> Self {
+        Self {
+            kind: ErrorReportKind::Panic,
+            code: "panic".to_string(),
+            message: scrub(message),
+            location,
+        }
+    }
+}
+
+impl From<&VaultError> for ErrorReport {
+    fn from(error: &VaultError) -> Self {
+        Self::error(error.code(), &error.to_string())
+    }
+}
+
+/// Removes values that may identify user data from `message`.
+pub fn scrub(message: &str) -> String {
+    let mut unquoted = String::with_capacity(message.len());
+    let mut chars = message.chars();
+
+    while let Some(c) = chars.next() {
+        if matches!(c, '\'' | '"' | '`') {
+            let rest = chars.as_str();
+            if let Some(end) = rest.find(c) {
+                unquoted.push_str(REDACTED);
+                chars = rest[end + c.len_utf8()..].chars();
+                continue;
+            }
+        }
+        unquoted.push(c);
+    }
+
+    unquoted
+        .split(' ')
+        .map(|word| {
+            let token = word.trim_end_matches([',', '.', ';', ':', ')', ']']);
+            if is_sensitive(token) {
+                word.replacen(token, REDACTED, 1)
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn is_sensitive(token: &str) -> bool {
+    if token.contains('/') || token.contains('\\') {
+        return true;
+    }
+
+    let lowercase = token.to_ascii_lowercase();
+    if lowercase.starts_with("age-secret-key-1") || lowercase.starts_with("age1") {
+        return token.len() > 8;
+    }
+
+    token.len() >= MIN_SECRET_LENGTH
+        && token.chars().any(|c| c.is_ascii_digit())
+        && token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '=' | '_' | '-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrub_removes_names_paths_and_keys() {
+        assert_eq!(
+            scrub("IO Error: Failed to read payroll_vault/namespace/salaries.ns, retrying"),
+            "IO Error: Failed to read [redacted], retrying"
+        );
+        assert_eq!(
+            scrub("Vault mismatch: expected 'alpha', found \"beta\""),
+            "Vault mismatch: expected [redacted], found [redacted]"
+        );
+        assert_eq!(
+            scrub("Bad identity AGE-SECRET-KEY-1QQQQQQQQQQQQQQQQQQQQQQQ for age1qyqszqgpqyqszqgpqyqs."),
+            "Bad identity [redacted] for [redacted]."
+        );
+        assert_eq!(
+            scrub("Checksum 4bf92f3577b34da6a3ce929d0e0e4736 mismatched"),
+            "Checksum [redacted] mismatched"
+        );
+        assert_eq!(
+            scrub("Namespace is time-locked until 1700000000"),
+            "Namespace is time-locked until 1700000000"
+        );
+    }
+
+    #[test]
+    fn test_vault_error_report_keeps_code() {
+        let report = ErrorReport::from(&VaultError::io_error("Failed to open vault/notes"));
+
+        assert_eq!(report.kind, ErrorReportKind::Error);
+        assert_eq!(report.code, "io_error");
+        assert_eq!(report.message, "IO Error: Failed to open [redacted]");
+    }
+}
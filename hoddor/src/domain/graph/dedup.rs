@@ -0,0 +1,171 @@
+//! Finding and merging near-duplicate nodes.
+//!
+//! Agents that write what they learn into a graph tend to create the same
+//! entity again under slightly different wording. [`find_duplicate_nodes`]
+//! pairs nodes of the same type whose embeddings are at least `threshold`
+//! similar and whose labels, when both have some, share one.
+//! [`GraphPort::merge_nodes`] then folds each duplicate into the node kept.
+
+use super::{GraphNode, GraphResult, Id};
+use crate::ports::graph::GraphPort;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Two nodes found to describe the same entity. `keep` is the older one.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DuplicateNodes {
+    pub keep: Id,
+    pub duplicate: Id,
+    /// Cosine similarity of their embeddings, 1 for identical directions.
+    pub similarity: f32,
+}
+
+/// Pairs of nodes of `vault_id` at least `threshold` similar, most similar
+/// first. Nodes without an embedding are never paired.
+#[tracing::instrument(level = "debug", skip(graph))]
+pub async fn find_duplicate_nodes<G: GraphPort + ?Sized>(
+    graph: &G,
+    vault_id: &str,
+    threshold: f32,
+) -> GraphResult<Vec<DuplicateNodes>> {
+    let backup = graph.export_backup(vault_id).await?;
+
+    let mut by_type: BTreeMap<&str, Vec<&GraphNode>> = BTreeMap::new();
+    for node in backup.nodes.iter().filter(|node| node.embedding.is_some()) {
+        by_type.entry(&node.node_type).or_default().push(node);
+    }
+
+    let mut duplicates = Vec::new();
+    for nodes in by_type.values() {
+        // Backups list nodes oldest first, so `a` is the one to keep.
+        for (i, a) in nodes.iter().enumerate() {
+            for b in &nodes[i + 1..] {
+                if !labels_match(a, b) {
+                    continue;
+                }
+
+                let (Some(x), Some(y)) = (&a.embedding, &b.embedding) else {
+                    continue;
+                };
+                let similarity = cosine_similarity(x, y);
+                if similarity >= threshold {
+                    duplicates.push(DuplicateNodes {
+                        keep: a.id.clone(),
+                        duplicate: b.id.clone(),
+                        similarity,
+                    });
+                }
+            }
+        }
+    }
+
+    duplicates.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    Ok(duplicates)
+}
+
+fn labels_match(a: &GraphNode, b: &GraphNode) -> bool {
+    a.labels.is_empty() || b.labels.is_empty() || a.labels.iter().any(|l| b.labels.contains(l))
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::shared::MemoryGraphAdapter;
+    use crate::domain::graph::{NeighborFilter, EMBEDDING_DIM};
+    use futures::executor::block_on;
+
+    fn embedding(x: f32, y: f32) -> Vec<f32> {
+        let mut embedding = vec![0.0; EMBEDDING_DIM];
+        embedding[0] = x;
+        embedding[1] = y;
+        embedding
+    }
+
+    #[test]
+    fn test_duplicates_are_found_and_merged() {
+        let graph = MemoryGraphAdapter::isolated().unwrap();
+
+        block_on(async {
+            let create = |content: &str, labels: &[&str], embedding: Vec<f32>| {
+                graph.create_node(
+                    "v",
+                    "entity",
+                    content.to_string(),
+                    labels.iter().map(|label| label.to_string()).collect(),
+                    Some(embedding),
+                    None,
+                )
+            };
+            let paris = create("Paris", &["city"], embedding(1.0, 0.0))
+                .await
+                .unwrap();
+            let paris_again = create("Paris, France", &["city", "capital"], embedding(1.0, 0.05))
+                .await
+                .unwrap();
+            // Close, but labelled as something else.
+            create("Paris Hilton", &["person"], embedding(1.0, 0.05))
+                .await
+                .unwrap();
+            let france = create("France", &["country"], embedding(0.0, 1.0))
+                .await
+                .unwrap();
+            graph
+                .create_edge("v", &paris_again, &france, "capital_of", None, None)
+                .await
+                .unwrap();
+            graph
+                .create_edge("v", &paris, &paris_again, "same_as", None, None)
+                .await
+                .unwrap();
+
+            let duplicates = find_duplicate_nodes(&graph, "v", 0.99).await.unwrap();
+            assert_eq!(duplicates.len(), 1);
+            let DuplicateNodes {
+                keep, duplicate, ..
+            } = duplicates[0].clone();
+            // Created within the same millisecond, either may come first.
+            assert!([&keep, &duplicate].contains(&&paris));
+            assert!([&keep, &duplicate].contains(&&paris_again));
+
+            graph.merge_nodes("v", &keep, &duplicate).await.unwrap();
+
+            let nodes = graph.list_nodes_by_type("v", "entity", None).await.unwrap();
+            assert_eq!(nodes.len(), 3);
+            let mut labels = nodes
+                .iter()
+                .find(|node| node.id == keep)
+                .unwrap()
+                .labels
+                .clone();
+            labels.sort();
+            assert_eq!(labels, ["capital", "city"]);
+
+            // The edge between the two is gone, the other one moved.
+            let neighbors = graph
+                .get_neighbors("v", &keep, &NeighborFilter::default())
+                .await
+                .unwrap();
+            assert_eq!(neighbors.len(), 1);
+            assert_eq!(neighbors[0].node.id, france);
+            assert!(find_duplicate_nodes(&graph, "v", 0.99)
+                .await
+                .unwrap()
+                .is_empty());
+        });
+    }
+}
@@ -14,6 +14,7 @@ pub enum GraphError {
     IntegrityError(String),
     InvalidEmbedding(String),
     VaultMismatch { expected: String, found: String },
+    Cancelled,
     Other(String),
 }
 
@@ -38,6 +39,7 @@ impl fmt::Display for GraphError {
                     expected, found
                 )
             }
+            GraphError::Cancelled => write!(f, "Operation was cancelled"),
             GraphError::Other(e) => write!(f, "{}", e),
         }
     }
@@ -0,0 +1,330 @@
+//! Scheduled upkeep of the graph.
+//!
+//! A graph an application writes memories into only grows: nodes nobody
+//! has read in months stay searchable, embeddings computed by a model since
+//! replaced stay in the index, and backends keep the space of what was
+//! deleted. [`maintain_graph`] prunes the nodes of each vault neither
+//! created nor accessed within the retention window, queues the nodes
+//! embedded before the current [`embedding_version`] for the application to
+//! embed again, then compacts the backend. With `dry_run` it only reports
+//! what it would do. The facades run it on demand or spawn
+//! [`run_maintenance_scheduler`], which runs on a timer like the vault cleanup
+//! scheduler.
+
+use super::{GraphError, GraphNode, GraphResult, Id};
+use crate::domain::vault::{operations::list_vaults, CleanupSchedule};
+use crate::platform::Platform;
+use crate::ports::graph::GraphPort;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+static EMBEDDING_VERSION: AtomicU32 = AtomicU32::new(0);
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+static RUNNING: AtomicU64 = AtomicU64::new(0);
+
+/// Nodes waiting to be embedded again, per vault, oldest first.
+static REEMBED_QUEUE: Lazy<Mutex<BTreeMap<String, Vec<Id>>>> = Lazy::new(Default::default);
+
+/// Version of the embedding model the application currently uses. Nodes
+/// created from now on record it; maintenance queues those embedded with
+/// an older one.
+pub fn set_embedding_version(version: u32) {
+    EMBEDDING_VERSION.store(version, Ordering::Relaxed);
+}
+
+pub fn embedding_version() -> u32 {
+    EMBEDDING_VERSION.load(Ordering::Relaxed)
+}
+
+/// Takes up to `max` nodes of `vault_id` to embed again, to pass back
+/// through [`GraphPort::set_embedding`].
+pub fn take_reembed_queue(vault_id: &str, max: usize) -> Vec<Id> {
+    let mut queue = REEMBED_QUEUE.lock();
+    let Some(ids) = queue.get_mut(vault_id) else {
+        return Vec::new();
+    };
+
+    let taken: Vec<Id> = ids.drain(..max.min(ids.len())).collect();
+    if ids.is_empty() {
+        queue.remove(vault_id);
+    }
+    taken
+}
+
+pub fn reembed_queue_len(vault_id: &str) -> usize {
+    REEMBED_QUEUE.lock().get(vault_id).map_or(0, Vec::len)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct MaintenancePolicy {
+    /// Nodes neither created nor accessed for this long are pruned. `None`
+    /// keeps every node.
+    pub retention_seconds: Option<u64>,
+    pub compact: bool,
+    /// Reports what would be done without changing anything.
+    pub dry_run: bool,
+}
+
+impl Default for MaintenancePolicy {
+    fn default() -> Self {
+        Self {
+            retention_seconds: None,
+            compact: true,
+            dry_run: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct VaultMaintenance {
+    pub vault_id: String,
+    pub pruned: Vec<Id>,
+    /// Nodes queued, or that would be, to be embedded again.
+    pub stale_embeddings: Vec<Id>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MaintenanceReport {
+    pub dry_run: bool,
+    pub vaults: Vec<VaultMaintenance>,
+    pub compacted: bool,
+}
+
+/// Applies `policy` to the graphs of `vault_ids` at `now` (milliseconds
+/// since the epoch).
+#[tracing::instrument(level = "debug", skip(graph, vault_ids), fields(vaults = vault_ids.len()))]
+pub async fn maintain_graph<G: GraphPort + ?Sized>(
+    graph: &G,
+    vault_ids: &[String],
+    policy: &MaintenancePolicy,
+    now: u64,
+) -> GraphResult<MaintenanceReport> {
+    let version = embedding_version();
+    let cutoff = policy
+        .retention_seconds
+        .map(|seconds| now.saturating_sub(seconds.saturating_mul(1000)));
+    let expired = |node: &GraphNode| {
+        cutoff.is_some_and(|cutoff| node.created_at.max(node.last_accessed_at) < cutoff)
+    };
+
+    let mut report = MaintenanceReport {
+        dry_run: policy.dry_run,
+        ..MaintenanceReport::default()
+    };
+
+    for vault_id in vault_ids {
+        let backup = graph.export_backup(vault_id).await?;
+        let (pruned, kept): (Vec<&GraphNode>, Vec<&GraphNode>) =
+            backup.nodes.iter().partition(|node| expired(node));
+        let pruned: Vec<Id> = pruned.into_iter().map(|node| node.id.clone()).collect();
+        let stale_embeddings: Vec<Id> = kept
+            .into_iter()
+            .filter(|node| node.embedding.is_some() && node.embedding_version < version)
+            .map(|node| node.id.clone())
+            .collect();
+
+        if !policy.dry_run {
+            if !pruned.is_empty() {
+                graph.delete_nodes(vault_id, &pruned).await?;
+            }
+
+            let mut queue = REEMBED_QUEUE.lock();
+            let queued = queue.entry(vault_id.clone()).or_default();
+            queued.retain(|id| !pruned.contains(id));
+            for id in &stale_embeddings {
+                if !queued.contains(id) {
+                    queued.push(id.clone());
+                }
+            }
+            if queued.is_empty() {
+                queue.remove(vault_id);
+            }
+        }
+
+        tracing::debug!(
+            vault = %vault_id,
+            pruned = pruned.len(),
+            stale_embeddings = stale_embeddings.len(),
+            dry_run = policy.dry_run,
+            "Graph maintenance"
+        );
+        report.vaults.push(VaultMaintenance {
+            vault_id: vault_id.clone(),
+            pruned,
+            stale_embeddings,
+        });
+    }
+
+    if policy.compact && !policy.dry_run {
+        graph.compact().await?;
+        report.compacted = true;
+    }
+
+    Ok(report)
+}
+
+/// [`maintain_graph`] over the graph of every vault of `platform`.
+pub async fn maintain_all_graphs(
+    platform: &Platform,
+    policy: &MaintenancePolicy,
+) -> GraphResult<MaintenanceReport> {
+    let vault_ids = list_vaults(platform)
+        .await
+        .map_err(|e| GraphError::Other(e.to_string()))?;
+
+    maintain_graph(
+        platform.graph(),
+        &vault_ids,
+        policy,
+        platform.clock().now() as u64,
+    )
+    .await
+}
+
+/// Takes over from the running scheduler, if any, and returns the
+/// generation to pass to [`run_maintenance_scheduler`].
+pub fn begin_maintenance_scheduler() -> u64 {
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    RUNNING.store(generation, Ordering::SeqCst);
+    generation
+}
+
+/// Stops the running scheduler. Returns `false` if there was none.
+pub fn stop_maintenance_scheduler() -> bool {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    RUNNING.swap(0, Ordering::SeqCst) != 0
+}
+
+pub fn is_maintenance_scheduler_running() -> bool {
+    RUNNING.load(Ordering::SeqCst) != 0
+}
+
+fn is_current(generation: u64) -> bool {
+    GENERATION.load(Ordering::SeqCst) == generation
+}
+
+/// Maintains the graph of every vault on `schedule` until another
+/// scheduler begins or [`stop_maintenance_scheduler`] is called.
+pub async fn run_maintenance_scheduler(
+    platform: &Platform,
+    schedule: CleanupSchedule,
+    policy: MaintenancePolicy,
+    generation: u64,
+) {
+    while is_current(generation) {
+        platform.clock().sleep(schedule.next_delay_ms()).await;
+        if !is_current(generation) {
+            break;
+        }
+
+        match maintain_all_graphs(platform, &policy).await {
+            Ok(report) => tracing::debug!(
+                vaults = report.vaults.len(),
+                compacted = report.compacted,
+                "Scheduled graph maintenance done"
+            ),
+            Err(e) => tracing::warn!(error = %e, "Scheduled graph maintenance failed"),
+        }
+    }
+
+    let _ = RUNNING.compare_exchange(generation, 0, Ordering::SeqCst, Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::shared::MemoryGraphAdapter;
+    use crate::domain::graph::{GraphBackup, GraphStats, EMBEDDING_DIM};
+    use futures::executor::block_on;
+
+    const DAY: u64 = 24 * 60 * 60 * 1000;
+    const NOW: u64 = 1_700_000_000_000;
+    const VAULT: &str = "maintenance_test";
+
+    fn node(content: &str, created_at: u64, embedding_version: u32) -> GraphNode {
+        GraphNode {
+            id: Id::new(),
+            node_type: "memory".to_string(),
+            vault_id: VAULT.to_string(),
+            content: content.to_string(),
+            labels: Vec::new(),
+            embedding: Some(vec![1.0; EMBEDDING_DIM]),
+            created_at,
+            access_count: 0,
+            last_accessed_at: 0,
+            embedding_version,
+        }
+    }
+
+    #[test]
+    fn test_maintenance_prunes_and_queues_stale_embeddings() {
+        let graph = MemoryGraphAdapter::isolated().unwrap();
+        let old = node("old", NOW - 60 * DAY, 0);
+        let stale = node("stale", NOW - DAY, 0);
+        let current = node("current", NOW - DAY, 1);
+        set_embedding_version(1);
+
+        block_on(async {
+            graph
+                .import_backup(&GraphBackup {
+                    version: 1,
+                    nodes: vec![old.clone(), stale.clone(), current],
+                    edges: Vec::new(),
+                    created_at: NOW,
+                })
+                .await
+                .unwrap();
+            graph
+                .create_edge(VAULT, &old.id, &stale.id, "related", None, None)
+                .await
+                .unwrap();
+
+            let vaults = [VAULT.to_string()];
+            let mut policy = MaintenancePolicy {
+                retention_seconds: Some(30 * 24 * 60 * 60),
+                dry_run: true,
+                ..MaintenancePolicy::default()
+            };
+
+            let report = maintain_graph(&graph, &vaults, &policy, NOW).await.unwrap();
+            assert!(!report.compacted);
+            assert_eq!(report.vaults[0].pruned, std::slice::from_ref(&old.id));
+            assert_eq!(
+                report.vaults[0].stale_embeddings,
+                std::slice::from_ref(&stale.id)
+            );
+            assert_eq!(
+                graph.stats().await.unwrap(),
+                GraphStats { nodes: 3, edges: 1 }
+            );
+            assert_eq!(reembed_queue_len(VAULT), 0);
+
+            policy.dry_run = false;
+            let applied = maintain_graph(&graph, &vaults, &policy, NOW).await.unwrap();
+            assert!(applied.compacted);
+            assert_eq!(applied.vaults, report.vaults);
+            assert_eq!(
+                graph.stats().await.unwrap(),
+                GraphStats { nodes: 2, edges: 0 }
+            );
+
+            // A second round does not queue the node twice.
+            maintain_graph(&graph, &vaults, &policy, NOW).await.unwrap();
+            assert_eq!(
+                take_reembed_queue(VAULT, 10),
+                std::slice::from_ref(&stale.id)
+            );
+
+            graph
+                .set_embedding(VAULT, &stale.id, vec![0.5; EMBEDDING_DIM], 1)
+                .await
+                .unwrap();
+            let report = maintain_graph(&graph, &vaults, &policy, NOW).await.unwrap();
+            assert!(report.vaults[0].stale_embeddings.is_empty());
+        });
+    }
+}
@@ -1,7 +1,27 @@
+pub mod dedup;
 pub mod error;
+pub mod maintenance;
 pub mod persistence;
+pub mod query_cache;
+pub mod recall;
+pub mod rpc;
+pub mod streaming;
 pub mod types;
 
+pub use dedup::{find_duplicate_nodes, DuplicateNodes};
 pub use error::{GraphError, GraphResult};
+pub use maintenance::{
+    embedding_version, maintain_all_graphs, maintain_graph, reembed_queue_len,
+    set_embedding_version, take_reembed_queue, MaintenancePolicy, MaintenanceReport,
+    VaultMaintenance,
+};
 pub use persistence::{EncryptionConfig, GraphPersistenceService};
+pub use query_cache::{
+    query_cache_capacity, query_cache_stats, set_query_cache_capacity, QueryCacheStats,
+    DEFAULT_QUERY_CACHE_CAPACITY,
+};
+pub use recall::{recall, recall_score, RecallWeights, RecalledNode, RECALL_CANDIDATE_FACTOR};
+pub use streaming::{
+    graph_memory_budget, set_graph_memory_budget, SearchResultStream, DEFAULT_GRAPH_MEMORY_BUDGET,
+};
 pub use types::*;
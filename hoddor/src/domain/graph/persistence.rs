@@ -1,5 +1,6 @@
 use crate::domain::crypto;
 use crate::domain::graph::{GraphBackup, GraphError, GraphResult};
+use crate::domain::progress::{ignore_progress, Progress};
 use crate::platform::Platform;
 use crate::ports::graph::GraphPort;
 use crate::ports::StoragePort;
@@ -7,6 +8,9 @@ use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 
 const NAMESPACE_EXTENSION: &str = "hoddor";
 
+/// Nodes or edges imported between two progress reports.
+const RESTORE_BATCH_SIZE: usize = 100;
+
 #[derive(Clone)]
 pub struct EncryptionConfig {
     pub platform: Platform,
@@ -67,6 +71,16 @@ impl<G: GraphPort, S: StoragePort> GraphPersistenceService<G, S> {
     }
 
     pub async fn restore(&self, vault_id: &str) -> GraphResult<GraphBackup> {
+        self.restore_with_progress(vault_id, &ignore_progress).await
+    }
+
+    /// Like [`Self::restore`], importing nodes then edges in batches and
+    /// reporting the number of items imported after each batch.
+    pub async fn restore_with_progress(
+        &self,
+        vault_id: &str,
+        on_progress: &dyn Fn(Progress),
+    ) -> GraphResult<GraphBackup> {
         let file_content = self
             .storage
             .read_file(&format!(
@@ -96,7 +110,34 @@ impl<G: GraphPort, S: StoragePort> GraphPersistenceService<G, S> {
             GraphError::SerializationError(format!("Failed to deserialize backup: {}", e))
         })?;
 
-        self.graph.import_backup(&backup).await?;
+        let total = backup.nodes.len() + backup.edges.len();
+        let mut imported = 0;
+
+        for nodes in backup.nodes.chunks(RESTORE_BATCH_SIZE) {
+            self.graph
+                .import_backup(&GraphBackup {
+                    nodes: nodes.to_vec(),
+                    edges: Vec::new(),
+                    version: backup.version,
+                    created_at: backup.created_at,
+                })
+                .await?;
+            imported += nodes.len();
+            on_progress(Progress::items(imported, total));
+        }
+
+        for edges in backup.edges.chunks(RESTORE_BATCH_SIZE) {
+            self.graph
+                .import_backup(&GraphBackup {
+                    nodes: Vec::new(),
+                    edges: edges.to_vec(),
+                    version: backup.version,
+                    created_at: backup.created_at,
+                })
+                .await?;
+            imported += edges.len();
+            on_progress(Progress::items(imported, total));
+        }
 
         Ok(backup)
     }
@@ -130,8 +171,7 @@ mod tests {
     use super::*;
     use crate::adapters::wasm::OpfsStorage;
 
-    #[cfg(feature = "graph")]
-    use crate::adapters::wasm::CozoGraphAdapter;
+    use crate::adapters::Graph;
 
     use crate::domain::crypto;
     use crate::platform::Platform;
@@ -167,7 +207,7 @@ mod tests {
             identity: identity.clone(),
         };
 
-        let graph = CozoGraphAdapter::new().unwrap();
+        let graph = Graph::new().unwrap();
         let storage = OpfsStorage::new();
 
         storage.create_directory("graph_backups").await.unwrap();
@@ -226,7 +266,7 @@ mod tests {
             identity,
         };
 
-        let graph2 = CozoGraphAdapter::new().unwrap();
+        let graph2 = Graph::new().unwrap();
         let storage2 = OpfsStorage::new();
         let service2 = GraphPersistenceService::new(
             graph2,
@@ -257,7 +297,7 @@ mod tests {
             identity,
         };
 
-        let graph = CozoGraphAdapter::new().unwrap();
+        let graph = Graph::new().unwrap();
         let storage = OpfsStorage::new();
         storage.create_directory("graph_backups").await.unwrap();
         let service =
@@ -281,7 +321,7 @@ mod tests {
             identity,
         };
 
-        let graph = CozoGraphAdapter::new().unwrap();
+        let graph = Graph::new().unwrap();
         let storage = OpfsStorage::new();
         storage.create_directory("graph_backups").await.unwrap();
         let service =
@@ -339,7 +379,7 @@ mod tests {
         let identity = crypto::generate_identity(&platform).unwrap();
         let recipient = crypto::identity_to_public(&platform, &identity).unwrap();
 
-        let graph = CozoGraphAdapter::new().unwrap();
+        let graph = Graph::new().unwrap();
         let storage = OpfsStorage::new();
         storage
             .create_directory("encrypted_graph_backups")
@@ -434,10 +474,6 @@ mod tests {
         assert!(!service.backup_exists(vault_id).await);
     }
 
-    // Note: This test is skipped in WASM due to age library i18n limitations
-    // The age library tries to load language files when generating error messages
-    // which are not available in WASM environments
-    #[cfg_attr(target_arch = "wasm32", ignore)]
     #[wasm_bindgen_test]
     async fn test_encrypted_backup_wrong_key() {
         let platform = Platform::new();
@@ -447,7 +483,7 @@ mod tests {
 
         let identity2 = crypto::generate_identity(&platform).unwrap();
 
-        let graph = CozoGraphAdapter::new().unwrap();
+        let graph = Graph::new().unwrap();
         let storage = OpfsStorage::new();
         storage.create_directory("wrong_key_test").await.unwrap();
 
@@ -477,7 +513,7 @@ mod tests {
 
         service1.backup(vault_id).await.unwrap();
 
-        let graph2 = CozoGraphAdapter::new().unwrap();
+        let graph2 = Graph::new().unwrap();
         let storage2 = OpfsStorage::new();
 
         let encryption2 = EncryptionConfig {
@@ -493,8 +529,12 @@ mod tests {
             encryption2,
         );
 
-        let result = service2.restore(vault_id).await;
-        assert!(result.is_err());
+        match service2.restore(vault_id).await {
+            Err(GraphError::Other(message)) => {
+                assert!(message.contains("no identity matches"), "{message}")
+            }
+            other => panic!("expected a decryption failure, got {other:?}"),
+        }
 
         service1.delete_backup(vault_id).await.unwrap();
     }
@@ -511,7 +551,7 @@ mod tests {
             identity,
         };
 
-        let graph = CozoGraphAdapter::new().unwrap();
+        let graph = Graph::new().unwrap();
         let storage = OpfsStorage::new();
         storage.create_directory("graph_backups").await.unwrap();
         let service =
@@ -0,0 +1,164 @@
+//! Results of recent vector searches, per graph database.
+//!
+//! Searching recomputes distances to every node of the vault, so the same
+//! query asked twice costs twice. Each database keeps the results of its
+//! last [`query_cache_capacity`] searches, keyed by vault and a hash of the
+//! query, and drops those of a vault as soon as a node or an edge of it is
+//! written. Hits, misses and invalidations are counted process-wide and
+//! emitted as `monotonic_counter.graph_query_cache_*` events, exported as
+//! metrics when OTLP export is on.
+
+use super::{SearchQuery, SearchResult};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+pub const DEFAULT_QUERY_CACHE_CAPACITY: usize = 128;
+
+static CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_QUERY_CACHE_CAPACITY);
+static HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+static INVALIDATIONS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct QueryCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Cached results dropped because their vault was written.
+    pub invalidations: u64,
+}
+
+/// Number of searches each database keeps the results of; zero disables
+/// the cache.
+pub fn set_query_cache_capacity(capacity: usize) {
+    CAPACITY.store(capacity, Ordering::Relaxed);
+}
+
+pub fn query_cache_capacity() -> usize {
+    CAPACITY.load(Ordering::Relaxed)
+}
+
+pub fn query_cache_stats() -> QueryCacheStats {
+    QueryCacheStats {
+        hits: HITS.load(Ordering::Relaxed),
+        misses: MISSES.load(Ordering::Relaxed),
+        invalidations: INVALIDATIONS.load(Ordering::Relaxed),
+    }
+}
+
+/// Hash of `query`, and of the page of its results when only one is asked
+/// for. Embeddings are hashed bit for bit.
+pub fn query_hash(query: &SearchQuery, page: Option<(usize, usize)>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for value in &query.query_embedding {
+        value.to_bits().hash(&mut hasher);
+    }
+    query.max_results.hash(&mut hasher);
+    query.search_quality.hash(&mut hasher);
+    query.include_neighbors.hash(&mut hasher);
+    page.hash(&mut hasher);
+    hasher.finish()
+}
+
+type Key = (String, u64);
+
+#[derive(Debug, Default)]
+pub struct QueryCache {
+    results: HashMap<Key, Vec<SearchResult>>,
+    /// Keys from the oldest to the newest, for eviction.
+    order: VecDeque<Key>,
+    /// Bumped on every write, so a search that ran across one does not
+    /// cache what it read before it.
+    generation: u64,
+}
+
+impl QueryCache {
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Number of searches whose results are cached.
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    pub fn get(&self, vault_id: &str, hash: u64) -> Option<Vec<SearchResult>> {
+        if query_cache_capacity() == 0 {
+            return None;
+        }
+
+        let results = self.results.get(&(vault_id.to_string(), hash)).cloned();
+        if results.is_some() {
+            HITS.fetch_add(1, Ordering::Relaxed);
+            tracing::trace!(
+                monotonic_counter.graph_query_cache_hits = 1u64,
+                vault = vault_id
+            );
+        } else {
+            MISSES.fetch_add(1, Ordering::Relaxed);
+            tracing::trace!(
+                monotonic_counter.graph_query_cache_misses = 1u64,
+                vault = vault_id
+            );
+        }
+        results
+    }
+
+    /// Caches `results` of a search started at `generation`, unless the
+    /// database was written since.
+    pub fn insert(
+        &mut self,
+        vault_id: &str,
+        hash: u64,
+        results: Vec<SearchResult>,
+        generation: u64,
+    ) {
+        let capacity = query_cache_capacity();
+        if capacity == 0 || generation != self.generation {
+            return;
+        }
+
+        let key = (vault_id.to_string(), hash);
+        if self.results.insert(key.clone(), results).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.results.remove(&oldest);
+            }
+        }
+    }
+
+    /// Drops the results cached for `vault_id`.
+    pub fn invalidate_vault(&mut self, vault_id: &str) {
+        self.generation += 1;
+
+        let before = self.order.len();
+        self.order.retain(|(vault, _)| vault != vault_id);
+        self.results.retain(|(vault, _), _| vault != vault_id);
+        self.record_invalidations(before - self.order.len());
+    }
+
+    pub fn clear(&mut self) {
+        self.generation += 1;
+
+        let before = self.order.len();
+        self.order.clear();
+        self.results.clear();
+        self.record_invalidations(before);
+    }
+
+    fn record_invalidations(&self, count: usize) {
+        if count == 0 {
+            return;
+        }
+        INVALIDATIONS.fetch_add(count as u64, Ordering::Relaxed);
+        tracing::trace!(monotonic_counter.graph_query_cache_invalidations = count as u64);
+    }
+}
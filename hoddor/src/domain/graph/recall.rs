@@ -0,0 +1,192 @@
+//! Ranking for memory-style retrieval.
+//!
+//! A plain vector search ranks by similarity alone, so an application
+//! storing what an agent learns keeps surfacing notes that stopped
+//! mattering long ago. [`recall`] ranks a wider set of candidates by a
+//! weighted sum of similarity, recency and use, then counts an access to
+//! each node it returns: memories in use stay on top, the others fade.
+
+use super::{GraphNode, GraphResult, Id, SearchQuery, SearchResult};
+use crate::ports::graph::GraphPort;
+use serde::{Deserialize, Serialize};
+
+/// Candidates fetched per result asked for, so that a recent or often used
+/// node can outrank more similar ones.
+pub const RECALL_CANDIDATE_FACTOR: usize = 4;
+
+const DEFAULT_HALF_LIFE_SECONDS: f64 = 7.0 * 24.0 * 60.0 * 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RecallWeights {
+    pub similarity: f32,
+    pub recency: f32,
+    pub frequency: f32,
+    /// Time after which the recency of a node, and the weight of its past
+    /// accesses, halve.
+    pub half_life_seconds: f64,
+}
+
+impl Default for RecallWeights {
+    fn default() -> Self {
+        Self {
+            similarity: 0.6,
+            recency: 0.25,
+            frequency: 0.15,
+            half_life_seconds: DEFAULT_HALF_LIFE_SECONDS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecalledNode {
+    pub result: SearchResult,
+    pub score: f32,
+}
+
+/// Score of `node`, found at `distance` from the query, at `now`
+/// (milliseconds since the epoch). Each term lies between 0 and 1:
+/// similarity is `1 - distance`, recency halves every half-life since the
+/// node was created or last accessed, and frequency grows with accesses,
+/// each of which loses half its weight per half-life.
+pub fn recall_score(node: &GraphNode, distance: f32, weights: &RecallWeights, now: u64) -> f32 {
+    let half_life = weights.half_life_seconds * 1000.0;
+    let decay = |since: u64| {
+        if half_life <= 0.0 {
+            return 0.0;
+        }
+        0.5f64.powf(now.saturating_sub(since) as f64 / half_life)
+    };
+
+    let similarity = (1.0 - distance).clamp(0.0, 1.0);
+    let recency = decay(node.created_at.max(node.last_accessed_at));
+    let uses = node.access_count as f64 * decay(node.last_accessed_at);
+    let frequency = uses / (1.0 + uses);
+
+    weights.similarity * similarity
+        + weights.recency * recency as f32
+        + weights.frequency * frequency as f32
+}
+
+/// The `query.max_results` best nodes of `vault_id` by [`recall_score`],
+/// best first. Each of them counts one more access.
+#[tracing::instrument(level = "debug", skip(graph, query), fields(max_results = query.max_results))]
+pub async fn recall<G: GraphPort + ?Sized>(
+    graph: &G,
+    vault_id: &str,
+    query: &SearchQuery,
+    weights: &RecallWeights,
+    now: u64,
+) -> GraphResult<Vec<RecalledNode>> {
+    let candidates = query.max_results.saturating_mul(RECALL_CANDIDATE_FACTOR);
+    let results = graph
+        .vector_search_with_neighbors(
+            vault_id,
+            query.query_embedding.clone(),
+            candidates,
+            query.search_quality.max(candidates),
+            query.include_neighbors,
+        )
+        .await?;
+
+    let mut recalled: Vec<RecalledNode> = results
+        .into_iter()
+        .map(|result| RecalledNode {
+            score: recall_score(&result.node, result.distance, weights, now),
+            result,
+        })
+        .collect();
+    recalled.sort_by(|a, b| b.score.total_cmp(&a.score));
+    recalled.truncate(query.max_results);
+
+    let ids: Vec<Id> = recalled
+        .iter()
+        .map(|recalled| recalled.result.node.id.clone())
+        .collect();
+    graph.record_access(vault_id, &ids).await?;
+
+    Ok(recalled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::shared::MemoryGraphAdapter;
+    use crate::adapters::Clock;
+    use crate::domain::graph::{GraphBackup, EMBEDDING_DIM};
+    use crate::ports::clock::ClockPort;
+    use futures::executor::block_on;
+
+    const DAY: u64 = 24 * 60 * 60 * 1000;
+
+    fn node(content: &str, y: f32, created_at: u64, access_count: u64) -> GraphNode {
+        let mut embedding = vec![0.0; EMBEDDING_DIM];
+        embedding[0] = 1.0;
+        embedding[1] = y;
+
+        GraphNode {
+            id: Id::new(),
+            node_type: "memory".to_string(),
+            vault_id: "v".to_string(),
+            content: content.to_string(),
+            labels: Vec::new(),
+            embedding: Some(embedding),
+            created_at,
+            access_count,
+            last_accessed_at: if access_count > 0 { created_at } else { 0 },
+            embedding_version: 0,
+        }
+    }
+
+    #[test]
+    fn test_recall_prefers_fresh_and_used_memories() {
+        let graph = MemoryGraphAdapter::isolated().unwrap();
+        let now = Clock.now() as u64;
+
+        block_on(async {
+            graph
+                .import_backup(&GraphBackup {
+                    version: 1,
+                    nodes: vec![
+                        node("stale", 0.0, now - 90 * DAY, 0),
+                        node("fresh", 0.3, now - DAY, 5),
+                    ],
+                    edges: Vec::new(),
+                    created_at: now,
+                })
+                .await
+                .unwrap();
+
+            let mut embedding = vec![0.0; EMBEDDING_DIM];
+            embedding[0] = 1.0;
+            let query = SearchQuery {
+                query_embedding: embedding,
+                max_results: 1,
+                search_quality: 50,
+                include_neighbors: false,
+            };
+
+            let recalled = recall(&graph, "v", &query, &RecallWeights::default(), now)
+                .await
+                .unwrap();
+            assert_eq!(recalled.len(), 1);
+            assert_eq!(recalled[0].result.node.content, "fresh");
+
+            let similar_only = RecallWeights {
+                similarity: 1.0,
+                recency: 0.0,
+                frequency: 0.0,
+                ..RecallWeights::default()
+            };
+            let recalled = recall(&graph, "v", &query, &similar_only, now)
+                .await
+                .unwrap();
+            assert_eq!(recalled[0].result.node.content, "stale");
+
+            let nodes = graph.list_nodes_by_type("v", "memory", None).await.unwrap();
+            let fresh = nodes.iter().find(|node| node.content == "fresh").unwrap();
+            assert_eq!(fresh.access_count, 6);
+            assert!(fresh.last_accessed_at >= now);
+        });
+    }
+}
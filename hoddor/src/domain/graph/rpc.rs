@@ -0,0 +1,419 @@
+//! Wire format between a [`GraphPort`] shim and a graph engine loaded as a
+//! separate wasm module.
+//!
+//! Requests and responses are JSON strings, so the two modules share no
+//! memory and may come from different builds of this crate. Database `0` is
+//! the engine's shared graph; other ids name isolated graphs the engine
+//! creates on first use and drops on [`GraphRequest::Release`].
+
+use super::error::{GraphError, GraphResult};
+use super::types::{GraphBackup, Id, NeighborFilter, SearchQuery};
+use crate::ports::GraphPort;
+use serde::{Deserialize, Serialize};
+
+/// Database every platform shares, as opposed to isolated ones.
+pub const SHARED_DATABASE: u32 = 0;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphEnvelope {
+    pub database: u32,
+    #[serde(flatten)]
+    pub request: GraphRequest,
+}
+
+/// One [`GraphPort`] call, plus `release` to free an isolated database.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "camelCase")]
+pub enum GraphRequest {
+    #[serde(rename_all = "camelCase")]
+    CreateNode {
+        vault_id: String,
+        node_type: String,
+        content: String,
+        labels: Vec<String>,
+        embedding: Option<Vec<f32>>,
+        node_id: Option<Id>,
+    },
+    #[serde(rename_all = "camelCase")]
+    ListNodesByType {
+        vault_id: String,
+        node_type: String,
+        limit: Option<usize>,
+    },
+    #[serde(rename_all = "camelCase")]
+    CreateEdge {
+        vault_id: String,
+        from_node: Id,
+        to_node: Id,
+        edge_type: String,
+        weight: Option<f32>,
+        edge_id: Option<Id>,
+    },
+    #[serde(rename_all = "camelCase")]
+    GetNeighbors {
+        vault_id: String,
+        node_id: Id,
+        filter: NeighborFilter,
+    },
+    #[serde(rename_all = "camelCase")]
+    MergeNodes {
+        vault_id: String,
+        keep: Id,
+        remove: Id,
+    },
+    #[serde(rename_all = "camelCase")]
+    RecordAccess {
+        vault_id: String,
+        node_ids: Vec<Id>,
+    },
+    #[serde(rename_all = "camelCase")]
+    DeleteNodes {
+        vault_id: String,
+        node_ids: Vec<Id>,
+    },
+    #[serde(rename_all = "camelCase")]
+    SetEmbedding {
+        vault_id: String,
+        node_id: Id,
+        embedding: Vec<f32>,
+        version: u32,
+    },
+    Compact,
+    #[serde(rename_all = "camelCase")]
+    VectorSearch {
+        vault_id: String,
+        query_embedding: Vec<f32>,
+        max_results: usize,
+        search_quality: usize,
+        include_neighbors: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    VectorSearchPage {
+        vault_id: String,
+        query_embedding: Vec<f32>,
+        max_results: usize,
+        search_quality: usize,
+        include_neighbors: bool,
+        offset: usize,
+        limit: usize,
+    },
+    #[serde(rename_all = "camelCase")]
+    ExportBackup {
+        vault_id: String,
+    },
+    ImportBackup {
+        backup: GraphBackup,
+    },
+    Stats,
+    Reset,
+    Release,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum GraphResponse {
+    Ok { value: serde_json::Value },
+    Error { kind: String, message: String },
+}
+
+impl GraphResponse {
+    pub fn from_result<T: Serialize>(result: GraphResult<T>) -> Self {
+        let result = result.and_then(|value| {
+            serde_json::to_value(value).map_err(|e| GraphError::SerializationError(e.to_string()))
+        });
+
+        match result {
+            Ok(value) => GraphResponse::Ok { value },
+            Err(e) => GraphResponse::Error {
+                kind: error_kind(&e).to_string(),
+                message: error_message(e),
+            },
+        }
+    }
+
+    /// The value of the call, decoded as what the [`GraphPort`] method
+    /// returns.
+    pub fn into_result<T: for<'de> Deserialize<'de>>(self) -> GraphResult<T> {
+        match self {
+            GraphResponse::Ok { value } => serde_json::from_value(value)
+                .map_err(|e| GraphError::SerializationError(e.to_string())),
+            GraphResponse::Error { kind, message } => Err(error_from_kind(&kind, message)),
+        }
+    }
+}
+
+fn error_kind(error: &GraphError) -> &'static str {
+    match error {
+        GraphError::NodeNotFound(_) => "nodeNotFound",
+        GraphError::EdgeNotFound(_) => "edgeNotFound",
+        GraphError::NodeAlreadyExists(_) => "nodeAlreadyExists",
+        GraphError::InvalidNodeType(_) => "invalidNodeType",
+        GraphError::InvalidEdgeType(_) => "invalidEdgeType",
+        GraphError::EncryptionError(_) => "encryption",
+        GraphError::DecryptionError(_) => "decryption",
+        GraphError::SerializationError(_) => "serialization",
+        GraphError::DatabaseError(_) => "database",
+        GraphError::IntegrityError(_) => "integrity",
+        GraphError::InvalidEmbedding(_) => "invalidEmbedding",
+        GraphError::VaultMismatch { .. } => "vaultMismatch",
+        GraphError::Cancelled => "cancelled",
+        GraphError::Other(_) => "other",
+    }
+}
+
+/// The payload of the variant, which [`error_from_kind`] wraps again.
+fn error_message(error: GraphError) -> String {
+    match error {
+        GraphError::NodeNotFound(message)
+        | GraphError::EdgeNotFound(message)
+        | GraphError::NodeAlreadyExists(message)
+        | GraphError::InvalidNodeType(message)
+        | GraphError::InvalidEdgeType(message)
+        | GraphError::EncryptionError(message)
+        | GraphError::DecryptionError(message)
+        | GraphError::SerializationError(message)
+        | GraphError::DatabaseError(message)
+        | GraphError::IntegrityError(message)
+        | GraphError::InvalidEmbedding(message)
+        | GraphError::Other(message) => message,
+        error @ (GraphError::VaultMismatch { .. } | GraphError::Cancelled) => error.to_string(),
+    }
+}
+
+fn error_from_kind(kind: &str, message: String) -> GraphError {
+    match kind {
+        "nodeNotFound" => GraphError::NodeNotFound(message),
+        "edgeNotFound" => GraphError::EdgeNotFound(message),
+        "nodeAlreadyExists" => GraphError::NodeAlreadyExists(message),
+        "invalidNodeType" => GraphError::InvalidNodeType(message),
+        "invalidEdgeType" => GraphError::InvalidEdgeType(message),
+        "encryption" => GraphError::EncryptionError(message),
+        "decryption" => GraphError::DecryptionError(message),
+        "serialization" => GraphError::SerializationError(message),
+        "database" => GraphError::DatabaseError(message),
+        "integrity" => GraphError::IntegrityError(message),
+        "invalidEmbedding" => GraphError::InvalidEmbedding(message),
+        "cancelled" => GraphError::Cancelled,
+        _ => GraphError::Other(message),
+    }
+}
+
+/// Runs `request` on `graph`, the engine side of the shim. `Release` is
+/// handled by the caller, which owns the databases, and answers `null`
+/// here.
+pub async fn dispatch(graph: &dyn GraphPort, request: GraphRequest) -> GraphResponse {
+    match request {
+        GraphRequest::CreateNode {
+            vault_id,
+            node_type,
+            content,
+            labels,
+            embedding,
+            node_id,
+        } => GraphResponse::from_result(
+            graph
+                .create_node(
+                    &vault_id,
+                    &node_type,
+                    content,
+                    labels,
+                    embedding,
+                    node_id.as_ref(),
+                )
+                .await,
+        ),
+        GraphRequest::ListNodesByType {
+            vault_id,
+            node_type,
+            limit,
+        } => {
+            GraphResponse::from_result(graph.list_nodes_by_type(&vault_id, &node_type, limit).await)
+        }
+        GraphRequest::CreateEdge {
+            vault_id,
+            from_node,
+            to_node,
+            edge_type,
+            weight,
+            edge_id,
+        } => GraphResponse::from_result(
+            graph
+                .create_edge(
+                    &vault_id,
+                    &from_node,
+                    &to_node,
+                    &edge_type,
+                    weight,
+                    edge_id.as_ref(),
+                )
+                .await,
+        ),
+        GraphRequest::GetNeighbors {
+            vault_id,
+            node_id,
+            filter,
+        } => GraphResponse::from_result(graph.get_neighbors(&vault_id, &node_id, &filter).await),
+        GraphRequest::MergeNodes {
+            vault_id,
+            keep,
+            remove,
+        } => GraphResponse::from_result(graph.merge_nodes(&vault_id, &keep, &remove).await),
+        GraphRequest::RecordAccess { vault_id, node_ids } => {
+            GraphResponse::from_result(graph.record_access(&vault_id, &node_ids).await)
+        }
+        GraphRequest::DeleteNodes { vault_id, node_ids } => {
+            GraphResponse::from_result(graph.delete_nodes(&vault_id, &node_ids).await)
+        }
+        GraphRequest::SetEmbedding {
+            vault_id,
+            node_id,
+            embedding,
+            version,
+        } => GraphResponse::from_result(
+            graph
+                .set_embedding(&vault_id, &node_id, embedding, version)
+                .await,
+        ),
+        GraphRequest::Compact => GraphResponse::from_result(graph.compact().await),
+        GraphRequest::VectorSearch {
+            vault_id,
+            query_embedding,
+            max_results,
+            search_quality,
+            include_neighbors,
+        } => GraphResponse::from_result(
+            graph
+                .vector_search_with_neighbors(
+                    &vault_id,
+                    query_embedding,
+                    max_results,
+                    search_quality,
+                    include_neighbors,
+                )
+                .await,
+        ),
+        GraphRequest::VectorSearchPage {
+            vault_id,
+            query_embedding,
+            max_results,
+            search_quality,
+            include_neighbors,
+            offset,
+            limit,
+        } => {
+            let query = SearchQuery {
+                query_embedding,
+                max_results,
+                search_quality,
+                include_neighbors,
+            };
+            GraphResponse::from_result(
+                graph
+                    .vector_search_page(&vault_id, &query, offset, limit)
+                    .await,
+            )
+        }
+        GraphRequest::ExportBackup { vault_id } => {
+            GraphResponse::from_result(graph.export_backup(&vault_id).await)
+        }
+        GraphRequest::ImportBackup { backup } => {
+            GraphResponse::from_result(graph.import_backup(&backup).await)
+        }
+        GraphRequest::Stats => GraphResponse::from_result(graph.stats().await),
+        GraphRequest::Reset => GraphResponse::from_result(graph.reset().await),
+        GraphRequest::Release => GraphResponse::from_result(Ok(())),
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::adapters::shared::MemoryGraphAdapter;
+    use crate::domain::graph::{GraphNode, GraphStats, EMBEDDING_DIM};
+    use futures::executor::block_on;
+
+    /// Sends `request` through JSON both ways, as the shim does.
+    fn call<T: for<'de> Deserialize<'de>>(
+        graph: &MemoryGraphAdapter,
+        request: GraphRequest,
+    ) -> GraphResult<T> {
+        let envelope = GraphEnvelope {
+            database: SHARED_DATABASE,
+            request,
+        };
+        let sent: GraphEnvelope =
+            serde_json::from_str(&serde_json::to_string(&envelope).unwrap()).unwrap();
+
+        let response = block_on(dispatch(graph, sent.request));
+        let received: GraphResponse =
+            serde_json::from_str(&serde_json::to_string(&response).unwrap()).unwrap();
+        received.into_result()
+    }
+
+    #[test]
+    fn test_requests_roundtrip_through_json() {
+        let graph = MemoryGraphAdapter::isolated().unwrap();
+
+        let id: Id = call(
+            &graph,
+            GraphRequest::CreateNode {
+                vault_id: "v".to_string(),
+                node_type: "doc".to_string(),
+                content: "hello".to_string(),
+                labels: vec!["a".to_string()],
+                embedding: Some(vec![1.0; EMBEDDING_DIM]),
+                node_id: None,
+            },
+        )
+        .unwrap();
+
+        let nodes: Vec<GraphNode> = call(
+            &graph,
+            GraphRequest::ListNodesByType {
+                vault_id: "v".to_string(),
+                node_type: "doc".to_string(),
+                limit: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].id, id);
+
+        let stats: GraphStats = call(&graph, GraphRequest::Stats).unwrap();
+        assert_eq!(stats, GraphStats { nodes: 1, edges: 0 });
+    }
+
+    #[test]
+    fn test_errors_keep_their_kind() {
+        let graph = MemoryGraphAdapter::isolated().unwrap();
+
+        let result: GraphResult<Vec<GraphNode>> = call(
+            &graph,
+            GraphRequest::VectorSearch {
+                vault_id: "v".to_string(),
+                query_embedding: vec![1.0; 2],
+                max_results: 5,
+                search_quality: 50,
+                include_neighbors: false,
+            },
+        );
+        assert!(matches!(result, Err(GraphError::InvalidEmbedding(_))));
+    }
+
+    #[test]
+    fn test_envelope_wire_format() {
+        let json = serde_json::to_value(GraphEnvelope {
+            database: 3,
+            request: GraphRequest::ExportBackup {
+                vault_id: "v".to_string(),
+            },
+        })
+        .unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({ "database": 3, "method": "exportBackup", "vaultId": "v" })
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use crate::domain::graph::{GraphError, GraphResult, SearchQuery, SearchResult};
+use crate::domain::retry::CancellationToken;
+use crate::ports::graph::GraphPort;
+use crate::ports::StoragePort;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub const DEFAULT_GRAPH_MEMORY_BUDGET: usize = 16 * 1024 * 1024;
+const SCRATCH_DIRECTORY: &str = "graph_scratch";
+
+static MEMORY_BUDGET: AtomicUsize = AtomicUsize::new(DEFAULT_GRAPH_MEMORY_BUDGET);
+
+/// Approximate number of bytes search results may keep in memory before
+/// further pages are spilled to scratch storage.
+pub fn set_graph_memory_budget(bytes: usize) {
+    MEMORY_BUDGET.store(bytes, Ordering::SeqCst);
+}
+
+pub fn graph_memory_budget() -> usize {
+    MEMORY_BUDGET.load(Ordering::SeqCst)
+}
+
+fn estimated_size(result: &SearchResult) -> usize {
+    let node = &result.node;
+    let neighbors: usize = result
+        .neighbors
+        .iter()
+        .map(|neighbor| {
+            neighbor.node.content.len() + neighbor.node.node_type.len() + neighbor.edge_type.len()
+        })
+        .sum();
+
+    node.content.len()
+        + node.node_type.len()
+        + node.labels.iter().map(String::len).sum::<usize>()
+        + neighbors
+        + std::mem::size_of::<SearchResult>()
+}
+
+/// Paged view over the results of a vector search.
+///
+/// Opening the stream walks the ranking page by page. Pages are kept in
+/// memory until the configured budget is reached; the rest is written to
+/// scratch storage and read back as the caller advances, so the whole
+/// result set is never resident at once.
+pub struct SearchResultStream<S: StoragePort> {
+    storage: S,
+    scratch_path: String,
+    in_memory: VecDeque<Vec<SearchResult>>,
+    spilled: VecDeque<String>,
+}
+
+impl<S: StoragePort> SearchResultStream<S> {
+    /// Cancelling `cancellation` stops the walk between pages and removes
+    /// whatever was spilled so far.
+    pub async fn open<G: GraphPort>(
+        graph: &G,
+        storage: S,
+        vault_id: &str,
+        query: SearchQuery,
+        page_size: usize,
+        cancellation: &CancellationToken,
+    ) -> GraphResult<Self> {
+        let page_size = page_size.max(1);
+        let budget = graph_memory_budget();
+
+        let mut stream = Self {
+            storage,
+            scratch_path: format!("{SCRATCH_DIRECTORY}/{:016x}", rand::random::<u64>()),
+            in_memory: VecDeque::new(),
+            spilled: VecDeque::new(),
+        };
+
+        let mut resident = 0;
+        let mut offset = 0;
+
+        while offset < query.max_results {
+            if cancellation.is_cancelled() {
+                stream.close().await;
+                return Err(GraphError::Cancelled);
+            }
+
+            let limit = page_size.min(query.max_results - offset);
+            let page = graph
+                .vector_search_page(vault_id, &query, offset, limit)
+                .await?;
+            let fetched = page.len();
+
+            if fetched == 0 {
+                break;
+            }
+
+            let size: usize = page.iter().map(estimated_size).sum();
+            if resident + size <= budget || stream.in_memory.is_empty() {
+                resident += size;
+                stream.in_memory.push_back(page);
+            } else if let Err(e) = stream.spill(page).await {
+                stream.close().await;
+                return Err(e);
+            }
+
+            offset += fetched;
+            if fetched < limit {
+                break;
+            }
+        }
+
+        Ok(stream)
+    }
+
+    async fn spill(&mut self, page: Vec<SearchResult>) -> GraphResult<()> {
+        if self.spilled.is_empty() {
+            self.storage
+                .create_directory(&self.scratch_path)
+                .await
+                .map_err(|e| {
+                    GraphError::DatabaseError(format!("Failed to create scratch space: {}", e))
+                })?;
+        }
+
+        let json = serde_json::to_string(&page).map_err(|e| {
+            GraphError::SerializationError(format!("Failed to spill search results: {}", e))
+        })?;
+
+        let path = format!("{}/{}.json", self.scratch_path, self.spilled.len());
+        self.storage
+            .write_file(&path, &json)
+            .await
+            .map_err(|e| GraphError::DatabaseError(format!("Failed to spill page: {}", e)))?;
+
+        self.spilled.push_back(path);
+        Ok(())
+    }
+
+    /// Number of pages written to scratch storage instead of memory.
+    pub fn spilled_pages(&self) -> usize {
+        self.spilled.len()
+    }
+
+    /// Returns the next page, or `None` once every result has been consumed.
+    pub async fn next_page(&mut self) -> GraphResult<Option<Vec<SearchResult>>> {
+        if let Some(page) = self.in_memory.pop_front() {
+            return Ok(Some(page));
+        }
+
+        let Some(path) = self.spilled.pop_front() else {
+            self.close().await;
+            return Ok(None);
+        };
+
+        let json = self.storage.read_file(&path).await.map_err(|e| {
+            GraphError::DatabaseError(format!("Failed to read spilled page: {}", e))
+        })?;
+        let _ = self.storage.delete_file(&path).await;
+
+        serde_json::from_str(&json).map(Some).map_err(|e| {
+            GraphError::SerializationError(format!("Failed to read spilled page: {}", e))
+        })
+    }
+
+    /// Approximate bytes of results held in memory, spilled pages excluded.
+    pub fn resident_bytes(&self) -> usize {
+        self.in_memory.iter().flatten().map(estimated_size).sum()
+    }
+
+    /// Drops every remaining page and removes the scratch files.
+    pub async fn close(&mut self) {
+        self.in_memory.clear();
+
+        if self
+            .storage
+            .directory_exists(&self.scratch_path)
+            .await
+            .unwrap_or(false)
+        {
+            let _ = self.storage.delete_directory(&self.scratch_path).await;
+        }
+        self.spilled.clear();
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::adapters::wasm::OpfsStorage;
+    use crate::adapters::Graph;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    async fn test_stream_spills_pages_beyond_budget() {
+        let graph = Graph::new().unwrap();
+        let vault_id = "test_vault_streaming";
+
+        for i in 0..3 {
+            let mut embedding = vec![0.0; 384];
+            embedding[0] = 1.0;
+            embedding[1] = i as f32;
+            graph
+                .create_node(
+                    vault_id,
+                    "memory",
+                    format!("Doc {i}"),
+                    vec![],
+                    Some(embedding),
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+
+        let mut query_embedding = vec![0.0; 384];
+        query_embedding[0] = 1.0;
+        let query = SearchQuery {
+            query_embedding,
+            max_results: 3,
+            search_quality: 100,
+            include_neighbors: true,
+        };
+
+        set_graph_memory_budget(1);
+        let mut stream = SearchResultStream::open(
+            &graph,
+            OpfsStorage::new(),
+            vault_id,
+            query,
+            1,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        set_graph_memory_budget(DEFAULT_GRAPH_MEMORY_BUDGET);
+
+        assert_eq!(stream.spilled_pages(), 2);
+
+        let mut contents = Vec::new();
+        while let Some(page) = stream.next_page().await.unwrap() {
+            contents.extend(page.into_iter().map(|result| result.node.content));
+        }
+
+        assert_eq!(contents, vec!["Doc 0", "Doc 1", "Doc 2"]);
+        assert!(!OpfsStorage::new()
+            .directory_exists(&stream.scratch_path)
+            .await
+            .unwrap());
+    }
+}
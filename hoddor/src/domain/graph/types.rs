@@ -1,5 +1,11 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+
+/// Dimension of node and query embeddings (384 for sentence-transformers
+/// models). Every graph backend enforces it, so an application can move
+/// between them.
+pub const EMBEDDING_DIM: usize = 384;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Id(pub Uuid);
 
@@ -32,6 +38,16 @@ pub struct GraphNode {
     pub labels: Vec<String>,
     pub embedding: Option<Vec<f32>>,
     pub created_at: u64,
+    /// Times the node was returned by [`recall`](super::recall::recall).
+    #[serde(default)]
+    pub access_count: u64,
+    /// When it was last returned that way, 0 if never.
+    #[serde(default)]
+    pub last_accessed_at: u64,
+    /// [`embedding_version`](super::maintenance::embedding_version) the
+    /// embedding was computed with.
+    #[serde(default)]
+    pub embedding_version: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,21 +61,60 @@ pub struct GraphEdge {
     pub created_at: u64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
     pub node: GraphNode,
     pub distance: f32,
     pub neighbors: Vec<NeighborNode>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NeighborNode {
     pub node: GraphNode,
     pub edge_type: String,
     pub weight: f32,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Edges of a node followed by [`get_neighbors`](crate::ports::GraphPort::get_neighbors).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EdgeDirection {
+    /// Edges starting at the node.
+    Outgoing,
+    /// Edges ending at the node.
+    Incoming,
+    #[default]
+    Both,
+}
+
+/// Which neighbors of a node to return. The default returns all of them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct NeighborFilter {
+    pub direction: EdgeDirection,
+    /// Edge types to follow; empty follows every type.
+    pub edge_types: Vec<String>,
+    /// Edges lighter than this are not followed.
+    pub min_weight: Option<f32>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pub query_embedding: Vec<f32>,
+    pub max_results: usize,
+    pub search_quality: usize,
+    pub include_neighbors: bool,
+}
+
+/// Size of a graph database, all vaults included.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GraphStats {
+    pub nodes: usize,
+    pub edges: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GraphBackup {
     pub version: u32,
     pub nodes: Vec<GraphNode>,
@@ -0,0 +1,192 @@
+//! Storage timings and sizes, aggregated per vault.
+//!
+//! Every storage operation made through the platform is recorded here. Each
+//! one is also emitted as a `histogram.storage_io_ms` event, exported as a
+//! metric when OTLP export is on; operations slower than the configured
+//! threshold and files larger than the configured size are logged as
+//! warnings, as these are what makes operations stall on slow devices.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+pub const DEFAULT_SLOW_IO_MILLIS: u64 = 250;
+pub const DEFAULT_OVERSIZED_FILE_BYTES: u64 = 8 * 1024 * 1024;
+
+static SLOW_IO_MILLIS: AtomicU64 = AtomicU64::new(DEFAULT_SLOW_IO_MILLIS);
+static OVERSIZED_FILE_BYTES: AtomicU64 = AtomicU64::new(DEFAULT_OVERSIZED_FILE_BYTES);
+static STATS: Lazy<Mutex<BTreeMap<String, IoStats>>> = Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoOperation {
+    Read,
+    Write,
+    Delete,
+    CreateDirectory,
+    DeleteDirectory,
+    DirectoryExists,
+    List,
+    RenameDirectory,
+}
+
+impl IoOperation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IoOperation::Read => "read",
+            IoOperation::Write => "write",
+            IoOperation::Delete => "delete",
+            IoOperation::CreateDirectory => "create_directory",
+            IoOperation::DeleteDirectory => "delete_directory",
+            IoOperation::DirectoryExists => "directory_exists",
+            IoOperation::List => "list",
+            IoOperation::RenameDirectory => "rename_directory",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct IoStats {
+    pub reads: u64,
+    pub writes: u64,
+    /// Deletions, directory creations and renames, existence checks and
+    /// listings.
+    pub other_operations: u64,
+    pub failures: u64,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    pub total_millis: f64,
+    pub slowest_millis: f64,
+    pub slow_operations: u64,
+    pub oversized_files: u64,
+}
+
+/// Sets the duration above which an operation is logged as slow and the
+/// file size above which a read or write is logged as oversized.
+pub fn configure_io_warnings(slow_millis: u64, oversized_file_bytes: u64) {
+    SLOW_IO_MILLIS.store(slow_millis, Ordering::Relaxed);
+    OVERSIZED_FILE_BYTES.store(oversized_file_bytes, Ordering::Relaxed);
+}
+
+/// Records one storage operation on `path`, which took `millis` and moved
+/// `bytes` of file content.
+pub fn record(operation: IoOperation, path: &str, bytes: usize, millis: f64, succeeded: bool) {
+    let vault = vault_of(path);
+    let slow = millis > SLOW_IO_MILLIS.load(Ordering::Relaxed) as f64;
+    let oversized = bytes as u64 > OVERSIZED_FILE_BYTES.load(Ordering::Relaxed);
+
+    tracing::trace!(
+        histogram.storage_io_ms = millis,
+        operation = operation.as_str(),
+        vault,
+        bytes,
+        "Storage operation"
+    );
+    if slow {
+        tracing::warn!(
+            operation = operation.as_str(),
+            vault,
+            bytes,
+            millis,
+            "Slow storage operation"
+        );
+    }
+    if oversized {
+        tracing::warn!(
+            operation = operation.as_str(),
+            vault,
+            bytes,
+            "Oversized file"
+        );
+    }
+
+    let mut all = STATS.lock().unwrap_or_else(PoisonError::into_inner);
+    let stats = all.entry(vault.to_string()).or_default();
+
+    match operation {
+        IoOperation::Read => {
+            stats.reads += 1;
+            stats.bytes_read += bytes as u64;
+        }
+        IoOperation::Write => {
+            stats.writes += 1;
+            stats.bytes_written += bytes as u64;
+        }
+        _ => stats.other_operations += 1,
+    }
+    if !succeeded {
+        stats.failures += 1;
+    }
+    stats.total_millis += millis;
+    stats.slowest_millis = stats.slowest_millis.max(millis);
+    stats.slow_operations += u64::from(slow);
+    stats.oversized_files += u64::from(oversized);
+}
+
+/// Stats of `vault_name` since startup or the last reset.
+pub fn io_stats(vault_name: &str) -> IoStats {
+    STATS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(vault_name)
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Stats of every vault touched, keyed by vault name. Operations on the
+/// storage root, such as listing vaults, are under an empty name.
+pub fn all_io_stats() -> BTreeMap<String, IoStats> {
+    STATS.lock().unwrap_or_else(PoisonError::into_inner).clone()
+}
+
+pub fn reset_io_stats() {
+    STATS.lock().unwrap_or_else(PoisonError::into_inner).clear();
+}
+
+fn vault_of(path: &str) -> &str {
+    match path.split('/').next() {
+        Some(".") | None => "",
+        Some(vault) => vault,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_are_aggregated_per_vault() {
+        record(
+            IoOperation::Write,
+            "io_stats_test/notes.hoddor",
+            10,
+            1.0,
+            true,
+        );
+        record(
+            IoOperation::Read,
+            "io_stats_test/notes.hoddor",
+            10,
+            3.0,
+            true,
+        );
+        record(
+            IoOperation::Read,
+            "io_stats_test/metadata.json",
+            0,
+            DEFAULT_SLOW_IO_MILLIS as f64 + 1.0,
+            false,
+        );
+        record(IoOperation::List, ".", 0, 1.0, true);
+
+        let stats = io_stats("io_stats_test");
+        assert_eq!(stats.reads, 2);
+        assert_eq!(stats.writes, 1);
+        assert_eq!(stats.bytes_read, 10);
+        assert_eq!(stats.failures, 1);
+        assert_eq!(stats.slow_operations, 1);
+        assert_eq!(stats.slowest_millis, DEFAULT_SLOW_IO_MILLIS as f64 + 1.0);
+        assert!(all_io_stats().contains_key(""));
+    }
+}
@@ -1,5 +1,16 @@
 pub mod authentication;
+pub mod clock_skew;
 pub mod crypto;
+pub mod error_messages;
+pub mod error_report;
+pub mod io_stats;
+pub mod privacy;
+pub mod progress;
+pub mod recent_events;
+pub mod retry;
+pub mod test_mode;
+pub mod trace_context;
+pub mod usage_stats;
 pub mod vault;
 
 #[cfg(feature = "graph")]
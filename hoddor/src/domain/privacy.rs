@@ -0,0 +1,14 @@
+//! Noise for differentially private releases.
+
+/// Sample of a Laplace distribution centred on 0 with the given `scale`,
+/// drawn by inverting its cumulative distribution. Releasing a value plus
+/// `laplace(sensitivity / epsilon)` is `epsilon`-differentially private
+/// when one record changes the value by at most `sensitivity`.
+///
+/// Seeded in test mode like every other random value.
+pub fn laplace(scale: f64) -> f64 {
+    let bits = u64::from_le_bytes(crate::domain::test_mode::random_bytes::<8>());
+    let uniform = (bits >> 11) as f64 / (1u64 << 53) as f64 - 0.5;
+
+    -scale * uniform.signum() * (1.0 - 2.0 * uniform.abs()).max(f64::MIN_POSITIVE).ln()
+}
@@ -0,0 +1,38 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressUnit {
+    Bytes,
+    Items,
+}
+
+/// Snapshot of a long-running operation, reported after each step so a UI
+/// can render a progress bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Progress {
+    pub processed: u64,
+    pub total: u64,
+    pub unit: ProgressUnit,
+}
+
+impl Progress {
+    pub fn items(processed: usize, total: usize) -> Self {
+        Self {
+            processed: processed as u64,
+            total: total as u64,
+            unit: ProgressUnit::Items,
+        }
+    }
+
+    pub fn bytes(processed: usize, total: usize) -> Self {
+        Self {
+            processed: processed as u64,
+            total: total as u64,
+            unit: ProgressUnit::Bytes,
+        }
+    }
+}
+
+/// Progress sink for callers that do not render any.
+pub fn ignore_progress(_: Progress) {}
@@ -0,0 +1,74 @@
+//! The last events emitted through the platform notifier.
+//!
+//! Events are otherwise only posted to whoever listens at the time, so a
+//! dashboard opened after the fact would never see them. The most recent
+//! [`RECENT_EVENTS_CAPACITY`] are kept here, across all vaults.
+
+use crate::notifications::EventType;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Mutex, PoisonError};
+
+pub const RECENT_EVENTS_CAPACITY: usize = 100;
+
+static EVENTS: Lazy<Mutex<VecDeque<RecordedEvent>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(RECENT_EVENTS_CAPACITY)));
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecordedEvent {
+    pub vault_name: String,
+    pub event: EventType,
+    pub detail: String,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: f64,
+}
+
+pub fn record(vault_name: &str, event: EventType, detail: &str, timestamp: f64) {
+    let mut events = EVENTS.lock().unwrap_or_else(PoisonError::into_inner);
+    if events.len() == RECENT_EVENTS_CAPACITY {
+        events.pop_front();
+    }
+    events.push_back(RecordedEvent {
+        vault_name: vault_name.to_string(),
+        event,
+        detail: detail.to_string(),
+        timestamp,
+    });
+}
+
+/// Up to `limit` of the recorded events, most recent first.
+pub fn recent_events(limit: usize) -> Vec<RecordedEvent> {
+    EVENTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .rev()
+        .take(limit)
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_the_most_recent_events_are_kept() {
+        for i in 0..RECENT_EVENTS_CAPACITY + 5 {
+            record(
+                "recent_events_test",
+                EventType::BackupCompleted,
+                &i.to_string(),
+                i as f64,
+            );
+        }
+
+        let events = recent_events(RECENT_EVENTS_CAPACITY + 5);
+        assert!(events.len() <= RECENT_EVENTS_CAPACITY);
+        assert!(events
+            .iter()
+            .all(|event| event.vault_name != "recent_events_test" || event.timestamp >= 5.0));
+        assert_eq!(recent_events(1).len(), 1);
+    }
+}
@@ -0,0 +1,276 @@
+use crate::platform::Platform;
+use once_cell::sync::Lazy;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+static DEFAULT_POLICY: Lazy<Mutex<RetryPolicy>> = Lazy::new(|| Mutex::new(RetryPolicy::default()));
+
+/// Replaces the policy vault, sync and signaling operations retry with.
+pub fn set_default_retry_policy(policy: RetryPolicy) {
+    *DEFAULT_POLICY
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = policy;
+}
+
+pub fn default_retry_policy() -> RetryPolicy {
+    DEFAULT_POLICY
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Cooperative cancellation flag shared between a caller and a running
+/// [`RetryPolicy`]. Cancelling takes effect before the next attempt or once
+/// the current backoff delay elapsed.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RetryError<E> {
+    Cancelled,
+    Exhausted { attempts: u32, last_error: E },
+}
+
+impl<E: fmt::Display> fmt::Display for RetryError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetryError::Cancelled => write!(f, "Operation was cancelled"),
+            RetryError::Exhausted {
+                attempts,
+                last_error,
+            } => write!(f, "Gave up after {attempts} attempt(s): {last_error}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for RetryError<E> {}
+
+/// Exponential backoff with jitter.
+///
+/// The delay before attempt `n + 1` is `initial_delay_ms * multiplier^(n - 1)`,
+/// capped at `max_delay_ms`, plus a random `0..=jitter_ms` so callers woken
+/// together do not retry in lockstep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_delay_ms: u32,
+    pub max_delay_ms: u32,
+    pub multiplier: f64,
+    pub jitter_ms: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 10,
+            initial_delay_ms: 50,
+            max_delay_ms: 1000,
+            multiplier: 1.5,
+            jitter_ms: 50,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Runs the operation exactly once.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_backoff(
+        mut self,
+        initial_delay_ms: u32,
+        max_delay_ms: u32,
+        multiplier: f64,
+    ) -> Self {
+        self.initial_delay_ms = initial_delay_ms;
+        self.max_delay_ms = max_delay_ms.max(initial_delay_ms);
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter_ms: u32) -> Self {
+        self.jitter_ms = jitter_ms;
+        self
+    }
+
+    /// Delay before the attempt following the `attempt`-th failure, without
+    /// jitter.
+    pub fn backoff_delay(&self, attempt: u32) -> u32 {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = f64::from(self.initial_delay_ms) * self.multiplier.powi(exponent);
+
+        delay.min(f64::from(self.max_delay_ms)) as u32
+    }
+
+    fn delay_with_jitter(&self, attempt: u32) -> u32 {
+        let jitter = match self.jitter_ms {
+            0 => 0,
+            max => rand::random::<u32>() % (max + 1),
+        };
+
+        self.backoff_delay(attempt).saturating_add(jitter)
+    }
+
+    /// Retries every error.
+    pub async fn run<T, E, F, Fut>(
+        &self,
+        platform: &Platform,
+        cancellation: &CancellationToken,
+        operation: F,
+    ) -> Result<T, RetryError<E>>
+    where
+        F: FnMut(u32) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.run_if(platform, cancellation, |_| true, operation)
+            .await
+    }
+
+    /// Calls `operation` with the 1-based attempt number until it succeeds,
+    /// returns an error `should_retry` rejects, the attempts run out or
+    /// `cancellation` fires.
+    pub async fn run_if<T, E, F, Fut, P>(
+        &self,
+        platform: &Platform,
+        cancellation: &CancellationToken,
+        should_retry: P,
+        mut operation: F,
+    ) -> Result<T, RetryError<E>>
+    where
+        F: FnMut(u32) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        P: Fn(&E) -> bool,
+    {
+        let max_attempts = self.max_attempts.max(1);
+        let mut attempt = 1;
+
+        loop {
+            if cancellation.is_cancelled() {
+                return Err(RetryError::Cancelled);
+            }
+
+            let last_error = match operation(attempt).await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+
+            if attempt >= max_attempts || !should_retry(&last_error) {
+                return Err(RetryError::Exhausted {
+                    attempts: attempt,
+                    last_error,
+                });
+            }
+
+            platform
+                .clock()
+                .sleep(self.delay_with_jitter(attempt))
+                .await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    fn fast_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy::default()
+            .with_max_attempts(max_attempts)
+            .with_backoff(0, 0, 1.0)
+            .with_jitter(0)
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = RetryPolicy::default().with_backoff(100, 300, 2.0);
+
+        assert_eq!(policy.backoff_delay(1), 100);
+        assert_eq!(policy.backoff_delay(2), 200);
+        assert_eq!(policy.backoff_delay(3), 300);
+        assert_eq!(policy.backoff_delay(30), 300);
+    }
+
+    #[test]
+    fn test_run_retries_until_success() {
+        let platform = Platform::new();
+
+        let result = block_on(fast_policy(5).run(
+            &platform,
+            &CancellationToken::new(),
+            |attempt| async move {
+                if attempt < 3 {
+                    Err("busy")
+                } else {
+                    Ok(attempt)
+                }
+            },
+        ));
+
+        assert_eq!(result, Ok(3));
+    }
+
+    #[test]
+    fn test_run_if_stops_on_permanent_error() {
+        let platform = Platform::new();
+
+        let result: Result<(), _> = block_on(fast_policy(5).run_if(
+            &platform,
+            &CancellationToken::new(),
+            |e: &&str| *e == "busy",
+            |_| async { Err("denied") },
+        ));
+
+        assert_eq!(
+            result,
+            Err(RetryError::Exhausted {
+                attempts: 1,
+                last_error: "denied"
+            })
+        );
+    }
+
+    #[test]
+    fn test_cancellation_stops_retrying() {
+        let platform = Platform::new();
+        let cancellation = CancellationToken::new();
+
+        let result: Result<(), RetryError<&str>> =
+            block_on(fast_policy(10).run(&platform, &cancellation, |attempt| {
+                if attempt == 2 {
+                    cancellation.cancel();
+                }
+                async { Err("busy") }
+            }));
+
+        assert_eq!(result, Err(RetryError::Cancelled));
+    }
+}
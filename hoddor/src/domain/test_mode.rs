@@ -0,0 +1,119 @@
+//! Deterministic mode for end-to-end tests of apps embedding hoddor.
+//!
+//! While it is on, every new [`Platform`](crate::Platform) reads the time
+//! from a clock the test sets and advances, on which sleeps return at once;
+//! pairing goes through an in-memory transport instead of WebRTC; and the
+//! identifiers hoddor makes up (peer ids, chunk ids, journal transaction
+//! ids) come from a seeded generator. Two runs of the same test then see the
+//! same values without a signaling server or real timers.
+//!
+//! Only builds with the `test-mode` feature can turn it on.
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// 2023-11-14T22:13:20Z, the default start of the test clock.
+pub const DEFAULT_START_MILLIS: u64 = 1_700_000_000_000;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static NOW_MILLIS: AtomicU64 = AtomicU64::new(DEFAULT_START_MILLIS);
+static NEXT_PEER: AtomicU64 = AtomicU64::new(1);
+static RNG: Lazy<Mutex<StdRng>> = Lazy::new(|| Mutex::new(StdRng::seed_from_u64(0)));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TestModeOptions {
+    /// Time of the test clock, in milliseconds since the Unix epoch.
+    pub start_millis: u64,
+    /// Seed of the identifiers.
+    pub seed: u64,
+}
+
+impl Default for TestModeOptions {
+    fn default() -> Self {
+        Self {
+            start_millis: DEFAULT_START_MILLIS,
+            seed: 0,
+        }
+    }
+}
+
+/// Turns test mode on, or restarts it: the clock is set back to
+/// `start_millis` and identifiers start over from `seed`.
+#[cfg(feature = "test-mode")]
+pub fn enable_test_mode(options: TestModeOptions) {
+    NOW_MILLIS.store(options.start_millis, Ordering::SeqCst);
+    NEXT_PEER.store(1, Ordering::SeqCst);
+    *RNG.lock() = StdRng::seed_from_u64(options.seed);
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+#[cfg(feature = "test-mode")]
+pub fn disable_test_mode() {
+    ENABLED.store(false, Ordering::SeqCst);
+}
+
+pub fn is_test_mode() -> bool {
+    cfg!(feature = "test-mode") && ENABLED.load(Ordering::SeqCst)
+}
+
+/// Milliseconds since the Unix epoch on the test clock.
+pub fn test_clock_millis() -> u64 {
+    NOW_MILLIS.load(Ordering::SeqCst)
+}
+
+pub fn set_test_clock(millis: u64) {
+    NOW_MILLIS.store(millis, Ordering::SeqCst);
+}
+
+pub fn advance_test_clock(millis: u64) {
+    NOW_MILLIS.fetch_add(millis, Ordering::SeqCst);
+}
+
+/// Random bytes, drawn from the seeded generator in test mode.
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    if is_test_mode() {
+        RNG.lock().fill_bytes(&mut bytes);
+    } else {
+        rand::thread_rng().fill_bytes(&mut bytes);
+    }
+    bytes
+}
+
+/// A new peer id: random, or `peer-1`, `peer-2`, ... in test mode.
+pub fn new_peer_id() -> String {
+    if is_test_mode() {
+        format!("peer-{}", NEXT_PEER.fetch_add(1, Ordering::SeqCst))
+    } else {
+        hex::encode(random_bytes::<16>())
+    }
+}
+
+#[cfg(all(test, feature = "test-mode"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifiers_repeat_for_the_same_seed() {
+        let run = |seed| {
+            enable_test_mode(TestModeOptions {
+                seed,
+                ..Default::default()
+            });
+            let ids = (new_peer_id(), new_peer_id(), random_bytes::<16>());
+            disable_test_mode();
+            ids
+        };
+
+        let first = run(7);
+        assert_eq!(first.0, "peer-1");
+        assert_eq!(first.1, "peer-2");
+        assert_eq!(run(7), first);
+        assert_ne!(run(8).2, first.2);
+    }
+}
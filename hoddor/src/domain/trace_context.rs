@@ -0,0 +1,124 @@
+//! W3C trace context carried by signaling and sync messages.
+//!
+//! Browsers have no exporter of their own, so a peer only needs the ids: it
+//! stamps outgoing messages with a `traceparent`, the signaling server
+//! continues that trace in its relay spans, and the receiving peer records
+//! the trace id on its own spans so the whole exchange can be correlated.
+
+use rand::RngCore;
+
+const VERSION: &str = "00";
+const SAMPLED: &str = "01";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+}
+
+impl TraceContext {
+    /// Starts a new trace.
+    pub fn new_root() -> Self {
+        let mut trace_id = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut trace_id);
+
+        Self {
+            trace_id,
+            span_id: random_span_id(),
+        }
+    }
+
+    /// A new span in the same trace, for the next message sent.
+    pub fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id,
+            span_id: random_span_id(),
+        }
+    }
+
+    /// Parses a `traceparent` header value. Unknown versions are accepted as
+    /// long as the fields they share with version `00` are well formed.
+    pub fn parse(traceparent: &str) -> Option<Self> {
+        let mut fields = traceparent.trim().split('-');
+        let version = fields.next()?;
+        let trace_id = fields.next()?;
+        let span_id = fields.next()?;
+        let flags = fields.next()?;
+
+        if version.len() != 2 || version == "ff" || flags.len() != 2 {
+            return None;
+        }
+        if version == VERSION && fields.next().is_some() {
+            return None;
+        }
+
+        let mut context = Self {
+            trace_id: [0u8; 16],
+            span_id: [0u8; 8],
+        };
+        hex::decode_to_slice(trace_id, &mut context.trace_id).ok()?;
+        hex::decode_to_slice(span_id, &mut context.span_id).ok()?;
+
+        if context.trace_id == [0u8; 16] || context.span_id == [0u8; 8] {
+            return None;
+        }
+
+        Some(context)
+    }
+
+    pub fn to_traceparent(&self) -> String {
+        format!(
+            "{VERSION}-{}-{}-{SAMPLED}",
+            hex::encode(self.trace_id),
+            hex::encode(self.span_id)
+        )
+    }
+
+    /// The trace id in lowercase hex, as shown by trace backends.
+    pub fn trace_id(&self) -> String {
+        hex::encode(self.trace_id)
+    }
+}
+
+fn random_span_id() -> [u8; 8] {
+    let mut span_id = [0u8; 8];
+    while span_id == [0u8; 8] {
+        rand::thread_rng().fill_bytes(&mut span_id);
+    }
+    span_id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceparent_roundtrip_and_validation() {
+        let root = TraceContext::new_root();
+        let child = root.child();
+        assert_eq!(child.trace_id(), root.trace_id());
+        assert_eq!(TraceContext::parse(&child.to_traceparent()), Some(child));
+
+        let parsed =
+            TraceContext::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+        assert_eq!(parsed.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+
+        assert!(
+            TraceContext::parse("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-x")
+                .is_some()
+        );
+        assert!(
+            TraceContext::parse("00-00000000000000000000000000000000-00f067aa0ba902b7-01")
+                .is_none()
+        );
+        assert!(
+            TraceContext::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-x")
+                .is_none()
+        );
+        assert!(
+            TraceContext::parse("ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+                .is_none()
+        );
+        assert!(TraceContext::parse("garbage").is_none());
+    }
+}
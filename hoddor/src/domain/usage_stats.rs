@@ -0,0 +1,219 @@
+//! Opt-in usage statistics, aggregated on the device.
+//!
+//! Once turned on, successful namespace reads, writes and removals add to
+//! counters of operations, payload sizes and latencies, the last two
+//! bucketed. Neither vault names, namespaces nor individual events are
+//! kept: the only way out is [`aggregated_stats`], which adds Laplace noise
+//! to every counter and spends part of a privacy budget. Once the budget is
+//! spent, no more statistics are released until usage stats are turned on
+//! again, which also starts the counters over.
+
+use crate::domain::privacy::laplace;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+
+/// Upper bounds of the payload size buckets, in bytes.
+const SIZE_BUCKETS: [(u64, &str); 4] = [
+    (1024, "<1KiB"),
+    (64 * 1024, "1KiB-64KiB"),
+    (1024 * 1024, "64KiB-1MiB"),
+    (u64::MAX, ">=1MiB"),
+];
+
+/// Upper bounds of the latency buckets, in milliseconds.
+const LATENCY_BUCKETS: [(f64, &str); 4] = [
+    (10.0, "<10ms"),
+    (100.0, "10-100ms"),
+    (1000.0, "100ms-1s"),
+    (f64::INFINITY, ">=1s"),
+];
+
+/// Counters one operation adds to: its kind, size and latency.
+const COUNTERS_PER_OPERATION: f64 = 3.0;
+
+static STATE: Lazy<Mutex<Option<UsageState>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageOperation {
+    Read,
+    Write,
+    Remove,
+}
+
+impl UsageOperation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UsageOperation::Read => "read",
+            UsageOperation::Write => "write",
+            UsageOperation::Remove => "remove",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UsageStatsOptions {
+    /// Privacy loss of each release; lower values add more noise.
+    pub epsilon_per_release: f64,
+    /// Total privacy loss allowed until usage stats are turned on again.
+    pub budget: f64,
+}
+
+impl Default for UsageStatsOptions {
+    fn default() -> Self {
+        Self {
+            epsilon_per_release: 0.5,
+            budget: 5.0,
+        }
+    }
+}
+
+/// Noisy counters, keyed by operation kind and bucket label.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AggregatedStats {
+    pub operations: BTreeMap<&'static str, u64>,
+    pub payload_sizes: BTreeMap<&'static str, u64>,
+    pub latencies: BTreeMap<&'static str, u64>,
+    /// Budget left after this release.
+    pub remaining_budget: f64,
+}
+
+#[derive(Debug)]
+struct UsageState {
+    options: UsageStatsOptions,
+    spent: f64,
+    operations: BTreeMap<&'static str, u64>,
+    payload_sizes: BTreeMap<&'static str, u64>,
+    latencies: BTreeMap<&'static str, u64>,
+}
+
+/// Turns usage stats on with a fresh budget and empty counters.
+pub fn enable_usage_stats(options: UsageStatsOptions) {
+    *STATE.lock().unwrap_or_else(PoisonError::into_inner) = Some(UsageState {
+        options,
+        spent: 0.0,
+        operations: BTreeMap::new(),
+        payload_sizes: BTreeMap::new(),
+        latencies: BTreeMap::new(),
+    });
+}
+
+/// Turns usage stats off and drops the counters.
+pub fn disable_usage_stats() {
+    *STATE.lock().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+pub fn is_usage_stats_enabled() -> bool {
+    STATE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .is_some()
+}
+
+/// Counts one successful operation that moved `bytes` of payload in
+/// `millis`. Does nothing while usage stats are off.
+pub fn record(operation: UsageOperation, bytes: usize, millis: f64) {
+    let mut state = STATE.lock().unwrap_or_else(PoisonError::into_inner);
+    let Some(state) = state.as_mut() else {
+        return;
+    };
+
+    *state.operations.entry(operation.as_str()).or_default() += 1;
+    *state
+        .payload_sizes
+        .entry(size_bucket(bytes as u64))
+        .or_default() += 1;
+    *state.latencies.entry(latency_bucket(millis)).or_default() += 1;
+}
+
+/// Releases the counters with noise added, spending `epsilon_per_release`
+/// of the budget. `None` while usage stats are off or once the budget
+/// cannot cover another release.
+pub fn aggregated_stats() -> Option<AggregatedStats> {
+    let mut state = STATE.lock().unwrap_or_else(PoisonError::into_inner);
+    let state = state.as_mut()?;
+
+    let epsilon = state.options.epsilon_per_release;
+    if epsilon <= 0.0 || state.spent + epsilon > state.options.budget {
+        return None;
+    }
+    state.spent += epsilon;
+
+    let scale = COUNTERS_PER_OPERATION / epsilon;
+    let operations = [
+        UsageOperation::Read,
+        UsageOperation::Write,
+        UsageOperation::Remove,
+    ]
+    .map(UsageOperation::as_str);
+    let sizes = SIZE_BUCKETS.map(|(_, label)| label);
+    let latencies = LATENCY_BUCKETS.map(|(_, label)| label);
+
+    Some(AggregatedStats {
+        operations: noisy(&state.operations, &operations, scale),
+        payload_sizes: noisy(&state.payload_sizes, &sizes, scale),
+        latencies: noisy(&state.latencies, &latencies, scale),
+        remaining_budget: state.options.budget - state.spent,
+    })
+}
+
+// Every label of a kind of counter, so that a missing label does not tell
+// that nothing was counted under it.
+fn noisy(
+    counters: &BTreeMap<&'static str, u64>,
+    labels: &[&'static str],
+    scale: f64,
+) -> BTreeMap<&'static str, u64> {
+    labels
+        .iter()
+        .map(|label| {
+            let count = counters.get(label).copied().unwrap_or(0) as f64;
+            (*label, (count + laplace(scale)).round().max(0.0) as u64)
+        })
+        .collect()
+}
+
+fn size_bucket(bytes: u64) -> &'static str {
+    SIZE_BUCKETS
+        .iter()
+        .find(|(bound, _)| bytes < *bound)
+        .map_or(">=1MiB", |(_, label)| label)
+}
+
+fn latency_bucket(millis: f64) -> &'static str {
+    LATENCY_BUCKETS
+        .iter()
+        .find(|(bound, _)| millis < *bound)
+        .map_or(">=1s", |(_, label)| label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_releases_stop_once_the_budget_is_spent() {
+        enable_usage_stats(UsageStatsOptions {
+            epsilon_per_release: 1.0,
+            budget: 2.0,
+        });
+        for _ in 0..1000 {
+            record(UsageOperation::Write, 10 * 1024, 5.0);
+        }
+
+        let stats = aggregated_stats().unwrap();
+        assert_eq!(stats.remaining_budget, 1.0);
+        assert!(stats.operations["write"].abs_diff(1000) < 100);
+        assert!(stats.payload_sizes["1KiB-64KiB"].abs_diff(1000) < 100);
+        assert!(stats.latencies["<10ms"].abs_diff(1000) < 100);
+        assert!(stats.operations.contains_key("remove"));
+
+        assert!(aggregated_stats().is_some());
+        assert!(aggregated_stats().is_none());
+
+        disable_usage_stats();
+        assert!(aggregated_stats().is_none());
+    }
+}
@@ -0,0 +1,417 @@
+//! Read access to a single namespace.
+//!
+//! A namespace is normally encrypted to the identity that wrote it. Granting
+//! a public key access re-encrypts that one namespace to the key as well,
+//! and later writes keep encrypting to it, so the holder of the matching
+//! identity can read the namespace from a copy of the vault without being
+//! able to read any other.
+//!
+//! Revoking re-encrypts without the key. It cannot take back what the
+//! recipient already read or copied.
+//!
+//! Access is read-only: recipients can neither write the namespace nor
+//! change who else reads it, as either would re-encrypt it away from its
+//! writer.
+
+use super::chunks;
+use super::error::VaultError;
+use super::integrity::integrity_tag;
+use super::operations::{
+    current_timestamp, ensure_not_recipient, lock_namespace, namespace_keys, read_vault,
+    write_namespace,
+};
+use crate::platform::Platform;
+
+/// Lets the holder of `recipient_public_key` read `namespace`. The caller
+/// must be able to decrypt the namespace. A deduplicated namespace gets
+/// chunks of its own from then on, as its chunks may be shared with others.
+#[tracing::instrument(skip_all, fields(vault = vault_name, namespace = namespace))]
+pub async fn grant_namespace_access(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    namespace: &str,
+    recipient_public_key: &str,
+) -> Result<(), VaultError> {
+    share_namespace(
+        platform,
+        vault_name,
+        identity_private_key,
+        namespace,
+        &[recipient_public_key],
+    )
+    .await
+}
+
+/// Grants every key of `recipient_public_keys` access to `namespace` with a
+/// single re-encryption. Nothing is granted if any key is invalid.
+#[tracing::instrument(
+    skip_all,
+    fields(vault = vault_name, namespace = namespace, recipients = recipient_public_keys.len())
+)]
+pub async fn share_namespace(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    namespace: &str,
+    recipient_public_keys: &[&str],
+) -> Result<(), VaultError> {
+    let parsed = recipient_public_keys
+        .iter()
+        .map(|key| {
+            crate::domain::crypto::parse_recipient(platform, key)
+                .map_err(|e| VaultError::io_error(e.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    update_recipients(
+        platform,
+        vault_name,
+        identity_private_key,
+        namespace,
+        |recipients| {
+            for recipient in parsed {
+                if !recipients.contains(&recipient) {
+                    recipients.push(recipient);
+                }
+            }
+        },
+    )
+    .await
+}
+
+/// Withdraws access granted with [`grant_namespace_access`].
+#[tracing::instrument(skip_all, fields(vault = vault_name, namespace = namespace))]
+pub async fn revoke_namespace_access(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    namespace: &str,
+    recipient_public_key: &str,
+) -> Result<(), VaultError> {
+    update_recipients(
+        platform,
+        vault_name,
+        identity_private_key,
+        namespace,
+        |recipients| recipients.retain(|key| key != recipient_public_key),
+    )
+    .await
+}
+
+/// Public keys granted access to `namespace` alone.
+pub async fn namespace_recipients(
+    platform: &Platform,
+    vault_name: &str,
+    namespace: &str,
+) -> Result<Vec<String>, VaultError> {
+    let vault = read_vault(platform, vault_name).await?;
+
+    vault
+        .namespaces
+        .get(namespace)
+        .map(|namespace_data| namespace_data.recipients.clone())
+        .ok_or(VaultError::NamespaceNotFound)
+}
+
+async fn update_recipients(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    namespace: &str,
+    update: impl FnOnce(&mut Vec<String>),
+) -> Result<(), VaultError> {
+    let _guards = lock_namespace(platform, vault_name, namespace).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.observer {
+        return Err(VaultError::ObserverVault);
+    }
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    let namespace_data = vault
+        .namespaces
+        .get(namespace)
+        .ok_or(VaultError::NamespaceNotFound)?;
+    // The key of a time-locked namespace is held by escrow peers.
+    if let Some(timelock) = &namespace_data.timelock {
+        return Err(VaultError::TimeLocked(timelock.release_at));
+    }
+
+    let owner_public_key =
+        crate::domain::crypto::identity_to_public(platform, identity_private_key)
+            .map_err(|_| VaultError::InvalidPassword)?;
+    ensure_not_recipient(Some(namespace_data), &owner_public_key)?;
+    let plaintext =
+        chunks::decrypt_namespace(platform, &vault, namespace_data, identity_private_key).await?;
+
+    let mut recipients = namespace_data.recipients.clone();
+    update(&mut recipients);
+    recipients.retain(|key| *key != owner_public_key);
+    if recipients == namespace_data.recipients {
+        return Ok(());
+    }
+
+    let (payload, compression) =
+        super::compression::compress(namespace_data.compression, &plaintext);
+    let keys = namespace_keys(&owner_public_key, &recipients);
+    let (data, chunk_ids) = chunks::encrypt_payload(platform, &mut vault, &keys, &payload).await?;
+    // The MAC key must not stay readable by revoked recipients either.
+    let integrity = integrity_tag(platform, &owner_public_key, &keys, &plaintext).await?;
+    let now = current_timestamp(platform);
+
+    if let Some(namespace_data) = vault.namespaces.get_mut(namespace) {
+        namespace_data.data = data;
+        namespace_data.chunks = chunk_ids;
+        namespace_data.recipients = recipients;
+        namespace_data.updated_at = Some(now);
+        namespace_data.compression = compression;
+        namespace_data.integrity = Some(integrity);
+    }
+
+    write_namespace(platform, vault_name, &vault, namespace).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::crypto::{generate_identity, identity_to_public};
+    use crate::domain::vault::operations::{
+        create_vault, delete_vault, read_namespace, save_vault, upsert_namespace,
+    };
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_granted_recipient_reads_only_its_namespace() {
+        let platform = Platform::new();
+        let vault_name = "access_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let owner = generate_identity(&platform).unwrap();
+            let owner_public = identity_to_public(&platform, &owner).unwrap();
+            let reader = generate_identity(&platform).unwrap();
+            let reader_public = identity_to_public(&platform, &reader).unwrap();
+
+            for namespace in ["shared", "private"] {
+                upsert_namespace(
+                    &platform,
+                    vault_name,
+                    &owner_public,
+                    namespace,
+                    namespace.as_bytes().to_vec(),
+                    None,
+                    false,
+                )
+                .await
+                .unwrap();
+            }
+
+            grant_namespace_access(&platform, vault_name, &owner, "shared", &reader_public)
+                .await
+                .unwrap();
+            assert_eq!(
+                namespace_recipients(&platform, vault_name, "shared")
+                    .await
+                    .unwrap(),
+                vec![reader_public.clone()]
+            );
+            assert_eq!(
+                read_namespace(&platform, vault_name, &reader, "shared")
+                    .await
+                    .unwrap(),
+                b"shared"
+            );
+            assert!(read_namespace(&platform, vault_name, &reader, "private")
+                .await
+                .is_err());
+
+            // Rewrites keep the grant.
+            upsert_namespace(
+                &platform,
+                vault_name,
+                &owner_public,
+                "shared",
+                b"updated".to_vec(),
+                None,
+                true,
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                read_namespace(&platform, vault_name, &reader, "shared")
+                    .await
+                    .unwrap(),
+                b"updated"
+            );
+
+            revoke_namespace_access(&platform, vault_name, &owner, "shared", &reader_public)
+                .await
+                .unwrap();
+            assert!(read_namespace(&platform, vault_name, &reader, "shared")
+                .await
+                .is_err());
+            assert_eq!(
+                read_namespace(&platform, vault_name, &owner, "shared")
+                    .await
+                    .unwrap(),
+                b"updated"
+            );
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_share_namespace_with_several_recipients() {
+        let platform = Platform::new();
+        let vault_name = "access_share_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let owner = generate_identity(&platform).unwrap();
+            let owner_public = identity_to_public(&platform, &owner).unwrap();
+            let readers: Vec<String> = (0..2)
+                .map(|_| generate_identity(&platform).unwrap())
+                .collect();
+            let reader_keys: Vec<String> = readers
+                .iter()
+                .map(|reader| identity_to_public(&platform, reader).unwrap())
+                .collect();
+
+            upsert_namespace(
+                &platform,
+                vault_name,
+                &owner_public,
+                "shared",
+                b"shared".to_vec(),
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+
+            // An invalid key fails the whole call.
+            assert!(share_namespace(
+                &platform,
+                vault_name,
+                &owner,
+                "shared",
+                &[&reader_keys[0], "not-a-key"]
+            )
+            .await
+            .is_err());
+            assert!(namespace_recipients(&platform, vault_name, "shared")
+                .await
+                .unwrap()
+                .is_empty());
+
+            share_namespace(
+                &platform,
+                vault_name,
+                &owner,
+                "shared",
+                &[&reader_keys[0], &reader_keys[1], &owner_public],
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                namespace_recipients(&platform, vault_name, "shared")
+                    .await
+                    .unwrap(),
+                reader_keys
+            );
+            for reader in &readers {
+                assert_eq!(
+                    read_namespace(&platform, vault_name, reader, "shared")
+                        .await
+                        .unwrap(),
+                    b"shared"
+                );
+            }
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_recipients_cannot_reshare_or_rewrite() {
+        let platform = Platform::new();
+        let vault_name = "access_recipient_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let owner = generate_identity(&platform).unwrap();
+            let owner_public = identity_to_public(&platform, &owner).unwrap();
+            let reader = generate_identity(&platform).unwrap();
+            let reader_public = identity_to_public(&platform, &reader).unwrap();
+            let other = generate_identity(&platform).unwrap();
+            let other_public = identity_to_public(&platform, &other).unwrap();
+
+            upsert_namespace(
+                &platform,
+                vault_name,
+                &owner_public,
+                "shared",
+                b"shared".to_vec(),
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+            grant_namespace_access(&platform, vault_name, &owner, "shared", &reader_public)
+                .await
+                .unwrap();
+
+            assert!(matches!(
+                share_namespace(&platform, vault_name, &reader, "shared", &[&other_public]).await,
+                Err(VaultError::ReadOnlyRecipient)
+            ));
+            assert!(matches!(
+                upsert_namespace(
+                    &platform,
+                    vault_name,
+                    &reader_public,
+                    "shared",
+                    b"overwritten".to_vec(),
+                    None,
+                    true,
+                )
+                .await,
+                Err(VaultError::ReadOnlyRecipient)
+            ));
+
+            assert_eq!(
+                read_namespace(&platform, vault_name, &owner, "shared")
+                    .await
+                    .unwrap(),
+                b"shared"
+            );
+            assert_eq!(
+                namespace_recipients(&platform, vault_name, "shared")
+                    .await
+                    .unwrap(),
+                vec![reader_public]
+            );
+            assert!(read_namespace(&platform, vault_name, &other, "shared")
+                .await
+                .is_err());
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
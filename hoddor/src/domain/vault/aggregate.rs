@@ -0,0 +1,323 @@
+//! Differentially private counts and sums over append-only logs.
+//!
+//! The entries are decrypted and aggregated here, and only the aggregate
+//! leaves, with Laplace noise calibrated to how much one entry can move it.
+//! Every release spends its `epsilon` from the privacy budget of the log,
+//! stored next to the namespaces in `privacy_budgets.json`. A log has no
+//! budget until [`set_privacy_budget`] gives it one; once spent, queries
+//! fail with [`VaultError::PrivacyBudgetExhausted`].
+
+use super::error::VaultError;
+use super::log::read_log_range;
+use super::query::JsonPath;
+use crate::domain::privacy::laplace;
+use crate::platform::Platform;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const BUDGETS_FILENAME: &str = "privacy_budgets.json";
+
+/// Privacy loss allowed for the releases of one log, and how much of it the
+/// releases so far used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PrivacyBudget {
+    pub total: f64,
+    pub spent: f64,
+}
+
+impl PrivacyBudget {
+    pub fn remaining(&self) -> f64 {
+        (self.total - self.spent).max(0.0)
+    }
+}
+
+/// A released aggregate.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct NoisyAggregate {
+    /// The aggregate with noise added.
+    pub value: f64,
+    /// Privacy loss of this release.
+    pub epsilon: f64,
+    /// Budget of the log left after it.
+    pub remaining_budget: f64,
+}
+
+/// What [`noisy_sum`] adds up: the first number `path` selects in each
+/// entry, clamped to `lower..=upper`, released at privacy loss `epsilon`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SumQuery {
+    pub path: String,
+    pub lower: f64,
+    pub upper: f64,
+    pub epsilon: f64,
+}
+
+fn budgets_path(vault_name: &str) -> String {
+    format!("{vault_name}/{BUDGETS_FILENAME}")
+}
+
+fn budgets_lock_name(vault_name: &str) -> String {
+    budgets_path(vault_name)
+}
+
+async fn read_budgets(
+    platform: &Platform,
+    vault_name: &str,
+) -> Result<BTreeMap<String, PrivacyBudget>, VaultError> {
+    match platform
+        .storage()
+        .read_file(&budgets_path(vault_name))
+        .await
+    {
+        Ok(json) => serde_json::from_str(&json)
+            .map_err(|_| VaultError::serialization_error("Failed to parse privacy budgets")),
+        Err(_) => Ok(BTreeMap::new()),
+    }
+}
+
+async fn write_budgets(
+    platform: &Platform,
+    vault_name: &str,
+    budgets: &BTreeMap<String, PrivacyBudget>,
+) -> Result<(), VaultError> {
+    let json = serde_json::to_string(budgets)
+        .map_err(|_| VaultError::serialization_error("Failed to serialize privacy budgets"))?;
+
+    platform
+        .storage()
+        .write_file(&budgets_path(vault_name), &json)
+        .await
+}
+
+/// Sets the total privacy budget of `log_name`. What earlier releases
+/// spent stays spent.
+pub async fn set_privacy_budget(
+    platform: &Platform,
+    vault_name: &str,
+    log_name: &str,
+    total: f64,
+) -> Result<(), VaultError> {
+    if !total.is_finite() || total < 0.0 {
+        return Err(VaultError::io_error(
+            "Privacy budget must be a non-negative number",
+        ));
+    }
+
+    let _guard = platform
+        .locks()
+        .acquire(&budgets_lock_name(vault_name))
+        .await?;
+
+    let mut budgets = read_budgets(platform, vault_name).await?;
+    budgets.entry(log_name.to_string()).or_default().total = total;
+    write_budgets(platform, vault_name, &budgets).await
+}
+
+pub async fn privacy_budget(
+    platform: &Platform,
+    vault_name: &str,
+    log_name: &str,
+) -> Result<PrivacyBudget, VaultError> {
+    Ok(read_budgets(platform, vault_name)
+        .await?
+        .get(log_name)
+        .copied()
+        .unwrap_or_default())
+}
+
+// Takes `epsilon` from the budget of `log_name` before anything is
+// computed, so a failed query still counts against it.
+async fn spend(
+    platform: &Platform,
+    vault_name: &str,
+    log_name: &str,
+    epsilon: f64,
+) -> Result<f64, VaultError> {
+    if !epsilon.is_finite() || epsilon <= 0.0 {
+        return Err(VaultError::io_error("Epsilon must be a positive number"));
+    }
+
+    let _guard = platform
+        .locks()
+        .acquire(&budgets_lock_name(vault_name))
+        .await?;
+
+    let mut budgets = read_budgets(platform, vault_name).await?;
+    let budget = budgets.entry(log_name.to_string()).or_default();
+    if budget.spent + epsilon > budget.total {
+        return Err(VaultError::PrivacyBudgetExhausted(budget.remaining()));
+    }
+    budget.spent += epsilon;
+    let remaining = budget.remaining();
+
+    write_budgets(platform, vault_name, &budgets).await?;
+    Ok(remaining)
+}
+
+/// Number of entries of `log_name` appended from `from` to `to`, in
+/// milliseconds, released with noise at privacy loss `epsilon`.
+pub async fn noisy_count(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    log_name: &str,
+    from: i64,
+    to: i64,
+    epsilon: f64,
+) -> Result<NoisyAggregate, VaultError> {
+    let remaining_budget = spend(platform, vault_name, log_name, epsilon).await?;
+    let entries = read_log_range(
+        platform,
+        vault_name,
+        identity_private_key,
+        log_name,
+        from,
+        to,
+    )
+    .await?;
+
+    Ok(NoisyAggregate {
+        value: (entries.len() as f64 + laplace(1.0 / epsilon)).max(0.0),
+        epsilon,
+        remaining_budget,
+    })
+}
+
+/// Sum of `query` over the JSON entries of `log_name` appended from `from`
+/// to `to`, released with noise. Clamping bounds what one entry can add;
+/// entries without a number at the path contribute nothing.
+pub async fn noisy_sum(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    log_name: &str,
+    from: i64,
+    to: i64,
+    query: &SumQuery,
+) -> Result<NoisyAggregate, VaultError> {
+    let SumQuery {
+        lower,
+        upper,
+        epsilon,
+        ..
+    } = *query;
+
+    // Rejected before any budget is spent.
+    let path = JsonPath::parse(&query.path)?;
+    if !lower.is_finite() || !upper.is_finite() || lower > upper {
+        return Err(VaultError::io_error("Invalid bounds for a noisy sum"));
+    }
+
+    let remaining_budget = spend(platform, vault_name, log_name, epsilon).await?;
+    let entries = read_log_range(
+        platform,
+        vault_name,
+        identity_private_key,
+        log_name,
+        from,
+        to,
+    )
+    .await?;
+
+    let sum: f64 = entries
+        .iter()
+        .filter_map(|entry| {
+            let document: serde_json::Value = serde_json::from_slice(&entry.data).ok()?;
+            let value = path
+                .select(&document)
+                .into_iter()
+                .find_map(|v| v.as_f64())?;
+            Some(value.clamp(lower, upper))
+        })
+        .sum();
+    let sensitivity = lower.abs().max(upper.abs());
+
+    Ok(NoisyAggregate {
+        value: sum + laplace(sensitivity / epsilon),
+        epsilon,
+        remaining_budget,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::log::append_to_log;
+    use crate::domain::vault::operations::{create_vault, delete_vault, save_vault};
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_releases_spend_the_log_budget() {
+        let platform = Platform::new();
+        let vault_name = "aggregate_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+            for _ in 0..50 {
+                append_to_log(
+                    &platform,
+                    vault_name,
+                    &identity,
+                    "sales",
+                    br#"{"amount": 2}"#,
+                )
+                .await
+                .unwrap();
+            }
+
+            assert!(matches!(
+                noisy_count(&platform, vault_name, &identity, "sales", 0, i64::MAX, 0.5).await,
+                Err(VaultError::PrivacyBudgetExhausted(_))
+            ));
+
+            set_privacy_budget(&platform, vault_name, "sales", 1.0)
+                .await
+                .unwrap();
+
+            let count = noisy_count(&platform, vault_name, &identity, "sales", 0, i64::MAX, 0.5)
+                .await
+                .unwrap();
+            assert!((count.value - 50.0).abs() < 40.0);
+            assert_eq!(count.remaining_budget, 0.5);
+
+            let sum = noisy_sum(
+                &platform,
+                vault_name,
+                &identity,
+                "sales",
+                0,
+                i64::MAX,
+                &SumQuery {
+                    path: "$.amount".to_string(),
+                    lower: 0.0,
+                    upper: 10.0,
+                    epsilon: 0.5,
+                },
+            )
+            .await
+            .unwrap();
+            assert!((sum.value - 100.0).abs() < 400.0);
+
+            assert!(matches!(
+                noisy_count(&platform, vault_name, &identity, "sales", 0, i64::MAX, 0.1).await,
+                Err(VaultError::PrivacyBudgetExhausted(_))
+            ));
+            assert_eq!(
+                privacy_budget(&platform, vault_name, "sales")
+                    .await
+                    .unwrap(),
+                PrivacyBudget {
+                    total: 1.0,
+                    spent: 1.0
+                }
+            );
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
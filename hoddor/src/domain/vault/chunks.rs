@@ -0,0 +1,452 @@
+//! Content-addressed chunk store for deduplicated namespaces.
+//!
+//! A deduplicated namespace keeps an ordered list of chunk ids instead of an
+//! inline payload. Chunks are encrypted individually and stored once per
+//! vault under `<vault>/chunks/<id>`, so successive versions of a document
+//! only add the chunks that actually changed. Chunks travel with vault
+//! export and import; per-namespace sync operations still carry inline
+//! payloads only.
+//!
+//! Chunk ids are an HMAC of the plaintext keyed by a secret derived from the
+//! writer's private key, so only the writer can confirm a guess of a chunk's
+//! content from its id. Equal chunks still get equal ids, which shows which
+//! namespaces share content; vaults whose namespaces must stay
+//! indistinguishable should keep using regular namespaces.
+//!
+//! Regular namespaces larger than the split threshold are stored as chunks
+//! too, but under random ids: they are split only to keep each file small,
+//! never deduplicated, and their chunks belong to that namespace alone.
+
+use super::compression::decompress;
+use super::error::VaultError;
+use super::operations::READ_BATCH_SIZE;
+use super::types::{NamespaceData, Vault};
+use crate::domain::crypto::mac::hmac_sha256;
+use crate::platform::Platform;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::Mac;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use zeroize::Zeroizing;
+
+pub const CHUNK_SIZE: usize = 64 * 1024;
+pub const CHUNKS_DIRECTORY: &str = "chunks";
+
+const CHUNK_KEY_CONTEXT: &str = "hoddor 2025-01-01 chunk ids v1";
+
+/// Payload size above which a regular namespace is split. Namespace files
+/// written before the binary storage format held the ciphertext as a JSON
+/// array, about 3.5 times its size, so this kept them under the default
+/// oversized-file warning of 8 MiB.
+pub const DEFAULT_SPLIT_THRESHOLD: u64 = 2 * 1024 * 1024;
+
+static SPLIT_THRESHOLD: AtomicU64 = AtomicU64::new(DEFAULT_SPLIT_THRESHOLD);
+
+/// Sets the payload size above which namespaces are split into chunk
+/// files of that size. Zero stores every namespace in a single file.
+pub fn set_split_threshold(bytes: u64) {
+    SPLIT_THRESHOLD.store(bytes, Ordering::SeqCst);
+}
+
+pub fn split_threshold() -> u64 {
+    SPLIT_THRESHOLD.load(Ordering::SeqCst)
+}
+
+pub fn chunks_path(vault_name: &str) -> String {
+    format!("{vault_name}/{CHUNKS_DIRECTORY}")
+}
+
+pub fn chunk_id(identity_private_key: &str, chunk: &[u8]) -> String {
+    let key = Zeroizing::new(blake3::derive_key(
+        CHUNK_KEY_CONTEXT,
+        identity_private_key.as_bytes(),
+    ));
+    let mut mac = hmac_sha256(key.as_ref());
+    mac.update(chunk);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Id of a chunk that is never deduplicated, unrelated to its content.
+pub fn random_chunk_id() -> String {
+    hex::encode(crate::domain::test_mode::random_bytes::<16>())
+}
+
+/// Splits `data` into chunks, encrypts the ones the vault does not hold yet
+/// and returns the ordered ids describing `data`.
+pub async fn store_chunks(
+    platform: &Platform,
+    vault: &mut Vault,
+    identity_private_key: &str,
+    data: &[u8],
+) -> Result<Vec<String>, VaultError> {
+    let public_key = crate::domain::crypto::identity_to_public(platform, identity_private_key)
+        .map_err(|_| VaultError::InvalidPassword)?;
+    let mut ids = Vec::with_capacity(data.len().div_ceil(CHUNK_SIZE));
+
+    for chunk in data.chunks(CHUNK_SIZE) {
+        let id = chunk_id(identity_private_key, chunk);
+
+        if !vault.chunks.contains_key(&id) {
+            let encrypted =
+                crate::domain::crypto::encrypt_for_recipients(platform, chunk, &[&public_key])
+                    .await
+                    .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+            vault.chunks.insert(id.clone(), encrypted);
+        }
+
+        ids.push(id);
+    }
+
+    Ok(ids)
+}
+
+/// Encrypts `data` to `recipients` as a namespace payload: inline when it
+/// fits under the split threshold, otherwise as chunks added to `vault`.
+/// Returns the inline ciphertext and the ordered chunk ids, one of them
+/// empty.
+pub async fn encrypt_payload(
+    platform: &Platform,
+    vault: &mut Vault,
+    recipients: &[&str],
+    data: &[u8],
+) -> Result<(Vec<u8>, Vec<String>), VaultError> {
+    let threshold = split_threshold();
+
+    if threshold == 0 || data.len() as u64 <= threshold {
+        let encrypted = crate::domain::crypto::encrypt_for_recipients(platform, data, recipients)
+            .await
+            .map_err(|e| VaultError::io_error(e.to_string()))?;
+        return Ok((encrypted, Vec::new()));
+    }
+
+    let piece_size = usize::try_from(threshold).unwrap_or(usize::MAX);
+    let mut ids = Vec::with_capacity(data.len().div_ceil(piece_size));
+
+    for piece in data.chunks(piece_size) {
+        let encrypted = crate::domain::crypto::encrypt_for_recipients(platform, piece, recipients)
+            .await
+            .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+        let id = random_chunk_id();
+        vault.chunks.insert(id.clone(), encrypted);
+        ids.push(id);
+    }
+
+    Ok((Vec::new(), ids))
+}
+
+/// Decrypts a namespace whether its payload is inline or chunked, and
+/// undoes its compression.
+pub async fn decrypt_namespace(
+    platform: &Platform,
+    vault: &Vault,
+    namespace_data: &NamespaceData,
+    identity_private_key: &str,
+) -> Result<Vec<u8>, VaultError> {
+    decrypt_with_chunks(
+        platform,
+        &vault.chunks,
+        namespace_data,
+        identity_private_key,
+    )
+    .await
+}
+
+/// Same as [`decrypt_namespace`], with the chunks taken from `chunks`
+/// instead of a vault.
+pub async fn decrypt_with_chunks(
+    platform: &Platform,
+    chunks: &BTreeMap<String, Vec<u8>>,
+    namespace_data: &NamespaceData,
+    identity_private_key: &str,
+) -> Result<Vec<u8>, VaultError> {
+    if namespace_data.chunks.is_empty() {
+        let data = crate::domain::crypto::decrypt_with_identity(
+            platform,
+            &namespace_data.data,
+            identity_private_key,
+        )
+        .await
+        .map_err(|_| VaultError::InvalidPassword)?;
+        return decompress(namespace_data.compression, data);
+    }
+
+    let mut encrypted = Vec::with_capacity(namespace_data.chunks.len());
+    for id in &namespace_data.chunks {
+        encrypted.push(
+            chunks
+                .get(id)
+                .ok_or_else(|| VaultError::io_error(format!("Missing chunk {id}")))?
+                .as_slice(),
+        );
+    }
+
+    let mut data = Vec::new();
+    for chunk in
+        crate::domain::crypto::decrypt_many(platform, &encrypted, identity_private_key).await
+    {
+        data.extend_from_slice(&chunk.map_err(|_| VaultError::InvalidPassword)?);
+    }
+
+    decompress(namespace_data.compression, data)
+}
+
+/// Drops every chunk no namespace references any more and returns how many
+/// were removed. The files themselves go away on the next save.
+pub fn collect_garbage(vault: &mut Vault) -> usize {
+    let referenced: HashSet<&String> = vault
+        .namespaces
+        .values()
+        .flat_map(|namespace| namespace.chunks.iter())
+        .collect();
+
+    let before = vault.chunks.len();
+    let unreferenced: Vec<String> = vault
+        .chunks
+        .keys()
+        .filter(|id| !referenced.contains(id))
+        .cloned()
+        .collect();
+
+    for id in unreferenced {
+        vault.chunks.remove(&id);
+    }
+
+    before - vault.chunks.len()
+}
+
+pub async fn list_stored_chunks(platform: &Platform, vault_name: &str) -> Vec<String> {
+    let storage = platform.storage();
+    let path = chunks_path(vault_name);
+
+    if !storage.directory_exists(&path).await.unwrap_or(false) {
+        return Vec::new();
+    }
+
+    storage.list_entries(&path).await.unwrap_or_default()
+}
+
+/// Reads the chunks listed in `ids`, calling `on_read` with the number read
+/// so far after each one. Reads are issued in batches.
+pub async fn read_chunks(
+    platform: &Platform,
+    vault_name: &str,
+    ids: Vec<String>,
+    on_read: &dyn Fn(usize),
+) -> Result<BTreeMap<String, Vec<u8>>, VaultError> {
+    let storage = platform.storage();
+    let mut chunks = BTreeMap::new();
+
+    for batch in ids.chunks(READ_BATCH_SIZE) {
+        let paths: Vec<String> = batch
+            .iter()
+            .map(|id| format!("{}/{id}", chunks_path(vault_name)))
+            .collect();
+
+        for (id, text) in batch.iter().zip(storage.read_files(&paths).await) {
+            let encrypted = STANDARD
+                .decode(text?)
+                .map_err(|_| VaultError::serialization_error("Failed to decode chunk"))?;
+
+            chunks.insert(id.clone(), encrypted);
+            on_read(chunks.len());
+        }
+    }
+
+    Ok(chunks)
+}
+
+pub fn encode_chunk(encrypted: &[u8]) -> String {
+    STANDARD.encode(encrypted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::types::{Compression, IdentitySalts, VaultMetadata};
+    use futures::executor::block_on;
+
+    fn empty_vault() -> Vault {
+        Vault {
+            metadata: VaultMetadata {
+                peer_id: None,
+                sync_policy: Default::default(),
+            },
+            identity_salts: IdentitySalts::new(),
+            username_pk: BTreeMap::new(),
+            namespaces: BTreeMap::new(),
+            sync_enabled: false,
+            observer: false,
+            frozen: false,
+            retention: BTreeMap::new(),
+            residency: BTreeMap::new(),
+            chunks: BTreeMap::new(),
+            max_namespace_bytes: None,
+            manifest: None,
+            compression: Compression::None,
+            journal_sequence: 0,
+            stored_digests: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_identical_chunks_are_stored_once() {
+        let platform = Platform::new();
+        let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+        let mut vault = empty_vault();
+
+        let data = vec![7u8; CHUNK_SIZE * 3];
+        let ids = block_on(store_chunks(&platform, &mut vault, &identity, &data)).unwrap();
+
+        assert_eq!(ids.len(), 3);
+        assert_eq!(vault.chunks.len(), 1);
+
+        let namespace = NamespaceData {
+            data: Vec::new(),
+            expiration: None,
+            chunks: ids,
+            timelock: None,
+            updated_at: None,
+            recipients: Vec::new(),
+            crdt: None,
+            integrity: None,
+            crypto_version: None,
+            compression: Compression::None,
+        };
+        let decrypted =
+            block_on(decrypt_namespace(&platform, &vault, &namespace, &identity)).unwrap();
+        assert_eq!(decrypted, data);
+    }
+
+    #[test]
+    fn test_collect_garbage_keeps_referenced_chunks() {
+        let mut vault = empty_vault();
+        vault.chunks.insert("kept".to_string(), vec![1]);
+        vault.chunks.insert("orphan".to_string(), vec![2]);
+        vault.namespaces.insert(
+            "doc".to_string(),
+            NamespaceData {
+                data: Vec::new(),
+                expiration: None,
+                chunks: vec!["kept".to_string()],
+                timelock: None,
+                updated_at: None,
+                recipients: Vec::new(),
+                crdt: None,
+                integrity: None,
+                crypto_version: None,
+                compression: Compression::None,
+            },
+        );
+
+        assert_eq!(collect_garbage(&mut vault), 1);
+        assert!(vault.chunks.contains_key("kept"));
+        assert!(!vault.chunks.contains_key("orphan"));
+    }
+
+    #[test]
+    fn test_removed_namespace_chunks_are_deleted_from_storage() {
+        use crate::domain::vault::operations;
+
+        let platform = Platform::new();
+        let vault_name = "chunks_test_gc";
+        let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+
+        block_on(async {
+            let _ = operations::delete_vault(&platform, vault_name).await;
+
+            let vault = operations::create_vault().await.unwrap();
+            operations::save_vault(&platform, vault_name, vault)
+                .await
+                .unwrap();
+
+            let shared = vec![1u8; CHUNK_SIZE];
+            let mut second = shared.clone();
+            second.extend_from_slice(b"tail");
+
+            for (namespace, data) in [("v1", shared.clone()), ("v2", second.clone())] {
+                operations::upsert_namespace_deduplicated(
+                    &platform, vault_name, &identity, namespace, data, None, false,
+                )
+                .await
+                .unwrap();
+            }
+            assert_eq!(list_stored_chunks(&platform, vault_name).await.len(), 2);
+
+            operations::remove_namespace(&platform, vault_name, "v2")
+                .await
+                .unwrap();
+            assert_eq!(list_stored_chunks(&platform, vault_name).await.len(), 1);
+            assert_eq!(
+                operations::read_namespace(&platform, vault_name, &identity, "v1")
+                    .await
+                    .unwrap(),
+                shared
+            );
+
+            platform
+                .storage()
+                .delete_directory(vault_name)
+                .await
+                .unwrap();
+        });
+    }
+
+    #[test]
+    fn test_oversize_namespace_is_split_and_reassembled() {
+        use crate::domain::vault::operations;
+
+        let platform = Platform::new();
+        let vault_name = "chunks_test_split";
+        let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+        let public_key = crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+
+        block_on(async {
+            let _ = operations::delete_vault(&platform, vault_name).await;
+            operations::save_vault(
+                &platform,
+                vault_name,
+                operations::create_vault().await.unwrap(),
+            )
+            .await
+            .unwrap();
+
+            let data: Vec<u8> = (0..DEFAULT_SPLIT_THRESHOLD + 10).map(|i| i as u8).collect();
+            operations::upsert_namespace(
+                &platform,
+                vault_name,
+                &public_key,
+                "large",
+                data.clone(),
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+
+            let vault = operations::read_vault(&platform, vault_name).await.unwrap();
+            let namespace = &vault.namespaces["large"];
+            assert!(namespace.data.is_empty());
+            assert_eq!(namespace.chunks.len(), 2);
+            assert_eq!(list_stored_chunks(&platform, vault_name).await.len(), 2);
+
+            assert_eq!(
+                operations::read_namespace(&platform, vault_name, &identity, "large")
+                    .await
+                    .unwrap(),
+                data
+            );
+
+            operations::delete_vault(&platform, vault_name)
+                .await
+                .unwrap();
+        });
+    }
+
+    #[test]
+    fn test_chunk_id_depends_on_key() {
+        assert_eq!(chunk_id("a", b"data"), chunk_id("a", b"data"));
+        assert_ne!(chunk_id("a", b"data"), chunk_id("b", b"data"));
+    }
+}
@@ -0,0 +1,193 @@
+//! Periodic removal of expired namespaces across all vaults.
+//!
+//! Expired namespaces otherwise stay on disk until they are read or a
+//! cleanup is forced. The facades spawn [`run_cleanup_scheduler`] on their
+//! own executor; each round sleeps for the interval plus a random jitter,
+//! so tabs and processes started together do not all rewrite their vaults
+//! at the same moment, then cleans every vault. Starting a scheduler stops
+//! the previous one, which notices at the end of its current sleep.
+
+use super::error::VaultError;
+use super::operations::{cleanup_vault, list_vaults};
+use crate::platform::Platform;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+static RUNNING: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CleanupSchedule {
+    pub interval_seconds: u32,
+    /// Upper bound of the random delay added to each interval.
+    pub jitter_seconds: u32,
+}
+
+impl CleanupSchedule {
+    /// Every `interval_seconds`, with up to a tenth of it as jitter.
+    pub fn new(interval_seconds: u32) -> Self {
+        Self {
+            interval_seconds: interval_seconds.max(1),
+            jitter_seconds: interval_seconds / 10,
+        }
+    }
+
+    pub fn with_jitter(mut self, jitter_seconds: u32) -> Self {
+        self.jitter_seconds = jitter_seconds;
+        self
+    }
+
+    /// Delay before the next round, in milliseconds.
+    pub fn next_delay_ms(&self) -> u32 {
+        let jitter_ms = u64::from(self.jitter_seconds) * 1000;
+        let jitter = match jitter_ms {
+            0 => 0,
+            max => rand::random::<u64>() % (max + 1),
+        };
+
+        (u64::from(self.interval_seconds) * 1000 + jitter).min(u64::from(u32::MAX)) as u32
+    }
+}
+
+/// Takes over from the running scheduler, if any, and returns the
+/// generation to pass to [`run_cleanup_scheduler`].
+pub fn begin_cleanup_scheduler() -> u64 {
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    RUNNING.store(generation, Ordering::SeqCst);
+    generation
+}
+
+/// Stops the running scheduler. Returns `false` if there was none.
+pub fn stop_cleanup_scheduler() -> bool {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    RUNNING.swap(0, Ordering::SeqCst) != 0
+}
+
+pub fn is_cleanup_scheduler_running() -> bool {
+    RUNNING.load(Ordering::SeqCst) != 0
+}
+
+fn is_current(generation: u64) -> bool {
+    GENERATION.load(Ordering::SeqCst) == generation
+}
+
+/// Cleans every vault on `schedule` until another scheduler begins or
+/// [`stop_cleanup_scheduler`] is called.
+pub async fn run_cleanup_scheduler(
+    platform: &Platform,
+    schedule: CleanupSchedule,
+    generation: u64,
+) {
+    while is_current(generation) {
+        platform.clock().sleep(schedule.next_delay_ms()).await;
+        if !is_current(generation) {
+            break;
+        }
+
+        match cleanup_all_vaults(platform).await {
+            Ok(cleaned) => tracing::debug!(cleaned, "Scheduled cleanup done"),
+            Err(e) => tracing::warn!(error = %e, "Scheduled cleanup failed"),
+        }
+    }
+
+    let _ = RUNNING.compare_exchange(generation, 0, Ordering::SeqCst, Ordering::SeqCst);
+}
+
+/// Removes the expired namespaces of every vault and returns how many
+/// vaults lost some. A vault that fails is logged and skipped.
+pub async fn cleanup_all_vaults(platform: &Platform) -> Result<usize, VaultError> {
+    let mut cleaned = 0;
+
+    for vault_name in list_vaults(platform).await? {
+        let mut removed = false;
+        loop {
+            match cleanup_vault(platform, &vault_name).await {
+                Ok(true) => removed = true,
+                Ok(false) => break,
+                Err(e) => {
+                    tracing::warn!(vault = %vault_name, error = %e, "Skipped vault cleanup");
+                    break;
+                }
+            }
+        }
+        cleaned += usize::from(removed);
+    }
+
+    Ok(cleaned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{
+        create_vault, delete_vault, read_vault, save_vault, upsert_namespace,
+    };
+    use crate::ports::ClockPort;
+    use futures::executor::block_on;
+
+    struct FixedClock(f64);
+
+    #[async_trait::async_trait(?Send)]
+    impl ClockPort for FixedClock {
+        fn now(&self) -> f64 {
+            self.0
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+
+        async fn sleep(&self, _milliseconds: u32) {}
+    }
+
+    // Earlier than the clocks of other tests, so cleaning every vault does
+    // not expire what they wrote.
+    static WRITTEN: FixedClock = FixedClock(1_600_000_000_000.0);
+    static EXPIRED: FixedClock = FixedClock(1_600_000_020_000.0);
+
+    #[test]
+    fn test_delay_stays_within_the_jitter() {
+        let schedule = CleanupSchedule::new(60);
+        assert_eq!(schedule.jitter_seconds, 6);
+        for _ in 0..100 {
+            assert!((60_000..=66_000).contains(&schedule.next_delay_ms()));
+        }
+        assert_eq!(schedule.with_jitter(0).next_delay_ms(), 60_000);
+    }
+
+    #[test]
+    fn test_cleanup_all_vaults_removes_expired_namespaces() {
+        let written = Platform::new().with_clock(&WRITTEN);
+        let expired = Platform::new().with_clock(&EXPIRED);
+        let vault_name = "cleanup_scheduler_test";
+
+        block_on(async {
+            let _ = delete_vault(&written, vault_name).await;
+            save_vault(&written, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let identity = crate::domain::crypto::generate_identity(&written).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&written, &identity).unwrap();
+            for (namespace, expires_in) in [("kept", None), ("expiring", Some(10))] {
+                upsert_namespace(
+                    &written,
+                    vault_name,
+                    &public_key,
+                    namespace,
+                    vec![1],
+                    expires_in,
+                    false,
+                )
+                .await
+                .unwrap();
+            }
+
+            assert!(cleanup_all_vaults(&expired).await.unwrap() >= 1);
+            let vault = read_vault(&expired, vault_name).await.unwrap();
+            assert_eq!(vault.namespaces.keys().collect::<Vec<_>>(), ["kept"]);
+
+            delete_vault(&written, vault_name).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,175 @@
+//! Compression of namespace payloads before encryption.
+//!
+//! Ciphertext does not compress, so payloads are compressed before they are
+//! encrypted; large JSON documents typically shrink several times over.
+//! Writes use the compression they ask for, or else the default of the
+//! vault set with [`set_vault_compression`]. A payload that does not get
+//! smaller is stored as is. The compression used is recorded in the
+//! namespace file and reads undo it, so callers only ever see the original
+//! payload. Size limits and integrity MACs apply to that original payload.
+//!
+//! Only deflate is offered: zstd has no pure-Rust encoder, and the crate
+//! has to build for wasm without a C toolchain.
+
+use super::error::VaultError;
+use super::operations::{lock_vault, read_vault, read_vault_metadata, write_vault};
+use super::types::Compression;
+use crate::platform::Platform;
+use flate2::write::{DeflateDecoder, DeflateEncoder};
+use std::borrow::Cow;
+use std::io::Write;
+
+/// `data` compressed with `compression`, along with the compression to
+/// record: [`Compression::None`] when it did not make `data` smaller.
+pub fn compress(compression: Compression, data: &[u8]) -> (Cow<'_, [u8]>, Compression) {
+    let compressed = match compression {
+        Compression::None => None,
+        Compression::Deflate => {
+            let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data).and_then(|()| encoder.finish()).ok()
+        }
+    };
+
+    match compressed {
+        Some(compressed) if compressed.len() < data.len() => (Cow::Owned(compressed), compression),
+        _ => (Cow::Borrowed(data), Compression::None),
+    }
+}
+
+/// Undoes [`compress`].
+pub fn decompress(compression: Compression, data: Vec<u8>) -> Result<Vec<u8>, VaultError> {
+    let mut decompressor = Decompressor::new(compression);
+    let mut decompressed = decompressor.push(&data)?;
+    decompressed.extend(decompressor.finish()?);
+    Ok(decompressed)
+}
+
+/// Decompresses a payload piece by piece, for streaming reads.
+pub enum Decompressor {
+    None,
+    Deflate(DeflateDecoder<Vec<u8>>),
+}
+
+impl Decompressor {
+    pub fn new(compression: Compression) -> Self {
+        match compression {
+            Compression::None => Self::None,
+            Compression::Deflate => Self::Deflate(DeflateDecoder::new(Vec::new())),
+        }
+    }
+
+    /// Decompressed bytes available after `piece`.
+    pub fn push(&mut self, piece: &[u8]) -> Result<Vec<u8>, VaultError> {
+        match self {
+            Self::None => Ok(piece.to_vec()),
+            Self::Deflate(decoder) => {
+                decoder.write_all(piece).map_err(decompression_error)?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+        }
+    }
+
+    /// Bytes left once every piece has been pushed.
+    pub fn finish(self) -> Result<Vec<u8>, VaultError> {
+        match self {
+            Self::None => Ok(Vec::new()),
+            Self::Deflate(decoder) => decoder.finish().map_err(decompression_error),
+        }
+    }
+}
+
+fn decompression_error(_: std::io::Error) -> VaultError {
+    VaultError::serialization_error("Failed to decompress namespace")
+}
+
+/// Sets the compression of namespace writes to `vault_name` that do not
+/// choose one. Namespaces already written keep theirs.
+pub async fn set_vault_compression(
+    platform: &Platform,
+    vault_name: &str,
+    compression: Compression,
+) -> Result<(), VaultError> {
+    let _guard = lock_vault(platform, vault_name).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    vault.compression = compression;
+
+    write_vault(platform, vault_name, vault, Vec::new()).await
+}
+
+pub async fn vault_compression(
+    platform: &Platform,
+    vault_name: &str,
+) -> Result<Compression, VaultError> {
+    Ok(read_vault_metadata(platform, vault_name).await?.compression)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{
+        create_vault, delete_vault, read_namespace, save_vault, upsert_namespace,
+    };
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_vault_default_compresses_and_reads_decompress() {
+        let platform = Platform::new();
+        let vault_name = "compression_test";
+        let document = br#"{"status":"ok","items":[1,2,3]}"#.repeat(200);
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+            set_vault_compression(&platform, vault_name, Compression::Deflate)
+                .await
+                .unwrap();
+
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+            for (namespace, data) in [("document", document.clone()), ("tiny", vec![7])] {
+                upsert_namespace(
+                    &platform,
+                    vault_name,
+                    &public_key,
+                    namespace,
+                    data,
+                    None,
+                    false,
+                )
+                .await
+                .unwrap();
+            }
+
+            let vault = read_vault(&platform, vault_name).await.unwrap();
+            assert_eq!(
+                vault.namespaces["document"].compression,
+                Compression::Deflate
+            );
+            assert!(vault.namespaces["document"].data.len() < document.len() / 4);
+            assert_eq!(vault.namespaces["tiny"].compression, Compression::None);
+
+            assert_eq!(
+                read_namespace(&platform, vault_name, &identity, "document")
+                    .await
+                    .unwrap(),
+                document
+            );
+            assert_eq!(
+                read_namespace(&platform, vault_name, &identity, "tiny")
+                    .await
+                    .unwrap(),
+                [7]
+            );
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,736 @@
+//! Conflict-free replicated values stored in namespaces: grow-only and
+//! positive-negative counters, last-writer-wins maps and observed-remove
+//! sets.
+//!
+//! Sync only ever sees ciphertext, so it cannot merge two replicas itself.
+//! When a peer sends a CRDT namespace that differs from the local one, the
+//! remote copy is kept aside, still encrypted, and folded in by the next
+//! device holding the key: reads merge every copy, and the next update
+//! stores the merged value and drops the copies it absorbed.
+//!
+//! Counters are split by replica, a random id each device keeps next to the
+//! vault files and never syncs.
+
+use super::chunks;
+use super::error::VaultError;
+use super::migration::CURRENT_CRYPTO_VERSION;
+use super::operations::{
+    current_timestamp, ensure_not_recipient, lock_namespace, namespace_keys, read_vault,
+    write_namespace,
+};
+use super::types::{Compression, NamespaceData, Vault};
+use crate::platform::Platform;
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+pub(super) const REPLICA_ID_FILENAME: &str = "replica_id";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CrdtKind {
+    GCounter,
+    PnCounter,
+    LwwMap,
+    OrSet,
+}
+
+impl FromStr for CrdtKind {
+    type Err = VaultError;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "g-counter" => Ok(CrdtKind::GCounter),
+            "pn-counter" => Ok(CrdtKind::PnCounter),
+            "lww-map" => Ok(CrdtKind::LwwMap),
+            "or-set" => Ok(CrdtKind::OrSet),
+            _ => Err(VaultError::io_error(format!("Unknown CRDT type: {kind}"))),
+        }
+    }
+}
+
+/// Marks a namespace holding a CRDT value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrdtNamespace {
+    pub kind: CrdtKind,
+    /// Encrypted copies received from peers and not merged yet.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pending: Vec<ByteBuf>,
+}
+
+impl CrdtNamespace {
+    pub fn new(kind: CrdtKind) -> Self {
+        Self {
+            kind,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Keeps `data`, a peer's copy of the namespace, for the next merge.
+    /// Returns `false` if it is already kept.
+    pub fn add_pending(&mut self, data: Vec<u8>) -> bool {
+        if self.pending.iter().any(|pending| **pending == data) {
+            return false;
+        }
+        self.pending.push(ByteBuf::from(data));
+        true
+    }
+}
+
+/// An update to a CRDT value. `by` defaults to 1.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum CrdtOperation {
+    Increment {
+        #[serde(default = "one")]
+        by: u64,
+    },
+    Decrement {
+        #[serde(default = "one")]
+        by: u64,
+    },
+    Set {
+        key: String,
+        value: Value,
+    },
+    Delete {
+        key: String,
+    },
+    Add {
+        element: Value,
+    },
+    Remove {
+        element: Value,
+    },
+}
+
+fn one() -> u64 {
+    1
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LwwEntry {
+    /// `None` once the key was deleted.
+    pub value: Option<Value>,
+    /// Milliseconds since the Unix epoch on the writer's clock.
+    pub timestamp: u64,
+    /// Breaks ties between writes made in the same millisecond.
+    pub replica: String,
+}
+
+impl LwwEntry {
+    fn wins_over(&self, other: &LwwEntry) -> bool {
+        (self.timestamp, &self.replica) > (other.timestamp, &other.replica)
+    }
+}
+
+/// Replicated state of a CRDT namespace, as stored encrypted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum CrdtValue {
+    GCounter {
+        counts: BTreeMap<String, u64>,
+    },
+    PnCounter {
+        increments: BTreeMap<String, u64>,
+        decrements: BTreeMap<String, u64>,
+    },
+    LwwMap {
+        entries: BTreeMap<String, LwwEntry>,
+    },
+    /// Elements are keyed by their JSON text and carry the tags of the adds
+    /// not yet observed by a remove.
+    OrSet {
+        elements: BTreeMap<String, BTreeSet<String>>,
+        removed: BTreeSet<String>,
+    },
+}
+
+impl CrdtValue {
+    pub fn new(kind: CrdtKind) -> Self {
+        match kind {
+            CrdtKind::GCounter => CrdtValue::GCounter {
+                counts: BTreeMap::new(),
+            },
+            CrdtKind::PnCounter => CrdtValue::PnCounter {
+                increments: BTreeMap::new(),
+                decrements: BTreeMap::new(),
+            },
+            CrdtKind::LwwMap => CrdtValue::LwwMap {
+                entries: BTreeMap::new(),
+            },
+            CrdtKind::OrSet => CrdtValue::OrSet {
+                elements: BTreeMap::new(),
+                removed: BTreeSet::new(),
+            },
+        }
+    }
+
+    pub fn kind(&self) -> CrdtKind {
+        match self {
+            CrdtValue::GCounter { .. } => CrdtKind::GCounter,
+            CrdtValue::PnCounter { .. } => CrdtKind::PnCounter,
+            CrdtValue::LwwMap { .. } => CrdtKind::LwwMap,
+            CrdtValue::OrSet { .. } => CrdtKind::OrSet,
+        }
+    }
+
+    /// Applies `operation` as written by `replica` at `timestamp`, in
+    /// milliseconds.
+    pub fn apply(
+        &mut self,
+        operation: &CrdtOperation,
+        replica: &str,
+        timestamp: u64,
+    ) -> Result<(), VaultError> {
+        match (self, operation) {
+            (CrdtValue::GCounter { counts }, CrdtOperation::Increment { by })
+            | (
+                CrdtValue::PnCounter {
+                    increments: counts, ..
+                },
+                CrdtOperation::Increment { by },
+            )
+            | (
+                CrdtValue::PnCounter {
+                    decrements: counts, ..
+                },
+                CrdtOperation::Decrement { by },
+            ) => {
+                let count = counts.entry(replica.to_string()).or_default();
+                *count = count.saturating_add(*by);
+            }
+            (CrdtValue::LwwMap { entries }, CrdtOperation::Set { key, value }) => {
+                set_entry(entries, key, Some(value.clone()), replica, timestamp);
+            }
+            (CrdtValue::LwwMap { entries }, CrdtOperation::Delete { key }) => {
+                set_entry(entries, key, None, replica, timestamp);
+            }
+            (CrdtValue::OrSet { elements, .. }, CrdtOperation::Add { element }) => {
+                let tag = format!("{replica}:{:016x}", rand::random::<u64>());
+                elements
+                    .entry(element_key(element))
+                    .or_default()
+                    .insert(tag);
+            }
+            (CrdtValue::OrSet { elements, removed }, CrdtOperation::Remove { element }) => {
+                if let Some(tags) = elements.remove(&element_key(element)) {
+                    removed.extend(tags);
+                }
+            }
+            (value, operation) => {
+                return Err(VaultError::io_error(format!(
+                    "Operation {operation:?} does not apply to a {:?}",
+                    value.kind()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Merges `other` into `self`. Merging is commutative, associative and
+    /// idempotent, so replicas merging the same copies in any order agree.
+    pub fn merge(&mut self, other: &CrdtValue) -> Result<(), VaultError> {
+        match (self, other) {
+            (CrdtValue::GCounter { counts }, CrdtValue::GCounter { counts: theirs }) => {
+                merge_counts(counts, theirs);
+            }
+            (
+                CrdtValue::PnCounter {
+                    increments,
+                    decrements,
+                },
+                CrdtValue::PnCounter {
+                    increments: their_increments,
+                    decrements: their_decrements,
+                },
+            ) => {
+                merge_counts(increments, their_increments);
+                merge_counts(decrements, their_decrements);
+            }
+            (CrdtValue::LwwMap { entries }, CrdtValue::LwwMap { entries: theirs }) => {
+                for (key, entry) in theirs {
+                    match entries.get(key) {
+                        Some(ours) if !entry.wins_over(ours) => {}
+                        _ => {
+                            entries.insert(key.clone(), entry.clone());
+                        }
+                    }
+                }
+            }
+            (
+                CrdtValue::OrSet { elements, removed },
+                CrdtValue::OrSet {
+                    elements: their_elements,
+                    removed: their_removed,
+                },
+            ) => {
+                removed.extend(their_removed.iter().cloned());
+                for (element, tags) in their_elements {
+                    elements
+                        .entry(element.clone())
+                        .or_default()
+                        .extend(tags.iter().cloned());
+                }
+                elements.retain(|_, tags| {
+                    tags.retain(|tag| !removed.contains(tag));
+                    !tags.is_empty()
+                });
+            }
+            (value, other) => {
+                return Err(VaultError::io_error(format!(
+                    "Cannot merge a {:?} into a {:?}",
+                    other.kind(),
+                    value.kind()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Plain value seen by the application: a number for counters, an
+    /// object for maps and an array for sets.
+    pub fn value(&self) -> Value {
+        match self {
+            CrdtValue::GCounter { counts } => Value::from(counts.values().sum::<u64>()),
+            CrdtValue::PnCounter {
+                increments,
+                decrements,
+            } => {
+                let total = |counts: &BTreeMap<String, u64>| {
+                    counts.values().map(|count| *count as i128).sum::<i128>()
+                };
+                let value = total(increments) - total(decrements);
+                Value::from(value.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+            }
+            CrdtValue::LwwMap { entries } => Value::Object(
+                entries
+                    .iter()
+                    .filter_map(|(key, entry)| Some((key.clone(), entry.value.clone()?)))
+                    .collect(),
+            ),
+            CrdtValue::OrSet { elements, .. } => Value::Array(
+                elements
+                    .keys()
+                    .filter_map(|element| serde_json::from_str(element).ok())
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn merge_counts(counts: &mut BTreeMap<String, u64>, theirs: &BTreeMap<String, u64>) {
+    for (replica, count) in theirs {
+        let ours = counts.entry(replica.clone()).or_default();
+        *ours = (*ours).max(*count);
+    }
+}
+
+fn set_entry(
+    entries: &mut BTreeMap<String, LwwEntry>,
+    key: &str,
+    value: Option<Value>,
+    replica: &str,
+    timestamp: u64,
+) {
+    // A clock behind the last write must not lose the local update.
+    let timestamp = entries
+        .get(key)
+        .map_or(timestamp, |entry| timestamp.max(entry.timestamp + 1));
+
+    entries.insert(
+        key.to_string(),
+        LwwEntry {
+            value,
+            timestamp,
+            replica: replica.to_string(),
+        },
+    );
+}
+
+// serde_json keeps object members sorted, so equal elements get equal keys.
+fn element_key(element: &Value) -> String {
+    element.to_string()
+}
+
+/// Applies `operation` to the CRDT stored in `namespace`, creating it as a
+/// `kind` if the namespace does not exist, and stores the result merged
+/// with every copy received from peers.
+#[tracing::instrument(skip_all, fields(vault = vault_name, namespace = namespace))]
+pub async fn update_crdt(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    namespace: &str,
+    kind: CrdtKind,
+    operation: &CrdtOperation,
+) -> Result<Value, VaultError> {
+    let _guards = lock_namespace(platform, vault_name, namespace).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.observer {
+        return Err(VaultError::ObserverVault);
+    }
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    let (mut value, expiration, recipients) = match vault.namespaces.get(namespace) {
+        Some(namespace_data) => (
+            load_crdt(platform, &vault, namespace_data, identity_private_key).await?,
+            namespace_data.expiration.clone(),
+            namespace_data.recipients.clone(),
+        ),
+        None => (CrdtValue::new(kind), None, Vec::new()),
+    };
+
+    if value.kind() != kind {
+        return Err(VaultError::io_error(format!(
+            "Namespace holds a {:?}, not a {kind:?}",
+            value.kind()
+        )));
+    }
+
+    let replica = replica_id(platform, vault_name).await?;
+    value.apply(operation, &replica, platform.clock().now() as u64)?;
+
+    let data = serde_json::to_vec(&value)
+        .map_err(|_| VaultError::serialization_error("Failed to serialize CRDT value"))?;
+    super::limits::ensure_within_limit(&vault, data.len())?;
+
+    let public_key = crate::domain::crypto::identity_to_public(platform, identity_private_key)
+        .map_err(|_| VaultError::InvalidPassword)?;
+    ensure_not_recipient(vault.namespaces.get(namespace), &public_key)?;
+
+    let (encrypted_data, chunk_ids) = chunks::encrypt_payload(
+        platform,
+        &mut vault,
+        &namespace_keys(&public_key, &recipients),
+        &data,
+    )
+    .await?;
+
+    vault.namespaces.insert(
+        namespace.to_string(),
+        NamespaceData {
+            data: encrypted_data,
+            expiration,
+            chunks: chunk_ids,
+            timelock: None,
+            updated_at: Some(current_timestamp(platform)),
+            recipients,
+            crdt: Some(CrdtNamespace::new(kind)),
+            integrity: None,
+            crypto_version: Some(CURRENT_CRYPTO_VERSION),
+            compression: Compression::None,
+        },
+    );
+
+    write_namespace(platform, vault_name, &vault, namespace).await?;
+
+    Ok(value.value())
+}
+
+/// Current value of the CRDT stored in `namespace`, merged with every copy
+/// received from peers.
+#[tracing::instrument(skip_all, fields(vault = vault_name, namespace = namespace))]
+pub async fn read_crdt(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    namespace: &str,
+) -> Result<Value, VaultError> {
+    let vault = read_vault(platform, vault_name).await?;
+
+    let namespace_data = vault
+        .namespaces
+        .get(namespace)
+        .ok_or(VaultError::NamespaceNotFound)?;
+
+    if let Some(expiration) = &namespace_data.expiration {
+        if current_timestamp(platform) >= expiration.expires_at {
+            return Err(VaultError::DataExpired);
+        }
+    }
+
+    Ok(
+        load_crdt(platform, &vault, namespace_data, identity_private_key)
+            .await?
+            .value(),
+    )
+}
+
+async fn load_crdt(
+    platform: &Platform,
+    vault: &Vault,
+    namespace_data: &NamespaceData,
+    identity_private_key: &str,
+) -> Result<CrdtValue, VaultError> {
+    let Some(crdt) = &namespace_data.crdt else {
+        return Err(VaultError::io_error("Namespace does not hold a CRDT value"));
+    };
+
+    let plaintext =
+        chunks::decrypt_namespace(platform, vault, namespace_data, identity_private_key).await?;
+    let mut value = parse_crdt(&plaintext)?;
+
+    // A copy encrypted to keys this identity lacks, e.g. from before a
+    // rotation, is skipped; its author still holds it and sends it again.
+    for pending in &crdt.pending {
+        let merged = match crate::domain::crypto::decrypt_with_identity(
+            platform,
+            pending,
+            identity_private_key,
+        )
+        .await
+        {
+            Ok(plaintext) => parse_crdt(&plaintext).and_then(|copy| value.merge(&copy)),
+            Err(e) => Err(VaultError::io_error(e.to_string())),
+        };
+        if let Err(e) = merged {
+            tracing::warn!(error = %e, "Skipped unreadable CRDT copy");
+        }
+    }
+
+    Ok(value)
+}
+
+fn parse_crdt(plaintext: &[u8]) -> Result<CrdtValue, VaultError> {
+    serde_json::from_slice(plaintext)
+        .map_err(|_| VaultError::serialization_error("Namespace does not hold a CRDT value"))
+}
+
+/// Id of this device's replica of `vault_name`, created on first use.
+async fn replica_id(platform: &Platform, vault_name: &str) -> Result<String, VaultError> {
+    let storage = platform.storage();
+    let path = format!("{vault_name}/{REPLICA_ID_FILENAME}");
+
+    if let Ok(id) = storage.read_file(&path).await {
+        if !id.is_empty() {
+            return Ok(id);
+        }
+    }
+
+    let id = hex::encode(rand::random::<[u8; 8]>());
+    storage.write_file(&path, &id).await?;
+
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{create_vault, delete_vault, save_vault};
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    fn operation(value: Value) -> CrdtOperation {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn updated(kind: CrdtKind, replica: &str, operations: &[Value]) -> CrdtValue {
+        let mut value = CrdtValue::new(kind);
+        for (timestamp, op) in operations.iter().enumerate() {
+            value
+                .apply(&operation(op.clone()), replica, timestamp as u64)
+                .unwrap();
+        }
+        value
+    }
+
+    fn merged(left: &CrdtValue, right: &CrdtValue) -> CrdtValue {
+        let mut merged = left.clone();
+        merged.merge(right).unwrap();
+        merged
+    }
+
+    #[test]
+    fn test_counters_add_up_across_replicas() {
+        let laptop = updated(
+            CrdtKind::PnCounter,
+            "laptop",
+            &[
+                json!({ "op": "increment", "by": 5 }),
+                json!({ "op": "decrement" }),
+            ],
+        );
+        let phone = updated(
+            CrdtKind::PnCounter,
+            "phone",
+            &[json!({ "op": "increment" })],
+        );
+
+        assert_eq!(merged(&laptop, &phone).value(), json!(5));
+        assert_eq!(merged(&laptop, &phone), merged(&phone, &laptop));
+        assert_eq!(merged(&merged(&laptop, &phone), &phone).value(), json!(5));
+
+        let mut grow_only = CrdtValue::new(CrdtKind::GCounter);
+        assert!(grow_only
+            .apply(&operation(json!({ "op": "decrement" })), "laptop", 0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_lww_map_keeps_the_latest_write() {
+        let mut laptop = CrdtValue::new(CrdtKind::LwwMap);
+        laptop
+            .apply(
+                &operation(json!({ "op": "set", "key": "theme", "value": "dark" })),
+                "laptop",
+                10,
+            )
+            .unwrap();
+        let mut phone = CrdtValue::new(CrdtKind::LwwMap);
+        phone
+            .apply(
+                &operation(json!({ "op": "set", "key": "theme", "value": "light" })),
+                "phone",
+                20,
+            )
+            .unwrap();
+        phone
+            .apply(
+                &operation(json!({ "op": "set", "key": "lang", "value": "fr" })),
+                "phone",
+                5,
+            )
+            .unwrap();
+        laptop
+            .apply(
+                &operation(json!({ "op": "delete", "key": "lang" })),
+                "laptop",
+                30,
+            )
+            .unwrap();
+
+        assert_eq!(merged(&laptop, &phone).value(), json!({ "theme": "light" }));
+        assert_eq!(merged(&laptop, &phone), merged(&phone, &laptop));
+    }
+
+    #[test]
+    fn test_or_set_add_wins_over_unobserved_remove() {
+        let base = updated(
+            CrdtKind::OrSet,
+            "laptop",
+            &[
+                json!({ "op": "add", "element": "a" }),
+                json!({ "op": "add", "element": { "id": 1 } }),
+            ],
+        );
+        let mut laptop = base.clone();
+        laptop
+            .apply(
+                &operation(json!({ "op": "remove", "element": "a" })),
+                "laptop",
+                2,
+            )
+            .unwrap();
+        let mut phone = base.clone();
+        phone
+            .apply(
+                &operation(json!({ "op": "add", "element": "a" })),
+                "phone",
+                2,
+            )
+            .unwrap();
+
+        let value = merged(&laptop, &phone);
+        assert_eq!(value, merged(&phone, &laptop));
+        assert_eq!(value.value(), json!(["a", { "id": 1 }]));
+    }
+
+    #[test]
+    fn test_update_merges_pending_copies() {
+        let platform = Platform::new();
+        let vault_name = "crdt_test";
+        let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+        let public_key = crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let increment = operation(json!({ "op": "increment", "by": 2 }));
+            assert_eq!(
+                update_crdt(
+                    &platform,
+                    vault_name,
+                    &identity,
+                    "visits",
+                    CrdtKind::GCounter,
+                    &increment
+                )
+                .await
+                .unwrap(),
+                json!(2)
+            );
+
+            // A copy from another device, as sync would have kept it.
+            let remote = CrdtValue::GCounter {
+                counts: BTreeMap::from([("phone".to_string(), 3)]),
+            };
+            let encrypted = crate::domain::crypto::encrypt_for_recipients(
+                &platform,
+                &serde_json::to_vec(&remote).unwrap(),
+                &[public_key.as_str()],
+            )
+            .await
+            .unwrap();
+            let mut vault = read_vault(&platform, vault_name).await.unwrap();
+            vault
+                .namespaces
+                .get_mut("visits")
+                .and_then(|namespace_data| namespace_data.crdt.as_mut())
+                .unwrap()
+                .add_pending(encrypted);
+            save_vault(&platform, vault_name, vault).await.unwrap();
+
+            assert_eq!(
+                read_crdt(&platform, vault_name, &identity, "visits")
+                    .await
+                    .unwrap(),
+                json!(5)
+            );
+            assert_eq!(
+                update_crdt(
+                    &platform,
+                    vault_name,
+                    &identity,
+                    "visits",
+                    CrdtKind::GCounter,
+                    &increment
+                )
+                .await
+                .unwrap(),
+                json!(7)
+            );
+
+            let vault = read_vault(&platform, vault_name).await.unwrap();
+            assert!(vault.namespaces["visits"]
+                .crdt
+                .as_ref()
+                .unwrap()
+                .pending
+                .is_empty());
+
+            assert!(update_crdt(
+                &platform,
+                vault_name,
+                &identity,
+                "visits",
+                CrdtKind::OrSet,
+                &operation(json!({ "op": "add", "element": 1 }))
+            )
+            .await
+            .is_err());
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,217 @@
+//! Inspecting and revoking the WebAuthn credentials registered on a vault.
+//!
+//! A credential is the username mapped to the public key of the identity
+//! its authenticator derives, along with the salt and credential id needed
+//! to ask the authenticator for it again. Revoking a credential forgets all
+//! of these, so the vault no longer offers it at unlock; what was encrypted
+//! to its identity stays encrypted to it until the vault is rekeyed, which
+//! [`revoke_credential`] does when given the identity and a new passphrase.
+
+use super::error::VaultError;
+use super::operations::{lock_vault, read_vault, write_vault};
+use super::rotation::rotate_vault_identity;
+use crate::domain::authentication::IdentityKeys;
+use crate::platform::Platform;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CredentialInfo {
+    pub username: String,
+    pub public_key: String,
+    /// Registration time in seconds, unknown for credentials registered
+    /// before it was recorded.
+    pub created_at: Option<i64>,
+}
+
+/// What [`revoke_credential`] needs to rekey the vault: the identity of the
+/// revoked credential, obtained before revoking it, and the passphrase of
+/// the identity replacing it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CredentialRekey {
+    pub identity_private_key: String,
+    pub new_passphrase: String,
+}
+
+/// Credentials registered on `vault_name`, ordered by username.
+pub async fn list_credentials(
+    platform: &Platform,
+    vault_name: &str,
+) -> Result<Vec<CredentialInfo>, VaultError> {
+    let vault = read_vault(platform, vault_name).await?;
+
+    Ok(vault
+        .username_pk
+        .iter()
+        .filter(|(_, public_key)| vault.identity_salts.get_credential_id(public_key).is_some())
+        .map(|(username, public_key)| CredentialInfo {
+            username: username.clone(),
+            public_key: public_key.clone(),
+            created_at: vault.identity_salts.get_credential_created_at(public_key),
+        })
+        .collect())
+}
+
+/// Registers the credential of `username` under `new_username`. The name
+/// the authenticator shows for it is left as it was.
+pub async fn rename_credential(
+    platform: &Platform,
+    vault_name: &str,
+    username: &str,
+    new_username: &str,
+) -> Result<(), VaultError> {
+    let _guard = lock_vault(platform, vault_name).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+    if vault.username_pk.contains_key(new_username) {
+        return Err(VaultError::io_error(format!(
+            "A credential is already registered for {new_username}"
+        )));
+    }
+
+    let public_key = vault
+        .username_pk
+        .remove(username)
+        .ok_or_else(|| unknown_username(username))?;
+    vault
+        .username_pk
+        .insert(new_username.to_string(), public_key);
+
+    write_vault(platform, vault_name, vault, Vec::new()).await
+}
+
+/// Forgets the credential of `username`. With `rekey`, the vault is also
+/// re-encrypted to the identity derived from the new passphrase, which is
+/// returned.
+pub async fn revoke_credential(
+    platform: &Platform,
+    vault_name: &str,
+    username: &str,
+    rekey: Option<&CredentialRekey>,
+) -> Result<Option<IdentityKeys>, VaultError> {
+    if let Some(rekey) = rekey {
+        let vault = read_vault(platform, vault_name).await?;
+        let public_key = vault
+            .username_pk
+            .get(username)
+            .ok_or_else(|| unknown_username(username))?;
+        let identity_public_key =
+            crate::domain::crypto::identity_to_public(platform, &rekey.identity_private_key)
+                .map_err(|_| VaultError::InvalidPassword)?;
+        if identity_public_key != *public_key {
+            return Err(VaultError::InvalidPassword);
+        }
+
+        // Rotation forgets the old identity, credential included.
+        let identity = rotate_vault_identity(
+            platform,
+            vault_name,
+            &rekey.identity_private_key,
+            &rekey.new_passphrase,
+        )
+        .await?;
+        return Ok(Some(identity));
+    }
+
+    let _guard = lock_vault(platform, vault_name).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    let public_key = vault
+        .username_pk
+        .remove(username)
+        .ok_or_else(|| unknown_username(username))?;
+    if !vault.username_pk.values().any(|other| *other == public_key) {
+        vault.identity_salts.remove(&public_key);
+    }
+
+    write_vault(platform, vault_name, vault, Vec::new()).await?;
+    Ok(None)
+}
+
+fn unknown_username(username: &str) -> VaultError {
+    VaultError::io_error(format!("No credential registered for {username}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{create_vault, delete_vault, save_vault};
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_credentials_can_be_renamed_and_revoked() {
+        let platform = Platform::new();
+        let vault_name = "credentials_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            let mut vault = create_vault().await.unwrap();
+            for (username, public_key) in [("alice", "pk_alice"), ("bob", "pk_bob")] {
+                vault
+                    .identity_salts
+                    .set_salt(public_key.to_string(), [1; 32]);
+                vault
+                    .identity_salts
+                    .set_credential_id(public_key.to_string(), vec![2; 16]);
+                vault
+                    .username_pk
+                    .insert(username.to_string(), public_key.to_string());
+            }
+            vault
+                .identity_salts
+                .set_credential_created_at("pk_alice".to_string(), 1_700_000_000);
+            save_vault(&platform, vault_name, vault).await.unwrap();
+
+            let credentials = list_credentials(&platform, vault_name).await.unwrap();
+            assert_eq!(
+                credentials,
+                [
+                    CredentialInfo {
+                        username: "alice".to_string(),
+                        public_key: "pk_alice".to_string(),
+                        created_at: Some(1_700_000_000),
+                    },
+                    CredentialInfo {
+                        username: "bob".to_string(),
+                        public_key: "pk_bob".to_string(),
+                        created_at: None,
+                    },
+                ]
+            );
+
+            assert!(rename_credential(&platform, vault_name, "alice", "bob")
+                .await
+                .is_err());
+            rename_credential(&platform, vault_name, "alice", "carol")
+                .await
+                .unwrap();
+
+            assert!(revoke_credential(&platform, vault_name, "carol", None)
+                .await
+                .unwrap()
+                .is_none());
+            assert!(revoke_credential(&platform, vault_name, "carol", None)
+                .await
+                .is_err());
+
+            let vault = read_vault(&platform, vault_name).await.unwrap();
+            assert!(vault.identity_salts.get_salt("pk_alice").is_none());
+            assert!(vault.identity_salts.get_credential_id("pk_alice").is_none());
+            let usernames: Vec<_> = list_credentials(&platform, vault_name)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|credential| credential.username)
+                .collect();
+            assert_eq!(usernames, ["bob"]);
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
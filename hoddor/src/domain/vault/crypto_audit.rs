@@ -0,0 +1,248 @@
+//! Inventory of the algorithms and parameters a vault relies on.
+//!
+//! Nothing in a vault names the algorithms it was written with: they are
+//! implied by the version of hoddor that wrote it, and by the age headers of
+//! its ciphertexts. [`describe_crypto`] spells them out, reading the
+//! recipient types from those headers, so reviews and migration tooling can
+//! tell which vaults still use parameters due to be replaced without having
+//! to decrypt anything.
+
+use super::error::VaultError;
+use super::operations::read_vault;
+use super::types::Vault;
+use crate::platform::Platform;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+const AGE_HEADER: &[u8] = b"age-encryption.org/v1\n";
+const STANZA_PREFIX: &str = "-> ";
+const HEADER_END_PREFIX: &str = "---";
+
+/// Recipient types of age that resist a quantum adversary.
+const POST_QUANTUM_RECIPIENTS: &[&str] = &["mlkem768x25519"];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CryptoDescription {
+    pub format: &'static str,
+    pub kdf: KdfDescription,
+    pub cipher_suite: CipherSuite,
+    /// Age recipient types found in the ciphertext headers, with the number
+    /// of namespaces and chunks encrypted to each.
+    pub recipient_types: BTreeMap<String, usize>,
+    pub identities: Vec<IdentityDescription>,
+    /// Namespaces and chunks whose header could not be read.
+    pub unreadable_headers: usize,
+    /// Namespaces stored without an integrity MAC.
+    pub namespaces_without_mac: usize,
+    /// Whether every ciphertext is encrypted to post-quantum recipients
+    /// only. Always `false` for vaults with no ciphertext.
+    pub post_quantum: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KdfDescription {
+    pub algorithm: &'static str,
+    pub version: u32,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    pub salt_bytes: usize,
+    pub output_bytes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CipherSuite {
+    pub payload: &'static str,
+    pub payload_key_bits: u32,
+    pub file_key_bits: u32,
+    pub key_wrap: &'static str,
+    pub integrity_mac: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IdentityDescription {
+    pub public_key: String,
+    /// `passphrase` for identities derived with the KDF, `prf` for those
+    /// derived from a passkey or security key.
+    pub source: &'static str,
+    /// Key type, from the prefix of the public key.
+    pub key_type: String,
+}
+
+/// Describes the algorithms `vault_name` is encrypted with.
+pub async fn describe_crypto(
+    platform: &Platform,
+    vault_name: &str,
+) -> Result<CryptoDescription, VaultError> {
+    let vault = read_vault(platform, vault_name).await?;
+    Ok(describe_vault_crypto(&vault))
+}
+
+pub fn describe_vault_crypto(vault: &Vault) -> CryptoDescription {
+    let mut recipient_types = BTreeMap::new();
+    let mut unreadable_headers = 0;
+
+    let ciphertexts = vault
+        .namespaces
+        .values()
+        .filter(|namespace| namespace.chunks.is_empty())
+        .map(|namespace| namespace.data.as_slice())
+        .chain(vault.chunks.values().map(Vec::as_slice));
+    for ciphertext in ciphertexts {
+        match recipient_stanzas(ciphertext) {
+            Some(stanzas) => {
+                for stanza in stanzas {
+                    *recipient_types.entry(stanza).or_insert(0) += 1;
+                }
+            }
+            None => unreadable_headers += 1,
+        }
+    }
+
+    let identities = vault
+        .identity_salts
+        .iter()
+        .map(|(public_key, _)| IdentityDescription {
+            public_key: public_key.clone(),
+            source: if vault.identity_salts.get_credential_id(public_key).is_some() {
+                "prf"
+            } else {
+                "passphrase"
+            },
+            key_type: key_type(public_key),
+        })
+        .collect();
+
+    let post_quantum = !recipient_types.is_empty()
+        && unreadable_headers == 0
+        && recipient_types
+            .keys()
+            .all(|stanza| POST_QUANTUM_RECIPIENTS.contains(&stanza.as_str()));
+
+    CryptoDescription {
+        format: "age-encryption.org/v1",
+        kdf: kdf_description(),
+        cipher_suite: CipherSuite {
+            payload: "ChaCha20-Poly1305",
+            payload_key_bits: 256,
+            file_key_bits: 128,
+            key_wrap: "X25519, HKDF-SHA256, ChaCha20-Poly1305",
+            integrity_mac: "BLAKE3 keyed hash",
+        },
+        recipient_types,
+        identities,
+        unreadable_headers,
+        namespaces_without_mac: vault
+            .namespaces
+            .values()
+            .filter(|namespace| namespace.integrity.is_none())
+            .count(),
+        post_quantum,
+    }
+}
+
+// The parameters `Argon2Kdf` derives passphrase identities with.
+fn kdf_description() -> KdfDescription {
+    let params = argon2::Params::default();
+
+    KdfDescription {
+        algorithm: "argon2id",
+        version: argon2::Version::default() as u32,
+        memory_kib: params.m_cost(),
+        iterations: params.t_cost(),
+        parallelism: params.p_cost(),
+        salt_bytes: 32,
+        output_bytes: 32,
+    }
+}
+
+// Bech32 human-readable part of the public key: `age` for X25519.
+fn key_type(public_key: &str) -> String {
+    match public_key.rsplit_once('1') {
+        Some(("age", _)) => "X25519".to_string(),
+        Some((prefix, _)) => prefix.to_string(),
+        None => "unknown".to_string(),
+    }
+}
+
+// Recipient types of the stanzas in the header of an age ciphertext, or
+// `None` when it does not start with one. The random `*-grease` stanzas
+// age adds to keep parsers tolerant are not recipients.
+fn recipient_stanzas(ciphertext: &[u8]) -> Option<Vec<String>> {
+    let header = ciphertext.strip_prefix(AGE_HEADER)?;
+    let mut stanzas = Vec::new();
+
+    for line in header.split(|byte| *byte == b'\n') {
+        let line = std::str::from_utf8(line).ok()?;
+        if line.starts_with(HEADER_END_PREFIX) {
+            return Some(stanzas);
+        }
+        if let Some(arguments) = line.strip_prefix(STANZA_PREFIX) {
+            let stanza_type = arguments.split(' ').next()?;
+            if !stanza_type.ends_with("-grease") {
+                stanzas.push(stanza_type.to_string());
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{
+        create_vault, delete_vault, save_vault, upsert_namespace,
+    };
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_describes_recipients_and_identities() {
+        let platform = Platform::new();
+        let vault_name = "crypto_audit_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            let mut vault = create_vault().await.unwrap();
+
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+            vault.identity_salts.set_salt(public_key.clone(), [1; 32]);
+            save_vault(&platform, vault_name, vault).await.unwrap();
+
+            upsert_namespace(
+                &platform,
+                vault_name,
+                &public_key,
+                "notes",
+                b"notes".to_vec(),
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+
+            let description = describe_crypto(&platform, vault_name).await.unwrap();
+            assert_eq!(description.kdf.algorithm, "argon2id");
+            assert_eq!(description.kdf.version, 0x13);
+            assert_eq!(
+                description.recipient_types,
+                BTreeMap::from([("X25519".to_string(), 1)])
+            );
+            assert_eq!(description.unreadable_headers, 0);
+            assert_eq!(description.namespaces_without_mac, 0);
+            assert!(!description.post_quantum);
+            assert_eq!(
+                description.identities,
+                [IdentityDescription {
+                    public_key,
+                    source: "passphrase",
+                    key_type: "X25519".to_string(),
+                }]
+            );
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,242 @@
+//! Linking a new device to a vault without exporting the vault.
+//!
+//! A device link carries only what a new device needs to join: the identity
+//! salts and credentials that let it derive the vault identities, the sync
+//! settings and the sync profile with its trusted peers. Namespaces then
+//! arrive through sync. The link is encrypted to a short one-time code,
+//! stretched with the same Argon2 derivation as passphrases, and expires
+//! after [`DEVICE_LINK_TTL_SECONDS`] by default, so the code can be read
+//! out or typed while the blob travels through any channel.
+
+use super::error::VaultError;
+use super::operations::{create_vault, current_timestamp, save_vault};
+use super::sync_policy::SyncPolicy;
+use super::sync_profile::{load_sync_profile, save_sync_profile, SyncProfile};
+use super::types::IdentitySalts;
+use crate::domain::crypto;
+use crate::platform::Platform;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const DEVICE_LINK_MAGIC_NUMBER: &[u8; 6] = b"HLINK1";
+const SALT_LENGTH: usize = 32;
+/// Crockford's base32, which leaves out letters mistaken for digits.
+const CODE_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const CODE_LENGTH: usize = 10;
+
+pub const DEVICE_LINK_TTL_SECONDS: i64 = 10 * 60;
+
+/// A link made by [`export_device_link`]: the code is shown on the linking
+/// device, the blob handed to the new one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeviceLinkExport {
+    pub code: String,
+    pub blob: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DeviceLink {
+    vault_name: String,
+    expires_at: i64,
+    identity_salts: IdentitySalts,
+    username_pk: BTreeMap<String, String>,
+    sync_enabled: bool,
+    sync_policy: SyncPolicy,
+    sync_profile: SyncProfile,
+}
+
+/// Bundles what a new device needs to join `vault_name`, encrypted to a
+/// fresh code valid for `valid_for_seconds`.
+#[tracing::instrument(skip_all, fields(vault = vault_name))]
+pub async fn export_device_link(
+    platform: &Platform,
+    vault_name: &str,
+    valid_for_seconds: i64,
+) -> Result<DeviceLinkExport, VaultError> {
+    let vault = super::operations::read_vault_metadata(platform, vault_name).await?;
+
+    let link = DeviceLink {
+        vault_name: vault_name.to_string(),
+        expires_at: current_timestamp(platform) + valid_for_seconds,
+        identity_salts: vault.identity_salts,
+        username_pk: vault.username_pk,
+        sync_enabled: vault.sync_enabled,
+        sync_policy: vault.metadata.sync_policy,
+        sync_profile: load_sync_profile(platform, vault_name).await?,
+    };
+    let json = serde_json::to_vec(&link)
+        .map_err(|_| VaultError::serialization_error("Failed to serialize device link"))?;
+
+    let code = generate_code();
+    let mut salt = [0u8; SALT_LENGTH];
+    rand::thread_rng().fill_bytes(&mut salt);
+
+    let identity = crypto::identity_from_passphrase(platform, &normalize_code(&code), &salt)
+        .await
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+    let recipient = crypto::identity_to_public(platform, &identity)
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+    let encrypted = crypto::encrypt_for_recipients(platform, &json, &[&recipient])
+        .await
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+    let mut blob =
+        Vec::with_capacity(DEVICE_LINK_MAGIC_NUMBER.len() + SALT_LENGTH + encrypted.len());
+    blob.extend_from_slice(DEVICE_LINK_MAGIC_NUMBER);
+    blob.extend_from_slice(&salt);
+    blob.extend_from_slice(&encrypted);
+
+    Ok(DeviceLinkExport { code, blob })
+}
+
+/// Creates the vault of a link made by [`export_device_link`] on this
+/// device, ready to unlock and sync, and returns its name. Fails with
+/// [`VaultError::InvalidPassword`] for a wrong code,
+/// [`VaultError::DataExpired`] for an expired link and
+/// [`VaultError::VaultAlreadyExists`] when the device has the vault already.
+#[tracing::instrument(skip_all)]
+pub async fn import_device_link(
+    platform: &Platform,
+    code: &str,
+    blob: &[u8],
+) -> Result<String, VaultError> {
+    let header_length = DEVICE_LINK_MAGIC_NUMBER.len() + SALT_LENGTH;
+    if blob.len() <= header_length
+        || &blob[..DEVICE_LINK_MAGIC_NUMBER.len()] != DEVICE_LINK_MAGIC_NUMBER
+    {
+        return Err(VaultError::serialization_error(
+            "Invalid device link: missing or incorrect magic number",
+        ));
+    }
+
+    let salt = &blob[DEVICE_LINK_MAGIC_NUMBER.len()..header_length];
+    let identity = crypto::identity_from_passphrase(platform, &normalize_code(code), salt)
+        .await
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+    let json = crypto::decrypt_with_identity(platform, &blob[header_length..], &identity)
+        .await
+        .map_err(|_| VaultError::InvalidPassword)?;
+
+    let link: DeviceLink = serde_json::from_slice(&json)
+        .map_err(|_| VaultError::serialization_error("Failed to parse device link"))?;
+
+    if current_timestamp(platform) > link.expires_at {
+        return Err(VaultError::DataExpired);
+    }
+    super::validation::validate_vault_name(&link.vault_name)?;
+    if platform
+        .storage()
+        .directory_exists(&link.vault_name)
+        .await?
+    {
+        return Err(VaultError::VaultAlreadyExists);
+    }
+
+    let mut vault = create_vault().await?;
+    vault.identity_salts = link.identity_salts;
+    vault.username_pk = link.username_pk;
+    vault.sync_enabled = link.sync_enabled;
+    vault.metadata.sync_policy = link.sync_policy;
+
+    save_vault(platform, &link.vault_name, vault).await?;
+    save_sync_profile(platform, &link.vault_name, &link.sync_profile).await?;
+
+    Ok(link.vault_name)
+}
+
+// Ten characters, about 50 bits, shown as two groups of five.
+fn generate_code() -> String {
+    let mut rng = rand::thread_rng();
+    let code: String = (0..CODE_LENGTH)
+        .map(|_| char::from(CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())]))
+        .collect();
+    format!("{}-{}", &code[..CODE_LENGTH / 2], &code[CODE_LENGTH / 2..])
+}
+
+// Codes are typed by hand: case and separators do not matter, and letters
+// read as digits are taken for them.
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| match c.to_ascii_uppercase() {
+            'O' => '0',
+            'I' | 'L' => '1',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{delete_vault, read_vault};
+    use crate::domain::vault::sync_profile::TrustedPeer;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_device_link_recreates_the_vault_without_namespaces() {
+        let platform = Platform::new();
+        let vault_name = "device_link_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            let mut vault = create_vault().await.unwrap();
+            vault
+                .identity_salts
+                .set_salt("age1device".to_string(), [5; 32]);
+            vault
+                .username_pk
+                .insert("alice".to_string(), "age1device".to_string());
+            vault.sync_enabled = true;
+            save_vault(&platform, vault_name, vault).await.unwrap();
+
+            let profile = SyncProfile {
+                trusted_peers: BTreeMap::from([("peer-1".to_string(), TrustedPeer::default())]),
+                ..SyncProfile::default()
+            };
+            save_sync_profile(&platform, vault_name, &profile)
+                .await
+                .unwrap();
+
+            let link = export_device_link(&platform, vault_name, DEVICE_LINK_TTL_SECONDS)
+                .await
+                .unwrap();
+            assert_eq!(link.code.len(), CODE_LENGTH + 1);
+            let expired = export_device_link(&platform, vault_name, -1).await.unwrap();
+            delete_vault(&platform, vault_name).await.unwrap();
+
+            assert!(matches!(
+                import_device_link(&platform, "00000-00000", &link.blob).await,
+                Err(VaultError::InvalidPassword)
+            ));
+            assert!(matches!(
+                import_device_link(&platform, &expired.code, &expired.blob).await,
+                Err(VaultError::DataExpired)
+            ));
+
+            let typed = link.code.to_lowercase().replace('-', " ");
+            assert_eq!(
+                import_device_link(&platform, &typed, &link.blob)
+                    .await
+                    .unwrap(),
+                vault_name
+            );
+
+            let vault = read_vault(&platform, vault_name).await.unwrap();
+            assert_eq!(vault.identity_salts.get_salt("age1device"), Some(&[5; 32]));
+            assert_eq!(vault.username_pk["alice"], "age1device");
+            assert!(vault.sync_enabled);
+            assert_eq!(
+                load_sync_profile(&platform, vault_name).await.unwrap(),
+                profile
+            );
+            assert!(matches!(
+                import_device_link(&platform, &link.code, &link.blob).await,
+                Err(VaultError::VaultAlreadyExists)
+            ));
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
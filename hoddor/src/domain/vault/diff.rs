@@ -0,0 +1,314 @@
+//! Comparison of two vaults, e.g. replicas about to be merged or a vault and
+//! the backup it was restored from.
+//!
+//! Namespaces are compared by the hash of their stored ciphertext first.
+//! Encryption is randomized, so the same content written twice differs
+//! there; when an identity is given, namespaces it can decrypt are also
+//! compared by the hash of their plaintext.
+
+use super::chunks;
+use super::error::VaultError;
+use super::operations;
+use super::serialization::deserialize_vault;
+use super::types::{NamespaceData, Vault};
+use crate::platform::Platform;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NamespaceStatus {
+    OnlyInLeft,
+    OnlyInRight,
+    /// Same ciphertext and expiration.
+    Identical,
+    /// Different ciphertext decrypting to the same content.
+    SameContent,
+    Different,
+    /// Different ciphertext that could not be decrypted on both sides.
+    Undetermined,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NamespaceSummary {
+    /// SHA-256 of the stored ciphertext, chunk ids included. Changes with
+    /// every write, so it doubles as the revision of the namespace.
+    pub revision: String,
+    /// SHA-256 of the plaintext, when it could be decrypted.
+    pub content_hash: Option<String>,
+    pub expires_at: Option<i64>,
+    pub updated_at: Option<i64>,
+    pub timelocked: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NamespaceDiff {
+    pub namespace: String,
+    pub status: NamespaceStatus,
+    pub left: Option<NamespaceSummary>,
+    pub right: Option<NamespaceSummary>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct VaultDiff {
+    /// Identities able to unlock only one side.
+    pub recipients_only_in_left: Vec<String>,
+    pub recipients_only_in_right: Vec<String>,
+    /// Flags (`sync_enabled`, `observer`) set differently.
+    pub flags_changed: Vec<String>,
+    pub namespaces: Vec<NamespaceDiff>,
+}
+
+impl VaultDiff {
+    pub fn is_identical(&self) -> bool {
+        self.recipients_only_in_left.is_empty()
+            && self.recipients_only_in_right.is_empty()
+            && self.flags_changed.is_empty()
+            && self
+                .namespaces
+                .iter()
+                .all(|namespace| namespace.status == NamespaceStatus::Identical)
+    }
+
+    pub fn count(&self, status: NamespaceStatus) -> usize {
+        self.namespaces
+            .iter()
+            .filter(|namespace| namespace.status == status)
+            .count()
+    }
+}
+
+/// Compares two vaults. `identity`, when given, is used to compare the
+/// content of namespaces whose ciphertext differs.
+pub async fn diff_vaults(
+    platform: &Platform,
+    left: &Vault,
+    right: &Vault,
+    identity: Option<&str>,
+) -> VaultDiff {
+    let left_recipients: BTreeSet<&String> = left.identity_salts.iter().map(|(pk, _)| pk).collect();
+    let right_recipients: BTreeSet<&String> =
+        right.identity_salts.iter().map(|(pk, _)| pk).collect();
+
+    let mut flags_changed = Vec::new();
+    if left.sync_enabled != right.sync_enabled {
+        flags_changed.push("sync_enabled".to_string());
+    }
+    if left.observer != right.observer {
+        flags_changed.push("observer".to_string());
+    }
+
+    let names: BTreeSet<&String> = left
+        .namespaces
+        .keys()
+        .chain(right.namespaces.keys())
+        .collect();
+
+    let mut namespaces = Vec::with_capacity(names.len());
+    for name in names {
+        let left_data = left.namespaces.get(name);
+        let right_data = right.namespaces.get(name);
+        debug_assert!(left_data.is_some() || right_data.is_some());
+
+        let mut left_summary = left_data.map(summarize);
+        let mut right_summary = right_data.map(summarize);
+
+        let status = match (&mut left_summary, &mut right_summary) {
+            (Some(_), None) => NamespaceStatus::OnlyInLeft,
+            (None, Some(_)) => NamespaceStatus::OnlyInRight,
+            (Some(l), Some(r)) if l.revision == r.revision && l.expires_at == r.expires_at => {
+                NamespaceStatus::Identical
+            }
+            (Some(l), Some(r)) => {
+                if let Some(identity) = identity {
+                    l.content_hash = content_hash(platform, left, left_data, identity).await;
+                    r.content_hash = content_hash(platform, right, right_data, identity).await;
+                }
+
+                match (&l.content_hash, &r.content_hash) {
+                    (Some(lh), Some(rh)) if lh == rh => NamespaceStatus::SameContent,
+                    (Some(_), Some(_)) => NamespaceStatus::Different,
+                    _ if l.revision == r.revision => NamespaceStatus::Different,
+                    _ => NamespaceStatus::Undetermined,
+                }
+            }
+            (None, None) => continue,
+        };
+
+        namespaces.push(NamespaceDiff {
+            namespace: name.clone(),
+            status,
+            left: left_summary,
+            right: right_summary,
+        });
+    }
+
+    VaultDiff {
+        recipients_only_in_left: left_recipients
+            .difference(&right_recipients)
+            .map(|pk| pk.to_string())
+            .collect(),
+        recipients_only_in_right: right_recipients
+            .difference(&left_recipients)
+            .map(|pk| pk.to_string())
+            .collect(),
+        flags_changed,
+        namespaces,
+    }
+}
+
+/// Compares two files produced by `export_vault`.
+pub async fn diff_vault_exports(
+    platform: &Platform,
+    left: &[u8],
+    right: &[u8],
+    identity: Option<&str>,
+) -> Result<VaultDiff, VaultError> {
+    let left = deserialize_vault(left)?;
+    let right = deserialize_vault(right)?;
+
+    Ok(diff_vaults(platform, &left, &right, identity).await)
+}
+
+/// Compares two vaults in storage.
+pub async fn diff_stored_vaults(
+    platform: &Platform,
+    left_name: &str,
+    right_name: &str,
+    identity: Option<&str>,
+) -> Result<VaultDiff, VaultError> {
+    let left = operations::read_vault(platform, left_name).await?;
+    let right = operations::read_vault(platform, right_name).await?;
+
+    Ok(diff_vaults(platform, &left, &right, identity).await)
+}
+
+fn summarize(data: &NamespaceData) -> NamespaceSummary {
+    let mut hasher = Sha256::new();
+    hasher.update(&data.data);
+    for chunk in &data.chunks {
+        hasher.update(chunk.as_bytes());
+    }
+
+    NamespaceSummary {
+        revision: hex::encode(hasher.finalize()),
+        content_hash: None,
+        expires_at: data.expiration.as_ref().map(|e| e.expires_at),
+        updated_at: data.updated_at,
+        timelocked: data.timelock.is_some(),
+    }
+}
+
+async fn content_hash(
+    platform: &Platform,
+    vault: &Vault,
+    data: Option<&NamespaceData>,
+    identity: &str,
+) -> Option<String> {
+    let data = data.filter(|data| data.timelock.is_none())?;
+    let plaintext = chunks::decrypt_namespace(platform, vault, data, identity)
+        .await
+        .ok()?;
+
+    Some(hex::encode(Sha256::digest(&plaintext)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::crypto;
+    use crate::domain::vault::operations::create_vault;
+    use crate::domain::vault::types::{Compression, Expiration};
+    use futures::executor::block_on;
+
+    async fn encrypted(platform: &Platform, recipient: &str, data: &[u8]) -> NamespaceData {
+        NamespaceData {
+            data: crypto::encrypt_for_recipients(platform, data, &[recipient])
+                .await
+                .unwrap(),
+            expiration: None,
+            chunks: Vec::new(),
+            timelock: None,
+            updated_at: None,
+            recipients: Vec::new(),
+            crdt: None,
+            integrity: None,
+            crypto_version: None,
+            compression: Compression::None,
+        }
+    }
+
+    #[test]
+    fn test_diff_vaults_classifies_namespaces() {
+        let platform = Platform::new();
+
+        block_on(async {
+            let identity = crypto::generate_identity(&platform).unwrap();
+            let recipient = crypto::identity_to_public(&platform, &identity).unwrap();
+
+            let mut left = create_vault().await.unwrap();
+            left.identity_salts.set_salt(recipient.clone(), [0u8; 32]);
+            let mut right = left.clone();
+
+            let shared = encrypted(&platform, &recipient, b"shared").await;
+            left.namespaces.insert("shared".into(), shared.clone());
+            right.namespaces.insert("shared".into(), shared);
+
+            left.namespaces.insert(
+                "rewritten".into(),
+                encrypted(&platform, &recipient, b"same").await,
+            );
+            right.namespaces.insert(
+                "rewritten".into(),
+                encrypted(&platform, &recipient, b"same").await,
+            );
+
+            left.namespaces.insert(
+                "edited".into(),
+                encrypted(&platform, &recipient, b"before").await,
+            );
+            right.namespaces.insert(
+                "edited".into(),
+                encrypted(&platform, &recipient, b"after").await,
+            );
+
+            let mut expiring = encrypted(&platform, &recipient, b"gone").await;
+            left.namespaces.insert("restored".into(), expiring.clone());
+            expiring.expiration = Some(Expiration { expires_at: 1 });
+            right.namespaces.insert("removed".into(), expiring);
+
+            right.sync_enabled = true;
+
+            let status = |diff: &VaultDiff, name: &str| {
+                diff.namespaces
+                    .iter()
+                    .find(|namespace| namespace.namespace == name)
+                    .map(|namespace| namespace.status)
+                    .unwrap()
+            };
+
+            let blind = diff_vaults(&platform, &left, &right, None).await;
+            assert_eq!(status(&blind, "shared"), NamespaceStatus::Identical);
+            assert_eq!(status(&blind, "rewritten"), NamespaceStatus::Undetermined);
+            assert_eq!(status(&blind, "edited"), NamespaceStatus::Undetermined);
+            assert_eq!(status(&blind, "restored"), NamespaceStatus::OnlyInLeft);
+            assert_eq!(status(&blind, "removed"), NamespaceStatus::OnlyInRight);
+            assert_eq!(blind.flags_changed, vec!["sync_enabled".to_string()]);
+            assert!(!blind.is_identical());
+
+            let decrypted = diff_vaults(&platform, &left, &right, Some(&identity)).await;
+            assert_eq!(
+                status(&decrypted, "rewritten"),
+                NamespaceStatus::SameContent
+            );
+            assert_eq!(status(&decrypted, "edited"), NamespaceStatus::Different);
+
+            let exported = crate::domain::vault::serialize_vault(&left).unwrap();
+            assert!(diff_vault_exports(&platform, &exported, &exported, None)
+                .await
+                .unwrap()
+                .is_identical());
+        });
+    }
+}
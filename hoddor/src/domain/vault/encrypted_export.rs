@@ -0,0 +1,250 @@
+//! Vault exports wrapped in an age layer.
+//!
+//! A plain export carries the identity salts and key checks of the vault in
+//! the clear, which is all an attacker needs to try passphrases offline. The
+//! encrypted form wraps the whole export, either to an age recipient or to a
+//! passphrase (through the same Argon2 derivation as vault identities, with a
+//! fresh salt), so a backup left on a cloud drive reveals nothing but its
+//! size.
+
+use super::error::VaultError;
+use super::operations::{export_vault_bytes_with_progress, import_vault_from_bytes_with_progress};
+use crate::domain::crypto;
+use crate::domain::progress::Progress;
+use crate::platform::Platform;
+use rand::RngCore;
+
+const ENCRYPTED_MAGIC_NUMBER: &[u8; 6] = b"VAULTX";
+const TO_RECIPIENT: u8 = b'r';
+const TO_PASSPHRASE: u8 = b'p';
+const SALT_LENGTH: usize = 32;
+
+/// What an export is encrypted to.
+#[derive(Debug, Clone, Copy)]
+pub enum ExportKey<'a> {
+    /// An age public key (`age1...`).
+    Recipient(&'a str),
+    Passphrase(&'a str),
+}
+
+/// What decrypts an export made with the matching [`ExportKey`].
+#[derive(Debug, Clone, Copy)]
+pub enum ExportSecret<'a> {
+    /// An age private key.
+    Identity(&'a str),
+    Passphrase(&'a str),
+}
+
+/// Whether `bytes` look like an export made by [`encrypt_export`].
+pub fn is_encrypted_export(bytes: &[u8]) -> bool {
+    bytes.len() > ENCRYPTED_MAGIC_NUMBER.len()
+        && &bytes[..ENCRYPTED_MAGIC_NUMBER.len()] == ENCRYPTED_MAGIC_NUMBER
+}
+
+/// Wraps a plain export in an age layer.
+pub async fn encrypt_export(
+    platform: &Platform,
+    vault_bytes: &[u8],
+    key: ExportKey<'_>,
+) -> Result<Vec<u8>, VaultError> {
+    let mut header = ENCRYPTED_MAGIC_NUMBER.to_vec();
+
+    let recipient = match key {
+        ExportKey::Recipient(recipient) => {
+            header.push(TO_RECIPIENT);
+            crypto::parse_recipient(platform, recipient)
+                .map_err(|e| VaultError::io_error(e.to_string()))?
+        }
+        ExportKey::Passphrase(passphrase) => {
+            let mut salt = [0u8; SALT_LENGTH];
+            rand::thread_rng().fill_bytes(&mut salt);
+            header.push(TO_PASSPHRASE);
+            header.extend_from_slice(&salt);
+
+            let identity = crypto::identity_from_passphrase(platform, passphrase, &salt)
+                .await
+                .map_err(|e| VaultError::io_error(e.to_string()))?;
+            crypto::identity_to_public(platform, &identity)
+                .map_err(|e| VaultError::io_error(e.to_string()))?
+        }
+    };
+
+    let encrypted = crypto::encrypt_for_recipients(platform, vault_bytes, &[&recipient])
+        .await
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+    header.extend_from_slice(&encrypted);
+    Ok(header)
+}
+
+/// Removes the age layer added by [`encrypt_export`], returning the plain
+/// export. A wrong identity or passphrase fails with
+/// [`VaultError::InvalidPassword`].
+pub async fn decrypt_export(
+    platform: &Platform,
+    bytes: &[u8],
+    secret: ExportSecret<'_>,
+) -> Result<Vec<u8>, VaultError> {
+    if !is_encrypted_export(bytes) {
+        return Err(VaultError::serialization_error(
+            "Invalid encrypted export: missing or incorrect magic number",
+        ));
+    }
+
+    let mode = bytes[ENCRYPTED_MAGIC_NUMBER.len()];
+    let body = &bytes[ENCRYPTED_MAGIC_NUMBER.len() + 1..];
+
+    let (identity, encrypted) = match (mode, secret) {
+        (TO_RECIPIENT, ExportSecret::Identity(identity)) => (identity.to_string(), body),
+        (TO_PASSPHRASE, ExportSecret::Passphrase(passphrase)) if body.len() > SALT_LENGTH => {
+            let (salt, encrypted) = body.split_at(SALT_LENGTH);
+            let identity = crypto::identity_from_passphrase(platform, passphrase, salt)
+                .await
+                .map_err(|e| VaultError::io_error(e.to_string()))?;
+            (identity, encrypted)
+        }
+        (TO_RECIPIENT, ExportSecret::Passphrase(_)) => {
+            return Err(VaultError::serialization_error(
+                "Export is encrypted to a recipient, not a passphrase",
+            ))
+        }
+        (TO_PASSPHRASE, ExportSecret::Identity(_)) => {
+            return Err(VaultError::serialization_error(
+                "Export is encrypted to a passphrase, not a recipient",
+            ))
+        }
+        _ => {
+            return Err(VaultError::serialization_error(
+                "Invalid encrypted export: unknown or truncated header",
+            ))
+        }
+    };
+
+    crypto::decrypt_with_identity(platform, encrypted, &identity)
+        .await
+        .map_err(|_| VaultError::InvalidPassword)
+}
+
+/// Exports `vault_name` and encrypts the export to `key`.
+#[tracing::instrument(skip_all, fields(vault = vault_name))]
+pub async fn export_vault_encrypted(
+    platform: &Platform,
+    vault_name: &str,
+    key: ExportKey<'_>,
+    on_progress: &dyn Fn(Progress),
+) -> Result<Vec<u8>, VaultError> {
+    let vault_bytes = export_vault_bytes_with_progress(platform, vault_name, on_progress).await?;
+
+    encrypt_export(platform, &vault_bytes, key).await
+}
+
+/// Decrypts an export made by [`export_vault_encrypted`] and imports it as
+/// a new vault.
+#[tracing::instrument(skip_all, fields(vault = vault_name))]
+pub async fn import_vault_encrypted(
+    platform: &Platform,
+    vault_name: &str,
+    bytes: &[u8],
+    secret: ExportSecret<'_>,
+    on_progress: &dyn Fn(Progress),
+) -> Result<(), VaultError> {
+    let vault_bytes = decrypt_export(platform, bytes, secret).await?;
+
+    import_vault_from_bytes_with_progress(platform, vault_name, &vault_bytes, on_progress).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::progress::ignore_progress;
+    use crate::domain::vault::operations::{create_vault, delete_vault, read_vault, save_vault};
+    use crate::domain::vault::types::{Compression, NamespaceData};
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_encrypted_export_roundtrips_to_recipient_and_passphrase() {
+        let platform = Platform::new();
+        let source = "encrypted_export_source";
+        let target = "encrypted_export_target";
+
+        block_on(async {
+            for vault_name in [source, target] {
+                let _ = delete_vault(&platform, vault_name).await;
+            }
+
+            let mut vault = create_vault().await.unwrap();
+            vault.namespaces.insert(
+                "notes".to_string(),
+                NamespaceData {
+                    data: vec![1, 2, 3],
+                    expiration: None,
+                    chunks: Vec::new(),
+                    timelock: None,
+                    updated_at: None,
+                    recipients: Vec::new(),
+                    crdt: None,
+                    integrity: None,
+                    crypto_version: None,
+                    compression: Compression::None,
+                },
+            );
+            save_vault(&platform, source, vault).await.unwrap();
+
+            let identity = crypto::generate_identity(&platform).unwrap();
+            let recipient = crypto::identity_to_public(&platform, &identity).unwrap();
+
+            let bytes = export_vault_encrypted(
+                &platform,
+                source,
+                ExportKey::Recipient(&recipient),
+                &ignore_progress,
+            )
+            .await
+            .unwrap();
+            assert!(is_encrypted_export(&bytes));
+            assert!(!bytes.windows(5).any(|window| window == b"notes"));
+
+            let other = crypto::generate_identity(&platform).unwrap();
+            assert!(matches!(
+                decrypt_export(&platform, &bytes, ExportSecret::Identity(&other)).await,
+                Err(VaultError::InvalidPassword)
+            ));
+
+            import_vault_encrypted(
+                &platform,
+                target,
+                &bytes,
+                ExportSecret::Identity(&identity),
+                &ignore_progress,
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                read_vault(&platform, target).await.unwrap().namespaces["notes"].data,
+                vec![1, 2, 3]
+            );
+
+            let bytes = export_vault_encrypted(
+                &platform,
+                source,
+                ExportKey::Passphrase("correct horse"),
+                &ignore_progress,
+            )
+            .await
+            .unwrap();
+            assert!(matches!(
+                decrypt_export(&platform, &bytes, ExportSecret::Passphrase("wrong")).await,
+                Err(VaultError::InvalidPassword)
+            ));
+            let plain =
+                decrypt_export(&platform, &bytes, ExportSecret::Passphrase("correct horse"))
+                    .await
+                    .unwrap();
+            assert_eq!(&plain[..6], b"VAULT1");
+
+            for vault_name in [source, target] {
+                delete_vault(&platform, vault_name).await.unwrap();
+            }
+        });
+    }
+}
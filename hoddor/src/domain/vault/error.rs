@@ -1,3 +1,4 @@
+use crate::domain::retry::RetryError;
 use std::fmt;
 
 #[derive(Debug, Clone)]
@@ -10,6 +11,31 @@ pub enum VaultError {
     NamespaceAlreadyExists,
     VaultAlreadyExists,
     VaultNotFound,
+    ObserverVault,
+    Cancelled,
+    TimeLocked(i64),
+    FrozenVault,
+    RetentionLocked(i64),
+    SnapshotNotFound,
+    /// A namespace write refused by the secret scan, with the kinds of
+    /// secrets found.
+    SecretDetected(String),
+    /// A differentially private query the log's budget cannot cover, with
+    /// the budget left.
+    PrivacyBudgetExhausted(f64),
+    /// A decrypted namespace whose plaintext does not match its integrity
+    /// MAC, with the namespace name.
+    IntegrityCheckFailed(String),
+    /// A write to a namespace, or a change of its recipients, by a key only
+    /// granted read access to it.
+    ReadOnlyRecipient,
+    /// A namespace write larger than the vault's limit, with the bytes the
+    /// vault already stores.
+    QuotaExceeded {
+        size: u64,
+        limit: u64,
+        used: u64,
+    },
 }
 
 impl fmt::Display for VaultError {
@@ -23,12 +49,49 @@ impl fmt::Display for VaultError {
             VaultError::NamespaceAlreadyExists => write!(f, "Namespace already exists"),
             VaultError::VaultAlreadyExists => write!(f, "Vault already exists"),
             VaultError::VaultNotFound => write!(f, "Vault not found"),
+            VaultError::ObserverVault => {
+                write!(f, "Vault is an observer replica without decryption keys")
+            }
+            VaultError::Cancelled => write!(f, "Operation was cancelled"),
+            VaultError::TimeLocked(release_at) => {
+                write!(f, "Namespace is time-locked until {release_at}")
+            }
+            VaultError::FrozenVault => write!(f, "Vault is frozen and read-only"),
+            VaultError::RetentionLocked(removable_at) => {
+                write!(f, "Namespace is under retention until {removable_at}")
+            }
+            VaultError::SnapshotNotFound => write!(f, "Snapshot not found"),
+            VaultError::PrivacyBudgetExhausted(remaining) => {
+                write!(f, "Privacy budget exhausted ({remaining} left)")
+            }
+            VaultError::IntegrityCheckFailed(namespace) => {
+                write!(f, "Namespace {namespace} does not match its integrity MAC")
+            }
+            VaultError::SecretDetected(kinds) => {
+                write!(f, "Namespace payload looks like it contains key material: {kinds}")
+            }
+            VaultError::ReadOnlyRecipient => {
+                write!(f, "Recipients of a namespace can only read it")
+            }
+            VaultError::QuotaExceeded { size, limit, used } => write!(
+                f,
+                "Namespace payload of {size} bytes exceeds the {limit} byte limit ({used} bytes already stored)"
+            ),
         }
     }
 }
 
 impl std::error::Error for VaultError {}
 
+impl From<RetryError<VaultError>> for VaultError {
+    fn from(error: RetryError<VaultError>) -> Self {
+        match error {
+            RetryError::Cancelled => VaultError::Cancelled,
+            RetryError::Exhausted { last_error, .. } => last_error,
+        }
+    }
+}
+
 impl VaultError {
     pub fn io_error(message: impl Into<String>) -> Self {
         VaultError::IoError(message.into())
@@ -37,4 +100,35 @@ impl VaultError {
     pub fn serialization_error(message: impl Into<String>) -> Self {
         VaultError::SerializationError(message.into())
     }
+
+    /// Whether retrying the same operation may succeed, e.g. when storage was
+    /// momentarily held by another tab.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, VaultError::IoError(_))
+    }
+
+    /// Stable identifier of the variant, unlike the message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            VaultError::IoError(_) => "io_error",
+            VaultError::NamespaceNotFound => "namespace_not_found",
+            VaultError::InvalidPassword => "invalid_password",
+            VaultError::SerializationError(_) => "serialization_error",
+            VaultError::DataExpired => "data_expired",
+            VaultError::NamespaceAlreadyExists => "namespace_already_exists",
+            VaultError::VaultAlreadyExists => "vault_already_exists",
+            VaultError::VaultNotFound => "vault_not_found",
+            VaultError::ObserverVault => "observer_vault",
+            VaultError::Cancelled => "cancelled",
+            VaultError::TimeLocked(_) => "time_locked",
+            VaultError::FrozenVault => "frozen_vault",
+            VaultError::RetentionLocked(_) => "retention_locked",
+            VaultError::SnapshotNotFound => "snapshot_not_found",
+            VaultError::SecretDetected(_) => "secret_detected",
+            VaultError::PrivacyBudgetExhausted(_) => "privacy_budget_exhausted",
+            VaultError::IntegrityCheckFailed(_) => "integrity_check_failed",
+            VaultError::ReadOnlyRecipient => "read_only_recipient",
+            VaultError::QuotaExceeded { .. } => "quota_exceeded",
+        }
+    }
 }
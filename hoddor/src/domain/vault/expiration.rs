@@ -1,7 +1,27 @@
 use super::error::VaultError;
 use super::operations::get_namespace_filename;
 use super::types::{Expiration, Vault};
+use crate::domain::clock_skew::ClockSkew;
+use crate::notifications::EventType;
 use crate::platform::Platform;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// Called with the subscription id and the name of the namespace that
+/// expired.
+pub type ExpirationCallback = Arc<dyn Fn(u32, &str) + Send + Sync>;
+
+struct Subscription {
+    vault_name: String,
+    callback: ExpirationCallback,
+}
+
+static SUBSCRIPTIONS: Lazy<Mutex<HashMap<u32, Arc<Subscription>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_SUBSCRIPTION_ID: AtomicU32 = AtomicU32::new(1);
 
 pub fn is_expired(expiration: &Option<Expiration>, now: i64) -> bool {
     expiration.as_ref().is_some_and(|exp| now >= exp.expires_at)
@@ -15,6 +35,60 @@ pub fn create_expiration(expires_in_seconds: Option<i64>, now: i64) -> Option<Ex
         })
 }
 
+/// Moves an expiration set on a peer's clock onto the local one, so a peer
+/// with a wrong clock neither expires synced data early nor keeps it alive.
+pub fn normalize_remote_expiration(
+    expiration: Option<Expiration>,
+    skew: &ClockSkew,
+) -> Option<Expiration> {
+    expiration.map(|expiration| Expiration {
+        expires_at: skew.to_local_seconds(expiration.expires_at),
+    })
+}
+
+/// Registers `callback` for the namespaces of `vault_name` removed once
+/// expired, by a read or by [`cleanup_expired_namespaces`], and returns the
+/// id to pass to [`off_namespace_expired`].
+pub fn on_namespace_expired(vault_name: &str, callback: ExpirationCallback) -> u32 {
+    let id = NEXT_SUBSCRIPTION_ID.fetch_add(1, Ordering::Relaxed);
+    SUBSCRIPTIONS.lock().insert(
+        id,
+        Arc::new(Subscription {
+            vault_name: vault_name.to_string(),
+            callback,
+        }),
+    );
+    id
+}
+
+/// Removes the subscription `id`. Returns `false` if there was none.
+pub fn off_namespace_expired(id: u32) -> bool {
+    SUBSCRIPTIONS.lock().remove(&id).is_some()
+}
+
+/// Emits a `namespaceExpired` event through the notifier and calls the
+/// subscriptions on `vault_name`.
+pub(super) fn notify_namespace_expired(platform: &Platform, vault_name: &str, namespace: &str) {
+    if let Err(e) =
+        platform
+            .notifier()
+            .notify_event(vault_name, EventType::NamespaceExpired, namespace)
+    {
+        tracing::debug!(error = %e, "Failed to notify namespace expiration");
+    }
+
+    let subscriptions: Vec<(u32, Arc<Subscription>)> = SUBSCRIPTIONS
+        .lock()
+        .iter()
+        .filter(|(_, subscription)| subscription.vault_name == vault_name)
+        .map(|(id, subscription)| (*id, subscription.clone()))
+        .collect();
+
+    for (id, subscription) in subscriptions {
+        (subscription.callback)(id, namespace);
+    }
+}
+
 pub async fn cleanup_expired_namespaces(
     platform: &Platform,
     vault: &mut Vault,
@@ -27,7 +101,9 @@ pub async fn cleanup_expired_namespaces(
         .namespaces
         .iter()
         .filter_map(|(namespace, encrypted)| {
-            if is_expired(&encrypted.expiration, now) {
+            if is_expired(&encrypted.expiration, now)
+                && super::retention::ensure_removable(vault, namespace, now).is_ok()
+            {
                 Some(namespace.clone())
             } else {
                 None
@@ -43,9 +119,8 @@ pub async fn cleanup_expired_namespaces(
         let _ = storage.delete_file(&namespace_path).await;
         vault.namespaces.remove(&namespace);
         data_removed = true;
-        platform
-            .logger()
-            .log(&format!("Removed expired namespace: {namespace}"));
+        tracing::debug!(namespace = %namespace, "Removed expired namespace");
+        notify_namespace_expired(platform, vault_name, &namespace);
     }
 
     Ok(data_removed)
@@ -54,6 +129,29 @@ pub async fn cleanup_expired_namespaces(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::vault::operations::{
+        cleanup_vault, create_vault, delete_vault, read_namespace, save_vault, upsert_namespace,
+    };
+    use crate::ports::ClockPort;
+    use futures::executor::block_on;
+
+    struct FixedClock(f64);
+
+    #[async_trait::async_trait(?Send)]
+    impl ClockPort for FixedClock {
+        fn now(&self) -> f64 {
+            self.0
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+
+        async fn sleep(&self, _milliseconds: u32) {}
+    }
+
+    static WRITTEN: FixedClock = FixedClock(1_700_000_000_000.0);
+    static EXPIRED: FixedClock = FixedClock(1_700_000_100_000.0);
 
     #[test]
     fn test_is_expired_with_no_expiration() {
@@ -121,4 +219,68 @@ mod tests {
         let expiration = result.unwrap();
         assert_eq!(expiration.expires_at, now + one_year_seconds);
     }
+
+    #[test]
+    fn test_normalize_remote_expiration_applies_skew() {
+        let mut skew = ClockSkew::new();
+        skew.observe(1_000_000, 1_300_000);
+
+        let normalized = normalize_remote_expiration(Some(Expiration { expires_at: 2000 }), &skew);
+
+        assert_eq!(normalized.unwrap().expires_at, 2300);
+        assert!(normalize_remote_expiration(None, &skew).is_none());
+    }
+
+    #[test]
+    fn test_subscriptions_hear_of_expired_namespaces() {
+        let written = Platform::new().with_clock(&WRITTEN);
+        let expired = Platform::new().with_clock(&EXPIRED);
+        let vault_name = "expiration_events_test";
+
+        block_on(async {
+            let _ = delete_vault(&written, vault_name).await;
+            save_vault(&written, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let identity = crate::domain::crypto::generate_identity(&written).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&written, &identity).unwrap();
+            for namespace in ["read", "cleaned"] {
+                upsert_namespace(
+                    &written,
+                    vault_name,
+                    &public_key,
+                    namespace,
+                    vec![1],
+                    Some(10),
+                    false,
+                )
+                .await
+                .unwrap();
+            }
+
+            let heard = Arc::new(Mutex::new(Vec::new()));
+            let id = on_namespace_expired(vault_name, {
+                let heard = heard.clone();
+                Arc::new(move |_, namespace: &str| heard.lock().push(namespace.to_string()))
+            });
+            let other = on_namespace_expired(
+                "other_vault",
+                Arc::new(|_, _: &str| panic!("Subscription of another vault called")),
+            );
+
+            assert!(matches!(
+                read_namespace(&expired, vault_name, &identity, "read").await,
+                Err(VaultError::DataExpired)
+            ));
+            assert!(cleanup_vault(&expired, vault_name).await.unwrap());
+            assert_eq!(*heard.lock(), ["read", "cleaned"]);
+
+            assert!(off_namespace_expired(id));
+            assert!(!off_namespace_expired(id));
+            assert!(off_namespace_expired(other));
+            delete_vault(&written, vault_name).await.unwrap();
+        });
+    }
 }
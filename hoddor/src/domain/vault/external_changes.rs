@@ -0,0 +1,31 @@
+//! Changes made to vault files by another process.
+//!
+//! A file watcher reports them here, bumping a per-vault generation, as do
+//! renames, which take the files away from views of the old name. Views
+//! cached in memory, such as the metadata held by a
+//! [`VaultHandle`](super::VaultHandle), remember the generation they were
+//! read at and know they are stale once it moves.
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+
+static GENERATIONS: Lazy<Mutex<HashMap<String, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn record_external_change(vault_name: &str) {
+    *GENERATIONS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(vault_name.to_string())
+        .or_default() += 1;
+}
+
+/// Number of external changes seen on `vault_name` so far.
+pub fn change_generation(vault_name: &str) -> u64 {
+    GENERATIONS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(vault_name)
+        .copied()
+        .unwrap_or(0)
+}
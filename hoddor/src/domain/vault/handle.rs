@@ -0,0 +1,265 @@
+use super::error::VaultError;
+use super::types::Vault;
+use super::{external_changes, operations, unlock_attempts, validation, wal};
+use crate::domain::authentication;
+use crate::domain::retry::{self, CancellationToken, RetryPolicy};
+use crate::platform::Platform;
+
+/// An opened vault.
+///
+/// Opening replays interrupted writes once and caches the platform and the
+/// vault metadata, so calls made through the handle skip both. Namespaces are
+/// still read and locked per call: holding a vault lock for the lifetime of
+/// the handle would block every other tab working on the same vault.
+///
+/// When another process changes the vault files, the cached metadata is
+/// marked stale and no longer trusted until [`refresh`](Self::refresh).
+#[derive(Clone)]
+pub struct VaultHandle {
+    platform: Platform,
+    name: String,
+    metadata: Vault,
+    generation: u64,
+    retry_policy: RetryPolicy,
+}
+
+impl VaultHandle {
+    pub async fn open(platform: Platform, vault_name: &str) -> Result<Self, VaultError> {
+        validation::validate_vault_name(vault_name)?;
+
+        wal::recover_vault(&platform, vault_name).await?;
+        let generation = external_changes::change_generation(vault_name);
+        let metadata = operations::read_vault_metadata(&platform, vault_name).await?;
+
+        Ok(Self {
+            platform,
+            name: vault_name.to_string(),
+            metadata,
+            generation,
+            retry_policy: retry::default_retry_policy(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn platform(&self) -> &Platform {
+        &self.platform
+    }
+
+    /// Metadata as of opening or the last [`refresh`](Self::refresh); its
+    /// namespaces and chunks are always empty.
+    pub fn metadata(&self) -> &Vault {
+        &self.metadata
+    }
+
+    pub async fn refresh(&mut self) -> Result<(), VaultError> {
+        let generation = external_changes::change_generation(&self.name);
+        self.metadata = operations::read_vault_metadata(&self.platform, &self.name).await?;
+        self.generation = generation;
+        Ok(())
+    }
+
+    /// Whether another process changed the vault since the metadata was read.
+    pub fn is_stale(&self) -> bool {
+        external_changes::change_generation(&self.name) != self.generation
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// Overrides the default retry policy for writes made through this
+    /// handle.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry_policy = policy;
+    }
+
+    /// Checks `identity_private_key` against the cached key check values and
+    /// only reads the vault when they cannot settle it.
+    pub async fn verify_identity(&self, identity_private_key: &str) -> Result<(), VaultError> {
+        // Observer replicas may have been promoted since the handle opened,
+        // and another process may have changed the identities.
+        if !self.metadata.observer && !self.is_stale() {
+            let verified =
+                crate::domain::crypto::identity_to_public(&self.platform, identity_private_key)
+                    .ok()
+                    .and_then(|public_key| {
+                        authentication::verify_key_check(
+                            &self.metadata.identity_salts,
+                            &public_key,
+                            identity_private_key,
+                        )
+                    });
+
+            if verified == Some(true) {
+                unlock_attempts::reset_unlock_failures(&self.name);
+                return Ok(());
+            }
+        }
+
+        operations::verify_vault_identity(&self.platform, &self.name, identity_private_key).await
+    }
+
+    pub async fn read_namespace(
+        &self,
+        identity_private_key: &str,
+        namespace: &str,
+    ) -> Result<Vec<u8>, VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        operations::read_namespace(&self.platform, &self.name, identity_private_key, namespace)
+            .await
+    }
+
+    /// Stores `data` under `namespace`, retrying transient storage errors with
+    /// the handle's retry policy until `cancellation` fires.
+    pub async fn upsert_namespace(
+        &self,
+        identity_public_key: &str,
+        namespace: &str,
+        data: Vec<u8>,
+        expires_in_seconds: Option<i64>,
+        replace_if_exists: bool,
+        cancellation: &CancellationToken,
+    ) -> Result<(), VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        self.retry_policy
+            .run_if(
+                &self.platform,
+                cancellation,
+                VaultError::is_transient,
+                |_| {
+                    operations::upsert_namespace(
+                        &self.platform,
+                        &self.name,
+                        identity_public_key,
+                        namespace,
+                        data.clone(),
+                        expires_in_seconds,
+                        replace_if_exists,
+                    )
+                },
+            )
+            .await
+            .map_err(VaultError::from)
+    }
+
+    pub async fn remove_namespace(&self, namespace: &str) -> Result<(), VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        operations::remove_namespace(&self.platform, &self.name, namespace).await
+    }
+
+    pub async fn list_namespaces(&self) -> Result<Vec<String>, VaultError> {
+        operations::list_namespaces_in_vault(&self.platform, &self.name).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_open_missing_vault_fails() {
+        let platform = Platform::new();
+
+        assert!(block_on(VaultHandle::open(platform, "handle_test_missing")).is_err());
+    }
+
+    #[test]
+    fn test_handle_round_trip() {
+        let platform = Platform::new();
+        let vault_name = "handle_test_round_trip";
+
+        block_on(async {
+            let _ = operations::delete_vault(&platform, vault_name).await;
+
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+
+            let mut vault = operations::create_vault().await.unwrap();
+            authentication::record_key_check(&mut vault.identity_salts, &public_key, &identity);
+            operations::save_vault(&platform, vault_name, vault)
+                .await
+                .unwrap();
+
+            let mut handle = VaultHandle::open(platform, vault_name).await.unwrap();
+            handle.verify_identity(&identity).await.unwrap();
+
+            handle
+                .upsert_namespace(
+                    &public_key,
+                    "notes",
+                    vec![1, 2, 3],
+                    None,
+                    false,
+                    &CancellationToken::new(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(
+                handle.read_namespace(&identity, "notes").await.unwrap(),
+                vec![1, 2, 3]
+            );
+            assert_eq!(handle.list_namespaces().await.unwrap(), vec!["notes"]);
+
+            handle.remove_namespace("notes").await.unwrap();
+            assert!(handle.list_namespaces().await.unwrap().is_empty());
+
+            assert!(!handle.is_stale());
+            external_changes::record_external_change(vault_name);
+            assert!(handle.is_stale());
+            handle.verify_identity(&identity).await.unwrap();
+            handle.refresh().await.unwrap();
+            assert!(!handle.is_stale());
+
+            operations::delete_vault(handle.platform(), vault_name)
+                .await
+                .unwrap();
+        });
+    }
+
+    #[test]
+    fn test_handles_of_renamed_vaults_are_stale() {
+        let platform = Platform::new();
+        let (vault_name, new_name) = ("handle_rename_test", "handle_renamed_test");
+
+        block_on(async {
+            for name in [vault_name, new_name] {
+                let _ = operations::delete_vault(&platform, name).await;
+            }
+
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+            let mut vault = operations::create_vault().await.unwrap();
+            authentication::record_key_check(&mut vault.identity_salts, &public_key, &identity);
+            operations::save_vault(&platform, vault_name, vault)
+                .await
+                .unwrap();
+
+            let mut handle = VaultHandle::open(Platform::new(), vault_name)
+                .await
+                .unwrap();
+            operations::rename_vault(&platform, vault_name, new_name)
+                .await
+                .unwrap();
+
+            // The cached key check no longer vouches for a vault that moved.
+            assert!(handle.is_stale());
+            assert!(handle.verify_identity(&identity).await.is_err());
+            assert!(handle.refresh().await.is_err());
+
+            let renamed = VaultHandle::open(Platform::new(), new_name).await.unwrap();
+            renamed.verify_identity(&identity).await.unwrap();
+
+            operations::delete_vault(&platform, new_name).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,434 @@
+//! Point-in-time history of namespaces, for undo and audit.
+//!
+//! History is off by default. Once [`enable_history`] turned it on for a
+//! vault, every namespace write keeps the version it writes under
+//! `history/` instead of only overwriting the namespace file: the namespace
+//! as stored, still encrypted, along with the chunks it references. Each
+//! snapshot file is named after the SHA-256 of its content, so identical
+//! versions are stored once. `history/index.json` lists the snapshots in the
+//! order they were taken; rewriting a namespace with the content of its last
+//! snapshot adds none.
+//!
+//! Snapshots are never removed on their own: [`prune_snapshots`] drops those
+//! a [`PrunePolicy`] no longer keeps.
+
+use super::chunks;
+use super::error::VaultError;
+use super::integrity::check_integrity;
+use super::serialization::{decode_stored, encode_stored};
+use super::types::{NamespaceData, Vault};
+use crate::platform::Platform;
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+const SETTINGS_FILENAME: &str = "history.json";
+const HISTORY_DIRECTORY: &str = "history";
+const INDEX_FILENAME: &str = "index.json";
+
+/// One version of a namespace kept in the vault history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    /// SHA-256 of the stored snapshot, in hex.
+    pub id: String,
+    pub namespace: String,
+    /// Unix time in seconds the snapshot was taken.
+    pub taken_at: i64,
+    /// Encrypted size in bytes, chunks included.
+    pub size: u64,
+}
+
+/// Snapshots [`prune_snapshots`] keeps. A snapshot is dropped when either
+/// limit rules it out; without limits every snapshot is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct PrunePolicy {
+    /// Most recent snapshots kept per namespace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_last: Option<usize>,
+    /// Age in seconds past which snapshots are dropped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_seconds: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HistorySettings {
+    enabled_at: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SnapshotIndex {
+    snapshots: Vec<SnapshotInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredSnapshot {
+    namespace: NamespaceData,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    chunks: BTreeMap<String, ByteBuf>,
+}
+
+fn settings_path(vault_name: &str) -> String {
+    format!("{vault_name}/{SETTINGS_FILENAME}")
+}
+
+fn history_path(vault_name: &str) -> String {
+    format!("{vault_name}/{HISTORY_DIRECTORY}")
+}
+
+fn index_path(vault_name: &str) -> String {
+    format!("{vault_name}/{HISTORY_DIRECTORY}/{INDEX_FILENAME}")
+}
+
+fn snapshot_path(vault_name: &str, id: &str) -> String {
+    format!("{vault_name}/{HISTORY_DIRECTORY}/{id}")
+}
+
+fn history_lock_name(vault_name: &str) -> String {
+    format!("{vault_name}/{HISTORY_DIRECTORY}")
+}
+
+/// Starts keeping a snapshot of every namespace write of `vault_name`.
+/// Snapshots taken before history was last disabled stay.
+pub async fn enable_history(platform: &Platform, vault_name: &str) -> Result<(), VaultError> {
+    let settings = HistorySettings {
+        enabled_at: super::operations::current_timestamp(platform),
+    };
+    let json = serde_json::to_string(&settings)
+        .map_err(|_| VaultError::serialization_error("Failed to serialize history settings"))?;
+
+    platform
+        .storage()
+        .write_file(&settings_path(vault_name), &json)
+        .await
+}
+
+/// Stops taking snapshots. Those already taken stay until pruned.
+pub async fn disable_history(platform: &Platform, vault_name: &str) -> Result<(), VaultError> {
+    let _ = platform
+        .storage()
+        .delete_file(&settings_path(vault_name))
+        .await;
+    Ok(())
+}
+
+pub async fn is_history_enabled(platform: &Platform, vault_name: &str) -> bool {
+    platform
+        .storage()
+        .read_file(&settings_path(vault_name))
+        .await
+        .is_ok_and(|json| serde_json::from_str::<HistorySettings>(&json).is_ok())
+}
+
+/// Snapshots `namespaces` of `vault` as about to be written, when history
+/// is on. Called before the write, so no version reaches storage without
+/// its snapshot.
+pub(super) async fn record_snapshots<'a>(
+    platform: &Platform,
+    vault_name: &str,
+    vault: &Vault,
+    namespaces: impl IntoIterator<Item = &'a str>,
+) -> Result<(), VaultError> {
+    if !is_history_enabled(platform, vault_name).await {
+        return Ok(());
+    }
+
+    let _guard = platform
+        .locks()
+        .acquire(&history_lock_name(vault_name))
+        .await?;
+
+    let storage = platform.storage();
+    let mut index = read_index(platform, vault_name).await?;
+    let mut changed = false;
+    let now = super::operations::current_timestamp(platform);
+
+    for namespace in namespaces {
+        let Some(data) = vault.namespaces.get(namespace) else {
+            continue;
+        };
+
+        let snapshot = StoredSnapshot {
+            namespace: data.clone(),
+            chunks: data
+                .chunks
+                .iter()
+                .filter_map(|id| {
+                    let chunk = vault.chunks.get(id)?;
+                    Some((id.clone(), ByteBuf::from(chunk.clone())))
+                })
+                .collect(),
+        };
+        let content = encode_stored(&snapshot)?;
+        let id = hex::encode(Sha256::digest(content.as_bytes()));
+
+        let last = index
+            .snapshots
+            .iter()
+            .rev()
+            .find(|snapshot| snapshot.namespace == namespace);
+        if last.is_some_and(|last| last.id == id) {
+            continue;
+        }
+
+        if !changed {
+            storage.create_directory(&history_path(vault_name)).await?;
+        }
+        if !index.snapshots.iter().any(|snapshot| snapshot.id == id) {
+            storage
+                .write_file(&snapshot_path(vault_name, &id), &content)
+                .await?;
+        }
+
+        index.snapshots.push(SnapshotInfo {
+            id,
+            namespace: namespace.to_string(),
+            taken_at: now,
+            size: content.len() as u64,
+        });
+        changed = true;
+    }
+
+    if changed {
+        write_index(platform, vault_name, &index).await?;
+    }
+
+    Ok(())
+}
+
+async fn read_index(platform: &Platform, vault_name: &str) -> Result<SnapshotIndex, VaultError> {
+    let storage = platform.storage();
+    if !storage.directory_exists(&history_path(vault_name)).await? {
+        return Ok(SnapshotIndex::default());
+    }
+
+    match storage.read_file(&index_path(vault_name)).await {
+        Ok(json) => serde_json::from_str(&json)
+            .map_err(|_| VaultError::serialization_error("Failed to parse snapshot index")),
+        Err(_) => Ok(SnapshotIndex::default()),
+    }
+}
+
+async fn write_index(
+    platform: &Platform,
+    vault_name: &str,
+    index: &SnapshotIndex,
+) -> Result<(), VaultError> {
+    let json = serde_json::to_string(index)
+        .map_err(|_| VaultError::serialization_error("Failed to serialize snapshot index"))?;
+
+    platform
+        .storage()
+        .write_file(&index_path(vault_name), &json)
+        .await
+}
+
+/// Snapshots of `vault_name`, oldest first.
+pub async fn list_snapshots(
+    platform: &Platform,
+    vault_name: &str,
+) -> Result<Vec<SnapshotInfo>, VaultError> {
+    Ok(read_index(platform, vault_name).await?.snapshots)
+}
+
+/// Content of `namespace` as it was in snapshot `snapshot_id`.
+pub async fn read_at(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    namespace: &str,
+    snapshot_id: &str,
+) -> Result<Vec<u8>, VaultError> {
+    let index = read_index(platform, vault_name).await?;
+    if !index
+        .snapshots
+        .iter()
+        .any(|snapshot| snapshot.id == snapshot_id && snapshot.namespace == namespace)
+    {
+        return Err(VaultError::SnapshotNotFound);
+    }
+
+    let content = platform
+        .storage()
+        .read_file(&snapshot_path(vault_name, snapshot_id))
+        .await?;
+    let snapshot: StoredSnapshot = decode_stored(&content)?;
+
+    if let Some(timelock) = &snapshot.namespace.timelock {
+        return Err(VaultError::TimeLocked(timelock.release_at));
+    }
+
+    let chunks: BTreeMap<String, Vec<u8>> = snapshot
+        .chunks
+        .into_iter()
+        .map(|(id, chunk)| (id, chunk.into_vec()))
+        .collect();
+
+    let plaintext =
+        chunks::decrypt_with_chunks(platform, &chunks, &snapshot.namespace, identity_private_key)
+            .await?;
+    check_integrity(
+        platform,
+        namespace,
+        &snapshot.namespace,
+        &plaintext,
+        identity_private_key,
+    )
+    .await?;
+
+    Ok(plaintext)
+}
+
+/// Drops the snapshots `policy` rules out and the files no snapshot refers
+/// to any more. Returns the number of snapshots dropped.
+pub async fn prune_snapshots(
+    platform: &Platform,
+    vault_name: &str,
+    policy: PrunePolicy,
+) -> Result<usize, VaultError> {
+    let _guard = platform
+        .locks()
+        .acquire(&history_lock_name(vault_name))
+        .await?;
+
+    let mut index = read_index(platform, vault_name).await?;
+    let before = index.snapshots.len();
+    let now = super::operations::current_timestamp(platform);
+
+    let mut kept_per_namespace: HashMap<String, usize> = HashMap::new();
+    let mut kept = Vec::with_capacity(before);
+    for snapshot in index.snapshots.into_iter().rev() {
+        let count = kept_per_namespace
+            .entry(snapshot.namespace.clone())
+            .or_default();
+        let too_many = policy
+            .keep_last
+            .is_some_and(|keep_last| *count >= keep_last);
+        let too_old = policy
+            .max_age_seconds
+            .is_some_and(|max_age| now - snapshot.taken_at > max_age);
+
+        if !too_many && !too_old {
+            *count += 1;
+            kept.push(snapshot);
+        }
+    }
+    kept.reverse();
+    index.snapshots = kept;
+
+    let removed = before - index.snapshots.len();
+    if removed == 0 {
+        return Ok(0);
+    }
+
+    write_index(platform, vault_name, &index).await?;
+
+    let referenced: HashSet<&str> = index
+        .snapshots
+        .iter()
+        .map(|snapshot| snapshot.id.as_str())
+        .collect();
+    let storage = platform.storage();
+    for entry in storage.list_entries(&history_path(vault_name)).await? {
+        if entry != INDEX_FILENAME && !referenced.contains(entry.as_str()) {
+            storage
+                .delete_file(&snapshot_path(vault_name, &entry))
+                .await?;
+        }
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::crypto::{generate_identity, identity_to_public};
+    use crate::domain::vault::operations::{
+        create_vault, delete_vault, read_namespace, save_vault, upsert_namespace,
+    };
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_snapshots_keep_every_version() {
+        let platform = Platform::new();
+        let vault_name = "history_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+            enable_history(&platform, vault_name).await.unwrap();
+
+            let identity = generate_identity(&platform).unwrap();
+            let public_key = identity_to_public(&platform, &identity).unwrap();
+
+            for version in ["first", "second", "third"] {
+                upsert_namespace(
+                    &platform,
+                    vault_name,
+                    &public_key,
+                    "notes",
+                    version.as_bytes().to_vec(),
+                    None,
+                    true,
+                )
+                .await
+                .unwrap();
+            }
+
+            let snapshots = list_snapshots(&platform, vault_name).await.unwrap();
+            assert_eq!(snapshots.len(), 3);
+            assert_eq!(
+                read_at(&platform, vault_name, &identity, "notes", &snapshots[0].id)
+                    .await
+                    .unwrap(),
+                b"first"
+            );
+            assert_eq!(
+                read_namespace(&platform, vault_name, &identity, "notes")
+                    .await
+                    .unwrap(),
+                b"third"
+            );
+
+            // Saving the vault unchanged adds no snapshot.
+            let vault = crate::domain::vault::operations::read_vault(&platform, vault_name)
+                .await
+                .unwrap();
+            save_vault(&platform, vault_name, vault).await.unwrap();
+            assert_eq!(
+                list_snapshots(&platform, vault_name).await.unwrap().len(),
+                3
+            );
+
+            let removed = prune_snapshots(
+                &platform,
+                vault_name,
+                PrunePolicy {
+                    keep_last: Some(1),
+                    max_age_seconds: None,
+                },
+            )
+            .await
+            .unwrap();
+            assert_eq!(removed, 2);
+
+            let snapshots = list_snapshots(&platform, vault_name).await.unwrap();
+            assert_eq!(snapshots.len(), 1);
+            assert_eq!(
+                read_at(&platform, vault_name, &identity, "notes", &snapshots[0].id)
+                    .await
+                    .unwrap(),
+                b"third"
+            );
+            assert!(matches!(
+                read_at(&platform, vault_name, &identity, "notes", "missing").await,
+                Err(VaultError::SnapshotNotFound)
+            ));
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
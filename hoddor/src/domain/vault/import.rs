@@ -0,0 +1,542 @@
+//! Vault import that never holds a second copy of the vault in memory.
+//!
+//! The exported JSON is first split into borrowed raw values, then
+//! namespaces and chunks are decoded and written one at a time, so an import
+//! needs little more than the export itself. The metadata file goes last:
+//! until it is written the vault does not exist for readers, and a failed
+//! import removes whatever it had written.
+//!
+//! [`import_namespaces`] restores only some namespaces of an export, into a
+//! new vault or into an existing one. Namespaces stay encrypted to the
+//! identities of the export, which are added to the existing vault as a
+//! merge would.
+
+use super::chunks;
+use super::error::VaultError;
+use super::merge::{apply_merge, plan_merge, MergeAction, MergeStrategy};
+use super::operations::{
+    get_namespace_filename, lock_vault, read_vault, request_persistence, write_vault_with_progress,
+    METADATA_FILENAME,
+};
+use super::serialization::{encode_stored, vault_json};
+use super::types::{NamespaceData, Vault};
+use crate::domain::progress::Progress;
+use crate::platform::Platform;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+type RawMap<'a> = BTreeMap<String, &'a RawValue>;
+
+/// What happens to a selected namespace the target vault already holds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExistingNamespaces {
+    #[default]
+    Skip,
+    Overwrite,
+}
+
+impl FromStr for ExistingNamespaces {
+    type Err = VaultError;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "skip" => Ok(ExistingNamespaces::Skip),
+            "overwrite" => Ok(ExistingNamespaces::Overwrite),
+            _ => Err(VaultError::io_error(format!(
+                "Unknown existing namespace mode: {mode}"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportOptions {
+    /// Namespaces to restore; every namespace of the export when `None`.
+    #[serde(default)]
+    pub namespaces: Option<BTreeSet<String>>,
+    #[serde(default)]
+    pub existing: ExistingNamespaces,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ImportReport {
+    pub imported: Vec<String>,
+    /// Selected namespaces left as they were in the target vault.
+    pub skipped: Vec<String>,
+}
+
+// An export split into its parts, borrowing from the exported bytes.
+struct Export<'a> {
+    metadata: Vault,
+    namespaces: RawMap<'a>,
+    chunks: RawMap<'a>,
+}
+
+impl<'a> Export<'a> {
+    fn parse(vault_bytes: &'a [u8]) -> Result<Self, VaultError> {
+        let mut fields: RawMap = serde_json::from_slice(vault_json(vault_bytes)?)
+            .map_err(|_| VaultError::serialization_error("Failed to deserialize vault data"))?;
+
+        let namespaces = take_map(&mut fields, "namespaces")?;
+        let chunks = take_map(&mut fields, "chunks")?;
+
+        Ok(Self {
+            metadata: metadata_vault(fields)?,
+            namespaces,
+            chunks,
+        })
+    }
+
+    fn select(&mut self, selection: &BTreeSet<String>) -> Result<(), VaultError> {
+        if !selection
+            .iter()
+            .all(|namespace| self.namespaces.contains_key(namespace))
+        {
+            return Err(VaultError::NamespaceNotFound);
+        }
+
+        self.namespaces
+            .retain(|namespace, _| selection.contains(namespace));
+
+        let mut referenced = BTreeSet::new();
+        for raw in self.namespaces.values() {
+            let references: ChunkReferences = serde_json::from_str(raw.get()).map_err(|_| {
+                VaultError::serialization_error("Failed to deserialize namespace data")
+            })?;
+            referenced.extend(references.chunks);
+        }
+        self.chunks.retain(|id, _| referenced.contains(id));
+
+        Ok(())
+    }
+}
+
+// The chunk list of a namespace, read without decoding its data.
+#[derive(Deserialize)]
+struct ChunkReferences {
+    #[serde(default)]
+    chunks: Vec<String>,
+}
+
+pub(super) async fn import_vault(
+    platform: &Platform,
+    vault_name: &str,
+    vault_bytes: &[u8],
+    on_progress: &dyn Fn(Progress),
+) -> Result<(), VaultError> {
+    let export = Export::parse(vault_bytes)?;
+
+    let _guard = lock_vault(platform, vault_name).await?;
+
+    match read_vault(platform, vault_name).await {
+        Ok(_) => return Err(VaultError::VaultAlreadyExists),
+        Err(VaultError::IoError(..)) => {
+            tracing::debug!("No existing vault with that name; proceeding with import");
+        }
+        Err(e) => return Err(e),
+    }
+
+    write_new_vault(platform, vault_name, &export, on_progress).await
+}
+
+/// Restores the namespaces of an export selected by `options` into
+/// `vault_name`, creating the vault when it does not exist. Fails with
+/// [`VaultError::NamespaceNotFound`] when a selected namespace is not in the
+/// export.
+#[tracing::instrument(skip_all, fields(vault = vault_name))]
+pub async fn import_namespaces(
+    platform: &Platform,
+    vault_name: &str,
+    vault_bytes: &[u8],
+    options: &ImportOptions,
+    on_progress: &dyn Fn(Progress),
+) -> Result<ImportReport, VaultError> {
+    let mut export = Export::parse(vault_bytes)?;
+    if let Some(selection) = &options.namespaces {
+        export.select(selection)?;
+    }
+
+    let _guard = lock_vault(platform, vault_name).await?;
+
+    match read_vault(platform, vault_name).await {
+        Ok(vault) => {
+            merge_into(
+                platform,
+                vault_name,
+                vault,
+                &export,
+                options.existing,
+                on_progress,
+            )
+            .await
+        }
+        Err(VaultError::IoError(..)) => {
+            write_new_vault(platform, vault_name, &export, on_progress).await?;
+
+            Ok(ImportReport {
+                imported: export.namespaces.keys().cloned().collect(),
+                skipped: Vec::new(),
+            })
+        }
+        Err(e) => Err(e),
+    }
+}
+
+async fn write_new_vault(
+    platform: &Platform,
+    vault_name: &str,
+    export: &Export<'_>,
+    on_progress: &dyn Fn(Progress),
+) -> Result<(), VaultError> {
+    request_persistence(platform).await;
+
+    let storage = platform.storage();
+    let created = !storage.directory_exists(vault_name).await?;
+
+    if let Err(e) = write_contents(platform, vault_name, export, on_progress).await {
+        if created {
+            if let Err(cleanup) = storage.delete_directory(vault_name).await {
+                tracing::warn!(error = %cleanup, "Failed to remove partially imported vault");
+            }
+        }
+        return Err(e);
+    }
+
+    let vault_bytes = serde_json::to_vec(&export.metadata).map_err(|_| {
+        VaultError::serialization_error("Failed to serialize vault for notification")
+    })?;
+
+    let _ = platform
+        .notifier()
+        .notify_vault_update(vault_name, &vault_bytes);
+
+    super::watch::notify_watches_from_storage(platform, vault_name).await;
+
+    Ok(())
+}
+
+// Only the selected namespaces are decoded here, so unlike a fresh import
+// this holds them in memory alongside the existing vault.
+async fn merge_into(
+    platform: &Platform,
+    vault_name: &str,
+    mut vault: Vault,
+    export: &Export<'_>,
+    existing: ExistingNamespaces,
+    on_progress: &dyn Fn(Progress),
+) -> Result<ImportReport, VaultError> {
+    if vault.observer {
+        return Err(VaultError::ObserverVault);
+    }
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    let mut imported_vault = export.metadata.clone();
+    for (namespace, raw) in &export.namespaces {
+        let data = decode_namespace(raw)?;
+        for id in &data.chunks {
+            if let Some(raw) = export.chunks.get(id) {
+                imported_vault.chunks.insert(id.clone(), decode_chunk(raw)?);
+            }
+        }
+        imported_vault.namespaces.insert(namespace.clone(), data);
+    }
+
+    let mut plan = plan_merge(&vault, &imported_vault, MergeStrategy::PreferPrimary);
+    if existing == ExistingNamespaces::Overwrite {
+        for entry in &mut plan.entries {
+            if entry.action == MergeAction::Keep {
+                entry.action = MergeAction::Replace;
+            }
+        }
+    }
+
+    let imported: Vec<String> = plan
+        .entries
+        .iter()
+        .filter(|entry| entry.action != MergeAction::Keep)
+        .map(|entry| entry.namespace.clone())
+        .collect();
+    let skipped = export
+        .namespaces
+        .keys()
+        .filter(|namespace| !imported.contains(namespace))
+        .cloned()
+        .collect();
+
+    if !plan.is_noop() {
+        apply_merge(&mut vault, &imported_vault, &plan);
+        write_vault_with_progress(platform, vault_name, vault, Vec::new(), on_progress).await?;
+    }
+
+    Ok(ImportReport { imported, skipped })
+}
+
+// Removes `key` from the top-level fields and splits its object value.
+fn take_map<'a>(fields: &mut RawMap<'a>, key: &str) -> Result<RawMap<'a>, VaultError> {
+    fields
+        .remove(key)
+        .map(|raw| serde_json::from_str(raw.get()))
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(|_| VaultError::serialization_error(format!("Invalid vault file: bad {key}")))
+}
+
+// The vault without namespaces or chunks, as stored in the metadata file.
+fn metadata_vault(fields: RawMap) -> Result<Vault, VaultError> {
+    let empty = RawValue::from_string("{}".to_string())
+        .map_err(|_| VaultError::serialization_error("Failed to build vault metadata"))?;
+    let mut fields: BTreeMap<&str, &RawValue> = fields
+        .iter()
+        .map(|(key, raw)| (key.as_str(), *raw))
+        .collect();
+    fields.insert("namespaces", &empty);
+
+    let json = serde_json::to_string(&fields)
+        .map_err(|_| VaultError::serialization_error("Failed to serialize vault metadata"))?;
+
+    serde_json::from_str(&json)
+        .map_err(|_| VaultError::serialization_error("Failed to deserialize vault data"))
+}
+
+fn decode_namespace(raw: &RawValue) -> Result<NamespaceData, VaultError> {
+    serde_json::from_str(raw.get())
+        .map_err(|_| VaultError::serialization_error("Failed to deserialize namespace data"))
+}
+
+fn decode_chunk(raw: &RawValue) -> Result<Vec<u8>, VaultError> {
+    serde_json::from_str(raw.get())
+        .map_err(|_| VaultError::serialization_error("Failed to deserialize chunk"))
+}
+
+// Progress is reported against the size of the raw values, an upper bound
+// of what gets written.
+async fn write_contents(
+    platform: &Platform,
+    vault_name: &str,
+    export: &Export<'_>,
+    on_progress: &dyn Fn(Progress),
+) -> Result<(), VaultError> {
+    let storage = platform.storage();
+
+    let metadata = encode_stored(&export.metadata)?;
+
+    let total_bytes = export
+        .namespaces
+        .values()
+        .chain(export.chunks.values())
+        .map(|raw| raw.get().len())
+        .sum::<usize>()
+        + metadata.len();
+    let mut written_bytes = 0;
+
+    storage.create_directory(vault_name).await?;
+
+    if !export.chunks.is_empty() {
+        let chunks_path = chunks::chunks_path(vault_name);
+        storage.create_directory(&chunks_path).await?;
+
+        for (id, raw) in &export.chunks {
+            storage
+                .write_file(
+                    &format!("{chunks_path}/{id}"),
+                    &chunks::encode_chunk(&decode_chunk(raw)?),
+                )
+                .await?;
+
+            written_bytes += raw.get().len();
+            on_progress(Progress::bytes(written_bytes, total_bytes));
+        }
+    }
+
+    for (namespace, raw) in &export.namespaces {
+        storage
+            .write_file(
+                &format!("{}/{}", vault_name, get_namespace_filename(namespace)),
+                &encode_stored(&decode_namespace(raw)?)?,
+            )
+            .await?;
+
+        written_bytes += raw.get().len();
+        on_progress(Progress::bytes(written_bytes, total_bytes));
+    }
+
+    storage
+        .write_file(&format!("{vault_name}/{METADATA_FILENAME}"), &metadata)
+        .await?;
+    on_progress(Progress::bytes(total_bytes, total_bytes));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::progress::ignore_progress;
+    use crate::domain::vault::operations::{create_vault, delete_vault, save_vault};
+    use crate::domain::vault::serialization::serialize_vault;
+    use crate::domain::vault::types::Compression;
+    use futures::executor::block_on;
+
+    fn namespace_data(data: Vec<u8>) -> NamespaceData {
+        NamespaceData {
+            data,
+            expiration: None,
+            chunks: Vec::new(),
+            timelock: None,
+            updated_at: Some(1_700_000_000),
+            recipients: Vec::new(),
+            crdt: None,
+            integrity: None,
+            crypto_version: None,
+            compression: Compression::None,
+        }
+    }
+
+    #[test]
+    fn test_import_writes_every_namespace_and_chunk() {
+        let platform = Platform::new();
+        let vault_name = "streaming_import_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+
+            let mut vault = create_vault().await.unwrap();
+            vault.metadata.peer_id = Some("peer".to_string());
+            vault
+                .namespaces
+                .insert("first".to_string(), namespace_data(vec![1, 2, 3]));
+            vault
+                .namespaces
+                .insert("second".to_string(), namespace_data(vec![4; 64]));
+            vault.chunks.insert("abc".to_string(), vec![9, 8, 7]);
+
+            import_vault(
+                &platform,
+                vault_name,
+                &serialize_vault(&vault).unwrap(),
+                &ignore_progress,
+            )
+            .await
+            .unwrap();
+
+            let imported = read_vault(&platform, vault_name).await.unwrap();
+            assert_eq!(imported.metadata.peer_id.as_deref(), Some("peer"));
+            assert_eq!(imported.namespaces.len(), 2);
+            for (namespace, data) in &vault.namespaces {
+                assert_eq!(imported.namespaces[namespace].data, data.data);
+            }
+            assert_eq!(imported.chunks, vault.chunks);
+
+            assert!(matches!(
+                import_vault(
+                    &platform,
+                    vault_name,
+                    &serialize_vault(&vault).unwrap(),
+                    &ignore_progress
+                )
+                .await,
+                Err(VaultError::VaultAlreadyExists)
+            ));
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_failed_import_leaves_no_vault() {
+        let platform = Platform::new();
+        let vault_name = "streaming_import_failure_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+
+            let mut vault = create_vault().await.unwrap();
+            vault
+                .namespaces
+                .insert("good".to_string(), namespace_data(vec![1]));
+            vault
+                .namespaces
+                .insert("zbad".to_string(), namespace_data(vec![2]));
+            let mut bytes = serialize_vault(&vault).unwrap();
+            // Corrupt the last namespace so it fails after "good" is written.
+            let json = String::from_utf8(bytes.split_off(10)).unwrap();
+            let json = json.replacen("\"data\":[2]", "\"data\":\"oops\"", 1);
+            bytes.truncate(6);
+            bytes.extend_from_slice(&(json.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(json.as_bytes());
+
+            assert!(
+                import_vault(&platform, vault_name, &bytes, &ignore_progress)
+                    .await
+                    .is_err()
+            );
+            assert!(!platform
+                .storage()
+                .directory_exists(vault_name)
+                .await
+                .unwrap());
+        });
+    }
+
+    #[test]
+    fn test_import_selected_namespaces_into_existing_vault() {
+        let platform = Platform::new();
+        let vault_name = "partial_import_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+
+            let mut exported = create_vault().await.unwrap();
+            for (namespace, byte) in [("kept", 1), ("restored", 2), ("ignored", 3)] {
+                exported
+                    .namespaces
+                    .insert(namespace.to_string(), namespace_data(vec![byte]));
+            }
+            let bytes = serialize_vault(&exported).unwrap();
+
+            let mut existing = create_vault().await.unwrap();
+            existing
+                .namespaces
+                .insert("kept".to_string(), namespace_data(vec![9]));
+            save_vault(&platform, vault_name, existing).await.unwrap();
+
+            let mut options = ImportOptions {
+                namespaces: Some(["kept", "restored"].map(String::from).into()),
+                existing: ExistingNamespaces::Skip,
+            };
+            let report =
+                import_namespaces(&platform, vault_name, &bytes, &options, &ignore_progress)
+                    .await
+                    .unwrap();
+            assert_eq!(report.imported, vec!["restored".to_string()]);
+            assert_eq!(report.skipped, vec!["kept".to_string()]);
+
+            let vault = read_vault(&platform, vault_name).await.unwrap();
+            assert_eq!(vault.namespaces["kept"].data, vec![9]);
+            assert_eq!(vault.namespaces["restored"].data, vec![2]);
+            assert!(!vault.namespaces.contains_key("ignored"));
+
+            options.existing = ExistingNamespaces::Overwrite;
+            let report =
+                import_namespaces(&platform, vault_name, &bytes, &options, &ignore_progress)
+                    .await
+                    .unwrap();
+            assert_eq!(report.imported, vec!["kept".to_string()]);
+            let vault = read_vault(&platform, vault_name).await.unwrap();
+            assert_eq!(vault.namespaces["kept"].data, vec![1]);
+
+            options.namespaces = Some(["missing"].map(String::from).into());
+            assert!(matches!(
+                import_namespaces(&platform, vault_name, &bytes, &options, &ignore_progress).await,
+                Err(VaultError::NamespaceNotFound)
+            ));
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
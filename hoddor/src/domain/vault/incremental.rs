@@ -0,0 +1,247 @@
+//! Incremental exports for scheduled backups.
+//!
+//! An incremental export holds the namespaces written since a point in time,
+//! the chunks they reference and the vault metadata, along with a manifest
+//! naming every namespace the vault held when it was made. Applying it to a
+//! copy of the vault brings that copy up to date: changed namespaces are
+//! replaced and namespaces missing from the manifest are removed. The
+//! manifest's `until` is the `since` of the next export.
+//!
+//! Namespaces written before write times were recorded are always included.
+
+use super::chunks;
+use super::error::VaultError;
+use super::operations::{
+    current_timestamp, get_namespace_filename, lock_vault, read_vault, write_vault,
+};
+use super::retention::ensure_removable;
+use super::types::Vault;
+use crate::platform::Platform;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+const INCREMENTAL_MAGIC_NUMBER: &[u8; 6] = b"VAULTI";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IncrementalManifest {
+    /// Unix time in seconds the export starts from.
+    pub since: i64,
+    /// Unix time in seconds the export was made.
+    pub until: i64,
+    /// Every namespace of the vault at `until`, changed or not.
+    pub namespaces: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IncrementalReport {
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IncrementalExport {
+    manifest: IncrementalManifest,
+    vault: Vault,
+}
+
+/// Exports the namespaces of `vault_name` written at or after `since`, Unix
+/// time in seconds. Namespaces written during the second of `since` are
+/// included again, which is harmless when applying.
+#[tracing::instrument(skip_all, fields(vault = vault_name, since = since))]
+pub async fn export_vault_incremental(
+    platform: &Platform,
+    vault_name: &str,
+    since: i64,
+) -> Result<Vec<u8>, VaultError> {
+    let until = current_timestamp(platform);
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    let manifest = IncrementalManifest {
+        since,
+        until,
+        namespaces: vault.namespaces.keys().cloned().collect(),
+    };
+
+    vault
+        .namespaces
+        .retain(|_, data| data.updated_at.is_none_or(|updated_at| updated_at >= since));
+    chunks::collect_garbage(&mut vault);
+
+    tracing::debug!(
+        changed = vault.namespaces.len(),
+        total = manifest.namespaces.len(),
+        "Exporting changed namespaces"
+    );
+
+    let json = serde_json::to_vec(&IncrementalExport { manifest, vault })
+        .map_err(|_| VaultError::serialization_error("Failed to serialize incremental export"))?;
+
+    let mut bytes = Vec::with_capacity(INCREMENTAL_MAGIC_NUMBER.len() + 4 + json.len());
+    bytes.extend_from_slice(INCREMENTAL_MAGIC_NUMBER);
+    bytes.extend_from_slice(&(json.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(&json);
+
+    Ok(bytes)
+}
+
+/// Reads the manifest of an incremental export without applying it.
+pub fn read_incremental_manifest(bytes: &[u8]) -> Result<IncrementalManifest, VaultError> {
+    Ok(parse(bytes)?.manifest)
+}
+
+/// Applies an export made by [`export_vault_incremental`] to the existing
+/// vault `vault_name`. Removals are subject to retention policies, and the
+/// whole export is refused when one of them cannot be removed yet.
+#[tracing::instrument(skip_all, fields(vault = vault_name))]
+pub async fn apply_incremental_export(
+    platform: &Platform,
+    vault_name: &str,
+    bytes: &[u8],
+) -> Result<IncrementalReport, VaultError> {
+    let IncrementalExport {
+        manifest,
+        vault: mut changes,
+    } = parse(bytes)?;
+
+    let _guard = lock_vault(platform, vault_name).await?;
+    let vault = read_vault(platform, vault_name).await?;
+
+    if vault.observer {
+        return Err(VaultError::ObserverVault);
+    }
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    let removed: Vec<String> = vault
+        .namespaces
+        .keys()
+        .filter(|namespace| !manifest.namespaces.contains(*namespace))
+        .cloned()
+        .collect();
+
+    let now = current_timestamp(platform);
+    for namespace in &removed {
+        ensure_removable(&vault, namespace, now)?;
+    }
+
+    let updated: Vec<String> = changes.namespaces.keys().cloned().collect();
+
+    let mut namespaces = vault.namespaces;
+    let mut stored_chunks = vault.chunks;
+    for namespace in &removed {
+        namespaces.remove(namespace);
+    }
+    namespaces.append(&mut changes.namespaces);
+    stored_chunks.append(&mut changes.chunks);
+
+    changes.namespaces = namespaces;
+    changes.chunks = stored_chunks;
+    chunks::collect_garbage(&mut changes);
+
+    let deletes = removed
+        .iter()
+        .map(|namespace| format!("{vault_name}/{}", get_namespace_filename(namespace)))
+        .collect();
+
+    write_vault(platform, vault_name, changes, deletes).await?;
+
+    Ok(IncrementalReport { updated, removed })
+}
+
+fn parse(bytes: &[u8]) -> Result<IncrementalExport, VaultError> {
+    if bytes.len() < 10 || &bytes[..6] != INCREMENTAL_MAGIC_NUMBER {
+        return Err(VaultError::serialization_error(
+            "Invalid incremental export: missing or incorrect magic number",
+        ));
+    }
+
+    let length = u32::from_be_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]) as usize;
+    if bytes.len() != length + 10 {
+        return Err(VaultError::serialization_error(
+            "Invalid incremental export: content length mismatch",
+        ));
+    }
+
+    serde_json::from_slice(&bytes[10..])
+        .map_err(|_| VaultError::serialization_error("Failed to deserialize incremental export"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{create_vault, delete_vault, save_vault};
+    use crate::domain::vault::types::{Compression, NamespaceData};
+    use futures::executor::block_on;
+
+    fn namespace_data(byte: u8, updated_at: Option<i64>) -> NamespaceData {
+        NamespaceData {
+            data: vec![byte],
+            expiration: None,
+            chunks: Vec::new(),
+            timelock: None,
+            updated_at,
+            recipients: Vec::new(),
+            crdt: None,
+            integrity: None,
+            crypto_version: None,
+            compression: Compression::None,
+        }
+    }
+
+    #[test]
+    fn test_incremental_export_carries_changes_and_removals() {
+        let platform = Platform::new();
+        let source = "incremental_source";
+        let target = "incremental_target";
+
+        block_on(async {
+            for vault_name in [source, target] {
+                let _ = delete_vault(&platform, vault_name).await;
+            }
+
+            let mut vault = create_vault().await.unwrap();
+            vault
+                .namespaces
+                .insert("unchanged".to_string(), namespace_data(1, Some(100)));
+            vault
+                .namespaces
+                .insert("removed".to_string(), namespace_data(2, Some(100)));
+            save_vault(&platform, target, vault.clone()).await.unwrap();
+
+            vault.namespaces.remove("removed");
+            vault
+                .namespaces
+                .insert("changed".to_string(), namespace_data(3, Some(200)));
+            vault
+                .namespaces
+                .insert("legacy".to_string(), namespace_data(4, None));
+            save_vault(&platform, source, vault).await.unwrap();
+
+            let bytes = export_vault_incremental(&platform, source, 150)
+                .await
+                .unwrap();
+            let manifest = read_incremental_manifest(&bytes).unwrap();
+            assert_eq!(manifest.since, 150);
+            assert_eq!(manifest.namespaces.len(), 3);
+
+            let report = apply_incremental_export(&platform, target, &bytes)
+                .await
+                .unwrap();
+            assert_eq!(report.updated, vec!["changed", "legacy"]);
+            assert_eq!(report.removed, vec!["removed"]);
+
+            let applied = read_vault(&platform, target).await.unwrap();
+            assert_eq!(
+                applied.namespaces.keys().collect::<Vec<_>>(),
+                vec!["changed", "legacy", "unchanged"]
+            );
+            assert_eq!(applied.namespaces["unchanged"].data, vec![1]);
+            assert_eq!(applied.namespaces["changed"].data, vec![3]);
+
+            for vault_name in [source, target] {
+                delete_vault(&platform, vault_name).await.unwrap();
+            }
+        });
+    }
+}
@@ -0,0 +1,554 @@
+//! Integrity MACs over namespace plaintext.
+//!
+//! age authenticates every ciphertext, but not that the plaintext it
+//! decrypts to is the one that was written: chunks reassembled in the wrong
+//! order or a merge that kept the wrong version decrypt just fine. Writes
+//! therefore store a keyed BLAKE3 MAC of the plaintext next to the
+//! namespace, checked after decryption by reads and [`verify_vault`].
+//!
+//! Every write draws a random MAC key and stores it next to the MAC,
+//! age-encrypted to the same keys as the namespace, so every identity able
+//! to decrypt the namespace can check it and nobody else can compute MACs
+//! to confirm guesses of the plaintext. This catches corruption, not
+//! tampering by someone able to write to the vault. Namespaces written
+//! before MACs existed, or by code paths that do not add one, are not
+//! checked.
+//!
+//! Tampering is caught by the manifest [`seal_vault`] stores in the vault
+//! metadata instead: a hash of every namespace file, keyed with a key
+//! derived from the private key of a vault identity, plus a MAC over the
+//! whole list under another key derived from it. Without the identity,
+//! neither can be forged. Namespace files change with every write, and
+//! writers do not hold the identity, so the manifest only vouches for the
+//! files as they were when sealed: seal again after writing.
+
+use super::chunks;
+use super::error::VaultError;
+use super::operations::{
+    current_timestamp, get_namespace_filename, lock_vault, namespace_from_filename, read_vault,
+    write_vault, METADATA_FILENAME, NAMESPACE_EXTENSION,
+};
+use super::serialization::encode_stored;
+use super::types::NamespaceData;
+use crate::platform::Platform;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use zeroize::Zeroizing;
+
+const FILE_KEY_CONTEXT: &str = "hoddor 2025-01-01 manifest files v1";
+const SIGNATURE_KEY_CONTEXT: &str = "hoddor 2025-01-01 manifest signature v1";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegrityTag {
+    /// Public key of the writer.
+    pub key_id: String,
+    /// Hex-encoded BLAKE3 MAC of the plaintext.
+    pub mac: String,
+    /// MAC key, age-encrypted to the keys of the namespace.
+    #[serde(with = "super::serialization::compact_bytes")]
+    pub sealed_key: Vec<u8>,
+}
+
+/// Keyed hashes of the namespace files of a vault, recorded by
+/// [`seal_vault`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegrityManifest {
+    /// Public key of the identity that sealed the manifest.
+    pub key_id: String,
+    /// Unix time in seconds the manifest was sealed.
+    pub sealed_at: i64,
+    /// Hex-encoded keyed hash of each namespace file, by namespace.
+    pub files: BTreeMap<String, String>,
+    /// Hex-encoded MAC over the fields above.
+    pub signature: String,
+}
+
+/// Namespaces of a vault sorted by the outcome of their integrity check.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IntegrityReport {
+    /// Plaintext matching its MAC.
+    pub verified: Vec<String>,
+    /// Plaintext not matching its MAC.
+    pub corrupted: Vec<String>,
+    /// Namespaces without a MAC, time-locked, or that the identity cannot
+    /// decrypt.
+    pub unchecked: Vec<String>,
+    /// Namespace files changed since the manifest was sealed.
+    pub tampered: Vec<String>,
+    /// Namespaces in the manifest whose file is gone.
+    pub missing: Vec<String>,
+    /// Namespace files the manifest does not list.
+    pub orphaned: Vec<String>,
+    /// When the manifest checked against was sealed. `None` when the vault
+    /// was never sealed, or was sealed by another identity; the three lists
+    /// above are empty then.
+    pub sealed_at: Option<i64>,
+}
+
+async fn open_key(
+    platform: &Platform,
+    tag: &IntegrityTag,
+    identity_private_key: &str,
+) -> Result<Zeroizing<[u8; 32]>, VaultError> {
+    let key = Zeroizing::new(
+        crate::domain::crypto::decrypt_with_identity(
+            platform,
+            &tag.sealed_key,
+            identity_private_key,
+        )
+        .await
+        .map_err(|_| VaultError::InvalidPassword)?,
+    );
+
+    <[u8; 32]>::try_from(key.as_slice())
+        .map(Zeroizing::new)
+        .map_err(|_| VaultError::serialization_error("Malformed integrity key"))
+}
+
+/// MAC of `plaintext` written by the identity of `writer_public_key`, under
+/// a new key sealed to `keys`, those the namespace is encrypted to.
+pub async fn integrity_tag(
+    platform: &Platform,
+    writer_public_key: &str,
+    keys: &[&str],
+    plaintext: &[u8],
+) -> Result<IntegrityTag, VaultError> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(key.as_mut());
+
+    let sealed_key = crate::domain::crypto::encrypt_for_recipients(platform, key.as_ref(), keys)
+        .await
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+    Ok(IntegrityTag {
+        key_id: writer_public_key.to_string(),
+        mac: blake3::keyed_hash(&key, plaintext).to_hex().to_string(),
+        sealed_key,
+    })
+}
+
+/// Seals the MAC key of `tag` to `keys` instead, for a namespace
+/// re-encrypted to them.
+pub async fn reseal_integrity(
+    platform: &Platform,
+    tag: &mut IntegrityTag,
+    identity_private_key: &str,
+    keys: &[&str],
+) -> Result<(), VaultError> {
+    let key = open_key(platform, tag, identity_private_key).await?;
+    tag.sealed_key = crate::domain::crypto::encrypt_for_recipients(platform, key.as_ref(), keys)
+        .await
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Checks the `plaintext` of `namespace`, decrypted with
+/// `identity_private_key`, against its MAC, if it has one.
+pub async fn check_integrity(
+    platform: &Platform,
+    namespace: &str,
+    namespace_data: &NamespaceData,
+    plaintext: &[u8],
+    identity_private_key: &str,
+) -> Result<(), VaultError> {
+    let Some(tag) = &namespace_data.integrity else {
+        return Ok(());
+    };
+    let failed = || VaultError::IntegrityCheckFailed(namespace.to_string());
+
+    let key = open_key(platform, tag, identity_private_key)
+        .await
+        .map_err(|_| failed())?;
+    let actual = blake3::keyed_hash(&key, plaintext);
+
+    // `Hash` compares in constant time.
+    match blake3::Hash::from_hex(&tag.mac) {
+        Ok(expected) if expected == actual => Ok(()),
+        _ => Err(failed()),
+    }
+}
+
+// Binds the namespace too, so swapped files do not pass.
+fn file_hash(file_key: &[u8; 32], namespace: &str, content: &str) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new_keyed(file_key);
+    hasher.update(namespace.as_bytes());
+    hasher.update(&[0]);
+    hasher.update(content.as_bytes());
+    hasher.finalize()
+}
+
+fn manifest_signature(
+    identity_private_key: &str,
+    key_id: &str,
+    sealed_at: i64,
+    files: &BTreeMap<String, String>,
+) -> Result<blake3::Hash, VaultError> {
+    let signed = serde_json::to_vec(&(key_id, sealed_at, files))
+        .map_err(|_| VaultError::serialization_error("Failed to serialize manifest"))?;
+    let key = blake3::derive_key(SIGNATURE_KEY_CONTEXT, identity_private_key.as_bytes());
+    Ok(blake3::keyed_hash(&key, &signed))
+}
+
+/// Records a keyed hash of every namespace file of `vault_name` in a
+/// manifest signed with `identity_private_key`, which must be a vault
+/// identity, replacing the previous one.
+#[tracing::instrument(skip_all, fields(vault = vault_name))]
+pub async fn seal_vault(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+) -> Result<IntegrityManifest, VaultError> {
+    let _guard = lock_vault(platform, vault_name).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    let key_id = crate::domain::crypto::identity_to_public(platform, identity_private_key)
+        .map_err(|_| VaultError::InvalidPassword)?;
+    if vault.identity_salts.get_salt(&key_id).is_none() {
+        return Err(VaultError::InvalidPassword);
+    }
+
+    // Hashed as `write_vault` is about to store them.
+    let file_key = blake3::derive_key(FILE_KEY_CONTEXT, identity_private_key.as_bytes());
+    let mut files = BTreeMap::new();
+    for (namespace, namespace_data) in &vault.namespaces {
+        let content = encode_stored(namespace_data)?;
+        let hash = file_hash(&file_key, namespace, &content);
+        files.insert(namespace.clone(), hash.to_hex().to_string());
+    }
+
+    let sealed_at = current_timestamp(platform);
+    let signature = manifest_signature(identity_private_key, &key_id, sealed_at, &files)?;
+    let manifest = IntegrityManifest {
+        key_id,
+        sealed_at,
+        files,
+        signature: signature.to_hex().to_string(),
+    };
+    vault.manifest = Some(manifest.clone());
+
+    write_vault(platform, vault_name, vault, Vec::new()).await?;
+    Ok(manifest)
+}
+
+/// Decrypts every namespace `identity_private_key` can read and checks it
+/// against its MAC, then checks the namespace files against the manifest if
+/// the identity sealed it. Fails with `IntegrityCheckFailed` when the
+/// manifest itself was altered.
+pub async fn verify_vault(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+) -> Result<IntegrityReport, VaultError> {
+    let vault = read_vault(platform, vault_name).await?;
+    let mut report = IntegrityReport::default();
+
+    for (namespace, namespace_data) in &vault.namespaces {
+        if namespace_data.integrity.is_none() || namespace_data.timelock.is_some() {
+            report.unchecked.push(namespace.clone());
+            continue;
+        }
+
+        let Ok(plaintext) =
+            chunks::decrypt_namespace(platform, &vault, namespace_data, identity_private_key).await
+        else {
+            report.unchecked.push(namespace.clone());
+            continue;
+        };
+
+        match check_integrity(
+            platform,
+            namespace,
+            namespace_data,
+            &plaintext,
+            identity_private_key,
+        )
+        .await
+        {
+            Ok(()) => report.verified.push(namespace.clone()),
+            Err(_) => report.corrupted.push(namespace.clone()),
+        }
+    }
+
+    let public_key = crate::domain::crypto::identity_to_public(platform, identity_private_key)
+        .map_err(|_| VaultError::InvalidPassword)?;
+    match &vault.manifest {
+        Some(manifest) if manifest.key_id == public_key => {
+            check_manifest(
+                platform,
+                vault_name,
+                identity_private_key,
+                manifest,
+                &mut report,
+            )
+            .await?;
+        }
+        _ => {}
+    }
+
+    Ok(report)
+}
+
+async fn check_manifest(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    manifest: &IntegrityManifest,
+    report: &mut IntegrityReport,
+) -> Result<(), VaultError> {
+    let signature = manifest_signature(
+        identity_private_key,
+        &manifest.key_id,
+        manifest.sealed_at,
+        &manifest.files,
+    )?;
+    // `Hash` compares in constant time.
+    if blake3::Hash::from_hex(&manifest.signature).ok() != Some(signature) {
+        return Err(VaultError::IntegrityCheckFailed(
+            METADATA_FILENAME.to_string(),
+        ));
+    }
+
+    // Files as stored, rather than as decoded: a file can be altered
+    // without changing what it decodes to.
+    let storage = platform.storage();
+    let namespaces: Vec<String> = storage
+        .list_entries(vault_name)
+        .await?
+        .into_iter()
+        .filter(|entry| entry.ends_with(NAMESPACE_EXTENSION))
+        .filter_map(|entry| namespace_from_filename(&entry))
+        .collect();
+    let paths: Vec<String> = namespaces
+        .iter()
+        .map(|namespace| format!("{vault_name}/{}", get_namespace_filename(namespace)))
+        .collect();
+    let contents = storage.read_files(&paths).await;
+
+    let file_key = blake3::derive_key(FILE_KEY_CONTEXT, identity_private_key.as_bytes());
+    for (namespace, content) in namespaces.iter().zip(contents) {
+        let Some(expected) = manifest.files.get(namespace) else {
+            report.orphaned.push(namespace.clone());
+            continue;
+        };
+        let matches = match (blake3::Hash::from_hex(expected), content) {
+            (Ok(expected), Ok(content)) => expected == file_hash(&file_key, namespace, &content),
+            _ => false,
+        };
+        if !matches {
+            report.tampered.push(namespace.clone());
+        }
+    }
+    report.missing = manifest
+        .files
+        .keys()
+        .filter(|namespace| !namespaces.contains(namespace))
+        .cloned()
+        .collect();
+    report.sealed_at = Some(manifest.sealed_at);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{
+        create_vault, delete_vault, read_namespace, save_vault, upsert_namespace,
+    };
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_corrupted_plaintext_fails_the_check() {
+        let platform = Platform::new();
+        let vault_name = "integrity_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+            for namespace in ["kept", "swapped"] {
+                upsert_namespace(
+                    &platform,
+                    vault_name,
+                    &public_key,
+                    namespace,
+                    namespace.as_bytes().to_vec(),
+                    None,
+                    false,
+                )
+                .await
+                .unwrap();
+            }
+
+            // Ciphertext that age decrypts without complaint, but of other
+            // plaintext, as a faulty merge could leave behind.
+            let mut vault = read_vault(&platform, vault_name).await.unwrap();
+            let other = vault.namespaces["kept"].data.clone();
+            if let Some(namespace_data) = vault.namespaces.get_mut("swapped") {
+                namespace_data.data = other;
+            }
+            save_vault(&platform, vault_name, vault).await.unwrap();
+
+            assert_eq!(
+                read_namespace(&platform, vault_name, &identity, "kept")
+                    .await
+                    .unwrap(),
+                b"kept"
+            );
+            assert!(matches!(
+                read_namespace(&platform, vault_name, &identity, "swapped").await,
+                Err(VaultError::IntegrityCheckFailed(namespace)) if namespace == "swapped"
+            ));
+
+            let report = verify_vault(&platform, vault_name, &identity)
+                .await
+                .unwrap();
+            assert_eq!(report.verified, ["kept"]);
+            assert_eq!(report.corrupted, ["swapped"]);
+            assert!(report.unchecked.is_empty());
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_mac_is_checked_only_by_readers() {
+        let platform = Platform::new();
+
+        block_on(async {
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+            let outsider = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let namespace_data = NamespaceData {
+                integrity: Some(
+                    integrity_tag(&platform, &public_key, &[&public_key], b"guess")
+                        .await
+                        .unwrap(),
+                ),
+                ..test_namespace_data()
+            };
+
+            check_integrity(&platform, "guess", &namespace_data, b"guess", &identity)
+                .await
+                .unwrap();
+            assert!(matches!(
+                check_integrity(&platform, "guess", &namespace_data, b"guess", &outsider).await,
+                Err(VaultError::IntegrityCheckFailed(_))
+            ));
+
+            // A MAC whose key was not sealed cannot be checked.
+            let mut unsealed = namespace_data.clone();
+            if let Some(tag) = unsealed.integrity.as_mut() {
+                tag.sealed_key.clear();
+            }
+            assert!(matches!(
+                check_integrity(&platform, "guess", &unsealed, b"guess", &identity).await,
+                Err(VaultError::IntegrityCheckFailed(_))
+            ));
+        });
+    }
+
+    fn test_namespace_data() -> NamespaceData {
+        NamespaceData {
+            data: Vec::new(),
+            expiration: None,
+            chunks: Vec::new(),
+            timelock: None,
+            updated_at: None,
+            recipients: Vec::new(),
+            crdt: None,
+            integrity: None,
+            crypto_version: None,
+            compression: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_manifest_reports_tampered_missing_and_orphaned_files() {
+        let platform = Platform::new();
+        let vault_name = "integrity_manifest_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            let mut vault = create_vault().await.unwrap();
+
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+            vault.identity_salts.set_salt(public_key.clone(), [1; 32]);
+            save_vault(&platform, vault_name, vault).await.unwrap();
+
+            for namespace in ["kept", "changed", "deleted"] {
+                upsert_namespace(
+                    &platform,
+                    vault_name,
+                    &public_key,
+                    namespace,
+                    namespace.as_bytes().to_vec(),
+                    None,
+                    false,
+                )
+                .await
+                .unwrap();
+            }
+            let manifest = seal_vault(&platform, vault_name, &identity).await.unwrap();
+            assert_eq!(manifest.files.len(), 3);
+
+            for namespace in ["changed", "added"] {
+                upsert_namespace(
+                    &platform,
+                    vault_name,
+                    &public_key,
+                    namespace,
+                    b"later".to_vec(),
+                    None,
+                    true,
+                )
+                .await
+                .unwrap();
+            }
+            platform
+                .storage()
+                .delete_file(&format!(
+                    "{vault_name}/{}",
+                    get_namespace_filename("deleted")
+                ))
+                .await
+                .unwrap();
+
+            let report = verify_vault(&platform, vault_name, &identity)
+                .await
+                .unwrap();
+            assert_eq!(report.tampered, ["changed"]);
+            assert_eq!(report.missing, ["deleted"]);
+            assert_eq!(report.orphaned, ["added"]);
+            assert_eq!(report.sealed_at, Some(manifest.sealed_at));
+
+            // A manifest rewritten without the identity.
+            let mut vault = read_vault(&platform, vault_name).await.unwrap();
+            if let Some(manifest) = vault.manifest.as_mut() {
+                manifest.files.remove("deleted");
+            }
+            save_vault(&platform, vault_name, vault).await.unwrap();
+            assert!(matches!(
+                verify_vault(&platform, vault_name, &identity).await,
+                Err(VaultError::IntegrityCheckFailed(file)) if file == METADATA_FILENAME
+            ));
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
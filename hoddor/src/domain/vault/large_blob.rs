@@ -0,0 +1,272 @@
+//! Vault keys kept on the authenticator itself, with the WebAuthn largeBlob
+//! extension.
+//!
+//! PRF alone only lets an authenticator re-derive an identity from the salt
+//! stored in the vault, so a new device has nothing to unlock with until
+//! the salts have been synced. Authenticators supporting largeBlob store a
+//! small blob next to the credential instead: here, the identities of the
+//! vaults registered with it, their salts and the credential id, encrypted
+//! to an identity derived from the PRF output for
+//! [`LARGE_BLOB_PRF_NONCE`]. Anyone holding the blob but not the
+//! authenticator learns nothing from it.
+//!
+//! The blob is a magic number, a format version and an age ciphertext of
+//! the JSON list of [`LargeBlobEntry`]. Authenticators offer about a
+//! kilobyte to all their credentials together, which is enough for one or
+//! two vaults per credential.
+
+use super::error::VaultError;
+use super::operations::{create_vault, lock_vault, read_vault, write_vault};
+use crate::domain::authentication::{record_key_check, IdentityKeys};
+use crate::domain::crypto;
+use crate::platform::Platform;
+use serde::{Deserialize, Serialize};
+
+/// Nonce the PRF is evaluated on to derive the identity the blob is
+/// encrypted to, distinct from every vault salt.
+pub const LARGE_BLOB_PRF_NONCE: &[u8] = b"hoddor/large-blob";
+
+const LARGE_BLOB_MAGIC: &[u8; 4] = b"HDLB";
+const LARGE_BLOB_VERSION: u8 = 1;
+
+/// What a new device needs to unlock `vault_name` with the credential.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LargeBlobEntry {
+    pub vault_name: String,
+    pub username: String,
+    pub public_key: String,
+    pub identity_private_key: String,
+    /// Hex-encoded PRF salt of the identity.
+    pub salt: String,
+    /// Hex-encoded WebAuthn credential id.
+    pub credential_id: String,
+}
+
+/// The entry for the credential of `username` on `vault_name`, whose
+/// identity `identity_private_key` must be.
+pub async fn large_blob_entry(
+    platform: &Platform,
+    vault_name: &str,
+    username: &str,
+    identity_private_key: &str,
+) -> Result<LargeBlobEntry, VaultError> {
+    let vault = read_vault(platform, vault_name).await?;
+
+    let public_key = vault
+        .username_pk
+        .get(username)
+        .ok_or_else(|| VaultError::io_error(format!("No credential registered for {username}")))?;
+    let salt = vault
+        .identity_salts
+        .get_salt(public_key)
+        .ok_or(VaultError::InvalidPassword)?;
+    let credential_id = vault
+        .identity_salts
+        .get_credential_id(public_key)
+        .ok_or_else(|| VaultError::io_error(format!("{username} has no WebAuthn credential")))?;
+
+    if crypto::identity_to_public(platform, identity_private_key)
+        .ok()
+        .as_ref()
+        != Some(public_key)
+    {
+        return Err(VaultError::InvalidPassword);
+    }
+
+    Ok(LargeBlobEntry {
+        vault_name: vault_name.to_string(),
+        username: username.to_string(),
+        public_key: public_key.clone(),
+        identity_private_key: identity_private_key.to_string(),
+        salt: hex::encode(salt),
+        credential_id: hex::encode(credential_id),
+    })
+}
+
+/// Entries stored in `blob`, which an authenticator that never had one
+/// written returns empty.
+pub async fn open_large_blob(
+    platform: &Platform,
+    blob: &[u8],
+    wrapping_identity: &str,
+) -> Result<Vec<LargeBlobEntry>, VaultError> {
+    if blob.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let header = LARGE_BLOB_MAGIC.len() + 1;
+    if blob.len() <= header || &blob[..LARGE_BLOB_MAGIC.len()] != LARGE_BLOB_MAGIC {
+        return Err(VaultError::serialization_error(
+            "Large blob was not written by hoddor",
+        ));
+    }
+    if blob[LARGE_BLOB_MAGIC.len()] != LARGE_BLOB_VERSION {
+        return Err(VaultError::serialization_error(format!(
+            "Unsupported large blob version {}",
+            blob[LARGE_BLOB_MAGIC.len()]
+        )));
+    }
+
+    let json = crypto::decrypt_with_identity(platform, &blob[header..], wrapping_identity)
+        .await
+        .map_err(|_| VaultError::InvalidPassword)?;
+
+    serde_json::from_slice(&json).map_err(|e| VaultError::serialization_error(e.to_string()))
+}
+
+/// Encrypts `entries` into a blob for the authenticator.
+pub async fn seal_large_blob(
+    platform: &Platform,
+    entries: &[LargeBlobEntry],
+    wrapping_identity: &str,
+) -> Result<Vec<u8>, VaultError> {
+    let json =
+        serde_json::to_vec(entries).map_err(|e| VaultError::serialization_error(e.to_string()))?;
+    let recipient = crypto::identity_to_public(platform, wrapping_identity)
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+    let encrypted = crypto::encrypt_for_recipients(platform, &json, &[&recipient])
+        .await
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+    let mut blob = LARGE_BLOB_MAGIC.to_vec();
+    blob.push(LARGE_BLOB_VERSION);
+    blob.extend_from_slice(&encrypted);
+    Ok(blob)
+}
+
+/// Adds `entry` to `entries`, replacing the one for the same vault.
+pub fn upsert_large_blob_entry(entries: &mut Vec<LargeBlobEntry>, entry: LargeBlobEntry) {
+    entries.retain(|existing| existing.vault_name != entry.vault_name);
+    entries.push(entry);
+}
+
+/// Registers the credential of `entry` on its vault, creating the vault
+/// when this device has none yet, and returns its identity. Namespaces
+/// reach a created vault through sync.
+#[tracing::instrument(skip_all, fields(vault = %entry.vault_name))]
+pub async fn restore_from_large_blob(
+    platform: &Platform,
+    entry: &LargeBlobEntry,
+) -> Result<IdentityKeys, VaultError> {
+    let public_key = crypto::identity_to_public(platform, &entry.identity_private_key)
+        .map_err(|_| VaultError::InvalidPassword)?;
+    if public_key != entry.public_key {
+        return Err(VaultError::InvalidPassword);
+    }
+
+    let salt: [u8; 32] = hex::decode(&entry.salt)
+        .ok()
+        .and_then(|salt| salt.try_into().ok())
+        .ok_or_else(|| VaultError::serialization_error("Invalid salt in large blob"))?;
+    let credential_id = hex::decode(&entry.credential_id)
+        .map_err(|_| VaultError::serialization_error("Invalid credential id in large blob"))?;
+
+    let _guard = lock_vault(platform, &entry.vault_name).await?;
+    let mut vault = if platform
+        .storage()
+        .directory_exists(&entry.vault_name)
+        .await?
+    {
+        read_vault(platform, &entry.vault_name).await?
+    } else {
+        create_vault().await?
+    };
+
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    if vault
+        .identity_salts
+        .get_credential_id(&public_key)
+        .is_none()
+    {
+        vault.identity_salts.set_salt(public_key.clone(), salt);
+        record_key_check(
+            &mut vault.identity_salts,
+            &public_key,
+            &entry.identity_private_key,
+        );
+        vault
+            .identity_salts
+            .set_credential_id(public_key.clone(), credential_id);
+        vault
+            .username_pk
+            .insert(entry.username.clone(), public_key.clone());
+
+        write_vault(platform, &entry.vault_name, vault, Vec::new()).await?;
+    }
+
+    Ok(IdentityKeys::new(
+        public_key,
+        entry.identity_private_key.clone(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{delete_vault, save_vault};
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_large_blob_restores_the_credential_on_a_new_device() {
+        let platform = Platform::new();
+        let vault_name = "large_blob_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            let mut vault = create_vault().await.unwrap();
+
+            let identity = crypto::generate_identity(&platform).unwrap();
+            let public_key = crypto::identity_to_public(&platform, &identity).unwrap();
+            vault.identity_salts.set_salt(public_key.clone(), [3; 32]);
+            vault
+                .identity_salts
+                .set_credential_id(public_key.clone(), vec![9, 9]);
+            vault
+                .username_pk
+                .insert("alice".to_string(), public_key.clone());
+            save_vault(&platform, vault_name, vault).await.unwrap();
+
+            let entry = large_blob_entry(&platform, vault_name, "alice", &identity)
+                .await
+                .unwrap();
+            let wrapping_identity = crypto::generate_identity(&platform).unwrap();
+            let mut entries = Vec::new();
+            upsert_large_blob_entry(&mut entries, entry.clone());
+            upsert_large_blob_entry(&mut entries, entry.clone());
+            let blob = seal_large_blob(&platform, &entries, &wrapping_identity)
+                .await
+                .unwrap();
+
+            let other_identity = crypto::generate_identity(&platform).unwrap();
+            assert!(matches!(
+                open_large_blob(&platform, &blob, &other_identity).await,
+                Err(VaultError::InvalidPassword)
+            ));
+            let entries = open_large_blob(&platform, &blob, &wrapping_identity)
+                .await
+                .unwrap();
+            assert_eq!(entries, std::slice::from_ref(&entry));
+
+            // A device that never saw the vault.
+            delete_vault(&platform, vault_name).await.unwrap();
+            let keys = restore_from_large_blob(&platform, &entries[0])
+                .await
+                .unwrap();
+            assert_eq!(keys.public_key, public_key);
+
+            let vault = read_vault(&platform, vault_name).await.unwrap();
+            assert_eq!(vault.username_pk["alice"], public_key);
+            assert_eq!(vault.identity_salts.get_salt(&public_key), Some(&[3; 32]));
+            assert_eq!(
+                vault.identity_salts.get_credential_id(&public_key),
+                Some(&vec![9, 9])
+            );
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,162 @@
+//! Size limit on namespace payloads.
+//!
+//! A namespace is encrypted and serialized in one piece, so an oversized
+//! write used to fail deep inside serialization or on the storage quota
+//! with an opaque error. Writes above the vault's limit are now refused
+//! before any work with [`VaultError::QuotaExceeded`].
+
+use super::error::VaultError;
+use super::operations::{lock_vault, read_vault, read_vault_metadata, write_vault};
+use super::types::Vault;
+use crate::platform::Platform;
+
+/// Limit of vaults that have not set one. Well under the quota browsers
+/// grant an origin's OPFS, and small enough for the serialized namespace to
+/// fit in wasm memory alongside the plaintext.
+pub const DEFAULT_MAX_NAMESPACE_BYTES: u64 = 32 * 1024 * 1024;
+
+pub fn max_namespace_bytes(vault: &Vault) -> u64 {
+    vault
+        .max_namespace_bytes
+        .unwrap_or(DEFAULT_MAX_NAMESPACE_BYTES)
+}
+
+/// Encrypted bytes held by the vault's namespaces and chunks.
+pub fn stored_bytes(vault: &Vault) -> u64 {
+    let namespaces: usize = vault.namespaces.values().map(|n| n.data.len()).sum();
+    let chunks: usize = vault.chunks.values().map(Vec::len).sum();
+
+    (namespaces + chunks) as u64
+}
+
+/// Fails when a `size`-byte payload is over the limit of `vault`.
+pub fn ensure_within_limit(vault: &Vault, size: usize) -> Result<(), VaultError> {
+    let size = size as u64;
+    let limit = max_namespace_bytes(vault);
+
+    if size > limit {
+        return Err(VaultError::QuotaExceeded {
+            size,
+            limit,
+            used: stored_bytes(vault),
+        });
+    }
+
+    Ok(())
+}
+
+/// Sets the largest payload a namespace of `vault_name` accepts; `None`
+/// goes back to [`DEFAULT_MAX_NAMESPACE_BYTES`]. Namespaces already larger
+/// stay readable but can only be rewritten smaller.
+pub async fn set_max_namespace_bytes(
+    platform: &Platform,
+    vault_name: &str,
+    limit: Option<u64>,
+) -> Result<(), VaultError> {
+    let _guard = lock_vault(platform, vault_name).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    vault.max_namespace_bytes = limit;
+
+    write_vault(platform, vault_name, vault, Vec::new()).await
+}
+
+pub async fn namespace_size_limit(
+    platform: &Platform,
+    vault_name: &str,
+) -> Result<u64, VaultError> {
+    let vault = read_vault_metadata(platform, vault_name).await?;
+
+    Ok(max_namespace_bytes(&vault))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{
+        create_vault, delete_vault, read_namespace, save_vault, upsert_namespace,
+    };
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_oversized_write_is_refused_with_usage() {
+        let platform = Platform::new();
+        let vault_name = "limits_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+
+            set_max_namespace_bytes(&platform, vault_name, Some(16))
+                .await
+                .unwrap();
+            assert_eq!(
+                namespace_size_limit(&platform, vault_name).await.unwrap(),
+                16
+            );
+
+            upsert_namespace(
+                &platform,
+                vault_name,
+                &public_key,
+                "small",
+                vec![1; 16],
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+
+            let result = upsert_namespace(
+                &platform,
+                vault_name,
+                &public_key,
+                "large",
+                vec![1; 17],
+                None,
+                false,
+            )
+            .await;
+            match result {
+                Err(VaultError::QuotaExceeded { size, limit, used }) => {
+                    assert_eq!((size, limit), (17, 16));
+                    assert!(used > 16, "the encrypted small namespace is counted");
+                }
+                other => panic!("expected QuotaExceeded, got {other:?}"),
+            }
+
+            set_max_namespace_bytes(&platform, vault_name, None)
+                .await
+                .unwrap();
+            upsert_namespace(
+                &platform,
+                vault_name,
+                &public_key,
+                "large",
+                vec![1; 17],
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                read_namespace(&platform, vault_name, &identity, "large")
+                    .await
+                    .unwrap(),
+                vec![1; 17]
+            );
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,324 @@
+//! Append-only logs partitioned by day.
+//!
+//! Entries of a log go to one namespace per UTC day, named
+//! `<log>@<YYYY-MM-DD>`, stored in the deduplicated chunk store. Full chunks
+//! never change, so an append only decrypts the last, partial chunk and
+//! writes it back with the new entry: the cost of an append does not grow
+//! with the day's segment. Replaced tail chunks stay on disk until the next
+//! garbage collection.
+//!
+//! Each entry is framed as its timestamp in milliseconds and its length,
+//! both little-endian, followed by its bytes.
+
+use super::chunks;
+use super::error::VaultError;
+use super::migration::CURRENT_CRYPTO_VERSION;
+use super::operations::{current_timestamp, lock_namespace, read_vault, write_namespace};
+use super::types::{Compression, NamespaceData};
+use super::validation::validate_namespace;
+use crate::platform::Platform;
+
+pub const SEGMENT_SEPARATOR: char = '@';
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+const HEADER_LEN: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct LogEntry {
+    /// Milliseconds since the Unix epoch, taken when the entry was appended.
+    pub timestamp: i64,
+    pub data: Vec<u8>,
+}
+
+/// Namespace holding the entries `log_name` received on the UTC day of
+/// `timestamp`.
+pub fn segment_namespace(log_name: &str, timestamp: i64) -> String {
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(MILLIS_PER_DAY));
+    format!("{log_name}{SEGMENT_SEPARATOR}{year:04}-{month:02}-{day:02}")
+}
+
+/// Appends `entry` to today's segment of `log_name`.
+#[tracing::instrument(skip_all, fields(vault = vault_name, log = log_name))]
+pub async fn append_to_log(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    log_name: &str,
+    entry: &[u8],
+) -> Result<LogEntry, VaultError> {
+    validate_log_name(log_name)?;
+
+    let timestamp = platform.clock().now() as i64;
+    let namespace = segment_namespace(log_name, timestamp);
+
+    let _guards = lock_namespace(platform, vault_name, &namespace).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.observer {
+        return Err(VaultError::ObserverVault);
+    }
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    let record = encode_entry(timestamp, entry)?;
+    super::limits::ensure_within_limit(&vault, record.len())?;
+
+    let mut ids = match vault.namespaces.get(&namespace) {
+        Some(segment) if segment.chunks.is_empty() => {
+            return Err(VaultError::io_error(format!(
+                "Namespace {namespace} is not a log segment"
+            )));
+        }
+        Some(segment) => segment.chunks.clone(),
+        None => Vec::new(),
+    };
+
+    // Only a partial last chunk is rewritten; full ones are left alone.
+    let mut tail = Vec::new();
+    if let Some(last) = ids.last() {
+        let encrypted = vault
+            .chunks
+            .get(last)
+            .ok_or_else(|| VaultError::io_error(format!("Missing chunk {last}")))?;
+        let plaintext =
+            crate::domain::crypto::decrypt_with_identity(platform, encrypted, identity_private_key)
+                .await
+                .map_err(|_| VaultError::InvalidPassword)?;
+
+        if plaintext.len() < chunks::CHUNK_SIZE {
+            ids.pop();
+            tail = plaintext;
+        }
+    }
+    tail.extend_from_slice(&record);
+    ids.extend(chunks::store_chunks(platform, &mut vault, identity_private_key, &tail).await?);
+
+    let existing = vault.namespaces.get(&namespace);
+    let expiration = existing.and_then(|segment| segment.expiration.clone());
+    // Full chunks keep the algorithms they were written with.
+    let crypto_version = existing.map_or(Some(CURRENT_CRYPTO_VERSION), |segment| {
+        segment.crypto_version
+    });
+    vault.namespaces.insert(
+        namespace.clone(),
+        NamespaceData {
+            data: Vec::new(),
+            expiration,
+            chunks: ids,
+            timelock: None,
+            updated_at: Some(current_timestamp(platform)),
+            recipients: Vec::new(),
+            crdt: None,
+            integrity: None,
+            crypto_version,
+            compression: Compression::None,
+        },
+    );
+
+    write_namespace(platform, vault_name, &vault, &namespace).await?;
+
+    Ok(LogEntry {
+        timestamp,
+        data: entry.to_vec(),
+    })
+}
+
+/// Entries of `log_name` appended from `from` (inclusive) to `to`
+/// (exclusive), both in milliseconds since the Unix epoch, oldest first.
+/// Only the segments of the days in range are decrypted.
+#[tracing::instrument(skip_all, fields(vault = vault_name, log = log_name))]
+pub async fn read_log_range(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    log_name: &str,
+    from: i64,
+    to: i64,
+) -> Result<Vec<LogEntry>, VaultError> {
+    validate_log_name(log_name)?;
+
+    let mut entries = Vec::new();
+    if from >= to {
+        return Ok(entries);
+    }
+
+    let vault = read_vault(platform, vault_name).await?;
+
+    // Segment names sort by date, so the range maps onto a name range.
+    let first = segment_namespace(log_name, from);
+    let last = segment_namespace(log_name, to - 1);
+
+    for (_, segment) in vault.namespaces.range(first..=last) {
+        let data =
+            chunks::decrypt_namespace(platform, &vault, segment, identity_private_key).await?;
+
+        entries.extend(
+            decode_entries(&data)?
+                .into_iter()
+                .filter(|entry| (from..to).contains(&entry.timestamp)),
+        );
+    }
+
+    entries.sort_by_key(|entry| entry.timestamp);
+
+    Ok(entries)
+}
+
+fn validate_log_name(log_name: &str) -> Result<(), VaultError> {
+    validate_namespace(log_name)?;
+
+    if log_name.contains(SEGMENT_SEPARATOR) {
+        return Err(VaultError::io_error(format!(
+            "Log name cannot contain '{SEGMENT_SEPARATOR}'"
+        )));
+    }
+
+    Ok(())
+}
+
+fn encode_entry(timestamp: i64, entry: &[u8]) -> Result<Vec<u8>, VaultError> {
+    let len =
+        u32::try_from(entry.len()).map_err(|_| VaultError::io_error("Log entry is too large"))?;
+
+    let mut record = Vec::with_capacity(HEADER_LEN + entry.len());
+    record.extend_from_slice(&timestamp.to_le_bytes());
+    record.extend_from_slice(&len.to_le_bytes());
+    record.extend_from_slice(entry);
+
+    Ok(record)
+}
+
+fn decode_entries(mut data: &[u8]) -> Result<Vec<LogEntry>, VaultError> {
+    let corrupted = || VaultError::serialization_error("Truncated log segment");
+    let mut entries = Vec::new();
+
+    while !data.is_empty() {
+        let (header, rest) = data.split_at_checked(HEADER_LEN).ok_or_else(corrupted)?;
+        let (timestamp, len) = header.split_at(8);
+        let timestamp = i64::from_le_bytes(timestamp.try_into().map_err(|_| corrupted())?);
+        let len = u32::from_le_bytes(len.try_into().map_err(|_| corrupted())?) as usize;
+
+        let (entry, rest) = rest.split_at_checked(len).ok_or_else(corrupted)?;
+        entries.push(LogEntry {
+            timestamp,
+            data: entry.to_vec(),
+        });
+        data = rest;
+    }
+
+    Ok(entries)
+}
+
+/// Gregorian date of a day count since 1970-01-01, after Howard Hinnant's
+/// `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{create_vault, delete_vault, save_vault};
+    use crate::ports::ClockPort;
+    use futures::executor::block_on;
+
+    struct FixedClock(f64);
+
+    #[async_trait::async_trait(?Send)]
+    impl ClockPort for FixedClock {
+        fn now(&self) -> f64 {
+            self.0
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+
+        async fn sleep(&self, _milliseconds: u32) {}
+    }
+
+    static END_OF_DAY_ONE: FixedClock = FixedClock((MILLIS_PER_DAY - 1_000) as f64);
+    static DAY_TWO: FixedClock = FixedClock((MILLIS_PER_DAY + 1_000) as f64);
+
+    #[test]
+    fn test_segment_names_follow_utc_days() {
+        assert_eq!(segment_namespace("audit", 0), "audit@1970-01-01");
+        assert_eq!(
+            segment_namespace("audit", 1_709_251_199_999),
+            "audit@2024-02-29"
+        );
+        assert_eq!(segment_namespace("audit", -1), "audit@1969-12-31");
+    }
+
+    #[test]
+    fn test_appended_entries_are_read_back_by_range() {
+        let day_one = Platform::new().with_clock(&END_OF_DAY_ONE);
+        let day_two = Platform::new().with_clock(&DAY_TWO);
+        let platform = Platform::new();
+        let vault_name = "log_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+
+            // Entries a third of a chunk long, so appends cross chunk
+            // boundaries.
+            let mut appended = Vec::new();
+            for (index, day) in [&day_one, &day_one, &day_two, &day_two]
+                .into_iter()
+                .enumerate()
+            {
+                let entry = vec![index as u8; chunks::CHUNK_SIZE / 3];
+                appended.push(
+                    append_to_log(day, vault_name, &identity, "audit", &entry)
+                        .await
+                        .unwrap(),
+                );
+            }
+
+            let vault = read_vault(&platform, vault_name).await.unwrap();
+            assert!(vault.namespaces.contains_key("audit@1970-01-01"));
+            assert!(vault.namespaces.contains_key("audit@1970-01-02"));
+
+            let all = read_log_range(&platform, vault_name, &identity, "audit", 0, i64::MAX)
+                .await
+                .unwrap();
+            assert_eq!(all, appended);
+
+            let second_day = read_log_range(
+                &platform,
+                vault_name,
+                &identity,
+                "audit",
+                MILLIS_PER_DAY,
+                2 * MILLIS_PER_DAY,
+            )
+            .await
+            .unwrap();
+            assert_eq!(second_day, appended[2..]);
+
+            assert!(
+                append_to_log(&platform, vault_name, &identity, "a@b", b"entry")
+                    .await
+                    .is_err()
+            );
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,210 @@
+//! Read-only vault open for large native vaults.
+//!
+//! [`read_vault`](super::operations::read_vault) loads every namespace and
+//! chunk file of a vault before anything can be read, which for vaults of
+//! several gigabytes means holding all of their ciphertext in memory.
+//! [`MappedVault`] only reads the metadata and the list of namespaces at
+//! open; each read then memory-maps the file of the namespace asked for,
+//! and the chunks it references, and decrypts just those.
+//!
+//! Writers truncate namespace files in place, so a file is only mapped
+//! while the namespace lock is held, and never kept mapped between reads.
+
+use super::chunks::{self, chunks_path};
+use super::error::VaultError;
+use super::integrity::check_integrity;
+use super::operations::{
+    current_timestamp, get_namespace_filename, lock_namespace, namespace_from_filename,
+    read_vault_metadata, LEGACY_NAMESPACE_EXTENSION,
+};
+use super::serialization::decode_stored;
+use super::types::{NamespaceData, Vault};
+use crate::adapters::native::FsStorage;
+use crate::platform::Platform;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use memmap2::Mmap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// A vault opened for reading without loading its namespaces.
+pub struct MappedVault {
+    platform: Platform,
+    name: String,
+    metadata: Vault,
+    namespaces: BTreeSet<String>,
+}
+
+impl MappedVault {
+    /// Opens `vault_name`, recovering an interrupted write first like
+    /// [`read_vault`](super::operations::read_vault) does.
+    pub async fn open(platform: Platform, vault_name: &str) -> Result<Self, VaultError> {
+        super::wal::recover_vault(&platform, vault_name).await?;
+        let metadata = read_vault_metadata(&platform, vault_name).await?;
+
+        let namespaces = platform
+            .storage()
+            .list_entries(vault_name)
+            .await?
+            .iter()
+            .filter_map(|entry| namespace_from_filename(entry))
+            .collect();
+
+        Ok(Self {
+            platform,
+            name: vault_name.to_string(),
+            metadata,
+            namespaces,
+        })
+    }
+
+    /// Namespaces of the vault as of [`open`](Self::open).
+    pub fn namespaces(&self) -> impl Iterator<Item = &str> {
+        self.namespaces.iter().map(String::as_str)
+    }
+
+    /// Decrypts `namespace`. Expired namespaces are reported as such but
+    /// left in place, since the vault is open read-only.
+    pub async fn read_namespace(
+        &self,
+        identity_private_key: &str,
+        namespace: &str,
+    ) -> Result<Vec<u8>, VaultError> {
+        if self.metadata.observer {
+            return Err(VaultError::ObserverVault);
+        }
+        if !self.namespaces.contains(namespace) {
+            return Err(VaultError::NamespaceNotFound);
+        }
+
+        let _guard = lock_namespace(&self.platform, &self.name, namespace).await?;
+        let namespace_data = self.map_namespace(namespace)?;
+
+        if let Some(timelock) = &namespace_data.timelock {
+            return Err(VaultError::TimeLocked(timelock.release_at));
+        }
+        if let Some(expiration) = &namespace_data.expiration {
+            if current_timestamp(&self.platform) >= expiration.expires_at {
+                return Err(VaultError::DataExpired);
+            }
+        }
+
+        let mut encrypted_chunks = BTreeMap::new();
+        for id in &namespace_data.chunks {
+            let path = root_path().join(chunks_path(&self.name)).join(id);
+            let encrypted = STANDARD
+                .decode(&*map_file(&path)?)
+                .map_err(|_| VaultError::serialization_error("Failed to decode chunk"))?;
+            encrypted_chunks.insert(id.clone(), encrypted);
+        }
+
+        let decrypted = chunks::decrypt_with_chunks(
+            &self.platform,
+            &encrypted_chunks,
+            &namespace_data,
+            identity_private_key,
+        )
+        .await?;
+        check_integrity(
+            &self.platform,
+            namespace,
+            &namespace_data,
+            &decrypted,
+            identity_private_key,
+        )
+        .await?;
+
+        Ok(decrypted)
+    }
+
+    fn map_namespace(&self, namespace: &str) -> Result<NamespaceData, VaultError> {
+        let directory = root_path().join(&self.name);
+        let path = directory.join(get_namespace_filename(namespace));
+        let path = if path.exists() {
+            path
+        } else {
+            directory.join(format!("{namespace}{LEGACY_NAMESPACE_EXTENSION}"))
+        };
+
+        let mapped = map_file(&path)?;
+        let text = std::str::from_utf8(&mapped)
+            .map_err(|_| VaultError::serialization_error("Failed to decode vault file"))?;
+        decode_stored(text)
+    }
+}
+
+// Storage root, which vault paths are relative to.
+fn root_path() -> PathBuf {
+    PathBuf::from(FsStorage::new().root_path())
+}
+
+fn map_file(path: &Path) -> Result<Mmap, VaultError> {
+    let file = File::open(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => VaultError::NamespaceNotFound,
+        _ => VaultError::io_error(format!("Failed to open {}: {e}", path.display())),
+    })?;
+
+    // SAFETY: callers hold the namespace lock, which every writer of the
+    // file takes, and drop the mapping before releasing it, so the file is
+    // not truncated or rewritten while mapped.
+    unsafe { Mmap::map(&file) }
+        .map_err(|e| VaultError::io_error(format!("Failed to map {}: {e}", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{
+        create_vault, delete_vault, save_vault, upsert_namespace,
+    };
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_mapped_vault_reads_namespaces_lazily() {
+        let platform = Platform::new();
+        let vault_name = "mapped_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+            for (namespace, data) in [("small", vec![1; 16]), ("large", vec![2; 3 << 20])] {
+                upsert_namespace(
+                    &platform,
+                    vault_name,
+                    &public_key,
+                    namespace,
+                    data,
+                    None,
+                    false,
+                )
+                .await
+                .unwrap();
+            }
+
+            let vault = MappedVault::open(Platform::new(), vault_name)
+                .await
+                .unwrap();
+            assert_eq!(vault.namespaces().collect::<Vec<_>>(), ["large", "small"]);
+            assert_eq!(
+                vault.read_namespace(&identity, "small").await.unwrap(),
+                vec![1; 16]
+            );
+            assert_eq!(
+                vault.read_namespace(&identity, "large").await.unwrap(),
+                vec![2; 3 << 20]
+            );
+            assert!(matches!(
+                vault.read_namespace(&identity, "missing").await,
+                Err(VaultError::NamespaceNotFound)
+            ));
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,406 @@
+//! Reconciliation of two replicas of a vault that diverged, typically the
+//! same vault name used on two devices while they were offline.
+//!
+//! The primary vault receives every namespace only the secondary holds; the
+//! strategy decides what happens to namespaces both hold with different
+//! content. Identities of the secondary are added to the primary so that
+//! namespaces encrypted to them stay readable.
+
+use super::chunks;
+use super::error::VaultError;
+use super::operations::{lock_vault, read_vault, write_vault};
+use super::serialization::deserialize_vault;
+use super::types::{NamespaceData, Vault};
+use crate::platform::Platform;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+const MERGED_SUFFIX: &str = "merged";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergeStrategy {
+    /// Keeps the copy written last. Copies without a write time, from before
+    /// it was recorded, lose against those with one.
+    PreferNewest,
+    PreferPrimary,
+    /// Keeps the primary copy and adds the secondary one under a suffixed
+    /// name.
+    KeepBothWithSuffix,
+}
+
+impl FromStr for MergeStrategy {
+    type Err = VaultError;
+
+    fn from_str(strategy: &str) -> Result<Self, Self::Err> {
+        match strategy {
+            "prefer-newest" => Ok(MergeStrategy::PreferNewest),
+            "prefer-primary" => Ok(MergeStrategy::PreferPrimary),
+            "keep-both-with-suffix" => Ok(MergeStrategy::KeepBothWithSuffix),
+            _ => Err(VaultError::io_error(format!(
+                "Unknown merge strategy: {strategy}"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum MergeAction {
+    /// Only the secondary holds the namespace; it is copied over.
+    Add,
+    /// The secondary copy replaces the primary one.
+    Replace,
+    /// The primary copy stays and the secondary one is dropped.
+    Keep,
+    /// The secondary copy is added as `namespace`.
+    AddAs { namespace: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MergeEntry {
+    pub namespace: String,
+    #[serde(flatten)]
+    pub action: MergeAction,
+}
+
+/// What a merge did, or would do on a dry run. Namespaces identical on both
+/// sides are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MergeReport {
+    pub dry_run: bool,
+    pub entries: Vec<MergeEntry>,
+    /// Identities of the secondary added to the primary.
+    pub recipients_added: Vec<String>,
+}
+
+impl MergeReport {
+    /// Whether applying the merge leaves the primary unchanged.
+    pub fn is_noop(&self) -> bool {
+        self.recipients_added.is_empty()
+            && self
+                .entries
+                .iter()
+                .all(|entry| entry.action == MergeAction::Keep)
+    }
+}
+
+/// Decides what merging `secondary` into `primary` does, without changing
+/// either.
+pub fn plan_merge(primary: &Vault, secondary: &Vault, strategy: MergeStrategy) -> MergeReport {
+    let mut entries = Vec::new();
+    let mut taken_names = Vec::new();
+
+    for (namespace, theirs) in &secondary.namespaces {
+        let action = match primary.namespaces.get(namespace) {
+            None => MergeAction::Add,
+            Some(ours) if same_copy(ours, theirs) => continue,
+            Some(ours) => match strategy {
+                MergeStrategy::PreferPrimary => MergeAction::Keep,
+                MergeStrategy::PreferNewest if theirs.updated_at > ours.updated_at => {
+                    MergeAction::Replace
+                }
+                MergeStrategy::PreferNewest => MergeAction::Keep,
+                MergeStrategy::KeepBothWithSuffix => {
+                    let renamed = suffixed_name(namespace, primary, secondary, &taken_names);
+                    taken_names.push(renamed.clone());
+                    MergeAction::AddAs { namespace: renamed }
+                }
+            },
+        };
+
+        entries.push(MergeEntry {
+            namespace: namespace.clone(),
+            action,
+        });
+    }
+
+    let recipients_added = secondary
+        .identity_salts
+        .iter()
+        .filter(|(public_key, _)| primary.identity_salts.get_salt(public_key).is_none())
+        .map(|(public_key, _)| public_key.clone())
+        .collect();
+
+    MergeReport {
+        dry_run: false,
+        entries,
+        recipients_added,
+    }
+}
+
+/// Applies a report made by [`plan_merge`] for the same two vaults.
+pub fn apply_merge(primary: &mut Vault, secondary: &Vault, report: &MergeReport) {
+    for public_key in &report.recipients_added {
+        if let Some(salt) = secondary.identity_salts.get_salt(public_key) {
+            primary.identity_salts.set_salt(public_key.clone(), *salt);
+        }
+        if let Some(credential_id) = secondary.identity_salts.get_credential_id(public_key) {
+            primary
+                .identity_salts
+                .set_credential_id(public_key.clone(), credential_id.clone());
+        }
+        if let Some(key_check) = secondary.identity_salts.get_key_check(public_key) {
+            primary
+                .identity_salts
+                .set_key_check(public_key.clone(), key_check.clone());
+        }
+    }
+    for (username, public_key) in &secondary.username_pk {
+        primary
+            .username_pk
+            .entry(username.clone())
+            .or_insert_with(|| public_key.clone());
+    }
+
+    for entry in &report.entries {
+        let Some(theirs) = secondary.namespaces.get(&entry.namespace) else {
+            continue;
+        };
+
+        let target = match &entry.action {
+            MergeAction::Keep => continue,
+            MergeAction::Add | MergeAction::Replace => entry.namespace.clone(),
+            MergeAction::AddAs { namespace } => namespace.clone(),
+        };
+
+        for chunk_id in &theirs.chunks {
+            if let Some(chunk) = secondary.chunks.get(chunk_id) {
+                primary
+                    .chunks
+                    .entry(chunk_id.clone())
+                    .or_insert_with(|| chunk.clone());
+            }
+        }
+        primary.namespaces.insert(target, theirs.clone());
+    }
+
+    chunks::collect_garbage(primary);
+}
+
+/// Merges `secondary` into the stored vault `primary_name`. With `dry_run`,
+/// only reports what the merge would do.
+pub async fn merge_vaults(
+    platform: &Platform,
+    primary_name: &str,
+    secondary: &Vault,
+    strategy: MergeStrategy,
+    dry_run: bool,
+) -> Result<MergeReport, VaultError> {
+    let _guard = lock_vault(platform, primary_name).await?;
+    let mut primary = read_vault(platform, primary_name).await?;
+
+    if primary.observer {
+        return Err(VaultError::ObserverVault);
+    }
+    if primary.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    let mut report = plan_merge(&primary, secondary, strategy);
+    report.dry_run = dry_run;
+
+    if !dry_run && !report.is_noop() {
+        apply_merge(&mut primary, secondary, &report);
+        write_vault(platform, primary_name, primary, Vec::new()).await?;
+    }
+
+    Ok(report)
+}
+
+/// Like [`merge_vaults`], with the secondary read from storage.
+pub async fn merge_stored_vaults(
+    platform: &Platform,
+    primary_name: &str,
+    secondary_name: &str,
+    strategy: MergeStrategy,
+    dry_run: bool,
+) -> Result<MergeReport, VaultError> {
+    let secondary = read_vault(platform, secondary_name).await?;
+
+    merge_vaults(platform, primary_name, &secondary, strategy, dry_run).await
+}
+
+/// Like [`merge_vaults`], with the secondary given as an export.
+pub async fn merge_vault_export(
+    platform: &Platform,
+    primary_name: &str,
+    secondary_bytes: &[u8],
+    strategy: MergeStrategy,
+    dry_run: bool,
+) -> Result<MergeReport, VaultError> {
+    let secondary = deserialize_vault(secondary_bytes)?;
+
+    merge_vaults(platform, primary_name, &secondary, strategy, dry_run).await
+}
+
+fn same_copy(ours: &NamespaceData, theirs: &NamespaceData) -> bool {
+    ours.data == theirs.data
+        && ours.chunks == theirs.chunks
+        && ours.expiration.as_ref().map(|e| e.expires_at)
+            == theirs.expiration.as_ref().map(|e| e.expires_at)
+}
+
+fn suffixed_name(namespace: &str, primary: &Vault, secondary: &Vault, taken: &[String]) -> String {
+    let is_free = |name: &String| {
+        !primary.namespaces.contains_key(name)
+            && !secondary.namespaces.contains_key(name)
+            && !taken.contains(name)
+    };
+
+    let first = format!("{namespace}.{MERGED_SUFFIX}");
+    if is_free(&first) {
+        return first;
+    }
+
+    let mut n = 2;
+    loop {
+        let candidate = format!("{namespace}.{MERGED_SUFFIX}-{n}");
+        if is_free(&candidate) {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{create_vault, delete_vault, save_vault};
+    use crate::domain::vault::types::Compression;
+    use futures::executor::block_on;
+
+    fn namespace(data: &[u8], updated_at: Option<i64>) -> NamespaceData {
+        NamespaceData {
+            data: data.to_vec(),
+            expiration: None,
+            chunks: Vec::new(),
+            timelock: None,
+            updated_at,
+            recipients: Vec::new(),
+            crdt: None,
+            integrity: None,
+            crypto_version: None,
+            compression: Compression::None,
+        }
+    }
+
+    fn replicas() -> (Vault, Vault) {
+        let mut primary = block_on(create_vault()).unwrap();
+        let mut secondary = primary.clone();
+
+        primary
+            .namespaces
+            .insert("same".into(), namespace(b"same", Some(10)));
+        secondary
+            .namespaces
+            .insert("same".into(), namespace(b"same", Some(10)));
+
+        primary
+            .namespaces
+            .insert("notes".into(), namespace(b"primary notes", Some(20)));
+        secondary
+            .namespaces
+            .insert("notes".into(), namespace(b"secondary notes", Some(30)));
+        primary
+            .namespaces
+            .insert("notes.merged".into(), namespace(b"taken", None));
+
+        secondary
+            .namespaces
+            .insert("offline".into(), namespace(b"written offline", Some(25)));
+        secondary
+            .identity_salts
+            .set_salt("age1secondary".into(), [7u8; 32]);
+
+        (primary, secondary)
+    }
+
+    #[test]
+    fn test_plan_merge_follows_strategy() {
+        let (primary, secondary) = replicas();
+        let action = |report: &MergeReport, name: &str| {
+            report
+                .entries
+                .iter()
+                .find(|entry| entry.namespace == name)
+                .map(|entry| entry.action.clone())
+        };
+
+        let newest = plan_merge(&primary, &secondary, MergeStrategy::PreferNewest);
+        assert_eq!(action(&newest, "same"), None);
+        assert_eq!(action(&newest, "notes"), Some(MergeAction::Replace));
+        assert_eq!(action(&newest, "offline"), Some(MergeAction::Add));
+        assert_eq!(newest.recipients_added, vec!["age1secondary".to_string()]);
+
+        let kept = plan_merge(&primary, &secondary, MergeStrategy::PreferPrimary);
+        assert_eq!(action(&kept, "notes"), Some(MergeAction::Keep));
+
+        let both = plan_merge(&primary, &secondary, MergeStrategy::KeepBothWithSuffix);
+        assert_eq!(
+            action(&both, "notes"),
+            Some(MergeAction::AddAs {
+                namespace: "notes.merged-2".to_string()
+            })
+        );
+
+        let mut merged = primary.clone();
+        apply_merge(&mut merged, &secondary, &both);
+        assert_eq!(merged.namespaces["notes"].data, b"primary notes");
+        assert_eq!(merged.namespaces["notes.merged-2"].data, b"secondary notes");
+        assert_eq!(merged.namespaces["offline"].data, b"written offline");
+        assert!(merged.identity_salts.get_salt("age1secondary").is_some());
+    }
+
+    #[test]
+    fn test_merge_vaults_dry_run_leaves_vault_untouched() {
+        let platform = Platform::new();
+        let vault_name = "merge_dry_run_test";
+        let (primary, secondary) = replicas();
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, primary).await.unwrap();
+
+            let report = merge_vaults(
+                &platform,
+                vault_name,
+                &secondary,
+                MergeStrategy::PreferNewest,
+                true,
+            )
+            .await
+            .unwrap();
+            assert!(report.dry_run);
+            assert!(!report.is_noop());
+            let stored = read_vault(&platform, vault_name).await.unwrap();
+            assert!(!stored.namespaces.contains_key("offline"));
+
+            merge_vaults(
+                &platform,
+                vault_name,
+                &secondary,
+                MergeStrategy::PreferNewest,
+                false,
+            )
+            .await
+            .unwrap();
+            let stored = read_vault(&platform, vault_name).await.unwrap();
+            assert_eq!(stored.namespaces["notes"].data, b"secondary notes");
+            assert!(stored.namespaces.contains_key("offline"));
+
+            let again = merge_vaults(
+                &platform,
+                vault_name,
+                &secondary,
+                MergeStrategy::PreferNewest,
+                false,
+            )
+            .await
+            .unwrap();
+            assert!(again.is_noop());
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,360 @@
+//! Lazy re-encryption of namespaces written with older algorithms.
+//!
+//! Every namespace records the version of the algorithms it was encrypted
+//! with. When the cipher suite or the KDF parameters change,
+//! [`CURRENT_CRYPTO_VERSION`] is bumped, and namespaces still on an older
+//! version are re-encrypted one at a time instead of in a single rotation
+//! pass: on their next read, on their next write, which encrypts with the
+//! current algorithms anyway, or in bounded batches of [`migrate_vault`].
+//! Namespaces written before versions were recorded count as version 0.
+//!
+//! Migration keeps the layout of a namespace, re-encrypting each of its
+//! chunks on its own under a new id, and adds the integrity MAC older
+//! namespaces lack. Only the identity that wrote a namespace migrates it,
+//! re-encrypting it to itself and the recipients granted that namespace:
+//! like rotation, it drops recipients added with `grant_vault_recipient`.
+//! Time-locked namespaces, whose key is held by escrow peers, and CRDT
+//! namespaces, whose pending copies are encrypted by peers, are left alone.
+
+use super::chunks;
+use super::compression::decompress;
+use super::error::VaultError;
+use super::integrity::{check_integrity, integrity_tag};
+use super::operations::{lock_namespace, namespace_keys, read_vault, write_namespace};
+use super::types::{NamespaceData, Vault};
+use crate::platform::Platform;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Version of the algorithms namespaces are written with.
+pub const CURRENT_CRYPTO_VERSION: u32 = 1;
+
+static LAZY_MIGRATION: AtomicBool = AtomicBool::new(true);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MigrationStatus {
+    pub current_version: u32,
+    /// Number of namespaces per version of the algorithms.
+    pub versions: BTreeMap<u32, usize>,
+    /// Namespaces due to be re-encrypted.
+    pub pending: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MigrationReport {
+    pub migrated: usize,
+    /// Pending namespaces the identity did not write.
+    pub skipped: usize,
+    /// Namespaces still due to be re-encrypted after this batch.
+    pub remaining: usize,
+}
+
+/// When enabled (the default), reading a namespace on an older version
+/// re-encrypts it with the current algorithms.
+pub fn set_lazy_migration(enabled: bool) {
+    LAZY_MIGRATION.store(enabled, Ordering::SeqCst);
+}
+
+pub fn crypto_version(namespace_data: &NamespaceData) -> u32 {
+    namespace_data.crypto_version.unwrap_or(0)
+}
+
+/// Whether `namespace_data` is on an older version and can be migrated.
+pub fn needs_migration(namespace_data: &NamespaceData) -> bool {
+    crypto_version(namespace_data) < CURRENT_CRYPTO_VERSION
+        && namespace_data.timelock.is_none()
+        && namespace_data.crdt.is_none()
+}
+
+/// Counts the namespaces of `vault_name` per version of the algorithms.
+pub async fn migration_status(
+    platform: &Platform,
+    vault_name: &str,
+) -> Result<MigrationStatus, VaultError> {
+    let vault = read_vault(platform, vault_name).await?;
+    let mut status = MigrationStatus {
+        current_version: CURRENT_CRYPTO_VERSION,
+        ..MigrationStatus::default()
+    };
+
+    for namespace_data in vault.namespaces.values() {
+        *status
+            .versions
+            .entry(crypto_version(namespace_data))
+            .or_insert(0) += 1;
+        status.pending += usize::from(needs_migration(namespace_data));
+    }
+
+    Ok(status)
+}
+
+/// Re-encrypts `namespace` with the current algorithms. Returns `false`
+/// when it is already current, cannot be migrated, or was written by
+/// another identity.
+#[tracing::instrument(skip_all, fields(vault = vault_name, namespace = namespace))]
+pub async fn migrate_namespace(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    namespace: &str,
+) -> Result<bool, VaultError> {
+    let _guards = lock_namespace(platform, vault_name, namespace).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.observer {
+        return Err(VaultError::ObserverVault);
+    }
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    let namespace_data = vault
+        .namespaces
+        .get(namespace)
+        .ok_or(VaultError::NamespaceNotFound)?;
+    if !needs_migration(namespace_data) {
+        return Ok(false);
+    }
+
+    let owner_public_key =
+        crate::domain::crypto::identity_to_public(platform, identity_private_key)
+            .map_err(|_| VaultError::InvalidPassword)?;
+    if !is_writer(&vault, namespace_data, &owner_public_key) {
+        return Ok(false);
+    }
+
+    let encrypted: Vec<&[u8]> = if namespace_data.chunks.is_empty() {
+        vec![namespace_data.data.as_slice()]
+    } else {
+        namespace_data
+            .chunks
+            .iter()
+            .map(|id| {
+                vault
+                    .chunks
+                    .get(id)
+                    .map(Vec::as_slice)
+                    .ok_or_else(|| VaultError::io_error(format!("Missing chunk {id}")))
+            })
+            .collect::<Result<_, _>>()?
+    };
+    let mut plaintexts = Vec::with_capacity(encrypted.len());
+    for plaintext in
+        crate::domain::crypto::decrypt_many(platform, &encrypted, identity_private_key).await
+    {
+        plaintexts.push(plaintext.map_err(|_| VaultError::InvalidPassword)?);
+    }
+
+    // Pieces are re-encrypted as they are, still compressed.
+    let plaintext = decompress(namespace_data.compression, plaintexts.concat())?;
+    check_integrity(
+        platform,
+        namespace,
+        namespace_data,
+        &plaintext,
+        identity_private_key,
+    )
+    .await?;
+
+    let recipients = namespace_data.recipients.clone();
+    let keys = namespace_keys(&owner_public_key, &recipients);
+    let mut reencrypted = Vec::with_capacity(plaintexts.len());
+    for piece in &plaintexts {
+        reencrypted.push(
+            crate::domain::crypto::encrypt_for_recipients(platform, piece, &keys)
+                .await
+                .map_err(|e| VaultError::io_error(e.to_string()))?,
+        );
+    }
+
+    let chunked = !namespace_data.chunks.is_empty();
+    let (data, chunk_ids) = if chunked {
+        // Chunks may be shared with other namespaces, which keep the old
+        // ones until they are migrated too.
+        let ids = reencrypted
+            .into_iter()
+            .map(|encrypted| {
+                let id = chunks::random_chunk_id();
+                vault.chunks.insert(id.clone(), encrypted);
+                id
+            })
+            .collect();
+        (Vec::new(), ids)
+    } else {
+        (reencrypted.concat(), Vec::new())
+    };
+
+    let integrity = integrity_tag(platform, &owner_public_key, &keys, &plaintext).await?;
+
+    // The content is unchanged, so the write time is left alone and peers
+    // holding a copy do not take it for a newer version.
+    if let Some(namespace_data) = vault.namespaces.get_mut(namespace) {
+        namespace_data.data = data;
+        namespace_data.chunks = chunk_ids;
+        namespace_data.integrity = Some(integrity);
+        namespace_data.crypto_version = Some(CURRENT_CRYPTO_VERSION);
+    }
+
+    write_namespace(platform, vault_name, &vault, namespace).await?;
+    Ok(true)
+}
+
+/// Migrates up to `max_namespaces` of the pending namespaces of
+/// `vault_name` written by `identity_private_key`, so a large vault can be
+/// upgraded in steps between other work.
+#[tracing::instrument(skip_all, fields(vault = vault_name))]
+pub async fn migrate_vault(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    max_namespaces: usize,
+) -> Result<MigrationReport, VaultError> {
+    let vault = read_vault(platform, vault_name).await?;
+    let pending: Vec<String> = vault
+        .namespaces
+        .iter()
+        .filter(|(_, namespace_data)| needs_migration(namespace_data))
+        .map(|(namespace, _)| namespace.clone())
+        .collect();
+
+    let mut report = MigrationReport::default();
+    for namespace in &pending {
+        if report.migrated == max_namespaces {
+            break;
+        }
+        match migrate_namespace(platform, vault_name, identity_private_key, namespace).await {
+            Ok(true) => report.migrated += 1,
+            Ok(false) | Err(VaultError::InvalidPassword) => report.skipped += 1,
+            // Removed since the vault was read.
+            Err(VaultError::NamespaceNotFound) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    report.remaining = pending.len() - report.migrated;
+
+    Ok(report)
+}
+
+/// Migrates `namespace` after `identity_private_key` read it, unless lazy
+/// migration is disabled. Failures are logged: the read itself succeeded.
+pub(super) async fn migrate_after_read(
+    platform: &Platform,
+    vault: &Vault,
+    vault_name: &str,
+    identity_private_key: &str,
+    namespace: &str,
+) {
+    let due = vault.namespaces.get(namespace).is_some_and(needs_migration);
+    if !due || vault.frozen || !LAZY_MIGRATION.load(Ordering::SeqCst) {
+        return;
+    }
+
+    if let Err(e) = migrate_namespace(platform, vault_name, identity_private_key, namespace).await {
+        tracing::warn!(
+            vault = %vault_name,
+            namespace = %namespace,
+            error = %e,
+            "Lazy migration failed"
+        );
+    }
+}
+
+// Whether the identity of `public_key` wrote `namespace_data`: the one its
+// MAC names, or for namespaces without one, any identity of the vault.
+fn is_writer(vault: &Vault, namespace_data: &NamespaceData, public_key: &str) -> bool {
+    match &namespace_data.integrity {
+        Some(tag) => tag.key_id == public_key,
+        None => vault.identity_salts.get_salt(public_key).is_some(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{
+        create_vault, delete_vault, read_namespace, save_vault, upsert_namespace,
+    };
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_older_namespaces_are_migrated_on_read_and_in_batches() {
+        let platform = Platform::new();
+        let vault_name = "migration_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            let mut vault = create_vault().await.unwrap();
+
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+            vault.identity_salts.set_salt(public_key.clone(), [1; 32]);
+            save_vault(&platform, vault_name, vault).await.unwrap();
+
+            for namespace in ["read", "batch_1", "batch_2"] {
+                upsert_namespace(
+                    &platform,
+                    vault_name,
+                    &public_key,
+                    namespace,
+                    namespace.as_bytes().to_vec(),
+                    None,
+                    false,
+                )
+                .await
+                .unwrap();
+            }
+
+            // As written before versions and MACs were recorded.
+            let mut vault = read_vault(&platform, vault_name).await.unwrap();
+            for namespace_data in vault.namespaces.values_mut() {
+                namespace_data.crypto_version = None;
+                namespace_data.integrity = None;
+            }
+            save_vault(&platform, vault_name, vault).await.unwrap();
+
+            let status = migration_status(&platform, vault_name).await.unwrap();
+            assert_eq!(status.versions, BTreeMap::from([(0, 3)]));
+            assert_eq!(status.pending, 3);
+
+            assert_eq!(
+                read_namespace(&platform, vault_name, &identity, "read")
+                    .await
+                    .unwrap(),
+                b"read"
+            );
+            let vault = read_vault(&platform, vault_name).await.unwrap();
+            assert_eq!(
+                vault.namespaces["read"].crypto_version,
+                Some(CURRENT_CRYPTO_VERSION)
+            );
+            assert!(vault.namespaces["read"].integrity.is_some());
+
+            let report = migrate_vault(&platform, vault_name, &identity, 1)
+                .await
+                .unwrap();
+            assert_eq!((report.migrated, report.remaining), (1, 1));
+            let report = migrate_vault(&platform, vault_name, &identity, 10)
+                .await
+                .unwrap();
+            assert_eq!((report.migrated, report.remaining), (1, 0));
+
+            let status = migration_status(&platform, vault_name).await.unwrap();
+            assert_eq!(
+                status.versions,
+                BTreeMap::from([(CURRENT_CRYPTO_VERSION, 3)])
+            );
+            for namespace in ["read", "batch_1", "batch_2"] {
+                assert_eq!(
+                    read_namespace(&platform, vault_name, &identity, namespace)
+                        .await
+                        .unwrap(),
+                    namespace.as_bytes()
+                );
+            }
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
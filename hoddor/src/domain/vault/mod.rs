@@ -1,16 +1,131 @@
+pub mod access;
+pub mod aggregate;
+pub mod chunks;
+pub mod cleanup_scheduler;
+pub mod compression;
+pub mod crdt;
+pub mod credentials;
+pub mod crypto_audit;
+pub mod device_link;
+pub mod diff;
+pub mod encrypted_export;
 pub mod error;
 pub mod expiration;
+pub mod external_changes;
+pub mod handle;
+pub mod history;
+pub mod import;
+pub mod incremental;
+pub mod integrity;
+pub mod large_blob;
+pub mod limits;
+pub mod log;
+#[cfg(all(feature = "mapped", not(target_arch = "wasm32")))]
+pub mod mapped;
+pub mod merge;
+pub mod migration;
 pub mod operations;
+pub mod outbox;
+pub mod patch;
+pub mod query;
+pub mod residency;
+pub mod retention;
+pub mod rotation;
+pub mod secret_scan;
 pub mod serialization;
+pub mod stream;
+pub mod sync_policy;
+pub mod sync_profile;
+pub mod sync_recording;
+pub mod timelock;
 pub mod types;
+pub mod unlock_attempts;
 pub mod validation;
+pub mod wal;
+pub mod watch;
 
+pub use access::{
+    grant_namespace_access, namespace_recipients, revoke_namespace_access, share_namespace,
+};
+pub use aggregate::{
+    noisy_count, noisy_sum, privacy_budget, set_privacy_budget, NoisyAggregate, PrivacyBudget,
+    SumQuery,
+};
+pub use cleanup_scheduler::{
+    cleanup_all_vaults, is_cleanup_scheduler_running, stop_cleanup_scheduler, CleanupSchedule,
+};
+pub use compression::{set_vault_compression, vault_compression};
+pub use crdt::{read_crdt, update_crdt, CrdtKind, CrdtNamespace, CrdtOperation, CrdtValue};
+pub use credentials::{
+    list_credentials, rename_credential, revoke_credential, CredentialInfo, CredentialRekey,
+};
+pub use crypto_audit::{describe_crypto, CryptoDescription};
+pub use device_link::{
+    export_device_link, import_device_link, DeviceLinkExport, DEVICE_LINK_TTL_SECONDS,
+};
+pub use diff::{diff_stored_vaults, diff_vault_exports, diff_vaults, VaultDiff};
+pub use encrypted_export::{
+    export_vault_encrypted, import_vault_encrypted, is_encrypted_export, ExportKey, ExportSecret,
+};
 pub use error::VaultError;
-pub use expiration::{cleanup_expired_namespaces, create_expiration, is_expired};
+pub use expiration::{
+    cleanup_expired_namespaces, create_expiration, is_expired, normalize_remote_expiration,
+    off_namespace_expired, on_namespace_expired, ExpirationCallback,
+};
+pub use handle::VaultHandle;
+pub use history::{
+    disable_history, enable_history, is_history_enabled, list_snapshots, prune_snapshots, read_at,
+    PrunePolicy, SnapshotInfo,
+};
+pub use import::{import_namespaces, ExistingNamespaces, ImportOptions, ImportReport};
+pub use incremental::{
+    apply_incremental_export, export_vault_incremental, read_incremental_manifest,
+    IncrementalManifest, IncrementalReport,
+};
+pub use integrity::{seal_vault, verify_vault, IntegrityManifest, IntegrityReport, IntegrityTag};
+pub use large_blob::{
+    large_blob_entry, open_large_blob, restore_from_large_blob, seal_large_blob,
+    upsert_large_blob_entry, LargeBlobEntry, LARGE_BLOB_PRF_NONCE,
+};
+pub use limits::{namespace_size_limit, set_max_namespace_bytes, DEFAULT_MAX_NAMESPACE_BYTES};
+pub use log::{append_to_log, read_log_range, LogEntry};
+#[cfg(all(feature = "mapped", not(target_arch = "wasm32")))]
+pub use mapped::MappedVault;
+pub use merge::{
+    merge_stored_vaults, merge_vault_export, merge_vaults, MergeReport, MergeStrategy,
+};
+pub use migration::{
+    migrate_namespace, migrate_vault, migration_status, set_lazy_migration, MigrationReport,
+    MigrationStatus, CURRENT_CRYPTO_VERSION,
+};
 pub use operations::{
-    create_vault, create_vault_from_sync, delete_namespace_file, delete_vault,
-    get_namespace_filename, list_vaults, read_vault, save_vault,
+    create_observer_vault, create_vault, create_vault_from_sync, delete_namespace_file,
+    delete_vault, get_namespace_filename, list_vaults, migrate_vault_files, read_vault, save_vault,
+    set_legacy_read_repair, upsert_namespace_with, UpsertOptions,
 };
+pub use patch::patch_namespace;
+pub use query::{query_namespace, JsonPath};
+pub use rotation::rotate_vault_identity;
+pub use secret_scan::{secret_scan_policy, set_secret_scan_policy, SecretKind, SecretScanPolicy};
 pub use serialization::{deserialize_vault, serialize_vault};
-pub use types::{Expiration, IdentitySalts, NamespaceData, Vault, VaultMetadata};
+pub use stream::{NamespaceReader, NamespaceWriter};
+pub use sync_policy::{set_sync_policy, sync_policy, SyncPolicy};
+pub use sync_profile::{
+    export_sync_profile, import_sync_profile, load_sync_profile, save_sync_profile,
+    validate_ice_config, AccessLevel, IceServer, IceTransportPolicy, SyncProfile, TrustedPeer,
+};
+pub use sync_recording::{
+    clear_sync_recording, decrypt_sync_recording, export_sync_recording, is_recording_sync, replay,
+    start_sync_recording, stop_sync_recording, RecordedSync, Replay, ReplayStep, SyncDirection,
+};
+pub use timelock::{read_timelocked, release_key_share, write_timelocked, KeyShare};
+pub use types::{
+    Compression, Expiration, IdentitySalts, NamespaceData, RetentionPolicy, TimeLock, Vault,
+    VaultMetadata,
+};
+pub use unlock_attempts::{
+    reset_unlock_failures, set_unlock_failure_threshold, DEFAULT_UNLOCK_FAILURE_THRESHOLD,
+};
 pub use validation::{validate_namespace, validate_passphrase, validate_vault_name};
+pub use wal::{recover_all_vaults, recover_vault};
+pub use watch::{unwatch_namespace, watch_namespace, WatchCallback};
@@ -1,84 +1,333 @@
+use super::chunks;
 use super::error::VaultError;
-use super::types::{Expiration, NamespaceData, Vault, VaultMetadata};
+use super::integrity::{check_integrity, integrity_tag, reseal_integrity};
+use super::migration::CURRENT_CRYPTO_VERSION;
+use super::serialization::{decode_stored, encode_stored};
+use super::types::{Compression, Expiration, NamespaceData, Vault, VaultMetadata};
+use super::wal::{self, WalWrite};
+use crate::domain::progress::{ignore_progress, Progress};
+use crate::domain::usage_stats::{self, UsageOperation};
 use crate::platform::Platform;
-use std::collections::HashMap;
+use crate::ports::LockGuard;
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub(super) const METADATA_FILENAME: &str = "metadata.json";
+pub(super) const NAMESPACE_EXTENSION: &str = ".hoddor";
+pub(super) const LEGACY_NAMESPACE_EXTENSION: &str = ".ns";
+/// Files read concurrently when loading a vault: enough to hide the latency
+/// of each OPFS read without flooding the browser with pending handles.
+pub(super) const READ_BATCH_SIZE: usize = 32;
+/// Directory a vault is copied to while renamed. Vault names cannot contain
+/// dots, so it never clashes with one.
+const RENAME_STAGING_PREFIX: &str = ".renaming-";
+
+static LEGACY_READ_REPAIR: AtomicBool = AtomicBool::new(true);
+
+/// Namespaces can be `/`-delimited paths such as `app/settings/theme`.
+/// Their files stay flat in the vault directory: `%` and `/` are escaped
+/// as `%25` and `%2F`.
+pub fn get_namespace_filename(namespace: &str) -> String {
+    let escaped = namespace.replace('%', "%25").replace('/', "%2F");
+    format!("{escaped}{NAMESPACE_EXTENSION}")
+}
 
-const METADATA_FILENAME: &str = "metadata.json";
-const NAMESPACE_EXTENSION: &str = ".hoddor";
-const LEGACY_NAMESPACE_EXTENSION: &str = ".ns";
+/// The namespace stored in file `entry_name`, `None` for other files.
+/// Legacy `.ns` files predate hierarchical namespaces and are not escaped.
+pub(super) fn namespace_from_filename(entry_name: &str) -> Option<String> {
+    if let Some(escaped) = entry_name.strip_suffix(NAMESPACE_EXTENSION) {
+        Some(escaped.replace("%2F", "/").replace("%25", "%"))
+    } else {
+        entry_name
+            .strip_suffix(LEGACY_NAMESPACE_EXTENSION)
+            .map(str::to_string)
+    }
+}
 
-pub fn get_namespace_filename(namespace: &str) -> String {
-    format!("{namespace}{NAMESPACE_EXTENSION}")
+pub(super) fn metadata_lock_name(vault_name: &str) -> String {
+    format!("{vault_name}/metadata")
+}
+
+fn namespace_lock_name(vault_name: &str, namespace: &str) -> String {
+    format!("{vault_name}/namespace/{namespace}")
+}
+
+/// Takes the vault-wide lock exclusively. Operations rewriting the whole
+/// vault hold it across their read and write; namespace writers hold it
+/// shared, so they run concurrently with each other but never with a
+/// whole-vault rewrite. Locks are not reentrant: do not call [`save_vault`]
+/// while holding it.
+///
+/// Completes an interrupted save of the vault before returning, see
+/// [`wal::recover_locked`].
+pub async fn lock_vault(
+    platform: &Platform,
+    vault_name: &str,
+) -> Result<Box<dyn LockGuard>, VaultError> {
+    let guard = platform
+        .locks()
+        .acquire(&metadata_lock_name(vault_name))
+        .await?;
+    wal::recover_locked(platform, vault_name).await?;
+
+    Ok(guard)
+}
+
+pub(super) async fn lock_namespace(
+    platform: &Platform,
+    vault_name: &str,
+    namespace: &str,
+) -> Result<(Box<dyn LockGuard>, Box<dyn LockGuard>), VaultError> {
+    let locks = platform.locks();
+    let mut metadata = locks
+        .acquire_shared(&metadata_lock_name(vault_name))
+        .await?;
+
+    // A journal seen under the shared lock belongs to a save that was
+    // interrupted: complete it before touching a namespace it may rewrite.
+    if wal::has_pending(platform, vault_name).await {
+        drop(metadata);
+        drop(lock_vault(platform, vault_name).await?);
+        metadata = locks
+            .acquire_shared(&metadata_lock_name(vault_name))
+            .await?;
+    }
+
+    let namespace = locks
+        .acquire(&namespace_lock_name(vault_name, namespace))
+        .await?;
+
+    Ok((metadata, namespace))
+}
+
+/// When enabled (the default), reading a vault that still holds legacy `.ns`
+/// namespace files rewrites them as `.hoddor` files.
+pub fn set_legacy_read_repair(enabled: bool) {
+    LEGACY_READ_REPAIR.store(enabled, Ordering::SeqCst);
 }
 
 pub async fn read_vault(platform: &Platform, vault_name: &str) -> Result<Vault, VaultError> {
+    read_vault_with_progress(platform, vault_name, &ignore_progress).await
+}
+
+/// Like [`read_vault`], reporting every namespace and chunk file read.
+#[tracing::instrument(level = "debug", skip_all, fields(vault = vault_name))]
+pub async fn read_vault_with_progress(
+    platform: &Platform,
+    vault_name: &str,
+    on_progress: &dyn Fn(Progress),
+) -> Result<Vault, VaultError> {
+    let (vault, legacy_files) = load_vault(platform, vault_name, on_progress).await?;
+
+    if !legacy_files.is_empty() && LEGACY_READ_REPAIR.load(Ordering::SeqCst) {
+        if let Err(e) = upgrade_legacy_files(platform, vault_name, &vault, legacy_files).await {
+            tracing::warn!(
+                vault = vault_name,
+                error = %e,
+                "Failed to upgrade legacy namespace files"
+            );
+        }
+    }
+
+    Ok(vault)
+}
+
+/// Rewrites every legacy `.ns` namespace file of `vault_name` as a `.hoddor`
+/// file and returns how many were upgraded.
+pub async fn migrate_vault_files(
+    platform: &Platform,
+    vault_name: &str,
+) -> Result<usize, VaultError> {
+    let (vault, legacy_files) = load_vault(platform, vault_name, &ignore_progress).await?;
+
+    if legacy_files.is_empty() {
+        return Ok(0);
+    }
+
+    upgrade_legacy_files(platform, vault_name, &vault, legacy_files).await
+}
+
+async fn upgrade_legacy_files(
+    platform: &Platform,
+    vault_name: &str,
+    vault: &Vault,
+    legacy_files: Vec<String>,
+) -> Result<usize, VaultError> {
+    // Not journaled: a legacy file is only deleted once its `.hoddor`
+    // replacement is written, and the replacement supersedes it on read, so
+    // an interrupted upgrade is simply resumed by the next one.
+    let mut writes = Vec::with_capacity(legacy_files.len());
+
+    for path in &legacy_files {
+        let Some(namespace) = path
+            .strip_prefix(&format!("{vault_name}/"))
+            .and_then(|entry| entry.strip_suffix(LEGACY_NAMESPACE_EXTENSION))
+        else {
+            continue;
+        };
+        let Some(data) = vault.namespaces.get(namespace) else {
+            continue;
+        };
+
+        writes.push(WalWrite {
+            path: format!("{}/{}", vault_name, get_namespace_filename(namespace)),
+            content: encode_stored(data)?,
+        });
+    }
+
     let storage = platform.storage();
 
-    let metadata_path = format!("{vault_name}/{METADATA_FILENAME}");
-    let metadata_text = storage.read_file(&metadata_path).await?;
+    for write in &writes {
+        storage.write_file(&write.path, &write.content).await?;
+    }
 
-    let mut vault: Vault = serde_json::from_str(&metadata_text)
-        .map_err(|_| VaultError::serialization_error("Failed to deserialize vault metadata"))?;
+    for path in &legacy_files {
+        storage.delete_file(path).await?;
+    }
 
-    vault.namespaces.clear();
+    Ok(legacy_files.len())
+}
 
-    let entries = storage.list_entries(vault_name).await?;
+/// Reads `metadata.json` alone: identities, usernames and flags, with no
+/// namespace or chunk loaded.
+#[tracing::instrument(level = "debug", skip_all, fields(vault = vault_name))]
+pub async fn read_vault_metadata(
+    platform: &Platform,
+    vault_name: &str,
+) -> Result<Vault, VaultError> {
+    let metadata_path = format!("{vault_name}/{METADATA_FILENAME}");
+    let metadata_text = platform.storage().read_file(&metadata_path).await?;
 
-    for entry_name in entries {
-        // Support both new .hoddor and legacy .ns extensions
-        let is_namespace = entry_name.ends_with(NAMESPACE_EXTENSION)
-            || entry_name.ends_with(LEGACY_NAMESPACE_EXTENSION);
+    let mut vault: Vault = decode_stored(&metadata_text)?;
 
-        if is_namespace {
-            let namespace_path = format!("{vault_name}/{entry_name}");
-            let namespace_text = storage.read_file(&namespace_path).await?;
+    vault.namespaces.clear();
+    vault.chunks.clear();
 
-            let namespace_data: NamespaceData =
-                serde_json::from_str(&namespace_text).map_err(|_| {
-                    VaultError::serialization_error("Failed to deserialize namespace data")
-                })?;
+    Ok(vault)
+}
 
-            // Strip the appropriate extension
-            let namespace = if let Some(ns) = entry_name.strip_suffix(NAMESPACE_EXTENSION) {
-                ns.to_string()
-            } else if let Some(ns) = entry_name.strip_suffix(LEGACY_NAMESPACE_EXTENSION) {
-                ns.to_string()
-            } else {
-                continue; // Should never happen due to the is_namespace check
+// Returns the vault along with the paths of the legacy `.ns` files it was
+// read from.
+async fn load_vault(
+    platform: &Platform,
+    vault_name: &str,
+    on_progress: &dyn Fn(Progress),
+) -> Result<(Vault, Vec<String>), VaultError> {
+    let storage = platform.storage();
+    let (metadata, entries, chunk_ids) = futures::join!(
+        read_vault_metadata(platform, vault_name),
+        storage.list_entries(vault_name),
+        chunks::list_stored_chunks(platform, vault_name),
+    );
+    let mut vault = metadata?;
+
+    // Support both new .hoddor and legacy .ns extensions
+    let namespace_entries: Vec<String> = entries?
+        .into_iter()
+        .filter(|entry_name| {
+            entry_name.ends_with(NAMESPACE_EXTENSION)
+                || entry_name.ends_with(LEGACY_NAMESPACE_EXTENSION)
+        })
+        .collect();
+
+    let namespace_count = namespace_entries.len();
+    let total = namespace_count + chunk_ids.len();
+    let mut legacy_files = Vec::new();
+    let mut read = 0;
+
+    for batch in namespace_entries.chunks(READ_BATCH_SIZE) {
+        let paths: Vec<String> = batch
+            .iter()
+            .map(|entry_name| format!("{vault_name}/{entry_name}"))
+            .collect();
+        let contents = storage.read_files(&paths).await;
+
+        for ((entry_name, namespace_path), namespace_text) in batch.iter().zip(paths).zip(contents)
+        {
+            let namespace_text = namespace_text?;
+            let namespace_data: NamespaceData = decode_stored(&namespace_text)?;
+
+            let Some(ns) = namespace_from_filename(entry_name) else {
+                continue;
             };
+            if entry_name.ends_with(NAMESPACE_EXTENSION) {
+                vault.namespaces.insert(ns, namespace_data);
+                vault.stored_digests.insert(
+                    namespace_path,
+                    *blake3::hash(namespace_text.as_bytes()).as_bytes(),
+                );
+            } else {
+                // A .hoddor file always supersedes its legacy counterpart.
+                vault.namespaces.entry(ns).or_insert(namespace_data);
+                legacy_files.push(namespace_path);
+            }
 
-            vault.namespaces.insert(namespace, namespace_data);
+            read += 1;
+            on_progress(Progress::items(read, total));
         }
     }
 
-    Ok(vault)
+    vault.chunks = chunks::read_chunks(platform, vault_name, chunk_ids, &|read| {
+        on_progress(Progress::items(namespace_count + read, total))
+    })
+    .await?;
+
+    Ok((vault, legacy_files))
 }
 
+/// Rewrites every file of the vault under the exclusive vault lock. Callers
+/// that read the vault first should rather hold [`lock_vault`] across both
+/// steps, or a namespace written in between may be reverted.
 pub async fn save_vault(
     platform: &Platform,
     vault_name: &str,
     vault: Vault,
 ) -> Result<(), VaultError> {
-    if !platform.persistence().has_requested() {
-        let is_persisted = platform.persistence().check().await.unwrap_or(false);
-
-        if !is_persisted {
-            let result = platform.persistence().request().await;
-
-            match result {
-                Ok(is_granted) => {
-                    platform
-                        .logger()
-                        .log(&format!("persistence request granted: {is_granted}"));
-                }
-                Err(e) => {
-                    platform
-                        .logger()
-                        .error(&format!("Persistence request failed: {e}"));
-                }
+    let _guard = lock_vault(platform, vault_name).await?;
+    write_vault(platform, vault_name, vault, Vec::new()).await
+}
+
+pub(super) async fn write_vault(
+    platform: &Platform,
+    vault_name: &str,
+    vault: Vault,
+    deletes: Vec<String>,
+) -> Result<(), VaultError> {
+    write_vault_with_progress(platform, vault_name, vault, deletes, &ignore_progress).await
+}
+
+// Asks once per session for storage that survives eviction, before the
+// first write.
+pub(super) async fn request_persistence(platform: &Platform) {
+    if platform.persistence().has_requested() {
+        return;
+    }
+
+    let is_persisted = platform.persistence().check().await.unwrap_or(false);
+
+    if !is_persisted {
+        match platform.persistence().request().await {
+            Ok(is_granted) => {
+                tracing::debug!(granted = is_granted, "Persistence request answered");
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Persistence request failed");
             }
         }
     }
+}
+
+// Reports the bytes of file content written; deletes are not counted.
+#[tracing::instrument(level = "debug", skip_all, fields(vault = vault_name))]
+pub(super) async fn write_vault_with_progress(
+    platform: &Platform,
+    vault_name: &str,
+    vault: Vault,
+    deletes: Vec<String>,
+    on_progress: &dyn Fn(Progress),
+) -> Result<(), VaultError> {
+    request_persistence(platform).await;
 
     let storage = platform.storage();
 
@@ -86,21 +335,86 @@ pub async fn save_vault(
 
     let mut metadata_vault = vault.clone();
     metadata_vault.namespaces.clear();
+    metadata_vault.chunks.clear();
+    metadata_vault.journal_sequence = wal::next_sequence(platform, vault_name).await;
+    let metadata_bytes = encode_stored(&metadata_vault)?.len();
 
-    let metadata_json = serde_json::to_string(&metadata_vault)
-        .map_err(|_| VaultError::serialization_error("Failed to serialize vault metadata"))?;
+    // Only namespace files that differ from what was read are rewritten.
+    let mut writes = Vec::new();
+    for (namespace, data) in &vault.namespaces {
+        let path = format!("{}/{}", vault_name, get_namespace_filename(namespace));
+        let content = encode_stored(data)?;
+        let digest = *blake3::hash(content.as_bytes()).as_bytes();
+        if vault.stored_digests.get(&path) != Some(&digest) {
+            writes.push(WalWrite { path, content });
+        }
+    }
 
-    let metadata_path = format!("{vault_name}/{METADATA_FILENAME}");
-    storage.write_file(&metadata_path, &metadata_json).await?;
+    // Chunks are immutable once written, so only new ones hit storage. They
+    // are written before the save commits and need no journal: until then
+    // nothing references them, and garbage collection drops them if the
+    // save never commits.
+    let stored_chunks = chunks::list_stored_chunks(platform, vault_name).await;
+    let chunks_path = chunks::chunks_path(vault_name);
 
-    for (namespace, data) in &vault.namespaces {
-        let namespace_json = serde_json::to_string(&data)
-            .map_err(|_| VaultError::serialization_error("Failed to serialize namespace data"))?;
+    if !vault.chunks.is_empty() {
+        storage.create_directory(&chunks_path).await?;
+    }
 
-        let namespace_path = format!("{}/{}", vault_name, get_namespace_filename(namespace));
-        storage.write_file(&namespace_path, &namespace_json).await?;
+    let new_chunks: Vec<(String, String)> = vault
+        .chunks
+        .iter()
+        .filter(|(id, _)| !stored_chunks.contains(*id))
+        .map(|(id, encrypted)| {
+            (
+                format!("{chunks_path}/{id}"),
+                chunks::encode_chunk(encrypted),
+            )
+        })
+        .collect();
+
+    let mut deletes = deletes;
+    for id in stored_chunks {
+        if !vault.chunks.contains_key(&id) {
+            deletes.push(format!("{chunks_path}/{id}"));
+        }
+    }
+
+    super::history::record_snapshots(
+        platform,
+        vault_name,
+        &vault,
+        vault.namespaces.keys().map(String::as_str),
+    )
+    .await?;
+
+    let total_bytes: usize = new_chunks
+        .iter()
+        .map(|(_, content)| content.len())
+        .chain(writes.iter().map(|write| write.content.len()))
+        .sum::<usize>()
+        + metadata_bytes;
+    let written_bytes = Cell::new(0);
+    let on_written = |bytes: usize| {
+        written_bytes.set(written_bytes.get() + bytes);
+        on_progress(Progress::bytes(written_bytes.get(), total_bytes));
+    };
+
+    for (path, content) in &new_chunks {
+        storage.write_file(path, content).await?;
+        on_written(content.len());
     }
 
+    wal::commit(
+        platform,
+        vault_name,
+        &metadata_vault,
+        writes,
+        deletes,
+        &on_written,
+    )
+    .await?;
+
     let vault_bytes = serde_json::to_vec(&vault).map_err(|_| {
         VaultError::serialization_error("Failed to serialize vault for notification")
     })?;
@@ -109,35 +423,110 @@ pub async fn save_vault(
         .notifier()
         .notify_vault_update(vault_name, &vault_bytes);
 
+    super::watch::notify_watches(platform, vault_name, &vault, None).await;
+
     Ok(())
 }
 
-pub async fn list_vaults(platform: &Platform) -> Result<Vec<String>, VaultError> {
-    platform.logger().log("Listing vaults from root directory");
+// Writes a single namespace file, plus the chunks it references that are not
+// stored yet, without touching the rest of the vault. Not journaled: the
+// chunks are written first and the namespace file write is atomic.
+#[tracing::instrument(level = "debug", skip_all, fields(vault = vault_name, namespace = namespace))]
+pub(super) async fn write_namespace(
+    platform: &Platform,
+    vault_name: &str,
+    vault: &Vault,
+    namespace: &str,
+) -> Result<(), VaultError> {
+    let storage = platform.storage();
+    let data = vault
+        .namespaces
+        .get(namespace)
+        .ok_or(VaultError::NamespaceNotFound)?;
+
+    let mut writes = Vec::new();
+
+    if !data.chunks.is_empty() {
+        let stored_chunks = chunks::list_stored_chunks(platform, vault_name).await;
+        let chunks_path = chunks::chunks_path(vault_name);
+        let mut queued = HashSet::new();
+        storage.create_directory(&chunks_path).await?;
+
+        for id in &data.chunks {
+            if stored_chunks.contains(id) || !queued.insert(id) {
+                continue;
+            }
+            let encrypted = vault
+                .chunks
+                .get(id)
+                .ok_or_else(|| VaultError::io_error(format!("Missing chunk {id}")))?;
+            writes.push(WalWrite {
+                path: format!("{chunks_path}/{id}"),
+                content: chunks::encode_chunk(encrypted),
+            });
+        }
+    }
+
+    writes.push(WalWrite {
+        path: format!("{}/{}", vault_name, get_namespace_filename(namespace)),
+        content: encode_stored(data)?,
+    });
+
+    super::history::record_snapshots(platform, vault_name, vault, [namespace]).await?;
+
+    for write in &writes {
+        storage.write_file(&write.path, &write.content).await?;
+    }
+
+    let vault_bytes = serde_json::to_vec(vault).map_err(|_| {
+        VaultError::serialization_error("Failed to serialize vault for notification")
+    })?;
 
+    let _ = platform
+        .notifier()
+        .notify_vault_update(vault_name, &vault_bytes);
+
+    super::watch::notify_watches(platform, vault_name, vault, Some(namespace)).await;
+
+    Ok(())
+}
+
+pub async fn list_vaults(platform: &Platform) -> Result<Vec<String>, VaultError> {
     let storage = platform.storage();
-    let vault_names = storage.list_entries(".").await?;
+    let mut vault_names = storage.list_entries(".").await?;
+    vault_names.retain(|name| !name.starts_with(RENAME_STAGING_PREFIX));
 
-    platform
-        .logger()
-        .log(&format!("Found {} vaults in total", vault_names.len()));
+    tracing::debug!(count = vault_names.len(), "Listed vaults");
     Ok(vault_names)
 }
 
 pub async fn create_vault() -> Result<Vault, VaultError> {
     Ok(Vault {
-        metadata: VaultMetadata { peer_id: None },
+        metadata: VaultMetadata {
+            peer_id: None,
+            sync_policy: Default::default(),
+        },
         identity_salts: super::types::IdentitySalts::new(),
-        username_pk: HashMap::new(),
-        namespaces: HashMap::new(),
+        username_pk: BTreeMap::new(),
+        namespaces: BTreeMap::new(),
         sync_enabled: false,
+        observer: false,
+        frozen: false,
+        retention: BTreeMap::new(),
+        residency: BTreeMap::new(),
+        chunks: BTreeMap::new(),
+        max_namespace_bytes: None,
+        manifest: None,
+        compression: Compression::None,
+        journal_sequence: 0,
+        stored_digests: BTreeMap::new(),
     })
 }
 
 pub async fn create_vault_from_sync(
     metadata: Option<VaultMetadata>,
     identity_salts: Option<super::types::IdentitySalts>,
-    username_pk: Option<HashMap<String, String>>,
+    username_pk: Option<BTreeMap<String, String>>,
 ) -> Result<Vault, VaultError> {
     let metadata = metadata.ok_or_else(|| {
         VaultError::io_error("Missing vault metadata in sync message for new vault")
@@ -147,14 +536,199 @@ pub async fn create_vault_from_sync(
         metadata,
         identity_salts: identity_salts.unwrap_or_default(),
         username_pk: username_pk.unwrap_or_default(),
-        namespaces: HashMap::new(),
+        namespaces: BTreeMap::new(),
+        sync_enabled: true,
+        observer: false,
+        frozen: false,
+        retention: BTreeMap::new(),
+        residency: BTreeMap::new(),
+        chunks: BTreeMap::new(),
+        max_namespace_bytes: None,
+        manifest: None,
+        compression: Compression::None,
+        journal_sequence: 0,
+        stored_digests: BTreeMap::new(),
+    })
+}
+
+pub async fn create_observer_vault() -> Result<Vault, VaultError> {
+    Ok(Vault {
+        metadata: VaultMetadata {
+            peer_id: None,
+            sync_policy: Default::default(),
+        },
+        identity_salts: super::types::IdentitySalts::new(),
+        username_pk: BTreeMap::new(),
+        namespaces: BTreeMap::new(),
         sync_enabled: true,
+        observer: true,
+        frozen: false,
+        retention: BTreeMap::new(),
+        residency: BTreeMap::new(),
+        chunks: BTreeMap::new(),
+        max_namespace_bytes: None,
+        manifest: None,
+        compression: Compression::None,
+        journal_sequence: 0,
+        stored_digests: BTreeMap::new(),
     })
 }
 
+/// Deletes every file of `vault_name`. Refused while the vault is frozen or
+/// holds a namespace under retention.
+#[tracing::instrument(skip_all, fields(vault = vault_name))]
 pub async fn delete_vault(platform: &Platform, vault_name: &str) -> Result<(), VaultError> {
+    if let Ok(vault) = read_vault_metadata(platform, vault_name).await {
+        if vault.frozen {
+            return Err(VaultError::FrozenVault);
+        }
+        super::retention::ensure_vault_removable(&vault, current_timestamp(platform))?;
+    }
+
+    let storage = platform.storage();
+    storage.delete_directory(vault_name).await?;
+    Ok(())
+}
+
+/// Moves `vault_name` to `new_name`. The vault keeps its sync peer id.
+/// Watches follow it to the new name, while [`VaultHandle`](super::VaultHandle)s
+/// opened on the old one turn stale. Refused while the vault is frozen.
+///
+/// The files are moved to a name no vault can have first, then to
+/// `new_name`, so `new_name` only appears once complete and a failure
+/// leaves `vault_name` as it was.
+#[tracing::instrument(skip_all, fields(vault = vault_name, new_name = new_name))]
+pub async fn rename_vault(
+    platform: &Platform,
+    vault_name: &str,
+    new_name: &str,
+) -> Result<(), VaultError> {
+    let _guards = lock_vault_pair(platform, vault_name, new_name).await?;
+    let vault = read_vault_metadata(platform, vault_name).await?;
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
     let storage = platform.storage();
+    let staging = format!("{RENAME_STAGING_PREFIX}{new_name}");
+    // Left by a rename that was interrupted.
+    if storage.directory_exists(&staging).await? {
+        storage.delete_directory(&staging).await?;
+    }
+
+    let moved = match copy_vault_directory(platform, vault_name, &staging, &[]).await {
+        Ok(()) => storage.rename_directory(&staging, new_name).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = moved {
+        for partial in [staging.as_str(), new_name] {
+            if storage.directory_exists(partial).await.unwrap_or(false) {
+                let _ = storage.delete_directory(partial).await;
+            }
+        }
+        return Err(e);
+    }
     storage.delete_directory(vault_name).await?;
+
+    super::watch::rename_watches(vault_name, new_name);
+    super::external_changes::record_external_change(vault_name);
+    Ok(())
+}
+
+/// Copies `vault_name` to `new_name`. The copy is a vault of its own: it
+/// leaves sync off, forgets the peer id and CRDT replica id of the source,
+/// and does not inherit its pending tasks.
+#[tracing::instrument(skip_all, fields(vault = vault_name, new_name = new_name))]
+pub async fn clone_vault(
+    platform: &Platform,
+    vault_name: &str,
+    new_name: &str,
+) -> Result<(), VaultError> {
+    let _guards = lock_vault_pair(platform, vault_name, new_name).await?;
+    read_vault_metadata(platform, vault_name).await?;
+
+    copy_vault_directory(
+        platform,
+        vault_name,
+        new_name,
+        &[
+            super::crdt::REPLICA_ID_FILENAME,
+            super::outbox::OUTBOX_FILENAME,
+        ],
+    )
+    .await?;
+
+    let mut vault = read_vault(platform, new_name).await?;
+    vault.metadata.peer_id = None;
+    vault.sync_enabled = false;
+    write_vault(platform, new_name, vault, Vec::new()).await
+}
+
+/// Locks both vaults, in name order so that two moves between the same
+/// vaults do not wait on each other, and checks that `new_name` is free.
+/// [`lock_vault`] completes the interrupted save of `vault_name` first, as
+/// journals name the files they write by path, under the old name.
+async fn lock_vault_pair(
+    platform: &Platform,
+    vault_name: &str,
+    new_name: &str,
+) -> Result<[Box<dyn LockGuard>; 2], VaultError> {
+    super::validation::validate_vault_name(new_name)?;
+    if vault_name == new_name {
+        return Err(VaultError::VaultAlreadyExists);
+    }
+
+    let (first, second) = if vault_name < new_name {
+        (vault_name, new_name)
+    } else {
+        (new_name, vault_name)
+    };
+    let guards = [
+        lock_vault(platform, first).await?,
+        lock_vault(platform, second).await?,
+    ];
+
+    let storage = platform.storage();
+    if !storage.directory_exists(vault_name).await? {
+        return Err(VaultError::VaultNotFound);
+    }
+    if storage.directory_exists(new_name).await? {
+        return Err(VaultError::VaultAlreadyExists);
+    }
+
+    Ok(guards)
+}
+
+/// Copies every file under `from` to `to`, except the top-level entries
+/// named in `skip`.
+async fn copy_vault_directory(
+    platform: &Platform,
+    from: &str,
+    to: &str,
+    skip: &[&str],
+) -> Result<(), VaultError> {
+    let storage = platform.storage();
+    let mut directories = vec![(from.to_string(), to.to_string(), skip)];
+
+    while let Some((from, to, skip)) = directories.pop() {
+        storage.create_directory(&to).await?;
+
+        for entry in storage.list_entries(&from).await? {
+            if skip.contains(&entry.as_str()) {
+                continue;
+            }
+            let source = format!("{from}/{entry}");
+            let target = format!("{to}/{entry}");
+
+            if storage.directory_exists(&source).await? {
+                directories.push((source, target, &[]));
+            } else {
+                let content = storage.read_file(&source).await?;
+                storage.write_file(&target, &content).await?;
+            }
+        }
+    }
+
     Ok(())
 }
 
@@ -170,6 +744,20 @@ pub async fn delete_namespace_file(
     storage.delete_file(&namespace_path).await
 }
 
+/// How [`upsert_namespace_with`] writes a namespace.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UpsertOptions {
+    pub expires_in_seconds: Option<i64>,
+    pub replace_if_exists: bool,
+    /// Compression of the payload; the vault default when `None`.
+    pub compression: Option<Compression>,
+}
+
+/// Encrypts `data` into `namespace`. Payloads above
+/// [`chunks::split_threshold`] are written as several chunk files; like
+/// those of [`upsert_namespace_deduplicated`], the chunks of a replaced
+/// version stay on disk until the next whole-vault write.
 pub async fn upsert_namespace(
     platform: &Platform,
     vault_name: &str,
@@ -179,114 +767,531 @@ pub async fn upsert_namespace(
     expires_in_seconds: Option<i64>,
     replace_if_exists: bool,
 ) -> Result<(), VaultError> {
+    upsert_namespace_with(
+        platform,
+        vault_name,
+        identity_public_key,
+        namespace,
+        data,
+        UpsertOptions {
+            expires_in_seconds,
+            replace_if_exists,
+            compression: None,
+        },
+    )
+    .await
+}
+
+/// Same as [`upsert_namespace`], with the compression chosen per write.
+#[tracing::instrument(skip_all, fields(vault = vault_name, namespace = namespace))]
+pub async fn upsert_namespace_with(
+    platform: &Platform,
+    vault_name: &str,
+    identity_public_key: &str,
+    namespace: &str,
+    data: Vec<u8>,
+    options: UpsertOptions,
+) -> Result<(), VaultError> {
+    let UpsertOptions {
+        expires_in_seconds,
+        replace_if_exists,
+        compression,
+    } = options;
+    let started = platform.clock().now();
+    let _guards = lock_namespace(platform, vault_name, namespace).await?;
     let mut vault = read_vault(platform, vault_name).await?;
 
+    if vault.observer {
+        return Err(VaultError::ObserverVault);
+    }
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
     if vault.namespaces.contains_key(namespace) && !replace_if_exists {
         return Err(VaultError::NamespaceAlreadyExists);
     }
-
-    let encrypted_data =
-        crate::domain::crypto::encrypt_for_recipients(platform, &data, &[identity_public_key])
-            .await
-            .map_err(|e| VaultError::io_error(e.to_string()))?;
-
+    ensure_not_recipient(vault.namespaces.get(namespace), identity_public_key)?;
+    super::limits::ensure_within_limit(&vault, data.len())?;
+    super::secret_scan::check_write(platform, vault_name, namespace, &data)?;
+    let data_len = data.len();
+
+    let recipients = granted_recipients(&vault, namespace);
+    let (payload, compression) =
+        super::compression::compress(compression.unwrap_or(vault.compression), &data);
+    let keys = namespace_keys(identity_public_key, &recipients);
+    let (encrypted_data, chunk_ids) =
+        chunks::encrypt_payload(platform, &mut vault, &keys, &payload).await?;
+    let integrity = integrity_tag(platform, identity_public_key, &keys, &data).await?;
+
+    let now = current_timestamp(platform);
     let expiration = expires_in_seconds.map(|secs| Expiration {
-        expires_at: get_current_timestamp() + secs,
+        expires_at: now + secs,
     });
 
     let namespace_data = NamespaceData {
         data: encrypted_data,
         expiration,
+        chunks: chunk_ids,
+        timelock: None,
+        updated_at: Some(now),
+        recipients,
+        crdt: None,
+        integrity: Some(integrity),
+        crypto_version: Some(CURRENT_CRYPTO_VERSION),
+        compression,
     };
 
     vault
         .namespaces
         .insert(namespace.to_string(), namespace_data);
 
-    save_vault(platform, vault_name, vault).await?;
+    write_namespace(platform, vault_name, &vault, namespace).await?;
 
+    usage_stats::record(
+        UsageOperation::Write,
+        data_len,
+        platform.clock().now() - started,
+    );
     Ok(())
 }
 
+/// Like [`upsert_namespace`], but stores `data` in the vault's
+/// content-addressed chunk store so chunks shared with other namespaces or
+/// earlier versions are only kept once.
+///
+/// Chunks a replaced version no longer needs stay on disk until the next
+/// whole-vault write, such as [`remove_namespace`] or
+/// [`collect_vault_garbage`], collects them.
+#[tracing::instrument(skip_all, fields(vault = vault_name, namespace = namespace))]
+pub async fn upsert_namespace_deduplicated(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    namespace: &str,
+    data: Vec<u8>,
+    expires_in_seconds: Option<i64>,
+    replace_if_exists: bool,
+) -> Result<(), VaultError> {
+    let started = platform.clock().now();
+    let _guards = lock_namespace(platform, vault_name, namespace).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.observer {
+        return Err(VaultError::ObserverVault);
+    }
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    if vault.namespaces.contains_key(namespace) && !replace_if_exists {
+        return Err(VaultError::NamespaceAlreadyExists);
+    }
+    super::limits::ensure_within_limit(&vault, data.len())?;
+    super::secret_scan::check_write(platform, vault_name, namespace, &data)?;
+    let data_len = data.len();
+
+    // Deduplicated chunks may be shared with other namespaces, so a
+    // namespace with its own recipients gets chunks of its own instead.
+    // Payloads are left uncompressed: a small edit changes the whole
+    // compressed stream, leaving no chunks to share.
+    let identity_public_key =
+        crate::domain::crypto::identity_to_public(platform, identity_private_key)
+            .map_err(|_| VaultError::InvalidPassword)?;
+    ensure_not_recipient(vault.namespaces.get(namespace), &identity_public_key)?;
+    let recipients = granted_recipients(&vault, namespace);
+    let keys = namespace_keys(&identity_public_key, &recipients);
+    let (encrypted_data, chunk_ids) = if recipients.is_empty() {
+        let chunk_ids =
+            chunks::store_chunks(platform, &mut vault, identity_private_key, &data).await?;
+        (Vec::new(), chunk_ids)
+    } else {
+        chunks::encrypt_payload(platform, &mut vault, &keys, &data).await?
+    };
+    let integrity = integrity_tag(platform, &identity_public_key, &keys, &data).await?;
+
+    let now = current_timestamp(platform);
+    let expiration = expires_in_seconds.map(|secs| Expiration {
+        expires_at: now + secs,
+    });
+
+    vault.namespaces.insert(
+        namespace.to_string(),
+        NamespaceData {
+            data: encrypted_data,
+            expiration,
+            chunks: chunk_ids,
+            timelock: None,
+            updated_at: Some(now),
+            recipients,
+            crdt: None,
+            integrity: Some(integrity),
+            crypto_version: Some(CURRENT_CRYPTO_VERSION),
+            compression: Compression::None,
+        },
+    );
+
+    write_namespace(platform, vault_name, &vault, namespace).await?;
+
+    usage_stats::record(
+        UsageOperation::Write,
+        data_len,
+        platform.clock().now() - started,
+    );
+    Ok(())
+}
+
+/// Recipients granted access to `namespace` alone, kept across rewrites.
+fn granted_recipients(vault: &Vault, namespace: &str) -> Vec<String> {
+    vault
+        .namespaces
+        .get(namespace)
+        .map(|namespace_data| namespace_data.recipients.clone())
+        .unwrap_or_default()
+}
+
+/// Fails when `writer_public_key` was only granted read access to
+/// `namespace_data`. Its writes would encrypt the namespace to itself
+/// instead of the namespace's writer, who would lose access.
+pub(super) fn ensure_not_recipient(
+    namespace_data: Option<&NamespaceData>,
+    writer_public_key: &str,
+) -> Result<(), VaultError> {
+    match namespace_data {
+        Some(namespace_data)
+            if namespace_data
+                .recipients
+                .iter()
+                .any(|key| key == writer_public_key) =>
+        {
+            Err(VaultError::ReadOnlyRecipient)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Keys a namespace payload is encrypted to: its writer's and those of the
+/// recipients granted access to it.
+pub(super) fn namespace_keys<'a>(
+    identity_public_key: &'a str,
+    recipients: &'a [String],
+) -> Vec<&'a str> {
+    std::iter::once(identity_public_key)
+        .chain(recipients.iter().map(String::as_str))
+        .collect()
+}
+
+/// Removes chunks no longer referenced by any namespace and returns how many
+/// were dropped.
+pub async fn collect_vault_garbage(
+    platform: &Platform,
+    vault_name: &str,
+) -> Result<usize, VaultError> {
+    let _guard = lock_vault(platform, vault_name).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    let removed = chunks::collect_garbage(&mut vault);
+    if removed > 0 {
+        write_vault(platform, vault_name, vault, Vec::new()).await?;
+    }
+
+    Ok(removed)
+}
+
+#[tracing::instrument(skip_all, fields(vault = vault_name, namespace = namespace))]
 pub async fn read_namespace(
     platform: &Platform,
     vault_name: &str,
     identity_private_key: &str,
     namespace: &str,
 ) -> Result<Vec<u8>, VaultError> {
+    let started = platform.clock().now();
     let mut vault = read_vault(platform, vault_name).await?;
 
+    if vault.observer {
+        return Err(VaultError::ObserverVault);
+    }
+
     let namespace_data = vault
         .namespaces
         .get(namespace)
         .ok_or(VaultError::NamespaceNotFound)?;
 
-    let now = get_current_timestamp();
+    if let Some(timelock) = &namespace_data.timelock {
+        return Err(VaultError::TimeLocked(timelock.release_at));
+    }
+
+    let now = current_timestamp(platform);
     if let Some(exp_time) = &namespace_data.expiration {
         if now >= exp_time.expires_at {
+            // Frozen vaults and retained namespaces keep expired data.
+            if vault.frozen || super::retention::ensure_removable(&vault, namespace, now).is_err() {
+                return Err(VaultError::DataExpired);
+            }
             vault.namespaces.remove(namespace);
-            save_vault(platform, vault_name, vault).await?;
+            chunks::collect_garbage(&mut vault);
+            let namespace_path = format!("{vault_name}/{}", get_namespace_filename(namespace));
+            let _guard = lock_vault(platform, vault_name).await?;
+            write_vault(platform, vault_name, vault, vec![namespace_path]).await?;
+            super::expiration::notify_namespace_expired(platform, vault_name, namespace);
             return Err(VaultError::DataExpired);
         }
     }
 
-    let decrypted_data = crate::domain::crypto::decrypt_with_identity(
+    let decrypted_data =
+        match chunks::decrypt_namespace(platform, &vault, namespace_data, identity_private_key)
+            .await
+        {
+            Ok(data) => data,
+            Err(VaultError::InvalidPassword) => {
+                super::unlock_attempts::record_unlock_failure(platform, vault_name);
+                return Err(VaultError::InvalidPassword);
+            }
+            Err(e) => return Err(e),
+        };
+    check_integrity(
         platform,
-        &namespace_data.data,
+        namespace,
+        namespace_data,
+        &decrypted_data,
         identity_private_key,
     )
-    .await
-    .map_err(|_| VaultError::InvalidPassword)?;
+    .await?;
+
+    super::unlock_attempts::reset_unlock_failures(vault_name);
+    usage_stats::record(
+        UsageOperation::Read,
+        decrypted_data.len(),
+        platform.clock().now() - started,
+    );
+    super::migration::migrate_after_read(
+        platform,
+        &vault,
+        vault_name,
+        identity_private_key,
+        namespace,
+    )
+    .await;
 
     Ok(decrypted_data)
 }
 
+#[tracing::instrument(skip_all, fields(vault = vault_name, namespace = namespace))]
 pub async fn remove_namespace(
     platform: &Platform,
     vault_name: &str,
     namespace: &str,
 ) -> Result<(), VaultError> {
+    let started = platform.clock().now();
+    let _guard = lock_vault(platform, vault_name).await?;
     let mut vault = read_vault(platform, vault_name).await?;
 
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+    super::retention::ensure_removable(&vault, namespace, current_timestamp(platform))?;
+
     if vault.namespaces.remove(namespace).is_none() {
         return Err(VaultError::NamespaceNotFound);
     }
+    chunks::collect_garbage(&mut vault);
+
+    let namespace_path = format!("{vault_name}/{}", get_namespace_filename(namespace));
+
+    write_vault(platform, vault_name, vault, vec![namespace_path]).await?;
+
+    usage_stats::record(UsageOperation::Remove, 0, platform.clock().now() - started);
+    Ok(())
+}
+
+/// Moves namespace `from` to `to` within `vault_name` without decrypting
+/// it: the payload does not depend on its name. Its residency tags follow
+/// it. Refused for a namespace under retention, as a removal would be.
+#[tracing::instrument(skip_all, fields(vault = vault_name, from = from, to = to))]
+pub async fn rename_namespace(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    from: &str,
+    to: &str,
+) -> Result<(), VaultError> {
+    super::validation::validate_namespace(to)?;
+    let _guard = lock_vault(platform, vault_name).await?;
+    verify_vault_identity(platform, vault_name, identity_private_key).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+    if vault.namespaces.contains_key(to) {
+        return Err(VaultError::NamespaceAlreadyExists);
+    }
+    super::retention::ensure_removable(&vault, from, current_timestamp(platform))?;
+
+    let namespace_data = vault
+        .namespaces
+        .remove(from)
+        .ok_or(VaultError::NamespaceNotFound)?;
+    vault.namespaces.insert(to.to_string(), namespace_data);
+    if let Some(tags) = vault.residency.remove(from) {
+        vault.residency.insert(to.to_string(), tags);
+    }
+
+    let from_path = format!("{vault_name}/{}", get_namespace_filename(from));
+    write_vault(platform, vault_name, vault, vec![from_path]).await
+}
+
+/// Copies `namespace` of `vault_name` into `target_vault`, encrypted to
+/// the caller's identity and keeping what remains of its expiration. The
+/// source is left untouched.
+#[tracing::instrument(skip_all, fields(vault = vault_name, target = target_vault, namespace = namespace))]
+pub async fn copy_namespace(
+    platform: &Platform,
+    vault_name: &str,
+    target_vault: &str,
+    identity_private_key: &str,
+    namespace: &str,
+) -> Result<(), VaultError> {
+    let data = read_namespace(platform, vault_name, identity_private_key, namespace).await?;
+    let expires_in_seconds = read_vault(platform, vault_name)
+        .await?
+        .namespaces
+        .get(namespace)
+        .and_then(|namespace_data| namespace_data.expiration.as_ref())
+        .map(|expiration| (expiration.expires_at - current_timestamp(platform)).max(1));
 
-    delete_namespace_file(platform, vault_name, namespace).await?;
+    let public_key = crate::domain::crypto::identity_to_public(platform, identity_private_key)
+        .map_err(|_| VaultError::InvalidPassword)?;
+
+    upsert_namespace_with(
+        platform,
+        target_vault,
+        &public_key,
+        namespace,
+        data,
+        UpsertOptions {
+            expires_in_seconds,
+            ..UpsertOptions::default()
+        },
+    )
+    .await
+}
+
+pub async fn list_namespaces_in_vault(
+    platform: &Platform,
+    vault_name: &str,
+) -> Result<Vec<String>, VaultError> {
+    let vault = read_vault(platform, vault_name).await?;
+
+    tracing::debug!(count = vault.namespaces.len(), "Listed namespaces");
+
+    let namespaces: Vec<String> = vault.namespaces.keys().cloned().collect();
+
+    Ok(namespaces)
+}
+
+/// Whether `namespace` is `prefix` or lies under it. The empty prefix
+/// covers every namespace.
+fn is_under_prefix(namespace: &str, prefix: &str) -> bool {
+    let prefix = prefix.strip_suffix('/').unwrap_or(prefix);
+    prefix.is_empty()
+        || namespace
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
 
-    save_vault(platform, vault_name, vault).await?;
+/// Namespaces of `vault_name` at or under the path `prefix`: `app/settings`
+/// lists `app/settings` and `app/settings/theme` but not `app/settings2`.
+pub async fn list_namespaces_with_prefix(
+    platform: &Platform,
+    vault_name: &str,
+    prefix: &str,
+) -> Result<Vec<String>, VaultError> {
+    let namespaces = list_namespaces_in_vault(platform, vault_name).await?;
 
-    Ok(())
+    Ok(namespaces
+        .into_iter()
+        .filter(|namespace| is_under_prefix(namespace, prefix))
+        .collect())
 }
 
-pub async fn list_namespaces_in_vault(
+/// Removes every namespace at or under the path `prefix` in a single vault
+/// write and returns their names. Nothing is removed if one of them is
+/// under retention.
+#[tracing::instrument(skip_all, fields(vault = vault_name, prefix = prefix))]
+pub async fn remove_prefix(
     platform: &Platform,
     vault_name: &str,
+    prefix: &str,
 ) -> Result<Vec<String>, VaultError> {
-    let vault = read_vault(platform, vault_name).await?;
+    super::validation::validate_namespace_prefix(prefix)?;
+    let started = platform.clock().now();
+    let _guard = lock_vault(platform, vault_name).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
 
-    platform.logger().log(&format!(
-        "Found {} namespaces in vault",
-        vault.namespaces.len()
-    ));
+    let removed: Vec<String> = vault
+        .namespaces
+        .keys()
+        .filter(|namespace| is_under_prefix(namespace, prefix))
+        .cloned()
+        .collect();
+    if removed.is_empty() {
+        return Ok(removed);
+    }
 
-    let namespaces: Vec<String> = vault.namespaces.keys().cloned().collect();
+    let now = current_timestamp(platform);
+    for namespace in &removed {
+        super::retention::ensure_removable(&vault, namespace, now)?;
+    }
+    for namespace in &removed {
+        vault.namespaces.remove(namespace);
+    }
+    chunks::collect_garbage(&mut vault);
 
-    Ok(namespaces)
+    let deletes = removed
+        .iter()
+        .map(|namespace| format!("{vault_name}/{}", get_namespace_filename(namespace)))
+        .collect();
+    write_vault(platform, vault_name, vault, deletes).await?;
+
+    tracing::debug!(count = removed.len(), "Removed namespaces under prefix");
+    usage_stats::record(UsageOperation::Remove, 0, platform.clock().now() - started);
+    Ok(removed)
 }
 
 pub async fn export_vault_bytes(
     platform: &Platform,
     vault_name: &str,
 ) -> Result<Vec<u8>, VaultError> {
-    let vault = read_vault(platform, vault_name).await?;
+    export_vault_bytes_with_progress(platform, vault_name, &ignore_progress).await
+}
+
+/// Like [`export_vault_bytes`], reporting the vault files read as items.
+#[tracing::instrument(skip_all, fields(vault = vault_name))]
+pub async fn export_vault_bytes_with_progress(
+    platform: &Platform,
+    vault_name: &str,
+    on_progress: &dyn Fn(Progress),
+) -> Result<Vec<u8>, VaultError> {
+    let vault = read_vault_with_progress(platform, vault_name, on_progress).await?;
 
     let vault_bytes = super::serialization::serialize_vault(&vault)?;
 
-    platform.logger().log(&format!(
-        "Exporting vault data: {} bytes",
-        vault_bytes.len()
-    ));
+    tracing::debug!(bytes = vault_bytes.len(), "Exporting vault");
+
+    let _ = platform.notifier().notify_event(
+        vault_name,
+        crate::notifications::EventType::BackupCompleted,
+        &format!("Exported {} bytes", vault_bytes.len()),
+    );
 
     Ok(vault_bytes)
 }
@@ -296,42 +1301,43 @@ pub async fn import_vault_from_bytes(
     vault_name: &str,
     vault_bytes: &[u8],
 ) -> Result<(), VaultError> {
-    platform.logger().log(&format!(
-        "Attempting to import vault data of size: {} bytes",
-        vault_bytes.len()
-    ));
-
-    let imported_vault = super::serialization::deserialize_vault(vault_bytes)?;
-
-    match read_vault(platform, vault_name).await {
-        Ok(_) => {
-            return Err(VaultError::VaultAlreadyExists);
-        }
-        Err(VaultError::IoError(..)) => {
-            platform.logger().log(&format!(
-                "No existing vault named '{vault_name}'; proceeding with import."
-            ));
-        }
-        Err(e) => {
-            return Err(e);
-        }
-    }
+    import_vault_from_bytes_with_progress(platform, vault_name, vault_bytes, &ignore_progress).await
+}
 
-    save_vault(platform, vault_name, imported_vault).await?;
+/// Like [`import_vault_from_bytes`], reporting the bytes written to storage.
+///
+/// Namespaces and chunks are decoded and written one at a time, see
+/// [`super::import`].
+#[tracing::instrument(skip_all, fields(vault = vault_name))]
+pub async fn import_vault_from_bytes_with_progress(
+    platform: &Platform,
+    vault_name: &str,
+    vault_bytes: &[u8],
+    on_progress: &dyn Fn(Progress),
+) -> Result<(), VaultError> {
+    tracing::debug!(bytes = vault_bytes.len(), "Importing vault");
 
-    Ok(())
+    super::import::import_vault(platform, vault_name, vault_bytes, on_progress).await
 }
 
+/// Removes expired namespaces. Frozen vaults are left as they are.
+#[tracing::instrument(skip_all, fields(vault = vault_name))]
 pub async fn cleanup_vault(platform: &Platform, vault_name: &str) -> Result<bool, VaultError> {
+    let _guard = lock_vault(platform, vault_name).await?;
     let mut vault = read_vault(platform, vault_name).await?;
 
-    let now = get_current_timestamp();
+    if vault.frozen {
+        return Ok(false);
+    }
+
+    let now = current_timestamp(platform);
     let data_removed =
         super::expiration::cleanup_expired_namespaces(platform, &mut vault, vault_name, now)
             .await?;
 
     if data_removed {
-        save_vault(platform, vault_name, vault).await?;
+        chunks::collect_garbage(&mut vault);
+        write_vault(platform, vault_name, vault, Vec::new()).await?;
     }
 
     Ok(data_removed)
@@ -344,38 +1350,274 @@ pub async fn verify_vault_identity(
 ) -> Result<(), VaultError> {
     let vault = read_vault(platform, vault_name).await?;
 
-    if let Some((_, namespace_data)) = vault.namespaces.iter().next() {
-        crate::domain::crypto::decrypt_with_identity(
+    if vault.observer {
+        return Err(VaultError::ObserverVault);
+    }
+
+    let Ok(public_key) = crate::domain::crypto::identity_to_public(platform, identity_private_key)
+    else {
+        super::unlock_attempts::record_unlock_failure(platform, vault_name);
+        return Err(VaultError::InvalidPassword);
+    };
+
+    match crate::domain::authentication::verify_key_check(
+        &vault.identity_salts,
+        &public_key,
+        identity_private_key,
+    ) {
+        Some(true) => {
+            super::unlock_attempts::reset_unlock_failures(vault_name);
+            return Ok(());
+        }
+        Some(false) => {
+            super::unlock_attempts::record_unlock_failure(platform, vault_name);
+            return Err(VaultError::InvalidPassword);
+        }
+        // Identities created before key check values fall back to
+        // decrypting a namespace.
+        None => {}
+    }
+
+    let checked = vault
+        .namespaces
+        .values()
+        .find(|namespace_data| namespace_data.timelock.is_none());
+    if let Some(namespace_data) = checked {
+        if chunks::decrypt_namespace(platform, &vault, namespace_data, identity_private_key)
+            .await
+            .is_err()
+        {
+            super::unlock_attempts::record_unlock_failure(platform, vault_name);
+            return Err(VaultError::InvalidPassword);
+        }
+    }
+
+    super::unlock_attempts::reset_unlock_failures(vault_name);
+
+    Ok(())
+}
+
+/// Re-encrypts every namespace so it is readable by both the caller's
+/// identity and `recipient_public_key`. Once synced, an observer replica
+/// holding the matching identity can be promoted.
+#[tracing::instrument(skip_all, fields(vault = vault_name))]
+pub async fn grant_vault_recipient(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    recipient_public_key: &str,
+) -> Result<(), VaultError> {
+    let _guard = lock_vault(platform, vault_name).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.observer {
+        return Err(VaultError::ObserverVault);
+    }
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    let owner_public_key =
+        crate::domain::crypto::identity_to_public(platform, identity_private_key)
+            .map_err(|_| VaultError::InvalidPassword)?;
+    let recipient = crate::domain::crypto::parse_recipient(platform, recipient_public_key)
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+    // Payloads keep the recipients granted access to their namespace.
+    let chunk_recipients: BTreeMap<String, Vec<String>> = vault
+        .namespaces
+        .values()
+        .filter(|namespace_data| !namespace_data.recipients.is_empty())
+        .flat_map(|namespace_data| {
+            namespace_data
+                .chunks
+                .iter()
+                .map(|id| (id.clone(), namespace_data.recipients.clone()))
+        })
+        .collect();
+
+    let mut ciphertexts: Vec<(&mut Vec<u8>, &[String])> = vault
+        .namespaces
+        .values_mut()
+        .filter(|namespace_data| {
+            namespace_data.chunks.is_empty() && namespace_data.timelock.is_none()
+        })
+        .map(|namespace_data| {
+            (
+                &mut namespace_data.data,
+                namespace_data.recipients.as_slice(),
+            )
+        })
+        .chain(vault.chunks.iter_mut().map(|(id, data)| {
+            let granted = chunk_recipients.get(id).map_or(&[][..], Vec::as_slice);
+            (data, granted)
+        }))
+        .collect();
+
+    let payloads: Vec<&[u8]> = ciphertexts
+        .iter()
+        .map(|(data, _)| data.as_slice())
+        .collect();
+    let plaintexts =
+        crate::domain::crypto::decrypt_many(platform, &payloads, identity_private_key).await;
+
+    for ((ciphertext, granted), plaintext) in ciphertexts.iter_mut().zip(plaintexts) {
+        let plaintext = plaintext.map_err(|_| VaultError::InvalidPassword)?;
+
+        let mut recipients = vec![recipient.clone()];
+        recipients.extend(granted.iter().filter(|key| **key != recipient).cloned());
+
+        **ciphertext = crate::domain::crypto::encrypt_for_recipients(
             platform,
-            &namespace_data.data,
-            identity_private_key,
+            &plaintext,
+            &namespace_keys(&owner_public_key, &recipients),
         )
         .await
-        .map_err(|_| VaultError::InvalidPassword)?;
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
     }
 
-    Ok(())
+    // Stored chunks are never rewritten in place, so the re-encrypted ones
+    // move to new ids. Later writes of the same content no longer share
+    // them and store chunks of their own.
+    let renamed: BTreeMap<String, String> = vault
+        .chunks
+        .keys()
+        .map(|id| (id.clone(), chunks::random_chunk_id()))
+        .collect();
+    vault.chunks = std::mem::take(&mut vault.chunks)
+        .into_iter()
+        .filter_map(|(id, encrypted)| Some((renamed.get(&id)?.clone(), encrypted)))
+        .collect();
+    for namespace_data in vault.namespaces.values_mut() {
+        for id in &mut namespace_data.chunks {
+            if let Some(new_id) = renamed.get(id) {
+                id.clone_from(new_id);
+            }
+        }
+    }
+
+    // So the recipient can check what it now decrypts.
+    for namespace_data in vault
+        .namespaces
+        .values_mut()
+        .filter(|namespace_data| namespace_data.timelock.is_none())
+    {
+        let Some(tag) = &mut namespace_data.integrity else {
+            continue;
+        };
+        let mut recipients = vec![recipient.clone()];
+        recipients.extend(
+            namespace_data
+                .recipients
+                .iter()
+                .filter(|key| **key != recipient)
+                .cloned(),
+        );
+        reseal_integrity(
+            platform,
+            tag,
+            identity_private_key,
+            &namespace_keys(&owner_public_key, &recipients),
+        )
+        .await?;
+    }
+
+    write_vault(platform, vault_name, vault, Vec::new()).await
+}
+
+/// Turns an observer replica into a regular vault once `identity_private_key`
+/// is able to decrypt every namespace it holds.
+#[tracing::instrument(skip_all, fields(vault = vault_name))]
+pub async fn promote_observer_vault(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+) -> Result<(), VaultError> {
+    let _guard = lock_vault(platform, vault_name).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if !vault.observer {
+        return Ok(());
+    }
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    let payloads: Vec<&[u8]> = vault
+        .namespaces
+        .values()
+        .filter(|namespace_data| {
+            namespace_data.chunks.is_empty() && namespace_data.timelock.is_none()
+        })
+        .map(|namespace_data| namespace_data.data.as_slice())
+        .chain(vault.chunks.values().map(Vec::as_slice))
+        .collect();
+
+    let decrypted =
+        crate::domain::crypto::decrypt_many(platform, &payloads, identity_private_key).await;
+    if decrypted.iter().any(Result::is_err) {
+        return Err(VaultError::InvalidPassword);
+    }
+
+    vault.observer = false;
+
+    write_vault(platform, vault_name, vault, Vec::new()).await
 }
 
-#[cfg(target_arch = "wasm32")]
-fn get_current_timestamp() -> i64 {
-    (js_sys::Date::now() / 1000.0) as i64
+/// Makes `vault_name` read-only: writes, removals, cleanup and incoming sync
+/// operations are refused until [`unfreeze_vault`]. Only an identity of the
+/// vault may freeze it.
+pub async fn freeze_vault(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+) -> Result<(), VaultError> {
+    set_frozen(platform, vault_name, identity_private_key, true).await
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-fn get_current_timestamp() -> i64 {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs() as i64
+pub async fn unfreeze_vault(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+) -> Result<(), VaultError> {
+    set_frozen(platform, vault_name, identity_private_key, false).await
+}
+
+#[tracing::instrument(skip_all, fields(vault = vault_name, frozen = frozen))]
+async fn set_frozen(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    frozen: bool,
+) -> Result<(), VaultError> {
+    let _guard = lock_vault(platform, vault_name).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    let public_key = crate::domain::crypto::identity_to_public(platform, identity_private_key)
+        .map_err(|_| VaultError::InvalidPassword)?;
+    if vault.identity_salts.get_salt(&public_key).is_none() {
+        return Err(VaultError::InvalidPassword);
+    }
+    verify_vault_identity(platform, vault_name, identity_private_key).await?;
+
+    if vault.frozen == frozen {
+        return Ok(());
+    }
+    vault.frozen = frozen;
+
+    write_vault(platform, vault_name, vault, Vec::new()).await
+}
+
+/// Seconds since the Unix epoch, as used by namespace expirations.
+pub(super) fn current_timestamp(platform: &Platform) -> i64 {
+    (platform.clock().now() / 1000.0) as i64
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::domain::vault::types::{IdentitySalts, Vault, VaultMetadata};
-    use std::collections::HashMap;
+    use std::collections::BTreeMap;
 
     #[test]
     fn test_get_namespace_filename() {
@@ -412,11 +1654,24 @@ mod tests {
     #[test]
     fn test_create_vault_returns_empty_vault() {
         let vault = Vault {
-            metadata: VaultMetadata { peer_id: None },
+            metadata: VaultMetadata {
+                peer_id: None,
+                sync_policy: Default::default(),
+            },
             identity_salts: IdentitySalts::new(),
-            username_pk: HashMap::new(),
-            namespaces: HashMap::new(),
+            username_pk: BTreeMap::new(),
+            namespaces: BTreeMap::new(),
             sync_enabled: false,
+            observer: false,
+            frozen: false,
+            retention: BTreeMap::new(),
+            residency: BTreeMap::new(),
+            chunks: BTreeMap::new(),
+            max_namespace_bytes: None,
+            manifest: None,
+            compression: Compression::None,
+            journal_sequence: 0,
+            stored_digests: BTreeMap::new(),
         };
 
         assert!(vault.metadata.peer_id.is_none());
@@ -429,16 +1684,27 @@ mod tests {
     fn test_create_vault_from_sync_with_all_params() {
         let metadata = VaultMetadata {
             peer_id: Some("test-peer-id".to_string()),
+            sync_policy: Default::default(),
         };
-        let mut username_pk = HashMap::new();
+        let mut username_pk = BTreeMap::new();
         username_pk.insert("user1".to_string(), "pk1".to_string());
 
         let vault = Vault {
             metadata: metadata.clone(),
             identity_salts: IdentitySalts::new(),
             username_pk: username_pk.clone(),
-            namespaces: HashMap::new(),
+            namespaces: BTreeMap::new(),
             sync_enabled: true,
+            observer: false,
+            frozen: false,
+            retention: BTreeMap::new(),
+            residency: BTreeMap::new(),
+            chunks: BTreeMap::new(),
+            max_namespace_bytes: None,
+            manifest: None,
+            compression: Compression::None,
+            journal_sequence: 0,
+            stored_digests: BTreeMap::new(),
         };
 
         assert_eq!(vault.metadata.peer_id, Some("test-peer-id".to_string()));
@@ -456,14 +1722,27 @@ mod tests {
 
     #[test]
     fn test_create_vault_from_sync_with_defaults() {
-        let metadata = VaultMetadata { peer_id: None };
+        let metadata = VaultMetadata {
+            peer_id: None,
+            sync_policy: Default::default(),
+        };
 
         let vault = Vault {
             metadata,
             identity_salts: IdentitySalts::new(),
-            username_pk: HashMap::new(),
-            namespaces: HashMap::new(),
+            username_pk: BTreeMap::new(),
+            namespaces: BTreeMap::new(),
             sync_enabled: true,
+            observer: false,
+            frozen: false,
+            retention: BTreeMap::new(),
+            residency: BTreeMap::new(),
+            chunks: BTreeMap::new(),
+            max_namespace_bytes: None,
+            manifest: None,
+            compression: Compression::None,
+            journal_sequence: 0,
+            stored_digests: BTreeMap::new(),
         };
 
         assert!(vault.metadata.peer_id.is_none());
@@ -476,20 +1755,49 @@ mod tests {
     fn test_create_vault_from_sync_with_peer_id() {
         let metadata = VaultMetadata {
             peer_id: Some("sync-peer-123".to_string()),
+            sync_policy: Default::default(),
         };
 
         let vault = Vault {
             metadata: metadata.clone(),
             identity_salts: IdentitySalts::new(),
-            username_pk: HashMap::new(),
-            namespaces: HashMap::new(),
+            username_pk: BTreeMap::new(),
+            namespaces: BTreeMap::new(),
             sync_enabled: true,
+            observer: false,
+            frozen: false,
+            retention: BTreeMap::new(),
+            residency: BTreeMap::new(),
+            chunks: BTreeMap::new(),
+            max_namespace_bytes: None,
+            manifest: None,
+            compression: Compression::None,
+            journal_sequence: 0,
+            stored_digests: BTreeMap::new(),
         };
 
         assert_eq!(vault.metadata.peer_id, Some("sync-peer-123".to_string()));
         assert!(vault.sync_enabled);
     }
 
+    #[test]
+    fn test_create_observer_vault() {
+        let vault = futures::executor::block_on(create_observer_vault()).unwrap();
+
+        assert!(vault.observer);
+        assert!(vault.sync_enabled);
+        assert!(vault.namespaces.is_empty());
+    }
+
+    #[test]
+    fn test_observer_flag_defaults_to_false_for_existing_metadata() {
+        let json = r#"{"metadata":{"peer_id":null},"identity_salts":{"salts":{},"credential_ids":{}},"username_pk":{},"namespaces":{},"sync_enabled":false}"#;
+
+        let vault: Vault = serde_json::from_str(json).unwrap();
+
+        assert!(!vault.observer);
+    }
+
     #[test]
     fn test_delete_namespace_file_constructs_correct_path() {
         let vault_name = "test_vault";
@@ -591,4 +1899,658 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_migrate_vault_files_upgrades_legacy_namespaces() {
+        use futures::executor::block_on;
+
+        let platform = Platform::new();
+        let vault_name = "legacy_migration_test";
+
+        block_on(async {
+            let vault = create_vault().await.unwrap();
+            save_vault(&platform, vault_name, vault).await.unwrap();
+
+            let legacy = NamespaceData {
+                data: vec![1, 2, 3],
+                expiration: None,
+                chunks: Vec::new(),
+                timelock: None,
+                updated_at: None,
+                recipients: Vec::new(),
+                crdt: None,
+                integrity: None,
+                crypto_version: None,
+                compression: Compression::None,
+            };
+            platform
+                .storage()
+                .write_file(
+                    &format!("{vault_name}/notes.ns"),
+                    &serde_json::to_string(&legacy).unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(migrate_vault_files(&platform, vault_name).await.unwrap(), 1);
+            assert_eq!(migrate_vault_files(&platform, vault_name).await.unwrap(), 0);
+
+            let entries = platform.storage().list_entries(vault_name).await.unwrap();
+            assert!(entries.contains(&"notes.hoddor".to_string()));
+            assert!(!entries.contains(&"notes.ns".to_string()));
+
+            let vault = read_vault(&platform, vault_name).await.unwrap();
+            assert_eq!(vault.namespaces["notes"].data, vec![1, 2, 3]);
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_save_only_rewrites_changed_namespaces() {
+        use futures::executor::block_on;
+
+        let platform = Platform::new();
+        let vault_name = "changed_namespaces_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            let mut vault = create_vault().await.unwrap();
+            for (namespace, byte) in [("kept", 1), ("changed", 2)] {
+                vault.namespaces.insert(
+                    namespace.to_string(),
+                    NamespaceData {
+                        data: vec![byte],
+                        expiration: None,
+                        chunks: Vec::new(),
+                        timelock: None,
+                        updated_at: None,
+                        recipients: Vec::new(),
+                        crdt: None,
+                        integrity: None,
+                        crypto_version: None,
+                        compression: Compression::None,
+                    },
+                );
+            }
+            save_vault(&platform, vault_name, vault).await.unwrap();
+
+            let mut vault = read_vault(&platform, vault_name).await.unwrap();
+            vault.namespaces.get_mut("changed").unwrap().data = vec![3];
+
+            // Stands in for a file the save must not touch.
+            let kept_path = format!("{vault_name}/kept.hoddor");
+            platform
+                .storage()
+                .write_file(&kept_path, "untouched")
+                .await
+                .unwrap();
+
+            save_vault(&platform, vault_name, vault).await.unwrap();
+
+            assert_eq!(
+                platform.storage().read_file(&kept_path).await.unwrap(),
+                "untouched"
+            );
+            let changed = platform
+                .storage()
+                .read_file(&format!("{vault_name}/changed.hoddor"))
+                .await
+                .unwrap();
+            assert_eq!(
+                decode_stored::<NamespaceData>(&changed).unwrap().data,
+                vec![3]
+            );
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_export_and_import_report_progress() {
+        use crate::domain::progress::ProgressUnit;
+        use futures::executor::block_on;
+        use std::cell::RefCell;
+
+        let platform = Platform::new();
+        let vault_name = "progress_export_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+
+            let mut vault = create_vault().await.unwrap();
+            for namespace in ["first", "second"] {
+                vault.namespaces.insert(
+                    namespace.to_string(),
+                    NamespaceData {
+                        data: vec![1, 2, 3],
+                        expiration: None,
+                        chunks: Vec::new(),
+                        timelock: None,
+                        updated_at: None,
+                        recipients: Vec::new(),
+                        crdt: None,
+                        integrity: None,
+                        crypto_version: None,
+                        compression: Compression::None,
+                    },
+                );
+            }
+            save_vault(&platform, vault_name, vault).await.unwrap();
+
+            let reports = RefCell::new(Vec::new());
+            let bytes = export_vault_bytes_with_progress(&platform, vault_name, &|progress| {
+                reports.borrow_mut().push(progress)
+            })
+            .await
+            .unwrap();
+
+            assert_eq!(
+                reports.take(),
+                vec![Progress::items(1, 2), Progress::items(2, 2)]
+            );
+
+            delete_vault(&platform, vault_name).await.unwrap();
+
+            import_vault_from_bytes_with_progress(&platform, vault_name, &bytes, &|progress| {
+                reports.borrow_mut().push(progress)
+            })
+            .await
+            .unwrap();
+
+            let reports = reports.take();
+            let last = reports.last().unwrap();
+            assert_eq!(reports.len(), 3);
+            assert_eq!(last.unit, ProgressUnit::Bytes);
+            assert_eq!(last.processed, last.total);
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_expiration_follows_platform_clock() {
+        use crate::ports::ClockPort;
+        use futures::executor::block_on;
+
+        struct FixedClock(f64);
+
+        #[async_trait::async_trait(?Send)]
+        impl ClockPort for FixedClock {
+            fn now(&self) -> f64 {
+                self.0
+            }
+
+            fn is_available(&self) -> bool {
+                true
+            }
+
+            async fn sleep(&self, _milliseconds: u32) {}
+        }
+
+        static WRITTEN_AT: FixedClock = FixedClock(1_700_000_000_000.0);
+        static MINUTE_LATER: FixedClock = FixedClock(1_700_000_060_000.0);
+
+        let platform = Platform::new().with_clock(&WRITTEN_AT);
+        let vault_name = "clock_expiration_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+            upsert_namespace(
+                &platform,
+                vault_name,
+                &public_key,
+                "session",
+                vec![1, 2, 3],
+                Some(30),
+                false,
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(
+                read_namespace(&platform, vault_name, &identity, "session")
+                    .await
+                    .unwrap(),
+                vec![1, 2, 3]
+            );
+
+            let later = Platform::new().with_clock(&MINUTE_LATER);
+            assert!(matches!(
+                read_namespace(&later, vault_name, &identity, "session").await,
+                Err(VaultError::DataExpired)
+            ));
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_frozen_vault_refuses_changes_until_unfrozen() {
+        use futures::executor::block_on;
+
+        let platform = Platform::new();
+        let vault_name = "frozen_vault_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+            let outsider = crate::domain::crypto::generate_identity(&platform).unwrap();
+
+            let mut vault = create_vault().await.unwrap();
+            vault.identity_salts.set_salt(public_key.clone(), [0u8; 32]);
+            save_vault(&platform, vault_name, vault).await.unwrap();
+            upsert_namespace(
+                &platform,
+                vault_name,
+                &public_key,
+                "evidence",
+                vec![1],
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+
+            assert!(matches!(
+                freeze_vault(&platform, vault_name, &outsider).await,
+                Err(VaultError::InvalidPassword)
+            ));
+            freeze_vault(&platform, vault_name, &identity)
+                .await
+                .unwrap();
+
+            assert!(matches!(
+                upsert_namespace(
+                    &platform,
+                    vault_name,
+                    &public_key,
+                    "evidence",
+                    vec![2],
+                    None,
+                    true
+                )
+                .await,
+                Err(VaultError::FrozenVault)
+            ));
+            assert!(matches!(
+                remove_namespace(&platform, vault_name, "evidence").await,
+                Err(VaultError::FrozenVault)
+            ));
+            assert!(matches!(
+                delete_vault(&platform, vault_name).await,
+                Err(VaultError::FrozenVault)
+            ));
+            assert_eq!(
+                read_namespace(&platform, vault_name, &identity, "evidence")
+                    .await
+                    .unwrap(),
+                vec![1]
+            );
+
+            unfreeze_vault(&platform, vault_name, &identity)
+                .await
+                .unwrap();
+            remove_namespace(&platform, vault_name, "evidence")
+                .await
+                .unwrap();
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_granted_recipient_reads_every_namespace_from_storage() {
+        use futures::executor::block_on;
+
+        let platform = Platform::new();
+        let vault_name = "grant_vault_recipient_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+
+            let owner = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let owner_public =
+                crate::domain::crypto::identity_to_public(&platform, &owner).unwrap();
+            let device = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let device_public =
+                crate::domain::crypto::identity_to_public(&platform, &device).unwrap();
+
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+            upsert_namespace(
+                &platform,
+                vault_name,
+                &owner_public,
+                "inline",
+                b"inline".to_vec(),
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+            let document = vec![5u8; chunks::CHUNK_SIZE + 1];
+            upsert_namespace_deduplicated(
+                &platform,
+                vault_name,
+                &owner,
+                "chunked",
+                document.clone(),
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+
+            grant_vault_recipient(&platform, vault_name, &owner, &device_public)
+                .await
+                .unwrap();
+
+            assert_eq!(
+                read_namespace(&platform, vault_name, &device, "inline")
+                    .await
+                    .unwrap(),
+                b"inline"
+            );
+            assert_eq!(
+                read_namespace(&platform, vault_name, &device, "chunked")
+                    .await
+                    .unwrap(),
+                document
+            );
+
+            let mut vault = read_vault(&platform, vault_name).await.unwrap();
+            vault.observer = true;
+            save_vault(&platform, vault_name, vault).await.unwrap();
+            promote_observer_vault(&platform, vault_name, &device)
+                .await
+                .unwrap();
+            assert!(!read_vault(&platform, vault_name).await.unwrap().observer);
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_clone_and_rename_vault() {
+        use futures::executor::block_on;
+
+        let platform = Platform::new();
+        let (source, copy, renamed) =
+            ("clone_source_test", "clone_copy_test", "clone_renamed_test");
+
+        block_on(async {
+            for vault_name in [source, copy, renamed] {
+                let _ = delete_vault(&platform, vault_name).await;
+            }
+
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+
+            let mut vault = create_vault().await.unwrap();
+            vault.identity_salts.set_salt(public_key.clone(), [0u8; 32]);
+            vault.metadata.peer_id = Some("peer".to_string());
+            vault.sync_enabled = true;
+            save_vault(&platform, source, vault).await.unwrap();
+            upsert_namespace(
+                &platform,
+                source,
+                &public_key,
+                "notes",
+                vec![7],
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+            platform
+                .storage()
+                .write_file(
+                    &format!("{source}/{}", crate::domain::vault::outbox::OUTBOX_FILENAME),
+                    "[]",
+                )
+                .await
+                .unwrap();
+
+            clone_vault(&platform, source, copy).await.unwrap();
+            assert!(matches!(
+                clone_vault(&platform, source, copy).await,
+                Err(VaultError::VaultAlreadyExists)
+            ));
+            let cloned = read_vault_metadata(&platform, copy).await.unwrap();
+            assert!(cloned.metadata.peer_id.is_none());
+            assert!(!cloned.sync_enabled);
+            let entries = platform.storage().list_entries(copy).await.unwrap();
+            assert!(!entries.contains(&crate::domain::vault::outbox::OUTBOX_FILENAME.to_string()));
+            assert_eq!(
+                read_namespace(&platform, copy, &identity, "notes")
+                    .await
+                    .unwrap(),
+                vec![7]
+            );
+
+            rename_vault(&platform, source, renamed).await.unwrap();
+            assert!(matches!(
+                rename_vault(&platform, source, renamed).await,
+                Err(VaultError::VaultNotFound)
+            ));
+            let moved = read_vault_metadata(&platform, renamed).await.unwrap();
+            assert_eq!(moved.metadata.peer_id.as_deref(), Some("peer"));
+            assert_eq!(
+                read_namespace(&platform, renamed, &identity, "notes")
+                    .await
+                    .unwrap(),
+                vec![7]
+            );
+
+            delete_vault(&platform, copy).await.unwrap();
+            delete_vault(&platform, renamed).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_failed_rename_leaves_no_partial_vault() {
+        use futures::executor::block_on;
+
+        let platform = Platform::new();
+        let (source, renamed) = ("rename_failure_source_test", "rename_failure_target_test");
+        let staging = format!("{RENAME_STAGING_PREFIX}{renamed}");
+        let root = std::path::Path::new(crate::adapters::native::FsStorage::new().root_path());
+
+        block_on(async {
+            for vault_name in [source, renamed, staging.as_str()] {
+                let _ = platform.storage().delete_directory(vault_name).await;
+            }
+            save_vault(&platform, source, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            // Left by an interrupted rename: replaced, and never listed.
+            platform.storage().create_directory(&staging).await.unwrap();
+            assert!(!list_vaults(&platform).await.unwrap().contains(&staging));
+
+            // A file storage cannot read makes the copy fail halfway.
+            std::fs::write(root.join(source).join("unreadable"), [0xff, 0xfe]).unwrap();
+            assert!(rename_vault(&platform, source, renamed).await.is_err());
+
+            let storage = platform.storage();
+            assert!(!storage.directory_exists(renamed).await.unwrap());
+            assert!(!storage.directory_exists(&staging).await.unwrap());
+            assert!(read_vault_metadata(&platform, source).await.is_ok());
+
+            std::fs::remove_file(root.join(source).join("unreadable")).unwrap();
+            rename_vault(&platform, source, renamed).await.unwrap();
+            assert!(!storage.directory_exists(source).await.unwrap());
+            assert!(!storage.directory_exists(&staging).await.unwrap());
+            assert!(read_vault_metadata(&platform, renamed).await.is_ok());
+
+            delete_vault(&platform, renamed).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_hierarchical_namespaces() {
+        use futures::executor::block_on;
+
+        assert_eq!(
+            get_namespace_filename("app/100%/theme"),
+            "app%2F100%25%2Ftheme.hoddor"
+        );
+        assert_eq!(
+            namespace_from_filename("app%2F100%25%2Ftheme.hoddor").as_deref(),
+            Some("app/100%/theme")
+        );
+        assert_eq!(
+            namespace_from_filename("app%252F.hoddor").as_deref(),
+            Some("app%2F")
+        );
+
+        let platform = Platform::new();
+        let vault_name = "hierarchical_namespaces_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            for namespace in [
+                "app/settings",
+                "app/settings/theme",
+                "app/settings2",
+                "other",
+            ] {
+                upsert_namespace(
+                    &platform,
+                    vault_name,
+                    &public_key,
+                    namespace,
+                    namespace.as_bytes().to_vec(),
+                    None,
+                    false,
+                )
+                .await
+                .unwrap();
+            }
+
+            assert_eq!(
+                list_namespaces_with_prefix(&platform, vault_name, "app/settings/")
+                    .await
+                    .unwrap(),
+                ["app/settings", "app/settings/theme"]
+            );
+            assert_eq!(
+                read_namespace(&platform, vault_name, &identity, "app/settings/theme")
+                    .await
+                    .unwrap(),
+                b"app/settings/theme"
+            );
+
+            assert_eq!(
+                remove_prefix(&platform, vault_name, "app/settings")
+                    .await
+                    .unwrap(),
+                ["app/settings", "app/settings/theme"]
+            );
+            assert_eq!(
+                list_namespaces_with_prefix(&platform, vault_name, "")
+                    .await
+                    .unwrap(),
+                ["app/settings2", "other"]
+            );
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_rename_and_copy_namespace() {
+        use futures::executor::block_on;
+
+        let platform = Platform::new();
+        let (source, target) = ("namespace_move_source_test", "namespace_move_target_test");
+
+        block_on(async {
+            let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+
+            for vault_name in [source, target] {
+                let _ = delete_vault(&platform, vault_name).await;
+                let mut vault = create_vault().await.unwrap();
+                vault.identity_salts.set_salt(public_key.clone(), [0u8; 32]);
+                save_vault(&platform, vault_name, vault).await.unwrap();
+            }
+            for namespace in ["drafts", "notes"] {
+                upsert_namespace(
+                    &platform,
+                    source,
+                    &public_key,
+                    namespace,
+                    namespace.as_bytes().to_vec(),
+                    None,
+                    false,
+                )
+                .await
+                .unwrap();
+            }
+
+            assert!(matches!(
+                rename_namespace(&platform, source, &identity, "drafts", "notes").await,
+                Err(VaultError::NamespaceAlreadyExists)
+            ));
+            assert!(matches!(
+                rename_namespace(&platform, source, &identity, "missing", "other").await,
+                Err(VaultError::NamespaceNotFound)
+            ));
+            rename_namespace(&platform, source, &identity, "drafts", "archive")
+                .await
+                .unwrap();
+            assert_eq!(
+                list_namespaces_in_vault(&platform, source).await.unwrap(),
+                ["archive", "notes"]
+            );
+            assert_eq!(
+                read_namespace(&platform, source, &identity, "archive")
+                    .await
+                    .unwrap(),
+                b"drafts"
+            );
+
+            copy_namespace(&platform, source, target, &identity, "notes")
+                .await
+                .unwrap();
+            assert!(matches!(
+                copy_namespace(&platform, source, target, &identity, "notes").await,
+                Err(VaultError::NamespaceAlreadyExists)
+            ));
+            for vault_name in [source, target] {
+                assert_eq!(
+                    read_namespace(&platform, vault_name, &identity, "notes")
+                        .await
+                        .unwrap(),
+                    b"notes"
+                );
+            }
+
+            delete_vault(&platform, source).await.unwrap();
+            delete_vault(&platform, target).await.unwrap();
+        });
+    }
 }
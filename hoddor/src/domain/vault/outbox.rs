@@ -0,0 +1,144 @@
+//! Work queued for later, when the page may be gone.
+//!
+//! Each vault keeps its pending tasks in `outbox.json` next to its
+//! namespaces, so a service worker woken by Background Sync, or the page on
+//! its next load, can pick them up. Tasks are removed only once they ran.
+
+use super::error::VaultError;
+use crate::platform::Platform;
+use serde::{Deserialize, Serialize};
+
+pub(super) const OUTBOX_FILENAME: &str = "outbox.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PendingTask {
+    /// Removes expired namespaces.
+    Cleanup,
+    /// Posts the exported vault to `url`.
+    UploadBackup { url: String },
+    /// Pushes the vault to the paired device once it is reachable.
+    FlushSync,
+}
+
+fn outbox_path(vault_name: &str) -> String {
+    format!("{vault_name}/{OUTBOX_FILENAME}")
+}
+
+fn outbox_lock_name(vault_name: &str) -> String {
+    format!("{vault_name}/outbox")
+}
+
+async fn read_outbox(platform: &Platform, vault_name: &str) -> Vec<PendingTask> {
+    match platform.storage().read_file(&outbox_path(vault_name)).await {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_default(),
+        Err(_) => Vec::new(),
+    }
+}
+
+async fn write_outbox(
+    platform: &Platform,
+    vault_name: &str,
+    tasks: &[PendingTask],
+) -> Result<(), VaultError> {
+    let storage = platform.storage();
+
+    if tasks.is_empty() {
+        let _ = storage.delete_file(&outbox_path(vault_name)).await;
+        return Ok(());
+    }
+
+    let json = serde_json::to_string(tasks)
+        .map_err(|_| VaultError::serialization_error("Failed to serialize outbox"))?;
+
+    storage.write_file(&outbox_path(vault_name), &json).await
+}
+
+/// Queues `task` for `vault_name`. A task already pending is not queued twice.
+pub async fn enqueue(
+    platform: &Platform,
+    vault_name: &str,
+    task: PendingTask,
+) -> Result<(), VaultError> {
+    let _guard = platform
+        .locks()
+        .acquire(&outbox_lock_name(vault_name))
+        .await?;
+
+    let mut tasks = read_outbox(platform, vault_name).await;
+    if tasks.contains(&task) {
+        return Ok(());
+    }
+    tasks.push(task);
+
+    write_outbox(platform, vault_name, &tasks).await
+}
+
+pub async fn pending(platform: &Platform, vault_name: &str) -> Vec<PendingTask> {
+    read_outbox(platform, vault_name).await
+}
+
+/// Removes `task` once it has run.
+pub async fn complete(
+    platform: &Platform,
+    vault_name: &str,
+    task: &PendingTask,
+) -> Result<(), VaultError> {
+    let _guard = platform
+        .locks()
+        .acquire(&outbox_lock_name(vault_name))
+        .await?;
+
+    let mut tasks = read_outbox(platform, vault_name).await;
+    tasks.retain(|pending| pending != task);
+
+    write_outbox(platform, vault_name, &tasks).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{create_vault, delete_vault, save_vault};
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_outbox_queues_each_task_once_until_completed() {
+        let platform = Platform::new();
+        let vault_name = "outbox_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let upload = PendingTask::UploadBackup {
+                url: "https://backup.example/vault".to_string(),
+            };
+            enqueue(&platform, vault_name, PendingTask::Cleanup)
+                .await
+                .unwrap();
+            enqueue(&platform, vault_name, upload.clone())
+                .await
+                .unwrap();
+            enqueue(&platform, vault_name, PendingTask::Cleanup)
+                .await
+                .unwrap();
+
+            assert_eq!(
+                pending(&platform, vault_name).await,
+                vec![PendingTask::Cleanup, upload.clone()]
+            );
+
+            complete(&platform, vault_name, &PendingTask::Cleanup)
+                .await
+                .unwrap();
+            assert_eq!(pending(&platform, vault_name).await, vec![upload.clone()]);
+
+            complete(&platform, vault_name, &upload).await.unwrap();
+            assert!(pending(&platform, vault_name).await.is_empty());
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,430 @@
+//! RFC 6902 JSON Patch applied to namespaces holding JSON documents.
+//!
+//! The patch is applied to the decrypted document while the namespace lock
+//! is held, so concurrent patches from other tabs or workers are serialized
+//! instead of overwriting each other as a read-modify-write in application
+//! code would. A patch applies entirely or not at all.
+//!
+//! Peers still receive the whole re-encrypted namespace: applying a patch
+//! takes the plaintext, which sync never has on the receiving side, so
+//! patches save a read-modify-write in the application but not bandwidth.
+//!
+//! CRDT namespaces are refused: their plaintext is replica state merged
+//! with peers' copies, changed through [`super::crdt::update_crdt`].
+
+use super::chunks;
+use super::error::VaultError;
+use super::integrity::{check_integrity, integrity_tag};
+use super::migration::CURRENT_CRYPTO_VERSION;
+use super::operations::{
+    current_timestamp, ensure_not_recipient, lock_namespace, namespace_keys, read_vault,
+    write_namespace,
+};
+use super::types::NamespaceData;
+use crate::platform::Platform;
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+/// Applies the JSON Patch `patch` to the JSON document stored in
+/// `namespace` and stores the result in its place, keeping its expiration
+/// and recipients. Namespaces holding a CRDT value cannot be patched.
+#[tracing::instrument(skip_all, fields(vault = vault_name, namespace = namespace))]
+pub async fn patch_namespace(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    namespace: &str,
+    patch: &Value,
+) -> Result<(), VaultError> {
+    let operations: Vec<PatchOperation> = serde_json::from_value(patch.clone())
+        .map_err(|e| VaultError::io_error(format!("Invalid JSON patch: {e}")))?;
+
+    let _guards = lock_namespace(platform, vault_name, namespace).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.observer {
+        return Err(VaultError::ObserverVault);
+    }
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    let namespace_data = vault
+        .namespaces
+        .get(namespace)
+        .ok_or(VaultError::NamespaceNotFound)?;
+
+    if let Some(timelock) = &namespace_data.timelock {
+        return Err(VaultError::TimeLocked(timelock.release_at));
+    }
+    if namespace_data.crdt.is_some() {
+        return Err(VaultError::io_error(
+            "CRDT namespaces are changed with update_crdt, not JSON patches",
+        ));
+    }
+    // Expired namespaces are removed by the next regular read or cleanup.
+    if let Some(expiration) = &namespace_data.expiration {
+        if current_timestamp(platform) >= expiration.expires_at {
+            return Err(VaultError::DataExpired);
+        }
+    }
+
+    let plaintext =
+        chunks::decrypt_namespace(platform, &vault, namespace_data, identity_private_key).await?;
+    check_integrity(
+        platform,
+        namespace,
+        namespace_data,
+        &plaintext,
+        identity_private_key,
+    )
+    .await?;
+    let mut document: Value = serde_json::from_slice(&plaintext)
+        .map_err(|_| VaultError::serialization_error("Namespace does not hold a JSON document"))?;
+
+    for (index, operation) in operations.iter().enumerate() {
+        apply_operation(&mut document, operation).map_err(|reason| {
+            VaultError::io_error(format!("JSON patch operation {index} failed: {reason}"))
+        })?;
+    }
+
+    let data = serde_json::to_vec(&document)
+        .map_err(|_| VaultError::serialization_error("Failed to serialize patched document"))?;
+    super::limits::ensure_within_limit(&vault, data.len())?;
+
+    let public_key = crate::domain::crypto::identity_to_public(platform, identity_private_key)
+        .map_err(|_| VaultError::InvalidPassword)?;
+    ensure_not_recipient(Some(namespace_data), &public_key)?;
+    let expiration = namespace_data.expiration.clone();
+    let recipients = namespace_data.recipients.clone();
+    // Patched documents keep their compression.
+    let (payload, compression) = super::compression::compress(namespace_data.compression, &data);
+
+    let keys = namespace_keys(&public_key, &recipients);
+    let (encrypted_data, chunk_ids) =
+        chunks::encrypt_payload(platform, &mut vault, &keys, &payload).await?;
+    let integrity = integrity_tag(platform, &public_key, &keys, &data).await?;
+
+    vault.namespaces.insert(
+        namespace.to_string(),
+        NamespaceData {
+            data: encrypted_data,
+            expiration,
+            chunks: chunk_ids,
+            timelock: None,
+            updated_at: Some(current_timestamp(platform)),
+            recipients,
+            crdt: None,
+            integrity: Some(integrity),
+            crypto_version: Some(CURRENT_CRYPTO_VERSION),
+            compression,
+        },
+    );
+
+    write_namespace(platform, vault_name, &vault, namespace).await
+}
+
+fn apply_operation(document: &mut Value, operation: &PatchOperation) -> Result<(), String> {
+    match operation {
+        PatchOperation::Add { path, value } => add(document, &parse_pointer(path)?, value.clone()),
+        PatchOperation::Remove { path } => remove(document, &parse_pointer(path)?).map(drop),
+        PatchOperation::Replace { path, value } => {
+            let target = get_mut(document, &parse_pointer(path)?)
+                .ok_or_else(|| format!("{path} does not exist"))?;
+            *target = value.clone();
+            Ok(())
+        }
+        PatchOperation::Move { from, path } => {
+            let from_tokens = parse_pointer(from)?;
+            let to_tokens = parse_pointer(path)?;
+            if to_tokens.len() > from_tokens.len() && to_tokens.starts_with(&from_tokens) {
+                return Err(format!("cannot move {from} into one of its children"));
+            }
+            let value = remove(document, &from_tokens)?;
+            add(document, &to_tokens, value)
+        }
+        PatchOperation::Copy { from, path } => {
+            let value = get_mut(document, &parse_pointer(from)?)
+                .ok_or_else(|| format!("{from} does not exist"))?
+                .clone();
+            add(document, &parse_pointer(path)?, value)
+        }
+        PatchOperation::Test { path, value } => match get_mut(document, &parse_pointer(path)?) {
+            Some(actual) if actual == value => Ok(()),
+            Some(_) => Err(format!("{path} does not hold the expected value")),
+            None => Err(format!("{path} does not exist")),
+        },
+    }
+}
+
+// RFC 6901 JSON Pointer: "" is the whole document, "/a/0" a member then an
+// element, with "~1" standing for "/" and "~0" for "~".
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(format!("{pointer} is not a JSON pointer"));
+    };
+
+    Ok(rest
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+fn array_index(token: &str, len: usize) -> Result<usize, String> {
+    let valid = !token.is_empty()
+        && token.bytes().all(|b| b.is_ascii_digit())
+        && (token == "0" || !token.starts_with('0'));
+
+    match token.parse::<usize>() {
+        Ok(index) if valid && index < len => Ok(index),
+        _ => Err(format!("{token} is not an index of the array")),
+    }
+}
+
+fn get_mut<'v>(document: &'v mut Value, tokens: &[String]) -> Option<&'v mut Value> {
+    tokens.iter().try_fold(document, |node, token| match node {
+        Value::Object(members) => members.get_mut(token),
+        Value::Array(items) => {
+            let index = array_index(token, items.len()).ok()?;
+            items.get_mut(index)
+        }
+        _ => None,
+    })
+}
+
+fn add(document: &mut Value, tokens: &[String], value: Value) -> Result<(), String> {
+    let Some((last, parent)) = tokens.split_last() else {
+        *document = value;
+        return Ok(());
+    };
+
+    match get_mut(document, parent) {
+        Some(Value::Object(members)) => {
+            members.insert(last.clone(), value);
+            Ok(())
+        }
+        Some(Value::Array(items)) if last == "-" => {
+            items.push(value);
+            Ok(())
+        }
+        Some(Value::Array(items)) => {
+            // Adding may target the position right after the last element.
+            let index = array_index(last, items.len() + 1)?;
+            items.insert(index, value);
+            Ok(())
+        }
+        _ => Err(format!(
+            "the parent of /{} does not exist",
+            tokens.join("/")
+        )),
+    }
+}
+
+fn remove(document: &mut Value, tokens: &[String]) -> Result<Value, String> {
+    let Some((last, parent)) = tokens.split_last() else {
+        return Err("the whole document cannot be removed".to_string());
+    };
+
+    let removed = match get_mut(document, parent) {
+        Some(Value::Object(members)) => members.remove(last),
+        Some(Value::Array(items)) => array_index(last, items.len())
+            .ok()
+            .map(|index| items.remove(index)),
+        _ => None,
+    };
+
+    removed.ok_or_else(|| format!("/{} does not exist", tokens.join("/")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::crdt::{read_crdt, update_crdt, CrdtKind};
+    use crate::domain::vault::operations::{
+        create_vault, delete_vault, read_namespace, save_vault, upsert_namespace,
+    };
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    fn patched(document: Value, patch: Value) -> Result<Value, String> {
+        let operations: Vec<PatchOperation> = serde_json::from_value(patch).unwrap();
+        let mut document = document;
+        for operation in &operations {
+            apply_operation(&mut document, operation)?;
+        }
+        Ok(document)
+    }
+
+    #[test]
+    fn test_operations_follow_rfc_6902() {
+        let document = json!({ "a/b": 1, "list": [1, 2], "nested": { "x": true } });
+
+        assert_eq!(
+            patched(
+                document.clone(),
+                json!([
+                    { "op": "add", "path": "/list/1", "value": 9 },
+                    { "op": "add", "path": "/list/-", "value": 3 },
+                    { "op": "replace", "path": "/a~1b", "value": 2 },
+                    { "op": "remove", "path": "/nested/x" },
+                    { "op": "copy", "from": "/list/0", "path": "/first" },
+                    { "op": "move", "from": "/first", "path": "/nested/first" },
+                    { "op": "test", "path": "/nested", "value": { "first": 1 } }
+                ])
+            )
+            .unwrap(),
+            json!({ "a/b": 2, "list": [1, 9, 2, 3], "nested": { "first": 1 } })
+        );
+
+        for patch in [
+            json!([{ "op": "replace", "path": "/missing", "value": 1 }]),
+            json!([{ "op": "remove", "path": "/list/2" }]),
+            json!([{ "op": "add", "path": "/list/01", "value": 1 }]),
+            json!([{ "op": "add", "path": "/none/x", "value": 1 }]),
+            json!([{ "op": "move", "from": "/nested", "path": "/nested/x/y" }]),
+            json!([{ "op": "test", "path": "/list/0", "value": 2 }]),
+            json!([{ "op": "remove", "path": "" }]),
+        ] {
+            assert!(patched(document.clone(), patch.clone()).is_err(), "{patch}");
+        }
+    }
+
+    #[test]
+    fn test_patch_namespace_is_all_or_nothing() {
+        let platform = Platform::new();
+        let vault_name = "patch_test";
+        let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+        let public_key = crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            upsert_namespace(
+                &platform,
+                vault_name,
+                &public_key,
+                "settings",
+                serde_json::to_vec(&json!({ "theme": "dark", "tabs": [] })).unwrap(),
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+
+            patch_namespace(
+                &platform,
+                vault_name,
+                &identity,
+                "settings",
+                &json!([
+                    { "op": "replace", "path": "/theme", "value": "light" },
+                    { "op": "add", "path": "/tabs/-", "value": "inbox" }
+                ]),
+            )
+            .await
+            .unwrap();
+
+            let read = || async {
+                let data = read_namespace(&platform, vault_name, &identity, "settings")
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<Value>(&data).unwrap()
+            };
+            let expected = json!({ "theme": "light", "tabs": ["inbox"] });
+            assert_eq!(read().await, expected);
+
+            // The first operation applies, the second fails: nothing is stored.
+            assert!(matches!(
+                patch_namespace(
+                    &platform,
+                    vault_name,
+                    &identity,
+                    "settings",
+                    &json!([
+                        { "op": "replace", "path": "/theme", "value": "blue" },
+                        { "op": "test", "path": "/tabs", "value": [] }
+                    ]),
+                )
+                .await,
+                Err(VaultError::IoError(msg)) if msg.contains("operation 1")
+            ));
+            assert_eq!(read().await, expected);
+
+            assert!(patch_namespace(
+                &platform,
+                vault_name,
+                &identity,
+                "settings",
+                &json!([{ "op": "frobnicate", "path": "/theme" }]),
+            )
+            .await
+            .is_err());
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_patch_namespace_refuses_crdt_namespaces() {
+        let platform = Platform::new();
+        let vault_name = "patch_crdt_test";
+        let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let increment = serde_json::from_value(json!({ "op": "increment", "by": 3 })).unwrap();
+            update_crdt(
+                &platform,
+                vault_name,
+                &identity,
+                "visits",
+                CrdtKind::GCounter,
+                &increment,
+            )
+            .await
+            .unwrap();
+
+            assert!(matches!(
+                patch_namespace(
+                    &platform,
+                    vault_name,
+                    &identity,
+                    "visits",
+                    &json!([{ "op": "replace", "path": "", "value": 0 }]),
+                )
+                .await,
+                Err(VaultError::IoError(msg)) if msg.contains("update_crdt")
+            ));
+            assert_eq!(
+                read_crdt(&platform, vault_name, &identity, "visits")
+                    .await
+                    .unwrap(),
+                json!(3)
+            );
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
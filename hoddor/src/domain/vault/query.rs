@@ -0,0 +1,627 @@
+//! JSONPath queries over namespaces holding JSON documents.
+//!
+//! The namespace is decrypted and queried here, so only the matching
+//! fragments leave the vault layer. The supported syntax is the common
+//! subset of JSONPath:
+//!
+//! - `$` for the document root, `@` for the current node inside filters;
+//! - `.name`, `['name']` and `["name"]` for object members;
+//! - `[0]`, `[-1]` and `[1:3]` for array elements and slices;
+//! - `*` and `[*]` for every child, `..` for every descendant;
+//! - `[a,b]` unions of the above;
+//! - `[?(@.price < 10)]` filters comparing a relative path with a literal
+//!   (`==`, `!=`, `<`, `<=`, `>`, `>=`), or `[?(@.isbn)]` testing that it
+//!   exists.
+
+use super::error::VaultError;
+use super::operations::read_namespace;
+use crate::platform::Platform;
+use serde_json::Value;
+use std::cmp::Ordering;
+
+/// Decrypts `namespace` and returns the values `path` selects in it, in
+/// document order.
+#[tracing::instrument(skip_all, fields(vault = vault_name, namespace = namespace))]
+pub async fn query_namespace(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    namespace: &str,
+    path: &str,
+) -> Result<Vec<Value>, VaultError> {
+    // Rejected before anything is decrypted.
+    let path = JsonPath::parse(path)?;
+
+    let data = read_namespace(platform, vault_name, identity_private_key, namespace).await?;
+    let document: Value = serde_json::from_slice(&data)
+        .map_err(|_| VaultError::serialization_error("Namespace does not hold a JSON document"))?;
+
+    Ok(path.select(&document).into_iter().cloned().collect())
+}
+
+/// A parsed JSONPath expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Child(Vec<Selector>),
+    Descendant(Vec<Selector>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Name(String),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Wildcard,
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    left: Operand,
+    comparison: Option<(Comparison, Operand)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Relative(Vec<Segment>),
+    Literal(Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl JsonPath {
+    pub fn parse(path: &str) -> Result<Self, VaultError> {
+        let mut parser = Parser {
+            chars: path.chars().collect(),
+            position: 0,
+        };
+
+        parser.skip_whitespace();
+        parser.expect('$')?;
+        let segments = parser.segments()?;
+        parser.skip_whitespace();
+
+        if parser.position < parser.chars.len() {
+            return Err(parser.error("unexpected character"));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Values of `document` the path selects, in document order.
+    pub fn select<'v>(&self, document: &'v Value) -> Vec<&'v Value> {
+        select_segments(&self.segments, document)
+    }
+}
+
+fn select_segments<'v>(segments: &[Segment], root: &'v Value) -> Vec<&'v Value> {
+    let mut nodes = vec![root];
+
+    for segment in segments {
+        nodes = match segment {
+            Segment::Child(selectors) => nodes
+                .into_iter()
+                .flat_map(|node| apply_selectors(selectors, node))
+                .collect(),
+            Segment::Descendant(selectors) => nodes
+                .into_iter()
+                .flat_map(descendants)
+                .flat_map(|node| apply_selectors(selectors, node))
+                .collect(),
+        };
+    }
+
+    nodes
+}
+
+// The node itself followed by every node below it, depth first.
+fn descendants(node: &Value) -> Vec<&Value> {
+    fn collect<'v>(node: &'v Value, found: &mut Vec<&'v Value>) {
+        found.push(node);
+        for child in children(node) {
+            collect(child, found);
+        }
+    }
+
+    let mut found = Vec::new();
+    collect(node, &mut found);
+    found
+}
+
+fn children(node: &Value) -> Vec<&Value> {
+    match node {
+        Value::Array(items) => items.iter().collect(),
+        Value::Object(members) => members.values().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn apply_selectors<'v>(selectors: &[Selector], node: &'v Value) -> Vec<&'v Value> {
+    selectors
+        .iter()
+        .flat_map(|selector| apply_selector(selector, node))
+        .collect()
+}
+
+fn apply_selector<'v>(selector: &Selector, node: &'v Value) -> Vec<&'v Value> {
+    match (selector, node) {
+        (Selector::Name(name), Value::Object(members)) => members.get(name).into_iter().collect(),
+        (Selector::Index(index), Value::Array(items)) => resolve_index(*index, items.len())
+            .and_then(|index| items.get(index))
+            .into_iter()
+            .collect(),
+        (Selector::Slice(start, end), Value::Array(items)) => {
+            let len = items.len();
+            let start = start.map_or(0, |start| clamp_index(start, len));
+            let end = end.map_or(len, |end| clamp_index(end, len));
+            items
+                .get(start..end.max(start))
+                .unwrap_or_default()
+                .iter()
+                .collect()
+        }
+        (Selector::Wildcard, _) => children(node),
+        (Selector::Filter(filter), _) => children(node)
+            .into_iter()
+            .filter(|child| filter.matches(child))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    if index < 0 {
+        len.checked_sub(usize::try_from(index.unsigned_abs()).ok()?)
+    } else {
+        usize::try_from(index).ok()
+    }
+}
+
+fn clamp_index(index: i64, len: usize) -> usize {
+    resolve_index(index, len).map_or(0, |index| index.min(len))
+}
+
+impl Filter {
+    fn matches(&self, current: &Value) -> bool {
+        let left = self.left.resolve(current);
+
+        let Some((comparison, right)) = &self.comparison else {
+            return left.is_some();
+        };
+        let right = right.resolve(current);
+
+        match comparison {
+            Comparison::Equal => values_equal(left, right),
+            Comparison::NotEqual => !values_equal(left, right),
+            Comparison::Less => compare(left, right) == Some(Ordering::Less),
+            Comparison::LessOrEqual => {
+                matches!(compare(left, right), Some(Ordering::Less | Ordering::Equal))
+            }
+            Comparison::Greater => compare(left, right) == Some(Ordering::Greater),
+            Comparison::GreaterOrEqual => matches!(
+                compare(left, right),
+                Some(Ordering::Greater | Ordering::Equal)
+            ),
+        }
+    }
+}
+
+impl Operand {
+    // A relative path stands for the first node it selects, if any.
+    fn resolve<'v>(&'v self, current: &'v Value) -> Option<&'v Value> {
+        match self {
+            Operand::Relative(segments) => select_segments(segments, current).into_iter().next(),
+            Operand::Literal(value) => Some(value),
+        }
+    }
+}
+
+fn values_equal(left: Option<&Value>, right: Option<&Value>) -> bool {
+    match (left, right) {
+        (Some(Value::Number(left)), Some(Value::Number(right))) => left.as_f64() == right.as_f64(),
+        (left, right) => left == right,
+    }
+}
+
+// Only numbers and strings are ordered; anything else never compares.
+fn compare(left: Option<&Value>, right: Option<&Value>) -> Option<Ordering> {
+    match (left?, right?) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64()?.partial_cmp(&right.as_f64()?),
+        (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+        _ => None,
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn error(&self, reason: &str) -> VaultError {
+        VaultError::io_error(format!("Invalid JSONPath at {}: {reason}", self.position))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), VaultError> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{expected}'")))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn segments(&mut self) -> Result<Vec<Segment>, VaultError> {
+        let mut segments = Vec::new();
+
+        loop {
+            if self.eat('.') {
+                if self.eat('.') {
+                    let selectors = if self.peek() == Some('[') {
+                        self.bracket()?
+                    } else {
+                        vec![self.shorthand()?]
+                    };
+                    segments.push(Segment::Descendant(selectors));
+                } else {
+                    segments.push(Segment::Child(vec![self.shorthand()?]));
+                }
+            } else if self.peek() == Some('[') {
+                segments.push(Segment::Child(self.bracket()?));
+            } else {
+                return Ok(segments);
+            }
+        }
+    }
+
+    // What follows `.` or `..`: a member name or `*`.
+    fn shorthand(&mut self) -> Result<Selector, VaultError> {
+        if self.eat('*') {
+            return Ok(Selector::Wildcard);
+        }
+
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '$')
+        {
+            self.position += 1;
+        }
+
+        if start == self.position {
+            return Err(self.error("expected a member name"));
+        }
+
+        Ok(Selector::Name(
+            self.chars[start..self.position].iter().collect(),
+        ))
+    }
+
+    fn bracket(&mut self) -> Result<Vec<Selector>, VaultError> {
+        self.expect('[')?;
+        let mut selectors = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+            selectors.push(self.selector()?);
+            self.skip_whitespace();
+
+            if self.eat(']') {
+                return Ok(selectors);
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector, VaultError> {
+        match self.peek() {
+            Some('*') => {
+                self.position += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some('\'' | '"') => Ok(Selector::Name(self.string()?)),
+            Some('?') => {
+                self.position += 1;
+                self.skip_whitespace();
+                let filter = if self.eat('(') {
+                    let filter = self.filter()?;
+                    self.skip_whitespace();
+                    self.expect(')')?;
+                    filter
+                } else {
+                    self.filter()?
+                };
+                Ok(Selector::Filter(filter))
+            }
+            _ => {
+                let start = if self.peek() == Some(':') {
+                    None
+                } else {
+                    Some(self.integer()?)
+                };
+                self.skip_whitespace();
+
+                if !self.eat(':') {
+                    return start
+                        .map(Selector::Index)
+                        .ok_or_else(|| self.error("expected an index"));
+                }
+
+                self.skip_whitespace();
+                let end = if matches!(self.peek(), Some(']' | ',')) {
+                    None
+                } else {
+                    Some(self.integer()?)
+                };
+                Ok(Selector::Slice(start, end))
+            }
+        }
+    }
+
+    fn integer(&mut self) -> Result<i64, VaultError> {
+        let start = self.position;
+        self.eat('-');
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+
+        self.chars[start..self.position]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .map_err(|_| {
+                self.position = start;
+                self.error("expected an integer")
+            })
+    }
+
+    fn string(&mut self) -> Result<String, VaultError> {
+        let Some(quote) = self.peek() else {
+            return Err(self.error("expected a string"));
+        };
+        self.position += 1;
+        let mut value = String::new();
+
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some(c) if c == quote => {
+                    self.position += 1;
+                    return Ok(value);
+                }
+                Some('\\') => {
+                    self.position += 1;
+                    let escaped = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    value.push(escaped);
+                    self.position += 1;
+                }
+                Some(c) => {
+                    value.push(c);
+                    self.position += 1;
+                }
+            }
+        }
+    }
+
+    fn filter(&mut self) -> Result<Filter, VaultError> {
+        let left = self.operand()?;
+        self.skip_whitespace();
+
+        let comparison = match self.comparison() {
+            Some(comparison) => {
+                self.skip_whitespace();
+                Some((comparison, self.operand()?))
+            }
+            None => None,
+        };
+
+        Ok(Filter { left, comparison })
+    }
+
+    fn comparison(&mut self) -> Option<Comparison> {
+        let rest: String = self.chars[self.position..].iter().take(2).collect();
+        let (comparison, len) = match rest.as_str() {
+            "==" => (Comparison::Equal, 2),
+            "!=" => (Comparison::NotEqual, 2),
+            "<=" => (Comparison::LessOrEqual, 2),
+            ">=" => (Comparison::GreaterOrEqual, 2),
+            _ if rest.starts_with('<') => (Comparison::Less, 1),
+            _ if rest.starts_with('>') => (Comparison::Greater, 1),
+            _ => return None,
+        };
+
+        self.position += len;
+        Some(comparison)
+    }
+
+    fn operand(&mut self) -> Result<Operand, VaultError> {
+        match self.peek() {
+            Some('@') => {
+                self.position += 1;
+                Ok(Operand::Relative(self.segments()?))
+            }
+            Some('\'' | '"') => Ok(Operand::Literal(Value::String(self.string()?))),
+            _ => {
+                let start = self.position;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.'))
+                {
+                    self.position += 1;
+                }
+
+                let literal: String = self.chars[start..self.position].iter().collect();
+                serde_json::from_str(&literal)
+                    .map(Operand::Literal)
+                    .map_err(|_| {
+                        self.position = start;
+                        self.error("expected '@' or a literal")
+                    })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{
+        create_vault, delete_vault, save_vault, upsert_namespace,
+    };
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    fn store() -> Value {
+        json!({
+            "store": {
+                "books": [
+                    { "title": "Sayings", "price": 8.95, "isbn": "0-553" },
+                    { "title": "Sword", "price": 12.99 },
+                    { "title": "Moby Dick", "price": 8.99, "isbn": "0-395" }
+                ],
+                "bicycle": { "color": "red", "price": 19.95 }
+            }
+        })
+    }
+
+    fn query(path: &str) -> Vec<Value> {
+        let document = store();
+        JsonPath::parse(path)
+            .unwrap()
+            .select(&document)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn test_paths_select_members_and_elements() {
+        assert_eq!(query("$"), vec![store()]);
+        assert_eq!(query("$.store.bicycle.color"), vec![json!("red")]);
+        assert_eq!(query("$['store'][\"bicycle\"].color"), vec![json!("red")]);
+        assert_eq!(query("$.store.books[-1].title"), vec![json!("Moby Dick")]);
+        assert_eq!(
+            query("$.store.books[0:2].title"),
+            vec![json!("Sayings"), json!("Sword")]
+        );
+        assert_eq!(
+            query("$.store.books[0,2].price"),
+            vec![json!(8.95), json!(8.99)]
+        );
+        assert_eq!(query("$.store.books[*]").len(), 3);
+        assert_eq!(query("$..price").len(), 4);
+        assert!(query("$.store.missing").is_empty());
+        assert!(query("$.store.books[7]").is_empty());
+    }
+
+    #[test]
+    fn test_filters_compare_relative_paths() {
+        assert_eq!(
+            query("$.store.books[?(@.price < 10)].title"),
+            vec![json!("Sayings"), json!("Moby Dick")]
+        );
+        assert_eq!(
+            query("$.store.books[?@.isbn].title"),
+            vec![json!("Sayings"), json!("Moby Dick")]
+        );
+        assert_eq!(
+            query("$..books[?(@.title == 'Sword')].price"),
+            vec![json!(12.99)]
+        );
+        assert_eq!(query("$.store.books[?(@.title != 'Sword')]").len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_paths_are_rejected() {
+        for path in ["", "store", "$.", "$[", "$['open", "$[?(@.a ==)]", "$.a b"] {
+            assert!(
+                matches!(JsonPath::parse(path), Err(VaultError::IoError(msg)) if msg.contains("JSONPath")),
+                "{path}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_query_namespace_returns_matches_only() {
+        let platform = Platform::new();
+        let vault_name = "query_test";
+        let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+        let public_key = crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            for (namespace, data) in [
+                ("catalog", serde_json::to_vec(&store()).unwrap()),
+                ("binary", vec![0xff, 0x00]),
+            ] {
+                upsert_namespace(
+                    &platform,
+                    vault_name,
+                    &public_key,
+                    namespace,
+                    data,
+                    None,
+                    false,
+                )
+                .await
+                .unwrap();
+            }
+
+            assert_eq!(
+                query_namespace(
+                    &platform,
+                    vault_name,
+                    &identity,
+                    "catalog",
+                    "$.store.bicycle"
+                )
+                .await
+                .unwrap(),
+                vec![json!({ "color": "red", "price": 19.95 })]
+            );
+            assert!(matches!(
+                query_namespace(&platform, vault_name, &identity, "binary", "$").await,
+                Err(VaultError::SerializationError(_))
+            ));
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
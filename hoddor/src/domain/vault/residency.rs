@@ -0,0 +1,120 @@
+//! Residency and classification tags on namespaces.
+//!
+//! Tags are opaque labels such as `eu` or `confidential`. Peers assert the
+//! attributes they satisfy when a pairing session starts, and a tagged
+//! namespace is only replicated to a peer asserting all of its tags.
+//! Untagged namespaces go to every peer.
+
+use super::error::VaultError;
+use super::operations::{lock_vault, read_vault, read_vault_metadata, write_vault};
+use super::types::Vault;
+use crate::platform::Platform;
+use std::collections::BTreeSet;
+
+/// Whether `namespace` may be sent to a peer asserting `peer_attributes`.
+pub fn may_replicate(vault: &Vault, namespace: &str, peer_attributes: &BTreeSet<String>) -> bool {
+    vault
+        .residency
+        .get(namespace)
+        .is_none_or(|tags| tags.is_subset(peer_attributes))
+}
+
+/// Trims tags and drops empty ones.
+pub fn normalize_tags<I, S>(tags: I) -> BTreeSet<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    tags.into_iter()
+        .map(|tag| tag.as_ref().trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// Replaces the residency tags of `namespace`; no tags clears them.
+///
+/// As with retention policies, the namespace need not exist yet so data can
+/// be tagged before it is first written.
+pub async fn set_namespace_residency(
+    platform: &Platform,
+    vault_name: &str,
+    namespace: &str,
+    tags: BTreeSet<String>,
+) -> Result<(), VaultError> {
+    let _guard = lock_vault(platform, vault_name).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    if tags.is_empty() {
+        vault.residency.remove(namespace);
+    } else {
+        vault.residency.insert(namespace.to_string(), tags);
+    }
+
+    write_vault(platform, vault_name, vault, Vec::new()).await
+}
+
+pub async fn namespace_residency(
+    platform: &Platform,
+    vault_name: &str,
+    namespace: &str,
+) -> Result<BTreeSet<String>, VaultError> {
+    let vault = read_vault_metadata(platform, vault_name).await?;
+
+    Ok(vault.residency.get(namespace).cloned().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{create_vault, delete_vault, save_vault};
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_tagged_namespace_requires_every_tag() {
+        let platform = Platform::new();
+        let vault_name = "residency_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            set_namespace_residency(
+                &platform,
+                vault_name,
+                "payroll",
+                normalize_tags(["eu", " confidential ", ""]),
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                namespace_residency(&platform, vault_name, "payroll")
+                    .await
+                    .unwrap(),
+                normalize_tags(["confidential", "eu"])
+            );
+
+            let vault = read_vault(&platform, vault_name).await.unwrap();
+            assert!(!may_replicate(&vault, "payroll", &normalize_tags(["eu"])));
+            assert!(may_replicate(
+                &vault,
+                "payroll",
+                &normalize_tags(["eu", "confidential", "us"])
+            ));
+            assert!(may_replicate(&vault, "notes", &BTreeSet::new()));
+
+            set_namespace_residency(&platform, vault_name, "payroll", BTreeSet::new())
+                .await
+                .unwrap();
+            let vault = read_vault(&platform, vault_name).await.unwrap();
+            assert!(may_replicate(&vault, "payroll", &BTreeSet::new()));
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
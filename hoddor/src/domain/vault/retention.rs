@@ -0,0 +1,202 @@
+//! Retention policies and legal holds on namespaces.
+//!
+//! A namespace under a policy cannot be removed before its policy date:
+//! `remove_namespace`, expiration cleanup, incoming sync deletions and
+//! deleting the whole vault all fail with [`VaultError::RetentionLocked`],
+//! and expired data is kept until then. Policies can be extended at any time
+//! but only loosened or cleared once that date has passed.
+
+use super::error::VaultError;
+use super::operations::{current_timestamp, lock_vault, read_vault, write_vault};
+use super::types::{RetentionPolicy, Vault};
+use crate::platform::Platform;
+
+impl RetentionPolicy {
+    /// Unix time in seconds from which a namespace last written at
+    /// `written_at` may be removed.
+    pub fn removable_at(&self, written_at: Option<i64>) -> i64 {
+        let retained_until = self
+            .min_retention_seconds
+            .map(|seconds| written_at.unwrap_or(self.applied_at) + seconds)
+            .unwrap_or(i64::MIN);
+
+        retained_until.max(self.delete_locked_until.unwrap_or(i64::MIN))
+    }
+}
+
+/// Unix time in seconds from which `namespace` may be removed, or `None`
+/// when it has no policy.
+pub fn removable_at(vault: &Vault, namespace: &str) -> Option<i64> {
+    let policy = vault.retention.get(namespace)?;
+    let written_at = vault
+        .namespaces
+        .get(namespace)
+        .and_then(|data| data.updated_at);
+
+    Some(policy.removable_at(written_at))
+}
+
+/// Fails with [`VaultError::RetentionLocked`] while `namespace` must be kept.
+pub fn ensure_removable(vault: &Vault, namespace: &str, now: i64) -> Result<(), VaultError> {
+    match removable_at(vault, namespace) {
+        Some(removable_at) if now < removable_at => Err(VaultError::RetentionLocked(removable_at)),
+        _ => Ok(()),
+    }
+}
+
+/// Like [`ensure_removable`] for every namespace, as when deleting the vault.
+pub fn ensure_vault_removable(vault: &Vault, now: i64) -> Result<(), VaultError> {
+    vault
+        .retention
+        .keys()
+        .try_for_each(|namespace| ensure_removable(vault, namespace, now))
+}
+
+/// Sets the retention policy of `namespace`, or clears it with `None`.
+///
+/// The namespace may not exist yet, so a policy can be in place before the
+/// first write. Policies that would let the namespace go earlier than the
+/// current one are refused until that one has lapsed.
+pub async fn set_namespace_retention(
+    platform: &Platform,
+    vault_name: &str,
+    namespace: &str,
+    min_retention_seconds: Option<i64>,
+    delete_locked_until: Option<i64>,
+) -> Result<Option<RetentionPolicy>, VaultError> {
+    let _guard = lock_vault(platform, vault_name).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    let now = current_timestamp(platform);
+    let policy = (min_retention_seconds.is_some() || delete_locked_until.is_some()).then_some(
+        RetentionPolicy {
+            min_retention_seconds,
+            delete_locked_until,
+            applied_at: now,
+        },
+    );
+
+    if let Some(current) = removable_at(&vault, namespace).filter(|&at| now < at) {
+        let written_at = vault.namespaces.get(namespace).and_then(|d| d.updated_at);
+        let proposed = policy.map(|policy| policy.removable_at(written_at));
+        if proposed.is_none_or(|proposed| proposed < current) {
+            return Err(VaultError::RetentionLocked(current));
+        }
+    }
+
+    match policy {
+        Some(policy) => vault.retention.insert(namespace.to_string(), policy),
+        None => vault.retention.remove(namespace),
+    };
+
+    write_vault(platform, vault_name, vault, Vec::new()).await?;
+
+    Ok(policy)
+}
+
+pub async fn namespace_retention(
+    platform: &Platform,
+    vault_name: &str,
+    namespace: &str,
+) -> Result<Option<RetentionPolicy>, VaultError> {
+    let vault = super::operations::read_vault_metadata(platform, vault_name).await?;
+
+    Ok(vault.retention.get(namespace).copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{
+        cleanup_vault, create_vault, delete_vault, remove_namespace, save_vault, upsert_namespace,
+    };
+    use crate::ports::ClockPort;
+    use futures::executor::block_on;
+
+    struct FixedClock(f64);
+
+    #[async_trait::async_trait(?Send)]
+    impl ClockPort for FixedClock {
+        fn now(&self) -> f64 {
+            self.0
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+
+        async fn sleep(&self, _milliseconds: u32) {}
+    }
+
+    static DAY_ONE: FixedClock = FixedClock(1_700_000_000_000.0);
+    static DAY_TWO: FixedClock = FixedClock(1_700_086_400_000.0);
+    static DAY_THREE: FixedClock = FixedClock(1_700_172_800_000.0);
+
+    #[test]
+    fn test_policy_uses_latest_of_retention_and_lock() {
+        let policy = RetentionPolicy {
+            min_retention_seconds: Some(100),
+            delete_locked_until: Some(500),
+            applied_at: 10,
+        };
+
+        assert_eq!(policy.removable_at(Some(300)), 500);
+        assert_eq!(policy.removable_at(Some(450)), 550);
+        assert_eq!(policy.removable_at(None), 500);
+    }
+
+    #[test]
+    fn test_retained_namespace_survives_removal_and_cleanup() {
+        let day_one = Platform::new().with_clock(&DAY_ONE);
+        let day_two = Platform::new().with_clock(&DAY_TWO);
+        let day_three = Platform::new().with_clock(&DAY_THREE);
+        let vault_name = "retention_test";
+
+        block_on(async {
+            let _ = delete_vault(&day_three, vault_name).await;
+
+            let identity = crate::domain::crypto::generate_identity(&day_one).unwrap();
+            let public_key =
+                crate::domain::crypto::identity_to_public(&day_one, &identity).unwrap();
+            save_vault(&day_one, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            set_namespace_retention(&day_one, vault_name, "ledger", Some(86_400 + 60), None)
+                .await
+                .unwrap();
+            upsert_namespace(
+                &day_one,
+                vault_name,
+                &public_key,
+                "ledger",
+                vec![1],
+                Some(10),
+                false,
+            )
+            .await
+            .unwrap();
+
+            assert!(matches!(
+                remove_namespace(&day_two, vault_name, "ledger").await,
+                Err(VaultError::RetentionLocked(_))
+            ));
+            assert!(!cleanup_vault(&day_two, vault_name).await.unwrap());
+            assert!(matches!(
+                delete_vault(&day_two, vault_name).await,
+                Err(VaultError::RetentionLocked(_))
+            ));
+            assert!(matches!(
+                set_namespace_retention(&day_two, vault_name, "ledger", None, None).await,
+                Err(VaultError::RetentionLocked(_))
+            ));
+
+            assert!(cleanup_vault(&day_three, vault_name).await.unwrap());
+            delete_vault(&day_three, vault_name).await.unwrap();
+        });
+    }
+}
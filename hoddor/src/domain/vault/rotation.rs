@@ -0,0 +1,269 @@
+//! Replacing a vault identity.
+//!
+//! Rotation derives a new identity from a new passphrase, re-encrypts what
+//! the old identity can read to it and forgets the old identity, in a
+//! single journaled write. Copies of the vault taken before the rotation,
+//! and anything already read with the old key, stay exposed.
+//!
+//! Namespaces the old identity cannot read, such as those written by
+//! another identity of the vault or time-locked ones, are left untouched.
+//! Recipients added with `grant_vault_recipient` lose access and have to
+//! be granted again; those granted a single namespace keep it.
+
+use super::chunks;
+use super::error::VaultError;
+use super::integrity::reseal_integrity;
+use super::operations::{
+    lock_vault, namespace_keys, read_vault, verify_vault_identity, write_vault,
+};
+use crate::domain::authentication::{self, IdentityKeys};
+use crate::platform::Platform;
+use std::collections::BTreeMap;
+
+/// Re-encrypts `vault_name` from `old_identity_private_key` to an identity
+/// derived from `new_passphrase` and returns the new identity.
+#[tracing::instrument(skip_all, fields(vault = vault_name))]
+pub async fn rotate_vault_identity(
+    platform: &Platform,
+    vault_name: &str,
+    old_identity_private_key: &str,
+    new_passphrase: &str,
+) -> Result<IdentityKeys, VaultError> {
+    let _guard = lock_vault(platform, vault_name).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.observer {
+        return Err(VaultError::ObserverVault);
+    }
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    let old_public_key =
+        crate::domain::crypto::identity_to_public(platform, old_identity_private_key)
+            .map_err(|_| VaultError::InvalidPassword)?;
+    if vault.identity_salts.get_salt(&old_public_key).is_none() {
+        return Err(VaultError::InvalidPassword);
+    }
+    verify_vault_identity(platform, vault_name, old_identity_private_key).await?;
+
+    let new_identity = authentication::create_vault_identity(platform, new_passphrase, &mut vault)
+        .await
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+    let inline: Vec<(String, &[u8])> = vault
+        .namespaces
+        .iter()
+        .filter(|(_, namespace_data)| {
+            namespace_data.chunks.is_empty() && namespace_data.timelock.is_none()
+        })
+        .map(|(namespace, namespace_data)| (namespace.clone(), namespace_data.data.as_slice()))
+        .collect();
+    let stored: Vec<(String, &[u8])> = vault
+        .chunks
+        .iter()
+        .map(|(id, data)| (id.clone(), data.as_slice()))
+        .collect();
+
+    let payloads: Vec<&[u8]> = inline
+        .iter()
+        .chain(&stored)
+        .map(|(_, data)| *data)
+        .collect();
+    let mut plaintexts =
+        crate::domain::crypto::decrypt_many(platform, &payloads, old_identity_private_key)
+            .await
+            .into_iter();
+
+    let inline: Vec<(String, Vec<u8>)> = inline
+        .into_iter()
+        .zip(&mut plaintexts)
+        .filter_map(|((namespace, _), plaintext)| Some((namespace, plaintext.ok()?)))
+        .collect();
+    let stored: Vec<(String, Vec<u8>)> = stored
+        .into_iter()
+        .zip(plaintexts)
+        .filter_map(|((id, _), plaintext)| Some((id, plaintext.ok()?)))
+        .collect();
+
+    for (namespace, plaintext) in inline {
+        if let Some(namespace_data) = vault.namespaces.get_mut(&namespace) {
+            namespace_data.data = crate::domain::crypto::encrypt_for_recipients(
+                platform,
+                &plaintext,
+                &namespace_keys(&new_identity.public_key, &namespace_data.recipients),
+            )
+            .await
+            .map_err(|e| VaultError::io_error(e.to_string()))?;
+        }
+    }
+
+    let chunk_recipients: BTreeMap<String, Vec<String>> = vault
+        .namespaces
+        .values()
+        .flat_map(|namespace_data| {
+            namespace_data
+                .chunks
+                .iter()
+                .map(|id| (id.clone(), namespace_data.recipients.clone()))
+        })
+        .collect();
+
+    // Stored chunks are never rewritten in place, so every re-encrypted
+    // chunk gets a new id. Deduplicated ones are keyed by the new identity,
+    // which also stops the old key from confirming guesses of their content.
+    let mut renamed = BTreeMap::new();
+    for (id, plaintext) in stored {
+        let granted = chunk_recipients.get(&id).map_or(&[][..], Vec::as_slice);
+        let encrypted = crate::domain::crypto::encrypt_for_recipients(
+            platform,
+            &plaintext,
+            &namespace_keys(&new_identity.public_key, granted),
+        )
+        .await
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+        let new_id = if chunks::chunk_id(old_identity_private_key, &plaintext) == id {
+            chunks::chunk_id(&new_identity.private_key, &plaintext)
+        } else {
+            chunks::random_chunk_id()
+        };
+
+        vault.chunks.remove(&id);
+        vault.chunks.insert(new_id.clone(), encrypted);
+        renamed.insert(id, new_id);
+    }
+
+    for namespace_data in vault.namespaces.values_mut() {
+        for id in &mut namespace_data.chunks {
+            if let Some(new_id) = renamed.get(id) {
+                id.clone_from(new_id);
+            }
+        }
+    }
+
+    // MAC keys the old identity cannot open belong to namespaces it could
+    // not read either.
+    for namespace_data in vault
+        .namespaces
+        .values_mut()
+        .filter(|namespace_data| namespace_data.timelock.is_none())
+    {
+        let Some(tag) = &mut namespace_data.integrity else {
+            continue;
+        };
+        let keys = namespace_keys(&new_identity.public_key, &namespace_data.recipients);
+        match reseal_integrity(platform, tag, old_identity_private_key, &keys).await {
+            Ok(()) => {
+                if tag.key_id == old_public_key {
+                    tag.key_id.clone_from(&new_identity.public_key);
+                }
+            }
+            Err(VaultError::InvalidPassword) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    vault.identity_salts.remove(&old_public_key);
+    vault
+        .username_pk
+        .retain(|_, public_key| *public_key != old_public_key);
+
+    write_vault(platform, vault_name, vault, Vec::new()).await?;
+    authentication::identity_cache::evict_identity(&old_public_key);
+
+    Ok(new_identity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{
+        create_vault, delete_vault, read_namespace, save_vault, upsert_namespace,
+        upsert_namespace_deduplicated,
+    };
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_rotation_moves_data_to_the_new_identity() {
+        let platform = Platform::new();
+        let vault_name = "rotation_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            let mut vault = create_vault().await.unwrap();
+            let old = authentication::derive_vault_identity(
+                &platform,
+                "old passphrase",
+                vault_name,
+                &mut vault,
+            )
+            .await
+            .unwrap();
+            save_vault(&platform, vault_name, vault).await.unwrap();
+
+            let document = vec![3u8; chunks::CHUNK_SIZE + 1];
+            upsert_namespace(
+                &platform,
+                vault_name,
+                &old.public_key,
+                "inline",
+                b"secret".to_vec(),
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+            upsert_namespace_deduplicated(
+                &platform,
+                vault_name,
+                &old.private_key,
+                "chunked",
+                document.clone(),
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+
+            let new =
+                rotate_vault_identity(&platform, vault_name, &old.private_key, "new passphrase")
+                    .await
+                    .unwrap();
+
+            for (namespace, expected) in [("inline", b"secret".to_vec()), ("chunked", document)] {
+                assert_eq!(
+                    read_namespace(&platform, vault_name, &new.private_key, namespace)
+                        .await
+                        .unwrap(),
+                    expected
+                );
+                assert!(
+                    read_namespace(&platform, vault_name, &old.private_key, namespace)
+                        .await
+                        .is_err()
+                );
+            }
+
+            let mut vault = read_vault(&platform, vault_name).await.unwrap();
+            assert!(vault.identity_salts.get_salt(&old.public_key).is_none());
+            assert_eq!(
+                chunks::list_stored_chunks(&platform, vault_name)
+                    .await
+                    .len(),
+                vault.chunks.len()
+            );
+            let unlocked = authentication::derive_vault_identity(
+                &platform,
+                "new passphrase",
+                vault_name,
+                &mut vault,
+            )
+            .await
+            .unwrap();
+            assert_eq!(unlocked.public_key, new.public_key);
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
@@ -1,8 +1,33 @@
 use super::error::VaultError;
 use super::types::Vault;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 const VAULT_MAGIC_NUMBER: &[u8; 6] = b"VAULT1";
 
+/// Marks vault files stored as MessagePack rather than JSON. The digit is
+/// the format version.
+///
+/// MessagePack, written with field names, is self-describing like the JSON
+/// it replaces: fields added with `#[serde(default)]` and left out with
+/// `skip_serializing_if` keep old and new files readable both ways, which
+/// bincode and postcard, relying on field order, would not. CBOR would do as
+/// well, but brings nothing over MessagePack at the same size.
+///
+/// The MessagePack follows as base64 because [`StoragePort`] moves text:
+/// OPFS and the filesystem adapters, journals embedding the files they
+/// replay and the digests of the integrity manifest all work on the stored
+/// string. Base64 costs a third on top of the binary, against the three and
+/// a half times of ciphertext as a JSON array of numbers; files are
+/// read and written whole, so the prefix check and the decoding add nothing
+/// noticeable to the I/O. A binary port would drop that third, at the cost
+/// of changing every adapter and what is hashed.
+///
+/// [`StoragePort`]: crate::ports::StoragePort
+const STORED_BINARY_PREFIX: &str = "hoddor-mp1:";
+const STORED_BINARY_MARKER: &str = "hoddor-mp";
+
 pub fn serialize_vault(vault: &Vault) -> Result<Vec<u8>, VaultError> {
     let serialized = serde_json::to_vec(vault)
         .map_err(|_| VaultError::serialization_error("Failed to serialize vault for export"))?;
@@ -18,6 +43,14 @@ pub fn serialize_vault(vault: &Vault) -> Result<Vec<u8>, VaultError> {
 }
 
 pub fn deserialize_vault(vault_bytes: &[u8]) -> Result<Vault, VaultError> {
+    let vault: Vault = serde_json::from_slice(vault_json(vault_bytes)?)
+        .map_err(|_| VaultError::serialization_error("Failed to deserialize vault data"))?;
+
+    Ok(vault)
+}
+
+/// Checks the container header and returns the JSON it wraps.
+pub(super) fn vault_json(vault_bytes: &[u8]) -> Result<&[u8], VaultError> {
     if vault_bytes.len() < 10 || &vault_bytes[..6] != VAULT_MAGIC_NUMBER {
         return Err(VaultError::serialization_error(
             "Invalid vault file: missing or incorrect magic number",
@@ -37,26 +70,90 @@ pub fn deserialize_vault(vault_bytes: &[u8]) -> Result<Vault, VaultError> {
         ));
     }
 
-    let vault: Vault = serde_json::from_slice(&vault_bytes[10..])
-        .map_err(|_| VaultError::serialization_error("Failed to deserialize vault data"))?;
+    Ok(&vault_bytes[10..])
+}
 
-    Ok(vault)
+/// Encodes the content of a metadata or namespace file. Byte fields become
+/// MessagePack binaries instead of JSON arrays of numbers, which makes
+/// namespace files about a third of their JSON size.
+pub(super) fn encode_stored<T: Serialize>(value: &T) -> Result<String, VaultError> {
+    let packed = rmp_serde::to_vec_named(value)
+        .map_err(|_| VaultError::serialization_error("Failed to encode vault file"))?;
+
+    Ok(format!("{STORED_BINARY_PREFIX}{}", STANDARD.encode(packed)))
+}
+
+/// Decodes a metadata or namespace file, whether written by
+/// [`encode_stored`] or as JSON by earlier versions.
+pub(super) fn decode_stored<T: DeserializeOwned>(text: &str) -> Result<T, VaultError> {
+    if let Some(encoded) = text.strip_prefix(STORED_BINARY_PREFIX) {
+        let packed = STANDARD
+            .decode(encoded)
+            .map_err(|_| VaultError::serialization_error("Failed to decode vault file"))?;
+        return rmp_serde::from_slice(&packed)
+            .map_err(|_| VaultError::serialization_error("Failed to decode vault file"));
+    }
+
+    if text.starts_with(STORED_BINARY_MARKER) {
+        return Err(VaultError::serialization_error(
+            "Vault file written by a newer version",
+        ));
+    }
+
+    serde_json::from_str(text)
+        .map_err(|_| VaultError::serialization_error("Failed to decode vault file"))
+}
+
+/// Serde adapter writing bytes as a binary in binary formats and as an
+/// array of numbers in human-readable ones, so JSON exports and the values
+/// posted to JavaScript keep their shape. Both forms are read back.
+pub(crate) mod compact_bytes {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            bytes.serialize(serializer)
+        } else {
+            serializer.serialize_bytes(bytes)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        if deserializer.is_human_readable() {
+            Vec::deserialize(deserializer)
+        } else {
+            serde_bytes::deserialize(deserializer)
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::vault::types::{IdentitySalts, VaultMetadata};
-    use std::collections::HashMap;
+    use crate::domain::vault::types::{Compression, IdentitySalts, VaultMetadata};
+    use std::collections::BTreeMap;
 
     #[test]
     fn test_serialize_vault() {
         let vault = Vault {
-            metadata: VaultMetadata { peer_id: None },
+            metadata: VaultMetadata {
+                peer_id: None,
+                sync_policy: Default::default(),
+            },
             identity_salts: IdentitySalts::new(),
-            username_pk: HashMap::new(),
-            namespaces: HashMap::new(),
+            username_pk: BTreeMap::new(),
+            namespaces: BTreeMap::new(),
             sync_enabled: false,
+            observer: false,
+            frozen: false,
+            retention: BTreeMap::new(),
+            residency: BTreeMap::new(),
+            chunks: BTreeMap::new(),
+            max_namespace_bytes: None,
+            manifest: None,
+            compression: Compression::None,
+            journal_sequence: 0,
+            stored_digests: BTreeMap::new(),
         };
 
         let result = serialize_vault(&vault);
@@ -72,11 +169,22 @@ mod tests {
         let vault = Vault {
             metadata: VaultMetadata {
                 peer_id: Some("test-peer".to_string()),
+                sync_policy: Default::default(),
             },
             identity_salts: IdentitySalts::new(),
-            username_pk: HashMap::new(),
-            namespaces: HashMap::new(),
+            username_pk: BTreeMap::new(),
+            namespaces: BTreeMap::new(),
             sync_enabled: true,
+            observer: false,
+            frozen: false,
+            retention: BTreeMap::new(),
+            residency: BTreeMap::new(),
+            chunks: BTreeMap::new(),
+            max_namespace_bytes: None,
+            manifest: None,
+            compression: Compression::None,
+            journal_sequence: 0,
+            stored_digests: BTreeMap::new(),
         };
 
         let bytes = serialize_vault(&vault).unwrap();
@@ -100,6 +208,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_stored_files_are_binary_and_read_json() {
+        use crate::domain::vault::types::NamespaceData;
+
+        let namespace = NamespaceData {
+            data: vec![200u8; 4096],
+            expiration: None,
+            chunks: Vec::new(),
+            timelock: None,
+            updated_at: Some(1_700_000_000),
+            recipients: vec!["age1recipient".to_string()],
+            crdt: None,
+            integrity: None,
+            crypto_version: None,
+            compression: Compression::None,
+        };
+
+        let json = serde_json::to_string(&namespace).unwrap();
+        let stored = encode_stored(&namespace).unwrap();
+        assert!(stored.starts_with(STORED_BINARY_PREFIX));
+        assert!(stored.len() * 2 < json.len());
+
+        for text in [stored.as_str(), json.as_str()] {
+            let decoded: NamespaceData = decode_stored(text).unwrap();
+            assert_eq!(decoded.data, namespace.data);
+            assert_eq!(decoded.updated_at, namespace.updated_at);
+            assert_eq!(decoded.recipients, namespace.recipients);
+        }
+
+        assert!(matches!(
+            decode_stored::<NamespaceData>("hoddor-mp2:AAAA"),
+            Err(VaultError::SerializationError(msg)) if msg.contains("newer version")
+        ));
+    }
+
     #[test]
     fn test_deserialize_vault_too_short() {
         let short_bytes = b"VAULT1";
@@ -127,18 +270,29 @@ mod tests {
 
     #[test]
     fn test_roundtrip_serialization() {
-        let mut username_pk = HashMap::new();
+        let mut username_pk = BTreeMap::new();
         username_pk.insert("user1".to_string(), "pk1".to_string());
         username_pk.insert("user2".to_string(), "pk2".to_string());
 
         let vault = Vault {
             metadata: VaultMetadata {
                 peer_id: Some("peer-123".to_string()),
+                sync_policy: Default::default(),
             },
             identity_salts: IdentitySalts::new(),
             username_pk,
-            namespaces: HashMap::new(),
+            namespaces: BTreeMap::new(),
             sync_enabled: true,
+            observer: false,
+            frozen: false,
+            retention: BTreeMap::new(),
+            residency: BTreeMap::new(),
+            chunks: BTreeMap::new(),
+            max_namespace_bytes: None,
+            manifest: None,
+            compression: Compression::None,
+            journal_sequence: 0,
+            stored_digests: BTreeMap::new(),
         };
 
         let serialized = serialize_vault(&vault).unwrap();
@@ -153,16 +307,81 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_serialization_ignores_insertion_order() {
+        use crate::domain::vault::types::NamespaceData;
+
+        let build = |names: &[&str]| {
+            let mut identity_salts = IdentitySalts::new();
+            let mut namespaces = BTreeMap::new();
+            for name in names {
+                identity_salts.set_salt(format!("pk-{name}"), [0u8; 32]);
+                namespaces.insert(
+                    name.to_string(),
+                    NamespaceData {
+                        data: name.as_bytes().to_vec(),
+                        expiration: None,
+                        chunks: Vec::new(),
+                        timelock: None,
+                        updated_at: None,
+                        recipients: Vec::new(),
+                        crdt: None,
+                        integrity: None,
+                        crypto_version: None,
+                        compression: Compression::None,
+                    },
+                );
+            }
+
+            Vault {
+                metadata: VaultMetadata {
+                    peer_id: None,
+                    sync_policy: Default::default(),
+                },
+                identity_salts,
+                username_pk: BTreeMap::new(),
+                namespaces,
+                sync_enabled: false,
+                observer: false,
+                frozen: false,
+                retention: BTreeMap::new(),
+                residency: BTreeMap::new(),
+                chunks: BTreeMap::new(),
+                max_namespace_bytes: None,
+                manifest: None,
+                compression: Compression::None,
+                journal_sequence: 0,
+                stored_digests: BTreeMap::new(),
+            }
+        };
+
+        let forward = serialize_vault(&build(&["a", "b", "c", "d", "e"])).unwrap();
+        let backward = serialize_vault(&build(&["e", "d", "c", "b", "a"])).unwrap();
+
+        assert_eq!(forward, backward);
+    }
+
     #[test]
     fn test_export_format_is_extension_agnostic() {
         let vault = Vault {
             metadata: VaultMetadata {
                 peer_id: Some("test-peer".to_string()),
+                sync_policy: Default::default(),
             },
             identity_salts: IdentitySalts::new(),
-            username_pk: HashMap::new(),
-            namespaces: HashMap::new(),
+            username_pk: BTreeMap::new(),
+            namespaces: BTreeMap::new(),
             sync_enabled: true,
+            observer: false,
+            frozen: false,
+            retention: BTreeMap::new(),
+            residency: BTreeMap::new(),
+            chunks: BTreeMap::new(),
+            max_namespace_bytes: None,
+            manifest: None,
+            compression: Compression::None,
+            journal_sequence: 0,
+            stored_digests: BTreeMap::new(),
         };
 
         let exported_bytes = serialize_vault(&vault).unwrap();
@@ -181,11 +400,22 @@ mod tests {
         let vault = Vault {
             metadata: VaultMetadata {
                 peer_id: Some("legacy-peer".to_string()),
+                sync_policy: Default::default(),
             },
             identity_salts: IdentitySalts::new(),
-            username_pk: HashMap::new(),
-            namespaces: HashMap::new(),
+            username_pk: BTreeMap::new(),
+            namespaces: BTreeMap::new(),
             sync_enabled: false,
+            observer: false,
+            frozen: false,
+            retention: BTreeMap::new(),
+            residency: BTreeMap::new(),
+            chunks: BTreeMap::new(),
+            max_namespace_bytes: None,
+            manifest: None,
+            compression: Compression::None,
+            journal_sequence: 0,
+            stored_digests: BTreeMap::new(),
         };
 
         let exported = serialize_vault(&vault).unwrap();
@@ -199,11 +429,24 @@ mod tests {
     #[test]
     fn test_vault_magic_number_provides_format_detection() {
         let valid_vault = Vault {
-            metadata: VaultMetadata { peer_id: None },
+            metadata: VaultMetadata {
+                peer_id: None,
+                sync_policy: Default::default(),
+            },
             identity_salts: IdentitySalts::new(),
-            username_pk: HashMap::new(),
-            namespaces: HashMap::new(),
+            username_pk: BTreeMap::new(),
+            namespaces: BTreeMap::new(),
             sync_enabled: false,
+            observer: false,
+            frozen: false,
+            retention: BTreeMap::new(),
+            residency: BTreeMap::new(),
+            chunks: BTreeMap::new(),
+            max_namespace_bytes: None,
+            manifest: None,
+            compression: Compression::None,
+            journal_sequence: 0,
+            stored_digests: BTreeMap::new(),
         };
 
         let valid_bytes = serialize_vault(&valid_vault).unwrap();
@@ -224,11 +467,24 @@ mod tests {
     #[test]
     fn test_export_format_stability() {
         let vault = Vault {
-            metadata: VaultMetadata { peer_id: None },
+            metadata: VaultMetadata {
+                peer_id: None,
+                sync_policy: Default::default(),
+            },
             identity_salts: IdentitySalts::new(),
-            username_pk: HashMap::new(),
-            namespaces: HashMap::new(),
+            username_pk: BTreeMap::new(),
+            namespaces: BTreeMap::new(),
             sync_enabled: false,
+            observer: false,
+            frozen: false,
+            retention: BTreeMap::new(),
+            residency: BTreeMap::new(),
+            chunks: BTreeMap::new(),
+            max_namespace_bytes: None,
+            manifest: None,
+            compression: Compression::None,
+            journal_sequence: 0,
+            stored_digests: BTreeMap::new(),
         };
 
         let export1 = serialize_vault(&vault).unwrap();
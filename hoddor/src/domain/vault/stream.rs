@@ -0,0 +1,387 @@
+//! Streaming namespace writes and reads.
+//!
+//! [`upsert_namespace`](super::operations::upsert_namespace) and
+//! [`read_namespace`](super::operations::read_namespace) hold the whole
+//! payload, its ciphertext and the rest of the vault in memory. A
+//! [`NamespaceWriter`] instead encrypts pieces as they arrive and writes
+//! each one to the chunk store right away, then the namespace file listing
+//! them once the stream ends; a [`NamespaceReader`] decrypts one chunk file
+//! at a time. Neither loads the vault beyond its metadata, so memory use
+//! stays at a piece or two whatever the payload size.
+//!
+//! Pieces are age files of their own, each authenticated through age's
+//! STREAM construction, and the order of the pieces is fixed by the
+//! namespace file. Chunks of a stream interrupted before
+//! [`NamespaceWriter::finish`] are unreferenced and go away at the next
+//! garbage collection. Whole-vault operations, such as export or identity
+//! rotation, still load every chunk.
+
+use super::chunks;
+use super::compression::Decompressor;
+use super::error::VaultError;
+use super::migration::CURRENT_CRYPTO_VERSION;
+use super::operations::{
+    current_timestamp, ensure_not_recipient, get_namespace_filename, lock_namespace,
+    read_vault_metadata,
+};
+use super::serialization::{decode_stored, encode_stored};
+use super::types::{Compression, Expiration, NamespaceData, Vault};
+use crate::platform::Platform;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::collections::VecDeque;
+
+/// Encrypts a namespace payload piece by piece as it is written.
+pub struct NamespaceWriter<'a> {
+    platform: &'a Platform,
+    vault_name: String,
+    namespace: String,
+    metadata: Vault,
+    keys: Vec<String>,
+    recipients: Vec<String>,
+    expires_in_seconds: Option<i64>,
+    piece_size: usize,
+    buffer: Vec<u8>,
+    ids: Vec<String>,
+    written: usize,
+}
+
+impl<'a> NamespaceWriter<'a> {
+    /// Starts writing `namespace`. Nothing is visible to readers until
+    /// [`finish`](Self::finish).
+    pub async fn open(
+        platform: &'a Platform,
+        vault_name: &str,
+        identity_public_key: &str,
+        namespace: &str,
+        expires_in_seconds: Option<i64>,
+        replace_if_exists: bool,
+    ) -> Result<Self, VaultError> {
+        let metadata = read_vault_metadata(platform, vault_name).await?;
+
+        if metadata.observer {
+            return Err(VaultError::ObserverVault);
+        }
+        if metadata.frozen {
+            return Err(VaultError::FrozenVault);
+        }
+
+        let existing = read_namespace_file(platform, vault_name, namespace).await?;
+        if existing.is_some() && !replace_if_exists {
+            return Err(VaultError::NamespaceAlreadyExists);
+        }
+        ensure_not_recipient(existing.as_ref(), identity_public_key)?;
+        let recipients = existing.map(|data| data.recipients).unwrap_or_default();
+
+        platform
+            .storage()
+            .create_directory(&chunks::chunks_path(vault_name))
+            .await?;
+
+        // A stream is always split, whatever the split threshold says.
+        let piece_size = match chunks::split_threshold() {
+            0 => chunks::DEFAULT_SPLIT_THRESHOLD,
+            threshold => threshold,
+        };
+
+        Ok(Self {
+            platform,
+            vault_name: vault_name.to_string(),
+            namespace: namespace.to_string(),
+            metadata,
+            keys: std::iter::once(identity_public_key.to_string())
+                .chain(recipients.iter().cloned())
+                .collect(),
+            recipients,
+            expires_in_seconds,
+            piece_size: usize::try_from(piece_size).unwrap_or(usize::MAX),
+            buffer: Vec::new(),
+            ids: Vec::new(),
+            written: 0,
+        })
+    }
+
+    /// Appends `data` to the payload, storing every piece it completes.
+    pub async fn write(&mut self, data: &[u8]) -> Result<(), VaultError> {
+        self.written += data.len();
+        // Only the metadata is loaded, so the usage reported on failure
+        // leaves out what the vault already stores.
+        super::limits::ensure_within_limit(&self.metadata, self.written)?;
+
+        self.buffer.extend_from_slice(data);
+
+        while self.buffer.len() >= self.piece_size {
+            let rest = self.buffer.split_off(self.piece_size);
+            let piece = std::mem::replace(&mut self.buffer, rest);
+            self.store_piece(&piece).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Stores what is left of the payload and the namespace file referencing
+    /// every piece, replacing the previous version of the namespace.
+    pub async fn finish(mut self) -> Result<(), VaultError> {
+        if !self.buffer.is_empty() || self.ids.is_empty() {
+            let piece = std::mem::take(&mut self.buffer);
+            self.store_piece(&piece).await?;
+        }
+
+        let _guards = lock_namespace(self.platform, &self.vault_name, &self.namespace).await?;
+
+        // The vault may have been frozen while the stream was written.
+        if read_vault_metadata(self.platform, &self.vault_name)
+            .await?
+            .frozen
+        {
+            return Err(VaultError::FrozenVault);
+        }
+
+        let now = current_timestamp(self.platform);
+        let namespace_data = NamespaceData {
+            data: Vec::new(),
+            expiration: self.expires_in_seconds.map(|secs| Expiration {
+                expires_at: now + secs,
+            }),
+            chunks: self.ids,
+            timelock: None,
+            updated_at: Some(now),
+            recipients: self.recipients,
+            crdt: None,
+            integrity: None,
+            crypto_version: Some(CURRENT_CRYPTO_VERSION),
+            compression: Compression::None,
+        };
+
+        // A single atomic write, after every chunk is stored: no journal.
+        self.platform
+            .storage()
+            .write_file(
+                &namespace_path(&self.vault_name, &self.namespace),
+                &encode_stored(&namespace_data)?,
+            )
+            .await?;
+
+        let _ = self.platform.notifier().notify_event(
+            &self.vault_name,
+            crate::notifications::EventType::VaultUpdate,
+            &format!("Streamed namespace {}", self.namespace),
+        );
+
+        Ok(())
+    }
+
+    async fn store_piece(&mut self, piece: &[u8]) -> Result<(), VaultError> {
+        let keys: Vec<&str> = self.keys.iter().map(String::as_str).collect();
+        let encrypted = crate::domain::crypto::encrypt_for_recipients(self.platform, piece, &keys)
+            .await
+            .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+        let id = chunks::random_chunk_id();
+        self.platform
+            .storage()
+            .write_file(
+                &format!("{}/{id}", chunks::chunks_path(&self.vault_name)),
+                &chunks::encode_chunk(&encrypted),
+            )
+            .await?;
+        self.ids.push(id);
+
+        Ok(())
+    }
+}
+
+/// Decrypts a namespace payload one stored piece at a time.
+pub struct NamespaceReader<'a> {
+    platform: &'a Platform,
+    vault_name: String,
+    identity_private_key: String,
+    inline: Option<Vec<u8>>,
+    ids: VecDeque<String>,
+    /// Taken once the last piece has been decrypted.
+    decompressor: Option<Decompressor>,
+}
+
+impl<'a> NamespaceReader<'a> {
+    pub async fn open(
+        platform: &'a Platform,
+        vault_name: &str,
+        identity_private_key: &str,
+        namespace: &str,
+    ) -> Result<Self, VaultError> {
+        let metadata = read_vault_metadata(platform, vault_name).await?;
+
+        if metadata.observer {
+            return Err(VaultError::ObserverVault);
+        }
+
+        let namespace_data = read_namespace_file(platform, vault_name, namespace)
+            .await?
+            .ok_or(VaultError::NamespaceNotFound)?;
+
+        if let Some(timelock) = &namespace_data.timelock {
+            return Err(VaultError::TimeLocked(timelock.release_at));
+        }
+        // Expired namespaces are removed by the next regular read or cleanup.
+        if let Some(expiration) = &namespace_data.expiration {
+            if current_timestamp(platform) >= expiration.expires_at {
+                return Err(VaultError::DataExpired);
+            }
+        }
+
+        let inline = namespace_data
+            .chunks
+            .is_empty()
+            .then_some(namespace_data.data);
+
+        Ok(Self {
+            platform,
+            vault_name: vault_name.to_string(),
+            identity_private_key: identity_private_key.to_string(),
+            inline,
+            ids: namespace_data.chunks.into(),
+            decompressor: Some(Decompressor::new(namespace_data.compression)),
+        })
+    }
+
+    /// The next piece of the payload, or `None` once it has all been read.
+    /// Pieces of a compressed namespace are decompressed as they come, and
+    /// may be empty.
+    pub async fn next_piece(&mut self) -> Result<Option<Vec<u8>>, VaultError> {
+        let encrypted = match self.inline.take() {
+            Some(data) => data,
+            None => {
+                let Some(id) = self.ids.pop_front() else {
+                    return match self.decompressor.take() {
+                        Some(Decompressor::None) | None => Ok(None),
+                        Some(decompressor) => Ok(Some(decompressor.finish()?)),
+                    };
+                };
+                let text = self
+                    .platform
+                    .storage()
+                    .read_file(&format!("{}/{id}", chunks::chunks_path(&self.vault_name)))
+                    .await?;
+                STANDARD
+                    .decode(text)
+                    .map_err(|_| VaultError::serialization_error("Failed to decode chunk"))?
+            }
+        };
+
+        let piece = crate::domain::crypto::decrypt_with_identity(
+            self.platform,
+            &encrypted,
+            &self.identity_private_key,
+        )
+        .await
+        .map_err(|_| VaultError::InvalidPassword)?;
+
+        match &mut self.decompressor {
+            Some(decompressor) => Ok(Some(decompressor.push(&piece)?)),
+            None => Ok(Some(piece)),
+        }
+    }
+}
+
+fn namespace_path(vault_name: &str, namespace: &str) -> String {
+    format!("{vault_name}/{}", get_namespace_filename(namespace))
+}
+
+async fn read_namespace_file(
+    platform: &Platform,
+    vault_name: &str,
+    namespace: &str,
+) -> Result<Option<NamespaceData>, VaultError> {
+    let storage = platform.storage();
+    let filename = get_namespace_filename(namespace);
+
+    if !storage.list_entries(vault_name).await?.contains(&filename) {
+        return Ok(None);
+    }
+
+    let text = storage
+        .read_file(&namespace_path(vault_name, namespace))
+        .await?;
+    Ok(Some(decode_stored(&text)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{
+        create_vault, delete_vault, read_namespace, save_vault, upsert_namespace,
+    };
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_streamed_namespace_round_trips() {
+        let platform = Platform::new();
+        let vault_name = "stream_test";
+        let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+        let public_key = crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let data: Vec<u8> = (0..chunks::DEFAULT_SPLIT_THRESHOLD as usize * 2 + 100)
+                .map(|i| (i % 251) as u8)
+                .collect();
+
+            let mut writer =
+                NamespaceWriter::open(&platform, vault_name, &public_key, "blob", None, false)
+                    .await
+                    .unwrap();
+            for part in data.chunks(700_000) {
+                writer.write(part).await.unwrap();
+            }
+            writer.finish().await.unwrap();
+
+            let mut reader = NamespaceReader::open(&platform, vault_name, &identity, "blob")
+                .await
+                .unwrap();
+            let mut pieces = 0;
+            let mut read = Vec::new();
+            while let Some(piece) = reader.next_piece().await.unwrap() {
+                pieces += 1;
+                read.extend(piece);
+            }
+            assert_eq!(pieces, 3);
+            assert_eq!(read, data);
+
+            // Streamed namespaces are regular chunked namespaces.
+            assert_eq!(
+                read_namespace(&platform, vault_name, &identity, "blob")
+                    .await
+                    .unwrap(),
+                data
+            );
+
+            upsert_namespace(
+                &platform,
+                vault_name,
+                &public_key,
+                "small",
+                b"inline".to_vec(),
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+            let mut reader = NamespaceReader::open(&platform, vault_name, &identity, "small")
+                .await
+                .unwrap();
+            assert_eq!(reader.next_piece().await.unwrap().unwrap(), b"inline");
+            assert_eq!(reader.next_piece().await.unwrap(), None);
+
+            assert!(matches!(
+                NamespaceWriter::open(&platform, vault_name, &public_key, "small", None, false)
+                    .await,
+                Err(VaultError::NamespaceAlreadyExists)
+            ));
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
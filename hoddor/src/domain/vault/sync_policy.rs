@@ -0,0 +1,162 @@
+//! Which namespaces of a vault take part in sync.
+//!
+//! The policy lives in the vault metadata. A namespace is synced when it
+//! matches an `include` entry, or when `include` is empty, and matches no
+//! `exclude` entry. Entries are namespace names or glob patterns where `*`
+//! stands for any run of characters and `?` for a single one. The policy
+//! applies both ways: excluded namespaces are never sent, and updates
+//! received for them are dropped. Vaults created from sync start with the
+//! policy of the peer they were created from.
+
+use super::error::VaultError;
+use super::operations::{lock_vault, read_vault, read_vault_metadata, write_vault};
+use crate::platform::Platform;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncPolicy {
+    /// Namespaces or patterns synced; every namespace when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Namespaces or patterns never synced, even when included.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+}
+
+impl SyncPolicy {
+    /// Whether the policy lets every namespace through.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Whether `namespace` may be sent to peers and accepted from them.
+    pub fn allows(&self, namespace: &str) -> bool {
+        let included = self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| glob_matches(pattern, namespace));
+
+        included
+            && !self
+                .exclude
+                .iter()
+                .any(|pattern| glob_matches(pattern, namespace))
+    }
+}
+
+pub async fn set_sync_policy(
+    platform: &Platform,
+    vault_name: &str,
+    policy: SyncPolicy,
+) -> Result<(), VaultError> {
+    let _guard = lock_vault(platform, vault_name).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    vault.metadata.sync_policy = policy;
+
+    write_vault(platform, vault_name, vault, Vec::new()).await
+}
+
+pub async fn sync_policy(platform: &Platform, vault_name: &str) -> Result<SyncPolicy, VaultError> {
+    let vault = read_vault_metadata(platform, vault_name).await?;
+
+    Ok(vault.metadata.sync_policy)
+}
+
+// Matches with backtracking to the last `*` only, which is enough for
+// patterns made of literals, `*` and `?`.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{create_vault, delete_vault, save_vault};
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_patterns_include_then_exclude() {
+        assert!(glob_matches("notes", "notes"));
+        assert!(glob_matches("notes-*", "notes-2024"));
+        assert!(glob_matches("*-draft", "a-b-draft"));
+        assert!(glob_matches("log@2024-??-01", "log@2024-03-01"));
+        assert!(!glob_matches("notes-*", "notes"));
+        assert!(!glob_matches("?", ""));
+
+        assert!(SyncPolicy::default().allows("anything"));
+
+        let policy = SyncPolicy {
+            include: vec!["shared-*".to_string(), "settings".to_string()],
+            exclude: vec!["*-draft".to_string()],
+        };
+        assert!(policy.allows("shared-photos"));
+        assert!(policy.allows("settings"));
+        assert!(!policy.allows("shared-draft"));
+        assert!(!policy.allows("private"));
+
+        let policy = SyncPolicy {
+            include: Vec::new(),
+            exclude: vec!["private".to_string()],
+        };
+        assert!(policy.allows("settings"));
+        assert!(!policy.allows("private"));
+    }
+
+    #[test]
+    fn test_policy_is_stored_in_metadata() {
+        let platform = Platform::new();
+        let vault_name = "sync_policy_test";
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+            assert!(sync_policy(&platform, vault_name).await.unwrap().is_empty());
+
+            let policy = SyncPolicy {
+                include: vec!["shared-*".to_string()],
+                exclude: Vec::new(),
+            };
+            set_sync_policy(&platform, vault_name, policy.clone())
+                .await
+                .unwrap();
+            assert_eq!(sync_policy(&platform, vault_name).await.unwrap(), policy);
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,320 @@
+//! Sync settings of a vault, portable between devices.
+//!
+//! The profile gathers what a device needs before it can sync: where to
+//! signal, which ICE servers to use and which peers are trusted with which
+//! namespaces. It is stored in `sync_profile.json` next to the namespaces and
+//! travels between devices as a blob encrypted to a passphrase.
+
+use super::error::VaultError;
+use crate::domain::crypto;
+use crate::platform::Platform;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const SYNC_PROFILE_FILENAME: &str = "sync_profile.json";
+const SYNC_PROFILE_MAGIC_NUMBER: &[u8; 6] = b"HSYNC1";
+const SALT_LENGTH: usize = 32;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub enum AccessLevel {
+    Viewer,
+    Contributor,
+    Administrator,
+}
+
+/// A STUN or TURN server. TURN servers (`turn:` and `turns:` URLs) relay
+/// the traffic of peers that cannot reach each other directly, e.g. behind
+/// symmetric NATs, and require `username` and `credential`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IceServer {
+    pub urls: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential: Option<String>,
+}
+
+impl IceServer {
+    /// A server from its bare URL, as the former STUN-only configuration
+    /// listed them.
+    pub fn from_url(url: impl Into<String>) -> Self {
+        Self {
+            urls: vec![url.into()],
+            ..Default::default()
+        }
+    }
+
+    pub fn is_turn(&self) -> bool {
+        self.urls
+            .iter()
+            .any(|url| url.starts_with("turn:") || url.starts_with("turns:"))
+    }
+
+    pub fn validate(&self) -> Result<(), VaultError> {
+        if self.urls.is_empty() {
+            return Err(VaultError::io_error("ICE server has no URL"));
+        }
+        if self.is_turn() && (self.username.is_none() || self.credential.is_none()) {
+            return Err(VaultError::io_error(format!(
+                "TURN server {} needs a username and a credential",
+                self.urls.join(", ")
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Candidates ICE may use, as in `RTCConfiguration.iceTransportPolicy`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IceTransportPolicy {
+    #[default]
+    All,
+    /// Only TURN relays, hiding the devices' addresses from each other.
+    Relay,
+}
+
+impl IceTransportPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IceTransportPolicy::All => "all",
+            IceTransportPolicy::Relay => "relay",
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        *self == IceTransportPolicy::All
+    }
+}
+
+/// Checks `ice_servers` can serve `policy`: every TURN server has
+/// credentials, and a relay-only policy has a TURN server to relay through.
+pub fn validate_ice_config(
+    ice_servers: &[IceServer],
+    policy: IceTransportPolicy,
+) -> Result<(), VaultError> {
+    for server in ice_servers {
+        server.validate()?;
+    }
+
+    if policy == IceTransportPolicy::Relay && !ice_servers.iter().any(IceServer::is_turn) {
+        return Err(VaultError::io_error(
+            "The relay ICE transport policy needs a TURN server",
+        ));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrustedPeer {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Access granted per namespace.
+    #[serde(default)]
+    pub permissions: BTreeMap<String, AccessLevel>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SyncProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signaling_url: Option<String>,
+    #[serde(default)]
+    pub ice_servers: Vec<IceServer>,
+    #[serde(default, skip_serializing_if = "IceTransportPolicy::is_default")]
+    pub ice_transport_policy: IceTransportPolicy,
+    /// Trusted peers by peer id.
+    #[serde(default)]
+    pub trusted_peers: BTreeMap<String, TrustedPeer>,
+}
+
+fn sync_profile_path(vault_name: &str) -> String {
+    format!("{vault_name}/{SYNC_PROFILE_FILENAME}")
+}
+
+/// Profile of `vault_name`, empty when none was saved.
+pub async fn load_sync_profile(
+    platform: &Platform,
+    vault_name: &str,
+) -> Result<SyncProfile, VaultError> {
+    match platform
+        .storage()
+        .read_file(&sync_profile_path(vault_name))
+        .await
+    {
+        Ok(json) => serde_json::from_str(&json)
+            .map_err(|_| VaultError::serialization_error("Failed to parse sync profile")),
+        Err(_) => Ok(SyncProfile::default()),
+    }
+}
+
+pub async fn save_sync_profile(
+    platform: &Platform,
+    vault_name: &str,
+    profile: &SyncProfile,
+) -> Result<(), VaultError> {
+    if !platform.storage().directory_exists(vault_name).await? {
+        return Err(VaultError::VaultNotFound);
+    }
+    validate_ice_config(&profile.ice_servers, profile.ice_transport_policy)?;
+
+    let json = serde_json::to_string(profile)
+        .map_err(|_| VaultError::serialization_error("Failed to serialize sync profile"))?;
+
+    platform
+        .storage()
+        .write_file(&sync_profile_path(vault_name), &json)
+        .await
+}
+
+/// Encrypts the profile of `vault_name` to `passphrase`.
+pub async fn export_sync_profile(
+    platform: &Platform,
+    vault_name: &str,
+    passphrase: &str,
+) -> Result<Vec<u8>, VaultError> {
+    let profile = load_sync_profile(platform, vault_name).await?;
+    let json = serde_json::to_vec(&profile)
+        .map_err(|_| VaultError::serialization_error("Failed to serialize sync profile"))?;
+
+    let mut salt = [0u8; SALT_LENGTH];
+    rand::thread_rng().fill_bytes(&mut salt);
+
+    let identity = crypto::identity_from_passphrase(platform, passphrase, &salt)
+        .await
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+    let recipient = crypto::identity_to_public(platform, &identity)
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+    let encrypted = crypto::encrypt_for_recipients(platform, &json, &[&recipient])
+        .await
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+    let mut blob =
+        Vec::with_capacity(SYNC_PROFILE_MAGIC_NUMBER.len() + SALT_LENGTH + encrypted.len());
+    blob.extend_from_slice(SYNC_PROFILE_MAGIC_NUMBER);
+    blob.extend_from_slice(&salt);
+    blob.extend_from_slice(&encrypted);
+
+    Ok(blob)
+}
+
+/// Decrypts a blob made by [`export_sync_profile`] and saves it as the
+/// profile of `vault_name`, replacing the current one.
+pub async fn import_sync_profile(
+    platform: &Platform,
+    vault_name: &str,
+    passphrase: &str,
+    blob: &[u8],
+) -> Result<SyncProfile, VaultError> {
+    let header_length = SYNC_PROFILE_MAGIC_NUMBER.len() + SALT_LENGTH;
+    if blob.len() <= header_length
+        || &blob[..SYNC_PROFILE_MAGIC_NUMBER.len()] != SYNC_PROFILE_MAGIC_NUMBER
+    {
+        return Err(VaultError::serialization_error(
+            "Invalid sync profile: missing or incorrect magic number",
+        ));
+    }
+
+    let salt = &blob[SYNC_PROFILE_MAGIC_NUMBER.len()..header_length];
+    let identity = crypto::identity_from_passphrase(platform, passphrase, salt)
+        .await
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+    let json = crypto::decrypt_with_identity(platform, &blob[header_length..], &identity)
+        .await
+        .map_err(|_| VaultError::InvalidPassword)?;
+
+    let profile: SyncProfile = serde_json::from_slice(&json)
+        .map_err(|_| VaultError::serialization_error("Failed to parse sync profile"))?;
+
+    save_sync_profile(platform, vault_name, &profile).await?;
+
+    Ok(profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{create_vault, delete_vault, save_vault};
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_ice_config_requires_turn_credentials_and_relays() {
+        let stun = IceServer::from_url("stun:stun.example:3478");
+        let mut turn = IceServer::from_url("turn:turn.example:3478?transport=tcp");
+
+        assert!(validate_ice_config(std::slice::from_ref(&stun), IceTransportPolicy::All).is_ok());
+        assert!(validate_ice_config(std::slice::from_ref(&turn), IceTransportPolicy::All).is_err());
+
+        turn.username = Some("device".to_string());
+        turn.credential = Some("secret".to_string());
+        assert!(validate_ice_config(&[stun.clone(), turn], IceTransportPolicy::Relay).is_ok());
+        assert!(validate_ice_config(&[stun], IceTransportPolicy::Relay).is_err());
+        assert!(validate_ice_config(&[IceServer::default()], IceTransportPolicy::All).is_err());
+    }
+
+    #[test]
+    fn test_sync_profile_roundtrips_through_passphrase() {
+        let platform = Platform::new();
+        let source = "sync_profile_source";
+        let target = "sync_profile_target";
+
+        block_on(async {
+            for vault_name in [source, target] {
+                let _ = delete_vault(&platform, vault_name).await;
+                save_vault(&platform, vault_name, create_vault().await.unwrap())
+                    .await
+                    .unwrap();
+            }
+
+            let mut permissions = BTreeMap::new();
+            permissions.insert("notes".to_string(), AccessLevel::Contributor);
+            let mut trusted_peers = BTreeMap::new();
+            trusted_peers.insert(
+                "laptop".to_string(),
+                TrustedPeer {
+                    label: Some("Work laptop".to_string()),
+                    permissions,
+                },
+            );
+            let profile = SyncProfile {
+                signaling_url: Some("wss://signal.example".to_string()),
+                ice_servers: vec![
+                    IceServer::from_url("stun:stun.example:3478"),
+                    IceServer {
+                        urls: vec!["turns:turn.example:5349".to_string()],
+                        username: Some("device".to_string()),
+                        credential: Some("secret".to_string()),
+                    },
+                ],
+                ice_transport_policy: IceTransportPolicy::Relay,
+                trusted_peers,
+            };
+            save_sync_profile(&platform, source, &profile)
+                .await
+                .unwrap();
+
+            let blob = export_sync_profile(&platform, source, "correct horse")
+                .await
+                .unwrap();
+            assert!(matches!(
+                import_sync_profile(&platform, target, "wrong horse", &blob).await,
+                Err(VaultError::InvalidPassword)
+            ));
+            assert_eq!(
+                load_sync_profile(&platform, target).await.unwrap(),
+                SyncProfile::default()
+            );
+
+            let imported = import_sync_profile(&platform, target, "correct horse", &blob)
+                .await
+                .unwrap();
+            assert_eq!(imported, profile);
+            assert_eq!(load_sync_profile(&platform, target).await.unwrap(), profile);
+
+            for vault_name in [source, target] {
+                delete_vault(&platform, vault_name).await.unwrap();
+            }
+        });
+    }
+}
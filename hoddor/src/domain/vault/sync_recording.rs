@@ -0,0 +1,484 @@
+//! Recording of sync traffic, to debug replicas that fail to converge.
+//!
+//! While recording is on, every sync message a vault sends or receives is
+//! stored under `sync_recording/` next to its namespaces, one file per
+//! message, encrypted to the public key given when recording started. That
+//! key may belong to whoever investigates: a user can then hand over the
+//! export of the recording without giving away an identity.
+//!
+//! An export holds one record per line, each the base64 of its age
+//! ciphertext, in the order the messages were recorded. [`replay`] rebuilds
+//! the namespaces of the recording replica from the records, step by step.
+
+use super::error::VaultError;
+use crate::platform::Platform;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+const SETTINGS_FILENAME: &str = "sync_recording.json";
+const RECORDS_DIRECTORY: &str = "sync_recording";
+const RECORD_EXTENSION: &str = ".rec";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncDirection {
+    Sent,
+    Received,
+}
+
+/// A sync message as it went over the wire.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedSync {
+    pub sequence: u64,
+    /// Milliseconds since the Unix epoch on the recording device.
+    pub recorded_at: i64,
+    pub direction: SyncDirection,
+    /// The serialized sync message.
+    #[serde(with = "serde_bytes")]
+    pub message: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordingSettings {
+    recipient: String,
+}
+
+fn settings_path(vault_name: &str) -> String {
+    format!("{vault_name}/{SETTINGS_FILENAME}")
+}
+
+fn records_path(vault_name: &str) -> String {
+    format!("{vault_name}/{RECORDS_DIRECTORY}")
+}
+
+fn recording_lock_name(vault_name: &str) -> String {
+    format!("{vault_name}/{RECORDS_DIRECTORY}")
+}
+
+/// Starts recording the sync messages of `vault_name`, encrypted to
+/// `recipient_public_key`. Records kept from an earlier recording stay.
+pub async fn start_sync_recording(
+    platform: &Platform,
+    vault_name: &str,
+    recipient_public_key: &str,
+) -> Result<(), VaultError> {
+    let recipient = crate::domain::crypto::parse_recipient(platform, recipient_public_key)
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+    let json = serde_json::to_string(&RecordingSettings { recipient })
+        .map_err(|_| VaultError::serialization_error("Failed to serialize recording settings"))?;
+
+    platform
+        .storage()
+        .write_file(&settings_path(vault_name), &json)
+        .await
+}
+
+/// Stops recording. The records stay until [`clear_sync_recording`].
+pub async fn stop_sync_recording(platform: &Platform, vault_name: &str) -> Result<(), VaultError> {
+    let _ = platform
+        .storage()
+        .delete_file(&settings_path(vault_name))
+        .await;
+    Ok(())
+}
+
+pub async fn clear_sync_recording(platform: &Platform, vault_name: &str) -> Result<(), VaultError> {
+    let _guard = platform
+        .locks()
+        .acquire(&recording_lock_name(vault_name))
+        .await?;
+
+    let storage = platform.storage();
+    if storage.directory_exists(&records_path(vault_name)).await? {
+        storage.delete_directory(&records_path(vault_name)).await?;
+    }
+    Ok(())
+}
+
+pub async fn is_recording_sync(platform: &Platform, vault_name: &str) -> bool {
+    recording_settings(platform, vault_name).await.is_some()
+}
+
+async fn recording_settings(platform: &Platform, vault_name: &str) -> Option<RecordingSettings> {
+    let json = platform
+        .storage()
+        .read_file(&settings_path(vault_name))
+        .await
+        .ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Records `message` when recording is on. Failures are logged: recording
+/// never gets in the way of sync itself. Only the wasm sync transport
+/// sends and receives messages.
+#[cfg(any(target_arch = "wasm32", test))]
+pub(crate) async fn record_sync_message(
+    platform: &Platform,
+    vault_name: &str,
+    direction: SyncDirection,
+    message: &[u8],
+) {
+    let Some(settings) = recording_settings(platform, vault_name).await else {
+        return;
+    };
+
+    if let Err(e) = append_record(platform, vault_name, &settings, direction, message).await {
+        tracing::warn!(vault = vault_name, error = %e, "Failed to record sync message");
+    }
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+async fn append_record(
+    platform: &Platform,
+    vault_name: &str,
+    settings: &RecordingSettings,
+    direction: SyncDirection,
+    message: &[u8],
+) -> Result<(), VaultError> {
+    let _guard = platform
+        .locks()
+        .acquire(&recording_lock_name(vault_name))
+        .await?;
+
+    let sequence = record_sequences(platform, vault_name)
+        .await?
+        .last()
+        .map_or(0, |last| last + 1);
+    let record = RecordedSync {
+        sequence,
+        recorded_at: platform.clock().now() as i64,
+        direction,
+        message: message.to_vec(),
+    };
+
+    let json = serde_json::to_vec(&record)
+        .map_err(|_| VaultError::serialization_error("Failed to serialize sync record"))?;
+    let encrypted =
+        crate::domain::crypto::encrypt_for_recipients(platform, &json, &[&settings.recipient])
+            .await
+            .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+    platform
+        .storage()
+        .write_file(
+            &format!(
+                "{}/{sequence:010}{RECORD_EXTENSION}",
+                records_path(vault_name)
+            ),
+            &STANDARD.encode(encrypted),
+        )
+        .await
+}
+
+// Sequences of the stored records, in increasing order.
+async fn record_sequences(platform: &Platform, vault_name: &str) -> Result<Vec<u64>, VaultError> {
+    let storage = platform.storage();
+    if !storage.directory_exists(&records_path(vault_name)).await? {
+        return Ok(Vec::new());
+    }
+
+    let mut sequences: Vec<u64> = storage
+        .list_entries(&records_path(vault_name))
+        .await?
+        .iter()
+        .filter_map(|entry| entry.strip_suffix(RECORD_EXTENSION)?.parse().ok())
+        .collect();
+    sequences.sort_unstable();
+
+    Ok(sequences)
+}
+
+/// The recording of `vault_name`, still encrypted, in the export format.
+pub async fn export_sync_recording(
+    platform: &Platform,
+    vault_name: &str,
+) -> Result<String, VaultError> {
+    let storage = platform.storage();
+    let paths: Vec<String> = record_sequences(platform, vault_name)
+        .await?
+        .iter()
+        .map(|sequence| {
+            format!(
+                "{}/{sequence:010}{RECORD_EXTENSION}",
+                records_path(vault_name)
+            )
+        })
+        .collect();
+
+    let mut export = String::new();
+    for record in storage.read_files(&paths).await {
+        export.push_str(record?.trim());
+        export.push('\n');
+    }
+
+    Ok(export)
+}
+
+/// Decrypts an export made by [`export_sync_recording`].
+pub async fn decrypt_sync_recording(
+    platform: &Platform,
+    export: &str,
+    identity_private_key: &str,
+) -> Result<Vec<RecordedSync>, VaultError> {
+    let mut records = Vec::new();
+
+    for (line, encoded) in export.lines().enumerate() {
+        if encoded.trim().is_empty() {
+            continue;
+        }
+
+        let encrypted = STANDARD.decode(encoded.trim()).map_err(|_| {
+            VaultError::serialization_error(format!("Record {} is not base64", line + 1))
+        })?;
+        let json = crate::domain::crypto::decrypt_with_identity(
+            platform,
+            &encrypted,
+            identity_private_key,
+        )
+        .await
+        .map_err(|_| VaultError::InvalidPassword)?;
+        let record: RecordedSync = serde_json::from_slice(&json).map_err(|_| {
+            VaultError::serialization_error(format!("Record {} is malformed", line + 1))
+        })?;
+
+        records.push(record);
+    }
+
+    records.sort_by_key(|record| record.sequence);
+    Ok(records)
+}
+
+/// State of a namespace on the recording replica, as far as the recorded
+/// messages tell.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReplayedNamespace {
+    /// SHA-256 of the ciphertext, as in vault diffs.
+    pub revision: String,
+    /// Author of the last message carrying the namespace.
+    pub author: String,
+    /// Seconds since the Unix epoch on the author's clock.
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReplayStep {
+    pub sequence: u64,
+    pub recorded_at: i64,
+    pub direction: SyncDirection,
+    pub author: String,
+    pub namespace: String,
+    /// `Insert`, `Update` or `Delete`, as in the sync message.
+    pub operation: String,
+    pub vector_clock: BTreeMap<String, u64>,
+    /// Revision of the namespace after the step, `None` once deleted.
+    pub revision: Option<String>,
+    /// Whether the step changed the namespace.
+    pub changed: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Replay {
+    pub steps: Vec<ReplayStep>,
+    /// Namespaces after the last replayed step.
+    pub namespaces: BTreeMap<String, ReplayedNamespace>,
+    /// Records that are not sync messages.
+    pub skipped: Vec<u64>,
+}
+
+// The fields of a sync message the replay needs, kept apart from the wire
+// types, which only build with the sync feature on wasm.
+#[derive(Deserialize)]
+struct RecordedMessage {
+    operation: RecordedOperation,
+    #[serde(default)]
+    vector_clock: BTreeMap<String, u64>,
+}
+
+#[derive(Deserialize)]
+struct RecordedOperation {
+    namespace: String,
+    operation_type: String,
+    #[serde(default)]
+    data: Option<Vec<u8>>,
+    #[serde(default)]
+    timestamp: u64,
+    #[serde(default)]
+    author: String,
+}
+
+/// Replays `records` up to and including the sequence `until`. Messages
+/// received are applied as the receiving side applies them; messages sent
+/// show what the recording replica held at the time.
+pub fn replay(records: &[RecordedSync], until: Option<u64>) -> Replay {
+    let mut replay = Replay::default();
+
+    for record in records
+        .iter()
+        .take_while(|record| until.is_none_or(|until| record.sequence <= until))
+    {
+        let Ok(message) = serde_json::from_slice::<RecordedMessage>(&record.message) else {
+            replay.skipped.push(record.sequence);
+            continue;
+        };
+        let operation = message.operation;
+
+        let previous = replay
+            .namespaces
+            .get(&operation.namespace)
+            .map(|namespace| namespace.revision.clone());
+        let revision = match (operation.operation_type.as_str(), &operation.data) {
+            ("Delete", _) => {
+                replay.namespaces.remove(&operation.namespace);
+                None
+            }
+            // Updates without a payload leave the namespace as it was.
+            (_, None) => previous.clone(),
+            (_, Some(data)) => {
+                let revision = hex::encode(Sha256::digest(data));
+                replay.namespaces.insert(
+                    operation.namespace.clone(),
+                    ReplayedNamespace {
+                        revision: revision.clone(),
+                        author: operation.author.clone(),
+                        timestamp: operation.timestamp,
+                    },
+                );
+                Some(revision)
+            }
+        };
+
+        replay.steps.push(ReplayStep {
+            sequence: record.sequence,
+            recorded_at: record.recorded_at,
+            direction: record.direction,
+            author: operation.author,
+            namespace: operation.namespace,
+            operation: operation.operation_type,
+            vector_clock: message.vector_clock,
+            changed: revision != previous,
+            revision,
+        });
+    }
+
+    replay
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{create_vault, delete_vault, save_vault};
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    fn message(operation_type: &str, namespace: &str, data: Option<&[u8]>) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "operation": {
+                "namespace": namespace,
+                "operation_type": operation_type,
+                "data": data,
+                "nonce": null,
+                "timestamp": 1_700_000_000,
+                "author": "peer-a",
+            },
+            "vector_clock": { "peer-a": 1 },
+            "vault_name": "notes",
+            "vault_metadata": null,
+            "identity_salts": null,
+            "username_pk": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_recorded_messages_replay_in_order() {
+        let platform = Platform::new();
+        let vault_name = "sync_recording_test";
+        let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+        let public_key = crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            // Nothing is recorded before recording starts.
+            record_sync_message(
+                &platform,
+                vault_name,
+                SyncDirection::Received,
+                &message("Insert", "ignored", Some(b"x")),
+            )
+            .await;
+
+            start_sync_recording(&platform, vault_name, &public_key)
+                .await
+                .unwrap();
+            assert!(is_recording_sync(&platform, vault_name).await);
+
+            for (direction, bytes) in [
+                (
+                    SyncDirection::Sent,
+                    message("Insert", "notes", Some(b"one")),
+                ),
+                (
+                    SyncDirection::Received,
+                    message("Update", "notes", Some(b"two")),
+                ),
+                (SyncDirection::Received, b"not a sync message".to_vec()),
+                (SyncDirection::Received, message("Delete", "notes", None)),
+            ] {
+                record_sync_message(&platform, vault_name, direction, &bytes).await;
+            }
+
+            stop_sync_recording(&platform, vault_name).await.unwrap();
+            record_sync_message(
+                &platform,
+                vault_name,
+                SyncDirection::Sent,
+                &message("Insert", "ignored", Some(b"x")),
+            )
+            .await;
+
+            let export = export_sync_recording(&platform, vault_name).await.unwrap();
+            assert_eq!(export.lines().count(), 4);
+
+            let other = crate::domain::crypto::generate_identity(&platform).unwrap();
+            assert!(matches!(
+                decrypt_sync_recording(&platform, &export, &other).await,
+                Err(VaultError::InvalidPassword)
+            ));
+
+            let records = decrypt_sync_recording(&platform, &export, &identity)
+                .await
+                .unwrap();
+            let sequences: Vec<u64> = records.iter().map(|record| record.sequence).collect();
+            assert_eq!(sequences, vec![0, 1, 2, 3]);
+
+            let partial = replay(&records, Some(1));
+            assert_eq!(partial.steps.len(), 2);
+            assert!(partial.steps.iter().all(|step| step.changed));
+            assert_eq!(
+                partial.namespaces["notes"].revision,
+                hex::encode(Sha256::digest(b"two"))
+            );
+
+            let full = replay(&records, None);
+            assert_eq!(full.skipped, vec![2]);
+            assert_eq!(full.steps.len(), 3);
+            assert_eq!(full.steps[2].revision, None);
+            assert!(full.namespaces.is_empty());
+
+            clear_sync_recording(&platform, vault_name).await.unwrap();
+            assert!(export_sync_recording(&platform, vault_name)
+                .await
+                .unwrap()
+                .is_empty());
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,253 @@
+//! Namespaces sealed until a release date.
+//!
+//! The data is encrypted to a fresh key that no vault identity holds. That
+//! key is split into Shamir shares, each sealed to one escrow peer, and the
+//! peers only hand their share back once the release date has passed. Any
+//! `threshold` released shares decrypt the namespace, so a single peer with
+//! a wrong clock or a lost share does not decide the release.
+
+use super::error::VaultError;
+use super::migration::CURRENT_CRYPTO_VERSION;
+use super::operations::{current_timestamp, lock_namespace, read_vault, write_namespace};
+use super::types::{Compression, NamespaceData, TimeLock};
+use crate::domain::crypto::{self, shamir};
+use crate::platform::Platform;
+use serde::{Deserialize, Serialize};
+
+/// Share of a time-locked namespace key, sealed to one escrow peer.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KeyShare {
+    pub vault_name: String,
+    pub namespace: String,
+    /// Public key of the escrow peer able to unseal the share.
+    pub holder: String,
+    pub release_at: i64,
+    pub sealed: Vec<u8>,
+}
+
+/// Stores `data` under `namespace` so that it can only be read after
+/// `release_at` (Unix seconds), and returns the key shares to hand to
+/// `holders`. The writer cannot read the namespace early either.
+pub async fn write_timelocked(
+    platform: &Platform,
+    vault_name: &str,
+    namespace: &str,
+    data: Vec<u8>,
+    release_at: i64,
+    holders: &[&str],
+    threshold: u8,
+) -> Result<Vec<KeyShare>, VaultError> {
+    let count = u8::try_from(holders.len())
+        .map_err(|_| VaultError::io_error("A time lock supports at most 255 escrow peers"))?;
+
+    let escrow_identity =
+        crypto::generate_identity(platform).map_err(|e| VaultError::io_error(e.to_string()))?;
+    let escrow_public_key = crypto::identity_to_public(platform, &escrow_identity)
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+    let shares = shamir::split(escrow_identity.as_bytes(), threshold, count)
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+    let mut key_shares = Vec::with_capacity(shares.len());
+    for (holder, share) in holders.iter().zip(shares) {
+        let sealed = crypto::encrypt_for_recipients(platform, &share, &[holder])
+            .await
+            .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+        key_shares.push(KeyShare {
+            vault_name: vault_name.to_string(),
+            namespace: namespace.to_string(),
+            holder: holder.to_string(),
+            release_at,
+            sealed,
+        });
+    }
+
+    let encrypted = crypto::encrypt_for_recipients(platform, &data, &[&escrow_public_key])
+        .await
+        .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+    let _guards = lock_namespace(platform, vault_name, namespace).await?;
+    let mut vault = read_vault(platform, vault_name).await?;
+
+    if vault.observer {
+        return Err(VaultError::ObserverVault);
+    }
+    if vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+    if vault.namespaces.contains_key(namespace) {
+        return Err(VaultError::NamespaceAlreadyExists);
+    }
+    super::limits::ensure_within_limit(&vault, data.len())?;
+
+    vault.namespaces.insert(
+        namespace.to_string(),
+        NamespaceData {
+            data: encrypted,
+            expiration: None,
+            chunks: Vec::new(),
+            timelock: Some(TimeLock {
+                release_at,
+                threshold,
+            }),
+            updated_at: Some(current_timestamp(platform)),
+            recipients: Vec::new(),
+            crdt: None,
+            integrity: None,
+            crypto_version: Some(CURRENT_CRYPTO_VERSION),
+            compression: Compression::None,
+        },
+    );
+
+    write_namespace(platform, vault_name, &vault, namespace).await?;
+
+    Ok(key_shares)
+}
+
+/// Run by an escrow peer: unseals its share with `holder_identity`, refusing
+/// to do so before the release date on its own clock.
+pub async fn release_key_share(
+    platform: &Platform,
+    share: &KeyShare,
+    holder_identity: &str,
+) -> Result<Vec<u8>, VaultError> {
+    if current_timestamp(platform) < share.release_at {
+        return Err(VaultError::TimeLocked(share.release_at));
+    }
+
+    crypto::decrypt_with_identity(platform, &share.sealed, holder_identity)
+        .await
+        .map_err(|_| VaultError::InvalidPassword)
+}
+
+/// Decrypts a time-locked namespace with shares released by escrow peers.
+pub async fn read_timelocked(
+    platform: &Platform,
+    vault_name: &str,
+    namespace: &str,
+    released_shares: &[Vec<u8>],
+) -> Result<Vec<u8>, VaultError> {
+    let vault = read_vault(platform, vault_name).await?;
+
+    let namespace_data = vault
+        .namespaces
+        .get(namespace)
+        .ok_or(VaultError::NamespaceNotFound)?;
+    let timelock = namespace_data.timelock.as_ref().ok_or_else(|| {
+        VaultError::io_error(format!("Namespace '{namespace}' is not time-locked"))
+    })?;
+
+    if current_timestamp(platform) < timelock.release_at
+        || released_shares.len() < timelock.threshold as usize
+    {
+        return Err(VaultError::TimeLocked(timelock.release_at));
+    }
+
+    let escrow_identity = shamir::combine(released_shares)
+        .ok()
+        .and_then(|identity| String::from_utf8(identity).ok())
+        .ok_or(VaultError::InvalidPassword)?;
+
+    crypto::decrypt_with_identity(platform, &namespace_data.data, &escrow_identity)
+        .await
+        .map_err(|_| VaultError::InvalidPassword)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{create_vault, delete_vault, save_vault};
+    use crate::ports::ClockPort;
+    use futures::executor::block_on;
+
+    struct FixedClock(f64);
+
+    #[async_trait::async_trait(?Send)]
+    impl ClockPort for FixedClock {
+        fn now(&self) -> f64 {
+            self.0
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+
+        async fn sleep(&self, _milliseconds: u32) {}
+    }
+
+    static BEFORE_RELEASE: FixedClock = FixedClock(1_700_000_000_000.0);
+    static AFTER_RELEASE: FixedClock = FixedClock(1_800_000_000_000.0);
+    const RELEASE_AT: i64 = 1_750_000_000;
+
+    #[test]
+    fn test_timelocked_namespace_opens_with_threshold_after_release() {
+        let before = Platform::new().with_clock(&BEFORE_RELEASE);
+        let after = Platform::new().with_clock(&AFTER_RELEASE);
+        let vault_name = "timelock_release_test";
+
+        block_on(async {
+            let _ = delete_vault(&before, vault_name).await;
+            save_vault(&before, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let holder_identities: Vec<String> = (0..3)
+                .map(|_| crypto::generate_identity(&before).unwrap())
+                .collect();
+            let holders: Vec<String> = holder_identities
+                .iter()
+                .map(|identity| crypto::identity_to_public(&before, identity).unwrap())
+                .collect();
+            let holder_refs: Vec<&str> = holders.iter().map(String::as_str).collect();
+
+            let shares = write_timelocked(
+                &before,
+                vault_name,
+                "will",
+                b"to be read in 2025".to_vec(),
+                RELEASE_AT,
+                &holder_refs,
+                2,
+            )
+            .await
+            .unwrap();
+            assert_eq!(shares.len(), 3);
+
+            assert!(matches!(
+                release_key_share(&before, &shares[0], &holder_identities[0]).await,
+                Err(VaultError::TimeLocked(RELEASE_AT))
+            ));
+            assert!(matches!(
+                crate::domain::vault::operations::read_namespace(
+                    &after,
+                    vault_name,
+                    &holder_identities[0],
+                    "will"
+                )
+                .await,
+                Err(VaultError::TimeLocked(RELEASE_AT))
+            ));
+
+            let mut released = Vec::new();
+            for (share, identity) in shares.iter().zip(&holder_identities).skip(1) {
+                released.push(release_key_share(&after, share, identity).await.unwrap());
+            }
+
+            assert!(read_timelocked(&before, vault_name, "will", &released)
+                .await
+                .is_err());
+            assert!(read_timelocked(&after, vault_name, "will", &released[..1])
+                .await
+                .is_err());
+            assert_eq!(
+                read_timelocked(&after, vault_name, "will", &released)
+                    .await
+                    .unwrap(),
+                b"to be read in 2025"
+            );
+
+            delete_vault(&before, vault_name).await.unwrap();
+        });
+    }
+}
@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use super::crdt::CrdtNamespace;
+use super::integrity::{IntegrityManifest, IntegrityTag};
+use super::sync_policy::SyncPolicy;
+use std::collections::{BTreeMap, BTreeSet};
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
 pub struct Expiration {
@@ -7,19 +10,105 @@ pub struct Expiration {
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
 pub struct NamespaceData {
+    #[serde(with = "super::serialization::compact_bytes")]
     pub data: Vec<u8>,
     pub expiration: Option<Expiration>,
+    /// Ordered chunk ids for deduplicated namespaces; `data` is empty then.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<String>,
+    /// Set on namespaces written with `write_timelocked`, whose key is held
+    /// by escrow peers instead of the vault identities.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timelock: Option<TimeLock>,
+    /// Unix time in seconds of the last write, as seen by this device.
+    /// Missing on namespaces written before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>,
+    /// Public keys granted read access to this namespace alone, on top of
+    /// the writer's. Writes encrypt to them too, until access is revoked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recipients: Vec<String>,
+    /// Set on namespaces written with `update_crdt`, which sync merges
+    /// instead of replacing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crdt: Option<CrdtNamespace>,
+    /// MAC of the plaintext, checked after decryption. Missing on
+    /// namespaces written before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<IntegrityTag>,
+    /// Version of the algorithms the namespace was encrypted with; see
+    /// [`super::migration`]. Missing on namespaces written before it was
+    /// recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crypto_version: Option<u32>,
+    /// Compression applied to the payload before encryption, undone by
+    /// reads.
+    #[serde(default, skip_serializing_if = "Compression::is_none")]
+    pub compression: Compression,
+}
+
+/// Compression of a namespace payload, applied before encryption since
+/// ciphertext does not compress.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    Deflate,
+}
+
+impl Compression {
+    pub fn is_none(&self) -> bool {
+        *self == Compression::None
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, Eq)]
+pub struct TimeLock {
+    /// Unix time in seconds before which escrow peers withhold their shares.
+    pub release_at: i64,
+    /// Number of key shares needed to decrypt.
+    pub threshold: u8,
+}
+
+/// Earliest date a namespace may be removed, whether by the user, expiration
+/// cleanup or a peer.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RetentionPolicy {
+    /// Seconds the namespace is kept after its last write.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_retention_seconds: Option<i64>,
+    /// Unix time in seconds before which the namespace cannot be removed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delete_locked_until: Option<i64>,
+    /// Unix time in seconds the policy was set, standing in for the last
+    /// write of namespaces written before write times were recorded.
+    pub applied_at: i64,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
 pub struct VaultMetadata {
     pub peer_id: Option<String>,
+    /// Namespaces taking part in sync; all of them when empty.
+    #[serde(default, skip_serializing_if = "SyncPolicy::is_empty")]
+    pub sync_policy: SyncPolicy,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Default)]
 pub struct IdentitySalts {
-    salts: HashMap<String, [u8; 32]>,
-    credential_ids: HashMap<String, Vec<u8>>,
+    salts: BTreeMap<String, [u8; 32]>,
+    credential_ids: BTreeMap<String, Vec<u8>>,
+    /// Public key of the identity unlocked most recently, tried first on the
+    /// next unlock. A passphrase-derived check value would let an attacker
+    /// screen guesses without paying for Argon2, so only this hint is kept.
+    #[serde(default)]
+    last_unlocked: Option<String>,
+    #[serde(default)]
+    key_checks: BTreeMap<String, String>,
+    /// When each credential was registered, in seconds. Credentials
+    /// registered before this was recorded have no entry.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    credentials_created_at: BTreeMap<String, i64>,
 }
 
 impl IdentitySalts {
@@ -39,6 +128,29 @@ impl IdentitySalts {
         self.salts.iter()
     }
 
+    /// Same as [`iter`](Self::iter), but starting with the last unlocked
+    /// identity.
+    pub fn iter_hinted_first(&self) -> impl Iterator<Item = (&String, &[u8; 32])> {
+        let hinted = self
+            .last_unlocked
+            .as_ref()
+            .and_then(|public_key| self.salts.get_key_value(public_key));
+
+        hinted.into_iter().chain(
+            self.salts
+                .iter()
+                .filter(move |(public_key, _)| Some(*public_key) != self.last_unlocked.as_ref()),
+        )
+    }
+
+    pub fn last_unlocked(&self) -> Option<&String> {
+        self.last_unlocked.as_ref()
+    }
+
+    pub fn set_last_unlocked(&mut self, public_key: String) {
+        self.last_unlocked = Some(public_key);
+    }
+
     pub fn get_credential_id(&self, public_key: &str) -> Option<&Vec<u8>> {
         self.credential_ids.get(public_key)
     }
@@ -47,6 +159,33 @@ impl IdentitySalts {
         self.credential_ids.insert(public_key, credential_id);
     }
 
+    pub fn get_credential_created_at(&self, public_key: &str) -> Option<i64> {
+        self.credentials_created_at.get(public_key).copied()
+    }
+
+    pub fn set_credential_created_at(&mut self, public_key: String, created_at: i64) {
+        self.credentials_created_at.insert(public_key, created_at);
+    }
+
+    pub fn get_key_check(&self, public_key: &str) -> Option<&String> {
+        self.key_checks.get(public_key)
+    }
+
+    pub fn set_key_check(&mut self, public_key: String, key_check: String) {
+        self.key_checks.insert(public_key, key_check);
+    }
+
+    /// Forgets everything stored for `public_key`.
+    pub fn remove(&mut self, public_key: &str) {
+        self.salts.remove(public_key);
+        self.credential_ids.remove(public_key);
+        self.key_checks.remove(public_key);
+        self.credentials_created_at.remove(public_key);
+        if self.last_unlocked.as_deref() == Some(public_key) {
+            self.last_unlocked = None;
+        }
+    }
+
     pub fn get_public_keys_with_credentials(&self) -> impl Iterator<Item = &String> {
         self.credential_ids.keys()
     }
@@ -56,7 +195,47 @@ impl IdentitySalts {
 pub struct Vault {
     pub metadata: VaultMetadata,
     pub identity_salts: IdentitySalts,
-    pub username_pk: HashMap<String, String>,
-    pub namespaces: HashMap<String, NamespaceData>,
+    pub username_pk: BTreeMap<String, String>,
+    pub namespaces: BTreeMap<String, NamespaceData>,
     pub sync_enabled: bool,
+    /// Observer replicas store encrypted namespaces received through sync
+    /// without holding any key able to decrypt them.
+    #[serde(default)]
+    pub observer: bool,
+    /// Set by `freeze_vault`: the vault is read-only until unfrozen.
+    #[serde(default)]
+    pub frozen: bool,
+    /// Retention policies by namespace, kept apart from the namespace data
+    /// so rewriting a namespace does not drop its policy.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub retention: BTreeMap<String, RetentionPolicy>,
+    /// Residency tags by namespace. Sync only replicates a tagged namespace
+    /// to peers asserting every one of its tags.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub residency: BTreeMap<String, BTreeSet<String>>,
+    /// Encrypted chunks referenced by deduplicated namespaces, keyed by id.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub chunks: BTreeMap<String, Vec<u8>>,
+    /// Largest namespace payload accepted, in bytes; `None` applies
+    /// `limits::DEFAULT_MAX_NAMESPACE_BYTES`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_namespace_bytes: Option<u64>,
+    /// Keyed hashes of the namespace files as of the last `seal_vault`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<IntegrityManifest>,
+    /// Compression of namespace writes that do not choose one.
+    #[serde(default, skip_serializing_if = "Compression::is_none")]
+    pub compression: Compression,
+    /// Sequence number of the last committed save. Journals of older saves
+    /// are stale and never replayed, see `wal`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub journal_sequence: u64,
+    /// blake3 digests of the namespace files as last read or written, by
+    /// path, so a save only rewrites and journals the files that changed.
+    #[serde(skip)]
+    pub stored_digests: BTreeMap<String, [u8; 32]>,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
 }
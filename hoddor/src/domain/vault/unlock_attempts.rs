@@ -0,0 +1,88 @@
+use crate::notifications::EventType;
+use crate::platform::Platform;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+
+pub const DEFAULT_UNLOCK_FAILURE_THRESHOLD: u32 = 5;
+
+// Both by vault name.
+static THRESHOLDS: Lazy<Mutex<HashMap<String, u32>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static FAILURES: Lazy<Mutex<HashMap<String, u32>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Number of consecutive failed unlocks of `vault_name` after which an
+/// `UnlockFailuresExceeded` event is emitted, instead of
+/// [`DEFAULT_UNLOCK_FAILURE_THRESHOLD`]. Zero disables the event.
+pub fn set_unlock_failure_threshold(vault_name: &str, threshold: u32) {
+    THRESHOLDS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(vault_name.to_string(), threshold);
+}
+
+fn unlock_failure_threshold(vault_name: &str) -> u32 {
+    THRESHOLDS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(vault_name)
+        .copied()
+        .unwrap_or(DEFAULT_UNLOCK_FAILURE_THRESHOLD)
+}
+
+pub fn record_unlock_failure(platform: &Platform, vault_name: &str) -> u32 {
+    let count = {
+        let mut failures = FAILURES.lock().unwrap_or_else(PoisonError::into_inner);
+        let count = failures.entry(vault_name.to_string()).or_insert(0);
+        *count += 1;
+        *count
+    };
+
+    let threshold = unlock_failure_threshold(vault_name);
+    if threshold > 0 && count % threshold == 0 {
+        let _ = platform.notifier().notify_event(
+            vault_name,
+            EventType::UnlockFailuresExceeded,
+            &format!("{count} consecutive failed unlock attempts"),
+        );
+    }
+
+    count
+}
+
+pub fn reset_unlock_failures(vault_name: &str) {
+    FAILURES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(vault_name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failures_are_counted_per_vault() {
+        let platform = Platform::new();
+
+        assert_eq!(record_unlock_failure(&platform, "attempts_a"), 1);
+        assert_eq!(record_unlock_failure(&platform, "attempts_a"), 2);
+        assert_eq!(record_unlock_failure(&platform, "attempts_b"), 1);
+
+        reset_unlock_failures("attempts_a");
+        assert_eq!(record_unlock_failure(&platform, "attempts_a"), 1);
+
+        reset_unlock_failures("attempts_a");
+        reset_unlock_failures("attempts_b");
+    }
+
+    #[test]
+    fn test_threshold_is_set_per_vault() {
+        set_unlock_failure_threshold("attempts_quiet", 0);
+
+        assert_eq!(unlock_failure_threshold("attempts_quiet"), 0);
+        assert_eq!(
+            unlock_failure_threshold("attempts_loud"),
+            DEFAULT_UNLOCK_FAILURE_THRESHOLD
+        );
+    }
+}
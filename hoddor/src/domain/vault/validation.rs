@@ -10,15 +10,30 @@ fn validate_not_empty(value: &str, error_msg: &str) -> Result<(), VaultError> {
 pub fn validate_namespace(namespace: &str) -> Result<(), VaultError> {
     validate_not_empty(namespace, "Namespace cannot be empty or whitespace only")?;
 
-    let invalid_chars = ['/', '\\', '<', '>', ':', '"', '|', '?', '*'];
+    let invalid_chars = ['\\', '<', '>', ':', '"', '|', '?', '*'];
     if namespace.chars().any(|c| invalid_chars.contains(&c)) {
         return Err(VaultError::io_error(
             "Namespace contains invalid characters",
         ));
     }
+    // `/` separates the segments of a hierarchical namespace.
+    if namespace
+        .split('/')
+        .any(|segment| segment.trim().is_empty())
+    {
+        return Err(VaultError::io_error(
+            "Namespace path cannot contain empty segments",
+        ));
+    }
     Ok(())
 }
 
+/// Validates the prefix of a namespace listing or removal: a namespace
+/// path, optionally ending with `/`.
+pub fn validate_namespace_prefix(prefix: &str) -> Result<(), VaultError> {
+    validate_namespace(prefix.strip_suffix('/').unwrap_or(prefix))
+}
+
 pub fn validate_passphrase(passphrase: &str) -> Result<(), VaultError> {
     validate_not_empty(passphrase, "Passphrase cannot be empty or whitespace only")
 }
@@ -43,6 +58,17 @@ mod tests {
         assert!(validate_namespace("my-namespace").is_ok());
         assert!(validate_namespace("namespace_123").is_ok());
         assert!(validate_namespace("CamelCase").is_ok());
+        assert!(validate_namespace("app/settings/theme").is_ok());
+    }
+
+    #[test]
+    fn test_validate_namespace_empty_segments() {
+        assert!(validate_namespace("/app").is_err());
+        assert!(validate_namespace("app/").is_err());
+        assert!(validate_namespace("app//theme").is_err());
+        assert!(validate_namespace("app/ /theme").is_err());
+        assert!(validate_namespace_prefix("app/settings/").is_ok());
+        assert!(validate_namespace_prefix("/").is_err());
     }
 
     #[test]
@@ -59,7 +85,6 @@ mod tests {
 
     #[test]
     fn test_validate_namespace_invalid_characters() {
-        assert!(validate_namespace("test/path").is_err());
         assert!(validate_namespace("test\\path").is_err());
         assert!(validate_namespace("test<file").is_err());
         assert!(validate_namespace("test>file").is_err());
@@ -0,0 +1,368 @@
+//! Write-ahead journal of whole-vault saves.
+//!
+//! A save is numbered with the sequence following the one recorded in
+//! `metadata.json`. It writes its journal, then the metadata carrying the new
+//! sequence, which commits it, then the namespace files in place, and finally
+//! deletes the journal. On recovery, under the exclusive vault lock, only a
+//! journal whose sequence matches the committed metadata is replayed: older
+//! ones belong to saves since superseded and newer ones to saves that never
+//! committed, so nothing of them reached the namespace files.
+//!
+//! Journals only carry the namespace files the save changes, exactly as they
+//! are stored: payloads stay age-encrypted and nothing is in the journal that
+//! is not also on disk. The metadata is never journaled, its write being the
+//! commit itself.
+
+use super::error::VaultError;
+use super::operations;
+use super::serialization::encode_stored;
+use super::types::Vault;
+use crate::platform::Platform;
+use serde::{Deserialize, Serialize};
+
+pub const WAL_EXTENSION: &str = ".wal";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WalWrite {
+    pub path: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WalEntry {
+    pub sequence: u64,
+    pub writes: Vec<WalWrite>,
+    pub deletes: Vec<String>,
+}
+
+fn wal_path(vault_name: &str, sequence: u64) -> String {
+    format!("{vault_name}/{sequence:016x}{WAL_EXTENSION}")
+}
+
+// A vault without metadata has never committed a save.
+async fn committed_sequence(platform: &Platform, vault_name: &str) -> u64 {
+    operations::read_vault_metadata(platform, vault_name)
+        .await
+        .map(|vault| vault.journal_sequence)
+        .unwrap_or(0)
+}
+
+/// The sequence number of the next save of `vault_name`, to stamp on its
+/// metadata before [`commit`].
+pub(super) async fn next_sequence(platform: &Platform, vault_name: &str) -> u64 {
+    committed_sequence(platform, vault_name).await + 1
+}
+
+/// Commits `metadata`, stamped by [`next_sequence`], along with the
+/// namespace `writes` and `deletes`, as described in the module
+/// documentation. The caller holds the exclusive vault lock and has already
+/// stored the chunks the namespaces reference. `on_written` is called with
+/// the size of every file written.
+pub(super) async fn commit(
+    platform: &Platform,
+    vault_name: &str,
+    metadata: &Vault,
+    writes: Vec<WalWrite>,
+    deletes: Vec<String>,
+    on_written: &dyn Fn(usize),
+) -> Result<(), VaultError> {
+    let storage = platform.storage();
+    let sequence = metadata.journal_sequence;
+
+    let journal = if writes.is_empty() && deletes.is_empty() {
+        None
+    } else {
+        let entry = WalEntry {
+            sequence,
+            writes,
+            deletes,
+        };
+        let json = serde_json::to_string(&entry)
+            .map_err(|_| VaultError::serialization_error("Failed to serialize journal entry"))?;
+        storage
+            .write_file(&wal_path(vault_name, sequence), &json)
+            .await?;
+        Some(entry)
+    };
+
+    let metadata_text = encode_stored(metadata)?;
+    storage
+        .write_file(
+            &format!("{vault_name}/{}", operations::METADATA_FILENAME),
+            &metadata_text,
+        )
+        .await?;
+    on_written(metadata_text.len());
+
+    if let Some(entry) = journal {
+        for write in &entry.writes {
+            storage.write_file(&write.path, &write.content).await?;
+            on_written(write.content.len());
+        }
+        for path in &entry.deletes {
+            storage.delete_file(path).await?;
+        }
+        storage.delete_file(&wal_path(vault_name, sequence)).await?;
+    }
+
+    Ok(())
+}
+
+/// Whether `vault_name` holds a journal left by an interrupted save.
+pub(super) async fn has_pending(platform: &Platform, vault_name: &str) -> bool {
+    platform
+        .storage()
+        .list_entries(vault_name)
+        .await
+        .is_ok_and(|entries| entries.iter().any(|name| name.ends_with(WAL_EXTENSION)))
+}
+
+/// Completes the committed save of `vault_name` that was interrupted, if
+/// any, and removes every journal. The caller holds the exclusive vault
+/// lock. Returns whether anything was replayed.
+pub(super) async fn recover_locked(
+    platform: &Platform,
+    vault_name: &str,
+) -> Result<bool, VaultError> {
+    let storage = platform.storage();
+
+    let Ok(entries) = storage.list_entries(vault_name).await else {
+        return Ok(false);
+    };
+    let journals: Vec<String> = entries
+        .into_iter()
+        .filter(|name| name.ends_with(WAL_EXTENSION))
+        .map(|name| format!("{vault_name}/{name}"))
+        .collect();
+
+    if journals.is_empty() {
+        return Ok(false);
+    }
+
+    let committed = committed_sequence(platform, vault_name).await;
+    let mut replayed = false;
+
+    for path in &journals {
+        // A journal that does not parse was torn while being written, before
+        // the metadata committing it.
+        let entry = storage
+            .read_file(path)
+            .await
+            .ok()
+            .and_then(|text| serde_json::from_str::<WalEntry>(&text).ok());
+
+        if let Some(entry) = entry.filter(|entry| entry.sequence == committed) {
+            for write in &entry.writes {
+                storage.write_file(&write.path, &write.content).await?;
+            }
+            for path in &entry.deletes {
+                let _ = storage.delete_file(path).await;
+            }
+            replayed = true;
+        }
+    }
+
+    for path in &journals {
+        storage.delete_file(path).await?;
+    }
+
+    if replayed {
+        tracing::warn!(vault = vault_name, committed, "Replayed interrupted save");
+    }
+
+    Ok(replayed)
+}
+
+/// Takes the exclusive lock of `vault_name` and runs [`recover_locked`].
+pub async fn recover_vault(platform: &Platform, vault_name: &str) -> Result<bool, VaultError> {
+    let _guard = platform
+        .locks()
+        .acquire(&operations::metadata_lock_name(vault_name))
+        .await?;
+
+    recover_locked(platform, vault_name).await
+}
+
+/// Runs [`recover_vault`] on every vault and returns the ones that needed it.
+pub async fn recover_all_vaults(platform: &Platform) -> Result<Vec<String>, VaultError> {
+    let mut recovered = Vec::new();
+
+    for vault_name in operations::list_vaults(platform).await? {
+        if recover_vault(platform, &vault_name).await? {
+            recovered.push(vault_name);
+        }
+    }
+
+    Ok(recovered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    async fn journals(platform: &Platform, vault_name: &str) -> Vec<String> {
+        platform
+            .storage()
+            .list_entries(vault_name)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|name| name.ends_with(WAL_EXTENSION))
+            .collect()
+    }
+
+    // Leaves `vault_name` with metadata committed at sequence 2 and the
+    // journals of `sequences`, each writing its own number to `data`.
+    async fn interrupted_vault(platform: &Platform, vault_name: &str, sequences: &[u64]) {
+        let storage = platform.storage();
+        let _ = storage.delete_directory(vault_name).await;
+        storage.create_directory(vault_name).await.unwrap();
+
+        let mut metadata = operations::create_vault().await.unwrap();
+        metadata.journal_sequence = 2;
+        storage
+            .write_file(
+                &format!("{vault_name}/{}", operations::METADATA_FILENAME),
+                &encode_stored(&metadata).unwrap(),
+            )
+            .await
+            .unwrap();
+        storage
+            .write_file(&format!("{vault_name}/data"), "0")
+            .await
+            .unwrap();
+
+        for &sequence in sequences {
+            let entry = WalEntry {
+                sequence,
+                writes: vec![WalWrite {
+                    path: format!("{vault_name}/data"),
+                    content: sequence.to_string(),
+                }],
+                deletes: vec![],
+            };
+            storage
+                .write_file(
+                    &wal_path(vault_name, sequence),
+                    &serde_json::to_string(&entry).unwrap(),
+                )
+                .await
+                .unwrap();
+        }
+    }
+
+    async fn data(platform: &Platform, vault_name: &str) -> String {
+        platform
+            .storage()
+            .read_file(&format!("{vault_name}/data"))
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_recover_replays_committed_journal() {
+        let platform = Platform::new();
+        let vault_name = "wal_test_replay";
+
+        block_on(async {
+            interrupted_vault(&platform, vault_name, &[2]).await;
+
+            assert!(recover_vault(&platform, vault_name).await.unwrap());
+            assert_eq!(data(&platform, vault_name).await, "2");
+            assert!(journals(&platform, vault_name).await.is_empty());
+            assert!(!recover_vault(&platform, vault_name).await.unwrap());
+
+            platform
+                .storage()
+                .delete_directory(vault_name)
+                .await
+                .unwrap();
+        });
+    }
+
+    #[test]
+    fn test_recover_drops_superseded_and_uncommitted_journals() {
+        let platform = Platform::new();
+        let vault_name = "wal_test_stale";
+
+        block_on(async {
+            interrupted_vault(&platform, vault_name, &[1, 3]).await;
+
+            assert!(!recover_vault(&platform, vault_name).await.unwrap());
+            assert_eq!(data(&platform, vault_name).await, "0");
+            assert!(journals(&platform, vault_name).await.is_empty());
+
+            platform
+                .storage()
+                .delete_directory(vault_name)
+                .await
+                .unwrap();
+        });
+    }
+
+    #[test]
+    fn test_torn_journal_is_rolled_back() {
+        let platform = Platform::new();
+        let vault_name = "wal_test_torn";
+
+        block_on(async {
+            interrupted_vault(&platform, vault_name, &[]).await;
+            platform
+                .storage()
+                .write_file(&wal_path(vault_name, 2), "{\"sequence\":2,\"wri")
+                .await
+                .unwrap();
+            assert!(has_pending(&platform, vault_name).await);
+
+            assert!(!recover_vault(&platform, vault_name).await.unwrap());
+            assert_eq!(data(&platform, vault_name).await, "0");
+            assert!(!has_pending(&platform, vault_name).await);
+
+            platform
+                .storage()
+                .delete_directory(vault_name)
+                .await
+                .unwrap();
+        });
+    }
+
+    #[test]
+    fn test_commit_numbers_saves_and_removes_its_journal() {
+        let platform = Platform::new();
+        let vault_name = "wal_test_commit";
+
+        block_on(async {
+            interrupted_vault(&platform, vault_name, &[]).await;
+            let mut metadata = operations::read_vault_metadata(&platform, vault_name)
+                .await
+                .unwrap();
+            metadata.journal_sequence = next_sequence(&platform, vault_name).await;
+
+            commit(
+                &platform,
+                vault_name,
+                &metadata,
+                vec![WalWrite {
+                    path: format!("{vault_name}/data"),
+                    content: "3".to_string(),
+                }],
+                vec![],
+                &|_| {},
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(metadata.journal_sequence, 3);
+            assert_eq!(committed_sequence(&platform, vault_name).await, 3);
+            assert_eq!(data(&platform, vault_name).await, "3");
+            assert!(journals(&platform, vault_name).await.is_empty());
+
+            platform
+                .storage()
+                .delete_directory(vault_name)
+                .await
+                .unwrap();
+        });
+    }
+}
@@ -0,0 +1,338 @@
+//! Watch expressions on namespaces holding JSON documents.
+//!
+//! A watch re-evaluates its JSONPath expression each time the vault is
+//! written, whether by a local write or by a change received from a peer,
+//! and calls back only when the selected values differ from the last ones
+//! it saw. The expression runs against the vault just written, so a watch
+//! costs one decryption of its namespace per write touching it.
+
+use super::chunks;
+use super::error::VaultError;
+use super::operations::{current_timestamp, read_vault};
+use super::query::JsonPath;
+use super::types::Vault;
+use crate::platform::Platform;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+/// Called with the watch id and the values the expression selects, in
+/// document order. An empty selection means nothing matches anymore, or the
+/// namespace was removed or has expired.
+pub type WatchCallback = Arc<dyn Fn(u32, &[Value]) + Send + Sync>;
+
+struct Watch {
+    /// Follows the vault when it is renamed.
+    vault_name: Mutex<String>,
+    namespace: String,
+    path: JsonPath,
+    identity_private_key: Zeroizing<String>,
+    last: Mutex<Vec<Value>>,
+    callback: WatchCallback,
+}
+
+static WATCHES: Lazy<Mutex<HashMap<u32, Arc<Watch>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_WATCH_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Registers `callback` for changes of the values `path` selects in
+/// `namespace` and returns the watch id to pass to [`unwatch_namespace`].
+///
+/// The current selection is taken as the starting point and is not
+/// reported. A namespace that does not exist yet selects nothing until it
+/// is written.
+#[tracing::instrument(skip_all, fields(vault = vault_name, namespace = namespace))]
+pub async fn watch_namespace(
+    platform: &Platform,
+    vault_name: &str,
+    identity_private_key: &str,
+    namespace: &str,
+    path: &str,
+    callback: WatchCallback,
+) -> Result<u32, VaultError> {
+    let path = JsonPath::parse(path)?;
+
+    let vault = read_vault(platform, vault_name).await?;
+    let current = select(platform, &vault, namespace, &path, identity_private_key).await?;
+
+    let id = NEXT_WATCH_ID.fetch_add(1, Ordering::Relaxed);
+    WATCHES.lock().insert(
+        id,
+        Arc::new(Watch {
+            vault_name: Mutex::new(vault_name.to_string()),
+            namespace: namespace.to_string(),
+            path,
+            identity_private_key: Zeroizing::new(identity_private_key.to_string()),
+            last: Mutex::new(current),
+            callback,
+        }),
+    );
+
+    Ok(id)
+}
+
+/// Removes the watch `id`. Returns `false` if there was none.
+pub fn unwatch_namespace(id: u32) -> bool {
+    WATCHES.lock().remove(&id).is_some()
+}
+
+/// Moves the watches on `vault_name` to `new_name`, once renamed.
+pub(super) fn rename_watches(vault_name: &str, new_name: &str) {
+    for watch in WATCHES.lock().values() {
+        let mut watched = watch.vault_name.lock();
+        if *watched == vault_name {
+            *watched = new_name.to_string();
+        }
+    }
+}
+
+/// Re-evaluates the watches on `vault_name` against `vault`, as just
+/// written. `namespace` limits them to the watches on the only namespace
+/// that changed.
+pub(super) async fn notify_watches(
+    platform: &Platform,
+    vault_name: &str,
+    vault: &Vault,
+    namespace: Option<&str>,
+) {
+    let watches: Vec<(u32, Arc<Watch>)> = WATCHES
+        .lock()
+        .iter()
+        .filter(|(_, watch)| {
+            *watch.vault_name.lock() == vault_name
+                && namespace.is_none_or(|name| name == watch.namespace)
+        })
+        .map(|(id, watch)| (*id, watch.clone()))
+        .collect();
+
+    for (id, watch) in watches {
+        let selected = match select(
+            platform,
+            vault,
+            &watch.namespace,
+            &watch.path,
+            &watch.identity_private_key,
+        )
+        .await
+        {
+            Ok(selected) => selected,
+            Err(e) => {
+                tracing::debug!(
+                    vault = vault_name,
+                    namespace = %watch.namespace,
+                    error = %e,
+                    "Skipped watch evaluation"
+                );
+                continue;
+            }
+        };
+
+        {
+            let mut last = watch.last.lock();
+            if *last == selected {
+                continue;
+            }
+            *last = selected.clone();
+        }
+
+        // An earlier callback may have removed it.
+        if WATCHES.lock().contains_key(&id) {
+            (watch.callback)(id, &selected);
+        }
+    }
+}
+
+/// Like [`notify_watches`] for writes that did not keep the vault at hand.
+pub(crate) async fn notify_watches_from_storage(platform: &Platform, vault_name: &str) {
+    if !WATCHES
+        .lock()
+        .values()
+        .any(|watch| *watch.vault_name.lock() == vault_name)
+    {
+        return;
+    }
+
+    match read_vault(platform, vault_name).await {
+        Ok(vault) => notify_watches(platform, vault_name, &vault, None).await,
+        Err(e) => {
+            tracing::debug!(vault = vault_name, error = %e, "Skipped watch evaluation");
+        }
+    }
+}
+
+async fn select(
+    platform: &Platform,
+    vault: &Vault,
+    namespace: &str,
+    path: &JsonPath,
+    identity_private_key: &str,
+) -> Result<Vec<Value>, VaultError> {
+    let Some(namespace_data) = vault.namespaces.get(namespace) else {
+        return Ok(Vec::new());
+    };
+
+    if let Some(expiration) = &namespace_data.expiration {
+        if current_timestamp(platform) >= expiration.expires_at {
+            return Ok(Vec::new());
+        }
+    }
+
+    let plaintext =
+        chunks::decrypt_namespace(platform, vault, namespace_data, identity_private_key).await?;
+    let document: Value = serde_json::from_slice(&plaintext)
+        .map_err(|_| VaultError::serialization_error("Namespace does not hold a JSON document"))?;
+
+    Ok(path.select(&document).into_iter().cloned().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::vault::operations::{
+        create_vault, delete_vault, remove_namespace, rename_vault, save_vault, upsert_namespace,
+    };
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    #[test]
+    fn test_watch_fires_only_when_selection_changes() {
+        let platform = Platform::new();
+        let vault_name = "watch_test";
+        let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+        let public_key = crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        block_on(async {
+            let _ = delete_vault(&platform, vault_name).await;
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let write = |document: Value| {
+                upsert_namespace(
+                    &platform,
+                    vault_name,
+                    &public_key,
+                    "settings",
+                    serde_json::to_vec(&document).unwrap(),
+                    None,
+                    true,
+                )
+            };
+
+            write(json!({ "theme": "dark", "size": 12 })).await.unwrap();
+
+            let recorded = seen.clone();
+            let id = watch_namespace(
+                &platform,
+                vault_name,
+                &identity,
+                "settings",
+                "$.theme",
+                Arc::new(move |_, values: &[Value]| recorded.lock().push(values.to_vec())),
+            )
+            .await
+            .unwrap();
+
+            write(json!({ "theme": "dark", "size": 14 })).await.unwrap();
+            assert!(seen.lock().is_empty());
+
+            write(json!({ "theme": "light", "size": 14 }))
+                .await
+                .unwrap();
+            assert_eq!(*seen.lock(), vec![vec![json!("light")]]);
+
+            remove_namespace(&platform, vault_name, "settings")
+                .await
+                .unwrap();
+            assert_eq!(seen.lock().last(), Some(&Vec::new()));
+
+            assert!(unwatch_namespace(id));
+            assert!(!unwatch_namespace(id));
+
+            write(json!({ "theme": "blue" })).await.unwrap();
+            assert_eq!(seen.lock().len(), 2);
+
+            delete_vault(&platform, vault_name).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_invalid_expression_is_rejected() {
+        let platform = Platform::new();
+        let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+
+        let result = block_on(watch_namespace(
+            &platform,
+            "watch_invalid_test",
+            &identity,
+            "settings",
+            "theme",
+            Arc::new(|_, _: &[Value]| {}),
+        ));
+
+        assert!(matches!(result, Err(VaultError::IoError(msg)) if msg.contains("JSONPath")));
+    }
+
+    #[test]
+    fn test_watches_follow_renamed_vaults() {
+        let platform = Platform::new();
+        let (vault_name, new_name) = ("watch_rename_test", "watch_renamed_test");
+        let identity = crate::domain::crypto::generate_identity(&platform).unwrap();
+        let public_key = crate::domain::crypto::identity_to_public(&platform, &identity).unwrap();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        block_on(async {
+            for name in [vault_name, new_name] {
+                let _ = delete_vault(&platform, name).await;
+            }
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+
+            let write = |vault_name, theme: &str| {
+                upsert_namespace(
+                    &platform,
+                    vault_name,
+                    &public_key,
+                    "settings",
+                    serde_json::to_vec(&json!({ "theme": theme })).unwrap(),
+                    None,
+                    true,
+                )
+            };
+            write(vault_name, "dark").await.unwrap();
+
+            let recorded = seen.clone();
+            let id = watch_namespace(
+                &platform,
+                vault_name,
+                &identity,
+                "settings",
+                "$.theme",
+                Arc::new(move |_, values: &[Value]| recorded.lock().push(values.to_vec())),
+            )
+            .await
+            .unwrap();
+
+            rename_vault(&platform, vault_name, new_name).await.unwrap();
+            write(new_name, "light").await.unwrap();
+            assert_eq!(*seen.lock(), vec![vec![json!("light")]]);
+
+            // A new vault under the old name is not watched.
+            save_vault(&platform, vault_name, create_vault().await.unwrap())
+                .await
+                .unwrap();
+            write(vault_name, "blue").await.unwrap();
+            assert_eq!(seen.lock().len(), 1);
+
+            assert!(unwatch_namespace(id));
+            for name in [vault_name, new_name] {
+                delete_vault(&platform, name).await.unwrap();
+            }
+        });
+    }
+}
@@ -0,0 +1,363 @@
+//! Settings of the command line tool and native daemons, kept encrypted.
+//!
+//! The file lives in `$XDG_CONFIG_HOME/hoddor` (`HODDOR_CONFIG_DIR`
+//! overrides it) and uses the encrypted export format, so it holds private
+//! keys without exposing them on disk. It is encrypted to the passphrase in
+//! `HODDOR_CONFIG_PASSPHRASE` when that is set, and otherwise to a key file
+//! created next to it on first save, readable by its owner only. Scripts then
+//! refer to identities by label instead of passing keys as arguments.
+
+use super::crypto::IdentityHandle;
+use crate::domain::crypto;
+use crate::domain::vault::encrypted_export::{self, ExportKey, ExportSecret};
+use crate::domain::vault::error::VaultError;
+use crate::platform::Platform;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const CONFIG_FILENAME: &str = "config.age";
+const KEY_FILENAME: &str = "config.key";
+
+/// Key derivation for vault identities.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kdf {
+    #[default]
+    Argon2id,
+}
+
+impl FromStr for Kdf {
+    type Err = VaultError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "argon2id" => Ok(Kdf::Argon2id),
+            other => Err(VaultError::io_error(format!("Unknown KDF: {other}"))),
+        }
+    }
+}
+
+impl fmt::Display for Kdf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kdf::Argon2id => write!(f, "argon2id"),
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Config {
+    /// Directory holding the vaults, `./hoddor_data` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault_directory: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signaling_server: Option<String>,
+    #[serde(default)]
+    pub kdf: Kdf,
+    /// Private keys by label.
+    #[serde(default)]
+    identities: BTreeMap<String, String>,
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("vault_directory", &self.vault_directory)
+            .field("signaling_server", &self.signaling_server)
+            .field("kdf", &self.kdf)
+            .field("identities", &self.identities.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Config {
+    /// Settings readable with [`get`](Config::get) and [`set`](Config::set).
+    pub const KEYS: [&'static str; 3] = ["vault-directory", "signaling-server", "kdf"];
+
+    pub fn get(&self, key: &str) -> Result<Option<String>, VaultError> {
+        match key {
+            "vault-directory" => Ok(self.vault_directory.clone()),
+            "signaling-server" => Ok(self.signaling_server.clone()),
+            "kdf" => Ok(Some(self.kdf.to_string())),
+            other => Err(unknown_key(other)),
+        }
+    }
+
+    /// Sets `key`, or resets it to its default with `None`.
+    pub fn set(&mut self, key: &str, value: Option<&str>) -> Result<(), VaultError> {
+        match key {
+            "vault-directory" => self.vault_directory = value.map(str::to_string),
+            "signaling-server" => {
+                if let Some(url) =
+                    value.filter(|url| !(url.starts_with("ws://") || url.starts_with("wss://")))
+                {
+                    return Err(VaultError::io_error(format!(
+                        "Signaling server must be a ws:// or wss:// URL, got {url}"
+                    )));
+                }
+                self.signaling_server = value.map(str::to_string);
+            }
+            "kdf" => self.kdf = value.map(str::parse).transpose()?.unwrap_or_default(),
+            other => return Err(unknown_key(other)),
+        }
+        Ok(())
+    }
+
+    pub fn identity_labels(&self) -> impl Iterator<Item = &str> {
+        self.identities.keys().map(String::as_str)
+    }
+
+    pub fn identity(&self, label: &str) -> Result<IdentityHandle, VaultError> {
+        let private_key = self
+            .identities
+            .get(label)
+            .ok_or_else(|| VaultError::io_error(format!("No identity labelled {label}")))?;
+
+        IdentityHandle::from_private_key(private_key)
+            .map_err(|e| VaultError::io_error(e.to_string()))
+    }
+
+    /// Stores `identity` under `label`, replacing any identity it had.
+    pub fn add_identity(
+        &mut self,
+        label: &str,
+        identity: &IdentityHandle,
+    ) -> Result<(), VaultError> {
+        if label.is_empty() || label.chars().any(char::is_whitespace) {
+            return Err(VaultError::io_error(
+                "Identity labels must be non-empty and without whitespace",
+            ));
+        }
+
+        self.identities
+            .insert(label.to_string(), identity.private_key());
+        Ok(())
+    }
+
+    /// Returns whether an identity was stored under `label`.
+    pub fn remove_identity(&mut self, label: &str) -> bool {
+        self.identities.remove(label).is_some()
+    }
+}
+
+fn unknown_key(key: &str) -> VaultError {
+    VaultError::io_error(format!(
+        "Unknown setting: {key} (expected one of {})",
+        Config::KEYS.join(", ")
+    ))
+}
+
+/// Location of the encrypted config file and what decrypts it.
+pub struct ConfigFile {
+    directory: PathBuf,
+    passphrase: Option<String>,
+}
+
+impl ConfigFile {
+    /// The file in the user's config directory, decrypted with
+    /// `HODDOR_CONFIG_PASSPHRASE` when set.
+    pub fn locate() -> Result<Self, VaultError> {
+        let directory = std::env::var_os("HODDOR_CONFIG_DIR")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("XDG_CONFIG_HOME").map(|dir| PathBuf::from(dir).join("hoddor"))
+            })
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config/hoddor"))
+            })
+            .or_else(|| std::env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("hoddor")))
+            .ok_or_else(|| VaultError::io_error("No config directory: set HODDOR_CONFIG_DIR"))?;
+
+        Ok(Self {
+            directory,
+            passphrase: std::env::var("HODDOR_CONFIG_PASSPHRASE").ok(),
+        })
+    }
+
+    /// The file in `directory`, encrypted to its key file.
+    pub fn at(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            passphrase: None,
+        }
+    }
+
+    pub fn with_passphrase(mut self, passphrase: &str) -> Self {
+        self.passphrase = Some(passphrase.to_string());
+        self
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.directory.join(CONFIG_FILENAME)
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path().is_file()
+    }
+
+    /// Reads the config; a missing file gives the defaults.
+    pub async fn load(&self, platform: &Platform) -> Result<Config, VaultError> {
+        let path = self.path();
+        if !path.is_file() {
+            return Ok(Config::default());
+        }
+
+        let bytes = fs::read(&path)
+            .map_err(|e| VaultError::io_error(format!("Failed to read {}: {e}", path.display())))?;
+
+        let plain = match &self.passphrase {
+            Some(passphrase) => {
+                encrypted_export::decrypt_export(
+                    platform,
+                    &bytes,
+                    ExportSecret::Passphrase(passphrase),
+                )
+                .await?
+            }
+            None => {
+                let identity = self.read_key()?.ok_or_else(|| {
+                    VaultError::io_error(format!(
+                        "Missing {KEY_FILENAME}: set HODDOR_CONFIG_PASSPHRASE to read {}",
+                        path.display()
+                    ))
+                })?;
+                encrypted_export::decrypt_export(
+                    platform,
+                    &bytes,
+                    ExportSecret::Identity(&identity),
+                )
+                .await?
+            }
+        };
+
+        serde_json::from_slice(&plain).map_err(|e| VaultError::serialization_error(e.to_string()))
+    }
+
+    /// Encrypts and writes `config`, creating the key file if needed.
+    pub async fn save(&self, platform: &Platform, config: &Config) -> Result<(), VaultError> {
+        fs::create_dir_all(&self.directory)
+            .map_err(|_| VaultError::io_error("Failed to create config directory"))?;
+
+        let plain = serde_json::to_vec(config)
+            .map_err(|e| VaultError::serialization_error(e.to_string()))?;
+
+        let encrypted = match &self.passphrase {
+            Some(passphrase) => {
+                encrypted_export::encrypt_export(
+                    platform,
+                    &plain,
+                    ExportKey::Passphrase(passphrase),
+                )
+                .await?
+            }
+            None => {
+                let identity = match self.read_key()? {
+                    Some(identity) => identity,
+                    None => {
+                        let identity = crypto::generate_identity(platform)
+                            .map_err(|e| VaultError::io_error(e.to_string()))?;
+                        write_private(&self.directory.join(KEY_FILENAME), identity.as_bytes())?;
+                        identity
+                    }
+                };
+                let recipient = crypto::identity_to_public(platform, &identity)
+                    .map_err(|e| VaultError::io_error(e.to_string()))?;
+                encrypted_export::encrypt_export(platform, &plain, ExportKey::Recipient(&recipient))
+                    .await?
+            }
+        };
+
+        write_private(&self.path(), &encrypted)
+    }
+
+    fn read_key(&self) -> Result<Option<String>, VaultError> {
+        match fs::read_to_string(self.directory.join(KEY_FILENAME)) {
+            Ok(key) => Ok(Some(key.trim().to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(VaultError::io_error(format!(
+                "Failed to read {KEY_FILENAME}: {e}"
+            ))),
+        }
+    }
+}
+
+/// Writes through a temporary file so a crash never leaves half a file,
+/// readable by the owner only on Unix.
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), VaultError> {
+    let temporary = path.with_extension("tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let write = || -> std::io::Result<()> {
+        use std::io::Write;
+        let mut file = options.open(&temporary)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&temporary, path)
+    };
+
+    write().map_err(|e| VaultError::io_error(format!("Failed to write {}: {e}", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_config_roundtrip_through_key_file_and_passphrase() {
+        let platform = Platform::new();
+        let directory = std::env::temp_dir().join(format!("hoddor_config_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+
+        let mut config = Config::default();
+        config.set("vault-directory", Some("/srv/vaults")).unwrap();
+        config
+            .set("signaling-server", Some("wss://signal.example.com"))
+            .unwrap();
+        assert!(config.set("signaling-server", Some("https://x")).is_err());
+        assert!(config.set("colour", Some("blue")).is_err());
+        let identity =
+            IdentityHandle::from_private_key(&crypto::generate_identity(&platform).unwrap())
+                .unwrap();
+        config.add_identity("deploy", &identity).unwrap();
+
+        block_on(async {
+            let file = ConfigFile::at(&directory);
+            assert_eq!(file.load(&platform).await.unwrap(), Config::default());
+
+            file.save(&platform, &config).await.unwrap();
+            let raw = fs::read(file.path()).unwrap();
+            assert!(!String::from_utf8_lossy(&raw).contains("srv/vaults"));
+
+            let loaded = file.load(&platform).await.unwrap();
+            assert_eq!(loaded, config);
+            assert_eq!(
+                loaded.identity("deploy").unwrap().public_key(),
+                identity.public_key()
+            );
+            assert!(!format!("{loaded:?}").contains("AGE-SECRET-KEY"));
+
+            let protected = ConfigFile::at(&directory).with_passphrase("correct horse battery");
+            protected.save(&platform, &config).await.unwrap();
+            assert_eq!(protected.load(&platform).await.unwrap(), config);
+            assert!(ConfigFile::at(&directory)
+                .with_passphrase("wrong")
+                .load(&platform)
+                .await
+                .is_err());
+        });
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -1,5 +1,7 @@
+pub mod config;
 pub mod crypto;
 pub mod vault;
 
+pub use config::{Config, ConfigFile, Kdf};
 pub use crypto::{generate_identity, CryptoError, IdentityHandle, RecipientHandle};
 pub use vault::VaultManager;
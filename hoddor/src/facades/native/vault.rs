@@ -1,6 +1,28 @@
+#[cfg(feature = "fido2")]
+use crate::adapters::native::Fido2Prf;
 use crate::domain::authentication;
-use crate::domain::vault::{error::VaultError, operations, validation, Vault};
+#[cfg(feature = "fido2")]
+use crate::domain::crypto;
+use crate::domain::io_stats::{self, IoStats};
+use crate::domain::progress::Progress;
+use crate::domain::usage_stats::{self, AggregatedStats};
+#[cfg(feature = "mapped")]
+use crate::domain::vault::MappedVault;
+use crate::domain::vault::{
+    access, aggregate, chunks, cleanup_scheduler, compression, crdt, credentials, crypto_audit,
+    device_link, diff, encrypted_export, error::VaultError, expiration, history, import,
+    incremental, integrity, limits, log, merge, migration, operations, patch, query, residency,
+    retention, rotation, sync_policy, sync_profile, sync_recording, timelock, validation, wal,
+    watch, CleanupSchedule, Compression, CrdtKind, CrdtOperation, CredentialInfo, CredentialRekey,
+    CryptoDescription, DeviceLinkExport, ExportKey, ExportSecret, ImportOptions, ImportReport,
+    IncrementalReport, IntegrityManifest, IntegrityReport, KeyShare, LogEntry, MergeReport,
+    MergeStrategy, MigrationReport, MigrationStatus, NamespaceReader, NamespaceWriter,
+    NoisyAggregate, PrivacyBudget, PrunePolicy, RecordedSync, RetentionPolicy, SnapshotInfo,
+    SumQuery, SyncPolicy, SyncProfile, UpsertOptions, Vault, VaultDiff, VaultHandle,
+};
 use crate::platform::Platform;
+use std::collections::BTreeSet;
+use std::sync::Arc;
 
 pub struct VaultManager {
     platform: Platform,
@@ -37,6 +59,187 @@ impl VaultManager {
         Ok((identity_keys.public_key, identity_keys.private_key))
     }
 
+    /// Re-encrypts `vault_name` to an identity derived from
+    /// `new_passphrase` and forgets the old identity, returning the new
+    /// public and private keys.
+    pub async fn rotate_vault_identity(
+        &self,
+        vault_name: &str,
+        old_identity_private_key: &str,
+        new_passphrase: &str,
+    ) -> Result<(String, String), VaultError> {
+        validation::validate_passphrase(new_passphrase)?;
+        validation::validate_vault_name(vault_name)?;
+
+        let identity_keys = rotation::rotate_vault_identity(
+            &self.platform,
+            vault_name,
+            old_identity_private_key,
+            new_passphrase,
+        )
+        .await?;
+
+        Ok((identity_keys.public_key, identity_keys.private_key))
+    }
+
+    pub async fn list_credentials(
+        &self,
+        vault_name: &str,
+    ) -> Result<Vec<CredentialInfo>, VaultError> {
+        validation::validate_vault_name(vault_name)?;
+        credentials::list_credentials(&self.platform, vault_name).await
+    }
+
+    pub async fn rename_credential(
+        &self,
+        vault_name: &str,
+        username: &str,
+        new_username: &str,
+    ) -> Result<(), VaultError> {
+        validation::validate_vault_name(vault_name)?;
+        credentials::rename_credential(&self.platform, vault_name, username, new_username).await
+    }
+
+    /// Forgets the credential of `username`, rekeying the vault when
+    /// `rekey` is given; see [`credentials::revoke_credential`]. Returns the
+    /// public and private keys of the new identity after a rekey.
+    pub async fn revoke_credential(
+        &self,
+        vault_name: &str,
+        username: &str,
+        rekey: Option<&CredentialRekey>,
+    ) -> Result<Option<(String, String)>, VaultError> {
+        validation::validate_vault_name(vault_name)?;
+        if let Some(rekey) = rekey {
+            validation::validate_passphrase(&rekey.new_passphrase)?;
+        }
+
+        let identity_keys =
+            credentials::revoke_credential(&self.platform, vault_name, username, rekey).await?;
+
+        Ok(identity_keys.map(|keys| (keys.public_key, keys.private_key)))
+    }
+
+    /// Registers a credential for `username` on the plugged-in security
+    /// key and returns the public and private keys of the identity it
+    /// derives. The key asks for two touches: one to create the credential,
+    /// one to evaluate it.
+    #[cfg(feature = "fido2")]
+    pub async fn create_security_key_credential(
+        &self,
+        vault_name: &str,
+        username: &str,
+        pin: Option<&str>,
+    ) -> Result<(String, String), VaultError> {
+        use rand::RngCore;
+
+        validation::validate_vault_name(vault_name)?;
+
+        let security_key = Fido2Prf::new();
+        let mut salt = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+
+        let credential_id = security_key
+            .make_credential(username, pin)
+            .map_err(|e| VaultError::io_error(e.to_string()))?;
+        let (private_key, public_key) =
+            self.security_key_identity(&security_key, &credential_id, &salt, pin)?;
+
+        let _guard = operations::lock_vault(&self.platform, vault_name).await?;
+        let mut vault = operations::read_vault(&self.platform, vault_name).await?;
+
+        vault.identity_salts.set_salt(public_key.clone(), salt);
+        authentication::record_key_check(&mut vault.identity_salts, &public_key, &private_key);
+        vault
+            .identity_salts
+            .set_credential_id(public_key.clone(), credential_id);
+        vault.identity_salts.set_credential_created_at(
+            public_key.clone(),
+            (self.platform.clock().now() / 1000.0) as i64,
+        );
+        vault
+            .username_pk
+            .insert(username.to_string(), public_key.clone());
+
+        operations::save_vault(&self.platform, vault_name, vault).await?;
+
+        Ok((public_key, private_key))
+    }
+
+    /// Unlocks the identity of `username` with the plugged-in security key,
+    /// returning its public and private keys.
+    #[cfg(feature = "fido2")]
+    pub async fn unlock_with_security_key(
+        &self,
+        vault_name: &str,
+        username: &str,
+        pin: Option<&str>,
+    ) -> Result<(String, String), VaultError> {
+        validation::validate_vault_name(vault_name)?;
+
+        let vault = operations::read_vault(&self.platform, vault_name).await?;
+        let public_key = vault.username_pk.get(username).ok_or_else(|| {
+            VaultError::io_error(format!("No credential registered for {username}"))
+        })?;
+        let credential_id = vault
+            .identity_salts
+            .get_credential_id(public_key)
+            .ok_or_else(|| VaultError::io_error("No security key credential for this user"))?;
+        let salt = vault
+            .identity_salts
+            .get_salt(public_key)
+            .ok_or(VaultError::InvalidPassword)?;
+
+        let (private_key, derived_public_key) =
+            self.security_key_identity(&Fido2Prf::new(), credential_id, salt, pin)?;
+        if derived_public_key != *public_key {
+            return Err(VaultError::InvalidPassword);
+        }
+
+        Ok((derived_public_key, private_key))
+    }
+
+    #[cfg(feature = "fido2")]
+    fn security_key_identity(
+        &self,
+        security_key: &Fido2Prf,
+        credential_id: &[u8],
+        salt: &[u8; 32],
+        pin: Option<&str>,
+    ) -> Result<(String, String), VaultError> {
+        let (first, second) = security_key
+            .evaluate(credential_id, salt, pin)
+            .map_err(|e| VaultError::io_error(e.to_string()))?;
+        let private_key = crypto::identity_from_prf(&self.platform, &first, &second)
+            .map_err(|e| VaultError::io_error(e.to_string()))?;
+        let public_key = crypto::identity_to_public(&self.platform, &private_key)
+            .map_err(|e| VaultError::io_error(e.to_string()))?;
+
+        Ok((private_key, public_key))
+    }
+
+    /// Opens `vault_name` for repeated use. Dropping the handle closes it.
+    // The platform is only `Copy` without the graph feature.
+    #[allow(clippy::clone_on_copy)]
+    pub async fn open_vault(&self, vault_name: &str) -> Result<VaultHandle, VaultError> {
+        VaultHandle::open(self.platform.clone(), vault_name).await
+    }
+
+    /// Opens `vault_name` read-only, memory-mapping each namespace as it is
+    /// read instead of loading them all. Meant for vaults too large to hold
+    /// in memory.
+    // The platform is only `Copy` without the graph feature.
+    #[allow(clippy::clone_on_copy)]
+    #[cfg(feature = "mapped")]
+    pub async fn open_vault_mapped(&self, vault_name: &str) -> Result<MappedVault, VaultError> {
+        validation::validate_vault_name(vault_name)?;
+        MappedVault::open(self.platform.clone(), vault_name).await
+    }
+
+    pub fn clear_identity_cache(&self) {
+        authentication::clear_identity_cache();
+    }
+
     pub async fn upsert_namespace(
         &self,
         vault_name: &str,
@@ -60,6 +263,54 @@ impl VaultManager {
         .await
     }
 
+    pub async fn upsert_namespace_with(
+        &self,
+        vault_name: &str,
+        identity_public_key: &str,
+        namespace: &str,
+        data: Vec<u8>,
+        options: UpsertOptions,
+    ) -> Result<(), VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        operations::upsert_namespace_with(
+            &self.platform,
+            vault_name,
+            identity_public_key,
+            namespace,
+            data,
+            options,
+        )
+        .await
+    }
+
+    pub async fn upsert_namespace_deduplicated(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        namespace: &str,
+        data: Vec<u8>,
+        expires_in_seconds: Option<i64>,
+        replace_if_exists: bool,
+    ) -> Result<(), VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        operations::upsert_namespace_deduplicated(
+            &self.platform,
+            vault_name,
+            identity_private_key,
+            namespace,
+            data,
+            expires_in_seconds,
+            replace_if_exists,
+        )
+        .await
+    }
+
+    pub async fn collect_garbage(&self, vault_name: &str) -> Result<usize, VaultError> {
+        operations::collect_vault_garbage(&self.platform, vault_name).await
+    }
+
     pub async fn read_namespace(
         &self,
         vault_name: &str,
@@ -72,6 +323,175 @@ impl VaultManager {
             .await
     }
 
+    /// Applies the RFC 6902 JSON Patch `patch` to the JSON document stored
+    /// in `namespace`, entirely or not at all.
+    pub async fn patch_namespace(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        namespace: &str,
+        patch: &serde_json::Value,
+    ) -> Result<(), VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        patch::patch_namespace(
+            &self.platform,
+            vault_name,
+            identity_private_key,
+            namespace,
+            patch,
+        )
+        .await
+    }
+
+    /// Applies `operation` to the CRDT value stored in `namespace`, creating
+    /// it as a `kind` if needed, and returns the new value.
+    pub async fn update_crdt(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        namespace: &str,
+        kind: CrdtKind,
+        operation: &CrdtOperation,
+    ) -> Result<serde_json::Value, VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        crdt::update_crdt(
+            &self.platform,
+            vault_name,
+            identity_private_key,
+            namespace,
+            kind,
+            operation,
+        )
+        .await
+    }
+
+    /// Value of the CRDT stored in `namespace`, merged with the copies
+    /// received from peers.
+    pub async fn read_crdt(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        namespace: &str,
+    ) -> Result<serde_json::Value, VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        crdt::read_crdt(&self.platform, vault_name, identity_private_key, namespace).await
+    }
+
+    /// Values the JSONPath `path` selects in the JSON document stored in
+    /// `namespace`.
+    pub async fn query_namespace(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        namespace: &str,
+        path: &str,
+    ) -> Result<Vec<serde_json::Value>, VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        query::query_namespace(
+            &self.platform,
+            vault_name,
+            identity_private_key,
+            namespace,
+            path,
+        )
+        .await
+    }
+
+    /// Calls `callback` with the values the JSONPath `path` selects in
+    /// `namespace` each time they change, from local writes or sync.
+    /// Returns the id to pass to [`VaultManager::unwatch_namespace`].
+    pub async fn watch_namespace(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        namespace: &str,
+        path: &str,
+        callback: impl Fn(&[serde_json::Value]) + Send + Sync + 'static,
+    ) -> Result<u32, VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        watch::watch_namespace(
+            &self.platform,
+            vault_name,
+            identity_private_key,
+            namespace,
+            path,
+            Arc::new(move |_, values: &[serde_json::Value]| callback(values)),
+        )
+        .await
+    }
+
+    pub fn unwatch_namespace(&self, watch_id: u32) -> bool {
+        watch::unwatch_namespace(watch_id)
+    }
+
+    /// Calls `callback` with the name of each namespace of `vault_name`
+    /// removed once expired, by a read or a cleanup. Returns the id to pass
+    /// to [`VaultManager::off_namespace_expired`].
+    pub fn on_namespace_expired(
+        &self,
+        vault_name: &str,
+        callback: impl Fn(&str) + Send + Sync + 'static,
+    ) -> u32 {
+        expiration::on_namespace_expired(
+            vault_name,
+            Arc::new(move |_, namespace: &str| callback(namespace)),
+        )
+    }
+
+    pub fn off_namespace_expired(&self, subscription_id: u32) -> bool {
+        expiration::off_namespace_expired(subscription_id)
+    }
+
+    /// Seals `data` until `release_at` (Unix seconds). The returned shares
+    /// go to `holders`; any `threshold` of them, released after that date,
+    /// decrypt the namespace.
+    pub async fn write_timelocked(
+        &self,
+        vault_name: &str,
+        namespace: &str,
+        data: Vec<u8>,
+        release_at: i64,
+        holders: &[&str],
+        threshold: u8,
+    ) -> Result<Vec<KeyShare>, VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        timelock::write_timelocked(
+            &self.platform,
+            vault_name,
+            namespace,
+            data,
+            release_at,
+            holders,
+            threshold,
+        )
+        .await
+    }
+
+    pub async fn release_key_share(
+        &self,
+        share: &KeyShare,
+        holder_identity: &str,
+    ) -> Result<Vec<u8>, VaultError> {
+        timelock::release_key_share(&self.platform, share, holder_identity).await
+    }
+
+    pub async fn read_timelocked(
+        &self,
+        vault_name: &str,
+        namespace: &str,
+        released_shares: &[Vec<u8>],
+    ) -> Result<Vec<u8>, VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        timelock::read_timelocked(&self.platform, vault_name, namespace, released_shares).await
+    }
+
     pub async fn remove_namespace(
         &self,
         vault_name: &str,
@@ -82,10 +502,60 @@ impl VaultManager {
         operations::remove_namespace(&self.platform, vault_name, namespace).await
     }
 
+    pub async fn rename_namespace(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<(), VaultError> {
+        validation::validate_namespace(from)?;
+
+        operations::rename_namespace(&self.platform, vault_name, identity_private_key, from, to)
+            .await
+    }
+
+    pub async fn copy_namespace(
+        &self,
+        vault_name: &str,
+        target_vault: &str,
+        identity_private_key: &str,
+        namespace: &str,
+    ) -> Result<(), VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        operations::copy_namespace(
+            &self.platform,
+            vault_name,
+            target_vault,
+            identity_private_key,
+            namespace,
+        )
+        .await
+    }
+
     pub async fn list_namespaces(&self, vault_name: &str) -> Result<Vec<String>, VaultError> {
         operations::list_namespaces_in_vault(&self.platform, vault_name).await
     }
 
+    pub async fn list_namespaces_with_prefix(
+        &self,
+        vault_name: &str,
+        prefix: &str,
+    ) -> Result<Vec<String>, VaultError> {
+        validation::validate_namespace_prefix(prefix)?;
+
+        operations::list_namespaces_with_prefix(&self.platform, vault_name, prefix).await
+    }
+
+    pub async fn remove_prefix(
+        &self,
+        vault_name: &str,
+        prefix: &str,
+    ) -> Result<Vec<String>, VaultError> {
+        operations::remove_prefix(&self.platform, vault_name, prefix).await
+    }
+
     pub async fn create_vault(&self, vault_name: &str) -> Result<(), VaultError> {
         validation::validate_vault_name(vault_name)?;
 
@@ -101,24 +571,703 @@ impl VaultManager {
         operations::save_vault(&self.platform, vault_name, vault).await
     }
 
-    pub async fn remove_vault(&self, vault_name: &str) -> Result<(), VaultError> {
-        operations::delete_vault(&self.platform, vault_name).await
-    }
+    pub async fn create_observer_vault(&self, vault_name: &str) -> Result<(), VaultError> {
+        validation::validate_vault_name(vault_name)?;
 
-    pub async fn list_vaults(&self) -> Result<Vec<String>, VaultError> {
-        operations::list_vaults(&self.platform).await
-    }
+        if operations::read_vault(&self.platform, vault_name)
+            .await
+            .is_ok()
+        {
+            return Err(VaultError::VaultAlreadyExists);
+        }
 
-    pub async fn export_vault(&self, vault_name: &str) -> Result<Vec<u8>, VaultError> {
-        operations::export_vault_bytes(&self.platform, vault_name).await
+        let vault = operations::create_observer_vault().await?;
+
+        operations::save_vault(&self.platform, vault_name, vault).await
     }
 
-    pub async fn import_vault(
+    pub async fn grant_recipient(
         &self,
         vault_name: &str,
-        vault_bytes: &[u8],
+        identity_private_key: &str,
+        recipient_public_key: &str,
     ) -> Result<(), VaultError> {
-        operations::import_vault_from_bytes(&self.platform, vault_name, vault_bytes).await
+        operations::grant_vault_recipient(
+            &self.platform,
+            vault_name,
+            identity_private_key,
+            recipient_public_key,
+        )
+        .await
+    }
+
+    /// Lets the holder of `recipient_public_key` read `namespace` and no
+    /// other namespace of the vault.
+    pub async fn grant_namespace_access(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        namespace: &str,
+        recipient_public_key: &str,
+    ) -> Result<(), VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        access::grant_namespace_access(
+            &self.platform,
+            vault_name,
+            identity_private_key,
+            namespace,
+            recipient_public_key,
+        )
+        .await
+    }
+
+    /// Lets the holders of all `recipient_public_keys` read `namespace`,
+    /// re-encrypting it once.
+    pub async fn share_namespace(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        namespace: &str,
+        recipient_public_keys: &[&str],
+    ) -> Result<(), VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        access::share_namespace(
+            &self.platform,
+            vault_name,
+            identity_private_key,
+            namespace,
+            recipient_public_keys,
+        )
+        .await
+    }
+
+    pub async fn revoke_namespace_access(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        namespace: &str,
+        recipient_public_key: &str,
+    ) -> Result<(), VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        access::revoke_namespace_access(
+            &self.platform,
+            vault_name,
+            identity_private_key,
+            namespace,
+            recipient_public_key,
+        )
+        .await
+    }
+
+    pub async fn namespace_recipients(
+        &self,
+        vault_name: &str,
+        namespace: &str,
+    ) -> Result<Vec<String>, VaultError> {
+        access::namespace_recipients(&self.platform, vault_name, namespace).await
+    }
+
+    pub async fn set_namespace_retention(
+        &self,
+        vault_name: &str,
+        namespace: &str,
+        min_retention_seconds: Option<i64>,
+        delete_locked_until: Option<i64>,
+    ) -> Result<Option<RetentionPolicy>, VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        retention::set_namespace_retention(
+            &self.platform,
+            vault_name,
+            namespace,
+            min_retention_seconds,
+            delete_locked_until,
+        )
+        .await
+    }
+
+    pub async fn namespace_retention(
+        &self,
+        vault_name: &str,
+        namespace: &str,
+    ) -> Result<Option<RetentionPolicy>, VaultError> {
+        retention::namespace_retention(&self.platform, vault_name, namespace).await
+    }
+
+    pub async fn set_namespace_residency(
+        &self,
+        vault_name: &str,
+        namespace: &str,
+        tags: &[&str],
+    ) -> Result<(), VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        residency::set_namespace_residency(
+            &self.platform,
+            vault_name,
+            namespace,
+            residency::normalize_tags(tags),
+        )
+        .await
+    }
+
+    pub async fn namespace_residency(
+        &self,
+        vault_name: &str,
+        namespace: &str,
+    ) -> Result<BTreeSet<String>, VaultError> {
+        residency::namespace_residency(&self.platform, vault_name, namespace).await
+    }
+
+    /// Writer encrypting `namespace` piece by piece, for payloads too large
+    /// to hold in memory.
+    pub async fn namespace_writer(
+        &self,
+        vault_name: &str,
+        identity_public_key: &str,
+        namespace: &str,
+        expires_in_seconds: Option<i64>,
+        replace_if_exists: bool,
+    ) -> Result<NamespaceWriter<'_>, VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        NamespaceWriter::open(
+            &self.platform,
+            vault_name,
+            identity_public_key,
+            namespace,
+            expires_in_seconds,
+            replace_if_exists,
+        )
+        .await
+    }
+
+    pub async fn namespace_reader(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        namespace: &str,
+    ) -> Result<NamespaceReader<'_>, VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        NamespaceReader::open(&self.platform, vault_name, identity_private_key, namespace).await
+    }
+
+    pub async fn append_to_log(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        log_name: &str,
+        entry: &[u8],
+    ) -> Result<LogEntry, VaultError> {
+        log::append_to_log(
+            &self.platform,
+            vault_name,
+            identity_private_key,
+            log_name,
+            entry,
+        )
+        .await
+    }
+
+    /// Entries of `log_name` appended from `from` (inclusive) to `to`
+    /// (exclusive), in milliseconds since the Unix epoch.
+    pub async fn read_log_range(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        log_name: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<LogEntry>, VaultError> {
+        log::read_log_range(
+            &self.platform,
+            vault_name,
+            identity_private_key,
+            log_name,
+            from,
+            to,
+        )
+        .await
+    }
+
+    /// Total privacy budget of the noisy aggregates of `log_name`; see
+    /// [`aggregate`].
+    pub async fn set_privacy_budget(
+        &self,
+        vault_name: &str,
+        log_name: &str,
+        total: f64,
+    ) -> Result<(), VaultError> {
+        aggregate::set_privacy_budget(&self.platform, vault_name, log_name, total).await
+    }
+
+    pub async fn privacy_budget(
+        &self,
+        vault_name: &str,
+        log_name: &str,
+    ) -> Result<PrivacyBudget, VaultError> {
+        aggregate::privacy_budget(&self.platform, vault_name, log_name).await
+    }
+
+    pub async fn noisy_count(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        log_name: &str,
+        from: i64,
+        to: i64,
+        epsilon: f64,
+    ) -> Result<NoisyAggregate, VaultError> {
+        aggregate::noisy_count(
+            &self.platform,
+            vault_name,
+            identity_private_key,
+            log_name,
+            from,
+            to,
+            epsilon,
+        )
+        .await
+    }
+
+    pub async fn noisy_sum(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        log_name: &str,
+        from: i64,
+        to: i64,
+        query: &SumQuery,
+    ) -> Result<NoisyAggregate, VaultError> {
+        aggregate::noisy_sum(
+            &self.platform,
+            vault_name,
+            identity_private_key,
+            log_name,
+            from,
+            to,
+            query,
+        )
+        .await
+    }
+
+    /// Largest namespace payload `vault_name` accepts; `None` restores
+    /// [`limits::DEFAULT_MAX_NAMESPACE_BYTES`].
+    pub async fn set_max_namespace_bytes(
+        &self,
+        vault_name: &str,
+        max_bytes: Option<u64>,
+    ) -> Result<(), VaultError> {
+        limits::set_max_namespace_bytes(&self.platform, vault_name, max_bytes).await
+    }
+
+    pub async fn max_namespace_bytes(&self, vault_name: &str) -> Result<u64, VaultError> {
+        limits::namespace_size_limit(&self.platform, vault_name).await
+    }
+
+    /// Compression of writes to `vault_name` that do not choose one.
+    pub async fn set_vault_compression(
+        &self,
+        vault_name: &str,
+        compression: Compression,
+    ) -> Result<(), VaultError> {
+        compression::set_vault_compression(&self.platform, vault_name, compression).await
+    }
+
+    pub async fn vault_compression(&self, vault_name: &str) -> Result<Compression, VaultError> {
+        compression::vault_compression(&self.platform, vault_name).await
+    }
+
+    /// Payload size above which namespaces of every vault are split into
+    /// chunk files; zero keeps them whole.
+    pub fn set_split_threshold(&self, bytes: u64) {
+        chunks::set_split_threshold(bytes);
+    }
+
+    pub async fn freeze_vault(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+    ) -> Result<(), VaultError> {
+        operations::freeze_vault(&self.platform, vault_name, identity_private_key).await
+    }
+
+    pub async fn unfreeze_vault(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+    ) -> Result<(), VaultError> {
+        operations::unfreeze_vault(&self.platform, vault_name, identity_private_key).await
+    }
+
+    pub async fn is_vault_frozen(&self, vault_name: &str) -> Result<bool, VaultError> {
+        Ok(operations::read_vault_metadata(&self.platform, vault_name)
+            .await?
+            .frozen)
+    }
+
+    /// Keeps a snapshot of every later namespace write of `vault_name`.
+    pub async fn enable_history(&self, vault_name: &str) -> Result<(), VaultError> {
+        history::enable_history(&self.platform, vault_name).await
+    }
+
+    pub async fn disable_history(&self, vault_name: &str) -> Result<(), VaultError> {
+        history::disable_history(&self.platform, vault_name).await
+    }
+
+    pub async fn is_history_enabled(&self, vault_name: &str) -> bool {
+        history::is_history_enabled(&self.platform, vault_name).await
+    }
+
+    pub async fn list_snapshots(&self, vault_name: &str) -> Result<Vec<SnapshotInfo>, VaultError> {
+        history::list_snapshots(&self.platform, vault_name).await
+    }
+
+    /// Records a signed manifest of the namespace files; see
+    /// [`integrity::seal_vault`].
+    pub async fn seal_vault(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+    ) -> Result<IntegrityManifest, VaultError> {
+        validation::validate_vault_name(vault_name)?;
+        integrity::seal_vault(&self.platform, vault_name, identity_private_key).await
+    }
+
+    /// Checks the plaintext of every namespace the identity can read
+    /// against its integrity MAC, and the namespace files against the
+    /// manifest; see [`integrity::verify_vault`].
+    pub async fn verify_vault(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+    ) -> Result<IntegrityReport, VaultError> {
+        validation::validate_vault_name(vault_name)?;
+        integrity::verify_vault(&self.platform, vault_name, identity_private_key).await
+    }
+
+    /// Algorithms and parameters `vault_name` is encrypted with; see
+    /// [`crypto_audit::describe_crypto`].
+    pub async fn describe_crypto(&self, vault_name: &str) -> Result<CryptoDescription, VaultError> {
+        validation::validate_vault_name(vault_name)?;
+        crypto_audit::describe_crypto(&self.platform, vault_name).await
+    }
+
+    /// Namespaces of `vault_name` per version of the algorithms, and how
+    /// many are due to be re-encrypted.
+    pub async fn migration_status(&self, vault_name: &str) -> Result<MigrationStatus, VaultError> {
+        validation::validate_vault_name(vault_name)?;
+        migration::migration_status(&self.platform, vault_name).await
+    }
+
+    /// Re-encrypts up to `max_namespaces` namespaces written with older
+    /// algorithms; see [`migration::migrate_vault`].
+    pub async fn migrate_vault(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        max_namespaces: usize,
+    ) -> Result<MigrationReport, VaultError> {
+        validation::validate_vault_name(vault_name)?;
+        migration::migrate_vault(
+            &self.platform,
+            vault_name,
+            identity_private_key,
+            max_namespaces,
+        )
+        .await
+    }
+
+    /// Whether reads re-encrypt namespaces written with older algorithms.
+    pub fn set_lazy_migration(&self, enabled: bool) {
+        migration::set_lazy_migration(enabled);
+    }
+
+    /// Content of `namespace` as it was in snapshot `snapshot_id`.
+    pub async fn read_at(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+        namespace: &str,
+        snapshot_id: &str,
+    ) -> Result<Vec<u8>, VaultError> {
+        validation::validate_namespace(namespace)?;
+
+        history::read_at(
+            &self.platform,
+            vault_name,
+            identity_private_key,
+            namespace,
+            snapshot_id,
+        )
+        .await
+    }
+
+    /// Drops the snapshots `policy` rules out and returns how many.
+    pub async fn prune_snapshots(
+        &self,
+        vault_name: &str,
+        policy: PrunePolicy,
+    ) -> Result<usize, VaultError> {
+        history::prune_snapshots(&self.platform, vault_name, policy).await
+    }
+
+    pub async fn promote_observer(
+        &self,
+        vault_name: &str,
+        identity_private_key: &str,
+    ) -> Result<(), VaultError> {
+        operations::promote_observer_vault(&self.platform, vault_name, identity_private_key).await
+    }
+
+    pub async fn remove_vault(&self, vault_name: &str) -> Result<(), VaultError> {
+        operations::delete_vault(&self.platform, vault_name).await
+    }
+
+    pub async fn rename_vault(&self, vault_name: &str, new_name: &str) -> Result<(), VaultError> {
+        operations::rename_vault(&self.platform, vault_name, new_name).await
+    }
+
+    pub async fn clone_vault(&self, vault_name: &str, new_name: &str) -> Result<(), VaultError> {
+        operations::clone_vault(&self.platform, vault_name, new_name).await
+    }
+
+    pub async fn list_vaults(&self) -> Result<Vec<String>, VaultError> {
+        operations::list_vaults(&self.platform).await
+    }
+
+    pub async fn migrate_vault_files(&self, vault_name: &str) -> Result<usize, VaultError> {
+        operations::migrate_vault_files(&self.platform, vault_name).await
+    }
+
+    pub async fn recover_vaults(&self) -> Result<Vec<String>, VaultError> {
+        wal::recover_all_vaults(&self.platform).await
+    }
+
+    pub async fn export_vault(&self, vault_name: &str) -> Result<Vec<u8>, VaultError> {
+        operations::export_vault_bytes(&self.platform, vault_name).await
+    }
+
+    pub async fn export_vault_with_progress(
+        &self,
+        vault_name: &str,
+        on_progress: impl Fn(Progress),
+    ) -> Result<Vec<u8>, VaultError> {
+        operations::export_vault_bytes_with_progress(&self.platform, vault_name, &on_progress).await
+    }
+
+    pub async fn import_vault(
+        &self,
+        vault_name: &str,
+        vault_bytes: &[u8],
+    ) -> Result<(), VaultError> {
+        operations::import_vault_from_bytes(&self.platform, vault_name, vault_bytes).await
+    }
+
+    /// Exports `vault_name` encrypted to a recipient or a passphrase; see
+    /// [`encrypted_export`].
+    pub async fn export_vault_encrypted(
+        &self,
+        vault_name: &str,
+        key: ExportKey<'_>,
+    ) -> Result<Vec<u8>, VaultError> {
+        encrypted_export::export_vault_encrypted(&self.platform, vault_name, key, &|_| {}).await
+    }
+
+    pub async fn import_vault_encrypted(
+        &self,
+        vault_name: &str,
+        bytes: &[u8],
+        secret: ExportSecret<'_>,
+    ) -> Result<(), VaultError> {
+        encrypted_export::import_vault_encrypted(&self.platform, vault_name, bytes, secret, &|_| {})
+            .await
+    }
+
+    /// Exports the namespaces written since `since`, Unix time in seconds;
+    /// see [`incremental`].
+    pub async fn export_vault_incremental(
+        &self,
+        vault_name: &str,
+        since: i64,
+    ) -> Result<Vec<u8>, VaultError> {
+        incremental::export_vault_incremental(&self.platform, vault_name, since).await
+    }
+
+    pub async fn apply_incremental_export(
+        &self,
+        vault_name: &str,
+        bytes: &[u8],
+    ) -> Result<IncrementalReport, VaultError> {
+        incremental::apply_incremental_export(&self.platform, vault_name, bytes).await
+    }
+
+    /// Starts reporting changes other processes make to the vault files;
+    /// see [`VaultWatcher`](crate::adapters::native::VaultWatcher).
+    #[cfg(feature = "watch")]
+    pub fn watch(
+        &self,
+        on_change: impl Fn(&crate::adapters::native::ExternalChange) + Send + 'static,
+    ) -> Result<crate::adapters::native::VaultWatcher, VaultError> {
+        crate::adapters::native::VaultWatcher::start(on_change)
+    }
+
+    /// Storage timings and sizes of `vault_name`; see [`io_stats`].
+    pub fn io_stats(&self, vault_name: &str) -> IoStats {
+        io_stats::io_stats(vault_name)
+    }
+
+    /// Noisy usage counters, spending part of the privacy budget; see
+    /// [`usage_stats`].
+    pub fn get_aggregated_stats(&self) -> Option<AggregatedStats> {
+        usage_stats::aggregated_stats()
+    }
+
+    /// Compares two stored vaults; see [`diff::diff_vaults`].
+    pub async fn diff_vaults(
+        &self,
+        left: &str,
+        right: &str,
+        identity_private_key: Option<&str>,
+    ) -> Result<VaultDiff, VaultError> {
+        diff::diff_stored_vaults(&self.platform, left, right, identity_private_key).await
+    }
+
+    /// Merges the stored vault `secondary` into `primary`; see
+    /// [`merge::merge_vaults`].
+    pub async fn merge_vaults(
+        &self,
+        primary: &str,
+        secondary: &str,
+        strategy: MergeStrategy,
+        dry_run: bool,
+    ) -> Result<MergeReport, VaultError> {
+        merge::merge_stored_vaults(&self.platform, primary, secondary, strategy, dry_run).await
+    }
+
+    pub async fn sync_policy(&self, vault_name: &str) -> Result<SyncPolicy, VaultError> {
+        sync_policy::sync_policy(&self.platform, vault_name).await
+    }
+
+    pub async fn set_sync_policy(
+        &self,
+        vault_name: &str,
+        policy: SyncPolicy,
+    ) -> Result<(), VaultError> {
+        sync_policy::set_sync_policy(&self.platform, vault_name, policy).await
+    }
+
+    pub async fn sync_profile(&self, vault_name: &str) -> Result<SyncProfile, VaultError> {
+        sync_profile::load_sync_profile(&self.platform, vault_name).await
+    }
+
+    pub async fn set_sync_profile(
+        &self,
+        vault_name: &str,
+        profile: &SyncProfile,
+    ) -> Result<(), VaultError> {
+        sync_profile::save_sync_profile(&self.platform, vault_name, profile).await
+    }
+
+    pub async fn export_sync_profile(
+        &self,
+        vault_name: &str,
+        passphrase: &str,
+    ) -> Result<Vec<u8>, VaultError> {
+        sync_profile::export_sync_profile(&self.platform, vault_name, passphrase).await
+    }
+
+    pub async fn import_sync_profile(
+        &self,
+        vault_name: &str,
+        passphrase: &str,
+        blob: &[u8],
+    ) -> Result<SyncProfile, VaultError> {
+        sync_profile::import_sync_profile(&self.platform, vault_name, passphrase, blob).await
+    }
+
+    /// Bundles what a new device needs to join `vault_name`, encrypted to a
+    /// one-time code valid for `valid_for_seconds`.
+    pub async fn export_device_link(
+        &self,
+        vault_name: &str,
+        valid_for_seconds: i64,
+    ) -> Result<DeviceLinkExport, VaultError> {
+        device_link::export_device_link(&self.platform, vault_name, valid_for_seconds).await
+    }
+
+    /// Creates the vault of a device link on this device and returns its
+    /// name.
+    pub async fn import_device_link(&self, code: &str, blob: &[u8]) -> Result<String, VaultError> {
+        device_link::import_device_link(&self.platform, code, blob).await
+    }
+
+    /// Records the sync messages of `vault_name`, encrypted to
+    /// `recipient_public_key`; see [`sync_recording`].
+    pub async fn start_sync_recording(
+        &self,
+        vault_name: &str,
+        recipient_public_key: &str,
+    ) -> Result<(), VaultError> {
+        sync_recording::start_sync_recording(&self.platform, vault_name, recipient_public_key).await
+    }
+
+    pub async fn stop_sync_recording(&self, vault_name: &str) -> Result<(), VaultError> {
+        sync_recording::stop_sync_recording(&self.platform, vault_name).await
+    }
+
+    pub async fn clear_sync_recording(&self, vault_name: &str) -> Result<(), VaultError> {
+        sync_recording::clear_sync_recording(&self.platform, vault_name).await
+    }
+
+    pub async fn export_sync_recording(&self, vault_name: &str) -> Result<String, VaultError> {
+        sync_recording::export_sync_recording(&self.platform, vault_name).await
+    }
+
+    pub async fn decrypt_sync_recording(
+        &self,
+        export: &str,
+        identity_private_key: &str,
+    ) -> Result<Vec<RecordedSync>, VaultError> {
+        sync_recording::decrypt_sync_recording(&self.platform, export, identity_private_key).await
+    }
+
+    pub async fn import_vault_with_progress(
+        &self,
+        vault_name: &str,
+        vault_bytes: &[u8],
+        on_progress: impl Fn(Progress),
+    ) -> Result<(), VaultError> {
+        operations::import_vault_from_bytes_with_progress(
+            &self.platform,
+            vault_name,
+            vault_bytes,
+            &on_progress,
+        )
+        .await
+    }
+
+    /// Restores some namespaces of an export, into a new or an existing
+    /// vault; see [`import::import_namespaces`].
+    pub async fn import_namespaces(
+        &self,
+        vault_name: &str,
+        vault_bytes: &[u8],
+        options: &ImportOptions,
+        on_progress: impl Fn(Progress),
+    ) -> Result<ImportReport, VaultError> {
+        import::import_namespaces(
+            &self.platform,
+            vault_name,
+            vault_bytes,
+            options,
+            &on_progress,
+        )
+        .await
     }
 
     pub async fn cleanup_vault(&self, vault_name: &str) -> Result<(), VaultError> {
@@ -131,6 +1280,28 @@ impl VaultManager {
         Ok(())
     }
 
+    /// Removes the expired namespaces of every vault on `schedule`, from a
+    /// background thread, replacing the running scheduler. The thread ends
+    /// at the end of its current sleep once stopped.
+    pub fn start_cleanup_scheduler(&self, schedule: CleanupSchedule) {
+        let generation = cleanup_scheduler::begin_cleanup_scheduler();
+        std::thread::spawn(move || {
+            let platform = Platform::new();
+            futures::executor::block_on(cleanup_scheduler::run_cleanup_scheduler(
+                &platform, schedule, generation,
+            ));
+        });
+    }
+
+    /// Stops the cleanup scheduler. Returns `false` if none was running.
+    pub fn stop_cleanup_scheduler(&self) -> bool {
+        cleanup_scheduler::stop_cleanup_scheduler()
+    }
+
+    pub fn is_cleanup_scheduler_running(&self) -> bool {
+        cleanup_scheduler::is_cleanup_scheduler_running()
+    }
+
     pub async fn verify_identity(
         &self,
         vault_name: &str,
@@ -0,0 +1,226 @@
+//! Background Sync integration.
+//!
+//! Queued tasks live in each vault's outbox and run from
+//! `run_background_tasks`, which a service worker calls when the browser
+//! fires a `sync` or `periodicsync` event:
+//!
+//! ```js
+//! import init, { run_background_tasks } from "@gatewatcher/hoddor";
+//!
+//! const ready = init();
+//! const run = () => ready.then(() => run_background_tasks());
+//! self.addEventListener("sync", (event) => event.waitUntil(run()));
+//! self.addEventListener("periodicsync", (event) => event.waitUntil(run()));
+//! ```
+//!
+//! Browsers without Background Sync leave the tasks queued until the page
+//! calls `run_background_tasks` itself, typically on its next load.
+
+use crate::domain::vault::operations;
+use crate::domain::vault::outbox::{self, PendingTask};
+use crate::platform::Platform;
+use js_sys::{Object, Promise, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{RequestInit, Response, WorkerGlobalScope};
+
+const BACKGROUND_SYNC_TAG: &str = "hoddor";
+
+/// Queues removal of expired namespaces. Resolves to whether a background
+/// sync was registered to run it.
+#[wasm_bindgen]
+pub async fn queue_vault_cleanup(vault_name: &str) -> Result<bool, JsValue> {
+    queue(vault_name, PendingTask::Cleanup).await
+}
+
+/// Queues an upload of the exported vault, POSTed to `url` as
+/// `application/octet-stream`.
+#[wasm_bindgen]
+pub async fn queue_backup_upload(vault_name: &str, url: &str) -> Result<bool, JsValue> {
+    queue(
+        vault_name,
+        PendingTask::UploadBackup {
+            url: url.to_string(),
+        },
+    )
+    .await
+}
+
+/// Queues a push of the vault to its paired device. Service workers cannot
+/// hold the peer connection, so this one only runs from a page that is
+/// paired at the time.
+#[cfg(feature = "sync")]
+#[wasm_bindgen]
+pub async fn queue_sync_flush(vault_name: &str) -> Result<bool, JsValue> {
+    queue(vault_name, PendingTask::FlushSync).await
+}
+
+/// Asks the browser to fire a one-off `sync` event once it is online.
+/// Resolves to `false` where Background Sync is not supported.
+#[wasm_bindgen]
+pub async fn request_background_sync(tag: Option<String>) -> Result<bool, JsValue> {
+    let Some(sync) = registration_member("sync").await? else {
+        return Ok(false);
+    };
+
+    let tag = tag.as_deref().unwrap_or(BACKGROUND_SYNC_TAG);
+    call_promise(&sync, "register", &[JsValue::from_str(tag)]).await?;
+
+    Ok(true)
+}
+
+/// Asks the browser to fire `periodicsync` events at most every
+/// `min_interval_ms`. Resolves to `false` where Periodic Background Sync is
+/// not supported.
+#[wasm_bindgen]
+pub async fn register_periodic_background_sync(
+    tag: Option<String>,
+    min_interval_ms: f64,
+) -> Result<bool, JsValue> {
+    let Some(periodic_sync) = registration_member("periodicSync").await? else {
+        return Ok(false);
+    };
+
+    let options = Object::new();
+    Reflect::set(&options, &"minInterval".into(), &min_interval_ms.into())?;
+
+    let tag = tag.as_deref().unwrap_or(BACKGROUND_SYNC_TAG);
+    call_promise(
+        &periodic_sync,
+        "register",
+        &[JsValue::from_str(tag), options.into()],
+    )
+    .await?;
+
+    Ok(true)
+}
+
+/// Runs every queued task of every vault and resolves to the number that
+/// completed. Rejects when one failed, which makes the browser retry the
+/// sync event later; failed tasks stay queued.
+#[wasm_bindgen]
+pub async fn run_background_tasks() -> Result<u32, JsValue> {
+    let platform = Platform::new();
+
+    let mut completed = 0;
+    let mut failures = Vec::new();
+
+    for vault_name in operations::list_vaults(&platform).await? {
+        for task in outbox::pending(&platform, &vault_name).await {
+            match run_task(&platform, &vault_name, &task).await {
+                Ok(true) => {
+                    outbox::complete(&platform, &vault_name, &task).await?;
+                    completed += 1;
+                }
+                Ok(false) => {}
+                Err(e) => failures.push(format!(
+                    "{vault_name}: {}",
+                    e.as_string().unwrap_or_else(|| format!("{e:?}"))
+                )),
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        return Err(JsValue::from_str(&format!(
+            "Background tasks failed: {}",
+            failures.join("; ")
+        )));
+    }
+
+    Ok(completed)
+}
+
+async fn queue(vault_name: &str, task: PendingTask) -> Result<bool, JsValue> {
+    let platform = Platform::new();
+
+    outbox::enqueue(&platform, vault_name, task).await?;
+
+    request_background_sync(None).await
+}
+
+// Returns false when the task cannot run in this context yet and must stay
+// queued without counting as a failure.
+async fn run_task(
+    platform: &Platform,
+    vault_name: &str,
+    task: &PendingTask,
+) -> Result<bool, JsValue> {
+    match task {
+        PendingTask::Cleanup => {
+            operations::cleanup_vault(platform, vault_name).await?;
+        }
+        PendingTask::UploadBackup { url } => {
+            let bytes = operations::export_vault_bytes(platform, vault_name).await?;
+            post_bytes(url, &bytes).await?;
+        }
+        #[cfg(feature = "sync")]
+        PendingTask::FlushSync => {
+            if !super::sync::is_pairing_connected(vault_name) {
+                return Ok(false);
+            }
+            super::sync::push_vault_to_paired_device(vault_name, None).await?;
+        }
+        // Queued by a build with sync; this one can never run it.
+        #[cfg(not(feature = "sync"))]
+        PendingTask::FlushSync => {}
+    }
+
+    Ok(true)
+}
+
+async fn post_bytes(url: &str, bytes: &[u8]) -> Result<(), JsValue> {
+    let init = RequestInit::new();
+    init.set_method("POST");
+    init.set_body(&Uint8Array::from(bytes).into());
+
+    let global = js_sys::global();
+    let promise = if let Some(worker) = global.dyn_ref::<WorkerGlobalScope>() {
+        worker.fetch_with_str_and_init(url, &init)
+    } else {
+        crate::global::window()?.fetch_with_str_and_init(url, &init)
+    };
+
+    let response: Response = JsFuture::from(promise).await?.dyn_into()?;
+    if !response.ok() {
+        return Err(JsValue::from_str(&format!(
+            "Backup upload to {url} failed with status {}",
+            response.status()
+        )));
+    }
+
+    Ok(())
+}
+
+// `sync` or `periodicSync` of the service worker registration, from either
+// the page or the service worker itself.
+async fn registration_member(name: &str) -> Result<Option<JsValue>, JsValue> {
+    let global = js_sys::global();
+
+    let mut registration = Reflect::get(&global, &"registration".into())?;
+    if registration.is_undefined() {
+        let navigator = Reflect::get(&global, &"navigator".into())?;
+        let container = Reflect::get(&navigator, &"serviceWorker".into())?;
+        if container.is_undefined() {
+            return Ok(None);
+        }
+
+        let ready: Promise = Reflect::get(&container, &"ready".into())?.dyn_into()?;
+        registration = JsFuture::from(ready).await?;
+    }
+
+    let member = Reflect::get(&registration, &name.into())?;
+    Ok((!member.is_undefined()).then_some(member))
+}
+
+async fn call_promise(
+    target: &JsValue,
+    method: &str,
+    args: &[JsValue],
+) -> Result<JsValue, JsValue> {
+    let function: js_sys::Function = Reflect::get(target, &method.into())?.dyn_into()?;
+    let promise: Promise = Reflect::apply(&function, target, &args.iter().collect())?.dyn_into()?;
+
+    JsFuture::from(promise).await
+}
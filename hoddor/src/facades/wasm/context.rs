@@ -0,0 +1,94 @@
+use super::converters;
+use super::dashboard::dashboard_snapshot_in;
+use super::memory::memory_stats_in;
+use crate::context::Context;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "sync")]
+use super::sync::{connect_loopback_in, SyncSession};
+
+#[cfg(feature = "graph")]
+use super::crypto::IdentityHandle;
+#[cfg(feature = "graph")]
+use super::graph::GraphSession;
+
+/// Isolated set of sync managers, app message handlers, signaling clients and
+/// graph database. Sessions created from a context only see its state;
+/// `dispose` tears all of it down.
+#[wasm_bindgen]
+pub struct HoddorContext {
+    context: Rc<Context>,
+}
+
+#[wasm_bindgen]
+impl HoddorContext {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Result<HoddorContext, JsValue> {
+        Ok(HoddorContext {
+            context: Context::new()?,
+        })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> u32 {
+        self.context.id()
+    }
+
+    #[cfg(feature = "sync")]
+    pub fn sync_session(&self, vault_name: &str) -> Result<SyncSession, JsValue> {
+        SyncSession::in_context(self.context.clone(), vault_name)
+    }
+
+    /// Connects the `vault_name` sync managers of this context and `other`
+    /// in memory, without a signaling server. Each side lets the other
+    /// contribute to `namespaces`.
+    #[cfg(feature = "sync")]
+    pub async fn connect_loopback(
+        &self,
+        other: &HoddorContext,
+        vault_name: &str,
+        namespaces: Vec<String>,
+    ) -> Result<(), JsValue> {
+        connect_loopback_in(&self.context, &other.context, vault_name, &namespaces).await
+    }
+
+    #[cfg(feature = "graph")]
+    pub fn graph_session(
+        &self,
+        vault_name: &str,
+        identity: Option<IdentityHandle>,
+    ) -> GraphSession {
+        GraphSession::with_platform(self.context.platform(), vault_name, identity)
+    }
+
+    /// Same as `get_memory_stats` for the sync managers and graph database
+    /// of this context.
+    pub async fn memory_stats(&self) -> Result<JsValue, JsValue> {
+        let stats = memory_stats_in(&self.context).await?;
+        converters::to_js_value(&stats)
+    }
+
+    /// Same as `get_dashboard_snapshot` for the sync peers and graph
+    /// database of this context.
+    pub async fn dashboard_snapshot(&self) -> Result<JsValue, JsValue> {
+        let snapshot = dashboard_snapshot_in(&self.context).await?;
+        converters::to_js_value(&snapshot)
+    }
+
+    /// Closes every peer, signaling socket and pairing session of the
+    /// context. Sessions created from it stay usable but start from scratch.
+    pub fn dispose(&self) {
+        self.context.dispose();
+    }
+}
+
+// Sessions hold the context too; freeing the object only tears it down
+// once none of them is left.
+impl Drop for HoddorContext {
+    fn drop(&mut self) {
+        if Rc::strong_count(&self.context) == 1 {
+            self.context.dispose();
+        }
+    }
+}
@@ -1,5 +1,18 @@
+use crate::adapters::wasm::error_conversions::report_vault_error;
+use crate::adapters::ErrorReporter;
+use crate::domain::crypto::CryptoError;
+use crate::domain::error_messages;
+use crate::domain::error_report::ErrorReport;
+use crate::domain::progress::Progress;
+use crate::domain::retry::CancellationToken;
+use crate::domain::vault::error::VaultError;
+use crate::ports::ErrorReporterPort;
+use futures::channel::oneshot;
+use futures::future::{Either, FutureExt, Shared};
 use js_sys::Uint8Array;
 use serde_wasm_bindgen::{from_value, to_value};
+use std::any::Any;
+use std::future::Future;
 use wasm_bindgen::prelude::*;
 
 pub fn js_value_to_bytes(value: JsValue) -> Result<Vec<u8>, JsValue> {
@@ -25,8 +38,26 @@ pub fn bytes_to_js_value(bytes: &[u8]) -> Result<JsValue, JsValue> {
     }
 }
 
-pub fn to_js_error<E: std::fmt::Display>(error: E) -> JsValue {
-    JsValue::from_str(&error.to_string())
+/// Converts an error leaving a facade function, reporting it on the way.
+/// Vault and crypto errors are translated when an error locale is set.
+pub fn to_js_error<E: std::fmt::Display + 'static>(error: E) -> JsValue {
+    let error_any = &error as &dyn Any;
+    let code = if let Some(error) = error_any.downcast_ref::<VaultError>() {
+        report_vault_error(error);
+        Some(error.code())
+    } else if let Some(error) = error_any.downcast_ref::<CryptoError>() {
+        ErrorReporter::new().report(&ErrorReport::error(error.code(), &error.to_string()));
+        Some(error.code())
+    } else {
+        ErrorReporter::new().report(&ErrorReport::error("error", &error.to_string()));
+        None
+    };
+
+    let message = error.to_string();
+    JsValue::from_str(&match code {
+        Some(code) => error_messages::localize(code, message),
+        None => message,
+    })
 }
 
 pub fn to_js_value<T: serde::Serialize>(value: &T) -> Result<JsValue, JsValue> {
@@ -53,6 +84,101 @@ pub fn identity_keys_to_handle(
     Ok(super::crypto::IdentityHandle::from(identity))
 }
 
+/// Turns an optional JS callback into a progress sink. The callback receives
+/// `{ processed, total, unit }`; anything it throws is ignored so a faulty
+/// progress bar cannot fail the operation it tracks.
+pub fn progress_callback(callback: Option<js_sys::Function>) -> impl Fn(Progress) {
+    move |progress| {
+        if let (Some(callback), Ok(value)) = (&callback, to_js_value(&progress)) {
+            let _ = callback.call1(&JsValue::NULL, &value);
+        }
+    }
+}
+
+/// Mirrors a JS `AbortSignal` into a [`CancellationToken`] for as long as the
+/// binding is alive. Dropping it detaches the listener from the signal.
+pub struct AbortBinding {
+    token: CancellationToken,
+    signal: Option<web_sys::AbortSignal>,
+    listener: Option<Closure<dyn FnMut()>>,
+    aborted: Option<Shared<oneshot::Receiver<()>>>,
+}
+
+impl AbortBinding {
+    pub fn new(signal: Option<web_sys::AbortSignal>) -> Self {
+        let token = CancellationToken::new();
+
+        let Some(signal) = signal else {
+            return Self {
+                token,
+                signal: None,
+                listener: None,
+                aborted: None,
+            };
+        };
+
+        if signal.aborted() {
+            token.cancel();
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        let listener = {
+            let token = token.clone();
+            let mut sender = Some(sender);
+            Closure::wrap(Box::new(move || {
+                token.cancel();
+                if let Some(sender) = sender.take() {
+                    let _ = sender.send(());
+                }
+            }) as Box<dyn FnMut()>)
+        };
+        let _ = signal.add_event_listener_with_callback("abort", listener.as_ref().unchecked_ref());
+
+        Self {
+            token,
+            signal: Some(signal),
+            listener: Some(listener),
+            aborted: Some(receiver.shared()),
+        }
+    }
+
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Drives `operation` until it completes or the signal fires. On abort
+    /// the operation is dropped at its current await point, which releases
+    /// the locks it holds, and the signal's reason is returned.
+    pub async fn run<T, E, F>(&self, operation: F) -> Result<T, JsValue>
+    where
+        F: Future<Output = Result<T, E>>,
+        E: Into<JsValue>,
+    {
+        let (Some(signal), Some(aborted)) = (&self.signal, &self.aborted) else {
+            return operation.await.map_err(Into::into);
+        };
+
+        if self.token.is_cancelled() {
+            return Err(signal.reason());
+        }
+
+        futures::pin_mut!(operation);
+        match futures::future::select(operation, aborted.clone()).await {
+            Either::Left((result, _)) => result.map_err(Into::into),
+            Either::Right(_) => Err(signal.reason()),
+        }
+    }
+}
+
+impl Drop for AbortBinding {
+    fn drop(&mut self) {
+        if let (Some(signal), Some(listener)) = (&self.signal, &self.listener) {
+            let _ = signal
+                .remove_event_listener_with_callback("abort", listener.as_ref().unchecked_ref());
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
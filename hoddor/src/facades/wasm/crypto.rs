@@ -1,4 +1,5 @@
 use super::converters;
+use crate::adapters::wasm::worker_kdf;
 use crate::domain::crypto;
 use crate::platform::Platform;
 use age::{
@@ -95,11 +96,11 @@ impl IdentityHandle {
         self.identity.to_string().expose_secret().to_string()
     }
 
-    pub fn to_json(&self) -> JsValue {
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
         let obj = js_sys::Object::new();
-        js_sys::Reflect::set(&obj, &"public_key".into(), &self.public_key().into()).unwrap();
-        js_sys::Reflect::set(&obj, &"private_key".into(), &self.private_key().into()).unwrap();
-        obj.into()
+        js_sys::Reflect::set(&obj, &"public_key".into(), &self.public_key().into())?;
+        js_sys::Reflect::set(&obj, &"private_key".into(), &self.private_key().into())?;
+        Ok(obj.into())
     }
 
     pub fn from_json(json: &JsValue) -> Result<IdentityHandle, JsValue> {
@@ -121,3 +122,34 @@ impl From<Identity> for IdentityHandle {
         IdentityHandle { identity }
     }
 }
+
+/// Runs passphrase derivations (Argon2, hundreds of milliseconds) in a
+/// dedicated worker so unlocking does not freeze the page. `module_url` is
+/// the URL of the `hoddor.js` the page imported, e.g.
+/// `new URL("./pkg/hoddor.js", import.meta.url).href`, which the worker
+/// loads for itself. Derivations fall back to the calling thread when the
+/// worker cannot be spawned or fails to load the module.
+#[wasm_bindgen]
+pub fn enable_kdf_worker(module_url: String) {
+    worker_kdf::enable(module_url);
+}
+
+#[wasm_bindgen]
+pub fn disable_kdf_worker() {
+    worker_kdf::disable();
+}
+
+/// The Argon2 seed of `passphrase` and `salt`, computed on the calling
+/// thread. Called by the worker `enable_kdf_worker` spawns.
+#[wasm_bindgen]
+pub async fn derive_passphrase_seed(passphrase: &str, salt: &[u8]) -> Result<Vec<u8>, JsValue> {
+    let platform = Platform::new();
+
+    let seed = platform
+        .kdf()
+        .derive_from_passphrase(passphrase, salt)
+        .await
+        .map_err(|e| converters::to_js_error(e.to_string()))?;
+
+    Ok(seed.to_vec())
+}
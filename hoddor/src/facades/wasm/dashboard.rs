@@ -0,0 +1,87 @@
+use super::converters;
+use super::memory::memory_stats_in;
+use crate::context::{default_context, Context, PeerState};
+use crate::domain::recent_events::{self, RecordedEvent};
+use crate::domain::vault::{limits, operations};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// Events included in a snapshot, most recent first.
+const SNAPSHOT_EVENTS: usize = 20;
+
+/// Everything an admin view shows at once, gathered in a single call.
+#[derive(Debug, Default, Serialize)]
+pub struct DashboardSnapshot {
+    pub vaults: Vec<VaultSummary>,
+    /// Sync peers of the context; always empty without the `sync` feature.
+    pub peers: Vec<PeerState>,
+    pub recent_events: Vec<RecordedEvent>,
+    pub graph_nodes: usize,
+    pub graph_edges: usize,
+    pub pending_sync_operations: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct VaultSummary {
+    pub name: String,
+    pub namespaces: usize,
+    /// Encrypted bytes of the namespaces and chunks.
+    pub stored_bytes: u64,
+    pub sync_enabled: bool,
+    pub frozen: bool,
+    pub observer: bool,
+    /// Why the vault could not be read, in which case the other fields are
+    /// left empty.
+    pub error: Option<String>,
+}
+
+/// Vaults with their sizes, sync peers, the last events and graph counts of
+/// the default context, as `DashboardSnapshot` with snake_case fields.
+#[wasm_bindgen]
+pub async fn get_dashboard_snapshot() -> Result<JsValue, JsValue> {
+    let snapshot = dashboard_snapshot_in(&default_context()).await?;
+    converters::to_js_value(&snapshot)
+}
+
+pub(crate) async fn dashboard_snapshot_in(context: &Context) -> Result<DashboardSnapshot, JsValue> {
+    let platform = context.platform();
+    let memory = memory_stats_in(context).await?;
+
+    let mut vaults = Vec::new();
+    for name in operations::list_vaults(&platform)
+        .await
+        .map_err(converters::to_js_error)?
+    {
+        let summary = match operations::read_vault(&platform, &name).await {
+            Ok(vault) => VaultSummary {
+                namespaces: vault.namespaces.len(),
+                stored_bytes: limits::stored_bytes(&vault),
+                sync_enabled: vault.sync_enabled,
+                frozen: vault.frozen,
+                observer: vault.observer,
+                name,
+                error: None,
+            },
+            Err(e) => VaultSummary {
+                name,
+                error: Some(e.to_string()),
+                ..VaultSummary::default()
+            },
+        };
+        vaults.push(summary);
+    }
+
+    #[cfg(feature = "sync")]
+    let peers = context.peer_states();
+    #[cfg(not(feature = "sync"))]
+    let peers = Vec::new();
+
+    Ok(DashboardSnapshot {
+        vaults,
+        peers,
+        recent_events: recent_events::recent_events(SNAPSHOT_EVENTS),
+        graph_nodes: memory.graph_nodes,
+        graph_edges: memory.graph_edges,
+        pending_sync_operations: memory.pending_sync_operations,
+    })
+}
@@ -0,0 +1,22 @@
+use super::converters;
+use crate::domain::error_messages;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+/// Registers translations for `locale` as an object mapping error codes
+/// (`invalid_password`, `no_matching_key`, ...) to messages.
+#[wasm_bindgen]
+pub fn register_error_messages(locale: &str, messages: JsValue) -> Result<(), JsValue> {
+    let messages: HashMap<String, String> =
+        serde_wasm_bindgen::from_value(messages).map_err(converters::to_js_error)?;
+    error_messages::register_error_messages(locale, messages);
+    Ok(())
+}
+
+/// Translates the errors hoddor functions throw to `locale`, e.g.
+/// `navigator.language`; `undefined` goes back to English. Codes without a
+/// registered message keep their English text.
+#[wasm_bindgen]
+pub fn set_error_locale(locale: Option<String>) {
+    error_messages::set_error_locale(locale.as_deref());
+}
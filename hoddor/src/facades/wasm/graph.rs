@@ -1,9 +1,23 @@
 use super::converters;
-use crate::domain::graph::Id;
-use crate::platform::Platform;
+use super::crypto::IdentityHandle;
+use crate::domain::graph::{
+    find_duplicate_nodes, maintenance, recall, Id, MaintenancePolicy, NeighborFilter,
+    RecallWeights, SearchQuery, SearchResult, SearchResultStream,
+};
+use crate::domain::vault::CleanupSchedule;
+use crate::platform::{Platform, PlatformStorage};
+use futures::TryFutureExt;
 use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 
+thread_local! {
+    static SEARCH_STREAMS: RefCell<HashMap<u32, SearchResultStream<PlatformStorage>>> =
+        RefCell::new(HashMap::new());
+    static NEXT_STREAM_ID: Cell<u32> = const { Cell::new(1) };
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct GraphNodeResult {
     pub id: String,
@@ -23,6 +37,27 @@ pub struct GraphNodeWithNeighborsResult {
     pub neighbors: Vec<GraphNodeResult>,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct GraphNeighborResult {
+    pub id: String,
+    pub node_type: String,
+    pub content: String,
+    pub labels: Vec<String>,
+    pub edge_type: String,
+    pub weight: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GraphRecallResult {
+    pub id: String,
+    pub node_type: String,
+    pub content: String,
+    pub labels: Vec<String>,
+    pub similarity: f32,
+    pub score: f32,
+    pub access_count: u64,
+}
+
 #[wasm_bindgen]
 pub async fn graph_create_memory_node(
     vault_name: &str,
@@ -30,7 +65,16 @@ pub async fn graph_create_memory_node(
     embedding: Vec<f32>,
     labels: Vec<String>,
 ) -> Result<String, JsValue> {
-    let platform = Platform::new();
+    create_memory_node_in(&Platform::new(), vault_name, content, embedding, labels).await
+}
+
+async fn create_memory_node_in(
+    platform: &Platform,
+    vault_name: &str,
+    content: String,
+    embedding: Vec<f32>,
+    labels: Vec<String>,
+) -> Result<String, JsValue> {
     let vault_id = vault_name;
 
     let node_id = platform
@@ -49,7 +93,23 @@ pub async fn graph_vector_search(
     max_results: usize,
     search_quality: usize,
 ) -> Result<JsValue, JsValue> {
-    let platform = Platform::new();
+    vector_search_in(
+        &Platform::new(),
+        vault_name,
+        query_embedding,
+        max_results,
+        search_quality,
+    )
+    .await
+}
+
+async fn vector_search_in(
+    platform: &Platform,
+    vault_name: &str,
+    query_embedding: Vec<f32>,
+    max_results: usize,
+    search_quality: usize,
+) -> Result<JsValue, JsValue> {
     let vault_id = vault_name;
 
     let results = platform
@@ -83,7 +143,14 @@ pub async fn graph_list_memory_nodes(
     vault_name: &str,
     limit: Option<usize>,
 ) -> Result<JsValue, JsValue> {
-    let platform = Platform::new();
+    list_memory_nodes_in(&Platform::new(), vault_name, limit).await
+}
+
+async fn list_memory_nodes_in(
+    platform: &Platform,
+    vault_name: &str,
+    limit: Option<usize>,
+) -> Result<JsValue, JsValue> {
     let vault_id = vault_name;
 
     let nodes = platform
@@ -114,7 +181,25 @@ pub async fn graph_create_edge(
     edge_type: &str,
     weight: Option<f32>,
 ) -> Result<String, JsValue> {
-    let platform = Platform::new();
+    create_edge_in(
+        &Platform::new(),
+        vault_name,
+        from_node_id,
+        to_node_id,
+        edge_type,
+        weight,
+    )
+    .await
+}
+
+async fn create_edge_in(
+    platform: &Platform,
+    vault_name: &str,
+    from_node_id: &str,
+    to_node_id: &str,
+    edge_type: &str,
+    weight: Option<f32>,
+) -> Result<String, JsValue> {
     let vault_id = vault_name;
 
     let from_node = Id::from_string(from_node_id)
@@ -132,6 +217,124 @@ pub async fn graph_create_edge(
     Ok(edge_id.as_str().to_string())
 }
 
+/// Neighbors of a node, heaviest edge first. `filter` is
+/// `{ direction?: "outgoing" | "incoming" | "both", edgeTypes?, minWeight?, limit? }`
+/// and may be omitted to return every neighbor.
+#[wasm_bindgen]
+pub async fn graph_get_neighbors(
+    vault_name: &str,
+    node_id: &str,
+    filter: JsValue,
+) -> Result<JsValue, JsValue> {
+    let node_id = Id::from_string(node_id)
+        .map_err(|e| JsValue::from_str(&format!("Invalid node_id: {}", e)))?;
+    let filter: NeighborFilter = if filter.is_undefined() || filter.is_null() {
+        NeighborFilter::default()
+    } else {
+        serde_wasm_bindgen::from_value(filter).map_err(converters::to_js_error)?
+    };
+
+    let neighbors = Platform::new()
+        .graph()
+        .get_neighbors(vault_name, &node_id, &filter)
+        .await
+        .map_err(converters::to_js_error)?;
+
+    let js_results: Vec<GraphNeighborResult> = neighbors
+        .into_iter()
+        .map(|neighbor| GraphNeighborResult {
+            id: neighbor.node.id.as_str().to_string(),
+            node_type: neighbor.node.node_type,
+            content: neighbor.node.content,
+            labels: neighbor.node.labels,
+            edge_type: neighbor.edge_type,
+            weight: neighbor.weight,
+        })
+        .collect();
+
+    serde_wasm_bindgen::to_value(&js_results).map_err(converters::to_js_error)
+}
+
+/// Pairs of nodes of the vault whose embeddings are at least `threshold`
+/// similar (cosine, 0 to 1) and whose labels overlap, as
+/// `{ keep, duplicate, similarity }`, most similar first.
+#[wasm_bindgen]
+pub async fn graph_find_duplicate_nodes(
+    vault_name: &str,
+    threshold: f32,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+    let duplicates = find_duplicate_nodes(platform.graph(), vault_name, threshold)
+        .await
+        .map_err(converters::to_js_error)?;
+
+    serde_wasm_bindgen::to_value(&duplicates).map_err(converters::to_js_error)
+}
+
+/// Folds the node `remove_id` into `keep_id`, moving its edges and labels.
+#[wasm_bindgen]
+pub async fn graph_merge_nodes(
+    vault_name: &str,
+    keep_id: &str,
+    remove_id: &str,
+) -> Result<(), JsValue> {
+    let keep = Id::from_string(keep_id)
+        .map_err(|e| JsValue::from_str(&format!("Invalid keep_id: {}", e)))?;
+    let remove = Id::from_string(remove_id)
+        .map_err(|e| JsValue::from_str(&format!("Invalid remove_id: {}", e)))?;
+
+    Platform::new()
+        .graph()
+        .merge_nodes(vault_name, &keep, &remove)
+        .await
+        .map_err(converters::to_js_error)
+}
+
+/// Memories of the vault ranked by similarity, recency and use, best first.
+/// `weights` is `{ similarity?, recency?, frequency?, halfLifeSeconds? }`
+/// and may be omitted for the defaults. Each memory returned counts one
+/// more access.
+#[wasm_bindgen]
+pub async fn graph_recall(
+    vault_name: &str,
+    query_embedding: Vec<f32>,
+    max_results: usize,
+    weights: JsValue,
+) -> Result<JsValue, JsValue> {
+    let weights: RecallWeights = if weights.is_undefined() || weights.is_null() {
+        RecallWeights::default()
+    } else {
+        serde_wasm_bindgen::from_value(weights).map_err(converters::to_js_error)?
+    };
+    let query = SearchQuery {
+        query_embedding,
+        max_results,
+        search_quality: 100,
+        include_neighbors: false,
+    };
+
+    let platform = Platform::new();
+    let now = platform.clock().now() as u64;
+    let recalled = recall(platform.graph(), vault_name, &query, &weights, now)
+        .await
+        .map_err(converters::to_js_error)?;
+
+    let js_results: Vec<GraphRecallResult> = recalled
+        .into_iter()
+        .map(|recalled| GraphRecallResult {
+            id: recalled.result.node.id.as_str().to_string(),
+            node_type: recalled.result.node.node_type,
+            content: recalled.result.node.content,
+            labels: recalled.result.node.labels,
+            similarity: recalled.result.distance,
+            score: recalled.score,
+            access_count: recalled.result.node.access_count,
+        })
+        .collect();
+
+    serde_wasm_bindgen::to_value(&js_results).map_err(converters::to_js_error)
+}
+
 #[wasm_bindgen]
 pub async fn graph_vector_search_with_neighbors(
     vault_name: &str,
@@ -139,7 +342,23 @@ pub async fn graph_vector_search_with_neighbors(
     max_results: usize,
     search_quality: usize,
 ) -> Result<JsValue, JsValue> {
-    let platform = Platform::new();
+    vector_search_with_neighbors_in(
+        &Platform::new(),
+        vault_name,
+        query_embedding,
+        max_results,
+        search_quality,
+    )
+    .await
+}
+
+async fn vector_search_with_neighbors_in(
+    platform: &Platform,
+    vault_name: &str,
+    query_embedding: Vec<f32>,
+    max_results: usize,
+    search_quality: usize,
+) -> Result<JsValue, JsValue> {
     let vault_id = vault_name;
 
     let results = platform
@@ -148,40 +367,271 @@ pub async fn graph_vector_search_with_neighbors(
         .await
         .map_err(converters::to_js_error)?;
 
-    let js_results: Vec<GraphNodeWithNeighborsResult> = results
+    let js_results: Vec<GraphNodeWithNeighborsResult> =
+        results.into_iter().map(to_neighbors_result).collect();
+
+    serde_wasm_bindgen::to_value(&js_results).map_err(converters::to_js_error)
+}
+
+fn to_neighbors_result(search_result: SearchResult) -> GraphNodeWithNeighborsResult {
+    GraphNodeWithNeighborsResult {
+        id: search_result.node.id.as_str().to_string(),
+        node_type: search_result.node.node_type,
+        content: search_result.node.content,
+        labels: search_result.node.labels,
+        similarity: search_result.distance,
+        neighbors: search_result
+            .neighbors
+            .into_iter()
+            .map(|neighbor| GraphNodeResult {
+                id: neighbor.node.id.as_str().to_string(),
+                node_type: neighbor.node.node_type,
+                content: neighbor.node.content,
+                labels: neighbor.node.labels,
+                similarity: None,
+            })
+            .collect(),
+    }
+}
+
+#[wasm_bindgen]
+pub fn graph_configure_memory_budget(bytes: usize) {
+    crate::domain::graph::set_graph_memory_budget(bytes);
+}
+
+/// Number of searches whose results each graph database caches; zero turns
+/// the cache off.
+#[wasm_bindgen]
+pub fn graph_configure_query_cache(capacity: usize) {
+    crate::domain::graph::set_query_cache_capacity(capacity);
+}
+
+/// `{ hits, misses, invalidations }` of the graph query cache since the
+/// module loaded.
+#[wasm_bindgen]
+pub fn graph_query_cache_stats() -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&crate::domain::graph::query_cache_stats())
+        .map_err(converters::to_js_error)
+}
+
+/// Version of the embedding model in use. Nodes embedded with an older one
+/// are queued for re-embedding by graph maintenance.
+#[wasm_bindgen]
+pub fn graph_configure_embedding_version(version: u32) {
+    crate::domain::graph::set_embedding_version(version);
+}
+
+fn maintenance_policy(policy: JsValue) -> Result<MaintenancePolicy, JsValue> {
+    if policy.is_undefined() || policy.is_null() {
+        return Ok(MaintenancePolicy::default());
+    }
+    serde_wasm_bindgen::from_value(policy).map_err(converters::to_js_error)
+}
+
+/// Maintains the graph of every vault now and returns the report.
+/// `policy` is `{ retentionSeconds?, compact?, dryRun? }`: nodes neither
+/// created nor accessed within the retention window are pruned, nodes
+/// embedded with an older model are queued for re-embedding, and the store
+/// is compacted unless `compact` is `false`. A dry run changes nothing.
+#[wasm_bindgen]
+pub async fn graph_run_maintenance(policy: JsValue) -> Result<JsValue, JsValue> {
+    let policy = maintenance_policy(policy)?;
+    let report = maintenance::maintain_all_graphs(&Platform::new(), &policy)
+        .await
+        .map_err(converters::to_js_error)?;
+
+    serde_wasm_bindgen::to_value(&report).map_err(converters::to_js_error)
+}
+
+/// Runs graph maintenance with `policy` every `interval_seconds` plus a
+/// random delay of up to `jitter_seconds`, a tenth of the interval by
+/// default, replacing the running scheduler.
+#[wasm_bindgen]
+pub fn start_graph_maintenance_scheduler(
+    interval_seconds: u32,
+    policy: JsValue,
+    jitter_seconds: Option<u32>,
+) -> Result<(), JsValue> {
+    let policy = maintenance_policy(policy)?;
+    let mut schedule = CleanupSchedule::new(interval_seconds);
+    if let Some(jitter_seconds) = jitter_seconds {
+        schedule = schedule.with_jitter(jitter_seconds);
+    }
+
+    tracing::info!(interval_seconds, "Starting scheduled graph maintenance");
+    let generation = maintenance::begin_maintenance_scheduler();
+    wasm_bindgen_futures::spawn_local(async move {
+        let platform = Platform::new();
+        maintenance::run_maintenance_scheduler(&platform, schedule, policy, generation).await;
+    });
+
+    Ok(())
+}
+
+/// Stops the graph maintenance scheduler. Returns `false` if none was
+/// running.
+#[wasm_bindgen]
+pub fn stop_graph_maintenance_scheduler() -> bool {
+    tracing::info!("Stopping scheduled graph maintenance");
+    maintenance::stop_maintenance_scheduler()
+}
+
+#[wasm_bindgen]
+pub fn is_graph_maintenance_scheduler_running() -> bool {
+    maintenance::is_maintenance_scheduler_running()
+}
+
+/// Takes up to `max` ids of nodes of the vault queued for re-embedding.
+/// Each new embedding goes back through `graph_set_embedding`.
+#[wasm_bindgen]
+pub fn graph_take_reembed_queue(vault_name: &str, max: usize) -> Vec<String> {
+    crate::domain::graph::take_reembed_queue(vault_name, max)
         .into_iter()
-        .map(|search_result| GraphNodeWithNeighborsResult {
-            id: search_result.node.id.as_str().to_string(),
-            node_type: search_result.node.node_type,
-            content: search_result.node.content,
-            labels: search_result.node.labels,
-            similarity: search_result.distance,
-            neighbors: search_result
-                .neighbors
-                .into_iter()
-                .map(|neighbor| GraphNodeResult {
-                    id: neighbor.node.id.as_str().to_string(),
-                    node_type: neighbor.node.node_type,
-                    content: neighbor.node.content,
-                    labels: neighbor.node.labels,
-                    similarity: None,
-                })
-                .collect(),
-        })
-        .collect();
+        .map(|id| id.as_str().to_string())
+        .collect()
+}
+
+/// Replaces the embedding of a node, computed with the current embedding
+/// version.
+#[wasm_bindgen]
+pub async fn graph_set_embedding(
+    vault_name: &str,
+    node_id: &str,
+    embedding: Vec<f32>,
+) -> Result<(), JsValue> {
+    let node_id = Id::from_string(node_id)
+        .map_err(|e| JsValue::from_str(&format!("Invalid node_id: {}", e)))?;
+
+    Platform::new()
+        .graph()
+        .set_embedding(
+            vault_name,
+            &node_id,
+            embedding,
+            crate::domain::graph::embedding_version(),
+        )
+        .await
+        .map_err(converters::to_js_error)
+}
+
+/// Bytes of search results held in memory by open streams.
+pub(crate) fn search_streams_resident_bytes() -> usize {
+    SEARCH_STREAMS.with(|streams| {
+        streams
+            .borrow()
+            .values()
+            .map(SearchResultStream::resident_bytes)
+            .sum()
+    })
+}
+
+/// Runs a vector search and returns a stream handle whose pages are read
+/// with `graph_search_next_page`. Results beyond the memory budget are
+/// spilled to OPFS until requested.
+#[wasm_bindgen]
+pub async fn graph_search_open(
+    vault_name: &str,
+    query_embedding: Vec<f32>,
+    max_results: usize,
+    search_quality: usize,
+    include_neighbors: bool,
+    page_size: usize,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<u32, JsValue> {
+    search_open_in(
+        &Platform::new(),
+        vault_name,
+        SearchQuery {
+            query_embedding,
+            max_results,
+            search_quality,
+            include_neighbors,
+        },
+        page_size,
+        signal,
+    )
+    .await
+}
+
+async fn search_open_in(
+    platform: &Platform,
+    vault_name: &str,
+    query: SearchQuery,
+    page_size: usize,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<u32, JsValue> {
+    let abort = converters::AbortBinding::new(signal);
+
+    let stream = SearchResultStream::open(
+        &platform.graph_owned(),
+        platform.storage_owned(),
+        vault_name,
+        query,
+        page_size,
+        abort.token(),
+    )
+    .await
+    .map_err(converters::to_js_error)?;
+
+    let handle = NEXT_STREAM_ID.with(|next| {
+        let handle = next.get();
+        next.set(handle.wrapping_add(1));
+        handle
+    });
+    SEARCH_STREAMS.with(|streams| streams.borrow_mut().insert(handle, stream));
+
+    Ok(handle)
+}
+
+/// Resolves to the next page of results, or `null` once the stream is
+/// exhausted (the handle is released at that point).
+#[wasm_bindgen]
+pub async fn graph_search_next_page(handle: u32) -> Result<JsValue, JsValue> {
+    let mut stream = SEARCH_STREAMS
+        .with(|streams| streams.borrow_mut().remove(&handle))
+        .ok_or_else(|| JsValue::from_str(&format!("Unknown search stream {handle}")))?;
+
+    let page = stream.next_page().await.map_err(converters::to_js_error)?;
+
+    let Some(page) = page else {
+        return Ok(JsValue::NULL);
+    };
+
+    SEARCH_STREAMS.with(|streams| streams.borrow_mut().insert(handle, stream));
+
+    let js_results: Vec<GraphNodeWithNeighborsResult> =
+        page.into_iter().map(to_neighbors_result).collect();
 
     serde_wasm_bindgen::to_value(&js_results).map_err(converters::to_js_error)
 }
 
+#[wasm_bindgen]
+pub async fn graph_search_close(handle: u32) {
+    let stream = SEARCH_STREAMS.with(|streams| streams.borrow_mut().remove(&handle));
+
+    if let Some(mut stream) = stream {
+        stream.close().await;
+    }
+}
+
 #[wasm_bindgen]
 pub async fn graph_backup_vault(
     vault_name: &str,
     recipient: &str,
     identity: &str,
+    signal: Option<web_sys::AbortSignal>,
 ) -> Result<(), JsValue> {
-    use crate::domain::graph::{EncryptionConfig, GraphPersistenceService};
+    backup_vault_in(&Platform::new(), vault_name, recipient, identity, signal).await
+}
 
-    let platform = Platform::new();
+async fn backup_vault_in(
+    platform: &Platform,
+    vault_name: &str,
+    recipient: &str,
+    identity: &str,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<(), JsValue> {
+    use crate::domain::graph::{EncryptionConfig, GraphPersistenceService};
 
     let encryption = EncryptionConfig {
         platform: platform.clone(),
@@ -196,21 +646,37 @@ pub async fn graph_backup_vault(
         encryption,
     );
 
-    service
-        .backup(vault_name)
+    converters::AbortBinding::new(signal)
+        .run(service.backup(vault_name).map_err(converters::to_js_error))
         .await
-        .map_err(converters::to_js_error)
 }
 
+/// `on_progress` is called with the number of nodes and edges imported so far.
 #[wasm_bindgen]
 pub async fn graph_restore_vault(
     vault_name: &str,
     recipient: &str,
     identity: &str,
+    on_progress: Option<js_sys::Function>,
 ) -> Result<bool, JsValue> {
-    use crate::domain::graph::{EncryptionConfig, GraphPersistenceService};
+    restore_vault_in(
+        &Platform::new(),
+        vault_name,
+        recipient,
+        identity,
+        on_progress,
+    )
+    .await
+}
 
-    let platform = Platform::new();
+async fn restore_vault_in(
+    platform: &Platform,
+    vault_name: &str,
+    recipient: &str,
+    identity: &str,
+    on_progress: Option<js_sys::Function>,
+) -> Result<bool, JsValue> {
+    use crate::domain::graph::{EncryptionConfig, GraphPersistenceService};
 
     let encryption = EncryptionConfig {
         platform: platform.clone(),
@@ -230,9 +696,219 @@ pub async fn graph_restore_vault(
     }
 
     service
-        .restore(vault_name)
+        .restore_with_progress(vault_name, &converters::progress_callback(on_progress))
         .await
         .map_err(converters::to_js_error)?;
 
     Ok(true)
 }
+
+/// Escape hatch for a shared graph database left unusable, e.g. by a panic
+/// in the middle of a query: drops every graph it holds and starts over with
+/// an empty one. Graphs can then be brought back with `graph_restore_vault`.
+#[wasm_bindgen]
+pub async fn reset_graph_state() -> Result<(), JsValue> {
+    reset_graph_state_in(&Platform::new()).await
+}
+
+async fn reset_graph_state_in(platform: &Platform) -> Result<(), JsValue> {
+    platform
+        .graph()
+        .reset()
+        .await
+        .map_err(converters::to_js_error)
+}
+
+/// Graph of one vault. Search streams opened through the session are closed
+/// along with it, and backups use the identity it was created with.
+#[wasm_bindgen]
+pub struct GraphSession {
+    platform: Platform,
+    vault_name: String,
+    identity: Option<IdentityHandle>,
+    streams: RefCell<Vec<u32>>,
+}
+
+#[wasm_bindgen]
+impl GraphSession {
+    /// Session on the shared graph database used by the flat graph
+    /// functions.
+    #[wasm_bindgen(constructor)]
+    pub fn new(vault_name: &str, identity: Option<IdentityHandle>) -> GraphSession {
+        Self::with_platform(Platform::new(), vault_name, identity)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn vault_name(&self) -> String {
+        self.vault_name.clone()
+    }
+
+    pub async fn create_memory_node(
+        &self,
+        content: String,
+        embedding: Vec<f32>,
+        labels: Vec<String>,
+    ) -> Result<String, JsValue> {
+        create_memory_node_in(&self.platform, &self.vault_name, content, embedding, labels).await
+    }
+
+    pub async fn create_edge(
+        &self,
+        from_node_id: &str,
+        to_node_id: &str,
+        edge_type: &str,
+        weight: Option<f32>,
+    ) -> Result<String, JsValue> {
+        create_edge_in(
+            &self.platform,
+            &self.vault_name,
+            from_node_id,
+            to_node_id,
+            edge_type,
+            weight,
+        )
+        .await
+    }
+
+    pub async fn list_memory_nodes(&self, limit: Option<usize>) -> Result<JsValue, JsValue> {
+        list_memory_nodes_in(&self.platform, &self.vault_name, limit).await
+    }
+
+    pub async fn vector_search(
+        &self,
+        query_embedding: Vec<f32>,
+        max_results: usize,
+        search_quality: usize,
+        include_neighbors: bool,
+    ) -> Result<JsValue, JsValue> {
+        if include_neighbors {
+            vector_search_with_neighbors_in(
+                &self.platform,
+                &self.vault_name,
+                query_embedding,
+                max_results,
+                search_quality,
+            )
+            .await
+        } else {
+            vector_search_in(
+                &self.platform,
+                &self.vault_name,
+                query_embedding,
+                max_results,
+                search_quality,
+            )
+            .await
+        }
+    }
+
+    /// Same as `graph_search_open`; the stream is closed with the session if
+    /// it was not exhausted before.
+    pub async fn search_open(
+        &self,
+        query_embedding: Vec<f32>,
+        max_results: usize,
+        search_quality: usize,
+        include_neighbors: bool,
+        page_size: usize,
+        signal: Option<web_sys::AbortSignal>,
+    ) -> Result<u32, JsValue> {
+        let handle = search_open_in(
+            &self.platform,
+            &self.vault_name,
+            SearchQuery {
+                query_embedding,
+                max_results,
+                search_quality,
+                include_neighbors,
+            },
+            page_size,
+            signal,
+        )
+        .await?;
+
+        self.streams.borrow_mut().push(handle);
+
+        Ok(handle)
+    }
+
+    pub async fn search_next_page(&self, handle: u32) -> Result<JsValue, JsValue> {
+        graph_search_next_page(handle).await
+    }
+
+    pub async fn backup(&self, signal: Option<web_sys::AbortSignal>) -> Result<(), JsValue> {
+        let identity = self.identity()?;
+
+        backup_vault_in(
+            &self.platform,
+            &self.vault_name,
+            &identity.public_key(),
+            &identity.private_key(),
+            signal,
+        )
+        .await
+    }
+
+    pub async fn restore(&self, on_progress: Option<js_sys::Function>) -> Result<bool, JsValue> {
+        let identity = self.identity()?;
+
+        restore_vault_in(
+            &self.platform,
+            &self.vault_name,
+            &identity.public_key(),
+            &identity.private_key(),
+            on_progress,
+        )
+        .await
+    }
+
+    /// Same as `reset_graph_state` for the database behind the session,
+    /// which for sessions of a `HoddorContext` is the context's own. Other
+    /// vaults' graphs in that database are dropped too.
+    pub async fn reset_state(&self) -> Result<(), JsValue> {
+        reset_graph_state_in(&self.platform).await
+    }
+
+    pub async fn close(&self) {
+        let streams = self.streams.take();
+
+        for handle in streams {
+            graph_search_close(handle).await;
+        }
+    }
+}
+
+impl GraphSession {
+    pub(crate) fn with_platform(
+        platform: Platform,
+        vault_name: &str,
+        identity: Option<IdentityHandle>,
+    ) -> Self {
+        GraphSession {
+            platform,
+            vault_name: vault_name.to_string(),
+            identity,
+            streams: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn identity(&self) -> Result<&IdentityHandle, JsValue> {
+        self.identity
+            .as_ref()
+            .ok_or_else(|| JsValue::from_str("Graph session has no identity for backups"))
+    }
+}
+
+impl Drop for GraphSession {
+    fn drop(&mut self) {
+        let streams = self.streams.take();
+
+        if !streams.is_empty() {
+            wasm_bindgen_futures::spawn_local(async move {
+                for handle in streams {
+                    graph_search_close(handle).await;
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! The two ends of a graph engine loaded as a separate wasm package.
+//!
+//! Applications build their main package with `graph-remote` and register
+//! [`set_graph_engine`]; the engine package, built with `graph-cozo`, answers
+//! through [`graph_rpc`]. See `domain::graph::rpc` for the wire format.
+
+use wasm_bindgen::prelude::*;
+
+#[cfg(all(feature = "graph-remote", not(feature = "graph-cozo")))]
+use js_sys::Function;
+
+#[cfg(any(feature = "graph-cozo", not(feature = "graph-remote")))]
+use crate::adapters::Graph;
+#[cfg(any(feature = "graph-cozo", not(feature = "graph-remote")))]
+use crate::domain::graph::rpc::{self, GraphEnvelope, GraphRequest, GraphResponse};
+#[cfg(any(feature = "graph-cozo", not(feature = "graph-remote")))]
+use std::cell::RefCell;
+#[cfg(any(feature = "graph-cozo", not(feature = "graph-remote")))]
+use std::collections::HashMap;
+
+#[cfg(any(feature = "graph-cozo", not(feature = "graph-remote")))]
+thread_local! {
+    static DATABASES: RefCell<HashMap<u32, Graph>> = RefCell::new(HashMap::new());
+}
+
+/// Sets the function graph calls are forwarded to. It receives each request
+/// as a JSON string and must resolve to the engine's JSON reply, e.g.
+///
+/// ```js
+/// set_graph_engine(async (request) => {
+///   const engine = await import("./pkg-graph/hoddor.js");
+///   await engine.default();
+///   return engine.graph_rpc(request);
+/// });
+/// ```
+///
+/// Passing `null` unregisters it; graph calls then fail.
+#[cfg(all(feature = "graph-remote", not(feature = "graph-cozo")))]
+#[wasm_bindgen]
+pub fn set_graph_engine(handler: Option<Function>) {
+    crate::adapters::wasm::remote_graph::set_engine(handler);
+}
+
+/// Runs one request sent by a `graph-remote` build and returns the reply,
+/// both as JSON strings. Malformed requests get an error reply rather than
+/// a rejection, so the caller always has a reply to decode.
+#[cfg(any(feature = "graph-cozo", not(feature = "graph-remote")))]
+#[wasm_bindgen]
+pub async fn graph_rpc(request: String) -> Result<String, JsValue> {
+    let response = match serde_json::from_str::<GraphEnvelope>(&request) {
+        Ok(GraphEnvelope {
+            database,
+            request: GraphRequest::Release,
+        }) => {
+            DATABASES.with(|databases| databases.borrow_mut().remove(&database));
+            GraphResponse::from_result(Ok(()))
+        }
+        Ok(GraphEnvelope { database, request }) => match database_graph(database) {
+            Ok(graph) => rpc::dispatch(&graph, request).await,
+            Err(e) => GraphResponse::from_result::<()>(Err(e)),
+        },
+        Err(e) => GraphResponse::from_result::<()>(Err(
+            crate::domain::graph::GraphError::SerializationError(e.to_string()),
+        )),
+    };
+
+    serde_json::to_string(&response).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[cfg(any(feature = "graph-cozo", not(feature = "graph-remote")))]
+fn database_graph(database: u32) -> crate::domain::graph::GraphResult<Graph> {
+    if database == rpc::SHARED_DATABASE {
+        return Ok(Graph::default());
+    }
+
+    if let Some(graph) = DATABASES.with(|databases| databases.borrow().get(&database).cloned()) {
+        return Ok(graph);
+    }
+
+    let graph = Graph::isolated()?;
+    DATABASES.with(|databases| databases.borrow_mut().insert(database, graph.clone()));
+    Ok(graph)
+}
@@ -0,0 +1,75 @@
+use super::converters;
+use crate::context::{default_context, Context};
+use crate::domain::authentication::identity_cache_usage;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// Memory held by hoddor, for embedders deciding when to call
+/// `clear_identity_cache`, close search streams or lock vaults.
+#[derive(Debug, Default, Serialize)]
+pub struct MemoryStats {
+    /// Size of the wasm linear memory. It only ever grows, so this is the
+    /// high-water mark rather than what is in use.
+    pub linear_memory_bytes: u64,
+    /// Key material of cached identities plus search results kept in
+    /// memory by open search streams.
+    pub cached_plaintext_bytes: u64,
+    pub cached_identities: usize,
+    pub graph_nodes: usize,
+    pub graph_edges: usize,
+    /// Sync operations queued and not yet sent to peers.
+    pub pending_sync_operations: usize,
+    pub pending_sync_bytes: u64,
+}
+
+/// Reports memory usage for the default context and the shared graph
+/// database, as `MemoryStats` with snake_case fields.
+#[wasm_bindgen]
+pub async fn get_memory_stats() -> Result<JsValue, JsValue> {
+    let stats = memory_stats_in(&default_context()).await?;
+    converters::to_js_value(&stats)
+}
+
+#[cfg_attr(not(any(feature = "sync", feature = "graph")), allow(unused_variables))]
+pub(crate) async fn memory_stats_in(context: &Context) -> Result<MemoryStats, JsValue> {
+    let (cached_identities, identity_bytes) = identity_cache_usage();
+    #[cfg(feature = "sync")]
+    let (pending_sync_operations, pending_sync_bytes) = context.pending_sync();
+    #[cfg(not(feature = "sync"))]
+    let (pending_sync_operations, pending_sync_bytes) = (0, 0);
+
+    #[allow(unused_mut)]
+    let mut stats = MemoryStats {
+        linear_memory_bytes: linear_memory_bytes(),
+        cached_plaintext_bytes: identity_bytes as u64,
+        cached_identities,
+        pending_sync_operations,
+        pending_sync_bytes: pending_sync_bytes as u64,
+        ..MemoryStats::default()
+    };
+
+    #[cfg(feature = "graph")]
+    {
+        let graph = context
+            .platform()
+            .graph()
+            .stats()
+            .await
+            .map_err(converters::to_js_error)?;
+
+        stats.graph_nodes = graph.nodes;
+        stats.graph_edges = graph.edges;
+        stats.cached_plaintext_bytes += super::graph::search_streams_resident_bytes() as u64;
+    }
+
+    Ok(stats)
+}
+
+fn linear_memory_bytes() -> u64 {
+    wasm_bindgen::memory()
+        .dyn_into::<js_sys::WebAssembly::Memory>()
+        .ok()
+        .and_then(|memory| memory.buffer().dyn_into::<js_sys::ArrayBuffer>().ok())
+        .map(|buffer| buffer.byte_length() as u64)
+        .unwrap_or(0)
+}
@@ -1,7 +1,20 @@
+pub mod background;
+pub mod context;
 pub mod converters;
 pub mod crypto;
+pub mod dashboard;
+pub mod error_messages;
+pub mod memory;
+pub mod telemetry;
 pub mod vault;
-pub mod webauthn;
 
 #[cfg(feature = "graph")]
 pub mod graph;
+#[cfg(feature = "graph")]
+pub mod graph_engine;
+#[cfg(feature = "sync")]
+pub mod sync;
+#[cfg(feature = "test-mode")]
+pub mod test_mode;
+#[cfg(feature = "webauthn")]
+pub mod webauthn;
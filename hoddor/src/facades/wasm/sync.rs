@@ -0,0 +1,680 @@
+use super::converters;
+use crate::context::{default_context, Context};
+use crate::domain::retry;
+use crate::domain::trace_context::TraceContext;
+use crate::domain::vault::sync_policy::{self, SyncPolicy};
+use crate::domain::vault::sync_profile::{self, SyncProfile};
+use crate::domain::vault::sync_recording::{self, SyncDirection};
+use crate::domain::vault::{device_link, operations, residency};
+use crate::platform::Platform;
+use crate::sync::{OperationType, PeerHello};
+use crate::webrtc::{AccessLevel, PeerConfig, WebRtcPeer};
+use futures::TryFutureExt;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+/// Sends an ephemeral message to every connected peer allowed to read
+/// `namespace`. Returns the number of peers the message was sent to.
+#[wasm_bindgen]
+pub fn send_app_message(vault_name: &str, namespace: &str, data: JsValue) -> Result<u32, JsValue> {
+    send_app_message_in(&default_context(), vault_name, namespace, data)
+}
+
+/// Registers `callback` for app messages received on `vault_name`, replacing
+/// any previous one. Passing `undefined` removes the handler.
+#[wasm_bindgen]
+pub fn on_app_message(vault_name: &str, callback: Option<js_sys::Function>) {
+    default_context().set_app_message_handler(vault_name, callback);
+}
+
+/// A new peer id for the signaling server: random, or `peer-1`, `peer-2`,
+/// ... in test mode.
+#[wasm_bindgen]
+pub fn new_peer_id() -> String {
+    crate::domain::test_mode::new_peer_id()
+}
+
+/// Starts pairing `vault_name` with a nearby device. The returned code must be
+/// handed to the other device, which answers with `accept_pairing_offer`.
+///
+/// Gathering connection candidates can take a while; aborting `signal` closes
+/// the half-open session.
+#[wasm_bindgen]
+pub async fn create_pairing_offer(
+    vault_name: &str,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<String, JsValue> {
+    create_pairing_offer_in(&default_context(), vault_name, signal).await
+}
+
+#[wasm_bindgen]
+pub async fn accept_pairing_offer(
+    vault_name: &str,
+    offer: &str,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<String, JsValue> {
+    accept_pairing_offer_in(&default_context(), vault_name, offer, signal).await
+}
+
+#[wasm_bindgen]
+pub async fn complete_pairing(
+    vault_name: &str,
+    answer: &str,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<(), JsValue> {
+    complete_pairing_in(&default_context(), vault_name, answer, signal).await
+}
+
+#[wasm_bindgen]
+pub fn is_pairing_connected(vault_name: &str) -> bool {
+    is_pairing_connected_in(&default_context(), vault_name)
+}
+
+/// Sends every namespace of `vault_name` to the paired device. Returns the
+/// number of namespaces sent.
+///
+/// Waits for the pairing connection to come up and retries failed sends with
+/// the configured retry policy; aborting `signal` stops waiting.
+#[wasm_bindgen]
+pub async fn push_vault_to_paired_device(
+    vault_name: &str,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<u32, JsValue> {
+    push_vault_in(&default_context(), vault_name, signal).await
+}
+
+/// Residency attributes this device asserts to paired devices, e.g. `["eu"]`.
+/// Namespaces tagged with `set_namespace_residency` are only pushed to
+/// devices asserting all of their tags.
+#[wasm_bindgen]
+pub fn set_residency_attributes(vault_name: &str, attributes: Vec<String>) {
+    set_residency_attributes_in(&default_context(), vault_name, attributes);
+}
+
+#[wasm_bindgen]
+pub fn close_pairing(vault_name: &str) {
+    close_pairing_in(&default_context(), vault_name);
+}
+
+/// Namespaces of `vault_name` taking part in sync, as
+/// `{ include, exclude }` lists of names or glob patterns.
+#[wasm_bindgen]
+pub async fn get_sync_policy(vault_name: &str) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let policy = sync_policy::sync_policy(&platform, vault_name).await?;
+
+    converters::to_js_value(&policy)
+}
+
+/// Restricts sync of `vault_name` to the namespaces `include` matches, all
+/// of them when it is empty, minus those `exclude` matches. Paired devices
+/// are neither sent nor allowed to update the others.
+#[wasm_bindgen]
+pub async fn set_sync_policy(vault_name: &str, policy: JsValue) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    let policy: SyncPolicy = serde_wasm_bindgen::from_value(policy)?;
+    sync_policy::set_sync_policy(&platform, vault_name, policy).await?;
+
+    Ok(())
+}
+
+/// Sync settings of `vault_name`: signaling URL, ICE servers and trusted
+/// peers with their namespace permissions.
+#[wasm_bindgen]
+pub async fn get_sync_profile(vault_name: &str) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let profile = sync_profile::load_sync_profile(&platform, vault_name).await?;
+
+    converters::to_js_value(&profile)
+}
+
+#[wasm_bindgen]
+pub async fn set_sync_profile(vault_name: &str, profile: JsValue) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    let profile: SyncProfile = serde_wasm_bindgen::from_value(profile)?;
+    sync_profile::save_sync_profile(&platform, vault_name, &profile).await?;
+
+    Ok(())
+}
+
+/// Bundles the sync profile of `vault_name` into a blob encrypted to
+/// `passphrase`, to be loaded on another device with `import_sync_profile`.
+#[wasm_bindgen]
+pub async fn export_sync_profile(vault_name: &str, passphrase: &str) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let blob = sync_profile::export_sync_profile(&platform, vault_name, passphrase).await?;
+
+    let array = js_sys::Uint8Array::new_with_length(blob.len() as u32);
+    array.copy_from(&blob);
+    Ok(array.into())
+}
+
+/// Replaces the sync profile of `vault_name` with the one in `data` and
+/// returns it.
+#[wasm_bindgen]
+pub async fn import_sync_profile(
+    vault_name: &str,
+    passphrase: &str,
+    data: JsValue,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let blob = converters::js_value_to_bytes(data)?;
+    let profile =
+        sync_profile::import_sync_profile(&platform, vault_name, passphrase, &blob).await?;
+
+    converters::to_js_value(&profile)
+}
+
+/// Bundles what a new device needs to join `vault_name` (identity salts,
+/// sync settings and trusted peers, but no namespaces) into a blob
+/// encrypted to a one-time code. Resolves to `{ code, blob }`: show the
+/// code on this device and hand the blob to `import_device_link` on the new
+/// one. The link expires after `valid_for_seconds`, ten minutes by default.
+#[wasm_bindgen]
+pub async fn export_device_link(
+    vault_name: &str,
+    valid_for_seconds: Option<u32>,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let link = device_link::export_device_link(
+        &platform,
+        vault_name,
+        valid_for_seconds.map_or(device_link::DEVICE_LINK_TTL_SECONDS, i64::from),
+    )
+    .await?;
+
+    let result = js_sys::Object::new();
+    js_sys::Reflect::set(&result, &"code".into(), &link.code.into())?;
+    js_sys::Reflect::set(
+        &result,
+        &"blob".into(),
+        &js_sys::Uint8Array::from(link.blob.as_slice()),
+    )?;
+    Ok(result.into())
+}
+
+/// Creates the vault of a link made by `export_device_link`, ready to
+/// unlock and sync, and resolves to its name. The code is not case
+/// sensitive and its dash is optional.
+#[wasm_bindgen]
+pub async fn import_device_link(code: &str, data: JsValue) -> Result<String, JsValue> {
+    let platform = Platform::new();
+
+    let blob = converters::js_value_to_bytes(data)?;
+
+    Ok(device_link::import_device_link(&platform, code, &blob).await?)
+}
+
+/// Records every sync message `vault_name` sends or receives from now on,
+/// encrypted to `recipient`, e.g. the public key of whoever debugs a sync
+/// issue. `export_sync_recording` returns the records to hand over.
+#[wasm_bindgen]
+pub async fn start_sync_recording(vault_name: &str, recipient: &str) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    sync_recording::start_sync_recording(&platform, vault_name, recipient).await?;
+
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub async fn stop_sync_recording(vault_name: &str) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    sync_recording::stop_sync_recording(&platform, vault_name).await?;
+
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub async fn clear_sync_recording(vault_name: &str) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    sync_recording::clear_sync_recording(&platform, vault_name).await?;
+
+    Ok(())
+}
+
+/// The encrypted sync recording of `vault_name`, one record per line, as
+/// `hoddor-cli replay` reads it.
+#[wasm_bindgen]
+pub async fn export_sync_recording(vault_name: &str) -> Result<String, JsValue> {
+    let platform = Platform::new();
+
+    Ok(sync_recording::export_sync_recording(&platform, vault_name).await?)
+}
+
+/// Pairing session of one vault with a nearby device. The peer connection and
+/// the app message handler live as long as the object: `close`, or freeing
+/// it, tears both down.
+#[wasm_bindgen]
+pub struct SyncSession {
+    context: Rc<Context>,
+    vault_name: String,
+}
+
+#[wasm_bindgen]
+impl SyncSession {
+    /// Session in the default context, shared with the flat sync functions.
+    #[wasm_bindgen(constructor)]
+    pub fn new(vault_name: &str) -> Result<SyncSession, JsValue> {
+        Self::in_context(default_context(), vault_name)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn vault_name(&self) -> String {
+        self.vault_name.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn is_connected(&self) -> bool {
+        is_pairing_connected_in(&self.context, &self.vault_name)
+    }
+
+    pub async fn create_offer(
+        &self,
+        signal: Option<web_sys::AbortSignal>,
+    ) -> Result<String, JsValue> {
+        create_pairing_offer_in(&self.context, &self.vault_name, signal).await
+    }
+
+    pub async fn accept_offer(
+        &self,
+        offer: &str,
+        signal: Option<web_sys::AbortSignal>,
+    ) -> Result<String, JsValue> {
+        accept_pairing_offer_in(&self.context, &self.vault_name, offer, signal).await
+    }
+
+    pub async fn complete(
+        &self,
+        answer: &str,
+        signal: Option<web_sys::AbortSignal>,
+    ) -> Result<(), JsValue> {
+        complete_pairing_in(&self.context, &self.vault_name, answer, signal).await
+    }
+
+    pub async fn push(&self, signal: Option<web_sys::AbortSignal>) -> Result<u32, JsValue> {
+        push_vault_in(&self.context, &self.vault_name, signal).await
+    }
+
+    pub fn set_residency_attributes(&self, attributes: Vec<String>) {
+        set_residency_attributes_in(&self.context, &self.vault_name, attributes);
+    }
+
+    pub fn send_app_message(&self, namespace: &str, data: JsValue) -> Result<u32, JsValue> {
+        send_app_message_in(&self.context, &self.vault_name, namespace, data)
+    }
+
+    pub fn on_app_message(&self, callback: Option<js_sys::Function>) {
+        self.context
+            .set_app_message_handler(&self.vault_name, callback);
+    }
+
+    pub fn close(&self) {
+        self.context.set_app_message_handler(&self.vault_name, None);
+        close_pairing_in(&self.context, &self.vault_name);
+    }
+}
+
+impl SyncSession {
+    pub(crate) fn in_context(context: Rc<Context>, vault_name: &str) -> Result<Self, JsValue> {
+        crate::domain::vault::validation::validate_vault_name(vault_name)?;
+
+        Ok(SyncSession {
+            context,
+            vault_name: vault_name.to_string(),
+        })
+    }
+}
+
+impl Drop for SyncSession {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+fn send_app_message_in(
+    context: &Context,
+    vault_name: &str,
+    namespace: &str,
+    data: JsValue,
+) -> Result<u32, JsValue> {
+    let payload = converters::js_value_to_bytes(data)?;
+
+    context
+        .sync_manager(vault_name)
+        .borrow()
+        .send_app_message(vault_name, namespace, payload)
+}
+
+async fn create_pairing_offer_in(
+    context: &Context,
+    vault_name: &str,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<String, JsValue> {
+    let platform = context.platform();
+    let session = context.pairing_session(vault_name);
+    let abort = converters::AbortBinding::new(signal);
+
+    abortable_pairing(
+        &platform,
+        &session,
+        &abort,
+        platform.transport().create_offer(&session),
+    )
+    .await
+}
+
+async fn accept_pairing_offer_in(
+    context: &Rc<Context>,
+    vault_name: &str,
+    offer: &str,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<String, JsValue> {
+    let platform = context.platform();
+    let session = context.pairing_session(vault_name);
+    let abort = converters::AbortBinding::new(signal);
+
+    let answer = abortable_pairing(
+        &platform,
+        &session,
+        &abort,
+        platform.transport().accept_offer(&session, offer),
+    )
+    .await?;
+
+    spawn_pairing_receiver(context.clone(), session, vault_name.to_string());
+
+    Ok(answer)
+}
+
+async fn complete_pairing_in(
+    context: &Rc<Context>,
+    vault_name: &str,
+    answer: &str,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<(), JsValue> {
+    let platform = context.platform();
+    let session = context.pairing_session(vault_name);
+    let abort = converters::AbortBinding::new(signal);
+
+    abortable_pairing(
+        &platform,
+        &session,
+        &abort,
+        platform.transport().accept_answer(&session, answer),
+    )
+    .await?;
+
+    spawn_pairing_receiver(context.clone(), session, vault_name.to_string());
+
+    Ok(())
+}
+
+/// Connects the `vault_name` sync managers of two contexts of this page
+/// through a pair of peers that exchange their descriptions in memory, so
+/// app messages and sync flow between them without a signaling server.
+/// Each side lets the other contribute to `namespaces`.
+pub(crate) async fn connect_loopback_in(
+    context: &Rc<Context>,
+    other: &Rc<Context>,
+    vault_name: &str,
+    namespaces: &[String],
+) -> Result<(), JsValue> {
+    crate::domain::vault::validation::validate_vault_name(vault_name)?;
+
+    let (mut offerer, _) = WebRtcPeer::create_peer_in(
+        context.clone(),
+        crate::domain::test_mode::new_peer_id(),
+        PeerConfig::default(),
+    )
+    .await?;
+    let (mut answerer, _) = WebRtcPeer::create_peer_in(
+        other.clone(),
+        crate::domain::test_mode::new_peer_id(),
+        PeerConfig::default(),
+    )
+    .await?;
+
+    offerer.connect_loopback(&mut answerer).await?;
+    for namespace in namespaces {
+        offerer.add_permission(namespace.clone(), AccessLevel::Contributor);
+        answerer.add_permission(namespace.clone(), AccessLevel::Contributor);
+    }
+
+    context
+        .sync_manager(vault_name)
+        .borrow_mut()
+        .add_peer(Rc::new(RefCell::new(offerer)));
+    other
+        .sync_manager(vault_name)
+        .borrow_mut()
+        .add_peer(Rc::new(RefCell::new(answerer)));
+
+    Ok(())
+}
+
+fn is_pairing_connected_in(context: &Context, vault_name: &str) -> bool {
+    let session = context.pairing_session(vault_name);
+    context.platform().transport().is_connected(&session)
+}
+
+fn set_residency_attributes_in(context: &Context, vault_name: &str, attributes: Vec<String>) {
+    context
+        .sync_manager(vault_name)
+        .borrow_mut()
+        .residency_attributes = residency::normalize_tags(attributes);
+}
+
+fn close_pairing_in(context: &Context, vault_name: &str) {
+    let session = context.pairing_session(vault_name);
+    context.platform().transport().close(&session);
+    context
+        .sync_manager(vault_name)
+        .borrow_mut()
+        .paired_attributes = None;
+}
+
+#[tracing::instrument(skip_all, fields(vault = vault_name, trace_id = tracing::field::Empty))]
+async fn push_vault_in(
+    context: &Context,
+    vault_name: &str,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<u32, JsValue> {
+    let platform = context.platform();
+    let session = context.pairing_session(vault_name);
+    let abort = converters::AbortBinding::new(signal);
+    let policy = retry::default_retry_policy();
+    let trace = TraceContext::new_root();
+    tracing::Span::current().record("trace_id", trace.trace_id());
+
+    abort
+        .run(policy.run(&platform, abort.token(), |_| async {
+            if platform.transport().is_connected(&session) {
+                Ok(())
+            } else {
+                Err(JsValue::from_str("Paired device is not connected"))
+            }
+        }))
+        .await?;
+
+    let peer_attributes =
+        paired_attributes(context, &platform, &session, vault_name, &abort).await?;
+
+    let vault = operations::read_vault(&platform, vault_name)
+        .await
+        .map_err(converters::to_js_error)?;
+
+    let messages = {
+        let manager = context.sync_manager(vault_name);
+        let mut manager = manager.borrow_mut();
+
+        let mut messages = Vec::with_capacity(vault.namespaces.len());
+        for (namespace, data) in &vault.namespaces {
+            if !manager.shares(&vault.metadata, namespace) {
+                tracing::debug!(
+                    namespace = %namespace,
+                    "Withholding namespace left out by the sync policy"
+                );
+                continue;
+            }
+            if !residency::may_replicate(&vault, namespace, &peer_attributes) {
+                tracing::debug!(
+                    namespace = %namespace,
+                    "Withholding namespace whose residency tags the peer does not assert"
+                );
+                continue;
+            }
+            if !data.chunks.is_empty() {
+                tracing::debug!(
+                    namespace = %namespace,
+                    "Skipping chunked namespace, sync operations carry inline payloads only"
+                );
+                continue;
+            }
+
+            let mut operation = manager.create_operation(
+                namespace.clone(),
+                OperationType::Insert,
+                Some(data.data.clone()),
+                None,
+                data.expiration.clone(),
+            );
+            operation.timelock = data.timelock.clone();
+            operation.recipients = data.recipients.clone();
+            operation.crdt = data.crdt.as_ref().map(|crdt| crdt.kind);
+            operation.integrity = data.integrity.clone();
+            operation.crypto_version = data.crypto_version;
+            operation.compression = data.compression;
+            operation.residency = vault.residency.get(namespace).cloned().unwrap_or_default();
+            let mut message = manager.create_sync_message(
+                vault_name.to_string(),
+                operation,
+                Some(vault.metadata.clone()),
+                Some(vault.identity_salts.clone()),
+                Some(vault.username_pk.clone()),
+            );
+            message.traceparent = Some(trace.child().to_traceparent());
+            messages.push(serde_json::to_vec(&message).map_err(converters::to_js_error)?);
+        }
+        messages
+    };
+
+    for message in &messages {
+        abort
+            .run(policy.run(&platform, abort.token(), |_| async {
+                platform
+                    .transport()
+                    .send(&session, message)
+                    .await
+                    .map_err(converters::to_js_error)
+            }))
+            .await?;
+        sync_recording::record_sync_message(&platform, vault_name, SyncDirection::Sent, message)
+            .await;
+    }
+
+    Ok(messages.len() as u32)
+}
+
+// Sends our hello unless the peer's already arrived, then waits for it with
+// the retry policy. A peer that never answers predates the handshake and is
+// treated as asserting no attribute.
+async fn paired_attributes(
+    context: &Context,
+    platform: &Platform,
+    session: &str,
+    vault_name: &str,
+    abort: &converters::AbortBinding,
+) -> Result<BTreeSet<String>, JsValue> {
+    let manager = context.sync_manager(vault_name);
+
+    if manager.borrow().paired_attributes.is_none() {
+        let hello = manager.borrow().hello(false);
+        send_hello(platform, session, hello).await?;
+    }
+
+    let policy = retry::default_retry_policy();
+    let answered = abort
+        .run(policy.run(platform, abort.token(), |_| async {
+            manager
+                .borrow()
+                .paired_attributes
+                .clone()
+                .ok_or_else(|| JsValue::from_str("Paired device has not sent its hello"))
+        }))
+        .await;
+
+    match answered {
+        Ok(attributes) => Ok(attributes),
+        Err(e) if abort.token().is_cancelled() => Err(e),
+        Err(_) => {
+            tracing::warn!("Paired device sent no hello; tagged namespaces are withheld");
+            Ok(BTreeSet::new())
+        }
+    }
+}
+
+async fn send_hello(platform: &Platform, session: &str, hello: PeerHello) -> Result<(), JsValue> {
+    let bytes = serde_json::to_vec(&hello).map_err(converters::to_js_error)?;
+
+    platform
+        .transport()
+        .send(session, &bytes)
+        .await
+        .map_err(converters::to_js_error)
+}
+
+// A pairing step dropped half-way leaves a peer connection behind that no
+// later step can complete, so it is closed on abort.
+async fn abortable_pairing<T>(
+    platform: &Platform,
+    session: &str,
+    abort: &converters::AbortBinding,
+    step: impl std::future::Future<Output = Result<T, Box<dyn std::error::Error>>>,
+) -> Result<T, JsValue> {
+    let result = abort.run(step.map_err(converters::to_js_error)).await;
+
+    if abort.token().is_cancelled() {
+        platform.transport().close(session);
+    }
+
+    result
+}
+
+fn spawn_pairing_receiver(context: Rc<Context>, session: String, vault_name: String) {
+    wasm_bindgen_futures::spawn_local(async move {
+        let platform = Platform::new();
+        let manager = context.sync_manager(&vault_name);
+        manager.borrow_mut().paired_attributes = None;
+
+        while let Ok(Some(data)) = platform.transport().receive(&session).await {
+            if let Ok(hello) = serde_json::from_slice::<PeerHello>(&data) {
+                manager.borrow_mut().paired_attributes = Some(hello.residency_attributes);
+                if !hello.reply {
+                    let reply = manager.borrow().hello(true);
+                    if let Err(e) = send_hello(&platform, &session, reply).await {
+                        tracing::error!("Failed to answer paired hello: {:?}", e);
+                    }
+                }
+                continue;
+            }
+
+            if let Err(e) =
+                crate::webrtc::update_vault_from_sync(&context, &vault_name, &data).await
+            {
+                tracing::error!(vault = %vault_name, error = %e, "Failed to apply paired update");
+            }
+        }
+    });
+}
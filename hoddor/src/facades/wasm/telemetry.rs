@@ -0,0 +1,88 @@
+use super::converters;
+use crate::adapters::wasm::{set_console_level, ErrorReporter};
+use crate::domain::io_stats;
+use crate::domain::usage_stats::{self, UsageStatsOptions};
+use tracing::level_filters::LevelFilter;
+use wasm_bindgen::prelude::*;
+
+/// Sets how verbose the console output is: `off`, `error`, `warn`, `info`
+/// (the default), `debug` or `trace`.
+#[wasm_bindgen]
+pub fn set_log_level(level: &str) -> Result<(), JsValue> {
+    let level: LevelFilter = level
+        .parse()
+        .map_err(|_| converters::to_js_error(format!("Unknown log level: {level}")))?;
+
+    if !set_console_level(level) {
+        return Err(converters::to_js_error(
+            "Console logging was replaced by another tracing subscriber",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Registers `callback` to receive a report for every error returned by a
+/// hoddor function and for panics, replacing any previous one; `undefined`
+/// turns reporting off. Reports are `{ kind, code, message, location }`
+/// objects whose message has been scrubbed of names, paths and keys, so they
+/// can be forwarded to a crash tracker as they are.
+#[wasm_bindgen]
+pub fn set_error_reporter(callback: Option<js_sys::Function>) {
+    ErrorReporter::set_callback(callback);
+}
+
+/// Sets the duration above which a storage operation is logged as slow and
+/// the size above which a file is logged as oversized.
+#[wasm_bindgen]
+pub fn configure_io_warnings(slow_millis: u32, oversized_file_bytes: u32) {
+    io_stats::configure_io_warnings(slow_millis.into(), oversized_file_bytes.into());
+}
+
+/// Storage stats of `vault_name`, or of every vault keyed by name when it is
+/// omitted, since the module loaded or `reset_io_stats` was last called.
+#[wasm_bindgen]
+pub fn get_io_stats(vault_name: Option<String>) -> Result<JsValue, JsValue> {
+    match vault_name {
+        Some(vault_name) => converters::to_js_value(&io_stats::io_stats(&vault_name)),
+        None => converters::to_js_value(&io_stats::all_io_stats()),
+    }
+}
+
+#[wasm_bindgen]
+pub fn reset_io_stats() {
+    io_stats::reset_io_stats();
+}
+
+/// Starts counting namespace reads, writes and removals, with payload sizes
+/// and latencies bucketed, for `get_aggregated_stats`. `options` is
+/// `{ epsilonPerRelease, budget }`; calling it again starts the counters and
+/// the budget over.
+#[wasm_bindgen]
+pub fn enable_usage_stats(options: JsValue) -> Result<(), JsValue> {
+    let options: UsageStatsOptions = if options.is_undefined() || options.is_null() {
+        UsageStatsOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)?
+    };
+
+    usage_stats::enable_usage_stats(options);
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn disable_usage_stats() {
+    usage_stats::disable_usage_stats();
+}
+
+/// Usage counters with noise added, as `{ operations, payload_sizes,
+/// latencies, remaining_budget }`. Each call spends part of the privacy
+/// budget and fails once it is spent.
+#[wasm_bindgen]
+pub fn get_aggregated_stats() -> Result<JsValue, JsValue> {
+    let stats = usage_stats::aggregated_stats().ok_or_else(|| {
+        converters::to_js_error("Usage stats are off or their privacy budget is spent")
+    })?;
+
+    converters::to_js_value(&stats)
+}
@@ -0,0 +1,51 @@
+//! Hooks for end-to-end tests of apps embedding hoddor, built with the
+//! `test-mode` feature only. See [`crate::domain::test_mode`].
+
+use crate::adapters::shared::LoopbackTransport;
+use crate::domain::test_mode::{self, TestModeOptions};
+use wasm_bindgen::prelude::*;
+
+/// Turns test mode on: the clock stands still at `options.startMillis`
+/// until moved with `advance_test_clock` or `set_test_clock`, pairing codes
+/// connect vaults of the same page in memory, and generated ids follow
+/// `options.seed`. Calling it again restarts from the options and closes
+/// every loopback pairing.
+#[wasm_bindgen]
+pub fn enable_test_mode(options: JsValue) -> Result<(), JsValue> {
+    let options: TestModeOptions = if options.is_undefined() || options.is_null() {
+        TestModeOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)?
+    };
+
+    LoopbackTransport::reset();
+    test_mode::enable_test_mode(options);
+
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn disable_test_mode() {
+    test_mode::disable_test_mode();
+    LoopbackTransport::reset();
+}
+
+#[wasm_bindgen]
+pub fn is_test_mode() -> bool {
+    test_mode::is_test_mode()
+}
+
+#[wasm_bindgen]
+pub fn advance_test_clock(milliseconds: f64) {
+    test_mode::advance_test_clock(milliseconds.max(0.0) as u64);
+}
+
+#[wasm_bindgen]
+pub fn set_test_clock(milliseconds_since_epoch: f64) {
+    test_mode::set_test_clock(milliseconds_since_epoch.max(0.0) as u64);
+}
+
+#[wasm_bindgen]
+pub fn test_clock_now() -> f64 {
+    test_mode::test_clock_millis() as f64
+}
@@ -1,40 +1,125 @@
 use super::converters;
-use super::crypto::IdentityHandle;
-use crate::domain::vault::{operations, validation};
+use super::crypto::{IdentityHandle, RecipientHandle};
+use crate::domain::retry::{self, RetryPolicy};
+use crate::domain::vault::error::VaultError;
+use crate::domain::vault::{
+    access, aggregate, chunks, cleanup_scheduler, compression, crdt, crypto_audit,
+    deserialize_vault, diff, encrypted_export, expiration, history, import, incremental, integrity,
+    limits, log, merge, migration, operations, patch, query, residency, retention, rotation,
+    secret_scan, stream, timelock, validation, wal, watch, CleanupSchedule, CrdtKind,
+    CrdtOperation, ExistingNamespaces, ExportKey, ExportSecret, ImportOptions, KeyShare,
+    MergeStrategy, PrunePolicy, SumQuery, UpsertOptions, VaultHandle,
+};
 use crate::platform::Platform;
-use std::sync::atomic::{AtomicI64, Ordering};
+use futures::TryFutureExt;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
 use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
 
-static CLEANUP_INTERVAL: AtomicI64 = AtomicI64::new(0);
-static LAST_CLEANUP: AtomicI64 = AtomicI64::new(0);
+thread_local! {
+    static OPEN_VAULTS: RefCell<HashMap<u32, Rc<VaultHandle>>> = RefCell::new(HashMap::new());
+    static NEXT_VAULT_HANDLE: Cell<u32> = const { Cell::new(1) };
+    static WATCH_CALLBACKS: RefCell<HashMap<u32, js_sys::Function>> = RefCell::new(HashMap::new());
+    static EXPIRATION_CALLBACKS: RefCell<HashMap<u32, js_sys::Function>> = RefCell::new(HashMap::new());
+}
 
 #[wasm_bindgen]
 pub async fn vault_identity_from_passphrase(
     passphrase: &str,
     vault_name: &str,
+    signal: Option<web_sys::AbortSignal>,
 ) -> Result<IdentityHandle, JsValue> {
     let platform = Platform::new();
 
     validation::validate_passphrase(passphrase).map_err(converters::to_js_error)?;
     validation::validate_vault_name(vault_name)?;
 
-    let mut vault = operations::read_vault(&platform, vault_name)
+    let abort = converters::AbortBinding::new(signal);
+
+    // Key derivation cannot be interrupted once started; an abort signalled
+    // meanwhile takes effect at the next await point.
+    let identity_keys = abortable_write(&platform, vault_name, &abort, async {
+        let mut vault = operations::read_vault(&platform, vault_name)
+            .await
+            .map_err(|e| {
+                converters::to_js_error(format!("Vault '{}' does not exist: {}", vault_name, e))
+            })?;
+
+        let identity_keys = crate::domain::authentication::derive_vault_identity(
+            &platform, passphrase, vault_name, &mut vault,
+        )
         .await
-        .map_err(|e| {
-            converters::to_js_error(format!("Vault '{}' does not exist: {}", vault_name, e))
-        })?;
+        .map_err(converters::to_js_error)?;
 
-    let identity_keys = crate::domain::authentication::derive_vault_identity(
-        &platform, passphrase, vault_name, &mut vault,
-    )
-    .await
-    .map_err(converters::to_js_error)?;
+        operations::save_vault(&platform, vault_name, vault).await?;
+
+        Ok::<_, JsValue>(identity_keys)
+    })
+    .await?;
+
+    converters::identity_keys_to_handle(identity_keys)
+}
+
+/// Replaces `old_identity` with one derived from `new_passphrase`: what it
+/// can read is re-encrypted to the new identity and the old passphrase no
+/// longer unlocks the vault. Copies made earlier stay readable with it.
+#[wasm_bindgen]
+pub async fn rotate_vault_identity(
+    vault_name: &str,
+    old_identity: &IdentityHandle,
+    new_passphrase: &str,
+) -> Result<IdentityHandle, JsValue> {
+    let platform = Platform::new();
+
+    validation::validate_passphrase(new_passphrase).map_err(converters::to_js_error)?;
+    validation::validate_vault_name(vault_name)?;
 
-    operations::save_vault(&platform, vault_name, vault).await?;
+    let identity_keys = rotation::rotate_vault_identity(
+        &platform,
+        vault_name,
+        &old_identity.private_key(),
+        new_passphrase,
+    )
+    .await?;
 
     converters::identity_keys_to_handle(identity_keys)
 }
 
+// Aborting drops a write at its current await point. Replaying the journal
+// afterwards leaves the vault with the write either fully applied or not at
+// all, and the dropped lock guards have already been released.
+async fn abortable_write<T, E, F>(
+    platform: &Platform,
+    vault_name: &str,
+    abort: &converters::AbortBinding,
+    operation: F,
+) -> Result<T, JsValue>
+where
+    F: std::future::Future<Output = Result<T, E>>,
+    E: Into<JsValue>,
+{
+    let result = abort.run(operation).await;
+
+    if abort.token().is_cancelled() {
+        if let Err(e) = wal::recover_vault(platform, vault_name).await {
+            tracing::warn!(vault = vault_name, error = %e, "Failed to settle aborted write");
+        }
+    }
+
+    result
+}
+
+/// Forgets the identities derived by `vault_identity_from_passphrase` during
+/// this session, so the next unlock pays for Argon2 again. They are
+/// forgotten on their own 15 minutes after being derived.
+#[wasm_bindgen]
+pub fn clear_identity_cache() {
+    crate::domain::authentication::clear_identity_cache();
+}
+
 #[wasm_bindgen]
 pub async fn upsert_vault(
     vault_name: &str,
@@ -43,24 +128,153 @@ pub async fn upsert_vault(
     data: JsValue,
     expires_in_seconds: Option<i64>,
     replace_if_exists: bool,
+    signal: Option<web_sys::AbortSignal>,
 ) -> Result<(), JsValue> {
     let platform = Platform::new();
 
     validation::validate_namespace(namespace).map_err(converters::to_js_error)?;
 
     let data_bytes = converters::js_value_to_bytes(data)?;
+    let public_key = identity.public_key();
+    let abort = converters::AbortBinding::new(signal);
 
-    operations::upsert_namespace(
+    abortable_write(
         &platform,
         vault_name,
-        &identity.public_key(),
-        namespace,
-        data_bytes,
-        expires_in_seconds,
-        replace_if_exists,
+        &abort,
+        retry::default_retry_policy()
+            .run_if(&platform, abort.token(), VaultError::is_transient, |_| {
+                operations::upsert_namespace(
+                    &platform,
+                    vault_name,
+                    &public_key,
+                    namespace,
+                    data_bytes.clone(),
+                    expires_in_seconds,
+                    replace_if_exists,
+                )
+            })
+            .map_err(VaultError::from),
+    )
+    .await
+}
+
+/// Like `upsert_vault`, with `options` given as
+/// `{ expiresInSeconds, replaceIfExists, compression }`. `compression` is
+/// `none` or `deflate`, and defaults to the compression of the vault.
+#[wasm_bindgen]
+pub async fn upsert_vault_with_options(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    namespace: &str,
+    data: JsValue,
+    options: JsValue,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    validation::validate_namespace(namespace).map_err(converters::to_js_error)?;
+
+    let options: UpsertOptions = if options.is_undefined() || options.is_null() {
+        UpsertOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)?
+    };
+    let data_bytes = converters::js_value_to_bytes(data)?;
+    let public_key = identity.public_key();
+    let abort = converters::AbortBinding::new(signal);
+
+    abortable_write(
+        &platform,
+        vault_name,
+        &abort,
+        retry::default_retry_policy()
+            .run_if(&platform, abort.token(), VaultError::is_transient, |_| {
+                operations::upsert_namespace_with(
+                    &platform,
+                    vault_name,
+                    &public_key,
+                    namespace,
+                    data_bytes.clone(),
+                    options,
+                )
+            })
+            .map_err(VaultError::from),
+    )
+    .await
+}
+
+/// Stores `data` in the vault's content-addressed chunk store, keeping chunks
+/// shared with other namespaces or earlier versions only once.
+#[wasm_bindgen]
+pub async fn upsert_vault_deduplicated(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    namespace: &str,
+    data: JsValue,
+    expires_in_seconds: Option<i64>,
+    replace_if_exists: bool,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    validation::validate_namespace(namespace).map_err(converters::to_js_error)?;
+
+    let data_bytes = converters::js_value_to_bytes(data)?;
+    let private_key = identity.private_key();
+    let abort = converters::AbortBinding::new(signal);
+
+    abortable_write(
+        &platform,
+        vault_name,
+        &abort,
+        retry::default_retry_policy()
+            .run_if(&platform, abort.token(), VaultError::is_transient, |_| {
+                operations::upsert_namespace_deduplicated(
+                    &platform,
+                    vault_name,
+                    &private_key,
+                    namespace,
+                    data_bytes.clone(),
+                    expires_in_seconds,
+                    replace_if_exists,
+                )
+            })
+            .map_err(VaultError::from),
     )
     .await
-    .map_err(|e| e.into())
+}
+
+/// Sets how vault writes and sync sends are retried when storage or the peer
+/// connection is momentarily unavailable. Pending operations can be cancelled
+/// through the `AbortSignal` they accept.
+#[wasm_bindgen]
+pub fn configure_retry_policy(
+    max_attempts: u32,
+    initial_delay_ms: u32,
+    max_delay_ms: u32,
+    jitter_ms: u32,
+) {
+    retry::set_default_retry_policy(
+        RetryPolicy::default()
+            .with_max_attempts(max_attempts)
+            .with_backoff(
+                initial_delay_ms,
+                max_delay_ms,
+                RetryPolicy::default().multiplier,
+            )
+            .with_jitter(jitter_ms),
+    );
+}
+
+#[wasm_bindgen]
+pub async fn collect_vault_garbage(vault_name: &str) -> Result<u32, JsValue> {
+    let platform = Platform::new();
+
+    operations::collect_vault_garbage(&platform, vault_name)
+        .await
+        .map(|removed| removed as u32)
+        .map_err(converters::to_js_error)
 }
 
 #[wasm_bindgen]
@@ -68,6 +282,71 @@ pub async fn read_from_vault(
     vault_name: &str,
     identity: &IdentityHandle,
     namespace: JsValue,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let namespace_str = converters::js_value_to_string(namespace)?;
+
+    validation::validate_namespace(&namespace_str).map_err(converters::to_js_error)?;
+
+    let abort = converters::AbortBinding::new(signal);
+    let data_bytes = abort
+        .run(
+            operations::read_namespace(
+                &platform,
+                vault_name,
+                &identity.private_key(),
+                &namespace_str,
+            )
+            .map_err(converters::to_js_error),
+        )
+        .await?;
+
+    converters::bytes_to_js_value(&data_bytes)
+}
+
+/// Applies the RFC 6902 JSON Patch `patch`, an array of operations, to the
+/// JSON document stored in `namespace`. Either every operation applies or
+/// the namespace is left as it was. Peers receive the whole namespace, and
+/// CRDT namespaces cannot be patched.
+#[wasm_bindgen]
+pub async fn patch_namespace(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    namespace: JsValue,
+    patch: JsValue,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    let namespace_str = converters::js_value_to_string(namespace)?;
+
+    validation::validate_namespace(&namespace_str).map_err(converters::to_js_error)?;
+
+    let patch: serde_json::Value = serde_wasm_bindgen::from_value(patch)?;
+
+    patch::patch_namespace(
+        &platform,
+        vault_name,
+        &identity.private_key(),
+        &namespace_str,
+        &patch,
+    )
+    .await
+    .map_err(converters::to_js_error)
+}
+
+/// Applies `operation` to the CRDT value stored in `namespace`, creating it
+/// as a `kind` (`"g-counter"`, `"pn-counter"`, `"lww-map"` or `"or-set"`)
+/// if needed, and resolves to the new value. Copies received from peers
+/// are merged in.
+#[wasm_bindgen]
+pub async fn update_crdt(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    namespace: JsValue,
+    kind: &str,
+    operation: JsValue,
 ) -> Result<JsValue, JsValue> {
     let platform = Platform::new();
 
@@ -75,146 +354,1860 @@ pub async fn read_from_vault(
 
     validation::validate_namespace(&namespace_str).map_err(converters::to_js_error)?;
 
-    let data_bytes = operations::read_namespace(
+    let kind: CrdtKind = kind.parse().map_err(converters::to_js_error)?;
+    let operation: CrdtOperation = serde_wasm_bindgen::from_value(operation)?;
+
+    let value = crdt::update_crdt(
         &platform,
         vault_name,
         &identity.private_key(),
         &namespace_str,
+        kind,
+        &operation,
     )
     .await
     .map_err(converters::to_js_error)?;
 
-    converters::bytes_to_js_value(&data_bytes)
+    converters::to_js_value(&value)
 }
 
+/// Resolves to the value of the CRDT stored in `namespace`, merged with the
+/// copies received from peers: a number for counters, an object for maps
+/// and an array for sets.
 #[wasm_bindgen]
-pub async fn remove_from_vault(
+pub async fn read_crdt(
     vault_name: &str,
     identity: &IdentityHandle,
     namespace: JsValue,
-) -> Result<(), JsValue> {
+) -> Result<JsValue, JsValue> {
     let platform = Platform::new();
 
     let namespace_str = converters::js_value_to_string(namespace)?;
+
     validation::validate_namespace(&namespace_str).map_err(converters::to_js_error)?;
 
-    operations::verify_vault_identity(&platform, vault_name, &identity.private_key()).await?;
+    let value = crdt::read_crdt(
+        &platform,
+        vault_name,
+        &identity.private_key(),
+        &namespace_str,
+    )
+    .await
+    .map_err(converters::to_js_error)?;
 
-    operations::remove_namespace(&platform, vault_name, &namespace_str)
-        .await
-        .map_err(|e| e.into())
+    converters::to_js_value(&value)
 }
 
+/// Resolves to the array of values the JSONPath `path` selects in the JSON
+/// document stored in `namespace`. Only the matches cross into JavaScript.
 #[wasm_bindgen]
-pub async fn list_namespaces(vault_name: &str) -> Result<JsValue, JsValue> {
+pub async fn query_namespace(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    namespace: JsValue,
+    path: &str,
+) -> Result<js_sys::Array, JsValue> {
     let platform = Platform::new();
 
-    let namespaces = operations::list_namespaces_in_vault(&platform, vault_name)
-        .await
-        .map_err(converters::to_js_error)?;
+    let namespace_str = converters::js_value_to_string(namespace)?;
 
-    converters::to_js_value(&namespaces)
+    validation::validate_namespace(&namespace_str).map_err(converters::to_js_error)?;
+
+    let matches = query::query_namespace(
+        &platform,
+        vault_name,
+        &identity.private_key(),
+        &namespace_str,
+        path,
+    )
+    .await
+    .map_err(converters::to_js_error)?;
+
+    let array = js_sys::Array::new();
+    for value in &matches {
+        array.push(&converters::to_js_value(value)?);
+    }
+
+    Ok(array)
 }
 
+/// Calls `callback` with the array of values the JSONPath `path` selects in
+/// `namespace` each time they change, whether written here, by another
+/// context or by a peer. Resolves to the id to pass to `unwatch_namespace`.
+/// The current values are not reported; read them with `query_namespace`.
 #[wasm_bindgen]
-pub async fn create_vault(vault_name: JsValue) -> Result<(), JsValue> {
+pub async fn watch_namespace(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    namespace: JsValue,
+    path: &str,
+    callback: js_sys::Function,
+) -> Result<u32, JsValue> {
     let platform = Platform::new();
 
-    let name = vault_name
-        .as_string()
-        .ok_or_else(|| JsValue::from_str("vault_name must be a string"))?;
+    let namespace_str = converters::js_value_to_string(namespace)?;
 
-    validation::validate_vault_name(&name).map_err(converters::to_js_error)?;
+    validation::validate_namespace(&namespace_str).map_err(converters::to_js_error)?;
 
-    if operations::read_vault(&platform, &name).await.is_ok() {
-        return Err(JsValue::from_str(&format!(
-            "Vault '{}' already exists",
-            name
-        )));
+    let watch_id = watch::watch_namespace(
+        &platform,
+        vault_name,
+        &identity.private_key(),
+        &namespace_str,
+        path,
+        Arc::new(call_watch_callback),
+    )
+    .await
+    .map_err(converters::to_js_error)?;
+
+    WATCH_CALLBACKS.with(|callbacks| callbacks.borrow_mut().insert(watch_id, callback));
+
+    Ok(watch_id)
+}
+
+/// Stops the watch `watch_id`. Returns `false` if there was none.
+#[wasm_bindgen]
+pub fn unwatch_namespace(watch_id: u32) -> bool {
+    WATCH_CALLBACKS.with(|callbacks| callbacks.borrow_mut().remove(&watch_id));
+    watch::unwatch_namespace(watch_id)
+}
+
+// JS functions cannot be shared across threads, so the domain only keeps the
+// watch id and the function stays on the thread that registered it.
+fn call_watch_callback(watch_id: u32, values: &[serde_json::Value]) {
+    let Some(callback) =
+        WATCH_CALLBACKS.with(|callbacks| callbacks.borrow().get(&watch_id).cloned())
+    else {
+        return;
+    };
+
+    let array = js_sys::Array::new();
+    for value in values {
+        match converters::to_js_value(value) {
+            Ok(value) => {
+                array.push(&value);
+            }
+            Err(e) => {
+                tracing::warn!(watch_id, error = ?e, "Failed to convert watched value");
+                return;
+            }
+        }
     }
 
-    let vault = operations::create_vault().await?;
+    if let Err(e) = callback.call1(&JsValue::NULL, &array) {
+        tracing::warn!(watch_id, error = ?e, "Watch callback threw");
+    }
+}
 
-    operations::save_vault(&platform, &name, vault)
-        .await
-        .map_err(|e| e.into())
+/// Calls `callback` with the name of each namespace of `vault_name` removed
+/// once expired, by a read or a cleanup, so the application can fetch it
+/// again. Returns the id to pass to `off_namespace_expired`. The same
+/// removals are posted as `namespaceExpired` events.
+#[wasm_bindgen]
+pub fn on_namespace_expired(vault_name: &str, callback: js_sys::Function) -> u32 {
+    let subscription_id =
+        expiration::on_namespace_expired(vault_name, Arc::new(call_expiration_callback));
+    EXPIRATION_CALLBACKS.with(|callbacks| callbacks.borrow_mut().insert(subscription_id, callback));
+
+    subscription_id
 }
 
+/// Removes the subscription `subscription_id`. Returns `false` if there was
+/// none.
 #[wasm_bindgen]
-pub async fn remove_vault(vault_name: &str) -> Result<(), JsValue> {
-    let platform = Platform::new();
+pub fn off_namespace_expired(subscription_id: u32) -> bool {
+    EXPIRATION_CALLBACKS.with(|callbacks| callbacks.borrow_mut().remove(&subscription_id));
+    expiration::off_namespace_expired(subscription_id)
+}
 
-    operations::delete_vault(&platform, vault_name)
-        .await
-        .map_err(|e| e.into())
+// Kept on the registering thread, like the watch callbacks.
+fn call_expiration_callback(subscription_id: u32, namespace: &str) {
+    let Some(callback) =
+        EXPIRATION_CALLBACKS.with(|callbacks| callbacks.borrow().get(&subscription_id).cloned())
+    else {
+        return;
+    };
+
+    if let Err(e) = callback.call1(&JsValue::NULL, &JsValue::from_str(namespace)) {
+        tracing::warn!(subscription_id, error = ?e, "Expiration callback threw");
+    }
 }
 
+/// Appends `entry` to today's segment of the append-only log `log_name`
+/// and resolves to the entry's timestamp, in milliseconds.
 #[wasm_bindgen]
-pub async fn list_vaults() -> Result<JsValue, JsValue> {
+pub async fn append_to_log(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    log_name: &str,
+    entry: JsValue,
+) -> Result<f64, JsValue> {
     let platform = Platform::new();
 
-    let vaults = operations::list_vaults(&platform)
-        .await
-        .map_err(converters::to_js_error)?;
+    let entry = converters::js_value_to_bytes(entry)?;
 
-    converters::to_js_value(&vaults)
+    let appended = log::append_to_log(
+        &platform,
+        vault_name,
+        &identity.private_key(),
+        log_name,
+        &entry,
+    )
+    .await?;
+
+    Ok(appended.timestamp as f64)
 }
 
+/// Resolves to the `{ timestamp, data }` entries of `log_name` appended from
+/// `from` up to, but excluding, `to` (milliseconds), oldest first.
 #[wasm_bindgen]
-pub async fn export_vault(vault_name: &str) -> Result<JsValue, JsValue> {
+pub async fn read_log_range(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    log_name: &str,
+    from: f64,
+    to: f64,
+) -> Result<js_sys::Array, JsValue> {
     let platform = Platform::new();
 
-    let vault_bytes = operations::export_vault_bytes(&platform, vault_name)
-        .await
-        .map_err(converters::to_js_error)?;
+    let entries = log::read_log_range(
+        &platform,
+        vault_name,
+        &identity.private_key(),
+        log_name,
+        from as i64,
+        to as i64,
+    )
+    .await?;
 
-    let array = js_sys::Uint8Array::new_with_length(vault_bytes.len() as u32);
-    array.copy_from(&vault_bytes);
-    Ok(array.into())
+    let array = js_sys::Array::new();
+    for entry in entries {
+        let object = js_sys::Object::new();
+        js_sys::Reflect::set(
+            &object,
+            &"timestamp".into(),
+            &(entry.timestamp as f64).into(),
+        )?;
+        js_sys::Reflect::set(
+            &object,
+            &"data".into(),
+            &converters::bytes_to_js_value(&entry.data)?,
+        )?;
+        array.push(&object);
+    }
+
+    Ok(array)
 }
 
+/// Sets the total privacy budget spent by `noisy_log_count` and
+/// `noisy_log_sum` on `log_name`. Logs have no budget until given one.
 #[wasm_bindgen]
-pub async fn import_vault(vault_name: &str, data: JsValue) -> Result<(), JsValue> {
+pub async fn set_log_privacy_budget(
+    vault_name: &str,
+    log_name: &str,
+    total: f64,
+) -> Result<(), JsValue> {
     let platform = Platform::new();
 
-    let vault_bytes = converters::js_value_to_bytes(data)?;
-
-    operations::import_vault_from_bytes(&platform, vault_name, &vault_bytes)
-        .await
-        .map_err(|e| e.into())
+    Ok(aggregate::set_privacy_budget(&platform, vault_name, log_name, total).await?)
 }
 
+/// Resolves to `{ total, spent }`.
 #[wasm_bindgen]
-pub async fn force_cleanup_vault(vault_name: &str) -> Result<(), JsValue> {
+pub async fn get_log_privacy_budget(vault_name: &str, log_name: &str) -> Result<JsValue, JsValue> {
     let platform = Platform::new();
 
-    loop {
-        let data_removed = operations::cleanup_vault(&platform, vault_name)
-            .await
-            .map_err(converters::to_js_error)?;
+    let budget = aggregate::privacy_budget(&platform, vault_name, log_name).await?;
+    converters::to_js_value(&budget)
+}
 
-        if !data_removed {
-            break;
-        }
-    }
+/// Number of entries of `log_name` appended from `from` up to `to`, with
+/// Laplace noise for privacy loss `epsilon`, spent from the log budget.
+/// Resolves to `{ value, epsilon, remaining_budget }`.
+#[wasm_bindgen]
+pub async fn noisy_log_count(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    log_name: &str,
+    from: f64,
+    to: f64,
+    epsilon: f64,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
 
-    Ok(())
+    let count = aggregate::noisy_count(
+        &platform,
+        vault_name,
+        &identity.private_key(),
+        log_name,
+        from as i64,
+        to as i64,
+        epsilon,
+    )
+    .await?;
+
+    converters::to_js_value(&count)
 }
 
+/// Sum over the JSON entries of `log_name` appended from `from` up to `to`
+/// of the number `query.path` selects, clamped to `query.lower` and
+/// `query.upper`, with Laplace noise for privacy loss `query.epsilon`.
 #[wasm_bindgen]
-pub fn configure_cleanup(interval_seconds: i64) {
+pub async fn noisy_log_sum(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    log_name: &str,
+    from: f64,
+    to: f64,
+    query: JsValue,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+    let query: SumQuery = serde_wasm_bindgen::from_value(query)?;
+
+    let sum = aggregate::noisy_sum(
+        &platform,
+        vault_name,
+        &identity.private_key(),
+        log_name,
+        from as i64,
+        to as i64,
+        &query,
+    )
+    .await?;
+
+    converters::to_js_value(&sum)
+}
+
+/// Stores the `Uint8Array` chunks `stream` yields, such as those of
+/// `File.stream()`, in `namespace`. The payload is encrypted piece by piece
+/// as it is read, so it never has to fit in memory.
+#[wasm_bindgen]
+pub async fn upsert_vault_stream(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    namespace: &str,
+    stream: web_sys::ReadableStream,
+    expires_in_seconds: Option<i64>,
+    replace_if_exists: bool,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    validation::validate_namespace(namespace).map_err(converters::to_js_error)?;
+
+    let mut writer = stream::NamespaceWriter::open(
+        &platform,
+        vault_name,
+        &identity.public_key(),
+        namespace,
+        expires_in_seconds,
+        replace_if_exists,
+    )
+    .await?;
+
+    let reader: web_sys::ReadableStreamDefaultReader = stream.get_reader().unchecked_into();
+    loop {
+        let result = JsFuture::from(reader.read()).await?;
+        if js_sys::Reflect::get(&result, &"done".into())?.is_truthy() {
+            break;
+        }
+
+        let chunk: js_sys::Uint8Array = js_sys::Reflect::get(&result, &"value".into())?
+            .dyn_into()
+            .map_err(|_| converters::to_js_error("Stream chunks must be Uint8Array"))?;
+        if let Err(e) = writer.write(&chunk.to_vec()).await {
+            let _ = reader.cancel();
+            return Err(e.into());
+        }
+    }
+
+    writer.finish().await?;
+
+    Ok(())
+}
+
+/// Writes the decrypted payload of `namespace` to `stream`, such as an OPFS
+/// file's `createWritable()`, one stored piece at a time, then closes it.
+#[wasm_bindgen]
+pub async fn read_from_vault_stream(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    namespace: &str,
+    stream: web_sys::WritableStream,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    validation::validate_namespace(namespace).map_err(converters::to_js_error)?;
+
+    let mut reader =
+        stream::NamespaceReader::open(&platform, vault_name, &identity.private_key(), namespace)
+            .await?;
+
+    let writer = stream.get_writer()?;
+    loop {
+        let piece = match reader.next_piece().await {
+            Ok(Some(piece)) => piece,
+            Ok(None) => break,
+            Err(e) => {
+                let _ = writer.abort();
+                return Err(e.into());
+            }
+        };
+
+        JsFuture::from(writer.ready()).await?;
+        JsFuture::from(writer.write_with_chunk(&js_sys::Uint8Array::from(piece.as_slice())))
+            .await?;
+    }
+
+    JsFuture::from(writer.close()).await?;
+
+    Ok(())
+}
+
+/// Stores `data` so that nobody, the writer included, can read it before
+/// `release_at` (Unix seconds). Resolves to one key share per escrow peer in
+/// `holders`; `threshold` of them, released after that date, open the
+/// namespace through `read_timelocked`.
+#[wasm_bindgen]
+pub async fn write_timelocked(
+    vault_name: &str,
+    namespace: &str,
+    data: JsValue,
+    release_at: i64,
+    holders: Vec<String>,
+    threshold: u8,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    validation::validate_namespace(namespace).map_err(converters::to_js_error)?;
+
+    let data_bytes = converters::js_value_to_bytes(data)?;
+    let holders: Vec<&str> = holders.iter().map(String::as_str).collect();
+
+    let shares = timelock::write_timelocked(
+        &platform, vault_name, namespace, data_bytes, release_at, &holders, threshold,
+    )
+    .await?;
+
+    serde_wasm_bindgen::to_value(&shares).map_err(converters::to_js_error)
+}
+
+/// Called by an escrow peer to unseal its share; fails until the release
+/// date has passed on this device's clock.
+#[wasm_bindgen]
+pub async fn release_timelock_share(
+    share: JsValue,
+    holder: &IdentityHandle,
+) -> Result<Vec<u8>, JsValue> {
+    let platform = Platform::new();
+
+    let share: KeyShare = serde_wasm_bindgen::from_value(share)?;
+
+    Ok(timelock::release_key_share(&platform, &share, &holder.private_key()).await?)
+}
+
+#[wasm_bindgen]
+pub async fn read_timelocked(
+    vault_name: &str,
+    namespace: &str,
+    released_shares: Vec<js_sys::Uint8Array>,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    validation::validate_namespace(namespace).map_err(converters::to_js_error)?;
+
+    let released_shares: Vec<Vec<u8>> = released_shares
+        .iter()
+        .map(js_sys::Uint8Array::to_vec)
+        .collect();
+
+    let data_bytes =
+        timelock::read_timelocked(&platform, vault_name, namespace, &released_shares).await?;
+
+    converters::bytes_to_js_value(&data_bytes)
+}
+
+#[wasm_bindgen]
+pub async fn remove_from_vault(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    namespace: JsValue,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    let namespace_str = converters::js_value_to_string(namespace)?;
+    validation::validate_namespace(&namespace_str).map_err(converters::to_js_error)?;
+
+    operations::verify_vault_identity(&platform, vault_name, &identity.private_key()).await?;
+
+    operations::remove_namespace(&platform, vault_name, &namespace_str)
+        .await
+        .map_err(|e| e.into())
+}
+
+/// Renames namespace `from` of `vault_name` to `to` without re-encrypting it.
+#[wasm_bindgen]
+pub async fn rename_namespace(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    from: &str,
+    to: &str,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    validation::validate_namespace(from).map_err(converters::to_js_error)?;
+
+    operations::rename_namespace(&platform, vault_name, &identity.private_key(), from, to)
+        .await
+        .map_err(converters::to_js_error)
+}
+
+/// Copies `namespace` of `vault_name` into `target_vault`, encrypted to
+/// `identity`. Fails if `target_vault` already holds that namespace.
+#[wasm_bindgen]
+pub async fn copy_namespace(
+    vault_name: &str,
+    target_vault: &str,
+    identity: &IdentityHandle,
+    namespace: &str,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    validation::validate_namespace(namespace).map_err(converters::to_js_error)?;
+
+    operations::copy_namespace(
+        &platform,
+        vault_name,
+        target_vault,
+        &identity.private_key(),
+        namespace,
+    )
+    .await
+    .map_err(converters::to_js_error)
+}
+
+/// Lists the namespaces of `vault_name`, or only those at or under the
+/// path `prefix`, such as `app/settings`.
+#[wasm_bindgen]
+pub async fn list_namespaces(vault_name: &str, prefix: Option<String>) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let namespaces = match prefix {
+        Some(prefix) => {
+            validation::validate_namespace_prefix(&prefix).map_err(converters::to_js_error)?;
+            operations::list_namespaces_with_prefix(&platform, vault_name, &prefix).await
+        }
+        None => operations::list_namespaces_in_vault(&platform, vault_name).await,
+    }
+    .map_err(converters::to_js_error)?;
+
+    converters::to_js_value(&namespaces)
+}
+
+/// Removes every namespace at or under the path `prefix` and returns their
+/// names.
+#[wasm_bindgen]
+pub async fn remove_prefix(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    prefix: &str,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    operations::verify_vault_identity(&platform, vault_name, &identity.private_key()).await?;
+
+    let removed = operations::remove_prefix(&platform, vault_name, prefix)
+        .await
+        .map_err(converters::to_js_error)?;
+
+    converters::to_js_value(&removed)
+}
+
+#[wasm_bindgen]
+pub async fn create_vault(vault_name: JsValue) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    let name = vault_name
+        .as_string()
+        .ok_or_else(|| JsValue::from_str("vault_name must be a string"))?;
+
+    validation::validate_vault_name(&name).map_err(converters::to_js_error)?;
+
+    if operations::read_vault(&platform, &name).await.is_ok() {
+        return Err(JsValue::from_str(&format!(
+            "Vault '{}' already exists",
+            name
+        )));
+    }
+
+    let vault = operations::create_vault().await?;
+
+    operations::save_vault(&platform, &name, vault)
+        .await
+        .map_err(|e| e.into())
+}
+
+#[wasm_bindgen]
+pub async fn create_observer_vault(vault_name: &str) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    validation::validate_vault_name(vault_name).map_err(converters::to_js_error)?;
+
+    if operations::read_vault(&platform, vault_name).await.is_ok() {
+        return Err(JsValue::from_str(&format!(
+            "Vault '{}' already exists",
+            vault_name
+        )));
+    }
+
+    let vault = operations::create_observer_vault().await?;
+
+    operations::save_vault(&platform, vault_name, vault)
+        .await
+        .map_err(|e| e.into())
+}
+
+#[wasm_bindgen]
+pub async fn is_observer_vault(vault_name: &str) -> Result<bool, JsValue> {
+    let platform = Platform::new();
+
+    let vault = operations::read_vault(&platform, vault_name)
+        .await
+        .map_err(converters::to_js_error)?;
+
+    Ok(vault.observer)
+}
+
+#[wasm_bindgen]
+pub async fn grant_vault_recipient(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    recipient: &str,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+    let abort = converters::AbortBinding::new(signal);
+
+    abortable_write(
+        &platform,
+        vault_name,
+        &abort,
+        operations::grant_vault_recipient(
+            &platform,
+            vault_name,
+            &identity.private_key(),
+            recipient,
+        )
+        .map_err(converters::to_js_error),
+    )
+    .await
+}
+
+/// Lets the holder of `recipient` read `namespace` without access to the
+/// rest of the vault. Later writes stay readable by `recipient` until
+/// `revoke_namespace_access`.
+#[wasm_bindgen]
+pub async fn grant_namespace_access(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    namespace: &str,
+    recipient: &str,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+    let abort = converters::AbortBinding::new(signal);
+
+    validation::validate_namespace(namespace)?;
+    abortable_write(
+        &platform,
+        vault_name,
+        &abort,
+        access::grant_namespace_access(
+            &platform,
+            vault_name,
+            &identity.private_key(),
+            namespace,
+            recipient,
+        )
+        .map_err(converters::to_js_error),
+    )
+    .await
+}
+
+/// Lets the holders of all `recipients` read `namespace`, re-encrypting it
+/// once. The keys are recorded with the namespace, so the vault can be
+/// exported or synced to them later without either side being online.
+#[wasm_bindgen]
+pub async fn share_namespace(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    namespace: &str,
+    recipients: Vec<String>,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+    let abort = converters::AbortBinding::new(signal);
+    let recipients: Vec<&str> = recipients.iter().map(String::as_str).collect();
+
+    validation::validate_namespace(namespace)?;
+    abortable_write(
+        &platform,
+        vault_name,
+        &abort,
+        access::share_namespace(
+            &platform,
+            vault_name,
+            &identity.private_key(),
+            namespace,
+            &recipients,
+        )
+        .map_err(converters::to_js_error),
+    )
+    .await
+}
+
+/// Re-encrypts `namespace` without `recipient`. Anything it already read
+/// stays readable to it.
+#[wasm_bindgen]
+pub async fn revoke_namespace_access(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    namespace: &str,
+    recipient: &str,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+    let abort = converters::AbortBinding::new(signal);
+
+    validation::validate_namespace(namespace)?;
+    abortable_write(
+        &platform,
+        vault_name,
+        &abort,
+        access::revoke_namespace_access(
+            &platform,
+            vault_name,
+            &identity.private_key(),
+            namespace,
+            recipient,
+        )
+        .map_err(converters::to_js_error),
+    )
+    .await
+}
+
+#[wasm_bindgen]
+pub async fn get_namespace_recipients(
+    vault_name: &str,
+    namespace: &str,
+) -> Result<Vec<String>, JsValue> {
+    let platform = Platform::new();
+
+    Ok(access::namespace_recipients(&platform, vault_name, namespace).await?)
+}
+
+#[wasm_bindgen]
+pub async fn promote_observer_vault(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+    let abort = converters::AbortBinding::new(signal);
+
+    abortable_write(
+        &platform,
+        vault_name,
+        &abort,
+        operations::promote_observer_vault(&platform, vault_name, &identity.private_key())
+            .map_err(converters::to_js_error),
+    )
+    .await
+}
+
+/// Keeps `namespace` for at least `min_retention_seconds` after its last
+/// write and, with `delete_locked_until` (Unix seconds), until that date.
+/// Removals, expiration cleanup and peer deletions fail before then. Passing
+/// neither clears the policy, which like any loosening is refused while it
+/// still holds.
+#[wasm_bindgen]
+pub async fn set_namespace_retention(
+    vault_name: &str,
+    namespace: &str,
+    min_retention_seconds: Option<i64>,
+    delete_locked_until: Option<i64>,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    validation::validate_namespace(namespace)?;
+    let policy = retention::set_namespace_retention(
+        &platform,
+        vault_name,
+        namespace,
+        min_retention_seconds,
+        delete_locked_until,
+    )
+    .await?;
+
+    converters::to_js_value(&policy)
+}
+
+#[wasm_bindgen]
+pub async fn get_namespace_retention(
+    vault_name: &str,
+    namespace: &str,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let policy = retention::namespace_retention(&platform, vault_name, namespace).await?;
+
+    converters::to_js_value(&policy)
+}
+
+/// Tags `namespace` with residency or classification labels. Paired
+/// devices only receive it when they assert every tag with
+/// `set_residency_attributes`; an empty list clears the tags.
+#[wasm_bindgen]
+pub async fn set_namespace_residency(
+    vault_name: &str,
+    namespace: &str,
+    tags: Vec<String>,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    validation::validate_namespace(namespace)?;
+    residency::set_namespace_residency(
+        &platform,
+        vault_name,
+        namespace,
+        residency::normalize_tags(tags),
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub async fn get_namespace_residency(
+    vault_name: &str,
+    namespace: &str,
+) -> Result<Vec<String>, JsValue> {
+    let platform = Platform::new();
+
+    let tags = residency::namespace_residency(&platform, vault_name, namespace).await?;
+
+    Ok(tags.into_iter().collect())
+}
+
+/// Caps the plaintext size of each namespace of the vault, in bytes; larger
+/// writes fail with a `quota_exceeded` error stating the vault's current
+/// usage. `undefined` restores the default of 32 MiB.
+#[wasm_bindgen]
+pub async fn set_max_namespace_size(
+    vault_name: &str,
+    max_bytes: Option<u32>,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    limits::set_max_namespace_bytes(&platform, vault_name, max_bytes.map(u64::from)).await?;
+
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub async fn get_max_namespace_size(vault_name: &str) -> Result<f64, JsValue> {
+    let platform = Platform::new();
+
+    Ok(limits::namespace_size_limit(&platform, vault_name).await? as f64)
+}
+
+/// Compresses later writes to the vault that do not choose a compression:
+/// `compression` is `none` (the default) or `deflate`. Payloads that do not
+/// get smaller are stored uncompressed.
+#[wasm_bindgen]
+pub async fn set_vault_compression(vault_name: &str, compression: JsValue) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    let compression = serde_wasm_bindgen::from_value(compression)?;
+    compression::set_vault_compression(&platform, vault_name, compression)
+        .await
+        .map_err(converters::to_js_error)
+}
+
+/// Sets the payload size above which namespaces are written as several
+/// chunk files instead of one, for every vault. `0` keeps each namespace in
+/// a single file. Reads reassemble split namespaces either way.
+#[wasm_bindgen]
+pub fn set_namespace_split_threshold(bytes: u32) {
+    chunks::set_split_threshold(bytes.into());
+}
+
+/// Scans the plaintext of every namespace write for AWS keys, PEM private
+/// keys and JSON Web Tokens. `policy` is `off` (the default), `warn`, which
+/// logs and emits a `secretDetected` event, or `block`, which fails the
+/// write with a `secret_detected` error.
+#[wasm_bindgen]
+pub fn set_secret_scan_policy(policy: &str) -> Result<(), JsValue> {
+    secret_scan::set_secret_scan_policy(policy.parse()?);
+    Ok(())
+}
+
+/// Makes the vault read-only until `unfreeze_vault`: writes, removals,
+/// cleanup and incoming sync operations fail with a frozen vault error.
+#[wasm_bindgen]
+pub async fn freeze_vault(vault_name: &str, identity: &IdentityHandle) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    operations::freeze_vault(&platform, vault_name, &identity.private_key())
+        .await
+        .map_err(converters::to_js_error)
+}
+
+#[wasm_bindgen]
+pub async fn unfreeze_vault(vault_name: &str, identity: &IdentityHandle) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    operations::unfreeze_vault(&platform, vault_name, &identity.private_key())
+        .await
+        .map_err(converters::to_js_error)
+}
+
+#[wasm_bindgen]
+pub async fn is_vault_frozen(vault_name: &str) -> Result<bool, JsValue> {
+    let platform = Platform::new();
+
+    let vault = operations::read_vault_metadata(&platform, vault_name)
+        .await
+        .map_err(converters::to_js_error)?;
+
+    Ok(vault.frozen)
+}
+
+/// Keeps a snapshot of every later namespace write of `vault_name`, listed
+/// by `list_snapshots` and read back with `read_at`.
+#[wasm_bindgen]
+pub async fn enable_history(vault_name: &str) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    history::enable_history(&platform, vault_name)
+        .await
+        .map_err(converters::to_js_error)
+}
+
+#[wasm_bindgen]
+pub async fn disable_history(vault_name: &str) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    history::disable_history(&platform, vault_name)
+        .await
+        .map_err(converters::to_js_error)
+}
+
+/// Records a keyed hash of every namespace file in a manifest signed with
+/// `identity`, for [`verify_vault`] to check against. Seal again after
+/// writing. Returns `{ key_id, sealed_at, files, signature }`.
+#[wasm_bindgen]
+pub async fn seal_vault(vault_name: &str, identity: &IdentityHandle) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let manifest = integrity::seal_vault(&platform, vault_name, &identity.private_key())
+        .await
+        .map_err(converters::to_js_error)?;
+
+    converters::to_js_value(&manifest)
+}
+
+/// Decrypts every namespace `identity` can read and checks its plaintext
+/// against the integrity MAC stored with it, then the namespace files
+/// against the manifest `identity` sealed, if any. Returns the namespace
+/// names as `{ verified, corrupted, unchecked, tampered, missing, orphaned,
+/// sealed_at }`.
+#[wasm_bindgen]
+pub async fn verify_vault(vault_name: &str, identity: &IdentityHandle) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let report = integrity::verify_vault(&platform, vault_name, &identity.private_key())
+        .await
+        .map_err(converters::to_js_error)?;
+
+    converters::to_js_value(&report)
+}
+
+/// Algorithms `vault_name` is encrypted with, for security reviews:
+/// `{ format, kdf, cipher_suite, recipient_types, identities,
+/// unreadable_headers, namespaces_without_mac, post_quantum }`. Recipient
+/// types are read from the ciphertext headers, without decrypting anything.
+#[wasm_bindgen]
+pub async fn describe_crypto(vault_name: &str) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let description = crypto_audit::describe_crypto(&platform, vault_name)
+        .await
+        .map_err(converters::to_js_error)?;
+
+    converters::to_js_value(&description)
+}
+
+/// Namespaces of `vault_name` per version of the algorithms they are
+/// encrypted with, as `{ current_version, versions, pending }`.
+#[wasm_bindgen]
+pub async fn migration_status(vault_name: &str) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let status = migration::migration_status(&platform, vault_name)
+        .await
+        .map_err(converters::to_js_error)?;
+
+    converters::to_js_value(&status)
+}
+
+/// Re-encrypts up to `max_namespaces` namespaces `identity` wrote with
+/// older algorithms. Call it again until `remaining` reaches zero, as
+/// `{ migrated, skipped, remaining }` reports.
+#[wasm_bindgen]
+pub async fn migrate_vault(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    max_namespaces: u32,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let report = migration::migrate_vault(
+        &platform,
+        vault_name,
+        &identity.private_key(),
+        max_namespaces as usize,
+    )
+    .await
+    .map_err(converters::to_js_error)?;
+
+    converters::to_js_value(&report)
+}
+
+/// Snapshots of `vault_name`, oldest first, as
+/// `{ id, namespace, taken_at, size }` objects.
+#[wasm_bindgen]
+pub async fn list_snapshots(vault_name: &str) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let snapshots = history::list_snapshots(&platform, vault_name)
+        .await
+        .map_err(converters::to_js_error)?;
+
+    converters::to_js_value(&snapshots)
+}
+
+#[wasm_bindgen]
+pub async fn read_at(
+    vault_name: &str,
+    identity: &IdentityHandle,
+    namespace: &str,
+    snapshot_id: &str,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+    validation::validate_namespace(namespace).map_err(converters::to_js_error)?;
+
+    let data = history::read_at(
+        &platform,
+        vault_name,
+        &identity.private_key(),
+        namespace,
+        snapshot_id,
+    )
+    .await
+    .map_err(converters::to_js_error)?;
+
+    converters::bytes_to_js_value(&data)
+}
+
+/// Drops the snapshots ruled out by `policy`, a
+/// `{ keep_last, max_age_seconds }` object, and resolves to their number.
+#[wasm_bindgen]
+pub async fn prune_snapshots(vault_name: &str, policy: JsValue) -> Result<u32, JsValue> {
+    let platform = Platform::new();
+    let policy: PrunePolicy = serde_wasm_bindgen::from_value(policy)?;
+
+    let removed = history::prune_snapshots(&platform, vault_name, policy)
+        .await
+        .map_err(converters::to_js_error)?;
+
+    Ok(removed as u32)
+}
+
+#[wasm_bindgen]
+pub async fn remove_vault(vault_name: &str) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    operations::delete_vault(&platform, vault_name)
+        .await
+        .map_err(|e| e.into())
+}
+
+/// Moves `vault_name` to `new_name`, which must not exist yet. The vault
+/// keeps its peer id, but its sync sessions under the old name are closed,
+/// so paired devices pair again with the new name.
+#[wasm_bindgen]
+pub async fn rename_vault(vault_name: &str, new_name: &str) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    operations::rename_vault(&platform, vault_name, new_name)
+        .await
+        .map_err(converters::to_js_error)?;
+
+    #[cfg(feature = "sync")]
+    crate::context::default_context().forget_vault(vault_name);
+    Ok(())
+}
+
+/// Copies `vault_name` to `new_name`, which must not exist yet, as an
+/// unsynced vault of its own.
+#[wasm_bindgen]
+pub async fn clone_vault(vault_name: &str, new_name: &str) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    operations::clone_vault(&platform, vault_name, new_name)
+        .await
+        .map_err(converters::to_js_error)
+}
+
+#[wasm_bindgen]
+pub async fn list_vaults() -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let vaults = operations::list_vaults(&platform)
+        .await
+        .map_err(converters::to_js_error)?;
+
+    converters::to_js_value(&vaults)
+}
+
+/// Rewrites legacy `.ns` namespace files of `vault_name` in the current
+/// format. Resolves to the number of files upgraded.
+#[wasm_bindgen]
+pub async fn migrate_vault_files(
+    vault_name: &str,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<u32, JsValue> {
+    let platform = Platform::new();
+    let abort = converters::AbortBinding::new(signal);
+
+    abortable_write(
+        &platform,
+        vault_name,
+        &abort,
+        operations::migrate_vault_files(&platform, vault_name).map_err(converters::to_js_error),
+    )
+    .await
+    .map(|migrated| migrated as u32)
+}
+
+#[wasm_bindgen]
+pub fn configure_legacy_read_repair(enabled: bool) {
+    operations::set_legacy_read_repair(enabled);
+}
+
+/// When enabled (the default), reading a namespace written with older
+/// algorithms re-encrypts it with the current ones.
+#[wasm_bindgen]
+pub fn configure_lazy_migration(enabled: bool) {
+    migration::set_lazy_migration(enabled);
+}
+
+/// Replays any save interrupted by a crash or closed tab. Call once at
+/// startup; resolves to the names of the vaults that were repaired.
+#[wasm_bindgen]
+pub async fn recover_vaults() -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let recovered = wal::recover_all_vaults(&platform)
+        .await
+        .map_err(converters::to_js_error)?;
+
+    converters::to_js_value(&recovered)
+}
+
+/// `on_progress` is called with the number of vault files read so far.
+#[wasm_bindgen]
+pub async fn export_vault(
+    vault_name: &str,
+    signal: Option<web_sys::AbortSignal>,
+    on_progress: Option<js_sys::Function>,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+    let abort = converters::AbortBinding::new(signal);
+    let on_progress = converters::progress_callback(on_progress);
+
+    let vault_bytes = abort
+        .run(
+            operations::export_vault_bytes_with_progress(&platform, vault_name, &on_progress)
+                .map_err(converters::to_js_error),
+        )
+        .await?;
+
+    let array = js_sys::Uint8Array::new_with_length(vault_bytes.len() as u32);
+    array.copy_from(&vault_bytes);
+    Ok(array.into())
+}
+
+/// `on_progress` is called with the number of bytes written to storage so far.
+#[wasm_bindgen]
+pub async fn import_vault(
+    vault_name: &str,
+    data: JsValue,
+    signal: Option<web_sys::AbortSignal>,
+    on_progress: Option<js_sys::Function>,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    let vault_bytes = converters::js_value_to_bytes(data)?;
+    let abort = converters::AbortBinding::new(signal);
+    let on_progress = converters::progress_callback(on_progress);
+
+    abortable_write(
+        &platform,
+        vault_name,
+        &abort,
+        operations::import_vault_from_bytes_with_progress(
+            &platform,
+            vault_name,
+            &vault_bytes,
+            &on_progress,
+        ),
+    )
+    .await
+}
+
+/// Like `export_vault`, with the export encrypted to `recipient` or to
+/// `passphrase`; exactly one of them must be given.
+#[wasm_bindgen]
+pub async fn export_vault_encrypted(
+    vault_name: &str,
+    recipient: Option<RecipientHandle>,
+    passphrase: Option<String>,
+    signal: Option<web_sys::AbortSignal>,
+    on_progress: Option<js_sys::Function>,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+    let recipient = recipient.map(|recipient| recipient.to_string());
+    let key = match (&recipient, &passphrase) {
+        (Some(recipient), None) => ExportKey::Recipient(recipient),
+        (None, Some(passphrase)) => ExportKey::Passphrase(passphrase),
+        _ => return Err(JsValue::from_str("Pass either a recipient or a passphrase")),
+    };
+    let abort = converters::AbortBinding::new(signal);
+    let on_progress = converters::progress_callback(on_progress);
+
+    let bytes = abort
+        .run(
+            encrypted_export::export_vault_encrypted(&platform, vault_name, key, &on_progress)
+                .map_err(converters::to_js_error),
+        )
+        .await?;
+
+    let array = js_sys::Uint8Array::new_with_length(bytes.len() as u32);
+    array.copy_from(&bytes);
+    Ok(array.into())
+}
+
+/// Imports an export made by `export_vault_encrypted`, decrypted with
+/// `identity` or `passphrase`.
+#[wasm_bindgen]
+pub async fn import_vault_encrypted(
+    vault_name: &str,
+    data: JsValue,
+    identity: Option<IdentityHandle>,
+    passphrase: Option<String>,
+    signal: Option<web_sys::AbortSignal>,
+    on_progress: Option<js_sys::Function>,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    let bytes = converters::js_value_to_bytes(data)?;
+    let private_key = identity.map(|identity| identity.private_key());
+    let secret = match (&private_key, &passphrase) {
+        (Some(private_key), None) => ExportSecret::Identity(private_key),
+        (None, Some(passphrase)) => ExportSecret::Passphrase(passphrase),
+        _ => return Err(JsValue::from_str("Pass either an identity or a passphrase")),
+    };
+    let abort = converters::AbortBinding::new(signal);
+    let on_progress = converters::progress_callback(on_progress);
+
+    abortable_write(
+        &platform,
+        vault_name,
+        &abort,
+        encrypted_export::import_vault_encrypted(
+            &platform,
+            vault_name,
+            &bytes,
+            secret,
+            &on_progress,
+        ),
+    )
+    .await
+}
+
+/// Exports the namespaces of `vault_name` written since `since`, Unix time in
+/// seconds, with a manifest of every namespace the vault holds. The
+/// manifest's `until`, read with `read_incremental_manifest`, is the `since`
+/// of the next backup.
+#[wasm_bindgen]
+pub async fn export_vault_incremental(vault_name: &str, since: i64) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let bytes = incremental::export_vault_incremental(&platform, vault_name, since).await?;
+
+    let array = js_sys::Uint8Array::new_with_length(bytes.len() as u32);
+    array.copy_from(&bytes);
+    Ok(array.into())
+}
+
+#[wasm_bindgen]
+pub fn read_incremental_manifest(data: JsValue) -> Result<JsValue, JsValue> {
+    let bytes = converters::js_value_to_bytes(data)?;
+
+    converters::to_js_value(&incremental::read_incremental_manifest(&bytes)?)
+}
+
+/// Brings `vault_name` up to date with an export made by
+/// `export_vault_incremental`. Resolves to `{ updated, removed }`.
+#[wasm_bindgen]
+pub async fn apply_incremental_export(
+    vault_name: &str,
+    data: JsValue,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let bytes = converters::js_value_to_bytes(data)?;
+    let abort = converters::AbortBinding::new(signal);
+
+    let report = abortable_write(
+        &platform,
+        vault_name,
+        &abort,
+        incremental::apply_incremental_export(&platform, vault_name, &bytes),
+    )
+    .await?;
+
+    converters::to_js_value(&report)
+}
+
+/// Restores the namespaces listed in `namespaces`, or all of them when it is
+/// omitted, from an export into `vault_name`, which is created if needed.
+/// `existing` is `skip` (the default) or `overwrite` and decides what
+/// happens to namespaces the vault already holds. Resolves to
+/// `{ imported, skipped }`.
+#[wasm_bindgen]
+pub async fn import_vault_namespaces(
+    vault_name: &str,
+    data: JsValue,
+    namespaces: Option<Vec<String>>,
+    existing: Option<String>,
+    signal: Option<web_sys::AbortSignal>,
+    on_progress: Option<js_sys::Function>,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let vault_bytes = converters::js_value_to_bytes(data)?;
+    let options = ImportOptions {
+        namespaces: namespaces.map(|namespaces| namespaces.into_iter().collect()),
+        existing: existing
+            .as_deref()
+            .map(str::parse::<ExistingNamespaces>)
+            .transpose()?
+            .unwrap_or_default(),
+    };
+    let abort = converters::AbortBinding::new(signal);
+    let on_progress = converters::progress_callback(on_progress);
+
+    let report = abortable_write(
+        &platform,
+        vault_name,
+        &abort,
+        import::import_namespaces(&platform, vault_name, &vault_bytes, &options, &on_progress),
+    )
+    .await?;
+
+    converters::to_js_value(&report)
+}
+
+/// Compares two vaults, each given either by name or as the bytes of an
+/// export. Namespaces whose ciphertext differs are compared by content when
+/// `identity` can decrypt them on both sides.
+#[wasm_bindgen]
+pub async fn diff_vaults(
+    left: JsValue,
+    right: JsValue,
+    identity: Option<IdentityHandle>,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let left = vault_from_js(&platform, left).await?;
+    let right = vault_from_js(&platform, right).await?;
+    let private_key = identity.map(|identity| identity.private_key());
+
+    let report = diff::diff_vaults(&platform, &left, &right, private_key.as_deref()).await;
+
+    converters::to_js_value(&report)
+}
+
+/// Merges `secondary`, a vault name or the bytes of an export, into the
+/// vault `primary`. `strategy` is `prefer-newest`, `prefer-primary` or
+/// `keep-both-with-suffix`; with `dry_run` the report is computed and
+/// nothing is written.
+#[wasm_bindgen]
+pub async fn merge_vaults(
+    primary: &str,
+    secondary: JsValue,
+    strategy: &str,
+    dry_run: bool,
+) -> Result<JsValue, JsValue> {
+    let platform = Platform::new();
+
+    let strategy: MergeStrategy = strategy.parse()?;
+    let secondary = vault_from_js(&platform, secondary).await?;
+
+    let report = merge::merge_vaults(&platform, primary, &secondary, strategy, dry_run).await?;
+
+    converters::to_js_value(&report)
+}
+
+async fn vault_from_js(
+    platform: &Platform,
+    value: JsValue,
+) -> Result<crate::domain::vault::Vault, JsValue> {
+    if value.is_instance_of::<js_sys::Uint8Array>() {
+        let bytes = js_sys::Uint8Array::from(value).to_vec();
+        return Ok(deserialize_vault(&bytes)?);
+    }
+
+    let vault_name = converters::js_value_to_string(value)?;
+    Ok(operations::read_vault(platform, &vault_name).await?)
+}
+
+#[wasm_bindgen]
+pub async fn force_cleanup_vault(
+    vault_name: &str,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+    let abort = converters::AbortBinding::new(signal);
+
+    loop {
+        let data_removed = abortable_write(
+            &platform,
+            vault_name,
+            &abort,
+            operations::cleanup_vault(&platform, vault_name).map_err(converters::to_js_error),
+        )
+        .await?;
+
+        if !data_removed {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the expired namespaces of every vault every `interval_seconds`,
+/// plus up to a tenth of it at random; `0` or less stops it. Same as
+/// `start_cleanup_scheduler(interval_seconds)` and `stop_cleanup_scheduler`.
+#[wasm_bindgen]
+pub fn configure_cleanup(interval_seconds: i64) {
     if interval_seconds > 0 {
-        web_sys::console::log_1(
-            &format!(
-                "Configuring cleanup with interval of {} seconds",
-                interval_seconds
-            )
-            .into(),
-        );
-        CLEANUP_INTERVAL.store(interval_seconds, Ordering::SeqCst);
-        LAST_CLEANUP.store(js_sys::Date::now() as i64 / 1000, Ordering::SeqCst);
+        start_cleanup_scheduler(interval_seconds.min(u32::MAX.into()) as u32, None);
     } else {
-        web_sys::console::log_1(&"Disabling automatic cleanup".into());
-        CLEANUP_INTERVAL.store(0, Ordering::SeqCst);
+        stop_cleanup_scheduler();
+    }
+}
+
+/// Removes the expired namespaces of every vault every `interval_seconds`
+/// plus a random delay of up to `jitter_seconds`, a tenth of the interval
+/// by default, replacing the running scheduler. The scheduler lives as long
+/// as the page or worker that started it.
+#[wasm_bindgen]
+pub fn start_cleanup_scheduler(interval_seconds: u32, jitter_seconds: Option<u32>) {
+    let mut schedule = CleanupSchedule::new(interval_seconds);
+    if let Some(jitter_seconds) = jitter_seconds {
+        schedule = schedule.with_jitter(jitter_seconds);
+    }
+
+    tracing::info!(interval_seconds, "Starting scheduled cleanup");
+    let generation = cleanup_scheduler::begin_cleanup_scheduler();
+    wasm_bindgen_futures::spawn_local(async move {
+        let platform = Platform::new();
+        cleanup_scheduler::run_cleanup_scheduler(&platform, schedule, generation).await;
+    });
+}
+
+/// Stops the cleanup scheduler. Returns `false` if none was running.
+#[wasm_bindgen]
+pub fn stop_cleanup_scheduler() -> bool {
+    tracing::info!("Stopping scheduled cleanup");
+    cleanup_scheduler::stop_cleanup_scheduler()
+}
+
+#[wasm_bindgen]
+pub fn is_cleanup_scheduler_running() -> bool {
+    cleanup_scheduler::is_cleanup_scheduler_running()
+}
+
+/// Opens `vault_name` and returns a handle for the `vault_handle_*`
+/// functions. The handle keeps the vault metadata and platform around between
+/// calls until `close_vault` releases it.
+#[wasm_bindgen]
+pub async fn open_vault(vault_name: &str) -> Result<u32, JsValue> {
+    let vault = VaultHandle::open(Platform::new(), vault_name).await?;
+
+    let handle = NEXT_VAULT_HANDLE.with(|next| {
+        let handle = next.get();
+        next.set(handle.wrapping_add(1));
+        handle
+    });
+    OPEN_VAULTS.with(|vaults| vaults.borrow_mut().insert(handle, Rc::new(vault)));
+
+    Ok(handle)
+}
+
+/// Releases `handle`. Calls already in flight on it still complete.
+#[wasm_bindgen]
+pub fn close_vault(handle: u32) {
+    OPEN_VAULTS.with(|vaults| vaults.borrow_mut().remove(&handle));
+}
+
+fn open_vault_handle(handle: u32) -> Result<Rc<VaultHandle>, JsValue> {
+    OPEN_VAULTS
+        .with(|vaults| vaults.borrow().get(&handle).cloned())
+        .ok_or_else(|| JsValue::from_str(&format!("Unknown or closed vault handle {handle}")))
+}
+
+/// Re-reads the metadata cached by `handle`, e.g. after an identity was
+/// added to the vault elsewhere.
+#[wasm_bindgen]
+pub async fn vault_handle_refresh(handle: u32) -> Result<(), JsValue> {
+    let mut vault = VaultHandle::clone(&*open_vault_handle(handle)?);
+    vault.refresh().await?;
+
+    OPEN_VAULTS.with(|vaults| {
+        if let Some(open) = vaults.borrow_mut().get_mut(&handle) {
+            *open = Rc::new(vault);
+        }
+    });
+
+    Ok(())
+}
+
+/// Sets the retry policy of writes made through `handle`, overriding
+/// `configure_retry_policy` for this vault only.
+#[wasm_bindgen]
+pub fn vault_handle_configure_retry_policy(
+    handle: u32,
+    max_attempts: u32,
+    initial_delay_ms: u32,
+    max_delay_ms: u32,
+    jitter_ms: u32,
+) -> Result<(), JsValue> {
+    let policy = RetryPolicy::default()
+        .with_max_attempts(max_attempts)
+        .with_backoff(
+            initial_delay_ms,
+            max_delay_ms,
+            RetryPolicy::default().multiplier,
+        )
+        .with_jitter(jitter_ms);
+
+    OPEN_VAULTS.with(|vaults| {
+        let mut vaults = vaults.borrow_mut();
+        let vault = vaults.get_mut(&handle).ok_or_else(|| {
+            JsValue::from_str(&format!("Unknown or closed vault handle {handle}"))
+        })?;
+        Rc::make_mut(vault).set_retry_policy(policy);
+        Ok(())
+    })
+}
+
+#[wasm_bindgen]
+pub async fn vault_handle_read(
+    handle: u32,
+    identity: &IdentityHandle,
+    namespace: &str,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<JsValue, JsValue> {
+    let vault = open_vault_handle(handle)?;
+    let abort = converters::AbortBinding::new(signal);
+
+    let data_bytes = abort
+        .run(vault.read_namespace(&identity.private_key(), namespace))
+        .await?;
+
+    converters::bytes_to_js_value(&data_bytes)
+}
+
+#[wasm_bindgen]
+pub async fn vault_handle_upsert(
+    handle: u32,
+    identity: &IdentityHandle,
+    namespace: &str,
+    data: JsValue,
+    expires_in_seconds: Option<i64>,
+    replace_if_exists: bool,
+    signal: Option<web_sys::AbortSignal>,
+) -> Result<(), JsValue> {
+    let vault = open_vault_handle(handle)?;
+    let data_bytes = converters::js_value_to_bytes(data)?;
+    let abort = converters::AbortBinding::new(signal);
+
+    abortable_write(
+        vault.platform(),
+        vault.name(),
+        &abort,
+        vault.upsert_namespace(
+            &identity.public_key(),
+            namespace,
+            data_bytes,
+            expires_in_seconds,
+            replace_if_exists,
+            abort.token(),
+        ),
+    )
+    .await
+}
+
+#[wasm_bindgen]
+pub async fn vault_handle_remove(
+    handle: u32,
+    identity: &IdentityHandle,
+    namespace: &str,
+) -> Result<(), JsValue> {
+    let vault = open_vault_handle(handle)?;
+
+    vault.verify_identity(&identity.private_key()).await?;
+    vault
+        .remove_namespace(namespace)
+        .await
+        .map_err(|e| e.into())
+}
+
+#[wasm_bindgen]
+pub async fn vault_handle_list_namespaces(handle: u32) -> Result<JsValue, JsValue> {
+    let vault = open_vault_handle(handle)?;
+
+    let namespaces = vault
+        .list_namespaces()
+        .await
+        .map_err(converters::to_js_error)?;
+
+    converters::to_js_value(&namespaces)
+}
+
+// State shared by a `Vault` object and the `NamespaceRef`s it hands out, so
+// closing the vault invalidates them and its identity is used by all.
+#[derive(Default)]
+struct VaultState {
+    handle: RefCell<Option<Rc<VaultHandle>>>,
+    identity: RefCell<Option<IdentityHandle>>,
+}
+
+impl VaultState {
+    fn handle(&self) -> Result<Rc<VaultHandle>, JsValue> {
+        self.handle
+            .borrow()
+            .clone()
+            .ok_or_else(|| JsValue::from_str("Vault is closed"))
+    }
+
+    fn identity(&self) -> Result<IdentityHandle, JsValue> {
+        self.identity
+            .borrow()
+            .clone()
+            .ok_or_else(|| JsValue::from_str("Vault is locked; call unlock first"))
+    }
+}
+
+/// An opened vault. Holds the identity it was unlocked with for as long as
+/// the object lives, so calls on it and its namespaces take no identity.
+#[wasm_bindgen]
+pub struct Vault {
+    state: Rc<VaultState>,
+}
+
+#[wasm_bindgen]
+impl Vault {
+    pub async fn open(vault_name: &str) -> Result<Vault, JsValue> {
+        let handle = VaultHandle::open(Platform::new(), vault_name).await?;
+
+        let state = VaultState::default();
+        *state.handle.borrow_mut() = Some(Rc::new(handle));
+
+        Ok(Vault {
+            state: Rc::new(state),
+        })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> Result<String, JsValue> {
+        Ok(self.state.handle()?.name().to_string())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn is_unlocked(&self) -> bool {
+        self.state.identity.borrow().is_some()
+    }
+
+    /// Derives the vault identity from `passphrase` and keeps it on the
+    /// object.
+    pub async fn unlock(
+        &self,
+        passphrase: &str,
+        signal: Option<web_sys::AbortSignal>,
+    ) -> Result<(), JsValue> {
+        let mut handle = VaultHandle::clone(&*self.state.handle()?);
+
+        let identity = vault_identity_from_passphrase(passphrase, handle.name(), signal).await?;
+
+        // Deriving may have recorded a new identity in the metadata.
+        handle.refresh().await?;
+        *self.state.handle.borrow_mut() = Some(Rc::new(handle));
+        *self.state.identity.borrow_mut() = Some(identity);
+
+        Ok(())
+    }
+
+    /// Unlocks with an identity obtained elsewhere, e.g. from a WebAuthn
+    /// credential, after checking it belongs to the vault.
+    pub async fn unlock_with_identity(&self, identity: &IdentityHandle) -> Result<(), JsValue> {
+        self.state
+            .handle()?
+            .verify_identity(&identity.private_key())
+            .await?;
+
+        *self.state.identity.borrow_mut() = Some(identity.clone());
+
+        Ok(())
+    }
+
+    /// Forgets the identity; the vault stays open.
+    pub fn lock(&self) {
+        self.state.identity.borrow_mut().take();
+    }
+
+    pub fn namespace(&self, namespace: &str) -> Result<NamespaceRef, JsValue> {
+        validation::validate_namespace(namespace)?;
+
+        Ok(NamespaceRef {
+            state: self.state.clone(),
+            namespace: namespace.to_string(),
+        })
+    }
+
+    pub async fn list_namespaces(&self) -> Result<JsValue, JsValue> {
+        let namespaces = self
+            .state
+            .handle()?
+            .list_namespaces()
+            .await
+            .map_err(converters::to_js_error)?;
+
+        converters::to_js_value(&namespaces)
+    }
+
+    /// Sets the retry policy of writes made through this vault, overriding
+    /// `configure_retry_policy`.
+    pub fn configure_retry_policy(
+        &self,
+        max_attempts: u32,
+        initial_delay_ms: u32,
+        max_delay_ms: u32,
+        jitter_ms: u32,
+    ) -> Result<(), JsValue> {
+        let mut handle = VaultHandle::clone(&*self.state.handle()?);
+        handle.set_retry_policy(
+            RetryPolicy::default()
+                .with_max_attempts(max_attempts)
+                .with_backoff(
+                    initial_delay_ms,
+                    max_delay_ms,
+                    RetryPolicy::default().multiplier,
+                )
+                .with_jitter(jitter_ms),
+        );
+        *self.state.handle.borrow_mut() = Some(Rc::new(handle));
+
+        Ok(())
+    }
+
+    /// Forgets the identity and releases the vault. Namespaces obtained from
+    /// it fail from then on.
+    pub fn close(&self) {
+        self.state.identity.borrow_mut().take();
+        self.state.handle.borrow_mut().take();
+    }
+}
+
+/// A namespace of an opened [`Vault`], read and written with the vault's
+/// identity.
+#[wasm_bindgen]
+pub struct NamespaceRef {
+    state: Rc<VaultState>,
+    namespace: String,
+}
+
+#[wasm_bindgen]
+impl NamespaceRef {
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.namespace.clone()
+    }
+
+    pub async fn read(&self, signal: Option<web_sys::AbortSignal>) -> Result<JsValue, JsValue> {
+        let handle = self.state.handle()?;
+        let identity = self.state.identity()?;
+        let abort = converters::AbortBinding::new(signal);
+
+        let data_bytes = abort
+            .run(handle.read_namespace(&identity.private_key(), &self.namespace))
+            .await?;
+
+        converters::bytes_to_js_value(&data_bytes)
+    }
+
+    pub async fn write(
+        &self,
+        data: JsValue,
+        expires_in_seconds: Option<i64>,
+        replace_if_exists: bool,
+        signal: Option<web_sys::AbortSignal>,
+    ) -> Result<(), JsValue> {
+        let handle = self.state.handle()?;
+        let identity = self.state.identity()?;
+        let data_bytes = converters::js_value_to_bytes(data)?;
+        let abort = converters::AbortBinding::new(signal);
+
+        abortable_write(
+            handle.platform(),
+            handle.name(),
+            &abort,
+            handle.upsert_namespace(
+                &identity.public_key(),
+                &self.namespace,
+                data_bytes,
+                expires_in_seconds,
+                replace_if_exists,
+                abort.token(),
+            ),
+        )
+        .await
+    }
+
+    pub async fn remove(&self) -> Result<(), JsValue> {
+        let handle = self.state.handle()?;
+        let identity = self.state.identity()?;
+
+        handle.verify_identity(&identity.private_key()).await?;
+        handle
+            .remove_namespace(&self.namespace)
+            .await
+            .map_err(|e| e.into())
     }
 }
@@ -60,9 +60,7 @@ fn identity_from_prf_internal(
     let (first, second) = prf_outputs_from_js(prf_output)?;
 
     let identity_str = crypto::identity_from_prf(platform, &first, &second).map_err(|e| {
-        platform
-            .logger()
-            .log(&format!("Failed to derive identity from PRF: {}", e));
+        tracing::debug!("Failed to derive identity from PRF: {}", e);
         JsValue::from_str(&e.to_string())
     })?;
 
@@ -3,9 +3,10 @@ use super::crypto::IdentityHandle;
 use js_sys::Uint8Array;
 use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{AuthenticationExtensionsPrfValues, PublicKeyCredential};
-use webauthn::{webauthn_create, webauthn_get};
+use web_sys::{AbortSignal, AuthenticationExtensionsPrfValues, PublicKeyCredential};
+use webauthn::{webauthn_create, webauthn_get, webauthn_large_blob};
 
+use crate::domain::vault::{credentials, large_blob, validation, CredentialRekey};
 use crate::platform::Platform;
 use rand::rngs::OsRng;
 use rand::RngCore;
@@ -24,19 +25,17 @@ fn get_identity_from_vault() -> Result<[u8; 32], JsValue> {
 pub async fn create_credential(
     vault_name: &str,
     username: &str,
+    signal: Option<AbortSignal>,
 ) -> Result<IdentityHandle, JsValue> {
-    let platform = Platform::new();
-    create_credential_internal(&platform, vault_name, username).await
+    create_credential_internal(vault_name, username, signal.as_ref()).await
 }
 
 async fn create_credential_internal(
-    platform: &Platform,
     vault_name: &str,
     username: &str,
+    signal: Option<&AbortSignal>,
 ) -> Result<IdentityHandle, JsValue> {
-    platform
-        .logger()
-        .log(&"Init credential creation".to_string());
+    tracing::debug!("Init credential creation");
 
     let challenge = Uint8Array::from(gen_random().as_slice());
     let new_salt = get_identity_from_vault()?;
@@ -46,7 +45,7 @@ async fn create_credential_internal(
         .await
         .map_err(converters::to_js_error)?;
 
-    let credential = JsFuture::from(webauthn_create(&challenge, username, &salt_array)?)
+    let credential = JsFuture::from(webauthn_create(&challenge, username, &salt_array, signal)?)
         .await?
         .dyn_into::<PublicKeyCredential>()
         .map_err(|_| JsValue::from_str("Failed to get credential"))?;
@@ -80,6 +79,11 @@ async fn create_credential_internal(
     let public_key = identity.public_key();
 
     vault.identity_salts.set_salt(public_key.clone(), new_salt);
+    crate::domain::authentication::record_key_check(
+        &mut vault.identity_salts,
+        &public_key,
+        &identity.private_key(),
+    );
 
     let raw_id = js_sys::Uint8Array::new(&credential.raw_id());
     let mut cred_id = vec![0; raw_id.length() as usize];
@@ -87,6 +91,10 @@ async fn create_credential_internal(
     vault
         .identity_salts
         .set_credential_id(public_key.clone(), cred_id.clone());
+    vault.identity_salts.set_credential_created_at(
+        public_key.clone(),
+        (Platform::new().clock().now() / 1000.0) as i64,
+    );
 
     vault
         .username_pk
@@ -99,20 +107,22 @@ async fn create_credential_internal(
     Ok(identity)
 }
 
+/// Aborting `signal` dismisses the browser's authenticator prompt.
 #[wasm_bindgen]
-pub async fn get_credential(vault_name: &str, username: &str) -> Result<IdentityHandle, JsValue> {
-    let platform = Platform::new();
-    get_credential_internal(&platform, vault_name, username).await
+pub async fn get_credential(
+    vault_name: &str,
+    username: &str,
+    signal: Option<AbortSignal>,
+) -> Result<IdentityHandle, JsValue> {
+    get_credential_internal(vault_name, username, signal.as_ref()).await
 }
 
 async fn get_credential_internal(
-    platform: &Platform,
     vault_name: &str,
     username: &str,
+    signal: Option<&AbortSignal>,
 ) -> Result<IdentityHandle, JsValue> {
-    platform
-        .logger()
-        .log(&format!("Init credential get for username: {}", username));
+    tracing::debug!("Init credential get for username: {}", username);
 
     let challenge = Uint8Array::from(gen_random().as_slice());
 
@@ -124,10 +134,11 @@ async fn get_credential_internal(
         JsValue::from_str(&format!("No public key found for username: {}", username))
     })?;
 
-    platform.logger().log(&format!(
+    tracing::debug!(
         "Found public key for username: {}, {:?}",
-        username, public_key
-    ));
+        username,
+        public_key
+    );
 
     let credential_id = vault
         .identity_salts
@@ -143,15 +154,17 @@ async fn get_credential_internal(
         JsValue::from_str(&format!("No salt found for public key: {}", public_key))
     })?;
 
-    platform.logger().log(&format!(
+    tracing::debug!(
         "Found credential ID and salt for public key: {}, {:?}",
-        public_key, salt
-    ));
+        public_key,
+        salt
+    );
 
     let credential = JsFuture::from(webauthn_get(
         &challenge,
         &Uint8Array::from(salt.as_slice()),
         Uint8Array::from(credential_id.as_slice()),
+        signal,
     )?)
     .await?
     .dyn_into::<PublicKeyCredential>()?;
@@ -169,42 +182,28 @@ async fn get_credential_internal(
 
     let first = js_sys::Reflect::get(&results, &"first".into())
         .map_err(|_| JsValue::from_str("First PRF result not found"))?;
-    platform
-        .logger()
-        .log(&format!("First value before conversion: {:?}", first));
+    tracing::debug!("First value before conversion: {:?}", first);
     let first: js_sys::ArrayBuffer = first
         .dyn_into()
         .map_err(|_| JsValue::from_str("First PRF result is not an ArrayBuffer"))?;
     let first_array = Uint8Array::new(&first);
-    platform.logger().log(&format!(
-        "First ArrayBuffer length: {}",
-        first_array.length()
-    ));
+    tracing::debug!("First ArrayBuffer length: {}", first_array.length());
     if first_array.length() > 0 {
         let first_vec = first_array.to_vec();
-        platform
-            .logger()
-            .log(&format!("First ArrayBuffer contents: {:?}", first_vec));
+        tracing::debug!("First ArrayBuffer contents: {:?}", first_vec);
     }
 
     let second = js_sys::Reflect::get(&results, &"second".into())
         .ok()
         .and_then(|val| {
-            platform
-                .logger()
-                .log(&format!("Second value before conversion: {:?}", val));
+            tracing::debug!("Second value before conversion: {:?}", val);
             let second_buf = val.dyn_into::<js_sys::ArrayBuffer>();
             if let Ok(buf) = second_buf {
                 let second_array = Uint8Array::new(&buf);
-                platform.logger().log(&format!(
-                    "Second ArrayBuffer length: {}",
-                    second_array.length()
-                ));
+                tracing::debug!("Second ArrayBuffer length: {}", second_array.length());
                 if second_array.length() > 0 {
                     let second_vec = second_array.to_vec();
-                    platform
-                        .logger()
-                        .log(&format!("Second ArrayBuffer contents: {:?}", second_vec));
+                    tracing::debug!("Second ArrayBuffer contents: {:?}", second_vec);
                 }
                 Some(buf)
             } else {
@@ -217,9 +216,7 @@ async fn get_credential_internal(
         prf_values.set_second(&Uint8Array::new(&buf));
     }
 
-    platform
-        .logger()
-        .log(&"PRF outputs processed successfully".to_string());
+    tracing::debug!("PRF outputs processed successfully");
 
     let identity = identity_from_prf(&prf_values)?;
 
@@ -248,3 +245,179 @@ pub async fn list_webauthn_public_keys(vault_name: &str) -> Result<JsValue, JsVa
 
     Ok(serde_wasm_bindgen::to_value(&public_keys)?)
 }
+
+/// Credentials registered on the vault, with their username, public key
+/// and registration time in seconds when known.
+#[wasm_bindgen]
+pub async fn list_credentials(vault_name: &str) -> Result<JsValue, JsValue> {
+    let credentials = credentials::list_credentials(&Platform::new(), vault_name).await?;
+
+    converters::to_js_value(&credentials)
+}
+
+#[wasm_bindgen]
+pub async fn rename_credential(
+    vault_name: &str,
+    username: &str,
+    new_username: &str,
+) -> Result<(), JsValue> {
+    credentials::rename_credential(&Platform::new(), vault_name, username, new_username).await?;
+
+    Ok(())
+}
+
+/// Forgets the credential of `username`. Data stays encrypted to its
+/// identity unless `identity`, obtained with `get_credential` beforehand,
+/// and `new_passphrase` are passed: the vault is then rekeyed to the
+/// identity derived from the passphrase, which is returned.
+#[wasm_bindgen]
+pub async fn revoke_credential(
+    vault_name: &str,
+    username: &str,
+    identity: Option<IdentityHandle>,
+    new_passphrase: Option<String>,
+) -> Result<Option<IdentityHandle>, JsValue> {
+    let rekey = match (identity, new_passphrase) {
+        (Some(identity), Some(new_passphrase)) => {
+            validation::validate_passphrase(&new_passphrase).map_err(converters::to_js_error)?;
+            Some(CredentialRekey {
+                identity_private_key: identity.private_key(),
+                new_passphrase,
+            })
+        }
+        (None, None) => None,
+        _ => {
+            return Err(JsValue::from_str(
+                "Rekeying needs both the identity and a new passphrase",
+            ))
+        }
+    };
+
+    credentials::revoke_credential(&Platform::new(), vault_name, username, rekey.as_ref())
+        .await?
+        .map(converters::identity_keys_to_handle)
+        .transpose()
+}
+
+/// Stores the identity of `username` on its authenticator with the largeBlob
+/// extension, so `unlock_with_authenticator` can open the vault on a device
+/// that has never seen it. Takes two authenticator prompts: one to read the
+/// blob and derive the key it is encrypted with, one to write it back.
+#[wasm_bindgen]
+pub async fn store_keys_on_authenticator(
+    vault_name: &str,
+    username: &str,
+    identity: &IdentityHandle,
+    signal: Option<AbortSignal>,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    let entry =
+        large_blob::large_blob_entry(&platform, vault_name, username, &identity.private_key())
+            .await?;
+    let credential_id = hex::decode(&entry.credential_id)
+        .map_err(|_| JsValue::from_str("Malformed credential ID"))?;
+
+    let (wrapping_identity, stored) =
+        read_large_blob(Some(&credential_id), signal.as_ref()).await?;
+    let mut entries = large_blob::open_large_blob(&platform, &stored, &wrapping_identity).await?;
+    large_blob::upsert_large_blob_entry(&mut entries, entry);
+    let blob = large_blob::seal_large_blob(&platform, &entries, &wrapping_identity).await?;
+
+    let challenge = Uint8Array::from(gen_random().as_slice());
+    let credential = JsFuture::from(webauthn_large_blob(
+        &challenge,
+        Some(Uint8Array::from(credential_id.as_slice())),
+        Some(&Uint8Array::from(blob.as_slice())),
+        signal.as_ref(),
+    )?)
+    .await?
+    .dyn_into::<PublicKeyCredential>()?;
+
+    let written = js_sys::Reflect::get(
+        &credential.get_client_extension_results(),
+        &"largeBlob".into(),
+    )
+    .and_then(|large_blob| js_sys::Reflect::get(&large_blob, &"written".into()))
+    .ok()
+    .and_then(|written| written.as_bool());
+    if written != Some(true) {
+        return Err(JsValue::from_str(
+            "The authenticator did not store the keys",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Unlocks `vault_name` with nothing but a security key holding its keys
+/// from `store_keys_on_authenticator`. On a device without the vault, an
+/// empty one is created with the credential registered, for sync to fill.
+#[wasm_bindgen]
+pub async fn unlock_with_authenticator(
+    vault_name: &str,
+    signal: Option<AbortSignal>,
+) -> Result<IdentityHandle, JsValue> {
+    let platform = Platform::new();
+
+    let (wrapping_identity, stored) = read_large_blob(None, signal.as_ref()).await?;
+    let entry = large_blob::open_large_blob(&platform, &stored, &wrapping_identity)
+        .await?
+        .into_iter()
+        .find(|entry| entry.vault_name == vault_name)
+        .ok_or_else(|| {
+            JsValue::from_str(&format!(
+                "The authenticator holds no keys for vault {vault_name}"
+            ))
+        })?;
+
+    converters::identity_keys_to_handle(
+        large_blob::restore_from_large_blob(&platform, &entry).await?,
+    )
+}
+
+// Reads the largeBlob of the credential, returning the identity derived
+// from its PRF output along with the blob, empty when none was written.
+async fn read_large_blob(
+    credential_id: Option<&[u8]>,
+    signal: Option<&AbortSignal>,
+) -> Result<(String, Vec<u8>), JsValue> {
+    let challenge = Uint8Array::from(gen_random().as_slice());
+    let credential = JsFuture::from(webauthn_large_blob(
+        &challenge,
+        credential_id.map(Uint8Array::from),
+        None,
+        signal,
+    )?)
+    .await?
+    .dyn_into::<PublicKeyCredential>()?;
+
+    let extensions = credential.get_client_extension_results();
+
+    let results = js_sys::Reflect::get(&extensions, &"prf".into())
+        .and_then(|prf| js_sys::Reflect::get(&prf, &"results".into()))
+        .map_err(|_| JsValue::from_str("PRF results not found"))?;
+    let first: js_sys::ArrayBuffer = js_sys::Reflect::get(&results, &"first".into())?
+        .dyn_into()
+        .map_err(|_| JsValue::from_str("First PRF result is not an ArrayBuffer"))?;
+    let second: js_sys::ArrayBuffer = js_sys::Reflect::get(&results, &"second".into())?
+        .dyn_into()
+        .map_err(|_| JsValue::from_str("Second PRF result is not an ArrayBuffer"))?;
+
+    let prf_values = AuthenticationExtensionsPrfValues::new(&Uint8Array::new(&first));
+    prf_values.set_second(&Uint8Array::new(&second));
+    let wrapping_identity = identity_from_prf(&prf_values)?.private_key();
+
+    let large_blob = js_sys::Reflect::get(&extensions, &"largeBlob".into())?;
+    if large_blob.is_undefined() {
+        return Err(JsValue::from_str(
+            "The authenticator does not support largeBlob",
+        ));
+    }
+    let blob = js_sys::Reflect::get(&large_blob, &"blob".into())?
+        .dyn_into::<js_sys::ArrayBuffer>()
+        .map(|blob| Uint8Array::new(&blob).to_vec())
+        .unwrap_or_default();
+
+    Ok((wrapping_identity, blob))
+}
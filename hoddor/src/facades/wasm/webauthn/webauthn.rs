@@ -1,7 +1,7 @@
 use js_sys::{Array, Promise, Uint8Array};
 use wasm_bindgen::JsValue;
 use web_sys::{
-    AuthenticationExtensionsClientInputs, AuthenticationExtensionsPrfInputs,
+    AbortSignal, AuthenticationExtensionsClientInputs, AuthenticationExtensionsPrfInputs,
     AuthenticatorAttachment, AuthenticatorSelectionCriteria, CredentialCreationOptions,
     CredentialRequestOptions, PublicKeyCredentialCreationOptions, PublicKeyCredentialDescriptor,
     PublicKeyCredentialParameters, PublicKeyCredentialRequestOptions, PublicKeyCredentialRpEntity,
@@ -9,7 +9,8 @@ use web_sys::{
 };
 
 use super::prf_inputs;
-use crate::{global::window, platform::Platform};
+use crate::domain::vault::LARGE_BLOB_PRF_NONCE;
+use crate::global::window;
 use sha2::{Digest, Sha256};
 
 /// Secure algorithms recommendation:
@@ -24,18 +25,18 @@ pub fn webauthn_create(
     challenge: &Uint8Array,
     name: &str,
     prf_salt: &Uint8Array,
+    signal: Option<&AbortSignal>,
 ) -> Result<Promise, JsValue> {
-    let platform = Platform::new();
-    webauthn_create_internal(&platform, challenge, name, prf_salt)
+    webauthn_create_internal(challenge, name, prf_salt, signal)
 }
 
 fn webauthn_create_internal(
-    platform: &Platform,
     challenge: &Uint8Array,
     name: &str,
     prf_salt: &Uint8Array,
+    signal: Option<&AbortSignal>,
 ) -> Result<Promise, JsValue> {
-    platform.logger().log(&"Create webauthn".to_string());
+    tracing::debug!("Create webauthn");
 
     let pk_rp_entity = PublicKeyCredentialRpEntity::new(name);
 
@@ -58,15 +59,26 @@ fn webauthn_create_internal(
 
     let authenticator_selection = AuthenticatorSelectionCriteria::new();
     authenticator_selection.set_authenticator_attachment(AuthenticatorAttachment::CrossPlatform);
+    // largeBlob needs a discoverable credential, which also lets a new
+    // device find it without knowing its id.
+    js_sys::Reflect::set(
+        &authenticator_selection,
+        &"residentKey".into(),
+        &"preferred".into(),
+    )?;
     pk_options.set_authenticator_selection(&authenticator_selection);
 
     let extensions = prf_extension_eval(prf_salt)?;
+    set_large_blob(&extensions, "support", &"preferred".into())?;
     pk_options.set_extensions(&extensions);
 
     let cred_options = CredentialCreationOptions::new();
     cred_options.set_public_key(&pk_options);
+    if let Some(signal) = signal {
+        cred_options.set_signal(signal);
+    }
 
-    window()
+    window()?
         .navigator()
         .credentials()
         .create_with_options(&cred_options)
@@ -76,6 +88,7 @@ pub fn webauthn_get(
     challenge: &Uint8Array,
     prf_salt: &Uint8Array,
     credential_id: Uint8Array,
+    signal: Option<&AbortSignal>,
 ) -> Result<Promise, JsValue> {
     let opts_obj = js_sys::Object::new();
 
@@ -96,14 +109,80 @@ pub fn webauthn_get(
 
     let cred_options = CredentialRequestOptions::new();
     cred_options.set_public_key(&pk_options);
+    if let Some(signal) = signal {
+        cred_options.set_signal(signal);
+    }
 
-    window()
+    window()?
         .navigator()
         .credentials()
         .get_with_options(&cred_options)
         .map_err(|e| JsValue::from_str(&format!("WebAuthn error: {:?}", e)))
 }
 
+/// Reads the largeBlob of the credential, evaluating the PRF on
+/// [`LARGE_BLOB_PRF_NONCE`] in the same ceremony, or writes `blob` to it.
+/// Without `credential_id`, the authenticator offers its discoverable
+/// credentials.
+pub fn webauthn_large_blob(
+    challenge: &Uint8Array,
+    credential_id: Option<Uint8Array>,
+    blob: Option<&Uint8Array>,
+    signal: Option<&AbortSignal>,
+) -> Result<Promise, JsValue> {
+    let pk_options = PublicKeyCredentialRequestOptions::new(&js_sys::Object::new());
+    pk_options.set_challenge(challenge);
+
+    if let Some(credential_id) = credential_id {
+        let allow_creds = Array::new();
+        allow_creds.push(&PublicKeyCredentialDescriptor::new(
+            &credential_id,
+            PublicKeyCredentialType::PublicKey,
+        ));
+        pk_options.set_allow_credentials(&allow_creds);
+    }
+
+    let extensions = match blob {
+        Some(blob) => {
+            let extensions = AuthenticationExtensionsClientInputs::new();
+            set_large_blob(&extensions, "write", blob)?;
+            extensions
+        }
+        None => {
+            let extensions = prf_extension_eval(&Uint8Array::from(LARGE_BLOB_PRF_NONCE))?;
+            set_large_blob(&extensions, "read", &JsValue::TRUE)?;
+            extensions
+        }
+    };
+    pk_options.set_extensions(&extensions);
+
+    pk_options.set_user_verification(UserVerificationRequirement::Required);
+
+    let cred_options = CredentialRequestOptions::new();
+    cred_options.set_public_key(&pk_options);
+    if let Some(signal) = signal {
+        cred_options.set_signal(signal);
+    }
+
+    window()?
+        .navigator()
+        .credentials()
+        .get_with_options(&cred_options)
+        .map_err(|e| JsValue::from_str(&format!("WebAuthn error: {:?}", e)))
+}
+
+// The largeBlob inputs have no typed binding.
+fn set_large_blob(
+    extensions: &AuthenticationExtensionsClientInputs,
+    key: &str,
+    value: &JsValue,
+) -> Result<(), JsValue> {
+    let large_blob = js_sys::Object::new();
+    js_sys::Reflect::set(&large_blob, &key.into(), value)?;
+    js_sys::Reflect::set(extensions, &"largeBlob".into(), &large_blob)?;
+    Ok(())
+}
+
 pub fn prf_extension_eval(
     salt: &Uint8Array,
 ) -> Result<AuthenticationExtensionsClientInputs, JsValue> {
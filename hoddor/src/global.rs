@@ -1,24 +1,26 @@
 use crate::domain::vault::error::VaultError;
 use wasm_bindgen::prelude::JsValue;
 use wasm_bindgen::prelude::*;
-use web_sys::{self, DedicatedWorkerGlobalScope, StorageManager, Window, WorkerGlobalScope};
+use web_sys::{self, StorageManager, Window, WorkerGlobalScope};
 
 pub fn get_global_scope() -> Result<JsValue, VaultError> {
-    if let Ok(scope) = js_sys::global().dyn_into::<DedicatedWorkerGlobalScope>() {
+    // Any worker, service workers included, reaches OPFS and locks through
+    // its WorkerGlobalScope.
+    if let Ok(scope) = js_sys::global().dyn_into::<WorkerGlobalScope>() {
         return Ok(JsValue::from(scope));
     }
 
     let window = web_sys::window().ok_or(VaultError::io_error(
-        "Neither DedicatedWorkerGlobalScope nor Window found",
+        "Neither WorkerGlobalScope nor Window found",
     ))?;
     Ok(JsValue::from(window))
 }
 
-pub fn window() -> Window {
-    get_global_scope()
-        .expect("Unable to retrieve global scope")
+/// The page's window; fails in workers, which have none.
+pub fn window() -> Result<Window, VaultError> {
+    get_global_scope()?
         .dyn_into::<Window>()
-        .expect("Unable to retrieve window")
+        .map_err(|_| VaultError::io_error("Not running in a window"))
 }
 
 pub fn get_storage_manager() -> Result<StorageManager, VaultError> {
@@ -1,3 +1,18 @@
+// Library code must not abort the wasm instance: fallible paths return
+// errors, and broken invariants are caught by `debug_assert!` in debug
+// builds instead of panicking in release ones.
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::unreachable,
+        clippy::todo,
+        clippy::unimplemented
+    )
+)]
+
 extern crate console_error_panic_hook;
 
 pub mod adapters;
@@ -8,23 +23,38 @@ pub mod ports;
 
 pub mod notifications;
 
+#[cfg(target_arch = "wasm32")]
+pub mod context;
 #[cfg(target_arch = "wasm32")]
 pub mod global;
 #[cfg(target_arch = "wasm32")]
 pub mod measure;
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(target_arch = "wasm32", feature = "sync"))]
 pub mod signaling;
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(target_arch = "wasm32", feature = "sync"))]
 pub mod sync;
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(target_arch = "wasm32", feature = "sync"))]
 pub mod webrtc;
 
+// The top-level `crypto`, `vault` and `webauthn` modules of releases before
+// the hexagonal architecture now live in the facades; these keep their old
+// paths working.
 #[cfg(target_arch = "wasm32")]
-pub use facades::wasm::{crypto, webauthn};
+pub use facades::wasm::crypto;
+
+#[cfg(target_arch = "wasm32")]
+pub use facades::wasm::vault;
+
+#[cfg(all(target_arch = "wasm32", feature = "webauthn"))]
+pub use facades::wasm::webauthn;
 
 #[cfg(all(target_arch = "wasm32", feature = "graph"))]
 pub use facades::wasm::graph;
 
+// Exposed to JS as `initThreadPool(navigator.hardwareConcurrency)`.
+#[cfg(all(target_arch = "wasm32", feature = "parallel"))]
+pub use wasm_bindgen_rayon::init_thread_pool;
+
 pub use domain::vault::{IdentitySalts, NamespaceData, Vault, VaultMetadata};
 pub use platform::Platform;
 
@@ -34,7 +64,20 @@ use wasm_bindgen::prelude::*;
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(start)]
 pub fn start_app() -> Result<(), JsValue> {
-    #[cfg(feature = "console_error_panic_hook")]
-    console_error_panic_hook::set_once();
+    // The Argon2 worker only derives seeds, and must not recover vaults or
+    // listen to other tabs alongside the page that spawned it.
+    if adapters::wasm::worker_kdf::in_kdf_worker() {
+        return Ok(());
+    }
+    adapters::wasm::install_panic_hook();
+    adapters::wasm::init_console_tracing(tracing::level_filters::LevelFilter::INFO);
+    if let Err(e) = adapters::wasm::tab_channel::listen() {
+        tracing::warn!("Other tabs' writes will go unnoticed: {:?}", e);
+    }
+    wasm_bindgen_futures::spawn_local(async {
+        if let Err(e) = domain::vault::recover_all_vaults(&Platform::new()).await {
+            tracing::warn!("Failed to recover interrupted saves: {}", e);
+        }
+    });
     Ok(())
 }
@@ -1,9 +1,32 @@
 use serde::Serialize;
 
-#[derive(Serialize)]
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub enum EventType {
     VaultUpdate,
+    BackupCompleted,
+    SyncConflict,
+    UnlockFailuresExceeded,
+    /// Another process changed the vault files.
+    ExternalChange,
+    /// A namespace write that looks like it contains key material.
+    SecretDetected,
+    /// An expired namespace was removed; the detail is its name.
+    NamespaceExpired,
+}
+
+impl EventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventType::VaultUpdate => "vaultUpdate",
+            EventType::BackupCompleted => "backupCompleted",
+            EventType::SyncConflict => "syncConflict",
+            EventType::UnlockFailuresExceeded => "unlockFailuresExceeded",
+            EventType::ExternalChange => "externalChange",
+            EventType::SecretDetected => "secretDetected",
+            EventType::NamespaceExpired => "namespaceExpired",
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -11,3 +34,29 @@ pub struct Message<T> {
     pub event: EventType,
     pub data: T,
 }
+
+#[derive(Serialize, Debug, Clone)]
+pub struct VaultEvent {
+    pub vault_name: String,
+    pub detail: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_type_serializes_as_camel_case() {
+        for event in [
+            EventType::VaultUpdate,
+            EventType::BackupCompleted,
+            EventType::SyncConflict,
+            EventType::UnlockFailuresExceeded,
+        ] {
+            assert_eq!(
+                serde_json::to_string(&event).unwrap(),
+                format!("\"{}\"", event.as_str())
+            );
+        }
+    }
+}
@@ -1,47 +1,75 @@
 use crate::adapters::{
-    AgeEncryption, AgeIdentity, Argon2Kdf, Clock, ConsoleLogger, Locks, Notifier, Persistence, Prf,
-    Storage,
+    AgeEncryption, AgeIdentity, Clock, ConsoleLogger, ErrorReporter, InstrumentedStorage, Kdf,
+    Locks, Notifier, Persistence, Prf, RecordedNotifier, Storage,
 };
 use crate::ports::{
-    ClockPort, EncryptionPort, IdentityPort, KeyDerivationPort, LockPort, LoggerPort, NotifierPort,
-    PersistencePort, PrfPort, StoragePort,
+    ClockPort, EncryptionPort, ErrorReporterPort, IdentityPort, KeyDerivationPort, LockPort,
+    LoggerPort, NotifierPort, PersistencePort, PrfPort, StoragePort,
 };
 
+#[cfg(feature = "sync")]
+use crate::adapters::Transport;
+#[cfg(feature = "sync")]
+use crate::ports::TransportPort;
+
+#[cfg(all(feature = "test-mode", feature = "sync"))]
+use crate::adapters::shared::LoopbackTransport;
+#[cfg(feature = "test-mode")]
+use crate::adapters::shared::TestClock;
+#[cfg(feature = "test-mode")]
+use crate::domain::test_mode;
+
 #[cfg(feature = "graph")]
 use crate::adapters::Graph;
 #[cfg(feature = "graph")]
 use crate::ports::GraphPort;
 
+/// The storage of the platform: the target's storage adapter, with every
+/// operation recorded in [`crate::domain::io_stats`].
+pub type PlatformStorage = InstrumentedStorage<Storage>;
+
+/// The notifier of the platform: the target's notifier adapter, with every
+/// event kept in [`crate::domain::recent_events`].
+pub type PlatformNotifier = RecordedNotifier<Notifier>;
+
 #[cfg_attr(not(feature = "graph"), derive(Clone, Copy))]
 #[cfg_attr(feature = "graph", derive(Clone))]
 pub struct Platform {
-    clock: Clock,
+    clock: &'static dyn ClockPort,
     logger: ConsoleLogger,
+    error_reporter: ErrorReporter,
     locks: Locks,
-    notifier: Notifier,
+    notifier: PlatformNotifier,
     persistence: Persistence,
-    storage: Storage,
+    storage: PlatformStorage,
     encryption: AgeEncryption,
     identity: AgeIdentity,
-    kdf: Argon2Kdf,
+    kdf: Kdf,
     prf: Prf,
+    #[cfg(feature = "sync")]
+    transport: Transport,
     #[cfg(feature = "graph")]
     graph: Graph,
 }
 
 impl Platform {
     pub fn new() -> Self {
+        let clock = default_clock();
+
         Self {
-            clock: Clock::new(),
+            clock,
             logger: ConsoleLogger::new(),
+            error_reporter: ErrorReporter::new(),
             locks: Locks::new(),
-            notifier: Notifier::new(),
+            notifier: RecordedNotifier::new(Notifier::new(), clock),
             persistence: Persistence::new(),
-            storage: Storage::new(),
+            storage: InstrumentedStorage::new(Storage::new(), clock),
             encryption: AgeEncryption::new(),
             identity: AgeIdentity::new(),
-            kdf: Argon2Kdf::new(),
+            kdf: Kdf::new(),
             prf: Prf::new(),
+            #[cfg(feature = "sync")]
+            transport: Transport::new(),
             #[cfg(feature = "graph")]
             graph: Graph::default(),
         }
@@ -49,7 +77,7 @@ impl Platform {
 
     #[inline]
     pub fn clock(&self) -> &dyn ClockPort {
-        &self.clock
+        self.clock
     }
 
     #[inline]
@@ -57,6 +85,11 @@ impl Platform {
         &self.logger
     }
 
+    #[inline]
+    pub fn error_reporter(&self) -> &dyn ErrorReporterPort {
+        &self.error_reporter
+    }
+
     #[inline]
     pub fn locks(&self) -> &dyn LockPort {
         &self.locks
@@ -73,8 +106,8 @@ impl Platform {
     }
 
     #[inline]
-    pub fn storage_owned(&self) -> Storage {
-        self.storage.clone()
+    pub fn storage_owned(&self) -> PlatformStorage {
+        self.storage
     }
 
     #[inline]
@@ -102,6 +135,23 @@ impl Platform {
         &self.prf
     }
 
+    #[cfg(feature = "sync")]
+    #[inline]
+    pub fn transport(&self) -> &dyn TransportPort {
+        #[cfg(feature = "test-mode")]
+        if test_mode::is_test_mode() {
+            return &LoopbackTransport;
+        }
+
+        &self.transport
+    }
+
+    #[cfg(feature = "sync")]
+    #[inline]
+    pub fn transport_owned(&self) -> Transport {
+        self.transport
+    }
+
     #[cfg(feature = "graph")]
     #[inline]
     pub fn graph(&self) -> &dyn GraphPort {
@@ -113,6 +163,41 @@ impl Platform {
     pub fn graph_owned(&self) -> Graph {
         self.graph.clone()
     }
+
+    /// Same platform reading the time from `clock`, so tests can pin it and
+    /// peers can correct for skew.
+    pub fn with_clock(mut self, clock: &'static dyn ClockPort) -> Self {
+        self.clock = clock;
+        self.storage = self.storage.with_clock(clock);
+        self.notifier = self.notifier.with_clock(clock);
+        #[cfg(feature = "graph")]
+        {
+            self.graph = self.graph.with_clock(clock);
+        }
+        self
+    }
+
+    /// Same platform backed by `graph` instead of the shared graph database.
+    #[cfg(feature = "graph")]
+    pub fn with_graph(mut self, graph: Graph) -> Self {
+        self.graph = graph.with_clock(self.clock);
+        self
+    }
+}
+
+// The clock of test mode while it is on, see [`crate::domain::test_mode`].
+#[cfg(feature = "test-mode")]
+fn default_clock() -> &'static dyn ClockPort {
+    if test_mode::is_test_mode() {
+        &TestClock
+    } else {
+        &Clock
+    }
+}
+
+#[cfg(not(feature = "test-mode"))]
+fn default_clock() -> &'static dyn ClockPort {
+    &Clock
 }
 
 impl Default for Platform {
@@ -131,6 +216,8 @@ mod tests {
         platform.logger().log("test");
     }
 
+    // The platform is only `Copy` without the graph feature.
+    #[allow(clippy::clone_on_copy)]
     #[test]
     fn test_platform_clone() {
         let platform = Platform::new();
@@ -187,6 +274,13 @@ mod tests {
         let _prf = platform.prf();
     }
 
+    #[cfg(feature = "sync")]
+    #[test]
+    fn test_platform_transport_access() {
+        let platform = Platform::new();
+        assert!(!platform.transport().is_connected("unknown-session"));
+    }
+
     #[test]
     fn test_platform_prf_availability() {
         let platform = Platform::new();
@@ -1,5 +1,12 @@
+use async_trait::async_trait;
+
+#[async_trait(?Send)]
 pub trait ClockPort: Send + Sync {
+    /// Milliseconds since the Unix epoch.
     fn now(&self) -> f64;
 
     fn is_available(&self) -> bool;
+
+    /// Waits `milliseconds` without blocking the event loop where there is one.
+    async fn sleep(&self, milliseconds: u32);
 }
@@ -0,0 +1,8 @@
+use crate::domain::error_report::ErrorReport;
+
+/// Delivers error reports to whatever the embedding application registered.
+/// Reporting must never fail the operation being reported, so it is
+/// infallible and adapters drop reports they cannot deliver.
+pub trait ErrorReporterPort: Send + Sync {
+    fn report(&self, report: &ErrorReport);
+}
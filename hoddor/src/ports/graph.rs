@@ -1,6 +1,12 @@
-use crate::domain::graph::{GraphBackup, GraphNode, GraphResult, Id, SearchResult};
+use crate::domain::graph::{
+    GraphBackup, GraphNode, GraphResult, GraphStats, Id, NeighborFilter, NeighborNode, SearchQuery,
+    SearchResult,
+};
 use async_trait::async_trait;
 
+/// Graph storage behind every `graph-*` feature. Callers see the same
+/// behavior whichever backend is built in: each implementation runs the
+/// conformance checks of `adapters::shared::graph_conformance` in its tests.
 #[async_trait(?Send)]
 pub trait GraphPort {
     async fn create_node(
@@ -30,6 +36,44 @@ pub trait GraphPort {
         edge_id: Option<&Id>,
     ) -> GraphResult<Id>;
 
+    /// Nodes linked to `node_id` by the edges `filter` lets through,
+    /// heaviest edge first, then by neighbor id.
+    async fn get_neighbors(
+        &self,
+        vault_id: &str,
+        node_id: &Id,
+        filter: &NeighborFilter,
+    ) -> GraphResult<Vec<NeighborNode>>;
+
+    /// Folds `remove` into `keep`: its edges are moved onto `keep`, its
+    /// labels and accesses added to those of `keep`, and it is deleted.
+    /// Edges between the two, and edges `keep` already has with the same
+    /// end and type, are dropped. Fails with
+    /// [`GraphError::NodeNotFound`](crate::domain::graph::GraphError::NodeNotFound)
+    /// unless both nodes are in `vault_id`.
+    async fn merge_nodes(&self, vault_id: &str, keep: &Id, remove: &Id) -> GraphResult<()>;
+
+    /// Counts one access to each of `node_ids`, stamped with the current
+    /// time. Ids not in `vault_id` are skipped.
+    async fn record_access(&self, vault_id: &str, node_ids: &[Id]) -> GraphResult<()>;
+
+    /// Deletes the nodes of `vault_id` among `node_ids` with their edges and
+    /// returns how many there were.
+    async fn delete_nodes(&self, vault_id: &str, node_ids: &[Id]) -> GraphResult<usize>;
+
+    /// Replaces the embedding of a node, computed with model `version`.
+    async fn set_embedding(
+        &self,
+        vault_id: &str,
+        node_id: &Id,
+        embedding: Vec<f32>,
+        version: u32,
+    ) -> GraphResult<()>;
+
+    /// Reclaims the space left by deleted entries, where the backend keeps
+    /// any.
+    async fn compact(&self) -> GraphResult<()>;
+
     async fn vector_search_with_neighbors(
         &self,
         vault_id: &str,
@@ -39,6 +83,25 @@ pub trait GraphPort {
         include_neighbors: bool,
     ) -> GraphResult<Vec<SearchResult>>;
 
+    /// Returns the results of `query` ranked `offset..offset + limit`, so
+    /// callers can walk a large result set without holding all of it.
+    async fn vector_search_page(
+        &self,
+        vault_id: &str,
+        query: &SearchQuery,
+        offset: usize,
+        limit: usize,
+    ) -> GraphResult<Vec<SearchResult>>;
+
+    /// Nodes and edges come ordered by creation time, then id, so exporting
+    /// an unchanged graph twice yields the same backup.
     async fn export_backup(&self, vault_id: &str) -> GraphResult<GraphBackup>;
     async fn import_backup(&self, backup: &GraphBackup) -> GraphResult<()>;
+
+    async fn stats(&self) -> GraphResult<GraphStats>;
+
+    /// Drops every graph held by the database and starts over with an empty
+    /// one. Meant as an escape hatch when the database is left unusable, so
+    /// implementations must not depend on its current state to succeed.
+    async fn reset(&self) -> GraphResult<()>;
 }
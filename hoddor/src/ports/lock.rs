@@ -6,4 +6,8 @@ pub trait LockGuard {}
 #[async_trait(?Send)]
 pub trait LockPort: Send + Sync {
     async fn acquire(&self, name: &str) -> Result<Box<dyn LockGuard>, VaultError>;
+
+    /// Acquires `name` alongside other shared holders; only excludes
+    /// [`acquire`](LockPort::acquire).
+    async fn acquire_shared(&self, name: &str) -> Result<Box<dyn LockGuard>, VaultError>;
 }
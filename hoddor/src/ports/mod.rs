@@ -1,21 +1,25 @@
 pub mod clock;
 pub mod crypto;
+pub mod error_reporter;
 pub mod lock;
 pub mod logger;
 pub mod notifier;
 pub mod persistence;
 pub mod storage;
+pub mod transport;
 
 #[cfg(feature = "graph")]
 pub mod graph;
 
 pub use clock::ClockPort;
 pub use crypto::{EncryptionPort, IdentityPort, KeyDerivationPort, PrfPort};
+pub use error_reporter::ErrorReporterPort;
 pub use lock::{LockGuard, LockPort};
 pub use logger::LoggerPort;
 pub use notifier::NotifierPort;
 pub use persistence::PersistencePort;
 pub use storage::StoragePort;
+pub use transport::TransportPort;
 
 #[cfg(feature = "graph")]
 pub use graph::GraphPort;
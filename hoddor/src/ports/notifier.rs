@@ -1,3 +1,9 @@
+use crate::notifications::EventType;
+
 pub trait NotifierPort: Send + Sync {
     fn notify_vault_update(&self, vault_name: &str, vault_data: &[u8]) -> Result<(), String>;
+
+    /// Called from vault operations, so implementations must not block on
+    /// delivery; slow channels are queued, as the native notifier does.
+    fn notify_event(&self, vault_name: &str, event: EventType, detail: &str) -> Result<(), String>;
 }
@@ -5,6 +5,17 @@ use async_trait::async_trait;
 pub trait StoragePort: Send + Sync {
     async fn read_file(&self, path: &str) -> Result<String, VaultError>;
 
+    /// Reads several files, returning their results in the order of `paths`.
+    /// Adapters for which each read is a round trip override this to issue
+    /// the reads concurrently.
+    async fn read_files(&self, paths: &[String]) -> Vec<Result<String, VaultError>> {
+        let mut contents = Vec::with_capacity(paths.len());
+        for path in paths {
+            contents.push(self.read_file(path).await);
+        }
+        contents
+    }
+
     async fn write_file(&self, path: &str, content: &str) -> Result<(), VaultError>;
 
     async fn delete_file(&self, path: &str) -> Result<(), VaultError>;
@@ -16,4 +27,29 @@ pub trait StoragePort: Send + Sync {
     async fn directory_exists(&self, path: &str) -> Result<bool, VaultError>;
 
     async fn list_entries(&self, path: &str) -> Result<Vec<String>, VaultError>;
+
+    /// Moves the directory `from` to `to`, which must not exist. Adapters
+    /// that can rename in one step override this; the default copies every
+    /// file, then deletes `from`, leaving a partial `to` if a copy fails.
+    async fn rename_directory(&self, from: &str, to: &str) -> Result<(), VaultError> {
+        let mut directories = vec![(from.to_string(), to.to_string())];
+
+        while let Some((from, to)) = directories.pop() {
+            self.create_directory(&to).await?;
+
+            for entry in self.list_entries(&from).await? {
+                let source = format!("{from}/{entry}");
+                let target = format!("{to}/{entry}");
+
+                if self.directory_exists(&source).await? {
+                    directories.push((source, target));
+                } else {
+                    let content = self.read_file(&source).await?;
+                    self.write_file(&target, &content).await?;
+                }
+            }
+        }
+
+        self.delete_directory(from).await
+    }
 }
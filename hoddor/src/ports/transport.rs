@@ -0,0 +1,25 @@
+use async_trait::async_trait;
+use std::error::Error;
+
+/// Carries opaque sync payloads between two paired devices.
+///
+/// Pairing is a three step handshake: the initiator creates an offer, the
+/// other device accepts it and produces an answer, and the initiator accepts
+/// that answer. How offers and answers travel is up to the caller.
+#[async_trait(?Send)]
+pub trait TransportPort: Send + Sync {
+    async fn create_offer(&self, session: &str) -> Result<String, Box<dyn Error>>;
+
+    async fn accept_offer(&self, session: &str, offer: &str) -> Result<String, Box<dyn Error>>;
+
+    async fn accept_answer(&self, session: &str, answer: &str) -> Result<(), Box<dyn Error>>;
+
+    async fn send(&self, session: &str, data: &[u8]) -> Result<(), Box<dyn Error>>;
+
+    /// Waits for the next payload. Returns `None` once the session is closed.
+    async fn receive(&self, session: &str) -> Result<Option<Vec<u8>>, Box<dyn Error>>;
+
+    fn is_connected(&self, session: &str) -> bool;
+
+    fn close(&self, session: &str);
+}
@@ -1,3 +1,5 @@
+use crate::domain::retry::{CancellationToken, RetryPolicy};
+use crate::domain::trace_context::TraceContext;
 use crate::platform::Platform;
 use futures_channel::mpsc;
 use futures_channel::mpsc::{UnboundedReceiver, UnboundedSender};
@@ -18,16 +20,24 @@ pub enum SignalingMessage {
         from: String,
         to: String,
         sdp: String,
+        /// W3C `traceparent` of the exchange, continued by the signaling
+        /// server and recorded by the receiving peer.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        traceparent: Option<String>,
     },
     Answer {
         from: String,
         to: String,
         sdp: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        traceparent: Option<String>,
     },
     IceCandidate {
         from: String,
         to: String,
         candidate: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        traceparent: Option<String>,
     },
     Leave {
         peer_id: String,
@@ -38,7 +48,6 @@ pub enum SignalingMessage {
 }
 
 pub struct SignalingClient {
-    platform: Platform,
     ws: WebSocket,
     peer_id: String,
     #[allow(dead_code)]
@@ -53,13 +62,11 @@ impl SignalingClient {
             from: self.peer_id.clone(),
             to: to.clone(),
             sdp,
+            traceparent: Some(TraceContext::new_root().to_traceparent()),
         };
         let msg_str = serde_json::to_string(&offer_msg)
             .map_err(|e| JsValue::from_str(&format!("Failed to serialize message: {}", e)))?;
-        self.platform.logger().log(&format!(
-            "Sending offer from {} to {}: {}",
-            self.peer_id, to, msg_str
-        ));
+        tracing::debug!("Sending offer from {} to {}: {}", self.peer_id, to, msg_str);
         self.ws.send_with_str(&msg_str)?;
         Ok(())
     }
@@ -69,13 +76,16 @@ impl SignalingClient {
             from: self.peer_id.clone(),
             to: to.clone(),
             sdp,
+            traceparent: Some(TraceContext::new_root().to_traceparent()),
         };
         let msg_str = serde_json::to_string(&answer_msg)
             .map_err(|e| JsValue::from_str(&format!("Failed to serialize message: {}", e)))?;
-        self.platform.logger().log(&format!(
+        tracing::debug!(
             "Sending answer from {} to {}: {}",
-            self.peer_id, to, msg_str
-        ));
+            self.peer_id,
+            to,
+            msg_str
+        );
         self.ws.send_with_str(&msg_str)?;
         Ok(())
     }
@@ -85,27 +95,27 @@ impl SignalingClient {
             from: self.peer_id.clone(),
             to: to.clone(),
             candidate,
+            traceparent: Some(TraceContext::new_root().to_traceparent()),
         };
         let msg_str = serde_json::to_string(&ice_msg)
             .map_err(|e| JsValue::from_str(&format!("Failed to serialize message: {}", e)))?;
-        self.platform.logger().log(&format!(
+        tracing::debug!(
             "Sending ICE candidate from {} to {}: {}",
-            self.peer_id, to, msg_str
-        ));
+            self.peer_id,
+            to,
+            msg_str
+        );
         self.ws.send_with_str(&msg_str)?;
         Ok(())
     }
 
     pub fn set_message_handler(&mut self, sender: UnboundedSender<SignalingMessage>) {
         let peer_id = self.peer_id.clone();
-        let platform = self.platform.clone();
 
         let onmessage_callback = Closure::wrap(Box::new(move |e: MessageEvent| {
             if let Ok(text) = e.data().dyn_into::<js_sys::JsString>() {
                 let text_str = String::from(text);
-                platform
-                    .logger()
-                    .log(&format!("Received message: {}", text_str));
+                tracing::debug!("Received message: {}", text_str);
 
                 match serde_json::from_str::<SignalingMessage>(&text_str) {
                     Ok(msg) => {
@@ -120,31 +130,25 @@ impl SignalingClient {
                         };
 
                         if is_for_us {
-                            platform
-                                .logger()
-                                .log(&format!("Processing message for {}: {:?}", peer_id, msg));
+                            tracing::debug!("Processing message for {}: {:?}", peer_id, msg);
                             match sender.unbounded_send(msg) {
                                 Ok(_) => (),
                                 Err(e) => {
                                     if e.is_disconnected() {
-                                        platform.logger().log(&format!(
+                                        tracing::debug!(
                                             "Message channel disconnected for {}, ignoring message",
                                             peer_id
-                                        ));
+                                        );
                                     } else {
-                                        platform
-                                            .logger()
-                                            .error(&format!("Failed to forward message: {:?}", e));
+                                        tracing::error!("Failed to forward message: {:?}", e);
                                     }
                                 }
                             }
                         } else {
-                            platform.logger().log("Message not for us, ignoring");
+                            tracing::debug!("Message not for us, ignoring");
                         }
                     }
-                    Err(e) => platform
-                        .logger()
-                        .error(&format!("Failed to parse message: {:?}", e)),
+                    Err(e) => tracing::error!("Failed to parse message: {:?}", e),
                 }
             }
         }) as Box<dyn FnMut(MessageEvent)>);
@@ -175,40 +179,28 @@ impl SignalingClient {
     }
 
     pub fn new(server_url: &str, peer_id: String) -> Result<Rc<RefCell<Self>>, JsValue> {
-        let platform = Platform::new();
-        platform.logger().log(&format!(
-            "Creating new WebSocket connection to {}",
-            server_url
-        ));
+        tracing::debug!("Creating new WebSocket connection to {}", server_url);
         let ws = WebSocket::new(server_url)?;
 
         // Set up error handler with more detailed logging
-        let platform_for_error = platform.clone();
         let onerror_callback = Closure::wrap(Box::new(move |e: ErrorEvent| {
-            platform_for_error
-                .logger()
-                .error(&format!("WebSocket error: {:?}", e));
+            tracing::error!("WebSocket error: {:?}", e);
             // Try to log more error details if available
             if let Ok(err_details) = js_sys::Reflect::get(&e, &"error".into()) {
-                platform_for_error
-                    .logger()
-                    .error(&format!("Error details: {:?}", err_details));
+                tracing::error!("Error details: {:?}", err_details);
             }
         }) as Box<dyn FnMut(ErrorEvent)>)
         .into_js_value();
 
         ws.set_onerror(Some(onerror_callback.unchecked_ref()));
 
-        platform
-            .logger()
-            .log(&format!("WebSocket setup complete for peer {}", peer_id));
+        tracing::debug!("WebSocket setup complete for peer {}", peer_id);
 
         let empty_callback =
             Closure::wrap(Box::new(move |_: MessageEvent| {}) as Box<dyn FnMut(MessageEvent)>)
                 .into_js_value();
 
         Ok(Rc::new(RefCell::new(Self {
-            platform,
             ws,
             peer_id,
             onmessage_callback: empty_callback.unchecked_into(),
@@ -218,7 +210,6 @@ impl SignalingClient {
 }
 
 pub struct SignalingManager {
-    platform: Platform,
     clients: RefCell<Vec<Rc<RefCell<SignalingClient>>>>,
 }
 
@@ -231,11 +222,17 @@ impl Default for SignalingManager {
 impl SignalingManager {
     pub fn new() -> Self {
         SignalingManager {
-            platform: Platform::new(),
             clients: RefCell::new(Vec::new()),
         }
     }
 
+    /// Closes every socket and forgets the clients.
+    pub fn close_all(&self) {
+        for client in self.clients.borrow_mut().drain(..) {
+            let _ = client.borrow().get_websocket().close();
+        }
+    }
+
     pub fn cleanup_client(&self, peer_id: &str) {
         let mut clients = self.clients.borrow_mut();
         clients.retain(|client| client.borrow().peer_id != peer_id);
@@ -245,15 +242,14 @@ impl SignalingManager {
         let clients = self.clients.borrow();
         if let Some(client) = clients.first() {
             let client = client.borrow();
-            self.platform.logger().log(&format!(
+            tracing::debug!(
                 "SignalingManager: Sending offer from {} to {}",
-                client.peer_id, to_peer_id
-            ));
+                client.peer_id,
+                to_peer_id
+            );
             client.send_offer(to_peer_id, sdp)?;
         } else {
-            self.platform
-                .logger()
-                .error("No local client found to send offer");
+            tracing::error!("No local client found to send offer");
         }
         Ok(())
     }
@@ -262,15 +258,14 @@ impl SignalingManager {
         let clients = self.clients.borrow();
         if let Some(client) = clients.first() {
             let client = client.borrow();
-            self.platform.logger().log(&format!(
+            tracing::debug!(
                 "SignalingManager: Sending answer from {} to {}",
-                client.peer_id, to_peer_id
-            ));
+                client.peer_id,
+                to_peer_id
+            );
             client.send_answer(to_peer_id, sdp)?;
         } else {
-            self.platform
-                .logger()
-                .error("No local client found to send answer");
+            tracing::error!("No local client found to send answer");
         }
         Ok(())
     }
@@ -279,15 +274,14 @@ impl SignalingManager {
         let clients = self.clients.borrow();
         if let Some(client) = clients.first() {
             let client = client.borrow();
-            self.platform.logger().log(&format!(
+            tracing::debug!(
                 "SignalingManager: Sending ICE candidate from {} to {}",
-                client.peer_id, to_peer_id
-            ));
+                client.peer_id,
+                to_peer_id
+            );
             client.send_ice_candidate(to_peer_id, candidate)?;
         } else {
-            self.platform
-                .logger()
-                .error("No local client found to send ICE candidate");
+            tracing::error!("No local client found to send ICE candidate");
         }
         Ok(())
     }
@@ -316,14 +310,9 @@ impl SignalingManager {
                         peer_id: peer_id.clone(),
                     };
                     if let Ok(msg_str) = serde_json::to_string(&join_msg) {
-                        self.platform.logger().log(&format!(
-                            "Sending join message on existing connection: {}",
-                            msg_str
-                        ));
+                        tracing::debug!("Sending join message on existing connection: {}", msg_str);
                         if let Err(e) = client_ref.get_websocket().send_with_str(&msg_str) {
-                            self.platform
-                                .logger()
-                                .error(&format!("Failed to send join message: {:?}", e));
+                            tracing::error!("Failed to send join message: {:?}", e);
                         }
                     }
                 }
@@ -340,21 +329,42 @@ impl SignalingManager {
         }
 
         self.clients.borrow_mut().push(client);
-        self.platform
-            .logger()
-            .log(&format!("Added new signaling client for peer {}", peer_id));
+        tracing::debug!("Added new signaling client for peer {}", peer_id);
 
         Ok(receiver)
     }
 }
 
-thread_local! {
-    static SIGNALING_MANAGER: RefCell<SignalingManager> = RefCell::new(SignalingManager::new());
-}
-
+/// Runs `f` with the signaling manager of the default context.
 pub fn with_signaling_manager<F, R>(f: F) -> R
 where
     F: FnOnce(&SignalingManager) -> R,
 {
-    SIGNALING_MANAGER.with(|manager| f(&manager.borrow()))
+    f(crate::context::default_context().signaling())
+}
+
+/// Waits until the signaling socket `signaling` holds for `peer_id` is open,
+/// polling according to `policy`.
+pub async fn wait_until_open(
+    signaling: &SignalingManager,
+    peer_id: &str,
+    policy: &RetryPolicy,
+    cancellation: &CancellationToken,
+) -> Result<(), JsValue> {
+    let platform = Platform::new();
+
+    policy
+        .run(&platform, cancellation, |_| async {
+            let open = signaling.get_client(peer_id).is_some_and(|client| {
+                client.borrow().get_websocket().ready_state() == WebSocket::OPEN
+            });
+
+            if open {
+                Ok(())
+            } else {
+                Err(JsValue::from_str("Signaling socket is not open"))
+            }
+        })
+        .await
+        .map_err(JsValue::from)
 }
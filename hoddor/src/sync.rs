@@ -1,11 +1,16 @@
-use crate::domain::vault::{IdentitySalts, VaultMetadata};
+use crate::domain::clock_skew::ClockSkew;
+use crate::domain::vault::{
+    Compression, CrdtKind, Expiration, IdentitySalts, IntegrityTag, TimeLock, VaultMetadata,
+};
+use js_sys::Function;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use wasm_bindgen::JsValue;
 
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use crate::context::default_context;
 use crate::platform::Platform;
 use crate::webrtc::{AccessLevel, WebRtcPeer};
 
@@ -17,6 +22,30 @@ pub struct VaultOperation {
     pub nonce: Option<[u8; 12]>,
     pub timestamp: u64,
     pub author: String,
+    /// Expiration on the author's clock.
+    #[serde(default)]
+    pub expiration: Option<Expiration>,
+    #[serde(default)]
+    pub timelock: Option<TimeLock>,
+    /// Residency tags of the namespace, applied on the receiving side.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub residency: BTreeSet<String>,
+    /// Recipients granted access to the namespace alone.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recipients: Vec<String>,
+    /// Set when the namespace holds a CRDT value, which the receiving side
+    /// merges with its own instead of replacing it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crdt: Option<CrdtKind>,
+    /// Integrity MAC of the namespace plaintext, kept by the receiving side.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<IntegrityTag>,
+    /// Version of the algorithms the payload is encrypted with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crypto_version: Option<u32>,
+    /// Compression of the payload, undone after decryption.
+    #[serde(default, skip_serializing_if = "Compression::is_none")]
+    pub compression: Compression,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -29,19 +58,52 @@ pub enum OperationType {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SyncMessage {
     pub operation: VaultOperation,
-    pub vector_clock: HashMap<String, u64>,
+    pub vector_clock: BTreeMap<String, u64>,
     pub vault_name: String,
     pub vault_metadata: Option<VaultMetadata>,
     pub identity_salts: Option<IdentitySalts>,
-    pub username_pk: Option<HashMap<String, String>>,
+    pub username_pk: Option<BTreeMap<String, String>>,
+    /// Milliseconds since the Unix epoch on the sender's clock, used to
+    /// estimate its skew. Zero from peers that predate it.
+    #[serde(default)]
+    pub sent_at: u64,
+    /// W3C `traceparent` of the push this message belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traceparent: Option<String>,
+}
+
+/// Ephemeral application payload exchanged over the sync data channel.
+/// Never persisted to the vault.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppMessage {
+    pub vault_name: String,
+    pub namespace: String,
+    pub author: String,
+    pub payload: Vec<u8>,
+    pub timestamp: u64,
+}
+
+/// First message on a pairing session: the residency attributes the sender
+/// satisfies. Tagged namespaces are withheld from peers not asserting them.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PeerHello {
+    pub residency_attributes: BTreeSet<String>,
+    /// Set on the answer to a hello, which must not be answered again.
+    #[serde(default)]
+    pub reply: bool,
 }
 
 pub struct SyncManager {
     platform: Platform,
     pub peer_id: String,
-    pub vector_clock: HashMap<String, u64>,
+    pub vector_clock: BTreeMap<String, u64>,
     pub peers: HashMap<String, Rc<RefCell<WebRtcPeer>>>,
     pub pending_operations: Vec<VaultOperation>,
+    /// Residency attributes asserted to paired peers.
+    pub residency_attributes: BTreeSet<String>,
+    /// Attributes asserted by the paired peer, once its hello arrived.
+    pub paired_attributes: Option<BTreeSet<String>>,
+    clock_skews: HashMap<String, ClockSkew>,
 }
 
 impl SyncManager {
@@ -49,9 +111,12 @@ impl SyncManager {
         Self {
             platform: Platform::new(),
             peer_id: peer_id.clone(),
-            vector_clock: HashMap::from([(peer_id, 0)]),
+            vector_clock: BTreeMap::from([(peer_id, 0)]),
             peers: HashMap::new(),
             pending_operations: Vec::new(),
+            residency_attributes: BTreeSet::new(),
+            paired_attributes: None,
+            clock_skews: HashMap::new(),
         }
     }
 
@@ -59,19 +124,15 @@ impl SyncManager {
         let peer_id = if let Some(remote_id) = peer.borrow().remote_peer_id() {
             remote_id
         } else {
-            self.platform
-                .logger()
-                .error("No remote peer ID found, skipping peer addition");
+            tracing::error!("No remote peer ID found, skipping peer addition");
             return;
         };
-        self.platform
-            .logger()
-            .log(&format!("Adding peer {} to sync manager", peer_id));
+        tracing::debug!("Adding peer {} to sync manager", peer_id);
         self.peers.insert(peer_id.clone(), peer);
-        self.platform.logger().log(&format!(
+        tracing::debug!(
             "Current peers in sync manager: {:?}",
             self.peers.keys().collect::<Vec<_>>()
-        ));
+        );
     }
 
     pub fn create_operation(
@@ -80,6 +141,7 @@ impl SyncManager {
         operation_type: OperationType,
         data: Option<Vec<u8>>,
         nonce: Option<[u8; 12]>,
+        expiration: Option<Expiration>,
     ) -> VaultOperation {
         VaultOperation {
             namespace,
@@ -88,9 +150,23 @@ impl SyncManager {
             nonce,
             timestamp: (self.platform.clock().now() / 1000.0) as u64,
             author: self.peer_id.clone(),
+            expiration,
+            timelock: None,
+            residency: BTreeSet::new(),
+            recipients: Vec::new(),
+            crdt: None,
+            integrity: None,
+            crypto_version: None,
+            compression: Compression::None,
         }
     }
 
+    /// Whether the sync policy of the vault lets `namespace` be sent to
+    /// peers and updates to it be accepted from them.
+    pub fn shares(&self, metadata: &VaultMetadata, namespace: &str) -> bool {
+        metadata.sync_policy.allows(namespace)
+    }
+
     pub fn can_apply_operation(&self, operation: &VaultOperation, peer: &WebRtcPeer) -> bool {
         match operation.operation_type {
             OperationType::Insert | OperationType::Update => {
@@ -120,13 +196,22 @@ impl SyncManager {
         merged
     }
 
+    /// Bytes of payload held by operations not yet sent.
+    pub fn pending_bytes(&self) -> usize {
+        self.pending_operations
+            .iter()
+            .filter_map(|operation| operation.data.as_ref())
+            .map(Vec::len)
+            .sum()
+    }
+
     pub fn create_sync_message(
         &mut self,
         vault_name: String,
         operation: VaultOperation,
         vault_metadata: Option<VaultMetadata>,
         identity_salts: Option<IdentitySalts>,
-        username_pk: Option<HashMap<String, String>>,
+        username_pk: Option<BTreeMap<String, String>>,
     ) -> SyncMessage {
         SyncMessage {
             operation,
@@ -135,6 +220,74 @@ impl SyncManager {
             vault_metadata,
             identity_salts,
             username_pk,
+            sent_at: self.platform.clock().now() as u64,
+            traceparent: None,
+        }
+    }
+
+    /// Refines the clock skew of `message`'s author with its send time and
+    /// returns the current estimate.
+    pub fn observe_clock(&mut self, message: &SyncMessage) -> ClockSkew {
+        let skew = self
+            .clock_skews
+            .entry(message.operation.author.clone())
+            .or_default();
+
+        if message.sent_at > 0 {
+            skew.observe(message.sent_at, self.platform.clock().now() as u64);
+        }
+
+        *skew
+    }
+
+    // App messages follow the sync rules: peers must be able to read a
+    // namespace to receive its messages and to write it to send them.
+    pub fn can_send_app_message(&self, namespace: &str, peer: &WebRtcPeer) -> bool {
+        peer.has_permission(namespace, AccessLevel::Viewer)
+    }
+
+    pub fn can_receive_app_message(&self, message: &AppMessage, peer: &WebRtcPeer) -> bool {
+        peer.has_permission(&message.namespace, AccessLevel::Contributor)
+    }
+
+    pub fn send_app_message(
+        &self,
+        vault_name: &str,
+        namespace: &str,
+        payload: Vec<u8>,
+    ) -> Result<u32, JsValue> {
+        let mut sent = 0;
+
+        for peer in self.peers.values() {
+            let peer = peer.borrow();
+
+            if !peer.is_connected() || !self.can_send_app_message(namespace, &peer) {
+                continue;
+            }
+
+            let message = AppMessage {
+                vault_name: vault_name.to_string(),
+                namespace: namespace.to_string(),
+                author: peer.metadata().peer_id.clone(),
+                payload: payload.clone(),
+                timestamp: self.platform.clock().now() as u64,
+            };
+
+            let bytes = serde_json::to_vec(&message).map_err(|e| {
+                JsValue::from_str(&format!("Failed to serialize app message: {}", e))
+            })?;
+
+            peer.send_message(bytes)?;
+            sent += 1;
+        }
+
+        Ok(sent)
+    }
+
+    pub fn hello(&self, reply: bool) -> PeerHello {
+        PeerHello {
+            residency_attributes: self.residency_attributes.clone(),
+            reply,
         }
     }
 
@@ -143,32 +296,15 @@ impl SyncManager {
     }
 }
 
-// ----------------------------------------------------
-// Global single-threadesd storage of all SyncManagers.
-//
-// We CANNOT use `lazy_static!` with a `RefCell<...>` that is not `Sync`.
-// Instead, we can use `thread_local!` or `once_cell::unsync::Lazy`.
-//
-// For simplicity, well show `thread_local!` here:
-
-thread_local! {
-    static SYNC_MANAGERS: RefCell<HashMap<String, Rc<RefCell<SyncManager>>>>
-        = RefCell::new(HashMap::new());
-}
-
+/// Sync manager of `vault_name` in the default context.
 pub fn get_sync_manager(vault_name: &str) -> Result<Rc<RefCell<SyncManager>>, JsValue> {
-    let result = SYNC_MANAGERS.with(|cell| {
-        let mut managers = cell.borrow_mut();
-
-        if !managers.contains_key(vault_name) {
-            managers.insert(
-                vault_name.to_string(),
-                Rc::new(RefCell::new(SyncManager::new(vault_name.to_string()))),
-            );
-        }
+    Ok(default_context().sync_manager(vault_name))
+}
 
-        managers.get(vault_name).cloned()
-    });
+pub fn set_app_message_handler(vault_name: &str, handler: Option<Function>) {
+    default_context().set_app_message_handler(vault_name, handler);
+}
 
-    result.ok_or_else(|| JsValue::from_str("Failed to retrieve SyncManager"))
+pub fn dispatch_app_message(message: AppMessage, from: &str) -> Result<(), JsValue> {
+    default_context().dispatch_app_message(message, from)
 }
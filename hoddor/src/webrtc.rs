@@ -1,8 +1,15 @@
+use crate::context::{default_context, Context};
+use crate::domain::retry::{self, CancellationToken, RetryPolicy};
+use crate::domain::trace_context::TraceContext;
 use crate::domain::vault::operations::create_vault_from_sync;
-use crate::domain::vault::{error::VaultError, NamespaceData};
+pub use crate::domain::vault::AccessLevel;
+use crate::domain::vault::{
+    error::VaultError, normalize_remote_expiration, validate_ice_config, CrdtNamespace, IceServer,
+    IceTransportPolicy, NamespaceData, SyncDirection, SyncProfile,
+};
 use crate::platform::Platform;
-use crate::signaling::{with_signaling_manager, SignalingMessage};
-use crate::sync::{OperationType, SyncMessage};
+use crate::signaling::SignalingMessage;
+use crate::sync::{AppMessage, OperationType, SyncMessage};
 use futures::channel::mpsc::{UnboundedReceiver, UnboundedSender};
 use futures::StreamExt;
 use futures_channel::mpsc;
@@ -19,21 +26,61 @@ use web_sys::{
     RtcIceCandidateInit, RtcPeerConnection, RtcSdpType, RtcSessionDescriptionInit,
 };
 
-// Private helper function for updating vault from sync messages
-async fn update_vault_from_sync(vault_name: &str, vault_data: &[u8]) -> Result<(), VaultError> {
+// Applies a sync message received from any transport to the local vault
+#[tracing::instrument(
+    skip_all,
+    fields(
+        vault = vault_name,
+        namespace = tracing::field::Empty,
+        operation = tracing::field::Empty,
+        author = tracing::field::Empty,
+        trace_id = tracing::field::Empty,
+    )
+)]
+pub(crate) async fn update_vault_from_sync(
+    context: &Context,
+    vault_name: &str,
+    vault_data: &[u8],
+) -> Result<(), VaultError> {
     let platform = Platform::new();
 
+    crate::domain::vault::sync_recording::record_sync_message(
+        &platform,
+        vault_name,
+        SyncDirection::Received,
+        vault_data,
+    )
+    .await;
+
     let sync_msg: SyncMessage = serde_json::from_slice(vault_data).map_err(|e| {
         VaultError::serialization_error(format!("Failed to deserialize sync message: {:?}", e))
     })?;
 
+    let span = tracing::Span::current();
+    span.record("namespace", sync_msg.operation.namespace.as_str());
+    span.record(
+        "operation",
+        tracing::field::debug(&sync_msg.operation.operation_type),
+    );
+    span.record("author", sync_msg.operation.author.as_str());
+    if let Some(trace) = sync_msg
+        .traceparent
+        .as_deref()
+        .and_then(TraceContext::parse)
+    {
+        span.record("trace_id", trace.trace_id());
+    }
+
+    let skew = context
+        .sync_manager(vault_name)
+        .borrow_mut()
+        .observe_clock(&sync_msg);
+
     let mut current_vault =
         match crate::domain::vault::operations::read_vault(&platform, vault_name).await {
             Ok(vault) => vault,
             Err(VaultError::IoError(msg)) if msg == "Failed to get directory handle" => {
-                platform
-                    .logger()
-                    .log(&format!("Creating new vault {} for sync", vault_name));
+                tracing::info!("Creating vault for sync");
 
                 let vault = create_vault_from_sync(
                     sync_msg.vault_metadata,
@@ -50,6 +97,19 @@ async fn update_vault_from_sync(vault_name: &str, vault_data: &[u8]) -> Result<(
             Err(e) => return Err(e),
         };
 
+    if current_vault.frozen {
+        return Err(VaultError::FrozenVault);
+    }
+
+    let shared = context
+        .sync_manager(vault_name)
+        .borrow()
+        .shares(&current_vault.metadata, &sync_msg.operation.namespace);
+    if !shared {
+        tracing::debug!("Dropped update to a namespace left out by the sync policy");
+        return Ok(());
+    }
+
     if let Some(salts) = sync_msg.identity_salts {
         current_vault.identity_salts = salts;
     }
@@ -58,58 +118,224 @@ async fn update_vault_from_sync(vault_name: &str, vault_data: &[u8]) -> Result<(
         OperationType::Insert | OperationType::Update => {
             if let (Some(data), _) = (sync_msg.operation.data, sync_msg.operation.nonce) {
                 let namespace = sync_msg.operation.namespace.clone();
+                let updated_at = skew.to_local_seconds(sync_msg.operation.timestamp as i64);
+
+                // Both sides hold a CRDT: the remote copy waits, encrypted,
+                // for a device holding the key to merge it.
+                if let Some(existing) = current_vault
+                    .namespaces
+                    .get_mut(&namespace)
+                    .filter(|existing| existing.data != data)
+                {
+                    if let (Some(crdt), Some(kind)) =
+                        (existing.crdt.as_mut(), sync_msg.operation.crdt)
+                    {
+                        if crdt.kind == kind {
+                            if crdt.add_pending(data) {
+                                existing.updated_at = existing.updated_at.max(Some(updated_at));
+                                tracing::debug!("Kept remote CRDT copy for merging");
+                            }
+                            crate::domain::vault::operations::save_vault(
+                                &platform,
+                                vault_name,
+                                current_vault,
+                            )
+                            .await?;
+                            return Ok(());
+                        }
+                    }
+                }
+
+                let conflicting =
+                    matches!(sync_msg.operation.operation_type, OperationType::Insert)
+                        && current_vault
+                            .namespaces
+                            .get(&namespace)
+                            .is_some_and(|existing| existing.data != data);
+                if conflicting {
+                    let _ = platform.notifier().notify_event(
+                        vault_name,
+                        crate::notifications::EventType::SyncConflict,
+                        &format!(
+                            "Namespace {} inserted by {} already existed locally; remote copy kept",
+                            namespace, sync_msg.operation.author
+                        ),
+                    );
+                }
+
                 let namespace_data = NamespaceData {
                     data,
-                    expiration: None,
+                    expiration: normalize_remote_expiration(sync_msg.operation.expiration, &skew),
+                    chunks: Vec::new(),
+                    timelock: sync_msg.operation.timelock.clone(),
+                    updated_at: Some(updated_at),
+                    recipients: sync_msg.operation.recipients.clone(),
+                    crdt: sync_msg.operation.crdt.map(|kind| {
+                        current_vault
+                            .namespaces
+                            .get(&namespace)
+                            .and_then(|existing| existing.crdt.clone())
+                            .filter(|crdt| crdt.kind == kind)
+                            .unwrap_or_else(|| CrdtNamespace::new(kind))
+                    }),
+                    integrity: sync_msg.operation.integrity.clone(),
+                    crypto_version: sync_msg.operation.crypto_version,
+                    compression: sync_msg.operation.compression,
                 };
                 current_vault
                     .namespaces
                     .insert(namespace.clone(), namespace_data.clone());
-                platform
-                    .logger()
-                    .log(&format!("Updated namespace {} in vault", namespace));
+                // Tags only ever accumulate here, so a peer unaware of them
+                // cannot strip the local ones.
+                if !sync_msg.operation.residency.is_empty() {
+                    current_vault
+                        .residency
+                        .entry(namespace.clone())
+                        .or_default()
+                        .extend(sync_msg.operation.residency.iter().cloned());
+                }
+                tracing::debug!("Applied remote namespace update");
             }
         }
         OperationType::Delete => {
             let namespace = sync_msg.operation.namespace.clone();
+            crate::domain::vault::retention::ensure_removable(
+                &current_vault,
+                &namespace,
+                (platform.clock().now() / 1000.0) as i64,
+            )?;
             current_vault.namespaces.remove(&namespace);
-            platform
-                .logger()
-                .log(&format!("Removed namespace {} from vault", namespace));
+            tracing::debug!("Applied remote namespace removal");
         }
     }
 
+    crate::domain::vault::chunks::collect_garbage(&mut current_vault);
+
     crate::domain::vault::operations::save_vault(&platform, vault_name, current_vault).await?;
 
     Ok(())
 }
 
+// Returns true when the payload was an app message, which never reaches the
+// vault sync pipeline. Messages are authorized against the peer at the other
+// end of the data channel they arrived on.
+fn handle_app_message(
+    context: &Context,
+    remote_peer_id: &RefCell<Option<String>>,
+    data: &[u8],
+) -> bool {
+    let Ok(message) = serde_json::from_slice::<AppMessage>(data) else {
+        return false;
+    };
+
+    let Some(from) = remote_peer_id.borrow().clone() else {
+        tracing::error!("Dropped app message from an unidentified peer");
+        return true;
+    };
+
+    if let Err(e) = context.dispatch_app_message(message, &from) {
+        tracing::error!("Dropped app message: {:?}", e);
+    }
+
+    true
+}
+
+/// ICE settings of a peer connection.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PeerConfig {
+    #[serde(default)]
+    pub ice_servers: Vec<IceServer>,
+    #[serde(default)]
+    pub ice_transport_policy: IceTransportPolicy,
+}
+
+impl PeerConfig {
+    /// Configuration using only the given STUN servers, by URL.
+    pub fn from_stun_servers(stun_servers: Vec<String>) -> Self {
+        Self {
+            ice_servers: stun_servers.into_iter().map(IceServer::from_url).collect(),
+            ice_transport_policy: IceTransportPolicy::All,
+        }
+    }
+}
+
+impl From<&SyncProfile> for PeerConfig {
+    fn from(profile: &SyncProfile) -> Self {
+        Self {
+            ice_servers: profile.ice_servers.clone(),
+            ice_transport_policy: profile.ice_transport_policy,
+        }
+    }
+}
+
+/// Candidates of the pair ICE selected for a connection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConnectionRoute {
+    /// Whether traffic goes through a TURN relay.
+    pub relayed: bool,
+    /// `host`, `srflx`, `prflx` or `relay`, as in `RTCIceCandidateType`.
+    pub local_candidate_type: String,
+    pub remote_candidate_type: String,
+}
+
+// Chromium names the selected pair on the transport stats; Firefox flags
+// the pair itself.
+fn selected_route(stats: &[serde_json::Value]) -> Option<ConnectionRoute> {
+    let by_id = |id: &str| stats.iter().find(|entry| entry["id"] == id);
+
+    let pair = stats
+        .iter()
+        .filter(|entry| entry["type"] == "transport")
+        .find_map(|entry| entry["selectedCandidatePairId"].as_str())
+        .and_then(by_id)
+        .or_else(|| {
+            stats.iter().find(|entry| {
+                entry["type"] == "candidate-pair"
+                    && (entry["selected"] == true
+                        || (entry["nominated"] == true && entry["state"] == "succeeded"))
+            })
+        })?;
+
+    let candidate_type = |key: &str| {
+        pair[key]
+            .as_str()
+            .and_then(by_id)
+            .and_then(|candidate| candidate["candidateType"].as_str())
+            .map(str::to_string)
+    };
+    let local_candidate_type = candidate_type("localCandidateId")?;
+    let remote_candidate_type = candidate_type("remoteCandidateId")?;
+
+    Some(ConnectionRoute {
+        relayed: local_candidate_type == "relay" || remote_candidate_type == "relay",
+        local_candidate_type,
+        remote_candidate_type,
+    })
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct WebRtcMetadata {
     pub peer_id: String,
     pub permissions: HashMap<String, AccessLevel>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
-pub enum AccessLevel {
-    Viewer,
-    Contributor,
-    Administrator,
-}
-
 #[derive(Clone)]
 pub struct WebRtcPeer {
     platform: Platform,
+    context: Rc<Context>,
     metadata: WebRtcMetadata,
     connection: RtcPeerConnection,
     data_channel: Option<RtcDataChannel>,
-    remote_peer_id: Option<String>,
+    /// Shared with the data channel callbacks, which authorize app messages
+    /// against it.
+    remote_peer_id: Rc<RefCell<Option<String>>>,
     connected: Rc<RefCell<bool>>,
     channel_open: Rc<RefCell<bool>>,
     ice_connected: Rc<RefCell<bool>>,
     message_sender: UnboundedSender<Vec<u8>>,
     connection_state_sender: UnboundedSender<bool>,
     is_offerer: bool,
+    trace: Rc<RefCell<TraceContext>>,
 }
 
 impl WebRtcPeer {
@@ -118,13 +344,63 @@ impl WebRtcPeer {
     }
 
     pub fn remote_peer_id(&self) -> Option<String> {
-        self.remote_peer_id.clone()
+        self.remote_peer_id.borrow().clone()
+    }
+
+    fn set_remote_peer_id(&self, remote_peer_id: String) {
+        *self.remote_peer_id.borrow_mut() = Some(remote_peer_id);
+    }
+
+    /// Trace id shared by the signaling messages of this connection.
+    pub fn trace_id(&self) -> String {
+        self.trace.borrow().trace_id()
+    }
+
+    /// Joins the trace of an incoming offer so both peers and the signaling
+    /// server report the connection under the same trace id.
+    fn adopt_trace(&self, traceparent: Option<&str>) {
+        if let Some(trace) = traceparent.and_then(TraceContext::parse) {
+            tracing::debug!(trace_id = %trace.trace_id(), "Joining trace of incoming offer");
+            *self.trace.borrow_mut() = trace;
+        }
+    }
+
+    fn next_traceparent(&self) -> Option<String> {
+        Some(self.trace.borrow().child().to_traceparent())
     }
 
     pub fn is_connected(&self) -> bool {
         *self.connected.borrow()
     }
 
+    /// Route ICE selected for the connection, `None` until a candidate pair
+    /// is selected. `relayed` tells whether a TURN server carries the
+    /// traffic, e.g. because a symmetric NAT prevented a direct connection.
+    pub async fn connection_route(&self) -> Result<Option<ConnectionRoute>, JsValue> {
+        let report: js_sys::Map = JsFuture::from(self.connection.get_stats())
+            .await?
+            .unchecked_into();
+
+        let mut stats = Vec::new();
+        report.for_each(&mut |entry, _| {
+            if let Ok(entry) = serde_wasm_bindgen::from_value(entry) {
+                stats.push(entry);
+            }
+        });
+
+        let route = selected_route(&stats);
+        if let Some(route) = &route {
+            tracing::debug!(
+                relayed = route.relayed,
+                local = %route.local_candidate_type,
+                remote = %route.remote_candidate_type,
+                "Selected ICE route"
+            );
+        }
+
+        Ok(route)
+    }
+
     pub fn set_connected(&mut self, connected: bool) {
         *self.connected.borrow_mut() = connected;
         let _ = self.connection_state_sender.unbounded_send(connected);
@@ -145,26 +421,69 @@ impl WebRtcPeer {
 
         let ready = connected && channel_open && ice_connected;
 
-        self.platform.logger().log(&format!("Checking connection readiness: connected={}, channel_open={}, ice_connected={}, ready={}",
-            connected, channel_open, ice_connected, ready));
+        tracing::debug!("Checking connection readiness: connected={}, channel_open={}, ice_connected={}, ready={}",
+            connected, channel_open, ice_connected, ready);
 
         ready
     }
 
+    /// Waits until the connection, ICE and data channel are all up, polling
+    /// according to `policy`.
+    pub async fn wait_until_ready(
+        &self,
+        policy: &RetryPolicy,
+        cancellation: &CancellationToken,
+    ) -> Result<(), JsValue> {
+        policy
+            .run(&self.platform, cancellation, |_| async {
+                if self.is_ready() {
+                    Ok(())
+                } else {
+                    Err(JsValue::from_str("Peer connection is not ready"))
+                }
+            })
+            .await
+            .map_err(JsValue::from)
+    }
+
     pub async fn create_peer(
         peer_id: String,
-        stun_servers: Vec<String>,
+        config: PeerConfig,
     ) -> Result<(Self, UnboundedReceiver<Vec<u8>>), JsValue> {
+        Self::create_peer_in(default_context(), peer_id, config).await
+    }
+
+    /// Creates a peer whose signaling and app messages go through `context`.
+    pub async fn create_peer_in(
+        context: Rc<Context>,
+        peer_id: String,
+        config: PeerConfig,
+    ) -> Result<(Self, UnboundedReceiver<Vec<u8>>), JsValue> {
+        validate_ice_config(&config.ice_servers, config.ice_transport_policy)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
         let rtc_config = RtcConfiguration::new();
         let ice_servers = Array::new();
 
-        for server in stun_servers {
+        for server in &config.ice_servers {
             let server_dict = Object::new();
-            Reflect::set(&server_dict, &"urls".into(), &server.into())?;
+            let urls: Array = server.urls.iter().map(JsValue::from).collect();
+            Reflect::set(&server_dict, &"urls".into(), &urls)?;
+            if let Some(username) = &server.username {
+                Reflect::set(&server_dict, &"username".into(), &username.into())?;
+            }
+            if let Some(credential) = &server.credential {
+                Reflect::set(&server_dict, &"credential".into(), &credential.into())?;
+            }
             ice_servers.push(&server_dict);
         }
 
         rtc_config.set_ice_servers(&ice_servers);
+        Reflect::set(
+            &rtc_config,
+            &"iceTransportPolicy".into(),
+            &config.ice_transport_policy.as_str().into(),
+        )?;
 
         let connection = RtcPeerConnection::new_with_configuration(&rtc_config)?;
 
@@ -181,16 +500,18 @@ impl WebRtcPeer {
 
         let mut peer = Self {
             platform: Platform::new(),
+            context,
             metadata,
             connection,
             data_channel: None,
-            remote_peer_id: None,
+            remote_peer_id: Rc::new(RefCell::new(None)),
             connected: Rc::new(RefCell::new(false)),
             channel_open,
             ice_connected,
             message_sender: sender,
             connection_state_sender,
             is_offerer: false,
+            trace: Rc::new(RefCell::new(TraceContext::new_root())),
         };
 
         peer.setup_connection().await?;
@@ -199,10 +520,7 @@ impl WebRtcPeer {
     }
 
     async fn setup_connection(&mut self) -> Result<(), JsValue> {
-        let platform = self.platform.clone();
-        platform
-            .logger()
-            .log("Setting up WebRTC connection handlers...");
+        tracing::debug!("Setting up WebRTC connection handlers...");
 
         let connected_flag = Rc::new(RefCell::new(false));
         let connected_flag_clone = connected_flag.clone();
@@ -212,25 +530,22 @@ impl WebRtcPeer {
         let state_sender = self.connection_state_sender.clone();
 
         let onicegatheringstatechange_callback = {
-            let platform = platform.clone();
             Closure::wrap(Box::new(move |_: web_sys::Event| {
                 let state = connection_ref.ice_gathering_state();
-                platform
-                    .logger()
-                    .log(&format!("ICE gathering state changed to: {:?}", state));
+                tracing::debug!("ICE gathering state changed to: {:?}", state);
 
                 match state {
                     web_sys::RtcIceGatheringState::New => {
-                        platform.logger().log("ICE gathering starting...");
+                        tracing::debug!("ICE gathering starting...");
                     }
                     web_sys::RtcIceGatheringState::Gathering => {
-                        platform.logger().log("ICE gathering in progress...");
+                        tracing::debug!("ICE gathering in progress...");
                     }
                     web_sys::RtcIceGatheringState::Complete => {
-                        platform.logger().log("ICE gathering complete");
+                        tracing::debug!("ICE gathering complete");
                     }
                     _ => {
-                        platform.logger().warn("Unknown ICE gathering state");
+                        tracing::warn!("Unknown ICE gathering state");
                     }
                 }
             }) as Box<dyn FnMut(web_sys::Event)>)
@@ -242,47 +557,47 @@ impl WebRtcPeer {
         onicegatheringstatechange_callback.forget();
 
         let onconnectionstatechange_callback = {
-            let platform = platform.clone();
             Closure::wrap(Box::new(move |_: web_sys::Event| {
                 let state = connection_ref2.connection_state();
                 let is_connected = state == web_sys::RtcPeerConnectionState::Connected;
                 *connected_flag_clone.borrow_mut() = is_connected;
                 let _ = state_sender.unbounded_send(is_connected);
 
-                platform.logger().log(&format!(
+                tracing::debug!(
                     "Connection state changed to: {:?}, connected={}",
-                    state, is_connected
-                ));
+                    state,
+                    is_connected
+                );
 
                 match state {
                     web_sys::RtcPeerConnectionState::New => {
-                        platform.logger().log("Connection is new");
+                        tracing::debug!("Connection is new");
                     }
                     web_sys::RtcPeerConnectionState::Connecting => {
-                        platform.logger().log("Connection is establishing...");
+                        tracing::debug!("Connection is establishing...");
                     }
                     web_sys::RtcPeerConnectionState::Connected => {
-                        platform.logger().log("Connection established!");
+                        tracing::info!("Connection established");
                         *connected_flag_clone.borrow_mut() = true;
                         let _ = state_sender.unbounded_send(true);
                     }
                     web_sys::RtcPeerConnectionState::Disconnected => {
-                        platform.logger().log("Connection disconnected");
+                        tracing::debug!("Connection disconnected");
                         *connected_flag_clone.borrow_mut() = false;
                         let _ = state_sender.unbounded_send(false);
                     }
                     web_sys::RtcPeerConnectionState::Failed => {
-                        platform.logger().log("Connection failed");
+                        tracing::debug!("Connection failed");
                         *connected_flag_clone.borrow_mut() = false;
                         let _ = state_sender.unbounded_send(false);
                     }
                     web_sys::RtcPeerConnectionState::Closed => {
-                        platform.logger().log("Connection closed");
+                        tracing::debug!("Connection closed");
                         *connected_flag_clone.borrow_mut() = false;
                         let _ = state_sender.unbounded_send(false);
                     }
                     _ => {
-                        platform.logger().warn("Unknown connection state");
+                        tracing::warn!("Unknown connection state");
                     }
                 }
             }) as Box<dyn FnMut(web_sys::Event)>)
@@ -296,44 +611,42 @@ impl WebRtcPeer {
 
         let ice_connected = self.ice_connected.clone();
         let onicestatechange_callback = {
-            let platform = platform.clone();
             Closure::wrap(Box::new(move |_: web_sys::Event| {
                 let state = connection_ref3.ice_connection_state();
                 let is_connected = state == web_sys::RtcIceConnectionState::Connected
                     || state == web_sys::RtcIceConnectionState::Completed;
                 *ice_connected.borrow_mut() = is_connected;
 
-                platform.logger().log(&format!(
+                tracing::debug!(
                     "ICE connection state changed to: {:?}, is_connected: {}",
-                    state, is_connected
-                ));
+                    state,
+                    is_connected
+                );
 
                 match state {
                     web_sys::RtcIceConnectionState::New => {
-                        platform.logger().log("ICE connection is new");
+                        tracing::debug!("ICE connection is new");
                     }
                     web_sys::RtcIceConnectionState::Checking => {
-                        platform
-                            .logger()
-                            .log("ICE connection is checking candidates...");
+                        tracing::debug!("ICE connection is checking candidates...");
                     }
                     web_sys::RtcIceConnectionState::Connected => {
-                        platform.logger().log("ICE connection established!");
+                        tracing::debug!("ICE connection established!");
                     }
                     web_sys::RtcIceConnectionState::Completed => {
-                        platform.logger().log("ICE connection completed!");
+                        tracing::debug!("ICE connection completed!");
                     }
                     web_sys::RtcIceConnectionState::Failed => {
-                        platform.logger().log("ICE connection failed");
+                        tracing::debug!("ICE connection failed");
                     }
                     web_sys::RtcIceConnectionState::Disconnected => {
-                        platform.logger().log("ICE connection disconnected");
+                        tracing::debug!("ICE connection disconnected");
                     }
                     web_sys::RtcIceConnectionState::Closed => {
-                        platform.logger().log("ICE connection closed");
+                        tracing::debug!("ICE connection closed");
                     }
                     _ => {
-                        platform.logger().warn("Unknown ICE connection state");
+                        tracing::warn!("Unknown ICE connection state");
                     }
                 }
             }) as Box<dyn FnMut(web_sys::Event)>)
@@ -346,83 +659,80 @@ impl WebRtcPeer {
 
         let onicecandidate = {
             let peer_id = self.metadata.peer_id.clone();
-            let remote_id_ref = Rc::new(RefCell::new(self.remote_peer_id.clone()));
-            let platform = platform.clone();
+            let remote_id_ref = self.remote_peer_id.clone();
+            let context = self.context.clone();
+            let trace = self.trace.clone();
             Closure::wrap(Box::new(move |ev: web_sys::RtcPeerConnectionIceEvent| {
-                platform.logger().log(&format!(
+                tracing::debug!(
                     "ICE candidate event triggered. Has candidate: {}",
                     ev.candidate().is_some()
-                ));
+                );
 
                 if let Some(candidate) = ev.candidate() {
                     let candidate_str = candidate.candidate();
-                    platform.logger().log(&format!(
-                        "ICE candidate details - sdp_m_line_index: {:?}, sdp_mid: {:?}, candidate: {}", 
+                    tracing::debug!("ICE candidate details - sdp_m_line_index: {:?}, sdp_mid: {:?}, candidate: {}", 
                         candidate.sdp_m_line_index(),
                         candidate.sdp_mid(),
-                        candidate_str
-                    ));
+                        candidate_str);
 
                     if let Some(remote_id) = &*remote_id_ref.borrow() {
-                        platform.logger().log(&format!(
+                        tracing::debug!(
                             "Sending ICE candidate to {}: {}",
-                            remote_id, candidate_str
-                        ));
+                            remote_id,
+                            candidate_str
+                        );
 
                         let ice_msg = SignalingMessage::IceCandidate {
                             from: peer_id.clone(),
                             to: remote_id.clone(),
                             candidate: candidate_str,
+                            traceparent: Some(trace.borrow().child().to_traceparent()),
                         };
 
-                        with_signaling_manager(|manager| {
-                            if let Some(signaling) = manager.get_client(&peer_id) {
+                        {
+                            if let Some(signaling) = context.signaling().get_client(&peer_id) {
                                 let signaling_ref = signaling.borrow();
                                 let websocket = signaling_ref.get_websocket();
 
                                 if websocket.ready_state() != web_sys::WebSocket::OPEN {
-                                    platform
-                                        .logger()
-                                        .warn("WebSocket not ready, cannot send ICE candidate");
+                                    tracing::warn!(
+                                        "WebSocket not ready, cannot send ICE candidate"
+                                    );
                                     return;
                                 }
 
                                 match serde_json::to_string(&ice_msg) {
                                     Ok(msg_str) => {
-                                        platform.logger().log(&format!(
+                                        tracing::debug!(
                                             "Sending ICE candidate message: {}",
                                             msg_str
-                                        ));
+                                        );
                                         match websocket.send_with_str(&msg_str) {
-                                            Ok(_) => platform
-                                                .logger()
-                                                .log("ICE candidate sent successfully"),
-                                            Err(e) => platform.logger().error(&format!(
+                                            Ok(_) => {
+                                                tracing::debug!("ICE candidate sent successfully")
+                                            }
+                                            Err(e) => tracing::error!(
                                                 "Failed to send ICE candidate: {:?}",
                                                 e
-                                            )),
+                                            ),
                                         }
                                     }
-                                    Err(e) => platform.logger().error(&format!(
+                                    Err(e) => tracing::error!(
                                         "Failed to serialize ICE candidate message: {:?}",
                                         e
-                                    )),
+                                    ),
                                 }
                             } else {
-                                platform.logger().error(
-                                    "No signaling client found when trying to send ICE candidate",
+                                tracing::error!(
+                                    "No signaling client found when trying to send ICE candidate"
                                 );
                             }
-                        });
+                        }
                     } else {
-                        platform
-                            .logger()
-                            .warn("Generated ICE candidate but no remote peer ID set yet");
+                        tracing::warn!("Generated ICE candidate but no remote peer ID set yet");
                     }
                 } else {
-                    platform
-                        .logger()
-                        .log("ICE candidate gathering complete (null candidate)");
+                    tracing::debug!("ICE candidate gathering complete (null candidate)");
                 }
             })
                 as Box<dyn FnMut(web_sys::RtcPeerConnectionIceEvent)>)
@@ -439,89 +749,84 @@ impl WebRtcPeer {
             let channel_open_clone = channel_open.clone();
             let message_sender_clone = message_sender.clone();
             let data_channel_ref = Rc::new(RefCell::new(self.data_channel.clone()));
-            let platform = platform.clone();
+            let context = self.context.clone();
+            let remote_peer_id = self.remote_peer_id.clone();
 
             Closure::wrap(Box::new(move |ev: web_sys::RtcDataChannelEvent| {
-                platform
-                    .logger()
-                    .log("Data channel received from remote peer");
+                tracing::debug!("Data channel received from remote peer");
                 let channel = ev.channel();
                 *data_channel_ref.borrow_mut() = Some(channel.clone());
 
                 let channel_open_clone = channel_open_clone.clone();
-                let platform_onopen = platform.clone();
                 let onopen = Closure::wrap(Box::new(move |_: web_sys::Event| {
-                    platform_onopen
-                        .logger()
-                        .log("Data channel opened (answerer)");
+                    tracing::debug!("Data channel opened (answerer)");
                     *channel_open_clone.borrow_mut() = true;
                 }) as Box<dyn FnMut(web_sys::Event)>);
                 channel.set_onopen(Some(onopen.as_ref().unchecked_ref()));
                 onopen.forget();
 
-                let platform_onclose = platform.clone();
                 let onclose = Closure::wrap(Box::new(move |_: web_sys::Event| {
-                    platform_onclose
-                        .logger()
-                        .log("Data channel closed (answerer)");
+                    tracing::debug!("Data channel closed (answerer)");
                 }) as Box<dyn FnMut(web_sys::Event)>);
                 channel.set_onclose(Some(onclose.as_ref().unchecked_ref()));
                 onclose.forget();
 
-                let platform_onerror = platform.clone();
                 let onerror = Closure::wrap(Box::new(move |e: web_sys::Event| {
-                    platform_onerror
-                        .logger()
-                        .error(&format!("Data channel error: {:?}", e));
+                    tracing::error!("Data channel error: {:?}", e);
                 }) as Box<dyn FnMut(web_sys::Event)>);
                 channel.set_onerror(Some(onerror.as_ref().unchecked_ref()));
                 onerror.forget();
 
                 let message_sender_clone = message_sender_clone.clone();
-                let platform_onmessage = platform.clone();
+                let context_onmessage = context.clone();
+                let remote_peer_id = remote_peer_id.clone();
                 let onmessage = Closure::wrap(Box::new(move |ev: MessageEvent| {
-                    platform_onmessage
-                        .logger()
-                        .log("Message received on data channel");
+                    tracing::debug!("Message received on data channel");
                     if let Ok(data) = ev.data().dyn_into::<js_sys::ArrayBuffer>() {
                         let array = js_sys::Uint8Array::new(&data);
                         let mut vec = vec![0; array.length() as usize];
                         array.copy_to(&mut vec[..]);
-                        platform_onmessage
-                            .logger()
-                            .log(&format!("Received message of {} bytes", vec.len()));
+                        tracing::debug!("Received message of {} bytes", vec.len());
+
+                        if handle_app_message(&context_onmessage, &remote_peer_id, &vec) {
+                            return;
+                        }
 
                         match serde_json::from_slice::<SyncMessage>(&vec) {
                             Ok(sync_msg) => {
-                                platform_onmessage.logger().log(&format!(
+                                tracing::debug!(
                                     "Received sync message for vault: {}, namespace: {}",
-                                    sync_msg.vault_name, sync_msg.operation.namespace
-                                ));
+                                    sync_msg.vault_name,
+                                    sync_msg.operation.namespace
+                                );
 
                                 let vault_name = sync_msg.vault_name.clone();
                                 let vec_clone = vec.clone();
-                                let platform_spawn = platform_onmessage.clone();
+                                let context_spawn = context_onmessage.clone();
 
                                 wasm_bindgen_futures::spawn_local(async move {
-                                    if let Err(e) =
-                                        update_vault_from_sync(&vault_name, &vec_clone).await
+                                    if let Err(e) = update_vault_from_sync(
+                                        &context_spawn,
+                                        &vault_name,
+                                        &vec_clone,
+                                    )
+                                    .await
                                     {
-                                        platform_spawn.logger().error(&format!(
+                                        tracing::error!(
                                             "Failed to update vault {}: {:?}",
-                                            vault_name, e
-                                        ));
+                                            vault_name,
+                                            e
+                                        );
                                     } else {
-                                        platform_spawn.logger().log(&format!(
+                                        tracing::debug!(
                                             "Successfully updated vault {} from sync message",
                                             vault_name
-                                        ));
+                                        );
                                     }
                                 });
                             }
                             Err(e) => {
-                                platform_onmessage
-                                    .logger()
-                                    .error(&format!("Failed to parse sync message: {}", e));
+                                tracing::error!("Failed to parse sync message: {}", e);
                             }
                         }
 
@@ -539,40 +844,32 @@ impl WebRtcPeer {
         ondatachannel_callback.forget();
 
         if self.is_offerer {
-            platform.logger().log("Creating data channel as offerer");
+            tracing::debug!("Creating data channel as offerer");
 
             let channel = self.connection.create_data_channel("data");
-            platform.logger().log(&format!(
+            tracing::debug!(
                 "Data channel created with state: {:?}",
                 channel.ready_state()
-            ));
+            );
             self.data_channel = Some(channel.clone());
 
             let channel_open_clone = self.channel_open.clone();
             let connected_flag = self.connected.clone();
             let state_sender = self.connection_state_sender.clone();
-            let platform_onopen = platform.clone();
             let onopen = Closure::wrap(Box::new(move |_: web_sys::Event| {
-                platform_onopen
-                    .logger()
-                    .log("Data channel opened (offerer)");
+                tracing::debug!("Data channel opened (offerer)");
                 *channel_open_clone.borrow_mut() = true;
                 *connected_flag.borrow_mut() = true;
                 let _ = state_sender.unbounded_send(true);
-                platform_onopen
-                    .logger()
-                    .log("channel_open and connected flags set to true");
+                tracing::debug!("channel_open and connected flags set to true");
             }) as Box<dyn FnMut(web_sys::Event)>);
             channel.set_onopen(Some(onopen.as_ref().unchecked_ref()));
             onopen.forget();
 
             let connected_flag = self.connected.clone();
             let state_sender = self.connection_state_sender.clone();
-            let platform_onclose = platform.clone();
             let onclose = Closure::wrap(Box::new(move |_: web_sys::Event| {
-                platform_onclose
-                    .logger()
-                    .log("Data channel closed (offerer)");
+                tracing::debug!("Data channel closed (offerer)");
                 *connected_flag.borrow_mut() = false;
                 let _ = state_sender.unbounded_send(false);
             }) as Box<dyn FnMut(web_sys::Event)>);
@@ -581,11 +878,8 @@ impl WebRtcPeer {
 
             let connected_flag = self.connected.clone();
             let state_sender = self.connection_state_sender.clone();
-            let platform_onerror = platform.clone();
             let onerror = Closure::wrap(Box::new(move |e: web_sys::Event| {
-                platform_onerror
-                    .logger()
-                    .error(&format!("Data channel error: {:?}", e));
+                tracing::error!("Data channel error: {:?}", e);
                 *connected_flag.borrow_mut() = false;
                 let _ = state_sender.unbounded_send(false);
             }) as Box<dyn FnMut(web_sys::Event)>);
@@ -593,30 +887,30 @@ impl WebRtcPeer {
             onerror.forget();
 
             let message_sender_clone = self.message_sender.clone();
-            let platform_onmessage = platform.clone();
+            let context_onmessage = self.context.clone();
+            let remote_peer_id = self.remote_peer_id.clone();
             let onmessage = Closure::wrap(Box::new(move |ev: MessageEvent| {
-                platform_onmessage
-                    .logger()
-                    .log("Message received on data channel");
+                tracing::debug!("Message received on data channel");
                 if let Ok(data) = ev.data().dyn_into::<js_sys::ArrayBuffer>() {
                     let array = js_sys::Uint8Array::new(&data);
                     let mut vec = vec![0; array.length() as usize];
                     array.copy_to(&mut vec[..]);
-                    platform_onmessage
-                        .logger()
-                        .log(&format!("Received message of {} bytes", vec.len()));
+                    tracing::debug!("Received message of {} bytes", vec.len());
+
+                    if handle_app_message(&context_onmessage, &remote_peer_id, &vec) {
+                        return;
+                    }
 
                     match serde_json::from_slice::<SyncMessage>(&vec) {
                         Ok(sync_msg) => {
-                            platform_onmessage.logger().log(&format!(
+                            tracing::debug!(
                                 "Received sync message for vault: {}, namespace: {}",
-                                sync_msg.vault_name, sync_msg.operation.namespace
-                            ));
+                                sync_msg.vault_name,
+                                sync_msg.operation.namespace
+                            );
                         }
                         Err(e) => {
-                            platform_onmessage
-                                .logger()
-                                .error(&format!("Failed to parse sync message: {}", e));
+                            tracing::error!("Failed to parse sync message: {}", e);
                         }
                     }
 
@@ -627,16 +921,14 @@ impl WebRtcPeer {
             onmessage.forget();
         }
 
-        platform
-            .logger()
-            .log("WebRTC connection handlers setup complete");
+        tracing::debug!("WebRTC connection handlers setup complete");
         Ok(())
     }
 
     pub async fn create_offer(&self) -> Result<String, JsValue> {
-        self.platform.logger().log("Creating WebRTC offer...");
+        tracing::debug!("Creating WebRTC offer...");
         let offer = JsFuture::from(self.connection.create_offer()).await?;
-        self.platform.logger().log("Setting local description...");
+        tracing::debug!("Setting local description...");
 
         let rtc_session_description_init = RtcSessionDescriptionInit::new(RtcSdpType::Offer);
         let sdp = Reflect::get(&offer, &JsValue::from_str("sdp"))?
@@ -649,15 +941,13 @@ impl WebRtcPeer {
                 .set_local_description(&rtc_session_description_init),
         )
         .await?;
-        self.platform
-            .logger()
-            .log("Local description set successfully");
+        tracing::debug!("Local description set successfully");
 
         Ok(sdp)
     }
 
     pub async fn create_answer(&self) -> Result<String, JsValue> {
-        self.platform.logger().log("Creating WebRTC answer...");
+        tracing::debug!("Creating WebRTC answer...");
         let answer = JsFuture::from(self.connection.create_answer()).await?;
         let sdp = Reflect::get(&answer, &JsValue::from_str("sdp"))?
             .as_string()
@@ -671,13 +961,11 @@ impl WebRtcPeer {
     }
 
     pub async fn handle_answer(&mut self, answer_sdp: &str) -> Result<(), JsValue> {
-        self.platform.logger().log("Handle answer...");
+        tracing::debug!("Handle answer...");
 
         // Make sure we're the offerer
         if !self.is_offerer {
-            self.platform
-                .logger()
-                .error("Received answer but we're not the offerer!");
+            tracing::error!("Received answer but we're not the offerer!");
             return Err(JsValue::from_str(
                 "Received answer but we're not the offerer",
             ));
@@ -685,68 +973,57 @@ impl WebRtcPeer {
 
         let answer_obj = RtcSessionDescriptionInit::new(RtcSdpType::Answer);
         answer_obj.set_sdp(answer_sdp);
-        self.platform.logger().log(&format!(
-            "Setting remote description (answer): {}",
-            answer_sdp
-        ));
+        tracing::debug!("Setting remote description (answer): {}", answer_sdp);
 
         JsFuture::from(self.connection.set_remote_description(&answer_obj)).await?;
-        self.platform
-            .logger()
-            .log("Remote description (answer) set successfully");
+        tracing::debug!("Remote description (answer) set successfully");
         Ok(())
     }
 
     pub async fn handle_offer(&mut self, offer_sdp: &str) -> Result<String, JsValue> {
-        self.platform.logger().log("Handle offer...");
+        tracing::debug!("Handle offer...");
         self.is_offerer = false;
 
         self.setup_connection().await?;
 
         let offer_obj = RtcSessionDescriptionInit::new(RtcSdpType::Offer);
         offer_obj.set_sdp(offer_sdp);
-        self.platform
-            .logger()
-            .log("Setting remote description (offer)...");
+        tracing::debug!("Setting remote description (offer)...");
         JsFuture::from(self.connection.set_remote_description(&offer_obj)).await?;
-        self.platform
-            .logger()
-            .log("Remote description set successfully");
+        tracing::debug!("Remote description set successfully");
 
-        self.platform.logger().log("Creating answer...");
+        tracing::debug!("Creating answer...");
         let answer = JsFuture::from(self.connection.create_answer()).await?;
         let answer_sdp = Reflect::get(&answer, &JsValue::from_str("sdp"))?
             .dyn_into::<JsString>()
             .map(String::from)
             .unwrap_or_default();
-        self.platform
-            .logger()
-            .log(&format!("Answer created: {}", answer_sdp));
+        tracing::debug!("Answer created: {}", answer_sdp);
 
         let answer_obj = RtcSessionDescriptionInit::new(RtcSdpType::Answer);
         answer_obj.set_sdp(&answer_sdp);
-        self.platform
-            .logger()
-            .log("Setting local description (answer)...");
+        tracing::debug!("Setting local description (answer)...");
         JsFuture::from(self.connection.set_local_description(&answer_obj)).await?;
-        self.platform
-            .logger()
-            .log("Local description set successfully");
-
-        if let Some(remote_id) = &self.remote_peer_id {
-            self.platform
-                .logger()
-                .log(&format!("Sending answer to remote peer {}", remote_id));
-            if let Some(client) =
-                with_signaling_manager(|mgr| mgr.get_client(&self.metadata.peer_id))
-            {
+        tracing::debug!("Local description set successfully");
+
+        if let Some(remote_id) = self.remote_peer_id() {
+            tracing::debug!("Sending answer to remote peer {}", remote_id);
+            // The answer is often ready before the signaling socket reopens
+            // after a reconnect; give it a chance instead of failing outright.
+            crate::signaling::wait_until_open(
+                self.context.signaling(),
+                &self.metadata.peer_id,
+                &retry::default_retry_policy(),
+                &CancellationToken::new(),
+            )
+            .await?;
+
+            if let Some(client) = self.context.signaling().get_client(&self.metadata.peer_id) {
                 let client_ref = client.borrow();
                 let websocket = client_ref.get_websocket();
 
                 if websocket.ready_state() != web_sys::WebSocket::OPEN {
-                    self.platform
-                        .logger()
-                        .warn("WebSocket not ready, cannot send answer");
+                    tracing::warn!("WebSocket not ready, cannot send answer");
                     return Err(JsValue::from_str("WebSocket not ready, cannot send answer"));
                 }
 
@@ -754,18 +1031,15 @@ impl WebRtcPeer {
                     from: self.metadata.peer_id.clone(),
                     to: remote_id.clone(),
                     sdp: answer_sdp.clone(),
+                    traceparent: self.next_traceparent(),
                 };
 
                 if let Ok(msg_str) = serde_json::to_string(&answer_msg) {
-                    self.platform
-                        .logger()
-                        .log(&format!("Sending answer message: {}", msg_str));
+                    tracing::debug!("Sending answer message: {}", msg_str);
                     match websocket.send_with_str(&msg_str) {
-                        Ok(_) => self.platform.logger().log("Answer sent successfully"),
+                        Ok(_) => tracing::debug!("Answer sent successfully"),
                         Err(e) => {
-                            self.platform
-                                .logger()
-                                .error(&format!("Failed to send answer: {:?}", e));
+                            tracing::error!("Failed to send answer: {:?}", e);
                             return Err(e);
                         }
                     }
@@ -776,44 +1050,91 @@ impl WebRtcPeer {
         Ok(answer_sdp)
     }
 
+    /// Connects this peer to `other`, another peer of the same page, without
+    /// a signaling server: the offer and answer are handed over in memory
+    /// once ICE gathering is complete, so they carry every candidate. This
+    /// peer makes the offer.
+    pub async fn connect_loopback(&mut self, other: &mut WebRtcPeer) -> Result<(), JsValue> {
+        if *self.connected.borrow() {
+            tracing::debug!("Already connected, skipping loopback connection");
+            return Ok(());
+        }
+
+        tracing::debug!(
+            "Connecting {} to {} in memory",
+            self.metadata.peer_id,
+            other.metadata.peer_id
+        );
+
+        self.is_offerer = true;
+        self.setup_connection().await?;
+        self.create_offer().await?;
+        let offer = self.gathered_description().await?;
+
+        // `other` has no remote peer yet, so handle_offer keeps the answer
+        // to itself instead of sending it through signaling.
+        other.handle_offer(&offer).await?;
+        let answer = other.gathered_description().await?;
+
+        self.handle_answer(&answer).await?;
+        self.set_remote_peer_id(other.metadata.peer_id.clone());
+        other.set_remote_peer_id(self.metadata.peer_id.clone());
+        Ok(())
+    }
+
+    async fn gathered_description(&self) -> Result<String, JsValue> {
+        retry::default_retry_policy()
+            .run(&self.platform, &CancellationToken::new(), |_| async {
+                if self.connection.ice_gathering_state() == web_sys::RtcIceGatheringState::Complete
+                {
+                    Ok(())
+                } else {
+                    Err(JsValue::from_str("ICE gathering is not complete"))
+                }
+            })
+            .await
+            .map_err(JsValue::from)?;
+
+        self.connection
+            .local_description()
+            .map(|description| description.sdp())
+            .ok_or_else(|| JsValue::from_str("Missing local description"))
+    }
+
     pub async fn connect(
         &mut self,
         signaling_url: &str,
         target_peer_id: Option<&str>,
     ) -> Result<(), JsValue> {
-        let platform = self.platform.clone();
-
         if *self.connected.borrow() {
-            platform
-                .logger()
-                .log("Already connected, skipping connection process");
+            tracing::debug!("Already connected, skipping connection process");
             return Ok(());
         }
 
-        platform.logger().log(&format!(
+        tracing::debug!(
             "Starting WebRTC connection process. Target peer: {:?}",
             target_peer_id
-        ));
+        );
 
         if let Some(target_id) = target_peer_id {
-            platform
-                .logger()
-                .log(&format!("Setting up as offerer for peer {}", target_id));
-            self.remote_peer_id = Some(target_id.to_string());
+            tracing::debug!("Setting up as offerer for peer {}", target_id);
+            self.set_remote_peer_id(target_id.to_string());
             self.is_offerer = true;
         }
 
-        platform.logger().log("Running connection setup...");
+        tracing::debug!("Running connection setup...");
         self.setup_connection().await?;
 
-        platform.logger().log(&format!(
+        tracing::debug!(
             "Setting up signaling client for {} at {}",
-            self.metadata.peer_id, signaling_url
-        ));
+            self.metadata.peer_id,
+            signaling_url
+        );
 
-        let signaling_receiver = with_signaling_manager(|mgr| {
-            mgr.add_client(signaling_url, self.metadata.peer_id.clone())
-        })?;
+        let signaling_receiver = self
+            .context
+            .signaling()
+            .add_client(signaling_url, self.metadata.peer_id.clone())?;
 
         let peer_id = self.metadata.peer_id.clone();
         let mut signaling_receiver = signaling_receiver;
@@ -821,23 +1142,24 @@ impl WebRtcPeer {
 
         wasm_bindgen_futures::spawn_local({
             let peer = peer.clone();
-            let platform_for_spawn = platform.clone();
             async move {
                 while let Some(msg) = signaling_receiver.next().await {
-                    platform_for_spawn.clone().logger().log(&format!(
-                        "Received signaling message for {}: {:?}",
-                        peer_id, msg
-                    ));
+                    tracing::debug!("Received signaling message for {}: {:?}", peer_id, msg);
                     let cloned_msg = msg.clone();
                     let peer_clone = Rc::clone(&peer);
-                    let platform_for_handler = platform_for_spawn.clone();
                     let handle_message = async move {
                         match cloned_msg {
-                            SignalingMessage::Offer { from, sdp, .. } => {
+                            SignalingMessage::Offer {
+                                from,
+                                sdp,
+                                traceparent,
+                                ..
+                            } => {
                                 // Set remote peer ID
                                 {
-                                    let mut peer_ref = peer_clone.borrow_mut();
-                                    peer_ref.remote_peer_id = Some(from.clone());
+                                    let peer_ref = peer_clone.borrow();
+                                    peer_ref.set_remote_peer_id(from.clone());
+                                    peer_ref.adopt_trace(traceparent.as_deref());
                                 }
 
                                 // Handle offer
@@ -852,19 +1174,27 @@ impl WebRtcPeer {
                                     from: peer_id.clone(),
                                     to: from.clone(),
                                     sdp: answer_sdp,
+                                    traceparent: peer_clone.borrow().next_traceparent(),
                                 };
 
+                                let context = peer_clone.borrow().context.clone();
+                                crate::signaling::wait_until_open(
+                                    context.signaling(),
+                                    &peer_id,
+                                    &retry::default_retry_policy(),
+                                    &CancellationToken::new(),
+                                )
+                                .await?;
+
                                 if let Ok(msg_str) = serde_json::to_string(&answer_msg) {
-                                    if let Some(client) =
-                                        with_signaling_manager(|mgr| mgr.get_client(&peer_id))
-                                    {
+                                    if let Some(client) = context.signaling().get_client(&peer_id) {
                                         let client_ref = client.borrow();
                                         let websocket = client_ref.get_websocket();
 
                                         if websocket.ready_state() != web_sys::WebSocket::OPEN {
-                                            platform_for_handler
-                                                .logger()
-                                                .warn("WebSocket not ready, cannot send answer");
+                                            tracing::warn!(
+                                                "WebSocket not ready, cannot send answer"
+                                            );
                                             return Err(JsValue::from_str(
                                                 "WebSocket not ready, cannot send answer",
                                             ));
@@ -878,8 +1208,8 @@ impl WebRtcPeer {
                             SignalingMessage::Answer { from, sdp, .. } => {
                                 // Set remote peer ID
                                 {
-                                    let mut peer_ref = peer_clone.borrow_mut();
-                                    peer_ref.remote_peer_id = Some(from.clone());
+                                    let peer_ref = peer_clone.borrow();
+                                    peer_ref.set_remote_peer_id(from.clone());
                                 }
 
                                 // Handle answer
@@ -897,23 +1227,22 @@ impl WebRtcPeer {
                     };
 
                     if let Err(e) = handle_message.await {
-                        platform_for_spawn
-                            .logger()
-                            .error(&format!("Error handling signaling message: {:?}", e));
+                        tracing::error!("Error handling signaling message: {:?}", e);
                     }
                 }
             }
         });
 
-        platform.logger().log("Waiting for WebSocket connection...");
+        tracing::debug!("Waiting for WebSocket connection...");
         let ws_ready = js_sys::Promise::new(&mut |resolve, reject| {
             let peer_id = self.metadata.peer_id.clone();
+            let context = self.context.clone();
             let reject_clone = reject.clone();
 
-            if let Some(client) = with_signaling_manager(|mgr| mgr.get_client(&peer_id)) {
+            if let Some(client) = context.signaling().get_client(&peer_id) {
                 let client_ref = client.borrow();
                 if client_ref.get_websocket().ready_state() == web_sys::WebSocket::OPEN {
-                    platform.clone().logger().log("WebSocket already connected");
+                    tracing::debug!("WebSocket already connected");
                     resolve.call0(&JsValue::NULL).unwrap_or_default();
                     return;
                 }
@@ -922,24 +1251,20 @@ impl WebRtcPeer {
             let onopen = {
                 let peer_id = peer_id.clone();
                 let reject = reject_clone.clone();
-                let platform = platform.clone();
+                let context = context.clone();
                 Closure::wrap(Box::new(move || {
-                    platform.logger().log("WebSocket connection opened");
+                    tracing::debug!("WebSocket connection opened");
 
-                    if let Some(client) = with_signaling_manager(|mgr| mgr.get_client(&peer_id)) {
+                    if let Some(client) = context.signaling().get_client(&peer_id) {
                         let join_msg = SignalingMessage::Join {
                             peer_id: peer_id.clone(),
                         };
                         if let Ok(msg_str) = serde_json::to_string(&join_msg) {
-                            platform
-                                .logger()
-                                .log(&format!("Sending join message: {}", msg_str));
+                            tracing::debug!("Sending join message: {}", msg_str);
                             match client.borrow().get_websocket().send_with_str(&msg_str) {
-                                Ok(_) => platform.logger().log("Join message sent successfully"),
+                                Ok(_) => tracing::debug!("Join message sent successfully"),
                                 Err(e) => {
-                                    platform
-                                        .logger()
-                                        .error(&format!("Failed to send join message: {:?}", e));
+                                    tracing::error!("Failed to send join message: {:?}", e);
                                     reject.call1(&JsValue::NULL, &e).unwrap_or_default();
                                     return;
                                 }
@@ -952,16 +1277,13 @@ impl WebRtcPeer {
 
             let onerror = {
                 let reject = reject_clone;
-                let platform = platform.clone();
                 Closure::wrap(Box::new(move |e: ErrorEvent| {
-                    platform
-                        .logger()
-                        .error(&format!("WebSocket error: {:?}", e));
+                    tracing::error!("WebSocket error: {:?}", e);
                     reject.call1(&JsValue::NULL, &e.into()).unwrap_or_default();
                 }) as Box<dyn FnMut(ErrorEvent)>)
             };
 
-            if let Some(client) = with_signaling_manager(|mgr| mgr.get_client(&peer_id)) {
+            if let Some(client) = context.signaling().get_client(&peer_id) {
                 let client_ref = client.borrow();
                 let ws = client_ref.get_websocket();
                 ws.set_onopen(Some(onopen.as_ref().unchecked_ref()));
@@ -978,53 +1300,61 @@ impl WebRtcPeer {
             }
         });
 
-        platform.logger().log("Awaiting WebSocket ready promise...");
+        tracing::debug!("Awaiting WebSocket ready promise...");
         JsFuture::from(ws_ready).await?;
-        platform.logger().log("WebSocket connection established");
+        tracing::debug!("WebSocket connection established");
 
         if self.is_offerer {
-            if let Some(target_id) = &self.remote_peer_id {
-                platform.logger().log("Creating offer as offerer...");
+            if let Some(target_id) = self.remote_peer_id() {
+                tracing::debug!(trace_id = %self.trace_id(), "Creating offer as offerer...");
                 let offer = self.create_offer().await?;
 
                 let offer_msg = SignalingMessage::Offer {
                     from: self.metadata.peer_id.clone(),
                     to: target_id.clone(),
                     sdp: offer,
+                    traceparent: self.next_traceparent(),
                 };
 
                 if let Ok(msg_str) = serde_json::to_string(&offer_msg) {
-                    platform.logger().log(&format!(
+                    tracing::debug!(
                         "Sending offer from {} to {}: {}",
-                        self.metadata.peer_id, target_id, msg_str
-                    ));
+                        self.metadata.peer_id,
+                        target_id,
+                        msg_str
+                    );
                     if let Some(client) =
-                        with_signaling_manager(|mgr| mgr.get_client(&self.metadata.peer_id))
+                        self.context.signaling().get_client(&self.metadata.peer_id)
                     {
                         let client_ref = client.borrow();
                         let ws = client_ref.get_websocket();
-                        platform.logger().log(&format!(
+                        tracing::debug!(
                             "WebSocket state before sending offer: {:?}",
                             ws.ready_state()
-                        ));
+                        );
                         if let Err(e) = ws.send_with_str(&msg_str) {
-                            platform
-                                .logger()
-                                .error(&format!("Failed to send offer: {:?}", e));
+                            tracing::error!("Failed to send offer: {:?}", e);
                             return Err(e);
                         }
-                        platform.logger().log("Offer sent successfully");
+                        tracing::debug!("Offer sent successfully");
                     }
                 }
             }
         }
 
-        platform
-            .logger()
-            .log("Connection setup complete. Waiting for peer connection to establish...");
+        tracing::debug!("Connection setup complete. Waiting for peer connection to establish...");
         Ok(())
     }
 
+    /// Closes the data channel and the peer connection.
+    pub fn close(&self) {
+        if let Some(channel) = &self.data_channel {
+            channel.close();
+        }
+        self.connection.close();
+        *self.connected.borrow_mut() = false;
+    }
+
     pub fn send_message(&self, data: Vec<u8>) -> Result<(), JsValue> {
         if let Some(channel) = &self.data_channel {
             let array = js_sys::Uint8Array::new_with_length(data.len() as u32);
@@ -1056,7 +1386,6 @@ impl WebRtcPeer {
     }
 
     pub async fn handle_connection_state_update(&mut self) {
-        let platform = self.platform.clone();
         let (state_sender, mut state_receiver) = mpsc::unbounded();
         self.connection_state_sender = state_sender;
 
@@ -1066,19 +1395,14 @@ impl WebRtcPeer {
             async move {
                 while let Some(is_connected) = state_receiver.next().await {
                     *connected.borrow_mut() = is_connected;
-                    platform
-                        .logger()
-                        .log(&format!("Updated connection state: {}", is_connected));
+                    tracing::debug!("Updated connection state: {}", is_connected);
                 }
             }
         });
     }
 
     pub async fn handle_ice_candidate(&self, candidate_str: &str) -> Result<(), JsValue> {
-        self.platform.logger().log(&format!(
-            "Handling incoming ICE candidate: {}",
-            candidate_str
-        ));
+        tracing::debug!("Handling incoming ICE candidate: {}", candidate_str);
 
         let candidate_init = RtcIceCandidateInit::new(candidate_str);
         candidate_init.set_sdp_mid(Some("0"));
@@ -1086,11 +1410,11 @@ impl WebRtcPeer {
 
         match RtcIceCandidate::new(&candidate_init) {
             Ok(candidate) => {
-                self.platform.logger().log(&format!(
+                tracing::debug!(
                     "Created ICE candidate object: sdp_mid={:?}, sdp_m_line_index={:?}",
                     candidate.sdp_mid(),
                     candidate.sdp_m_line_index()
-                ));
+                );
 
                 match JsFuture::from(
                     self.connection
@@ -1099,23 +1423,17 @@ impl WebRtcPeer {
                 .await
                 {
                     Ok(_) => {
-                        self.platform
-                            .logger()
-                            .log("Successfully added ICE candidate");
+                        tracing::debug!("Successfully added ICE candidate");
                         Ok(())
                     }
                     Err(e) => {
-                        self.platform
-                            .logger()
-                            .error(&format!("Failed to add ICE candidate: {:?}", e));
+                        tracing::error!("Failed to add ICE candidate: {:?}", e);
                         Err(e)
                     }
                 }
             }
             Err(e) => {
-                self.platform
-                    .logger()
-                    .error(&format!("Failed to create ICE candidate: {:?}", e));
+                tracing::error!("Failed to create ICE candidate: {:?}", e);
                 Err(e)
             }
         }
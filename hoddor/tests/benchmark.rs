@@ -28,7 +28,7 @@ async fn performance_test_bulk_upserts() {
         .await
         .expect("Failed to create vault for performance test");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
@@ -39,6 +39,7 @@ async fn performance_test_bulk_upserts() {
         JsValue::from_str("initial_data"),
         None,
         false,
+        None,
     )
     .await
     .expect("Failed to upsert initial data");
@@ -58,6 +59,7 @@ async fn performance_test_bulk_upserts() {
             JsValue::from_str(&data),
             None,
             false,
+            None,
         )
         .await
         .expect("Failed to upsert data in bulk");
@@ -68,7 +70,7 @@ async fn performance_test_bulk_upserts() {
     let t4 = platform.clock().now();
     for i in 0..num_upserts {
         let namespace = format!("{}{}", namespace_base, i);
-        read_from_vault(vault_name, &identity, JsValue::from_str(&namespace))
+        read_from_vault(vault_name, &identity, JsValue::from_str(&namespace), None)
             .await
             .expect("Failed to read data in bulk");
     }
@@ -111,18 +113,26 @@ async fn performance_test_large_data() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
-    upsert_vault(vault_name, &identity, namespace, data.clone(), None, false)
-        .await
-        .expect("Failed to create vault with large data");
+    upsert_vault(
+        vault_name,
+        &identity,
+        namespace,
+        data.clone(),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to create vault with large data");
     let t1 = platform.clock().now();
     let vault_creation_time = t1 - t0;
 
     let t2 = platform.clock().now();
-    let read_data = read_from_vault(vault_name, &identity, JsValue::from_str(namespace))
+    let read_data = read_from_vault(vault_name, &identity, JsValue::from_str(namespace), None)
         .await
         .expect("Failed to read large data");
     let t3 = platform.clock().now();
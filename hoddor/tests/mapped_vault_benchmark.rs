@@ -0,0 +1,90 @@
+#![cfg(all(feature = "mapped", not(target_arch = "wasm32")))]
+
+use futures::executor::block_on;
+use hoddor::domain::crypto::{generate_identity, identity_to_public};
+use hoddor::domain::vault::operations::read_namespace;
+use hoddor::facades::native::VaultManager;
+use hoddor::platform::Platform;
+
+#[test]
+fn performance_test_mapped_vault_read() {
+    let vault_name = "perf_mapped_vault";
+    let num_namespaces = 32;
+    let namespace_size = 256 * 1024;
+    let platform = Platform::new();
+    let manager = VaultManager::new();
+
+    block_on(async {
+        let _ = manager.remove_vault(vault_name).await;
+        manager
+            .create_vault(vault_name)
+            .await
+            .expect("Failed to create vault for performance test");
+
+        let identity = generate_identity(&platform).expect("Failed to create identity");
+        let public_key = identity_to_public(&platform, &identity).expect("Invalid identity");
+        for i in 0..num_namespaces {
+            manager
+                .upsert_namespace(
+                    vault_name,
+                    &public_key,
+                    &format!("namespace_{i}"),
+                    vec![i as u8; namespace_size],
+                    None,
+                    false,
+                )
+                .await
+                .expect("Failed to upsert data");
+        }
+
+        // The whole-vault path loads every namespace to read a single one.
+        let t0 = platform.clock().now();
+        let data = read_namespace(&platform, vault_name, &identity, "namespace_7")
+            .await
+            .expect("Failed to read namespace");
+        let t1 = platform.clock().now();
+        let whole_vault_time = t1 - t0;
+        assert_eq!(data, vec![7; namespace_size]);
+
+        let t2 = platform.clock().now();
+        let vault = manager
+            .open_vault_mapped(vault_name)
+            .await
+            .expect("Failed to open mapped vault");
+        let data = vault
+            .read_namespace(&identity, "namespace_7")
+            .await
+            .expect("Failed to read mapped namespace");
+        let t3 = platform.clock().now();
+        let mapped_time = t3 - t2;
+        assert_eq!(data, vec![7; namespace_size]);
+
+        let t4 = platform.clock().now();
+        for i in 0..num_namespaces {
+            vault
+                .read_namespace(&identity, &format!("namespace_{i}"))
+                .await
+                .expect("Failed to read mapped namespace");
+        }
+        let t5 = platform.clock().now();
+        let mapped_all_time = t5 - t4;
+        drop(vault);
+
+        manager
+            .remove_vault(vault_name)
+            .await
+            .expect("Failed to remove performance test vault");
+
+        platform.logger().log(&format!(
+            "Performance Report for Mapped Vault ({} namespaces of {} KiB):\n\
+            Whole-vault read of one namespace: {:.3}ms\n\
+            Mapped open and read of one namespace: {:.3}ms\n\
+            Mapped read of every namespace: {:.3}ms\n",
+            num_namespaces,
+            namespace_size / 1024,
+            whole_vault_time,
+            mapped_time,
+            mapped_all_time
+        ));
+    });
+}
@@ -0,0 +1,248 @@
+//! Checks the published fixtures in `tests/vectors` against this build. A
+//! failure here means the on-disk or on-wire format changed: regenerate the
+//! fixtures only if the change is intended and documented.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hoddor::domain::crypto;
+use hoddor::platform::Platform;
+use serde::Deserialize;
+
+const ARGON2: &str = include_str!("vectors/argon2.json");
+const AGE: &str = include_str!("vectors/age.json");
+const PRF: &str = include_str!("vectors/prf.json");
+const REFERENCE_VAULT: &[u8] = include_bytes!("vectors/reference.vault");
+#[cfg(not(target_arch = "wasm32"))]
+const REFERENCE: &str = include_str!("vectors/reference.json");
+const SYNC_MESSAGES: &str = include_str!("vectors/sync_messages.json");
+
+#[derive(Deserialize)]
+struct Argon2Vector {
+    passphrase: String,
+    salt: String,
+    seed: String,
+    identity: String,
+    recipient: String,
+}
+
+#[derive(Deserialize)]
+struct AgeVector {
+    identity: String,
+    recipient: String,
+    plaintext: String,
+    ciphertext: String,
+}
+
+#[derive(Deserialize)]
+struct PrfVector {
+    first: String,
+    second: String,
+    seed: String,
+    identity: String,
+    recipient: String,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Deserialize)]
+struct Reference {
+    passphrase: String,
+    identity: String,
+    recipient: String,
+    namespaces: std::collections::BTreeMap<String, String>,
+}
+
+fn parse<T: serde::de::DeserializeOwned>(json: &str) -> Vec<T> {
+    serde_json::from_str(json).unwrap()
+}
+
+fn seed(hex_seed: &str) -> [u8; 32] {
+    hex::decode(hex_seed).unwrap().try_into().unwrap()
+}
+
+#[test]
+fn argon2_vectors() {
+    let platform = Platform::new();
+
+    for vector in parse::<Argon2Vector>(ARGON2) {
+        let salt = hex::decode(&vector.salt).unwrap();
+        let derived = futures::executor::block_on(
+            platform
+                .kdf()
+                .derive_from_passphrase(&vector.passphrase, &salt),
+        )
+        .unwrap();
+        assert_eq!(hex::encode(derived), vector.seed, "{}", vector.passphrase);
+
+        let identity = futures::executor::block_on(crypto::identity_from_passphrase(
+            &platform,
+            &vector.passphrase,
+            &salt,
+        ))
+        .unwrap();
+        assert_eq!(identity, vector.identity);
+        assert_eq!(
+            crypto::identity_to_public(&platform, &identity).unwrap(),
+            vector.recipient
+        );
+    }
+}
+
+#[test]
+fn age_vectors() {
+    let platform = Platform::new();
+
+    for vector in parse::<AgeVector>(AGE) {
+        assert_eq!(
+            crypto::identity_to_public(&platform, &vector.identity).unwrap(),
+            vector.recipient
+        );
+
+        let ciphertext = STANDARD.decode(&vector.ciphertext).unwrap();
+        let plaintext = futures::executor::block_on(crypto::decrypt_with_identity(
+            &platform,
+            &ciphertext,
+            &vector.identity,
+        ))
+        .unwrap();
+        assert_eq!(hex::encode(plaintext), vector.plaintext);
+    }
+}
+
+/// Native builds have no authenticator, so the PRF mixing is recomputed here
+/// and only the identity encoding goes through the platform.
+#[test]
+fn prf_vectors() {
+    use hkdf::Hkdf;
+    use sha2::{Digest, Sha256};
+
+    let platform = Platform::new();
+
+    for vector in parse::<PrfVector>(PRF) {
+        let mut prf = hex::decode(&vector.first).unwrap();
+        prf.extend(hex::decode(&vector.second).unwrap());
+        let (prk, _) = Hkdf::<Sha256>::extract(Some(b"hoddor/vault"), &Sha256::digest(&prf));
+        assert_eq!(hex::encode(prk), vector.seed);
+
+        let identity = platform.identity().from_seed(seed(&vector.seed)).unwrap();
+        assert_eq!(identity, vector.identity);
+        assert_eq!(
+            crypto::identity_to_public(&platform, &identity).unwrap(),
+            vector.recipient
+        );
+    }
+}
+
+#[test]
+fn reference_vault_reserializes_byte_for_byte() {
+    use hoddor::domain::vault::serialization::{deserialize_vault, serialize_vault};
+
+    let vault = deserialize_vault(REFERENCE_VAULT).unwrap();
+    assert_eq!(serialize_vault(&vault).unwrap(), REFERENCE_VAULT);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn reference_vault_opens() {
+    use hoddor::facades::native::VaultManager;
+
+    let reference: Reference = serde_json::from_str(REFERENCE).unwrap();
+    let manager = VaultManager::new();
+    let vault_name = format!("test-vectors-{}", std::process::id());
+
+    futures::executor::block_on(async {
+        manager
+            .import_vault(&vault_name, REFERENCE_VAULT)
+            .await
+            .unwrap();
+
+        let (recipient, identity) = manager
+            .derive_identity_from_passphrase(&reference.passphrase, &vault_name)
+            .await
+            .unwrap();
+        assert_eq!(recipient, reference.recipient);
+        assert_eq!(identity, reference.identity);
+
+        let mut namespaces = manager.list_namespaces(&vault_name).await.unwrap();
+        namespaces.sort();
+        assert_eq!(
+            namespaces,
+            reference.namespaces.keys().cloned().collect::<Vec<_>>()
+        );
+
+        for (namespace, expected) in &reference.namespaces {
+            let data = manager
+                .read_namespace(&vault_name, &identity, namespace)
+                .await
+                .unwrap();
+            assert_eq!(String::from_utf8(data).unwrap(), *expected, "{namespace}");
+        }
+
+        manager.remove_vault(&vault_name).await.unwrap();
+    });
+}
+
+#[test]
+fn sync_messages_are_valid_json() {
+    let samples: serde_json::Value = serde_json::from_str(SYNC_MESSAGES).unwrap();
+    for kind in ["sync", "app", "hello", "signaling"] {
+        assert!(
+            samples[kind].as_array().is_some_and(|a| !a.is_empty()),
+            "{kind}"
+        );
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod wasm {
+    use super::*;
+    use hoddor::signaling::SignalingMessage;
+    use hoddor::sync::{AppMessage, PeerHello, SyncMessage};
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn samples<T: serde::de::DeserializeOwned>(kind: &str) -> Vec<T> {
+        let samples: serde_json::Value = serde_json::from_str(SYNC_MESSAGES).unwrap();
+        serde_json::from_value(samples[kind].clone()).unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn sync_message_samples_decode() {
+        let messages = samples::<SyncMessage>("sync");
+        assert_eq!(messages[0].operation.author, "peer-a");
+        assert_eq!(
+            messages[1].operation.nonce,
+            Some(std::array::from_fn(|i| i as u8))
+        );
+        assert!(messages[2].operation.data.is_none());
+        assert_eq!(messages[2].sent_at, 0);
+
+        samples::<AppMessage>("app");
+        let hellos = samples::<PeerHello>("hello");
+        assert!(!hellos[0].reply && hellos[1].reply);
+    }
+
+    #[wasm_bindgen_test]
+    fn signaling_samples_roundtrip() {
+        let expected: serde_json::Value = serde_json::from_str(SYNC_MESSAGES).unwrap();
+        let messages = samples::<SignalingMessage>("signaling");
+        assert_eq!(
+            serde_json::to_value(&messages).unwrap(),
+            expected["signaling"]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn prf_vectors_through_webauthn() {
+        let platform = Platform::new();
+
+        for vector in parse::<PrfVector>(PRF) {
+            let identity = crypto::identity_from_prf(
+                &platform,
+                &hex::decode(&vector.first).unwrap(),
+                &hex::decode(&vector.second).unwrap(),
+            )
+            .unwrap();
+            assert_eq!(identity, vector.identity);
+        }
+    }
+}
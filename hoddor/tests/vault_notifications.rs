@@ -98,13 +98,21 @@ async fn test_notification_on_upsert() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
-    upsert_vault(vault_name, &identity, namespace, data.clone(), None, false)
-        .await
-        .expect("Failed to upsert data");
+    upsert_vault(
+        vault_name,
+        &identity,
+        namespace,
+        data.clone(),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert data");
 
     if let Some(message) = listener.wait_for_message(1000) {
         Platform::new().logger().log("Got notification message!");
@@ -147,13 +155,21 @@ async fn test_notification_contains_vault_data() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
-    upsert_vault(vault_name, &identity, namespace, data.clone(), None, false)
-        .await
-        .expect("Failed to upsert data");
+    upsert_vault(
+        vault_name,
+        &identity,
+        namespace,
+        data.clone(),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert data");
 
     if let Some(message) = listener.wait_for_message(1000) {
         let vault_data = js_sys::Reflect::get(&message, &JsValue::from_str("data"))
@@ -194,13 +210,21 @@ async fn test_notification_on_remove() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
-    upsert_vault(vault_name, &identity, namespace, data.clone(), None, false)
-        .await
-        .expect("Failed to upsert data");
+    upsert_vault(
+        vault_name,
+        &identity,
+        namespace,
+        data.clone(),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert data");
 
     listener.clear();
 
@@ -243,7 +267,7 @@ async fn test_multiple_notifications() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
@@ -251,9 +275,17 @@ async fn test_multiple_notifications() {
         let namespace = format!("namespace_{}", i);
         let data: JsValue = format!("data_{}", i).into();
 
-        upsert_vault(vault_name, &identity, &namespace, data.clone(), None, false)
-            .await
-            .expect("Failed to upsert data");
+        upsert_vault(
+            vault_name,
+            &identity,
+            &namespace,
+            data.clone(),
+            None,
+            false,
+            None,
+        )
+        .await
+        .expect("Failed to upsert data");
     }
 
     gloo_timers::future::TimeoutFuture::new(500).await;
@@ -4,10 +4,13 @@ extern crate wasm_bindgen_test;
 use futures_util::future;
 use gloo_timers::future::TimeoutFuture;
 use hoddor::{
+    domain::vault::wal,
     facades::wasm::vault::{
-        create_vault, export_vault, force_cleanup_vault, import_vault, list_namespaces,
-        list_vaults, read_from_vault, remove_from_vault, remove_vault, upsert_vault,
-        vault_identity_from_passphrase,
+        close_vault, create_observer_vault, create_vault, export_vault, force_cleanup_vault,
+        grant_vault_recipient, import_vault, is_observer_vault, list_namespaces, list_vaults,
+        open_vault, promote_observer_vault, read_from_vault, recover_vaults, remove_from_vault,
+        remove_vault, upsert_vault, vault_handle_list_namespaces, vault_handle_read,
+        vault_handle_remove, vault_handle_upsert, vault_identity_from_passphrase, Vault,
     },
     platform::Platform,
 };
@@ -31,13 +34,21 @@ async fn test_vault_crud_operations() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, "default")
+    let identity = vault_identity_from_passphrase(password, "default", None)
         .await
         .expect("Failed to create identity");
 
-    upsert_vault("default", &identity, namespace, data.clone(), None, false)
-        .await
-        .expect("Failed to upsert data");
+    upsert_vault(
+        "default",
+        &identity,
+        namespace,
+        data.clone(),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert data");
 
     test_utils::cleanup_all_vaults().await;
 }
@@ -54,17 +65,17 @@ async fn test_list_namespaces() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, "default")
+    let identity = vault_identity_from_passphrase(password, "default", None)
         .await
         .expect("Failed to create identity");
 
     for ns in &namespaces {
-        upsert_vault("default", &identity, ns, data.clone(), None, false)
+        upsert_vault("default", &identity, ns, data.clone(), None, false, None)
             .await
             .expect("Failed to add namespace to vault");
     }
 
-    let listed = list_namespaces("default")
+    let listed = list_namespaces("default", None)
         .await
         .expect("Failed to list namespaces");
     let listed_namespaces: Vec<String> = from_value(listed).expect("Failed to convert namespaces");
@@ -98,19 +109,25 @@ async fn test_invalid_password() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, "default-2")
+    let identity = vault_identity_from_passphrase(password, "default-2", None)
         .await
         .expect("Failed to create identity");
 
-    upsert_vault("default-2", &identity, namespace, data, None, false)
+    upsert_vault("default-2", &identity, namespace, data, None, false, None)
         .await
         .expect("Failed to upsert data");
 
-    let wrong_identity = vault_identity_from_passphrase(wrong_password, "default-2")
+    let wrong_identity = vault_identity_from_passphrase(wrong_password, "default-2", None)
         .await
         .expect("Failed to create wrong identity");
 
-    let result = read_from_vault("default-2", &wrong_identity, JsValue::from_str(namespace)).await;
+    let result = read_from_vault(
+        "default-2",
+        &wrong_identity,
+        JsValue::from_str(namespace),
+        None,
+    )
+    .await;
     assert!(result.is_err(), "Should fail with wrong password");
 
     test_utils::cleanup_all_vaults().await;
@@ -129,7 +146,7 @@ async fn test_list_vaults() {
             .await
             .expect("Failed to create test vault");
 
-        let identity = vault_identity_from_passphrase(password, vault_name)
+        let identity = vault_identity_from_passphrase(password, vault_name, None)
             .await
             .expect("Failed to create identity");
 
@@ -140,6 +157,7 @@ async fn test_list_vaults() {
             JsValue::from_str(data),
             None,
             false,
+            None,
         )
         .await
         .expect("Failed to upsert data");
@@ -205,13 +223,21 @@ async fn test_duplicate_vault_creation() {
         .await
         .expect("Failed to create first vault");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
-    upsert_vault(vault_name, &identity, namespace, data1.clone(), None, false)
-        .await
-        .expect("Failed to upsert data");
+    upsert_vault(
+        vault_name,
+        &identity,
+        namespace,
+        data1.clone(),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert data");
 
     let result = create_vault(JsValue::from_str(vault_name)).await;
     assert!(
@@ -219,7 +245,7 @@ async fn test_duplicate_vault_creation() {
         "Should not be able to create duplicate vault"
     );
 
-    let read_data = read_from_vault(vault_name, &identity, JsValue::from_str(namespace))
+    let read_data = read_from_vault(vault_name, &identity, JsValue::from_str(namespace), None)
         .await
         .expect("Failed to read vault");
 
@@ -247,15 +273,23 @@ async fn test_special_characters_in_namespace() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
-    upsert_vault(vault_name, &identity, namespace, data.clone(), None, false)
-        .await
-        .expect("Failed to upsert with special characters");
+    upsert_vault(
+        vault_name,
+        &identity,
+        namespace,
+        data.clone(),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert with special characters");
 
-    let read_data = read_from_vault(vault_name, &identity, JsValue::from_str(namespace))
+    let read_data = read_from_vault(vault_name, &identity, JsValue::from_str(namespace), None)
         .await
         .expect("Failed to read vault with special characters");
 
@@ -286,7 +320,7 @@ async fn test_concurrent_vault_operations() {
 
         let future = async move {
             create_vault(JsValue::from_str(&vault_name)).await?;
-            let identity = vault_identity_from_passphrase(&password, &vault_name).await?;
+            let identity = vault_identity_from_passphrase(&password, &vault_name, None).await?;
             upsert_vault(
                 &vault_name,
                 &identity,
@@ -294,6 +328,7 @@ async fn test_concurrent_vault_operations() {
                 JsValue::from_str(&data_val),
                 None,
                 false,
+                None,
             )
             .await
         };
@@ -321,11 +356,20 @@ async fn test_empty_namespace() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
-    let result = upsert_vault(vault_name, &identity, namespace, data.clone(), None, false).await;
+    let result = upsert_vault(
+        vault_name,
+        &identity,
+        namespace,
+        data.clone(),
+        None,
+        false,
+        None,
+    )
+    .await;
     assert!(result.is_err(), "Should fail with empty namespace");
 
     test_utils::cleanup_all_vaults().await;
@@ -344,15 +388,23 @@ async fn test_empty_data() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
-    upsert_vault(vault_name, &identity, namespace, data.clone(), None, false)
-        .await
-        .expect("Failed to upsert empty data");
+    upsert_vault(
+        vault_name,
+        &identity,
+        namespace,
+        data.clone(),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert empty data");
 
-    let read_data = read_from_vault(vault_name, &identity, JsValue::from_str(namespace))
+    let read_data = read_from_vault(vault_name, &identity, JsValue::from_str(namespace), None)
         .await
         .expect("Failed to read from vault with empty data");
     assert_eq!(read_data.as_string().unwrap(), "");
@@ -402,7 +454,7 @@ async fn test_concurrent_vault_creation() {
             .await
             .expect("Failed to create vault");
 
-        let identity = vault_identity_from_passphrase(&passwords[i], &vault_names[i])
+        let identity = vault_identity_from_passphrase(&passwords[i], &vault_names[i], None)
             .await
             .expect("Failed to create identity");
 
@@ -413,6 +465,7 @@ async fn test_concurrent_vault_creation() {
             JsValue::from_str(&data[i]),
             None,
             false,
+            None,
         )
         .await
         .expect("Failed to upsert initial data");
@@ -427,7 +480,7 @@ async fn test_concurrent_vault_creation() {
         let data_val = data[i].clone();
 
         let future = async move {
-            let identity = vault_identity_from_passphrase(&password, &vault_name).await?;
+            let identity = vault_identity_from_passphrase(&password, &vault_name, None).await?;
             upsert_vault(
                 &vault_name,
                 &identity,
@@ -435,6 +488,7 @@ async fn test_concurrent_vault_creation() {
                 JsValue::from_str(&data_val),
                 None,
                 false,
+                None,
             )
             .await
         };
@@ -461,11 +515,11 @@ async fn test_read_non_existent_namespace() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
-    let result = read_from_vault(vault_name, &identity, JsValue::from_str(namespace)).await;
+    let result = read_from_vault(vault_name, &identity, JsValue::from_str(namespace), None).await;
     assert!(
         result.is_err(),
         "Reading from a non-existent namespace should fail"
@@ -486,19 +540,27 @@ async fn test_list_namespaces_in_empty_vault() {
         .await
         .expect("Failed to create initial vault");
 
-    let identity = vault_identity_from_passphrase(password, "default")
+    let identity = vault_identity_from_passphrase(password, "default", None)
         .await
         .expect("Failed to create identity");
 
-    upsert_vault("default", &identity, namespace, data.clone(), None, false)
-        .await
-        .expect("Failed to upsert initial data");
+    upsert_vault(
+        "default",
+        &identity,
+        namespace,
+        data.clone(),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert initial data");
 
     remove_from_vault("default", &identity, JsValue::from_str(namespace))
         .await
         .expect("Failed to remove initial namespace");
 
-    let listed = list_namespaces("default")
+    let listed = list_namespaces("default", None)
         .await
         .expect("Failed to list namespaces");
     let listed_namespaces: Vec<String> = from_value(listed).expect("Failed to convert namespaces");
@@ -523,17 +585,25 @@ async fn test_concurrent_read_operations() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, "default")
+    let identity = vault_identity_from_passphrase(password, "default", None)
         .await
         .expect("Failed to create identity");
 
-    upsert_vault("default", &identity, namespace, data.clone(), None, false)
-        .await
-        .expect("Failed to upsert data");
+    upsert_vault(
+        "default",
+        &identity,
+        namespace,
+        data.clone(),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert data");
 
     let mut read_futures = Vec::new();
     for _ in 0..3 {
-        let future = read_from_vault("default", &identity, JsValue::from_str(namespace));
+        let future = read_from_vault("default", &identity, JsValue::from_str(namespace), None);
         read_futures.push(future);
     }
 
@@ -560,7 +630,7 @@ async fn test_data_expiration() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
@@ -571,11 +641,12 @@ async fn test_data_expiration() {
         data.clone(),
         expires_in_seconds,
         false,
+        None,
     )
     .await
     .expect("Failed to upsert data with expiration");
 
-    let initial_read = read_from_vault(vault_name, &identity, JsValue::from_str(namespace))
+    let initial_read = read_from_vault(vault_name, &identity, JsValue::from_str(namespace), None)
         .await
         .expect("Failed to read expiring data immediately");
     assert_eq!(
@@ -586,7 +657,8 @@ async fn test_data_expiration() {
 
     TimeoutFuture::new(1100).await;
 
-    let expired_result = read_from_vault(vault_name, &identity, JsValue::from_str(namespace)).await;
+    let expired_result =
+        read_from_vault(vault_name, &identity, JsValue::from_str(namespace), None).await;
     assert!(expired_result.is_err(), "Reading expired data should fail");
 
     test_utils::cleanup_all_vaults().await;
@@ -605,7 +677,7 @@ async fn test_force_cleanup() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
@@ -616,6 +688,7 @@ async fn test_force_cleanup() {
         data.clone(),
         Some(1),
         false,
+        None,
     )
     .await
     .expect("Failed to upsert first namespace with expiration");
@@ -627,17 +700,18 @@ async fn test_force_cleanup() {
         JsValue::from_str("data2"),
         Some(1), // also 1 second
         false,
+        None,
     )
     .await
     .expect("Failed to insert second namespace with expiration");
 
     TimeoutFuture::new(1100).await;
 
-    force_cleanup_vault(vault_name)
+    force_cleanup_vault(vault_name, None)
         .await
         .expect("Failed to force cleanup vault");
 
-    let listed = list_namespaces(vault_name)
+    let listed = list_namespaces(vault_name, None)
         .await
         .expect("Failed to list namespaces after forced cleanup");
     let listed_namespaces: Vec<String> = from_value(listed).expect("Failed to convert namespaces");
@@ -662,15 +736,23 @@ async fn test_import_export_round_trip() {
         .await
         .expect("Failed to create vault for export");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
-    upsert_vault(vault_name, &identity, namespace, data.clone(), None, false)
-        .await
-        .expect("Failed to upsert data");
+    upsert_vault(
+        vault_name,
+        &identity,
+        namespace,
+        data.clone(),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert data");
 
-    let initial_data = read_from_vault(vault_name, &identity, JsValue::from_str(namespace))
+    let initial_data = read_from_vault(vault_name, &identity, JsValue::from_str(namespace), None)
         .await
         .expect("Failed to read data from created vault");
     assert_eq!(
@@ -679,7 +761,7 @@ async fn test_import_export_round_trip() {
         "Initial data mismatch"
     );
 
-    let exported_data = export_vault(vault_name)
+    let exported_data = export_vault(vault_name, None, None)
         .await
         .expect("Failed to export vault");
 
@@ -687,15 +769,15 @@ async fn test_import_export_round_trip() {
         .await
         .expect("Failed to remove vault");
 
-    import_vault(vault_name, exported_data)
+    import_vault(vault_name, exported_data, None, None)
         .await
         .expect("Failed to import vault");
 
-    let identity2 = vault_identity_from_passphrase(password, vault_name)
+    let identity2 = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity after import");
 
-    let read_data = read_from_vault(vault_name, &identity2, JsValue::from_str(namespace))
+    let read_data = read_from_vault(vault_name, &identity2, JsValue::from_str(namespace), None)
         .await
         .expect("Failed to read data from imported vault");
 
@@ -721,7 +803,7 @@ async fn test_expiration_on_read() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
@@ -732,13 +814,15 @@ async fn test_expiration_on_read() {
         data.clone(),
         Some(2),
         false,
+        None,
     )
     .await
     .expect("Failed to upsert data with short expiration");
 
     TimeoutFuture::new(3000).await;
 
-    let read_data = read_from_vault(vault_name, &identity, JsValue::from_str(namespace)).await;
+    let read_data =
+        read_from_vault(vault_name, &identity, JsValue::from_str(namespace), None).await;
 
     // Cleanup now happens automatically on read - expired data should fail to read
     assert!(
@@ -760,7 +844,7 @@ async fn test_concurrent_upserts_different_namespaces() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
@@ -771,6 +855,7 @@ async fn test_concurrent_upserts_different_namespaces() {
         JsValue::from_str("data0"),
         None,
         false,
+        None,
     )
     .await
     .expect("Failed to upsert initial namespace");
@@ -788,6 +873,7 @@ async fn test_concurrent_upserts_different_namespaces() {
             JsValue::from_str(&dt),
             None,
             false,
+            None,
         )
         .await
         {
@@ -801,7 +887,7 @@ async fn test_concurrent_upserts_different_namespaces() {
         }
     }
 
-    let namespaces = list_namespaces(vault_name)
+    let namespaces = list_namespaces(vault_name, None)
         .await
         .expect("Failed to list namespaces");
     let ns_array = js_sys::Array::from(&namespaces);
@@ -826,7 +912,7 @@ async fn test_concurrent_upserts_different_namespaces() {
             format!("data{}", i)
         };
 
-        match read_from_vault(vault_name, &identity, JsValue::from_str(&ns)).await {
+        match read_from_vault(vault_name, &identity, JsValue::from_str(&ns), None).await {
             Ok(read_val) => {
                 assert_eq!(
                     read_val.as_string().unwrap(),
@@ -859,7 +945,7 @@ async fn test_upsert_with_replace() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, "default")
+    let identity = vault_identity_from_passphrase(password, "default", None)
         .await
         .expect("Failed to create identity");
 
@@ -870,6 +956,7 @@ async fn test_upsert_with_replace() {
         initial_data.clone(),
         None,
         false,
+        None,
     )
     .await
     .expect("Failed to upsert initial data");
@@ -881,11 +968,12 @@ async fn test_upsert_with_replace() {
         updated_data.clone(),
         None,
         true,
+        None,
     )
     .await
     .expect("Failed to update data");
 
-    let read_data = read_from_vault("default", &identity, JsValue::from_str(namespace))
+    let read_data = read_from_vault("default", &identity, JsValue::from_str(namespace), None)
         .await
         .expect("Failed to read data");
     assert_eq!(read_data, updated_data, "Data was not updated correctly");
@@ -905,25 +993,34 @@ async fn test_namespace_removal_validation() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, "default")
+    let identity = vault_identity_from_passphrase(password, "default", None)
         .await
         .expect("Failed to create identity");
 
-    upsert_vault("default", &identity, namespace, data.clone(), None, false)
-        .await
-        .expect("Failed to upsert data");
+    upsert_vault(
+        "default",
+        &identity,
+        namespace,
+        data.clone(),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert data");
 
     remove_from_vault("default", &identity, JsValue::from_str(namespace))
         .await
         .expect("Failed to remove namespace");
 
-    let listed = list_namespaces("default")
+    let listed = list_namespaces("default", None)
         .await
         .expect("Failed to list namespaces");
     let listed_namespaces: Vec<String> = from_value(listed).expect("Failed to convert namespaces");
     assert!(!listed_namespaces.contains(&"test_namespace".to_string()));
 
-    let read_result = read_from_vault("default", &identity, JsValue::from_str(namespace)).await;
+    let read_result =
+        read_from_vault("default", &identity, JsValue::from_str(namespace), None).await;
     assert!(
         read_result.is_err(),
         "Should not be able to read removed namespace"
@@ -944,23 +1041,31 @@ async fn test_multiple_expired_namespaces() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, "default")
+    let identity = vault_identity_from_passphrase(password, "default", None)
         .await
         .expect("Failed to create identity");
 
     for ns in &namespaces {
-        upsert_vault("default", &identity, *ns, data.clone(), Some(1), false)
-            .await
-            .expect("Failed to add namespace");
+        upsert_vault(
+            "default",
+            &identity,
+            *ns,
+            data.clone(),
+            Some(1),
+            false,
+            None,
+        )
+        .await
+        .expect("Failed to add namespace");
     }
 
     TimeoutFuture::new(1500).await;
 
-    force_cleanup_vault("default")
+    force_cleanup_vault("default", None)
         .await
         .expect("Failed to force cleanup");
 
-    let listed = list_namespaces("default")
+    let listed = list_namespaces("default", None)
         .await
         .expect("Failed to list namespaces");
     let listed_namespaces: Vec<String> = from_value(listed).expect("Failed to convert namespaces");
@@ -986,15 +1091,23 @@ async fn test_large_data_payload() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, "default")
+    let identity = vault_identity_from_passphrase(password, "default", None)
         .await
         .expect("Failed to create identity");
 
-    upsert_vault("default", &identity, namespace, data.clone(), None, false)
-        .await
-        .expect("Failed to upsert large data");
+    upsert_vault(
+        "default",
+        &identity,
+        namespace,
+        data.clone(),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert large data");
 
-    let read_data = read_from_vault("default", &identity, JsValue::from_str(namespace))
+    let read_data = read_from_vault("default", &identity, JsValue::from_str(namespace), None)
         .await
         .expect("Failed to read large data");
 
@@ -1019,21 +1132,29 @@ async fn test_unicode_namespace() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, "default")
+    let identity = vault_identity_from_passphrase(password, "default", None)
         .await
         .expect("Failed to create identity");
 
-    upsert_vault("default", &identity, namespace, data.clone(), None, false)
-        .await
-        .expect("Failed to upsert data with Unicode namespace");
+    upsert_vault(
+        "default",
+        &identity,
+        namespace,
+        data.clone(),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert data with Unicode namespace");
 
-    let listed = list_namespaces("default")
+    let listed = list_namespaces("default", None)
         .await
         .expect("Failed to list namespaces");
     let listed_namespaces: Vec<String> = from_value(listed).expect("Failed to convert namespaces");
     assert!(listed_namespaces.contains(&"测试_namespace_🔒".to_string()));
 
-    let read_data = read_from_vault("default", &identity, JsValue::from_str(namespace))
+    let read_data = read_from_vault("default", &identity, JsValue::from_str(namespace), None)
         .await
         .expect("Failed to read from Unicode namespace");
     assert_eq!(
@@ -1056,7 +1177,7 @@ async fn test_concurrent_data_integrity() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, "default")
+    let identity = vault_identity_from_passphrase(password, "default", None)
         .await
         .expect("Failed to create identity");
 
@@ -1067,6 +1188,7 @@ async fn test_concurrent_data_integrity() {
         JsValue::from_str(base_data),
         None,
         false,
+        None,
     )
     .await
     .expect("Failed to create initial namespace");
@@ -1086,6 +1208,7 @@ async fn test_concurrent_data_integrity() {
                 JsValue::from_str(&data),
                 None,
                 false,
+                None,
             )
             .await
             {
@@ -1102,7 +1225,7 @@ async fn test_concurrent_data_integrity() {
         }
     }
 
-    let listed = list_namespaces("default")
+    let listed = list_namespaces("default", None)
         .await
         .expect("Failed to list namespaces");
     let listed_namespaces: Vec<String> = from_value(listed).expect("Failed to convert namespaces");
@@ -1117,7 +1240,7 @@ async fn test_concurrent_data_integrity() {
         let namespace = format!("{}{}", base_namespace, i);
         let expected_data = format!("{}{}", base_data, i);
 
-        let read_data = read_from_vault("default", &identity, JsValue::from_str(&namespace))
+        let read_data = read_from_vault("default", &identity, JsValue::from_str(&namespace), None)
             .await
             .expect("Failed to read data");
 
@@ -1144,7 +1267,7 @@ async fn test_concurrent_read_write_integrity() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, "default")
+    let identity = vault_identity_from_passphrase(password, "default", None)
         .await
         .expect("Failed to create identity");
 
@@ -1155,6 +1278,7 @@ async fn test_concurrent_read_write_integrity() {
         initial_data.clone(),
         None,
         false,
+        None,
     )
     .await
     .expect("Failed to upsert initial data");
@@ -1172,6 +1296,7 @@ async fn test_concurrent_read_write_integrity() {
             JsValue::from_str(&data),
             None,
             true,
+            None,
         ));
     }
 
@@ -1186,6 +1311,7 @@ async fn test_concurrent_read_write_integrity() {
             "default",
             &identity,
             JsValue::from_str(namespace),
+            None,
         ));
     }
 
@@ -1225,15 +1351,23 @@ async fn test_data_integrity_with_binary() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, "default")
+    let identity = vault_identity_from_passphrase(password, "default", None)
         .await
         .expect("Failed to create identity");
 
-    upsert_vault("default", &identity, namespace, data.clone(), None, false)
-        .await
-        .expect("Failed to upsert binary data");
+    upsert_vault(
+        "default",
+        &identity,
+        namespace,
+        data.clone(),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert binary data");
 
-    let read_data = read_from_vault("default", &identity, JsValue::from_str(namespace))
+    let read_data = read_from_vault("default", &identity, JsValue::from_str(namespace), None)
         .await
         .expect("Failed to read binary data");
 
@@ -1256,7 +1390,7 @@ async fn test_concurrent_same_namespace_upserts() {
         .await
         .expect("Failed to create vault");
 
-    let identity = vault_identity_from_passphrase(password, vault_name)
+    let identity = vault_identity_from_passphrase(password, vault_name, None)
         .await
         .expect("Failed to create identity");
 
@@ -1267,6 +1401,7 @@ async fn test_concurrent_same_namespace_upserts() {
         JsValue::from_str("initial_data"),
         None,
         false,
+        None,
     )
     .await
     .expect("Failed to create initial namespace");
@@ -1283,6 +1418,7 @@ async fn test_concurrent_same_namespace_upserts() {
             JsValue::from_str(&data),
             None,
             true,
+            None,
         ));
     }
 
@@ -1298,7 +1434,7 @@ async fn test_concurrent_same_namespace_upserts() {
     }
 
     let final_read_result =
-        read_from_vault(vault_name, &identity, JsValue::from_str(namespace)).await;
+        read_from_vault(vault_name, &identity, JsValue::from_str(namespace), None).await;
     let final_data = final_read_result
         .expect("Failed to read final data from concurrent upserts")
         .as_string()
@@ -1312,3 +1448,278 @@ async fn test_concurrent_same_namespace_upserts() {
 
     test_utils::cleanup_all_vaults().await;
 }
+
+#[wasm_bindgen_test]
+async fn test_observer_vault_promotion() {
+    test_utils::cleanup_all_vaults().await;
+
+    create_vault(JsValue::from_str("primary"))
+        .await
+        .expect("Failed to create vault");
+    let owner = vault_identity_from_passphrase("owner_password123", "primary", None)
+        .await
+        .expect("Failed to create owner identity");
+    upsert_vault(
+        "primary",
+        &owner,
+        "secrets",
+        JsValue::from_str("replicated"),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert data");
+
+    create_observer_vault("replica")
+        .await
+        .expect("Failed to create observer vault");
+    assert!(is_observer_vault("replica").await.unwrap());
+    let replica_identity = vault_identity_from_passphrase("replica_password123", "replica", None)
+        .await
+        .expect("Failed to create replica identity");
+
+    grant_vault_recipient("primary", &owner, &replica_identity.public_key(), None)
+        .await
+        .expect("Failed to grant replica recipient");
+
+    // Stand in for sync: copy the re-encrypted namespaces into the replica.
+    let platform = Platform::new();
+    let primary = hoddor::domain::vault::read_vault(&platform, "primary")
+        .await
+        .unwrap();
+    let mut replica = hoddor::domain::vault::read_vault(&platform, "replica")
+        .await
+        .unwrap();
+    replica.namespaces = primary.namespaces;
+    hoddor::domain::vault::save_vault(&platform, "replica", replica)
+        .await
+        .unwrap();
+
+    assert!(
+        read_from_vault(
+            "replica",
+            &replica_identity,
+            JsValue::from_str("secrets"),
+            None
+        )
+        .await
+        .is_err(),
+        "Observer replicas must refuse reads"
+    );
+
+    promote_observer_vault("replica", &replica_identity, None)
+        .await
+        .expect("Failed to promote observer");
+    assert!(!is_observer_vault("replica").await.unwrap());
+
+    let data = read_from_vault(
+        "replica",
+        &replica_identity,
+        JsValue::from_str("secrets"),
+        None,
+    )
+    .await
+    .expect("Promoted replica should decrypt")
+    .as_string()
+    .unwrap();
+    assert_eq!(data, "replicated");
+
+    let owner_data = read_from_vault("primary", &owner, JsValue::from_str("secrets"), None)
+        .await
+        .expect("Owner should still decrypt")
+        .as_string()
+        .unwrap();
+    assert_eq!(owner_data, "replicated");
+
+    test_utils::cleanup_all_vaults().await;
+}
+
+#[wasm_bindgen_test]
+async fn test_recover_vaults_replays_interrupted_save() {
+    test_utils::cleanup_all_vaults().await;
+
+    create_vault(JsValue::from_str("journaled"))
+        .await
+        .expect("Failed to create vault");
+    let identity = vault_identity_from_passphrase("journal_password123", "journaled", None)
+        .await
+        .expect("Failed to create identity");
+    upsert_vault(
+        "journaled",
+        &identity,
+        "notes",
+        JsValue::from_str("kept"),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert data");
+
+    let recovered: Vec<String> = from_value(recover_vaults().await.unwrap()).unwrap();
+    assert!(recovered.is_empty());
+
+    // Simulate a tab closed after journaling but before the namespace write.
+    let platform = Platform::new();
+    let storage = platform.storage();
+    let namespace_text = storage.read_file("journaled/notes.hoddor").await.unwrap();
+    wal::begin(
+        &platform,
+        "journaled",
+        vec![wal::WalWrite {
+            path: "journaled/restored.hoddor".to_string(),
+            content: namespace_text,
+        }],
+        vec![],
+    )
+    .await
+    .unwrap();
+
+    let recovered: Vec<String> = from_value(recover_vaults().await.unwrap()).unwrap();
+    assert_eq!(recovered, vec!["journaled".to_string()]);
+
+    let restored = read_from_vault("journaled", &identity, JsValue::from_str("restored"), None)
+        .await
+        .expect("Replayed namespace should be readable");
+    assert_eq!(restored.as_string().unwrap(), "kept");
+
+    test_utils::cleanup_all_vaults().await;
+}
+
+#[wasm_bindgen_test]
+async fn test_aborted_signal_cancels_upsert() {
+    test_utils::cleanup_all_vaults().await;
+
+    create_vault(JsValue::from_str("aborted"))
+        .await
+        .expect("Failed to create vault");
+    let identity = vault_identity_from_passphrase("abort_password123", "aborted", None)
+        .await
+        .expect("Failed to create identity");
+
+    let result = upsert_vault(
+        "aborted",
+        &identity,
+        "notes",
+        JsValue::from_str("never written"),
+        None,
+        false,
+        Some(web_sys::AbortSignal::abort()),
+    )
+    .await;
+    assert!(result.is_err(), "An aborted upsert must fail");
+
+    let namespaces: Vec<String> =
+        from_value(list_namespaces("aborted", None).await.unwrap()).unwrap();
+    assert!(namespaces.is_empty());
+
+    test_utils::cleanup_all_vaults().await;
+}
+
+#[wasm_bindgen_test]
+async fn test_vault_handle_lifecycle() {
+    test_utils::cleanup_all_vaults().await;
+
+    create_vault(JsValue::from_str("handled"))
+        .await
+        .expect("Failed to create vault");
+    let identity = vault_identity_from_passphrase("handle_password123", "handled", None)
+        .await
+        .expect("Failed to create identity");
+
+    let handle = open_vault("handled").await.expect("Failed to open vault");
+
+    vault_handle_upsert(
+        handle,
+        &identity,
+        "notes",
+        JsValue::from_str("through the handle"),
+        None,
+        false,
+        None,
+    )
+    .await
+    .expect("Failed to upsert through handle");
+
+    let read = vault_handle_read(handle, &identity, "notes", None)
+        .await
+        .expect("Failed to read through handle");
+    assert_eq!(read.as_string().unwrap(), "through the handle");
+
+    vault_handle_remove(handle, &identity, "notes")
+        .await
+        .expect("Failed to remove through handle");
+    let namespaces: Vec<String> =
+        from_value(vault_handle_list_namespaces(handle).await.unwrap()).unwrap();
+    assert!(namespaces.is_empty());
+
+    close_vault(handle);
+    assert!(vault_handle_list_namespaces(handle).await.is_err());
+
+    test_utils::cleanup_all_vaults().await;
+}
+
+#[wasm_bindgen_test]
+async fn test_vault_object_keeps_identity() {
+    test_utils::cleanup_all_vaults().await;
+
+    create_vault(JsValue::from_str("object"))
+        .await
+        .expect("Failed to create vault");
+
+    let vault = Vault::open("object").await.expect("Failed to open vault");
+    let notes = vault.namespace("notes").unwrap();
+
+    assert!(
+        notes.read(None).await.is_err(),
+        "A locked vault must not be readable"
+    );
+
+    vault
+        .unlock("object_password123", None)
+        .await
+        .expect("Failed to unlock vault");
+    notes
+        .write(JsValue::from_str("kept on the object"), None, false, None)
+        .await
+        .expect("Failed to write namespace");
+    assert_eq!(
+        notes.read(None).await.unwrap().as_string().unwrap(),
+        "kept on the object"
+    );
+
+    vault.close();
+    assert!(notes.read(None).await.is_err());
+
+    test_utils::cleanup_all_vaults().await;
+}
+
+#[wasm_bindgen_test]
+fn test_contexts_do_not_share_sync_state() {
+    use hoddor::context::{default_context, Context};
+    use std::rc::Rc;
+
+    let first = Context::new().expect("Failed to create context");
+    let second = Context::new().expect("Failed to create context");
+    assert_ne!(first.id(), second.id());
+
+    assert!(!Rc::ptr_eq(
+        &first.sync_manager("shared"),
+        &second.sync_manager("shared")
+    ));
+    assert!(!Rc::ptr_eq(
+        &first.sync_manager("shared"),
+        &default_context().sync_manager("shared")
+    ));
+    assert_ne!(
+        first.pairing_session("shared"),
+        default_context().pairing_session("shared")
+    );
+
+    let manager = first.sync_manager("shared");
+    first.dispose();
+    assert!(!Rc::ptr_eq(&manager, &first.sync_manager("shared")));
+
+    second.dispose();
+}
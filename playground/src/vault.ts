@@ -114,6 +114,10 @@ export class VaultWorker {
     await this.send('import_vault', { vaultName, data });
   }
 
+  async getDashboardSnapshot(): Promise<unknown> {
+    return this.send('get_dashboard_snapshot', {});
+  }
+
   async configureCleanup(intervalSeconds: number): Promise<void> {
     await this.send('configure_cleanup', { intervalSeconds });
   }
@@ -3,6 +3,7 @@ import init, {
   configure_cleanup,
   create_vault,
   export_vault,
+  get_dashboard_snapshot,
   import_vault,
   list_namespaces,
   list_vaults,
@@ -71,6 +72,9 @@ self.onmessage = async message => {
       case 'import_vault':
         result = await import_vault(payload.vaultName, payload.data);
         break;
+      case 'get_dashboard_snapshot':
+        result = await get_dashboard_snapshot();
+        break;
       case 'configure_cleanup':
         configure_cleanup(payload.intervalSeconds);
         result = { success: true };
@@ -6,19 +6,19 @@ use actix_web::{
     web, App, Error, HttpRequest, HttpResponse, HttpServer,
 };
 use actix_ws::{self, Message};
-use env_logger::Env;
 use futures::StreamExt;
-use log::{debug, error, info};
 use serde_json::json;
 use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::time::{interval, Duration};
+use tracing::{debug, error, info, Instrument};
 
 mod config;
 mod messages;
 mod security;
+mod telemetry;
 
 use config::CONFIG;
 use security::{generate_token, get_client_ip, validate_origin, verify_token, RateLimiter};
@@ -88,6 +88,36 @@ async fn forward_message(peers: &Arc<Mutex<HashMap<String, PeerState>>>, to: &st
     }
 }
 
+// Forwards an offer, answer or ICE candidate within a span continuing the
+// sender's trace; `build` gets the `traceparent` to forward.
+async fn relay(
+    peers: &Arc<Mutex<HashMap<String, PeerState>>>,
+    kind: &'static str,
+    from: &str,
+    to: &str,
+    traceparent: Option<String>,
+    build: impl FnOnce(Option<String>) -> SignalingMessage,
+) -> Result<(), Error> {
+    let span = tracing::info_span!("signaling.relay", message = kind, from = %from, to = %to);
+    let traceparent = telemetry::continue_trace(&span, traceparent);
+    let msg_str = serde_json::to_string(&build(traceparent))?;
+
+    async {
+        debug!(
+            monotonic_counter.signaling_messages_relayed = 1_u64,
+            message = kind,
+            "Forwarding {}: {}",
+            kind,
+            msg_str
+        );
+        forward_message(peers, to, &msg_str).await;
+    }
+    .instrument(span)
+    .await;
+
+    Ok(())
+}
+
 async fn handle_signaling_message(
     msg: SignalingMessage,
     session: &mut actix_ws::Session,
@@ -119,7 +149,10 @@ async fn handle_signaling_message(
                         peer_id.clone(),
                         PeerState::new(session.clone(), peer_id.clone()),
                     );
-                    info!("Added new peer {}", peer_id);
+                    info!(
+                        counter.signaling_peers_connected = 1_i64,
+                        "Added new peer {}", peer_id
+                    );
                 }
             }
 
@@ -148,43 +181,67 @@ async fn handle_signaling_message(
         SignalingMessage::Leave { peer_id } => {
             info!("Peer {} left", peer_id);
             let mut peers_lock = peers.lock().await;
-            peers_lock.remove(&peer_id);
+            if peers_lock.remove(&peer_id).is_some() {
+                debug!(
+                    counter.signaling_peers_connected = -1_i64,
+                    "Removed peer {}", peer_id
+                );
+            }
             let peer_ids: Vec<String> = peers_lock.keys().cloned().collect();
             debug!("Current peers after leave: {:?}", peer_ids);
         }
-        SignalingMessage::Offer { from, to, sdp } => {
-            debug!("Offer from {} to {}", from, to);
-            let msg_str = serde_json::to_string(&SignalingMessage::Offer {
-                from: from.clone(),
-                to: to.clone(),
-                sdp: sdp.clone(),
-            })?;
-            debug!("Forwarding offer: {}", msg_str);
-            forward_message(peers, &to, &msg_str).await;
+        SignalingMessage::Offer {
+            from,
+            to,
+            sdp,
+            traceparent,
+        } => {
+            relay(peers, "offer", &from, &to, traceparent, |traceparent| {
+                SignalingMessage::Offer {
+                    from: from.clone(),
+                    to: to.clone(),
+                    sdp,
+                    traceparent,
+                }
+            })
+            .await?;
         }
-        SignalingMessage::Answer { from, to, sdp } => {
-            debug!("Answer from {} to {}", from, to);
-            let msg_str = serde_json::to_string(&SignalingMessage::Answer {
-                from: from.clone(),
-                to: to.clone(),
-                sdp: sdp.clone(),
-            })?;
-            debug!("Forwarding answer: {}", msg_str);
-            forward_message(peers, &to, &msg_str).await;
+        SignalingMessage::Answer {
+            from,
+            to,
+            sdp,
+            traceparent,
+        } => {
+            relay(peers, "answer", &from, &to, traceparent, |traceparent| {
+                SignalingMessage::Answer {
+                    from: from.clone(),
+                    to: to.clone(),
+                    sdp,
+                    traceparent,
+                }
+            })
+            .await?;
         }
         SignalingMessage::IceCandidate {
             from,
             to,
             candidate,
+            traceparent,
         } => {
-            debug!("ICE candidate from {} to {}", from, to);
-            let msg_str = serde_json::to_string(&SignalingMessage::IceCandidate {
-                from: from.clone(),
-                to: to.clone(),
-                candidate: candidate.clone(),
-            })?;
-            debug!("Forwarding ICE candidate: {}", msg_str);
-            forward_message(peers, &to, &msg_str).await;
+            relay(
+                peers,
+                "ICE candidate",
+                &from,
+                &to,
+                traceparent,
+                |traceparent| SignalingMessage::IceCandidate {
+                    from: from.clone(),
+                    to: to.clone(),
+                    candidate,
+                    traceparent,
+                },
+            )
+            .await?;
         }
         SignalingMessage::Discovery { from } => {
             debug!("Discovery from {}", from);
@@ -327,7 +384,10 @@ async fn cleanup_stale_peers(peers: Arc<Mutex<HashMap<String, PeerState>>>) {
                 }
             }
             peers_lock.remove(peer_id.as_str());
-            debug!("Removing stale peer: {}", peer_id);
+            debug!(
+                counter.signaling_peers_connected = -1_i64,
+                "Removing stale peer: {}", peer_id
+            );
         }
 
         if !stale_peers.is_empty() {
@@ -343,11 +403,14 @@ async fn cleanup_stale_peers(peers: Arc<Mutex<HashMap<String, PeerState>>>) {
     }
 }
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
+fn main() -> std::io::Result<()> {
     dotenv::dotenv().ok();
-    env_logger::init_from_env(Env::default().default_filter_or("debug"));
+    let _telemetry = telemetry::init();
+
+    actix_web::rt::System::new().block_on(serve())
+}
 
+async fn serve() -> std::io::Result<()> {
     let rate_limiter = web::Data::new(RateLimiter::new(60, 100)); // 100 requests per minute
     let app_state = web::Data::new(AppState {
         peers: Arc::new(Mutex::new(HashMap::new())),
@@ -11,16 +11,22 @@ pub enum SignalingMessage {
         from: String,
         to: String,
         sdp: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        traceparent: Option<String>,
     },
     Answer {
         from: String,
         to: String,
         sdp: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        traceparent: Option<String>,
     },
     IceCandidate {
         from: String,
         to: String,
         candidate: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        traceparent: Option<String>,
     },
     Leave {
         peer_id: String,
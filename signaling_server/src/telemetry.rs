@@ -0,0 +1,161 @@
+//! Logging, and with the `otlp` feature, trace and metric export.
+//!
+//! Events are printed to stdout filtered by `RUST_LOG` (`debug` when unset).
+//! Built with `otlp` and started with `OTEL_EXPORTER_OTLP_ENDPOINT` set, the
+//! server also exports its spans and the `monotonic_counter.*` / `counter.*`
+//! fields of its events over OTLP/HTTP; the usual `OTEL_*` variables apply.
+//!
+//! Offers, answers and ICE candidates may carry a W3C `traceparent`. The
+//! relay span of each message continues that trace, and the forwarded
+//! message carries the relay span instead, so the browser, this server and
+//! the receiving peer end up in one trace.
+
+use tracing::Span;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// Keeps the exporters alive; dropping it flushes what is still buffered.
+pub struct Telemetry {
+    #[cfg(feature = "otlp")]
+    providers: Option<otlp::Providers>,
+}
+
+/// Installs the global subscriber. Must run before the async runtime starts,
+/// as the OTLP exporters use a blocking HTTP client.
+pub fn init() -> Telemetry {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("debug"));
+    let registry =
+        tracing_subscriber::registry().with(tracing_subscriber::fmt::layer().with_filter(filter));
+
+    #[cfg(feature = "otlp")]
+    {
+        let providers = otlp::Providers::from_env();
+        registry
+            .with(providers.as_ref().map(otlp::Providers::trace_layer))
+            .with(providers.as_ref().map(otlp::Providers::metrics_layer))
+            .init();
+
+        Telemetry { providers }
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    {
+        registry.init();
+
+        Telemetry {}
+    }
+}
+
+/// Makes `span` continue the trace of an incoming `traceparent` and returns
+/// the `traceparent` to forward in its place.
+pub fn continue_trace(span: &Span, traceparent: Option<String>) -> Option<String> {
+    #[cfg(feature = "otlp")]
+    {
+        otlp::continue_trace(span, traceparent)
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    {
+        let _ = span;
+        traceparent
+    }
+}
+
+#[cfg(feature = "otlp")]
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Some(providers) = self.providers.take() {
+            providers.shutdown();
+        }
+    }
+}
+
+#[cfg(feature = "otlp")]
+mod otlp {
+    use opentelemetry::propagation::TextMapPropagator;
+    use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+    use opentelemetry_otlp::{MetricExporter, SpanExporter};
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+    use std::collections::HashMap;
+    use tracing::Span;
+    use tracing_opentelemetry::{MetricsLayer, OpenTelemetryLayer, OpenTelemetrySpanExt};
+    use tracing_subscriber::registry::LookupSpan;
+
+    const SERVICE_NAME: &str = "hoddor-signaling";
+    const TRACEPARENT: &str = "traceparent";
+
+    pub struct Providers {
+        tracer: SdkTracerProvider,
+        meter: SdkMeterProvider,
+    }
+
+    impl Providers {
+        pub fn from_env() -> Option<Self> {
+            std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT")?;
+
+            let build = || -> Result<Self, opentelemetry_otlp::ExporterBuildError> {
+                let resource = Resource::builder().with_service_name(SERVICE_NAME).build();
+
+                let tracer = SdkTracerProvider::builder()
+                    .with_batch_exporter(SpanExporter::builder().with_http().build()?)
+                    .with_resource(resource.clone())
+                    .build();
+                let meter = SdkMeterProvider::builder()
+                    .with_periodic_exporter(MetricExporter::builder().with_http().build()?)
+                    .with_resource(resource)
+                    .build();
+
+                Ok(Self { tracer, meter })
+            };
+
+            match build() {
+                Ok(providers) => Some(providers),
+                Err(e) => {
+                    eprintln!("OTLP export disabled: {e}");
+                    None
+                }
+            }
+        }
+
+        pub fn trace_layer<S>(&self) -> OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>
+        where
+            S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+        {
+            OpenTelemetryLayer::new(self.tracer.tracer(SERVICE_NAME))
+        }
+
+        pub fn metrics_layer<S>(&self) -> MetricsLayer<S, SdkMeterProvider>
+        where
+            S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+        {
+            MetricsLayer::new(self.meter.clone())
+        }
+
+        pub fn shutdown(self) {
+            let _ = self.tracer.shutdown();
+            let _ = self.meter.shutdown();
+        }
+    }
+
+    pub fn continue_trace(span: &Span, traceparent: Option<String>) -> Option<String> {
+        let propagator = TraceContextPropagator::new();
+
+        if let Some(traceparent) = &traceparent {
+            let carrier = HashMap::from([(TRACEPARENT.to_string(), traceparent.clone())]);
+            let _ = span.set_parent(propagator.extract(&carrier));
+        }
+
+        let context = span.context();
+        if !context.span().span_context().is_valid() {
+            return traceparent;
+        }
+
+        let mut carrier = HashMap::new();
+        propagator.inject_context(&context, &mut carrier);
+        carrier.remove(TRACEPARENT).or(traceparent)
+    }
+}